@@ -0,0 +1,180 @@
+//! Measures lock contention in the in-memory store and the singleflight guard map with a single
+//! shard versus the default shard count.
+//!
+//! Run with `cargo bench --bench contention`.
+
+// std
+use std::{
+	collections::HashMap,
+	hint,
+	sync::Arc,
+	thread,
+	time::{Duration, Instant},
+};
+// crates.io
+use color_eyre::Result;
+use parking_lot::Mutex;
+use time::OffsetDateTime;
+// self
+use oauth2_broker::{
+	auth::{PrincipalId, ProviderId, ScopeSet, TenantId, TokenFamily, TokenRecord},
+	store::{BrokerStore, MemoryStore, Sharded},
+};
+
+const FAMILIES: usize = 20_000;
+const OPS_PER_WORKER: usize = 50_000;
+
+fn main() -> Result<()> {
+	color_eyre::install()?;
+
+	let workers = thread::available_parallelism().map_or(4, |count| count.get()) * 2;
+	let scope = ScopeSet::new(["bench.read"])?;
+	let families = (0..FAMILIES)
+		.map(|idx| {
+			Ok(TokenFamily::new(
+				TenantId::new(format!("tenant-{}", idx % 64))?,
+				PrincipalId::new(format!("principal-{idx}"))?,
+			)
+			.with_provider(ProviderId::new("provider-bench")?))
+		})
+		.collect::<Result<Vec<_>>>()?;
+	let families = Arc::new(families);
+
+	println!("{workers} workers, {FAMILIES} token families, {OPS_PER_WORKER} ops per worker");
+
+	for shards in [1, Sharded::<()>::DEFAULT_SHARDS] {
+		let elapsed = bench_store(MemoryStore::with_shards(shards), &families, &scope, workers)?;
+
+		report("memory_store fetch+cas", shards, workers, elapsed);
+	}
+	for shards in [1, Sharded::<()>::DEFAULT_SHARDS] {
+		let elapsed = bench_guards(shards, &families, workers);
+
+		report("singleflight guard lookup", shards, workers, elapsed);
+	}
+
+	Ok(())
+}
+
+/// Mixed workload: mostly fetches, with every eighth operation a refresh CAS that mismatches.
+fn bench_store(
+	store: MemoryStore,
+	families: &Arc<Vec<TokenFamily>>,
+	scope: &ScopeSet,
+	workers: usize,
+) -> Result<Duration> {
+	let runtime = tokio::runtime::Builder::new_multi_thread().worker_threads(workers).build()?;
+	let now = OffsetDateTime::now_utc();
+
+	runtime.block_on(async {
+		for family in families.iter() {
+			store.save(record(family, scope, now)?).await?;
+		}
+
+		Ok::<_, color_eyre::Report>(())
+	})?;
+
+	let store = Arc::new(store);
+	let started = Instant::now();
+
+	runtime.block_on(async {
+		let tasks = (0..workers)
+			.map(|worker| {
+				let store = store.clone();
+				let families = families.clone();
+				let scope = scope.clone();
+
+				tokio::spawn(async move {
+					let mut cursor = worker;
+
+					for op in 0..OPS_PER_WORKER {
+						cursor = next(cursor);
+
+						let family = &families[cursor % families.len()];
+
+						if op % 8 == 0 {
+							let replacement = record(family, &scope, now)?;
+
+							hint::black_box(
+								store
+									.compare_and_swap_refresh(
+										family,
+										&scope,
+										Some("stale"),
+										replacement,
+									)
+									.await?,
+							);
+						} else {
+							hint::black_box(store.fetch(family, &scope).await?);
+						}
+					}
+
+					Ok::<_, color_eyre::Report>(())
+				})
+			})
+			.collect::<Vec<_>>();
+
+		for task in tasks {
+			task.await??;
+		}
+
+		Ok::<_, color_eyre::Report>(())
+	})?;
+
+	Ok(started.elapsed())
+}
+
+/// Models the broker's per-key singleflight map: look up or insert a guard for each key.
+fn bench_guards(shards: usize, families: &Arc<Vec<TokenFamily>>, workers: usize) -> Duration {
+	let guards = Arc::new(Sharded::new(shards, Mutex::<HashMap<TokenFamily, Arc<()>>>::default));
+	let started = Instant::now();
+	let threads = (0..workers)
+		.map(|worker| {
+			let guards = guards.clone();
+			let families = families.clone();
+
+			thread::spawn(move || {
+				let mut cursor = worker;
+
+				for _ in 0..OPS_PER_WORKER {
+					cursor = next(cursor);
+
+					let family = &families[cursor % families.len()];
+					let guard =
+						guards.for_key(family).lock().entry(family.clone()).or_default().clone();
+
+					hint::black_box(guard);
+				}
+			})
+		})
+		.collect::<Vec<_>>();
+
+	for thread in threads {
+		let _ = thread.join();
+	}
+
+	started.elapsed()
+}
+
+fn record(family: &TokenFamily, scope: &ScopeSet, now: OffsetDateTime) -> Result<TokenRecord> {
+	Ok(TokenRecord::builder(family.clone(), scope.clone())
+		.access_token("bench-access")
+		.refresh_token("bench-refresh")
+		.issued_at(now)
+		.expires_at(now + time::Duration::hours(1))
+		.build()?)
+}
+
+fn next(cursor: usize) -> usize {
+	cursor.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407)
+}
+
+fn report(name: &str, shards: usize, workers: usize, elapsed: Duration) {
+	let ops = (workers * OPS_PER_WORKER) as f64;
+
+	println!(
+		"{name:<28} shards={shards:<3} {:>10.0} ops/s ({elapsed:?})",
+		ops / elapsed.as_secs_f64()
+	);
+}
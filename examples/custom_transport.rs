@@ -9,6 +9,7 @@
 
 // std
 use std::{
+	collections::BTreeMap,
 	error::Error as StdError,
 	fmt::{Display, Formatter, Result as FmtResult},
 	future::Future,
@@ -174,6 +175,7 @@ impl<'a> AsyncHttpClient<'a> for MockHttpHandle {
 					slot.store(ResponseMetadata {
 						status: Some(200),
 						retry_after: Some(Duration::seconds(1)),
+						headers: BTreeMap::new(),
 					});
 
 					Ok(HttpResponse::new(
@@ -185,6 +187,7 @@ impl<'a> AsyncHttpClient<'a> for MockHttpHandle {
 					slot.store(ResponseMetadata {
 						status: Some(503),
 						retry_after: Some(Duration::seconds(2)),
+						headers: BTreeMap::new(),
 					});
 
 					// The oauth2 crate keeps the `Reqwest` variant name even though the
@@ -193,7 +196,11 @@ impl<'a> AsyncHttpClient<'a> for MockHttpHandle {
 					Err(HttpClientError::Reqwest(Box::new(error)))
 				},
 				MockBehavior::Other(message) => {
-					slot.store(ResponseMetadata { status: None, retry_after: None });
+					slot.store(ResponseMetadata {
+						status: None,
+						retry_after: None,
+						headers: BTreeMap::new(),
+					});
 
 					Err(HttpClientError::Other(message.to_owned()))
 				},
@@ -34,7 +34,7 @@ fn main() -> Result<()> {
 		Url::parse("https://app.example.com/oauth/callback")?,
 	)?;
 
-	println!("Send your user to {}.", &session.authorize_url);
+	println!("Send your user to {}.", session.authorize_url);
 	println!(
 		"PKCE challenge ({:?}): {}.",
 		session.code_challenge_method(),
@@ -52,7 +52,7 @@ fn main() -> Result<()> {
 		stashed.validate_state(&returned_state)?;
 		println!(
 			"Validated state for tenant {} and principal {}.",
-			&stashed.tenant, &stashed.principal
+			stashed.tenant, stashed.principal
 		);
 		println!("Persist this session to call Broker::exchange_code during the callback.");
 	} else {
@@ -1,8 +1,9 @@
 //! Interactive Authorization Code + PKCE walkthrough for X (Twitter).
 //!
-//! The example prints the authorize URL, waits for the user to paste the returned
-//! `state` and `code` parameters via stdin, optionally exchanges the code for
-//! tokens, and can send a tweet so the bearer token is exercised end-to-end.
+//! The example listens on a loopback redirect URI, opens the authorize URL in the
+//! browser, exchanges the code once X redirects back, and can send a tweet so the
+//! bearer token is exercised end-to-end. Register `http://127.0.0.1:<port>/callback`
+//! as a callback URI of the X app first.
 
 // std
 use std::{
@@ -12,13 +13,13 @@ use std::{
 // crates.io
 use color_eyre::Result;
 use serde_json::{self, json};
-use url::Url;
 // self
 use oauth2_broker::{
-	auth::{PrincipalId, ProviderId, ScopeSet, TenantId},
-	flows::Broker,
+	auth::{PrincipalId, ScopeSet, TenantId},
+	flows::{Broker, LoopbackOptions, open_in_browser},
 	provider::{
-		DefaultProviderStrategy, GrantType, ProviderDescriptor, ProviderQuirks, ProviderStrategy,
+		ClientAuthMethod, DefaultProviderStrategy, ProviderStrategy, X_SCOPE_TWEET_READ,
+		X_SCOPE_TWEET_WRITE, X_SCOPE_USERS_READ, x_com, x_com_offline_scope,
 	},
 	reqwest::Client,
 	store::{BrokerStore, MemoryStore},
@@ -30,77 +31,63 @@ async fn main() -> Result<()> {
 
 	let client_id = prompt_with_default("Enter your X client ID", Some("demo-x-client"))?;
 	let client_secret = prompt_optional("Enter your X client secret (leave blank for PKCE-only)")?;
-	let redirect_input = prompt_with_default(
-		"Enter the redirect URI registered with X",
-		Some("https://app.example.com/x/callback"),
-	)?;
-	let redirect_uri = Url::parse(&redirect_input)?;
+	let port = prompt_with_default("Enter the loopback port registered with X", Some("8765"))?;
+	let client_secret = client_secret.filter(|value| !value.is_empty());
 	let store: Arc<dyn BrokerStore> = Arc::new(MemoryStore::default());
 	let strategy: Arc<dyn ProviderStrategy> = Arc::new(DefaultProviderStrategy);
-	let descriptor = ProviderDescriptor::builder(ProviderId::new("x-com")?)
-		.authorization_endpoint(Url::parse("https://x.com/i/oauth2/authorize")?)
-		.token_endpoint(Url::parse("https://api.x.com/2/oauth2/token")?)
-		.support_grants([GrantType::AuthorizationCode, GrantType::RefreshToken])
-		.quirks(ProviderQuirks { pkce_required: true, ..ProviderQuirks::default() })
-		.build()?;
-	let scope = ScopeSet::new(["tweet.read", "tweet.write", "users.read", "offline.access"])?;
+	let mut descriptor = x_com()?;
+
+	if client_secret.is_none() {
+		descriptor = descriptor.preferred_client_auth_method(ClientAuthMethod::NoneWithPkce);
+	}
+
+	let descriptor = descriptor.build()?;
+	let scope = x_com_offline_scope(&ScopeSet::new([
+		X_SCOPE_TWEET_READ,
+		X_SCOPE_TWEET_WRITE,
+		X_SCOPE_USERS_READ,
+	])?)?;
 	let mut broker = Broker::new(store, descriptor, strategy, client_id);
 
-	if let Some(secret) = client_secret.filter(|value| !value.is_empty()) {
+	if let Some(secret) = client_secret {
 		broker = broker.with_client_secret(secret);
 	}
 
-	let session = broker.start_authorization(
-		TenantId::new("tenant-acme")?,
-		PrincipalId::new("user-1729")?,
-		scope,
-		redirect_uri,
-	)?;
-
-	println!("Authorize URL: {}", &session.authorize_url);
-	println!(
-		"PKCE challenge ({:?}): {}.",
-		session.code_challenge_method(),
-		session.code_challenge()
-	);
-	println!(
-		"After X redirects back to your app, copy the `state` and `code` query parameters and paste them here."
-	);
-
-	let returned_state = prompt_with_default(
-		"State (press Enter to reuse the generated value)",
-		Some(session.state.as_str()),
-	)?;
-
-	session.validate_state(&returned_state)?;
-
-	let authorization_code =
-		prompt_optional("Authorization code (leave blank to skip the live token exchange)")?;
-
-	if let Some(code) = authorization_code {
-		let record = broker.exchange_code(session, code).await?;
-		println!("Access token: {}", record.access_token.expose());
-		if let Some(refresh) = record.refresh_token.as_ref() {
-			println!("Refresh token: {}", refresh.expose());
-		} else {
-			println!("Provider did not return a refresh token.");
-		}
-		println!("Expires at: {}", record.expires_at);
-		let tweet_prompt = prompt_optional(
-			"Tweet text (leave blank to skip posting to https://api.x.com/2/tweets)",
-		)?;
-		if let Some(text) = tweet_prompt {
-			post_tweet(record.access_token.expose(), &text).await?;
-		} else {
-			println!("Tweet skipped; token exchange confirmed.");
-		}
-		return Ok(());
+	let options = LoopbackOptions::default().with_port(port.parse()?).with_launcher(|url| {
+		println!("Opening the authorize URL in your browser: {url}");
+
+		open_in_browser(url)
+	});
+
+	println!("Waiting for X to redirect back...");
+
+	let record = broker
+		.authorize_via_loopback(
+			TenantId::new("tenant-acme")?,
+			PrincipalId::new("user-1729")?,
+			scope,
+			options,
+		)
+		.await?;
+
+	println!("Access token: {}", record.access_token.expose());
+
+	if let Some(refresh) = record.refresh_token.as_ref() {
+		println!("Refresh token: {}", refresh.expose());
+	} else {
+		println!("Provider did not return a refresh token.");
 	}
 
-	println!("Authorization code not provided; skipping token exchange.");
-	println!(
-		"Persist the session details and call Broker::exchange_code once a real authorization code is available."
-	);
+	println!("Expires at: {}", record.expires_at);
+
+	let tweet_prompt =
+		prompt_optional("Tweet text (leave blank to skip posting to https://api.x.com/2/tweets)")?;
+
+	if let Some(text) = tweet_prompt {
+		post_tweet(record.access_token.expose(), &text).await?;
+	} else {
+		println!("Tweet skipped; token exchange confirmed.");
+	}
 
 	Ok(())
 }
@@ -1,9 +1,16 @@
 //! Auth-domain identifiers, scope sets, and token models.
 
 pub mod id;
+pub mod pairwise;
+pub mod principal;
 pub mod scope;
 pub mod token;
 
+mod crypto;
+
 pub use id::*;
+pub use pairwise::*;
+pub use principal::*;
 pub use scope::*;
+#[cfg(feature = "ring")] pub use token::sealed::*;
 pub use token::{family::*, record::*, secret::*};
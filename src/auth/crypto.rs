@@ -0,0 +1,39 @@
+//! Keyed hashing shared by scope fingerprints and pairwise subjects.
+
+// crates.io
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// Computes HMAC-SHA-256 of `message` under `key`.
+pub(crate) fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+	// HMAC hashes or pads keys of any length, so keying never fails and the fallback is
+	// unreachable.
+	<Hmac<Sha256> as Mac>::new_from_slice(key)
+		.map_or([0; 32], |mac| mac.chain_update(message).finalize().into_bytes().into())
+}
+
+#[cfg(test)]
+mod tests {
+	// self
+	use super::*;
+
+	#[test]
+	fn hmac_sha256_matches_rfc_4231() {
+		// RFC 4231 test case 2.
+		let mac = hmac_sha256(b"Jefe", b"what do ya want for nothing?");
+
+		assert_eq!(
+			mac.iter().map(|b| format!("{b:02x}")).collect::<String>(),
+			"5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+		);
+
+		// RFC 4231 test case 6 (key longer than the block size).
+		let mac =
+			hmac_sha256(&[0xaa; 131], b"Test Using Larger Than Block-Size Key - Hash Key First");
+
+		assert_eq!(
+			mac.iter().map(|b| format!("{b:02x}")).collect::<String>(),
+			"60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+		);
+	}
+}
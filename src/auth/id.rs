@@ -1,12 +1,23 @@
 //! Strongly typed identifiers enforced across the broker domain.
+//!
+//! Every identifier must be non-empty and free of whitespace. Length limits and any stricter
+//! format rules come from the crate-wide [`IdentifierPolicy`] installed per [`IdentifierKind`]
+//! through [`set_identifier_policy`], which can also normalize identifiers to Unicode NFC before
+//! they are checked and stored.
 
 // std
-use std::{borrow::Borrow, ops::Deref};
+use std::{
+	borrow::{Borrow, Cow},
+	ops::Deref,
+};
+// crates.io
+use parking_lot::const_rwlock;
+use unicode_normalization::{UnicodeNormalization, is_nfc};
 // self
 use crate::_prelude::*;
 
 macro_rules! def_id {
-	($name:ident, $doc:literal, $kind:literal) => {
+	($name:ident, $doc:literal, $kind:expr) => {
 		#[doc = $doc]
 		#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 		#[serde(try_from = "String", into = "String")]
@@ -14,11 +25,7 @@ macro_rules! def_id {
 		impl $name {
 			/// Creates a new identifier after validation.
 			pub fn new(value: impl AsRef<str>) -> Result<Self, IdentifierError> {
-				let view = value.as_ref();
-
-				validate_view($kind, view)?;
-
-				Ok(Self(view.to_owned()))
+				Ok(Self(prepare($kind, value.as_ref())?.into_owned()))
 			}
 		}
 		impl Deref for $name {
@@ -42,9 +49,12 @@ macro_rules! def_id {
 			type Error = IdentifierError;
 
 			fn try_from(value: String) -> Result<Self, Self::Error> {
-				validate_view($kind, &value)?;
+				let normalized = match prepare($kind, &value)? {
+					Cow::Owned(normalized) => Some(normalized),
+					Cow::Borrowed(_) => None,
+				};
 
-				Ok(Self(value))
+				Ok(Self(normalized.unwrap_or(value)))
 			}
 		}
 		impl Borrow<str> for $name {
@@ -54,7 +64,7 @@ macro_rules! def_id {
 		}
 		impl Debug for $name {
 			fn fmt(&self, f: &mut Formatter) -> FmtResult {
-				write!(f, concat!($kind, "({})"), self.0)
+				write!(f, "{}({})", $kind.as_str(), self.0)
 			}
 		}
 		impl Display for $name {
@@ -72,7 +82,176 @@ macro_rules! def_id {
 	};
 }
 
-const IDENTIFIER_MAX_LEN: usize = 128;
+/// Default [`IdentifierPolicy::max_len`], in bytes.
+pub const IDENTIFIER_MAX_LEN: usize = 128;
+
+static POLICIES: RwLock<[Option<Arc<IdentifierPolicy>>; 3]> = const_rwlock([None, None, None]);
+
+type CharsetFn = dyn Fn(char) -> bool + Send + Sync;
+type ValidatorFn = dyn Fn(&str) -> Result<(), String> + Send + Sync;
+
+/// Identifier families governed by an [`IdentifierPolicy`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IdentifierKind {
+	/// [`TenantId`] values.
+	Tenant,
+	/// [`PrincipalId`] values.
+	Principal,
+	/// [`ProviderId`] values.
+	Provider,
+}
+impl IdentifierKind {
+	/// Returns the label used in error messages.
+	pub const fn as_str(self) -> &'static str {
+		match self {
+			IdentifierKind::Tenant => "Tenant",
+			IdentifierKind::Principal => "Principal",
+			IdentifierKind::Provider => "Provider",
+		}
+	}
+
+	const fn slot(self) -> usize {
+		match self {
+			IdentifierKind::Tenant => 0,
+			IdentifierKind::Principal => 1,
+			IdentifierKind::Provider => 2,
+		}
+	}
+}
+impl Display for IdentifierKind {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		f.write_str(self.as_str())
+	}
+}
+
+/// Validation rules applied on top of the built-in non-empty/no-whitespace checks.
+///
+/// The default policy allows up to [`IDENTIFIER_MAX_LEN`] bytes of any non-whitespace characters
+/// and keeps values byte-for-byte. Deployments can tighten the format (a charset predicate or a
+/// validator closure wrapping a regex), relax the length limit for long opaque IDs, or normalize
+/// to NFC so IDs from IdPs that emit decomposed forms compare equal, then install the policy with
+/// [`set_identifier_policy`].
+#[derive(Clone)]
+pub struct IdentifierPolicy {
+	max_len: usize,
+	nfc: bool,
+	charset: Option<Arc<CharsetFn>>,
+	validator: Option<Arc<ValidatorFn>>,
+}
+impl IdentifierPolicy {
+	/// Maximum identifier length, in bytes.
+	pub fn max_len(&self) -> usize {
+		self.max_len
+	}
+
+	/// Overrides the maximum identifier length, in bytes.
+	pub fn with_max_len(mut self, max_len: usize) -> Self {
+		self.max_len = max_len;
+
+		self
+	}
+
+	/// Returns true when identifiers are normalized to Unicode NFC before validation.
+	pub fn nfc(&self) -> bool {
+		self.nfc
+	}
+
+	/// Normalizes identifiers to Unicode NFC before validation.
+	///
+	/// The length limit and charset apply to the normalized form, which is also the value the
+	/// identifier stores. Records persisted under a non-normalized identifier keep their original
+	/// key, so enable this before any records are written.
+	pub fn with_nfc(mut self, enabled: bool) -> Self {
+		self.nfc = enabled;
+
+		self
+	}
+
+	/// Applies the policy's normalization to `value`, borrowing when nothing changes.
+	pub fn normalize<'a>(&self, value: &'a str) -> Cow<'a, str> {
+		if self.nfc && !is_nfc(value) {
+			Cow::Owned(value.nfc().collect())
+		} else {
+			Cow::Borrowed(value)
+		}
+	}
+
+	/// Restricts identifiers to characters accepted by `allowed`.
+	pub fn with_charset<F>(mut self, allowed: F) -> Self
+	where
+		F: 'static + Fn(char) -> bool + Send + Sync,
+	{
+		self.charset = Some(Arc::new(allowed));
+
+		self
+	}
+
+	/// Adds a custom validator; the returned message is surfaced through
+	/// [`IdentifierError::Rejected`].
+	pub fn with_validator<F>(mut self, validator: F) -> Self
+	where
+		F: 'static + Fn(&str) -> Result<(), String> + Send + Sync,
+	{
+		self.validator = Some(Arc::new(validator));
+
+		self
+	}
+
+	/// Validates `value` as an identifier of `kind`.
+	///
+	/// The value is checked as given; identifier constructors run [`Self::normalize`] first.
+	pub fn validate(&self, kind: IdentifierKind, value: &str) -> Result<(), IdentifierError> {
+		let kind = kind.as_str();
+
+		if value.is_empty() {
+			return Err(IdentifierError::Empty { kind });
+		}
+		if value.chars().any(char::is_whitespace) {
+			return Err(IdentifierError::ContainsWhitespace { kind });
+		}
+		if value.len() > self.max_len {
+			return Err(IdentifierError::TooLong { kind, max: self.max_len });
+		}
+		if let Some(allowed) = &self.charset
+			&& let Some(character) = value.chars().find(|c| !allowed(*c))
+		{
+			return Err(IdentifierError::DisallowedCharacter { kind, character });
+		}
+		if let Some(validator) = &self.validator {
+			validator(value).map_err(|reason| IdentifierError::Rejected { kind, reason })?;
+		}
+
+		Ok(())
+	}
+}
+impl Default for IdentifierPolicy {
+	fn default() -> Self {
+		Self { max_len: IDENTIFIER_MAX_LEN, nfc: false, charset: None, validator: None }
+	}
+}
+impl Debug for IdentifierPolicy {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		f.debug_struct("IdentifierPolicy")
+			.field("max_len", &self.max_len)
+			.field("nfc", &self.nfc)
+			.field("charset", &self.charset.is_some())
+			.field("validator", &self.validator.is_some())
+			.finish()
+	}
+}
+
+/// Installs `policy` for every identifier of `kind` created afterwards.
+///
+/// Existing identifiers are not re-validated. Install policies during startup, before any
+/// configuration or stored records are loaded.
+pub fn set_identifier_policy(kind: IdentifierKind, policy: IdentifierPolicy) {
+	POLICIES.write()[kind.slot()] = Some(Arc::new(policy));
+}
+
+/// Returns the policy currently applied to identifiers of `kind`.
+pub fn identifier_policy(kind: IdentifierKind) -> IdentifierPolicy {
+	POLICIES.read()[kind.slot()].as_deref().cloned().unwrap_or_default()
+}
 
 /// Error returned when identifier validation fails.
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, ThisError)]
@@ -97,24 +276,41 @@ pub enum IdentifierError {
 		/// Maximum permitted character count.
 		max: usize,
 	},
+	/// The identifier contains a character outside the policy's charset.
+	#[error("{kind} identifier contains disallowed character {character:?}.")]
+	DisallowedCharacter {
+		/// Kind of identifier (tenant, principal, provider).
+		kind: &'static str,
+		/// First offending character.
+		character: char,
+	},
+	/// The policy's custom validator rejected the identifier.
+	#[error("{kind} identifier was rejected: {reason}.")]
+	Rejected {
+		/// Kind of identifier (tenant, principal, provider).
+		kind: &'static str,
+		/// Validator-provided explanation.
+		reason: String,
+	},
 }
 
-def_id! { TenantId, "Unique identifier for a broker tenant.", "Tenant" }
-def_id! { PrincipalId, "Unique identifier for a broker principal.", "Principal" }
-def_id! { ProviderId, "Identifier for an OAuth provider descriptor.", "Provider" }
+def_id! { TenantId, "Unique identifier for a broker tenant.", IdentifierKind::Tenant }
+def_id! { PrincipalId, "Unique identifier for a broker principal.", IdentifierKind::Principal }
+def_id! { ProviderId, "Identifier for an OAuth provider descriptor.", IdentifierKind::Provider }
 
-fn validate_view(kind: &'static str, view: &str) -> Result<(), IdentifierError> {
-	if view.is_empty() {
-		return Err(IdentifierError::Empty { kind });
-	}
-	if view.chars().any(char::is_whitespace) {
-		return Err(IdentifierError::ContainsWhitespace { kind });
-	}
-	if view.len() > IDENTIFIER_MAX_LEN {
-		return Err(IdentifierError::TooLong { kind, max: IDENTIFIER_MAX_LEN });
-	}
+fn prepare(kind: IdentifierKind, view: &str) -> Result<Cow<'_, str>, IdentifierError> {
+	// Clone the policy out so custom validators never run while the lock is held.
+	let policy = POLICIES.read()[kind.slot()].clone();
+	let Some(policy) = policy else {
+		IdentifierPolicy::default().validate(kind, view)?;
+
+		return Ok(Cow::Borrowed(view));
+	};
+	let normalized = policy.normalize(view);
 
-	Ok(())
+	policy.validate(kind, &normalized)?;
+
+	Ok(normalized)
 }
 
 #[cfg(test)]
@@ -170,4 +366,84 @@ mod tests {
 
 		assert_eq!(map.get("tenant-123"), Some(&7));
 	}
+
+	#[test]
+	fn policies_enforce_charset_length_and_custom_rules() {
+		let policy = IdentifierPolicy::default()
+			.with_max_len(256)
+			.with_charset(|c| c.is_ascii_alphanumeric() || c == '-')
+			.with_validator(|value| {
+				value
+					.starts_with("acme-")
+					.then_some(())
+					.ok_or_else(|| "missing acme- prefix".into())
+			});
+
+		policy
+			.validate(IdentifierKind::Tenant, &format!("acme-{}", "a".repeat(200)))
+			.expect("Long identifiers within the policy limit should pass.");
+		assert_eq!(
+			policy.validate(IdentifierKind::Tenant, "acme_1"),
+			Err(IdentifierError::DisallowedCharacter { kind: "Tenant", character: '_' })
+		);
+		assert!(matches!(
+			policy.validate(IdentifierKind::Tenant, "other-1"),
+			Err(IdentifierError::Rejected { kind: "Tenant", .. })
+		));
+		assert!(matches!(
+			policy.validate(IdentifierKind::Tenant, "acme 1"),
+			Err(IdentifierError::ContainsWhitespace { .. })
+		));
+
+		// Installed policies only reject a value no other test uses, so parallel tests are
+		// unaffected.
+		set_identifier_policy(
+			IdentifierKind::Principal,
+			IdentifierPolicy::default().with_validator(|value| match value {
+				"policy-rejected" => Err("reserved".into()),
+				_ => Ok(()),
+			}),
+		);
+
+		assert!(PrincipalId::new("policy-rejected").is_err());
+		assert!(PrincipalId::new("policy-accepted").is_ok());
+
+		set_identifier_policy(IdentifierKind::Principal, IdentifierPolicy::default());
+
+		assert!(PrincipalId::new("policy-rejected").is_ok());
+		assert_eq!(identifier_policy(IdentifierKind::Principal).max_len(), IDENTIFIER_MAX_LEN);
+	}
+
+	#[test]
+	fn nfc_policy_normalizes_before_validation() {
+		let decomposed = "cafe\u{301}";
+		let policy = IdentifierPolicy::default().with_nfc(true).with_max_len(4);
+
+		assert_eq!(policy.normalize(decomposed), "caf\u{e9}");
+		assert!(matches!(policy.normalize("plain"), Cow::Borrowed("plain")));
+		assert!(matches!(
+			IdentifierPolicy::default()
+				.with_max_len(4)
+				.validate(IdentifierKind::Provider, decomposed),
+			Err(IdentifierError::TooLong { .. })
+		));
+
+		// NFC leaves the ASCII identifiers used by parallel tests unchanged.
+		set_identifier_policy(IdentifierKind::Provider, IdentifierPolicy::default().with_nfc(true));
+
+		let composed = ProviderId::new("caf\u{e9}").expect("Composed provider id should be valid.");
+		let normalized =
+			ProviderId::new(decomposed).expect("Decomposed provider id should be valid.");
+		let deserialized: ProviderId = serde_json::from_str(r#""cafe\u0301""#)
+			.expect("Decomposed provider id should deserialize.");
+
+		set_identifier_policy(IdentifierKind::Provider, IdentifierPolicy::default());
+
+		assert_eq!(normalized, composed);
+		assert_eq!(deserialized, composed);
+		assert_ne!(
+			ProviderId::new(decomposed).expect("Default policy should keep the value."),
+			composed
+		);
+	}
 }
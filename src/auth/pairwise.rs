@@ -0,0 +1,98 @@
+//! Pairwise pseudonymous subject identifiers for privacy-sensitive deployments.
+//!
+//! [`PairwiseSubjects`] derives a stable, provider-specific subject for each internal
+//! [`PrincipalId`] with HMAC-SHA-256, so identifiers sent to one provider cannot be correlated
+//! with those sent to another (or with internal IDs) without the key.
+
+// crates.io
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+// self
+use crate::{
+	_prelude::*,
+	auth::{PrincipalId, ProviderId, crypto},
+};
+
+/// Keyed mapping between internal principals and provider-facing pairwise subjects.
+///
+/// Derivation is one-way, so [`resolve`](Self::resolve) answers from the subjects this mapper
+/// has derived or [`remember`](Self::remember)ed; persist or re-derive them at startup when
+/// lookups must survive restarts.
+pub struct PairwiseSubjects {
+	key: Vec<u8>,
+	reverse: RwLock<HashMap<(ProviderId, String), PrincipalId>>,
+}
+impl PairwiseSubjects {
+	/// Creates a mapper keyed by `key`; rotating the key changes every derived subject.
+	pub fn new(key: impl Into<Vec<u8>>) -> Self {
+		Self { key: key.into(), reverse: Default::default() }
+	}
+
+	/// Derives the pairwise subject for `principal` at `provider` without recording it.
+	pub fn derive(&self, provider: &ProviderId, principal: &PrincipalId) -> String {
+		let message = format!("{}\0{}", provider.as_ref(), principal.as_ref());
+
+		URL_SAFE_NO_PAD.encode(crypto::hmac_sha256(&self.key, message.as_bytes()))
+	}
+
+	/// Returns the pairwise subject for `principal` at `provider`, recording it for
+	/// [`resolve`](Self::resolve).
+	pub fn subject_for(&self, provider: &ProviderId, principal: &PrincipalId) -> String {
+		let subject = self.derive(provider, principal);
+
+		self.reverse.write().insert((provider.clone(), subject.clone()), principal.clone());
+
+		subject
+	}
+
+	/// Records the subjects of `principals` at `provider`, e.g. when warming the mapper from a
+	/// principal directory.
+	pub fn remember<'a>(
+		&self,
+		provider: &ProviderId,
+		principals: impl IntoIterator<Item = &'a PrincipalId>,
+	) {
+		for principal in principals {
+			self.subject_for(provider, principal);
+		}
+	}
+
+	/// Resolves a pairwise `subject` issued for `provider` back to the internal principal.
+	pub fn resolve(&self, provider: &ProviderId, subject: &str) -> Option<PrincipalId> {
+		self.reverse.read().get(&(provider.clone(), subject.to_owned())).cloned()
+	}
+}
+impl Debug for PairwiseSubjects {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		f.debug_struct("PairwiseSubjects")
+			.field("key", &"<redacted>")
+			.field("known_subjects", &self.reverse.read().len())
+			.finish()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	// self
+	use super::*;
+
+	#[test]
+	fn subjects_are_pairwise_and_resolvable() {
+		let mapper = PairwiseSubjects::new(b"pairwise-key".to_vec());
+		let google = ProviderId::new("google").expect("Provider fixture should be valid.");
+		let github = ProviderId::new("github").expect("Provider fixture should be valid.");
+		let alice = PrincipalId::new("alice").expect("Principal fixture should be valid.");
+		let at_google = mapper.subject_for(&google, &alice);
+
+		assert_eq!(at_google, mapper.derive(&google, &alice));
+		assert_ne!(at_google, mapper.derive(&github, &alice));
+		assert_ne!(at_google, PairwiseSubjects::new(b"other-key".to_vec()).derive(&google, &alice));
+		assert_eq!(mapper.resolve(&google, &at_google), Some(alice.clone()));
+		assert_eq!(mapper.resolve(&github, &at_google), None);
+
+		let at_github = mapper.derive(&github, &alice);
+
+		mapper.remember(&github, [&alice]);
+
+		assert_eq!(mapper.resolve(&github, &at_github), Some(alice));
+	}
+}
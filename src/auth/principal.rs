@@ -0,0 +1,149 @@
+//! Hierarchical principal paths (`org/team/user`) layered over [`PrincipalId`].
+
+// self
+use crate::{
+	_prelude::*,
+	auth::{IdentifierError, IdentifierKind, PrincipalId},
+};
+
+/// Separator placed between [`PrincipalPath`] segments.
+pub const PRINCIPAL_PATH_SEPARATOR: char = '/';
+
+/// Structured principal such as `acme/platform/alice`.
+///
+/// A path renders to a plain [`PrincipalId`], so stores keep keying records by principal while
+/// [`BrokerStore::list_principal_subtree`](crate::store::BrokerStore::list_principal_subtree) and
+/// [`BrokerStore::revoke_principal_subtree`](crate::store::BrokerStore::revoke_principal_subtree)
+/// can address every principal below a prefix. Matching is segment-aware: `acme/ops` covers
+/// `acme/ops/bob` but not `acme/ops-legacy`.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct PrincipalPath(PrincipalId);
+impl PrincipalPath {
+	/// Parses a `/`-separated path, rejecting empty segments.
+	pub fn new(value: impl AsRef<str>) -> Result<Self, IdentifierError> {
+		let id = PrincipalId::new(value)?;
+
+		if id.split(PRINCIPAL_PATH_SEPARATOR).any(str::is_empty) {
+			return Err(IdentifierError::Rejected {
+				kind: IdentifierKind::Principal.as_str(),
+				reason: "principal paths cannot contain empty segments".into(),
+			});
+		}
+
+		Ok(Self(id))
+	}
+
+	/// Builds a path from individual segments.
+	pub fn from_segments<I, S>(segments: I) -> Result<Self, IdentifierError>
+	where
+		I: IntoIterator<Item = S>,
+		S: AsRef<str>,
+	{
+		let joined = segments
+			.into_iter()
+			.map(|segment| segment.as_ref().to_owned())
+			.collect::<Vec<_>>()
+			.join(&PRINCIPAL_PATH_SEPARATOR.to_string());
+
+		Self::new(joined)
+	}
+
+	/// Iterator over the path segments, root first.
+	pub fn segments(&self) -> impl Iterator<Item = &str> {
+		self.0.split(PRINCIPAL_PATH_SEPARATOR)
+	}
+
+	/// Parent path, or `None` for a single-segment path.
+	pub fn parent(&self) -> Option<Self> {
+		self.0
+			.rsplit_once(PRINCIPAL_PATH_SEPARATOR)
+			.and_then(|(parent, _)| PrincipalId::new(parent).ok())
+			.map(Self)
+	}
+
+	/// Appends a segment to the path.
+	pub fn join(&self, segment: impl AsRef<str>) -> Result<Self, IdentifierError> {
+		Self::new(format!("{}{PRINCIPAL_PATH_SEPARATOR}{}", self.0, segment.as_ref()))
+	}
+
+	/// Returns `true` when `principal` equals this path or lives below it.
+	pub fn contains(&self, principal: &PrincipalId) -> bool {
+		principal
+			.strip_prefix(self.0.as_ref())
+			.is_some_and(|rest| rest.is_empty() || rest.starts_with(PRINCIPAL_PATH_SEPARATOR))
+	}
+
+	/// Principal identifier the path renders to.
+	pub fn as_principal(&self) -> &PrincipalId {
+		&self.0
+	}
+}
+impl From<PrincipalPath> for PrincipalId {
+	fn from(value: PrincipalPath) -> Self {
+		value.0
+	}
+}
+impl From<PrincipalPath> for String {
+	fn from(value: PrincipalPath) -> Self {
+		value.0.into()
+	}
+}
+impl TryFrom<String> for PrincipalPath {
+	type Error = IdentifierError;
+
+	fn try_from(value: String) -> Result<Self, Self::Error> {
+		Self::new(value)
+	}
+}
+impl TryFrom<&PrincipalId> for PrincipalPath {
+	type Error = IdentifierError;
+
+	fn try_from(value: &PrincipalId) -> Result<Self, Self::Error> {
+		Self::new(value)
+	}
+}
+impl Debug for PrincipalPath {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		write!(f, "PrincipalPath({})", self.0)
+	}
+}
+impl Display for PrincipalPath {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		Display::fmt(&self.0, f)
+	}
+}
+impl FromStr for PrincipalPath {
+	type Err = IdentifierError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Self::new(s)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	// self
+	use super::*;
+
+	#[test]
+	fn paths_match_on_segment_boundaries() {
+		let team = PrincipalPath::new("acme/ops").expect("Team path should be valid.");
+		let user = team.join("bob").expect("Joined path should be valid.");
+
+		assert_eq!(user.segments().collect::<Vec<_>>(), ["acme", "ops", "bob"]);
+		assert_eq!(user.parent().as_ref(), Some(&team));
+		assert_eq!(PrincipalPath::new("acme").expect("Root should be valid.").parent(), None);
+		assert!(team.contains(user.as_principal()));
+		assert!(team.contains(team.as_principal()));
+		assert!(!team.contains(
+			&PrincipalId::new("acme/ops-legacy").expect("Sibling principal should be valid.")
+		));
+		assert!(PrincipalPath::new("acme//bob").is_err());
+		assert!(PrincipalPath::new("/acme").is_err());
+		assert_eq!(
+			PrincipalPath::from_segments(["acme", "ops", "bob"]).expect("Segments should join."),
+			user
+		);
+	}
+}
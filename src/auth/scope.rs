@@ -10,10 +10,125 @@ use std::{
 };
 // crates.io
 use base64::{Engine as _, engine::general_purpose::STANDARD_NO_PAD};
+use parking_lot::const_rwlock;
 use serde::{Deserializer, Serializer, de::Error as DeError, ser::SerializeSeq};
 use sha2::{Digest, Sha256};
+use smallvec::SmallVec;
 // self
-use crate::_prelude::*;
+use crate::{_prelude::*, auth::crypto};
+
+static FINGERPRINT_SCHEME: RwLock<Option<Arc<dyn FingerprintScheme>>> = const_rwlock(None);
+static SCOPE_INTERNER: RwLock<Option<ScopeInterner>> = const_rwlock(None);
+
+type ScopeList = SmallVec<[String; 4]>;
+
+/// Hash scheme used to derive [`ScopeSet::fingerprint`] and therefore [`StoreKey`] partitions.
+///
+/// Fingerprints render as `{version}:{digest}` so keys minted under different schemes never
+/// collide. The legacy [`Sha256Fingerprint`] reports an empty version and keeps producing the
+/// bare digest, so records stored before schemes became pluggable stay addressable.
+///
+/// [`StoreKey`]: crate::store::StoreKey
+pub trait FingerprintScheme: Send + Sync {
+	/// Version tag prefixed onto every fingerprint; empty only for the legacy scheme.
+	fn version(&self) -> &str;
+
+	/// Digests the normalized, space-delimited scope string.
+	fn digest(&self, normalized: &str) -> String;
+}
+
+/// Default scheme: base64 (no padding) SHA-256 of the normalized scope string.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sha256Fingerprint;
+impl FingerprintScheme for Sha256Fingerprint {
+	fn version(&self) -> &str {
+		""
+	}
+
+	fn digest(&self, normalized: &str) -> String {
+		STANDARD_NO_PAD.encode(Sha256::digest(normalized.as_bytes()))
+	}
+}
+
+/// Keyed scheme: base64 (no padding) HMAC-SHA-256 of the normalized scope string.
+///
+/// Keeps scope sets from being confirmed by hashing guesses when store keys leak.
+#[derive(Clone)]
+pub struct HmacSha256Fingerprint {
+	key: Vec<u8>,
+	version: String,
+}
+impl HmacSha256Fingerprint {
+	/// Default version tag for keyed fingerprints.
+	pub const DEFAULT_VERSION: &str = "hs256";
+
+	/// Creates a keyed scheme tagged with [`Self::DEFAULT_VERSION`].
+	pub fn new(key: impl Into<Vec<u8>>) -> Self {
+		Self { key: key.into(), version: Self::DEFAULT_VERSION.into() }
+	}
+
+	/// Overrides the version tag, e.g. to distinguish rotated keys (`hs256.2`).
+	pub fn with_version(mut self, version: impl Into<String>) -> Self {
+		self.version = version.into();
+
+		self
+	}
+}
+impl FingerprintScheme for HmacSha256Fingerprint {
+	fn version(&self) -> &str {
+		&self.version
+	}
+
+	fn digest(&self, normalized: &str) -> String {
+		STANDARD_NO_PAD.encode(crypto::hmac_sha256(&self.key, normalized.as_bytes()))
+	}
+}
+impl Debug for HmacSha256Fingerprint {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		f.debug_struct("HmacSha256Fingerprint")
+			.field("key", &"<redacted>")
+			.field("version", &self.version)
+			.finish()
+	}
+}
+
+/// Installs the crate-wide fingerprint scheme.
+///
+/// Install it at startup: scope sets cache their fingerprint on first use, and switching
+/// schemes orphans records keyed under the previous one until they are migrated.
+pub fn set_fingerprint_scheme(scheme: impl FingerprintScheme + 'static) {
+	*FINGERPRINT_SCHEME.write() = Some(Arc::new(scheme));
+}
+
+/// Returns the crate-wide fingerprint scheme ([`Sha256Fingerprint`] unless overridden).
+pub fn fingerprint_scheme() -> Arc<dyn FingerprintScheme> {
+	FINGERPRINT_SCHEME.read().clone().unwrap_or_else(|| Arc::new(Sha256Fingerprint))
+}
+
+/// Turns on the process-wide scope set interner, keeping up to `capacity` distinct sets.
+///
+/// Interned sets share one allocation, so their normalized string and fingerprint are computed
+/// once per distinct set instead of once per construction. Sets seen after the interner is full
+/// are built as usual; there is no eviction, so size `capacity` for the common sets a deployment
+/// actually requests. Like [`set_fingerprint_scheme`], call it at startup.
+pub fn enable_scope_interning(capacity: usize) {
+	let mut interner = SCOPE_INTERNER.write();
+
+	match interner.as_mut() {
+		Some(interner) => interner.capacity = capacity,
+		None => *interner = Some(ScopeInterner { capacity, sets: HashMap::new() }),
+	}
+}
+
+/// Returns how many distinct scope sets the interner currently holds.
+pub fn interned_scope_sets() -> usize {
+	SCOPE_INTERNER.read().as_ref().map_or(0, |interner| interner.sets.len())
+}
+
+struct ScopeInterner {
+	capacity: usize,
+	sets: HashMap<ScopeList, Arc<ScopeData>>,
+}
 
 /// Errors emitted when validating scopes.
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ThisError)]
@@ -28,79 +143,166 @@ pub enum ScopeValidationError {
 		/// The offending scope string.
 		scope: String,
 	},
+	/// Scope contains a character outside the RFC 6749 `scope-token` grammar.
+	#[error("Scope {scope} contains disallowed character {character:?}.")]
+	InvalidCharacter {
+		/// The offending scope string.
+		scope: String,
+		/// First character that failed validation.
+		character: char,
+	},
+	/// The normalized set exceeds the configured scope limit.
+	#[error("Scope set holds {count} scopes, exceeding the limit of {max}.")]
+	TooMany {
+		/// Number of distinct scopes after normalization.
+		count: usize,
+		/// Configured maximum.
+		max: usize,
+	},
+}
+
+/// Casing policy applied by [`ScopeSetBuilder`] before deduplication.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScopeCasing {
+	/// Keep scopes exactly as provided (OAuth scopes are case-sensitive).
+	#[default]
+	Preserve,
+	/// Lowercase every scope.
+	Lowercase,
+	/// Uppercase every scope.
+	Uppercase,
+}
+impl ScopeCasing {
+	fn apply(self, scope: String) -> String {
+		match self {
+			Self::Preserve => scope,
+			Self::Lowercase => scope.to_lowercase(),
+			Self::Uppercase => scope.to_uppercase(),
+		}
+	}
 }
 
 /// Normalized set of OAuth scopes with a stable fingerprint cache.
 ///
 /// Scopes are deduplicated and sorted so equality, ordering, and hashing
-/// remain consistent across platforms. The [`fingerprint`](Self::fingerprint) helper
-/// lazily caches a base64 (no padding) SHA-256 digest of the normalized string and
-/// the [`Hash`] implementation reuses that cache so hashing stays stable without
-/// re-normalizing the strings.
+/// remain consistent across platforms. Up to four scopes are stored inline next to the caches,
+/// so a typical set costs a single shared allocation. The normalized string and the
+/// [`fingerprint`](Self::fingerprint) are computed lazily and cached alongside the scopes, and
+/// the [`Hash`] implementation reuses the fingerprint cache so hashing stays stable without
+/// re-normalizing the strings. See [`enable_scope_interning`] to share those caches across
+/// every set with the same scopes.
 #[derive(Default)]
-pub struct ScopeSet {
-	/// The normalized scopes.
-	pub scopes: Arc<[String]>,
-	/// The fingerprint of the normalized scopes.
-	pub fingerprint_cache: OnceLock<String>,
-}
+pub struct ScopeSet(Arc<ScopeData>);
 impl ScopeSet {
+	/// Starts a [`ScopeSetBuilder`] for policy-checked construction.
+	pub fn builder() -> ScopeSetBuilder {
+		ScopeSetBuilder::default()
+	}
+
 	/// Creates a normalized scope set from any iterator.
 	pub fn new<I, S>(scopes: I) -> Result<Self, ScopeValidationError>
 	where
 		I: IntoIterator<Item = S>,
 		S: Into<String>,
 	{
-		Ok(Self { scopes: normalize(scopes)?, fingerprint_cache: OnceLock::new() })
+		Ok(Self::from_normalized(normalize(scopes)?))
 	}
 
 	/// Number of distinct scopes.
 	pub fn len(&self) -> usize {
-		self.scopes.len()
+		self.0.scopes.len()
 	}
 
 	/// Returns true if no scopes are defined.
 	pub fn is_empty(&self) -> bool {
-		self.scopes.is_empty()
+		self.0.scopes.is_empty()
 	}
 
 	/// Returns true if the normalized set contains the provided scope.
 	pub fn contains(&self, scope: &str) -> bool {
-		self.scopes.binary_search_by(|candidate| candidate.as_str().cmp(scope)).is_ok()
+		self.0.scopes.binary_search_by(|candidate| candidate.as_str().cmp(scope)).is_ok()
 	}
 
 	/// Iterator over normalized scopes.
 	pub fn iter(&self) -> impl Iterator<Item = &str> {
-		self.scopes.iter().map(|s| s.as_str())
+		self.0.scopes.iter().map(|s| s.as_str())
 	}
 
 	/// Returns the normalized string representation (space-delimited).
 	pub fn normalized(&self) -> String {
-		self.scopes.join(" ")
+		self.normalized_str().to_owned()
+	}
+
+	/// Borrows the cached normalized string representation (space-delimited).
+	pub fn normalized_str(&self) -> &str {
+		self.0.normalized.get_or_init(|| self.0.scopes.join(" "))
 	}
 
 	/// Stable fingerprint derived from the normalized scope list.
 	///
-	/// The fingerprint is a base64 (no padding) encoding of the SHA-256 digest for the
-	/// normalized, space-delimited scope string and is cached after the first
-	/// calculation.
+	/// The fingerprint comes from the crate-wide [`FingerprintScheme`] (by default a base64,
+	/// no padding, SHA-256 digest of the normalized, space-delimited scope string) and is cached
+	/// after the first calculation.
 	pub fn fingerprint(&self) -> String {
-		self.fingerprint_cache.get_or_init(|| compute_fingerprint(&self.scopes)).clone()
+		self.fingerprint_str().to_owned()
+	}
+
+	/// Borrows the cached fingerprint, computing it on first use.
+	pub fn fingerprint_str(&self) -> &str {
+		self.0.fingerprint.get_or_init(|| fingerprint_with(&*fingerprint_scheme(), self))
+	}
+
+	/// Fingerprint under an explicit scheme, bypassing the cache.
+	///
+	/// Useful when migrating stored keys from one scheme to another.
+	pub fn fingerprint_with(&self, scheme: &dyn FingerprintScheme) -> String {
+		fingerprint_with(scheme, self)
 	}
 
 	/// Returns the underlying slice of scope strings.
 	pub fn as_slice(&self) -> &[String] {
-		&self.scopes
+		&self.0.scopes
+	}
+
+	fn from_normalized(scopes: ScopeList) -> Self {
+		let guard = SCOPE_INTERNER.read();
+		let Some(interner) = guard.as_ref() else {
+			return Self(Arc::new(ScopeData::new(scopes)));
+		};
+
+		if let Some(data) = interner.sets.get(scopes.as_slice()) {
+			return Self(data.clone());
+		}
+		if interner.sets.len() >= interner.capacity {
+			return Self(Arc::new(ScopeData::new(scopes)));
+		}
+
+		drop(guard);
+
+		let mut guard = SCOPE_INTERNER.write();
+
+		match guard.as_mut() {
+			Some(interner) if interner.sets.len() < interner.capacity => {
+				let data = interner
+					.sets
+					.entry(scopes.clone())
+					.or_insert_with(|| Arc::new(ScopeData::new(scopes)));
+
+				Self(data.clone())
+			},
+			_ => Self(Arc::new(ScopeData::new(scopes))),
+		}
 	}
 }
 impl Clone for ScopeSet {
 	fn clone(&self) -> Self {
-		Self { scopes: self.scopes.clone(), fingerprint_cache: OnceLock::new() }
+		// Clones share the scopes and caches, so they neither copy scopes nor re-hash them.
+		Self(self.0.clone())
 	}
 }
 impl PartialEq for ScopeSet {
 	fn eq(&self, other: &Self) -> bool {
-		self.scopes == other.scopes
+		Arc::ptr_eq(&self.0, &other.0) || self.0.scopes == other.0.scopes
 	}
 }
 impl Eq for ScopeSet {}
@@ -111,22 +313,110 @@ impl PartialOrd for ScopeSet {
 }
 impl Ord for ScopeSet {
 	fn cmp(&self, other: &Self) -> Ordering {
-		self.scopes.cmp(&other.scopes)
+		self.0.scopes.cmp(&other.0.scopes)
 	}
 }
 impl Hash for ScopeSet {
 	fn hash<H: Hasher>(&self, state: &mut H) {
-		self.fingerprint_cache.get_or_init(|| compute_fingerprint(&self.scopes)).hash(state);
+		self.fingerprint_str().hash(state);
 	}
 }
 impl Debug for ScopeSet {
 	fn fmt(&self, f: &mut Formatter) -> FmtResult {
-		f.debug_tuple("ScopeSet").field(&self.scopes).finish()
+		f.debug_tuple("ScopeSet").field(&self.0.scopes).finish()
 	}
 }
 impl Display for ScopeSet {
 	fn fmt(&self, f: &mut Formatter) -> FmtResult {
-		f.write_str(&self.normalized())
+		f.write_str(self.normalized_str())
+	}
+}
+
+#[derive(Default)]
+struct ScopeData {
+	scopes: ScopeList,
+	normalized: OnceLock<String>,
+	fingerprint: OnceLock<String>,
+}
+impl ScopeData {
+	fn new(scopes: ScopeList) -> Self {
+		Self { scopes, normalized: OnceLock::new(), fingerprint: OnceLock::new() }
+	}
+}
+
+/// Builder that enforces org-wide scope hygiene while constructing a [`ScopeSet`].
+///
+/// Policies run in order: casing, the base empty/whitespace checks, the optional RFC 6749
+/// `scope-token` grammar (`%x21 / %x23-5B / %x5D-7E`), and finally the distinct-scope limit.
+#[derive(Clone, Debug, Default)]
+pub struct ScopeSetBuilder {
+	scopes: Vec<String>,
+	max_scopes: Option<usize>,
+	rfc6749_charset: bool,
+	casing: ScopeCasing,
+}
+impl ScopeSetBuilder {
+	/// Adds a single scope.
+	pub fn scope(mut self, scope: impl Into<String>) -> Self {
+		self.scopes.push(scope.into());
+
+		self
+	}
+
+	/// Adds every scope from the iterator.
+	pub fn scopes<I, S>(mut self, scopes: I) -> Self
+	where
+		I: IntoIterator<Item = S>,
+		S: Into<String>,
+	{
+		self.scopes.extend(scopes.into_iter().map(Into::into));
+
+		self
+	}
+
+	/// Rejects sets holding more than `max` distinct scopes.
+	pub fn max_scopes(mut self, max: usize) -> Self {
+		self.max_scopes = Some(max);
+
+		self
+	}
+
+	/// Restricts scopes to the RFC 6749 `scope-token` character set.
+	pub fn rfc6749_charset(mut self, enabled: bool) -> Self {
+		self.rfc6749_charset = enabled;
+
+		self
+	}
+
+	/// Sets the casing policy applied before deduplication.
+	pub fn casing(mut self, casing: ScopeCasing) -> Self {
+		self.casing = casing;
+
+		self
+	}
+
+	/// Validates the collected scopes and builds the normalized set.
+	pub fn build(self) -> Result<ScopeSet, ScopeValidationError> {
+		let casing = self.casing;
+		let scopes = normalize(self.scopes.into_iter().map(|scope| casing.apply(scope)))?;
+
+		if self.rfc6749_charset {
+			for scope in scopes.iter() {
+				if let Some(character) = scope.chars().find(|c| !is_scope_token_char(*c)) {
+					return Err(ScopeValidationError::InvalidCharacter {
+						scope: scope.clone(),
+						character,
+					});
+				}
+			}
+		}
+		if let Some(max) = self.max_scopes
+			&& scopes.len() > max
+		{
+			return Err(ScopeValidationError::TooMany { count: scopes.len(), max });
+		}
+
+		Ok(ScopeSet::from_normalized(scopes))
 	}
 }
 
@@ -160,7 +450,7 @@ impl<'a> IntoIterator for &'a ScopeSet {
 	type Item = &'a str;
 
 	fn into_iter(self) -> Self::IntoIter {
-		ScopeIter { inner: self.scopes.iter() }
+		ScopeIter { inner: self.0.scopes.iter() }
 	}
 }
 impl FromStr for ScopeSet {
@@ -182,9 +472,9 @@ impl Serialize for ScopeSet {
 	where
 		S: Serializer,
 	{
-		let mut seq = serializer.serialize_seq(Some(self.scopes.len()))?;
+		let mut seq = serializer.serialize_seq(Some(self.len()))?;
 
-		for scope in self.scopes.iter() {
+		for scope in self.iter() {
 			seq.serialize_element(scope)?;
 		}
 
@@ -202,7 +492,7 @@ impl<'de> Deserialize<'de> for ScopeSet {
 	}
 }
 
-fn normalize<I, S>(scopes: I) -> Result<Arc<[String]>, ScopeValidationError>
+fn normalize<I, S>(scopes: I) -> Result<ScopeList, ScopeValidationError>
 where
 	I: IntoIterator<Item = S>,
 	S: Into<String>,
@@ -222,18 +512,20 @@ where
 		set.insert(owned);
 	}
 
-	Ok(Arc::from(set.into_iter().collect::<Vec<_>>()))
+	Ok(set.into_iter().collect())
 }
 
-fn compute_fingerprint(scopes: &[String]) -> String {
-	let normalized = scopes.join(" ");
-	let mut hasher = Sha256::new();
-
-	hasher.update(normalized.as_bytes());
+fn is_scope_token_char(c: char) -> bool {
+	matches!(c, '\x21' | '\x23'..='\x5B' | '\x5D'..='\x7E')
+}
 
-	let digest = hasher.finalize();
+fn fingerprint_with(scheme: &dyn FingerprintScheme, scopes: &ScopeSet) -> String {
+	let digest = scheme.digest(scopes.normalized_str());
 
-	STANDARD_NO_PAD.encode(digest)
+	match scheme.version() {
+		"" => digest,
+		version => format!("{version}:{digest}"),
+	}
 }
 
 #[cfg(test)]
@@ -282,6 +574,81 @@ mod tests {
 		assert_eq!(fp1, fp2, "Fingerprint should be cached and stable.");
 	}
 
+	#[test]
+	fn clones_share_scopes_and_fingerprint_cache() {
+		let scopes = ScopeSet::new(["email", "profile"]).expect("Scope fixture should be valid.");
+		let fingerprint = scopes.fingerprint();
+		let clone = scopes.clone();
+
+		assert!(Arc::ptr_eq(&scopes.0, &clone.0));
+		assert_eq!(clone.0.fingerprint.get(), Some(&fingerprint));
+	}
+
+	#[test]
+	fn interned_sets_share_caches_up_to_capacity() {
+		enable_scope_interning(1);
+
+		let first =
+			ScopeSet::new(["interned-b", "interned-a"]).expect("Scope fixture should be valid.");
+		let second: ScopeSet =
+			"interned-a interned-b".parse().expect("Scope string should parse successfully.");
+
+		assert!(Arc::ptr_eq(&first.0, &second.0), "Equal sets should share one allocation.");
+		assert_eq!(first.normalized_str(), "interned-a interned-b");
+		assert_eq!(second.0.normalized.get().map(String::as_str), Some("interned-a interned-b"));
+
+		let overflow =
+			ScopeSet::new(["interned-c"]).expect("Overflow scope fixture should be valid.");
+		let again = ScopeSet::new(["interned-c"]).expect("Overflow scope fixture should be valid.");
+
+		assert!(!Arc::ptr_eq(&overflow.0, &again.0), "Sets past capacity are not interned.");
+		assert_eq!(overflow, again);
+		assert_eq!(interned_scope_sets(), 1);
+	}
+
+	#[test]
+	fn fingerprint_schemes_are_versioned() {
+		let scopes = ScopeSet::new(["email", "profile"]).expect("Scope fixture should be valid.");
+
+		assert_eq!(scopes.fingerprint_with(&Sha256Fingerprint), scopes.fingerprint());
+		assert!(!scopes.fingerprint().contains(':'));
+
+		let keyed = scopes.fingerprint_with(&HmacSha256Fingerprint::new("k1"));
+		let rotated =
+			scopes.fingerprint_with(&HmacSha256Fingerprint::new("k2").with_version("hs256.2"));
+
+		assert!(keyed.starts_with("hs256:"));
+		assert!(rotated.starts_with("hs256.2:"));
+		assert_ne!(keyed.split_once(':'), rotated.split_once(':'));
+	}
+
+	#[test]
+	fn builder_enforces_policies() {
+		let set = ScopeSet::builder()
+			.scopes(["Email", "email"])
+			.scope("PROFILE")
+			.casing(ScopeCasing::Lowercase)
+			.max_scopes(2)
+			.rfc6749_charset(true)
+			.build()
+			.expect("Lowercased scopes should fit the limit.");
+
+		assert_eq!(set.normalized(), "email profile");
+		assert_eq!(
+			ScopeSet::builder().scopes(["a", "b", "c"]).max_scopes(2).build(),
+			Err(ScopeValidationError::TooMany { count: 3, max: 2 })
+		);
+		assert_eq!(
+			ScopeSet::builder().scope("say\"hi\"").rfc6749_charset(true).build(),
+			Err(ScopeValidationError::InvalidCharacter {
+				scope: "say\"hi\"".into(),
+				character: '"'
+			})
+		);
+		assert!(ScopeSet::builder().scope("caf\u{e9}").rfc6749_charset(true).build().is_err());
+		assert!(ScopeSet::builder().scope("caf\u{e9}").build().is_ok());
+	}
+
 	#[test]
 	fn try_from_slice_round_trips() {
 		let raw = vec!["read".to_string(), "write".to_string()];
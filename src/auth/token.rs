@@ -2,4 +2,5 @@
 
 pub mod family;
 pub mod record;
+#[cfg(feature = "ring")] pub mod sealed;
 pub mod secret;
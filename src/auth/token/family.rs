@@ -1,4 +1,4 @@
-//! Token family classification helpers (tenant/principal/provider).
+//! Token family classification helpers (tenant/principal/provider/audience).
 
 // self
 use crate::{
@@ -15,10 +15,38 @@ pub struct TokenFamily {
 	pub principal: PrincipalId,
 	/// Optional provider identifier that minted the tokens.
 	pub provider: Option<ProviderId>,
+	/// Audience/resource the tokens were minted for, partitioning otherwise identical families.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub audience: Option<String>,
+	/// Grant variant the tokens were minted through (the `requested_token_use` sent with the
+	/// request), keeping grants that share an endpoint and audience apart.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub grant: Option<String>,
 }
 impl TokenFamily {
 	/// Creates a family for the provided tenant and principal.
 	pub fn new(tenant: TenantId, principal: PrincipalId) -> Self {
-		Self { tenant, principal, provider: None }
+		Self { tenant, principal, provider: None, audience: None, grant: None }
+	}
+
+	/// Attributes the family to the provider that mints its tokens.
+	pub fn with_provider(mut self, provider: ProviderId) -> Self {
+		self.provider = Some(provider);
+
+		self
+	}
+
+	/// Scopes the family to the provided audience/resource.
+	pub fn with_audience(mut self, audience: impl Into<String>) -> Self {
+		self.audience = Some(audience.into());
+
+		self
+	}
+
+	/// Scopes the family to the provided grant variant.
+	pub fn with_grant(mut self, grant: impl Into<String>) -> Self {
+		self.grant = Some(grant.into());
+
+		self
 	}
 }
@@ -1,5 +1,8 @@
 //! Immutable token record structs, lifecycle helpers, and builders.
 
+// crates.io
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use sha2::{Digest, Sha256};
 // self
 use crate::{
 	_prelude::*,
@@ -22,6 +25,113 @@ pub enum TokenStatus {
 	Revoked,
 }
 
+/// Why a token record was revoked, persisted alongside `revoked_at`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RevocationReason {
+	/// The end user (or the application acting for them) asked for revocation.
+	UserRequested,
+	/// The provider answered a grant with `invalid_grant` or a revoked-token error.
+	ProviderInvalidGrant,
+	/// A rotated refresh token was presented again.
+	ReuseDetected,
+	/// An operator or broker policy (for example a decommissioned client) revoked the record.
+	AdminAction,
+	/// The record outlived its usable lifetime and was retired.
+	Expired,
+}
+impl RevocationReason {
+	/// Returns a stable label suitable for audit events and metric labels.
+	pub const fn as_str(self) -> &'static str {
+		match self {
+			Self::UserRequested => "user_requested",
+			Self::ProviderInvalidGrant => "provider_invalid_grant",
+			Self::ReuseDetected => "reuse_detected",
+			Self::AdminAction => "admin_action",
+			Self::Expired => "expired",
+		}
+	}
+}
+impl Display for RevocationReason {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		f.write_str(self.as_str())
+	}
+}
+
+/// Delegation metadata for tokens obtained by an actor on behalf of the record's principal
+/// (RFC 8693 `act` / `may_act`, Entra on-behalf-of).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Delegation {
+	/// Identifier of the party currently acting for the principal (the `act.sub` claim).
+	pub actor: String,
+	/// Number of actors in the delegation chain; `1` for direct delegation.
+	pub depth: u32,
+}
+impl Delegation {
+	/// Creates direct (single-hop) delegation metadata for `actor`.
+	pub fn new(actor: impl Into<String>) -> Self {
+		Self { actor: actor.into(), depth: 1 }
+	}
+
+	/// Overrides the delegation chain depth (clamped to at least `1`).
+	pub fn with_depth(mut self, depth: u32) -> Self {
+		self.depth = depth.max(1);
+
+		self
+	}
+
+	/// Reads an `act` (or `may_act`) claim value, following nested `act` members to compute the
+	/// chain depth.
+	///
+	/// Returns `None` when the outermost actor carries no string `sub`.
+	pub fn from_act_claim(claim: &serde_json::Value) -> Option<Self> {
+		let actor = claim.get("sub")?.as_str()?;
+		let mut depth = 1;
+		let mut current = claim;
+
+		while let Some(prior) = current.get("act").filter(|value| value.is_object()) {
+			depth += 1;
+			current = prior;
+		}
+
+		Some(Self::new(actor).with_depth(depth))
+	}
+}
+
+/// Proof-of-possession key binding for sender-constrained access tokens.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum TokenBinding {
+	/// DPoP-bound token (RFC 9449); requests must carry a proof signed by the bound key.
+	Dpop {
+		/// RFC 7638 JWK SHA-256 thumbprint of the bound public key (`cnf.jkt`).
+		jkt: String,
+	},
+	/// Certificate-bound token (RFC 8705); requests must travel over mutual TLS with the bound
+	/// client certificate.
+	Certificate {
+		/// Base64url SHA-256 thumbprint of the DER client certificate (`cnf.x5t#S256`).
+		x5t_s256: String,
+	},
+}
+impl TokenBinding {
+	/// Computes the RFC 8705 `x5t#S256` thumbprint of a DER-encoded certificate.
+	pub fn certificate_thumbprint(der: &[u8]) -> String {
+		URL_SAFE_NO_PAD.encode(Sha256::digest(der))
+	}
+
+	/// Reads a `cnf` confirmation claim, preferring `jkt` over `x5t#S256` when both appear.
+	pub fn from_cnf_claim(cnf: &serde_json::Value) -> Option<Self> {
+		if let Some(jkt) = cnf.get("jkt").and_then(serde_json::Value::as_str) {
+			return Some(Self::Dpop { jkt: jkt.to_owned() });
+		}
+
+		cnf.get("x5t#S256")
+			.and_then(serde_json::Value::as_str)
+			.map(|x5t| Self::Certificate { x5t_s256: x5t.to_owned() })
+	}
+}
+
 /// Errors produced by [`TokenRecordBuilder`].
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ThisError)]
 pub enum TokenRecordBuilderError {
@@ -48,8 +158,61 @@ pub struct TokenRecord {
 	pub issued_at: OffsetDateTime,
 	/// Expiry instant derived from issued_at plus expires_in or absolute expiry.
 	pub expires_at: OffsetDateTime,
+	/// Refresh token expiry, when the provider advertises one (`None` means non-expiring or
+	/// unknown).
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub refresh_expires_at: Option<OffsetDateTime>,
 	/// Revocation instant if the record has been revoked.
 	pub revoked_at: Option<OffsetDateTime>,
+	/// Why the record was revoked; `None` for live records and records revoked before reasons
+	/// were tracked.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub revoked_reason: Option<RevocationReason>,
+	/// Non-secret provider response fields callers need alongside the token (for example
+	/// Salesforce's `instance_url`).
+	#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+	pub extras: BTreeMap<String, String>,
+	/// OAuth client identifier the token was minted under (`None` for records that predate
+	/// provenance tracking).
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub client_id: Option<String>,
+	/// Issuer identifier of the authorization server that minted the token, when known.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub issuer: Option<String>,
+	/// Actor metadata for delegated tokens (`None` when the principal obtained the token
+	/// directly).
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub delegation: Option<Delegation>,
+	/// Key binding for sender-constrained tokens (`None` for bearer tokens).
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub binding: Option<TokenBinding>,
+	/// Free-form annotations set by flows, strategies, or callers (for example a provider
+	/// account or installation id). Persisted with the record but ignored by
+	/// [`record_fingerprint`](crate::store::record_fingerprint).
+	#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+	pub metadata: BTreeMap<String, String>,
+	/// Last time the broker refreshed this record (`None` until the first refresh).
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub last_refreshed_at: Option<OffsetDateTime>,
+	/// Successful refreshes since the original grant.
+	#[serde(default, skip_serializing_if = "is_zero")]
+	pub refresh_count: u32,
+	/// Last time a flow served the record from the cache; only stamped when
+	/// [`Broker::with_served_tracking`](crate::flows::Broker::with_served_tracking) is set.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub last_served_at: Option<OffsetDateTime>,
+	/// Optimistic concurrency version of the stored record.
+	///
+	/// Stores set it to the stored version plus one on every conditional write (the
+	/// compare-and-swap methods and revocations), so it only grows while the record is rotated
+	/// in place; pass it to
+	/// [`BrokerStore::compare_and_swap_version`](crate::store::BrokerStore::compare_and_swap_version)
+	/// to replace the record only if nobody else did first. Freshly built records and records
+	/// written by unconditional saves start at `0`. Conditional reads through
+	/// [`BrokerStore::fetch_if_changed`](crate::store::BrokerStore::fetch_if_changed) compare a
+	/// content fingerprint instead, which also changes on unconditional saves.
+	#[serde(default, skip_serializing_if = "is_zero")]
+	pub version: u64,
 }
 impl TokenRecord {
 	/// Returns a builder for constructing rotation-friendly records.
@@ -102,14 +265,45 @@ impl TokenRecord {
 		matches!(self.status(), TokenStatus::Expired)
 	}
 
+	/// Returns `true` if the refresh token is known to have expired at the provided instant.
+	pub fn is_refresh_expired_at(&self, instant: OffsetDateTime) -> bool {
+		self.refresh_expires_at.is_some_and(|expires_at| instant >= expires_at)
+	}
+
 	/// Returns `true` if the record has been revoked.
 	pub fn is_revoked(&self) -> bool {
 		self.revoked_at.is_some()
 	}
 
-	/// Marks the record as revoked.
-	pub fn revoke(&mut self, instant: OffsetDateTime) {
+	/// Returns the provider response field captured under `key`, if any.
+	pub fn extra(&self, key: &str) -> Option<&str> {
+		self.extras.get(key).map(String::as_str)
+	}
+
+	/// Latest instant the record was issued, refreshed, or served.
+	///
+	/// Families whose last activity lies far in the past are candidates for purging.
+	pub fn last_activity(&self) -> OffsetDateTime {
+		[self.last_refreshed_at, self.last_served_at]
+			.into_iter()
+			.flatten()
+			.fold(self.issued_at, OffsetDateTime::max)
+	}
+
+	/// Returns the metadata value stored under `key`, if any.
+	pub fn meta(&self, key: &str) -> Option<&str> {
+		self.metadata.get(key).map(String::as_str)
+	}
+
+	/// Returns `true` when the record is known to have been minted under `client_id`.
+	pub fn minted_by(&self, client_id: &str) -> bool {
+		self.client_id.as_deref() == Some(client_id)
+	}
+
+	/// Marks the record as revoked for the provided reason.
+	pub fn revoke(&mut self, instant: OffsetDateTime, reason: RevocationReason) {
 		self.revoked_at = Some(instant);
+		self.revoked_reason = Some(reason);
 	}
 }
 impl Debug for TokenRecord {
@@ -121,7 +315,19 @@ impl Debug for TokenRecord {
 			.field("refresh_token", &self.refresh_token.as_ref().map(|_| "<redacted>"))
 			.field("issued_at", &self.issued_at)
 			.field("expires_at", &self.expires_at)
+			.field("refresh_expires_at", &self.refresh_expires_at)
 			.field("revoked_at", &self.revoked_at)
+			.field("revoked_reason", &self.revoked_reason)
+			.field("extras", &self.extras)
+			.field("client_id", &self.client_id)
+			.field("issuer", &self.issuer)
+			.field("delegation", &self.delegation)
+			.field("binding", &self.binding)
+			.field("metadata", &self.metadata)
+			.field("last_refreshed_at", &self.last_refreshed_at)
+			.field("refresh_count", &self.refresh_count)
+			.field("last_served_at", &self.last_served_at)
+			.field("version", &self.version)
 			.finish()
 	}
 }
@@ -136,6 +342,18 @@ pub struct TokenRecordBuilder {
 	issued_at: Option<OffsetDateTime>,
 	expires_at: Option<OffsetDateTime>,
 	expires_in: Option<Duration>,
+	refresh_expires_at: Option<OffsetDateTime>,
+	refresh_expires_in: Option<Duration>,
+	extras: BTreeMap<String, String>,
+	client_id: Option<String>,
+	issuer: Option<String>,
+	delegation: Option<Delegation>,
+	binding: Option<TokenBinding>,
+	metadata: BTreeMap<String, String>,
+	last_refreshed_at: Option<OffsetDateTime>,
+	refresh_count: u32,
+	last_served_at: Option<OffsetDateTime>,
+	version: u64,
 }
 impl TokenRecordBuilder {
 	fn new(family: TokenFamily, scope: ScopeSet) -> Self {
@@ -147,6 +365,18 @@ impl TokenRecordBuilder {
 			issued_at: None,
 			expires_at: None,
 			expires_in: None,
+			refresh_expires_at: None,
+			refresh_expires_in: None,
+			extras: BTreeMap::new(),
+			client_id: None,
+			issuer: None,
+			delegation: None,
+			binding: None,
+			metadata: BTreeMap::new(),
+			last_refreshed_at: None,
+			refresh_count: 0,
+			last_served_at: None,
+			version: 0,
 		}
 	}
 
@@ -176,6 +406,20 @@ impl TokenRecordBuilder {
 		self
 	}
 
+	/// Sets an absolute refresh token expiry instant.
+	pub fn refresh_expires_at(mut self, instant: OffsetDateTime) -> Self {
+		self.refresh_expires_at = Some(instant);
+
+		self
+	}
+
+	/// Sets a relative refresh token expiry duration from the issued instant.
+	pub fn refresh_expires_in(mut self, duration: Duration) -> Self {
+		self.refresh_expires_in = Some(duration);
+
+		self
+	}
+
 	/// Provides the access token value.
 	pub fn access_token(mut self, token: impl Into<String>) -> Self {
 		self.access_token = Some(TokenSecret::new(token));
@@ -190,6 +434,81 @@ impl TokenRecordBuilder {
 		self
 	}
 
+	/// Records a non-secret provider response field.
+	pub fn extra(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+		self.extras.insert(key.into(), value.into());
+
+		self
+	}
+
+	/// Replaces every captured provider response field.
+	pub fn extras(mut self, extras: BTreeMap<String, String>) -> Self {
+		self.extras = extras;
+
+		self
+	}
+
+	/// Records a metadata entry.
+	pub fn meta(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+		self.metadata.insert(key.into(), value.into());
+
+		self
+	}
+
+	/// Replaces every metadata entry.
+	pub fn metadata(mut self, metadata: BTreeMap<String, String>) -> Self {
+		self.metadata = metadata;
+
+		self
+	}
+
+	/// Records the OAuth client identifier the token was minted under.
+	pub fn client_id(mut self, client_id: impl Into<String>) -> Self {
+		self.client_id = Some(client_id.into());
+
+		self
+	}
+
+	/// Records the issuer identifier of the authorization server that minted the token.
+	pub fn issuer(mut self, issuer: impl Into<String>) -> Self {
+		self.issuer = Some(issuer.into());
+
+		self
+	}
+
+	/// Records the actor that obtained the token on the principal's behalf.
+	pub fn delegation(mut self, delegation: Delegation) -> Self {
+		self.delegation = Some(delegation);
+
+		self
+	}
+
+	/// Records the proof-of-possession key the token is bound to.
+	pub fn binding(mut self, binding: TokenBinding) -> Self {
+		self.binding = Some(binding);
+
+		self
+	}
+
+	/// Copies client, issuer, and delegation provenance from another record.
+	pub fn provenance_from(mut self, record: &TokenRecord) -> Self {
+		self.client_id = record.client_id.clone();
+		self.issuer = record.issuer.clone();
+		self.delegation = record.delegation.clone();
+
+		self
+	}
+
+	/// Copies refresh, serve, and version bookkeeping from another record.
+	pub fn usage_from(mut self, record: &TokenRecord) -> Self {
+		self.last_refreshed_at = record.last_refreshed_at;
+		self.refresh_count = record.refresh_count;
+		self.last_served_at = record.last_served_at;
+		self.version = record.version;
+
+		self
+	}
+
 	/// Consumes the builder and produces a [`TokenRecord`].
 	pub fn build(self) -> Result<TokenRecord, TokenRecordBuilderError> {
 		let access_token = self.access_token.ok_or(TokenRecordBuilderError::MissingAccessToken)?;
@@ -199,6 +518,9 @@ impl TokenRecordBuilder {
 			(None, Some(delta)) => issued_at + delta,
 			(None, None) => return Err(TokenRecordBuilderError::MissingExpiry),
 		};
+		let refresh_expires_at = self
+			.refresh_expires_at
+			.or_else(|| self.refresh_expires_in.and_then(|delta| issued_at.checked_add(delta)));
 
 		Ok(TokenRecord {
 			family: self.family,
@@ -207,11 +529,30 @@ impl TokenRecordBuilder {
 			refresh_token: self.refresh_token,
 			issued_at,
 			expires_at,
+			refresh_expires_at,
 			revoked_at: None,
+			revoked_reason: None,
+			extras: self.extras,
+			client_id: self.client_id,
+			issuer: self.issuer,
+			delegation: self.delegation,
+			binding: self.binding,
+			metadata: self.metadata,
+			last_refreshed_at: self.last_refreshed_at,
+			refresh_count: self.refresh_count,
+			last_served_at: self.last_served_at,
+			version: self.version,
 		})
 	}
 }
 
+fn is_zero<T>(value: &T) -> bool
+where
+	T: Default + PartialEq,
+{
+	*value == T::default()
+}
+
 #[cfg(test)]
 mod tests {
 	// crates.io
@@ -241,9 +582,10 @@ mod tests {
 		assert_eq!(record.status_at(macros::datetime!(2025-01-01 00:30 UTC)), TokenStatus::Active);
 		assert_eq!(record.status_at(macros::datetime!(2025-01-01 01:00 UTC)), TokenStatus::Expired);
 
-		record.revoke(macros::datetime!(2025-01-01 00:10 UTC));
+		record.revoke(macros::datetime!(2025-01-01 00:10 UTC), RevocationReason::UserRequested);
 
 		assert_eq!(record.status_at(macros::datetime!(2025-01-01 00:30 UTC)), TokenStatus::Revoked);
+		assert_eq!(record.revoked_reason, Some(RevocationReason::UserRequested));
 	}
 
 	#[test]
@@ -257,10 +599,35 @@ mod tests {
 			.access_token("secret")
 			.issued_at(macros::datetime!(2025-01-01 00:00 UTC))
 			.expires_in(Duration::minutes(30))
+			.refresh_expires_in(Duration::hours(8))
+			.extra("instance_url", "https://acme.my.salesforce.com")
+			.client_id("client-a")
+			.issuer("https://issuer.example.com")
+			.meta("installation_id", "inst-42")
 			.build()
 			.expect("Token record builder should support relative expiry calculations.");
 
 		assert_eq!(record.expires_at, macros::datetime!(2025-01-01 00:30 UTC));
+		assert_eq!(record.refresh_expires_at, Some(macros::datetime!(2025-01-01 08:00 UTC)));
+		assert!(record.is_refresh_expired_at(macros::datetime!(2025-01-01 08:00 UTC)));
+		assert_eq!(record.extra("instance_url"), Some("https://acme.my.salesforce.com"));
+		assert!(record.minted_by("client-a"));
+		assert!(!record.minted_by("client-b"));
+		assert_eq!(record.issuer.as_deref(), Some("https://issuer.example.com"));
+		assert_eq!(record.meta("installation_id"), Some("inst-42"));
+	}
+
+	#[test]
+	fn delegation_depth_follows_nested_act_claims() {
+		let claim = serde_json::json!({
+			"sub": "svc-gateway",
+			"act": { "sub": "svc-frontend", "act": { "sub": "svc-edge" } }
+		});
+		let delegation =
+			Delegation::from_act_claim(&claim).expect("Act claim with a subject should parse.");
+
+		assert_eq!(delegation, Delegation::new("svc-gateway").with_depth(3));
+		assert!(Delegation::from_act_claim(&serde_json::json!({ "iss": "x" })).is_none());
 	}
 
 	#[test]
@@ -297,7 +664,7 @@ mod tests {
 		assert!(active.is_active());
 		assert!(!active.is_revoked());
 
-		active.revoke(now);
+		active.revoke(now, RevocationReason::AdminAction);
 
 		assert!(active.is_revoked());
 
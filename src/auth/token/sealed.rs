@@ -0,0 +1,291 @@
+//! Token records whose secrets are encrypted to a caller-provided public key.
+//!
+//! When the broker runs as its own service, [`TokenRecord::seal`] keeps access and refresh
+//! tokens opaque to everything between the broker and the consumer that holds the private key.
+//! Secrets travel as a compact JWE (RFC 7516) using `ECDH-ES` key agreement on P-256 and
+//! `A256GCM` content encryption (RFC 7518 §4.6), so any JOSE library can open them.
+
+// crates.io
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use ring::{
+	aead::{AES_256_GCM, Aad, LessSafeKey, NONCE_LEN, Nonce, UnboundKey},
+	agreement::{ECDH_P256, EphemeralPrivateKey, UnparsedPublicKey, agree_ephemeral},
+	rand::{SecureRandom, SystemRandom},
+};
+use sha2::{Digest, Sha256};
+// self
+use crate::{
+	_prelude::*,
+	auth::{ScopeSet, TokenBinding, TokenFamily, TokenRecord},
+	error::ConfigError,
+	provider::Jwk,
+};
+
+const CONTENT_ENCRYPTION: &str = "A256GCM";
+const P256_POINT_LEN: usize = 65;
+
+/// P-256 public key of the consumer that receives sealed records.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecipientKey {
+	point: Vec<u8>,
+	key_id: Option<String>,
+}
+impl RecipientKey {
+	/// Wraps an uncompressed SEC1 P-256 point (`0x04 || x || y`).
+	pub fn from_uncompressed_point(point: &[u8]) -> Result<Self, ConfigError> {
+		if point.len() != P256_POINT_LEN || point[0] != 0x04 {
+			return Err(ConfigError::InvalidRecipientKey {
+				message: "expected a 65-byte uncompressed P-256 point".into(),
+			});
+		}
+
+		Ok(Self { point: point.to_vec(), key_id: None })
+	}
+
+	/// Reads an `EC`/`P-256` JSON Web Key, keeping its `kid`.
+	pub fn from_jwk(jwk: &Jwk) -> Result<Self, ConfigError> {
+		let invalid = |message: &str| ConfigError::InvalidRecipientKey { message: message.into() };
+		let coordinate = |value: &Option<String>| {
+			value
+				.as_deref()
+				.and_then(|value| URL_SAFE_NO_PAD.decode(value).ok())
+				.filter(|bytes| bytes.len() == 32)
+				.ok_or_else(|| invalid("JWK coordinates must be 32-byte base64url values"))
+		};
+
+		if jwk.kty != "EC" || jwk.crv.as_deref() != Some("P-256") {
+			return Err(invalid("only EC P-256 keys can receive sealed records"));
+		}
+
+		let point = [vec![0x04], coordinate(&jwk.x)?, coordinate(&jwk.y)?].concat();
+		let mut key = Self::from_uncompressed_point(&point)?;
+
+		key.key_id = jwk.kid.clone();
+
+		Ok(key)
+	}
+
+	/// Sets the `kid` echoed in the JWE header so consumers can pick the matching private key.
+	pub fn with_key_id(mut self, key_id: impl Into<String>) -> Self {
+		self.key_id = Some(key_id.into());
+
+		self
+	}
+}
+
+/// [`TokenRecord`] whose secrets were sealed to a [`RecipientKey`].
+///
+/// Everything except the access and refresh tokens stays readable so intermediaries can route
+/// and cache by expiry; the secrets live in [`sealed_secrets`](Self::sealed_secrets), a compact
+/// JWE whose plaintext is `{"access_token": "...", "refresh_token": "..."}`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SealedTokenRecord {
+	/// Logical token grouping (tenant/principal/provider).
+	pub family: TokenFamily,
+	/// Normalized scopes granted to this record.
+	pub scope: ScopeSet,
+	/// Issued-at instant recorded from the provider response.
+	pub issued_at: OffsetDateTime,
+	/// Access token expiry.
+	pub expires_at: OffsetDateTime,
+	/// Refresh token expiry, when the provider advertises one.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub refresh_expires_at: Option<OffsetDateTime>,
+	/// Whether the sealed payload carries a refresh token.
+	pub has_refresh_token: bool,
+	/// Non-secret provider response fields.
+	#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+	pub extras: BTreeMap<String, String>,
+	/// OAuth client identifier the token was minted under.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub client_id: Option<String>,
+	/// Issuer identifier of the authorization server that minted the token, when known.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub issuer: Option<String>,
+	/// Key binding for sender-constrained tokens.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub binding: Option<TokenBinding>,
+	/// Compact JWE (`ECDH-ES` + `A256GCM`) holding the secrets.
+	pub sealed_secrets: String,
+}
+
+impl TokenRecord {
+	/// Encrypts the record's secrets to `recipient`, keeping the rest of the record readable.
+	pub fn seal(&self, recipient: &RecipientKey) -> Result<SealedTokenRecord, ConfigError> {
+		let mut secrets = serde_json::Map::new();
+
+		secrets.insert("access_token".into(), self.access_token.expose().into());
+
+		if let Some(refresh) = &self.refresh_token {
+			secrets.insert("refresh_token".into(), refresh.expose().into());
+		}
+
+		let plaintext = serde_json::Value::Object(secrets).to_string();
+
+		Ok(SealedTokenRecord {
+			family: self.family.clone(),
+			scope: self.scope.clone(),
+			issued_at: self.issued_at,
+			expires_at: self.expires_at,
+			refresh_expires_at: self.refresh_expires_at,
+			has_refresh_token: self.refresh_token.is_some(),
+			extras: self.extras.clone(),
+			client_id: self.client_id.clone(),
+			issuer: self.issuer.clone(),
+			binding: self.binding.clone(),
+			sealed_secrets: seal_compact(recipient, plaintext.as_bytes())?,
+		})
+	}
+}
+
+fn seal_compact(recipient: &RecipientKey, plaintext: &[u8]) -> Result<String, ConfigError> {
+	let failed = |message: &str| ConfigError::Sealing { message: message.into() };
+	let rng = SystemRandom::new();
+	let ephemeral = EphemeralPrivateKey::generate(&ECDH_P256, &rng)
+		.map_err(|_| failed("ephemeral key generation failed"))?;
+	let epk = ephemeral.compute_public_key().map_err(|_| failed("ephemeral key is unusable"))?;
+	let epk = epk.as_ref();
+	let mut header = serde_json::json!({
+		"alg": "ECDH-ES",
+		"enc": CONTENT_ENCRYPTION,
+		"epk": {
+			"kty": "EC",
+			"crv": "P-256",
+			"x": URL_SAFE_NO_PAD.encode(&epk[1..33]),
+			"y": URL_SAFE_NO_PAD.encode(&epk[33..]),
+		},
+	});
+
+	if let Some(kid) = &recipient.key_id {
+		header["kid"] = kid.as_str().into();
+	}
+
+	let header = URL_SAFE_NO_PAD.encode(header.to_string());
+	let cek = agree_ephemeral(
+		ephemeral,
+		&UnparsedPublicKey::new(&ECDH_P256, &recipient.point),
+		concat_kdf,
+	)
+	.map_err(|_| failed("key agreement with the recipient key failed"))?;
+	let key = LessSafeKey::new(
+		UnboundKey::new(&AES_256_GCM, &cek).map_err(|_| failed("content key is unusable"))?,
+	);
+	let mut iv = [0_u8; NONCE_LEN];
+
+	rng.fill(&mut iv).map_err(|_| failed("IV generation failed"))?;
+
+	let mut ciphertext = plaintext.to_vec();
+	let tag = key
+		.seal_in_place_separate_tag(
+			Nonce::assume_unique_for_key(iv),
+			Aad::from(header.as_bytes()),
+			&mut ciphertext,
+		)
+		.map_err(|_| failed("content encryption failed"))?;
+
+	Ok(format!(
+		"{header}..{}.{}.{}",
+		URL_SAFE_NO_PAD.encode(iv),
+		URL_SAFE_NO_PAD.encode(&ciphertext),
+		URL_SAFE_NO_PAD.encode(tag.as_ref())
+	))
+}
+
+/// Single-round Concat KDF (NIST SP 800-56A) with the `ECDH-ES` direct-agreement inputs of
+/// RFC 7518 §4.6.2 and empty `apu`/`apv`.
+fn concat_kdf(shared_secret: &[u8]) -> [u8; 32] {
+	let algorithm = CONTENT_ENCRYPTION.as_bytes();
+	let mut hasher = Sha256::new();
+
+	hasher.update(1_u32.to_be_bytes());
+	hasher.update(shared_secret);
+	hasher.update((algorithm.len() as u32).to_be_bytes());
+	hasher.update(algorithm);
+	hasher.update(0_u32.to_be_bytes());
+	hasher.update(0_u32.to_be_bytes());
+	hasher.update(256_u32.to_be_bytes());
+
+	hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+	// self
+	use super::*;
+	use crate::auth::{PrincipalId, TenantId};
+
+	#[test]
+	fn sealed_secrets_open_with_the_recipient_key_only() {
+		let rng = SystemRandom::new();
+		let recipient_private = EphemeralPrivateKey::generate(&ECDH_P256, &rng)
+			.expect("Recipient key generation should succeed.");
+		let recipient_public =
+			recipient_private.compute_public_key().expect("Recipient public key should derive.");
+		let recipient = RecipientKey::from_uncompressed_point(recipient_public.as_ref())
+			.expect("Recipient point should be accepted.")
+			.with_key_id("consumer-1");
+		let record = TokenRecord::builder(
+			TokenFamily::new(
+				TenantId::new("tenant").expect("Tenant fixture should be valid."),
+				PrincipalId::new("principal").expect("Principal fixture should be valid."),
+			),
+			ScopeSet::new(["api"]).expect("Scope fixture should be valid."),
+		)
+		.access_token("access-secret")
+		.refresh_token("refresh-secret")
+		.expires_in(Duration::minutes(5))
+		.build()
+		.expect("Record fixture should build.");
+		let sealed = record.seal(&recipient).expect("Sealing should succeed.");
+
+		assert!(sealed.has_refresh_token);
+		assert!(!sealed.sealed_secrets.contains("secret"));
+		assert!(
+			!serde_json::to_string(&sealed)
+				.expect("Sealed record should serialize.")
+				.contains("access-secret")
+		);
+
+		let parts = sealed.sealed_secrets.split('.').collect::<Vec<_>>();
+		let decode =
+			|part: &str| URL_SAFE_NO_PAD.decode(part).expect("JWE part should be base64url.");
+		let header: serde_json::Value =
+			serde_json::from_slice(&decode(parts[0])).expect("JWE header should be JSON.");
+
+		assert_eq!(parts.len(), 5);
+		assert!(parts[1].is_empty());
+		assert_eq!(header["alg"], "ECDH-ES");
+		assert_eq!(header["kid"], "consumer-1");
+
+		let epk = [
+			vec![0x04],
+			decode(header["epk"]["x"].as_str().unwrap_or_default()),
+			decode(header["epk"]["y"].as_str().unwrap_or_default()),
+		]
+		.concat();
+		let cek = agree_ephemeral(
+			recipient_private,
+			&UnparsedPublicKey::new(&ECDH_P256, epk),
+			concat_kdf,
+		)
+		.expect("Recipient agreement should succeed.");
+		let key = LessSafeKey::new(
+			UnboundKey::new(&AES_256_GCM, &cek).expect("Derived key should be usable."),
+		);
+		let iv: [u8; NONCE_LEN] = decode(parts[2]).try_into().expect("IV should be 12 bytes.");
+		let mut in_out = [decode(parts[3]), decode(parts[4])].concat();
+		let plaintext = key
+			.open_in_place(
+				Nonce::assume_unique_for_key(iv),
+				Aad::from(parts[0].as_bytes()),
+				&mut in_out,
+			)
+			.expect("Sealed secrets should open with the recipient key.");
+		let secrets: serde_json::Value =
+			serde_json::from_slice(plaintext).expect("Plaintext should be JSON.");
+
+		assert_eq!(secrets["access_token"], "access-secret");
+		assert_eq!(secrets["refresh_token"], "refresh-secret");
+		assert!(RecipientKey::from_uncompressed_point(&[0x04; 12]).is_err());
+	}
+}
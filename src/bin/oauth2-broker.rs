@@ -0,0 +1,371 @@
+//! Operator CLI for brokers assembled from a [`BrokerConfig`] file.
+//!
+//! Built with the `cli` feature. Every command loads the same JSON configuration services use,
+//! so operators inspect and revoke the records a deployment actually serves, run an interactive
+//! Authorization Code + PKCE login, and validate provider descriptors before a rollout. Token
+//! secrets are never printed; records are shown as [`RecordDescription`] JSON lines.
+
+#![deny(clippy::all, missing_docs)]
+
+// std
+use std::{
+	io::{self, BufRead, Write},
+	path::PathBuf,
+	process::ExitCode,
+};
+// crates.io
+use clap::{Args, Parser, Subcommand};
+// self
+use oauth2_broker::{
+	auth::{PrincipalId, ProviderId, RevocationReason, ScopeSet, TenantId, TokenFamily},
+	config::BrokerConfig,
+	flows::{
+		BrokerRegistry, CachedTokenRequest, RecordDescription, ReqwestBroker, ValidationOptions,
+	},
+	http::ReqwestHttpClient,
+	oauth::ReqwestTransportErrorMapper,
+	store::BrokerStore,
+	url::{Url, form_urlencoded},
+};
+use time::OffsetDateTime;
+
+type Registry = BrokerRegistry<ReqwestHttpClient, ReqwestTransportErrorMapper>;
+type CliResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+/// Manage tokens and providers of an oauth2-broker deployment.
+#[derive(Debug, Parser)]
+#[command(name = "oauth2-broker", version)]
+struct Cli {
+	/// Broker configuration file (JSON, see `config::BrokerConfig`).
+	#[arg(long, short)]
+	config: PathBuf,
+	#[command(subcommand)]
+	command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+	/// Inspect or revoke stored token records.
+	#[command(subcommand)]
+	Tokens(TokensCommand),
+	/// Run an interactive Authorization Code + PKCE login and store the resulting record.
+	Login {
+		#[command(flatten)]
+		target: RecordTarget,
+		/// Redirect URI registered with the provider; paste the URL it was called with.
+		#[arg(long)]
+		redirect_uri: Url,
+	},
+	/// Validate provider descriptors without minting tokens.
+	Validate {
+		/// Only validate this provider (defaults to every configured provider).
+		#[arg(long)]
+		provider: Option<ProviderId>,
+		/// Compare the descriptor against this discovery document.
+		#[arg(long)]
+		discovery_url: Option<Url>,
+		/// Issue a bodiless request to each token endpoint to confirm it is reachable.
+		#[arg(long)]
+		probe: bool,
+	},
+}
+
+#[derive(Debug, Subcommand)]
+enum TokensCommand {
+	/// List stored records as JSON lines.
+	List {
+		/// Only list records of this tenant.
+		#[arg(long)]
+		tenant: Option<TenantId>,
+	},
+	/// Describe one stored record.
+	Describe {
+		#[command(flatten)]
+		target: RecordTarget,
+	},
+	/// Revoke one stored record without contacting the provider.
+	Revoke {
+		#[command(flatten)]
+		target: RecordTarget,
+		/// Reason recorded with the revocation.
+		#[arg(long, value_parser = parse_reason, default_value = "admin_action")]
+		reason: RevocationReason,
+	},
+}
+
+/// Record selected by provider, tenant, principal, and scope.
+#[derive(Debug, Args)]
+struct RecordTarget {
+	/// Provider identifier from the configuration.
+	#[arg(long)]
+	provider: ProviderId,
+	/// Tenant identifier.
+	#[arg(long)]
+	tenant: TenantId,
+	/// Principal identifier.
+	#[arg(long)]
+	principal: PrincipalId,
+	/// Space-delimited scopes.
+	#[arg(long)]
+	scope: ScopeSet,
+	/// Audience partition the record was minted for, if any.
+	#[arg(long)]
+	audience: Option<String>,
+}
+impl RecordTarget {
+	fn request(&self) -> CachedTokenRequest {
+		let request = CachedTokenRequest::new(
+			self.tenant.clone(),
+			self.principal.clone(),
+			self.scope.clone(),
+		);
+
+		match &self.audience {
+			Some(audience) => request.with_extra_param("audience", audience),
+			None => request,
+		}
+	}
+
+	fn family(&self) -> TokenFamily {
+		let mut family = TokenFamily::new(self.tenant.clone(), self.principal.clone());
+
+		family.provider = Some(self.provider.clone());
+		family.audience = self.audience.clone();
+
+		family
+	}
+
+	fn broker<'a>(&self, registry: &'a Registry) -> CliResult<&'a ReqwestBroker> {
+		registry.get(self.provider.as_ref()).ok_or_else(|| unknown_provider(&self.provider))
+	}
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+	let cli = Cli::parse();
+
+	match run(cli).await {
+		Ok(code) => code,
+		Err(e) => {
+			eprintln!("error: {e}");
+
+			ExitCode::FAILURE
+		},
+	}
+}
+
+async fn run(cli: Cli) -> CliResult<ExitCode> {
+	let registry = Registry::from_config(BrokerConfig::from_json_file(cli.config)?)?;
+
+	match cli.command {
+		Command::Tokens(TokensCommand::List { tenant }) => list(&registry, tenant.as_ref()).await,
+		Command::Tokens(TokensCommand::Describe { target }) => {
+			let description =
+				target.broker(&registry)?.describe_record(&target.family(), &target.scope).await?;
+
+			match description {
+				Some(description) => print_json(&description),
+				None => {
+					eprintln!("No record is stored for this target.");
+
+					Ok(ExitCode::FAILURE)
+				},
+			}
+		},
+		Command::Tokens(TokensCommand::Revoke { target, reason }) => {
+			match target.broker(&registry)?.revoke(target.request(), reason).await? {
+				Some(record) =>
+					print_json(&RecordDescription::of(&record, OffsetDateTime::now_utc())),
+				None => {
+					eprintln!("No record is stored for this target.");
+
+					Ok(ExitCode::FAILURE)
+				},
+			}
+		},
+		Command::Login { target, redirect_uri } => login(&registry, &target, redirect_uri).await,
+		Command::Validate { provider, discovery_url, probe } =>
+			validate(&registry, provider.as_ref(), discovery_url, probe).await,
+	}
+}
+
+async fn list(registry: &Registry, tenant: Option<&TenantId>) -> CliResult<ExitCode> {
+	const PAGE: usize = 200;
+
+	let now = OffsetDateTime::now_utc();
+	let mut after = None;
+
+	loop {
+		let page =
+			<dyn BrokerStore>::list_records(registry.store().as_ref(), after.as_deref(), PAGE)
+				.await?;
+
+		for record in &page.records {
+			if tenant.is_none_or(|tenant| record.family.tenant == *tenant) {
+				print_json(&RecordDescription::of(record, now))?;
+			}
+		}
+
+		match page.next {
+			Some(next) => after = Some(next),
+			None => return Ok(ExitCode::SUCCESS),
+		}
+	}
+}
+
+async fn login(
+	registry: &Registry,
+	target: &RecordTarget,
+	redirect_uri: Url,
+) -> CliResult<ExitCode> {
+	let broker = target.broker(registry)?;
+	let mut session = broker.start_authorization(
+		target.tenant.clone(),
+		target.principal.clone(),
+		target.scope.clone(),
+		redirect_uri,
+	)?;
+
+	println!("Open this URL and approve the request:\n\n  {}\n", session.authorize_url);
+	print!("Paste the URL the provider redirected to: ");
+	io::stdout().flush().map_err(|e| format!("Failed to write the prompt: {e}"))?;
+
+	let mut line = String::new();
+
+	io::stdin()
+		.lock()
+		.read_line(&mut line)
+		.map_err(|e| format!("Failed to read the callback URL: {e}"))?;
+
+	let callback = Url::parse(line.trim()).map_err(|e| format!("Callback URL is invalid: {e}"))?;
+	// Hybrid providers return the ID token in the fragment, plain code flows in the query.
+	let params = callback
+		.query_pairs()
+		.chain(form_urlencoded::parse(callback.fragment().unwrap_or_default().as_bytes()))
+		.map(|(key, value)| (key.into_owned(), value.into_owned()))
+		.collect::<Vec<_>>();
+	let param = |name: &str| params.iter().find(|(key, _)| key == name).map(|(_, value)| value);
+
+	if let Some(error) = param("error") {
+		eprintln!("The provider refused the login: {error}.");
+
+		return Ok(ExitCode::FAILURE);
+	}
+
+	let (Some(code), Some(state)) = (param("code"), param("state")) else {
+		return Err("Callback URL carries no `code` and `state`.".into());
+	};
+
+	session.validate_state(state)?;
+
+	if let Some(id_token) = param("id_token") {
+		session = session.with_id_token(id_token);
+	}
+	if let Some(iss) = param("iss") {
+		session = session.with_issuer(iss);
+	}
+
+	let record = broker.exchange_code(session, code).await?;
+
+	print_json(&RecordDescription::of(&record, OffsetDateTime::now_utc()))
+}
+
+async fn validate(
+	registry: &Registry,
+	provider: Option<&ProviderId>,
+	discovery_url: Option<Url>,
+	probe: bool,
+) -> CliResult<ExitCode> {
+	let providers = match provider {
+		Some(provider) => {
+			registry.get(provider.as_ref()).ok_or_else(|| unknown_provider(provider))?;
+
+			vec![provider.clone()]
+		},
+		None => registry.providers().cloned().collect(),
+	};
+	let mut code = ExitCode::SUCCESS;
+
+	for provider in providers {
+		let broker = registry.get(provider.as_ref()).ok_or_else(|| unknown_provider(&provider))?;
+		let mut options = ValidationOptions::default();
+
+		if let Some(url) = &discovery_url {
+			options = options.with_discovery_url(url.clone());
+		}
+		if probe {
+			options = options.with_token_endpoint_probe();
+		}
+
+		let report = broker.validate(options).await;
+
+		println!("{provider}: {}", if report.is_ok() { "ok" } else { "failed" });
+
+		for finding in &report.findings {
+			println!("  {:?} [{:?}] {}", finding.severity, finding.check, finding.message);
+		}
+
+		if !report.is_ok() {
+			code = ExitCode::FAILURE;
+		}
+	}
+
+	Ok(code)
+}
+
+fn print_json(value: &impl serde::Serialize) -> CliResult<ExitCode> {
+	println!("{}", serde_json::to_string(value)?);
+
+	Ok(ExitCode::SUCCESS)
+}
+
+fn parse_reason(raw: &str) -> Result<RevocationReason, String> {
+	serde_json::from_value(serde_json::Value::from(raw)).map_err(|_| {
+		"expected user_requested, provider_invalid_grant, reuse_detected, admin_action, or expired"
+			.into()
+	})
+}
+
+fn unknown_provider(provider: &ProviderId) -> Box<dyn std::error::Error> {
+	format!("Provider `{provider}` is not configured.").into()
+}
+
+#[cfg(test)]
+mod tests {
+	// crates.io
+	use clap::CommandFactory;
+	// self
+	use super::*;
+
+	#[test]
+	fn commands_parse_record_targets_and_reasons() {
+		Cli::command().debug_assert();
+
+		let cli = Cli::try_parse_from([
+			"oauth2-broker",
+			"--config",
+			"broker.json",
+			"tokens",
+			"revoke",
+			"--provider",
+			"github",
+			"--tenant",
+			"acme",
+			"--principal",
+			"user-1",
+			"--scope",
+			"repo read:org",
+			"--reason",
+			"reuse_detected",
+		])
+		.expect("Revoke arguments should parse.");
+		let Command::Tokens(TokensCommand::Revoke { target, reason }) = cli.command else {
+			panic!("Revoke arguments should select the revoke command.");
+		};
+
+		assert_eq!(reason, RevocationReason::ReuseDetected);
+		assert_eq!(target.scope.len(), 2);
+		assert_eq!(target.family().provider.as_ref().map(AsRef::as_ref), Some("github"));
+		assert!(parse_reason("forgotten").is_err());
+	}
+}
@@ -0,0 +1,555 @@
+//! Deserializable configuration used to assemble brokers without hand-written wiring.
+//!
+//! [`BrokerConfig`] describes the token store, every provider a service talks to (with the
+//! strategy that classifies its errors), and the policy and observability settings applied to
+//! each broker. Load it from JSON (a file or an environment variable) and hand it to
+//! [`BrokerRegistry::from_config`](crate::flows::BrokerRegistry::from_config) to obtain one
+//! broker per provider sharing the same store and HTTP transport.
+//!
+//! Secrets never need to be committed alongside the configuration: client secrets and store
+//! credentials (SQL connection strings, Redis URLs, etcd passwords, encryption keys) accept either
+//! an inline string with `${ENV_VAR}` placeholders or a `{ "file": "/run/secrets/..." }` reference,
+//! and store locations accept the same placeholders. Use `$$` to emit a literal `$`.
+
+// std
+use std::{env, fs, path::PathBuf};
+// crates.io
+#[cfg(feature = "ring")] use base64::{Engine, engine::general_purpose::STANDARD};
+// self
+#[cfg(feature = "etcd")] use crate::store::EtcdStore;
+#[cfg(feature = "redis")] use crate::store::RedisStore;
+#[cfg(feature = "sled")] use crate::store::SledStore;
+#[cfg(feature = "sqlx")] use crate::store::SqlStore;
+#[cfg(feature = "ring")] use crate::store::{EncryptedStore, MasterKey};
+use crate::{
+	_prelude::*,
+	auth::TenantId,
+	error::ConfigError,
+	obs::FlowKind,
+	provider::{
+		DefaultProviderStrategy, GitHubStrategy, ProviderDescriptor, ProviderEnvironment,
+		ProviderStrategy,
+	},
+	store::{BrokerStore, FileStore, MemoryStore},
+};
+
+/// Token store backend selected by a [`BrokerConfig`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum StoreConfig {
+	/// In-process [`MemoryStore`].
+	#[default]
+	Memory,
+	/// JSON snapshot [`FileStore`] persisted at `path`.
+	File {
+		/// Snapshot location on disk; `${ENV_VAR}` placeholders are expanded.
+		path: String,
+	},
+	/// Embedded [`SledStore`] database in the directory at `path`.
+	#[cfg(feature = "sled")]
+	Sled {
+		/// Database directory; `${ENV_VAR}` placeholders are expanded.
+		path: String,
+	},
+	/// [`EtcdStore`] talking to the v3 JSON gateway at `endpoint`.
+	#[cfg(feature = "etcd")]
+	Etcd {
+		/// Gateway URL (e.g. `http://127.0.0.1:2379`); `${ENV_VAR}` placeholders are expanded.
+		endpoint: String,
+		/// Key prefix, defaulting to [`EtcdStore::DEFAULT_PREFIX`].
+		#[serde(default)]
+		prefix: Option<String>,
+		/// etcd user to authenticate as when the cluster has auth enabled.
+		#[serde(default)]
+		username: Option<String>,
+		/// Password of `username`.
+		#[serde(default)]
+		password: Option<SecretSource>,
+	},
+	/// [`SqlStore`] on a `sqlx` pool opened from `dsn`.
+	///
+	/// The pool connects lazily and the table is not created; apply
+	/// [`SqlStore::schema`] or run [`SqlStore::migrate`] during deployment.
+	#[cfg(feature = "sqlx")]
+	Sql {
+		/// `postgres://`, `mysql://`, or `sqlite:` connection string.
+		dsn: SecretSource,
+		/// Table name, defaulting to [`SqlStore::DEFAULT_TABLE`].
+		#[serde(default)]
+		table: Option<String>,
+	},
+	/// [`RedisStore`] on a lazily connecting, reconnecting connection to `url`.
+	#[cfg(feature = "redis")]
+	Redis {
+		/// `redis://` URL, which may carry the password.
+		url: SecretSource,
+		/// Key prefix, defaulting to [`RedisStore::DEFAULT_PREFIX`].
+		#[serde(default)]
+		prefix: Option<String>,
+	},
+	/// [`EncryptedStore`] sealing the records of another backend.
+	#[cfg(feature = "ring")]
+	Encrypted {
+		/// Backend the sealed records are written to.
+		inner: Box<StoreConfig>,
+		/// Base64-encoded 32-byte master key.
+		master_key: SecretSource,
+		/// Previous master keys that stay readable during a rotation.
+		#[serde(default)]
+		retired_keys: Vec<SecretSource>,
+		/// Seals new writes under keys derived per tenant.
+		#[serde(default)]
+		per_tenant_keys: bool,
+	},
+}
+impl StoreConfig {
+	/// Opens the configured backend.
+	pub fn build(&self) -> Result<Arc<dyn BrokerStore>> {
+		Ok(match self {
+			Self::Memory => Arc::new(MemoryStore::default()),
+			Self::File { path } => Arc::new(FileStore::open(interpolate_env(path)?)?),
+			#[cfg(feature = "sled")]
+			Self::Sled { path } => Arc::new(SledStore::open(interpolate_env(path)?)?),
+			#[cfg(feature = "etcd")]
+			Self::Etcd { endpoint, prefix, username, password } => {
+				let endpoint = interpolate_env(endpoint)?;
+				let endpoint =
+					Url::parse(&endpoint).map_err(|e| ConfigError::InvalidBrokerConfig {
+						message: format!("Invalid etcd endpoint {endpoint}: {e}"),
+					})?;
+				let mut store = EtcdStore::new(endpoint);
+
+				if let Some(prefix) = prefix {
+					store = store.with_prefix(prefix);
+				}
+
+				match (username, password) {
+					(Some(username), Some(password)) =>
+						store = store.with_credentials(username, password.resolve()?),
+					(None, None) => (),
+					_ =>
+						return Err(ConfigError::InvalidBrokerConfig {
+							message: "etcd username and password must be set together".into(),
+						}
+						.into()),
+				}
+
+				Arc::new(store)
+			},
+			#[cfg(feature = "sqlx")]
+			Self::Sql { dsn, table } => {
+				let store = SqlStore::connect_lazy(&dsn.resolve()?)?;
+
+				Arc::new(match table {
+					Some(table) => store.with_table(table),
+					None => store,
+				})
+			},
+			#[cfg(feature = "redis")]
+			Self::Redis { url, prefix } => {
+				let store = RedisStore::connect_lazy(&url.resolve()?)?;
+
+				Arc::new(match prefix {
+					Some(prefix) => store.with_prefix(prefix),
+					None => store,
+				})
+			},
+			#[cfg(feature = "ring")]
+			Self::Encrypted { inner, master_key, retired_keys, per_tenant_keys } => {
+				let mut store = EncryptedStore::new(inner.build()?, master_key_from(master_key)?);
+
+				for retired in retired_keys {
+					store = store.with_retired_key(master_key_from(retired)?);
+				}
+				if *per_tenant_keys {
+					store = store.with_per_tenant_keys();
+				}
+
+				Arc::new(store)
+			},
+		})
+	}
+}
+
+/// Built-in [`ProviderStrategy`] selected for a [`ProviderConfig`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StrategyConfig {
+	/// [`DefaultProviderStrategy`], which follows RFC 6749 error codes.
+	#[default]
+	Default,
+	/// [`GitHubStrategy`], which understands GitHub's non-standard error codes.
+	#[serde(rename = "github")]
+	GitHub,
+}
+impl StrategyConfig {
+	/// Instantiates the selected strategy.
+	pub fn build(self) -> Arc<dyn ProviderStrategy> {
+		match self {
+			Self::Default => Arc::new(DefaultProviderStrategy),
+			Self::GitHub => Arc::new(GitHubStrategy),
+		}
+	}
+}
+
+/// Secret-bearing configuration value resolved when the registry is assembled.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SecretSource {
+	/// Inline value; `${ENV_VAR}` placeholders are expanded from the process environment.
+	Inline(String),
+	/// Value read from a file, with trailing line breaks removed.
+	File {
+		/// Location of the secret file; `${ENV_VAR}` placeholders are expanded.
+		file: String,
+	},
+}
+impl SecretSource {
+	/// Resolves the secret from the environment or the referenced file.
+	pub fn resolve(&self) -> Result<String, ConfigError> {
+		match self {
+			Self::Inline(raw) => interpolate_env(raw),
+			Self::File { file } => {
+				let path = interpolate_env(file)?;
+				let contents =
+					fs::read_to_string(&path).map_err(|e| ConfigError::InvalidBrokerConfig {
+						message: format!("Failed to read secret file {path}: {e}"),
+					})?;
+
+				Ok(contents.trim_end_matches(['\r', '\n']).to_owned())
+			},
+		}
+	}
+}
+impl Debug for SecretSource {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		match self {
+			Self::Inline(_) => f.debug_tuple("Inline").field(&"<redacted>").finish(),
+			Self::File { file } => f.debug_struct("File").field("file", file).finish(),
+		}
+	}
+}
+impl From<String> for SecretSource {
+	fn from(value: String) -> Self {
+		Self::Inline(value)
+	}
+}
+impl From<&str> for SecretSource {
+	fn from(value: &str) -> Self {
+		Self::Inline(value.to_owned())
+	}
+}
+
+/// Provider entry inside a [`BrokerConfig`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProviderConfig {
+	/// Descriptor for the provider; validated when the registry is assembled.
+	pub descriptor: ProviderDescriptor,
+	/// OAuth 2.0 client identifier registered with the provider.
+	pub client_id: String,
+	/// Optional client secret for confidential client authentication.
+	#[serde(default)]
+	pub client_secret: Option<SecretSource>,
+	/// Strategy that adjusts token requests and classifies the provider's errors.
+	#[serde(default)]
+	pub strategy: StrategyConfig,
+}
+
+/// Caching, coalescing, and flow gating applied to every broker of a [`BrokerConfig`].
+///
+/// Durations are whole seconds; unset values keep the broker defaults.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PolicyConfig {
+	/// Longest a single token endpoint call may take (see [`BrokerOverrides::timeout`]).
+	///
+	/// [`BrokerOverrides::timeout`]: crate::flows::BrokerOverrides::timeout
+	pub timeout_secs: Option<u64>,
+	/// Preemptive refresh window (see [`BrokerOverrides::preemptive_window`]).
+	///
+	/// [`BrokerOverrides::preemptive_window`]: crate::flows::BrokerOverrides::preemptive_window
+	pub preemptive_window_secs: Option<u64>,
+	/// Least remaining lifetime of a served token (see [`BrokerOverrides::min_ttl`]).
+	///
+	/// [`BrokerOverrides::min_ttl`]: crate::flows::BrokerOverrides::min_ttl
+	pub min_ttl_secs: Option<u64>,
+	/// Serves still-valid cached records when a singleflight wait times out.
+	pub allow_stale_on_timeout: bool,
+	/// Longest wait on a peer's in-flight request for the same token.
+	pub singleflight_wait_secs: Option<u64>,
+	/// Callers allowed to queue behind one in-flight request before new ones are shed.
+	pub max_queued_callers: Option<usize>,
+	/// How long revoked records are kept before a purge deletes them.
+	pub revoked_retention_secs: Option<u64>,
+	/// Flows disabled for every tenant.
+	pub disabled_flows: Vec<FlowKind>,
+	/// Flows disabled for individual tenants.
+	pub disabled_tenant_flows: BTreeMap<TenantId, Vec<FlowKind>>,
+}
+
+/// Logging and instrumentation applied to every broker of a [`BrokerConfig`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ObservabilityConfig {
+	/// Emits a redacted [`CallSummary`](crate::obs::CallSummary) for every cached-token call.
+	pub call_summaries: bool,
+	/// Wraps the store in an [`InstrumentedStore`](crate::obs::InstrumentedStore) so every store
+	/// call is traced and measured.
+	pub instrument_store: bool,
+	/// Stamps `last_served_at` on served records at most once per this many seconds.
+	pub served_tracking_secs: Option<u64>,
+}
+
+/// Root configuration for a set of brokers sharing one store.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BrokerConfig {
+	/// Token store shared by every provider.
+	#[serde(default)]
+	pub store: StoreConfig,
+	/// Providers to assemble brokers for.
+	#[serde(default)]
+	pub providers: Vec<ProviderConfig>,
+	/// Environment every provider is pointed at (see [`ProviderDescriptor::for_environment`]).
+	#[serde(default)]
+	pub environment: ProviderEnvironment,
+	/// Caching, coalescing, and flow gating settings.
+	#[serde(default)]
+	pub policy: PolicyConfig,
+	/// Logging and instrumentation settings.
+	#[serde(default)]
+	pub observability: ObservabilityConfig,
+}
+impl BrokerConfig {
+	/// Parses a configuration from a JSON string.
+	pub fn from_json_str(raw: &str) -> Result<Self, ConfigError> {
+		serde_json::from_str(raw)
+			.map_err(|e| ConfigError::InvalidBrokerConfig { message: e.to_string() })
+	}
+
+	/// Reads and parses a JSON configuration file.
+	pub fn from_json_file(path: impl Into<PathBuf>) -> Result<Self, ConfigError> {
+		let path = path.into();
+		let raw = fs::read_to_string(&path).map_err(|e| ConfigError::InvalidBrokerConfig {
+			message: format!("Failed to read {}: {e}", path.display()),
+		})?;
+
+		Self::from_json_str(&raw)
+	}
+
+	/// Parses a JSON configuration stored in the named environment variable.
+	pub fn from_env(var: &str) -> Result<Self, ConfigError> {
+		let raw = env::var(var).map_err(|e| ConfigError::InvalidBrokerConfig {
+			message: format!("Failed to read environment variable {var}: {e}"),
+		})?;
+
+		Self::from_json_str(&raw)
+	}
+}
+
+/// Expands `${ENV_VAR}` placeholders in `raw` using the process environment.
+pub fn interpolate_env(raw: &str) -> Result<String, ConfigError> {
+	interpolate_with(raw, |name| env::var(name).ok())
+}
+
+/// Converts a whole-second configuration value into a [`Duration`].
+pub(crate) fn seconds(secs: u64) -> Duration {
+	Duration::seconds(i64::try_from(secs).unwrap_or(i64::MAX))
+}
+
+#[cfg(feature = "ring")]
+fn master_key_from(source: &SecretSource) -> Result<MasterKey, ConfigError> {
+	let bytes = STANDARD
+		.decode(source.resolve()?.trim())
+		.ok()
+		.and_then(|bytes| <[u8; 32]>::try_from(bytes).ok());
+
+	bytes.map(MasterKey::new).ok_or_else(|| ConfigError::InvalidBrokerConfig {
+		message: "Store master keys must be 32 bytes encoded as base64".into(),
+	})
+}
+
+fn interpolate_with<F>(raw: &str, lookup: F) -> Result<String, ConfigError>
+where
+	F: Fn(&str) -> Option<String>,
+{
+	let mut buf = String::with_capacity(raw.len());
+	let mut rest = raw;
+
+	while let Some(idx) = rest.find('$') {
+		buf.push_str(&rest[..idx]);
+		rest = &rest[idx + 1..];
+
+		if let Some(tail) = rest.strip_prefix('$') {
+			buf.push('$');
+			rest = tail;
+		} else if let Some(tail) = rest.strip_prefix('{') {
+			let end = tail.find('}').ok_or_else(|| ConfigError::InvalidBrokerConfig {
+				message: format!("Unterminated environment placeholder in `{raw}`"),
+			})?;
+			let name = &tail[..end];
+			let value =
+				lookup(name).ok_or_else(|| ConfigError::MissingEnvVar { name: name.to_owned() })?;
+
+			buf.push_str(&value);
+			rest = &tail[end + 1..];
+		} else {
+			buf.push('$');
+		}
+	}
+
+	buf.push_str(rest);
+
+	Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+	// self
+	use super::*;
+
+	#[test]
+	fn parses_store_and_providers_from_json() {
+		let config = BrokerConfig::from_json_str(
+			r#"{
+				"store": { "kind": "file", "path": "/tmp/tokens.json" },
+				"providers": [{
+					"descriptor": {
+						"id": "demo",
+						"endpoints": {
+							"authorization": "https://demo.example.com/authorize",
+							"token": "https://demo.example.com/token",
+							"revocation": null
+						},
+						"supported_grants": {
+							"authorization_code": false,
+							"refresh_token": false,
+							"client_credentials": true
+						},
+						"preferred_client_auth_method": "client_secret_post",
+						"quirks": {}
+					},
+					"client_id": "demo-client"
+				}]
+			}"#,
+		)
+		.expect("Configuration fixture should parse.");
+
+		assert_eq!(config.store, StoreConfig::File { path: "/tmp/tokens.json".into() });
+		assert_eq!(config.providers.len(), 1);
+		assert_eq!(config.providers[0].client_secret, None);
+
+		let empty = BrokerConfig::from_json_str("{}").expect("Empty configuration should parse.");
+
+		assert_eq!(empty.store, StoreConfig::Memory);
+		assert!(empty.providers.is_empty());
+	}
+
+	#[test]
+	fn interpolation_expands_placeholders_and_escapes() {
+		let lookup = |name: &str| (name == "CLIENT_SECRET").then(|| "s3cr3t".to_owned());
+
+		assert_eq!(
+			interpolate_with("prefix-${CLIENT_SECRET}-suffix", lookup)
+				.expect("Known placeholders should expand."),
+			"prefix-s3cr3t-suffix"
+		);
+		assert_eq!(
+			interpolate_with("$$literal $plain", lookup).expect("Escapes should expand."),
+			"$literal $plain"
+		);
+		assert!(matches!(
+			interpolate_with("${MISSING}", lookup),
+			Err(ConfigError::MissingEnvVar { name }) if name == "MISSING"
+		));
+		assert!(interpolate_with("${UNTERMINATED", lookup).is_err());
+	}
+
+	#[test]
+	fn secret_sources_deserialize_and_redact() {
+		let inline: SecretSource =
+			serde_json::from_str("\"${CLIENT_SECRET}\"").expect("Inline secret should parse.");
+		let file: SecretSource = serde_json::from_str(r#"{ "file": "/run/secrets/client" }"#)
+			.expect("File secret reference should parse.");
+
+		assert_eq!(inline, SecretSource::Inline("${CLIENT_SECRET}".into()));
+		assert_eq!(file, SecretSource::File { file: "/run/secrets/client".into() });
+		assert_eq!(format!("{inline:?}"), "Inline(\"<redacted>\")");
+	}
+
+	#[cfg(feature = "ring")]
+	#[test]
+	fn encrypted_store_requires_a_32_byte_master_key() {
+		let encrypted = |master_key: &str| StoreConfig::Encrypted {
+			inner: Box::new(StoreConfig::Memory),
+			master_key: master_key.into(),
+			retired_keys: vec![STANDARD.encode([1; 32]).into()],
+			per_tenant_keys: true,
+		};
+
+		assert!(encrypted(&STANDARD.encode([7; 32])).build().is_ok());
+		assert!(encrypted(&STANDARD.encode([7; 16])).build().is_err());
+		assert!(encrypted("not base64").build().is_err());
+
+		let parsed: StoreConfig = serde_json::from_str(
+			r#"{ "kind": "encrypted", "inner": { "kind": "memory" }, "master_key": "${KEY}" }"#,
+		)
+		.expect("Encrypted store configuration should parse.");
+
+		assert!(matches!(parsed, StoreConfig::Encrypted { per_tenant_keys: false, .. }));
+	}
+
+	#[cfg(feature = "ring")]
+	#[test]
+	fn store_secrets_resolve_from_files() {
+		let path = env::temp_dir().join(format!("oauth2_broker_master_key_{}", std::process::id()));
+
+		fs::write(&path, format!("{}\n", STANDARD.encode([9; 32])))
+			.expect("Master key fixture should be written.");
+
+		let config = BrokerConfig::from_json_str(&format!(
+			r#"{{
+				"store": {{
+					"kind": "encrypted",
+					"inner": {{ "kind": "memory" }},
+					"master_key": {{ "file": "{}" }}
+				}}
+			}}"#,
+			path.display()
+		))
+		.expect("Configuration fixture should parse.");
+
+		assert!(config.store.build().is_ok());
+
+		fs::remove_file(&path).expect("Master key fixture should be removed.");
+
+		assert!(config.store.build().is_err());
+	}
+
+	#[cfg(feature = "etcd")]
+	#[test]
+	fn etcd_credentials_must_be_complete() {
+		let store: StoreConfig = serde_json::from_str(
+			r#"{ "kind": "etcd", "endpoint": "http://127.0.0.1:2379", "username": "broker" }"#,
+		)
+		.expect("etcd store configuration should parse.");
+
+		assert!(store.build().is_err());
+	}
+
+	#[cfg(feature = "redis")]
+	#[test]
+	fn redis_store_connects_lazily_inside_a_runtime() {
+		let store: StoreConfig = serde_json::from_str(
+			r#"{ "kind": "redis", "url": "redis://127.0.0.1:6379/0", "prefix": "{broker}:" }"#,
+		)
+		.expect("Redis store configuration should parse.");
+
+		assert!(store.build().is_err(), "Redis connections need a Tokio runtime.");
+
+		let runtime = tokio::runtime::Runtime::new()
+			.expect("Tokio runtime should start for the Redis store.");
+		let _entered = runtime.enter();
+
+		assert!(store.build().is_ok());
+	}
+}
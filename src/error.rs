@@ -1,7 +1,15 @@
 //! Broker-level error types shared across flows, providers, and stores.
 
+#[cfg(feature = "problem")] pub mod problem;
+#[cfg(feature = "problem")] pub use problem::*;
+
 // self
-use crate::_prelude::*;
+use crate::{
+	_prelude::*,
+	auth::{ScopeSet, TenantId},
+	ext::RetryDirective,
+	obs::FlowKind,
+};
 
 /// Broker-wide result type alias returning [`Error`] by default.
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -33,6 +41,8 @@ pub enum Error {
 	InsufficientScope {
 		/// Provider- or broker-supplied reason string.
 		reason: String,
+		/// Scopes the provider or resource server reported as required, when known.
+		required: Option<ScopeSet>,
 	},
 	/// Provider rejected the grant (e.g., bad code or refresh token).
 	#[error("Provider rejected the grant: {reason}.")]
@@ -49,6 +59,77 @@ pub enum Error {
 	/// Token has been revoked and must not be reused.
 	#[error("Token has been revoked.")]
 	Revoked,
+	/// The caller cancelled the flow through its
+	/// [`CancellationToken`](crate::flows::CancellationToken).
+	#[error("Flow was cancelled before {stage}.")]
+	Cancelled {
+		/// Checkpoint at which cancellation was observed.
+		stage: &'static str,
+	},
+	/// Transient provider failure observed during a scheduled maintenance window.
+	#[error("Provider is in a scheduled maintenance window until {ends_at}.")]
+	ProviderMaintenance {
+		/// End of the active maintenance window.
+		ends_at: OffsetDateTime,
+		/// Failure reported by the provider or transport.
+		#[source]
+		source: Box<Error>,
+	},
+	/// The broker's [`FlowPolicy`](crate::flows::FlowPolicy) disabled this flow.
+	#[error("The {flow} flow is disabled for tenant `{tenant}`.")]
+	FlowDisabled {
+		/// Flow that was refused.
+		flow: FlowKind,
+		/// Tenant the request was made for.
+		tenant: TenantId,
+	},
+	/// Waiting on another flow's singleflight guard exceeded
+	/// [`Broker::singleflight_wait`](crate::flows::Broker::singleflight_wait).
+	#[error(
+		"Timed out after {waited} waiting on an in-flight {blocking_flow} flow for the same token."
+	)]
+	SingleflightTimeout {
+		/// Flow holding the guard when the wait gave up (`"unknown"` if it just released it).
+		blocking_flow: &'static str,
+		/// When the blocking flow acquired the guard.
+		blocking_since: Option<OffsetDateTime>,
+		/// How long the caller waited.
+		waited: Duration,
+	},
+}
+
+impl Error {
+	/// Backoff suggested for retryable failures that carry no upstream `Retry-After` hint.
+	pub const DEFAULT_RETRY_BACKOFF: Duration = Duration::seconds(1);
+
+	/// Returns when the failed call may be retried, or `None` when retrying cannot succeed
+	/// without caller intervention (bad credentials, revoked grants, configuration errors).
+	///
+	/// Upstream `Retry-After` hints and rate-limit deferrals win; maintenance failures retry once
+	/// the window closes; other transient, transport, and singleflight timeout failures fall back
+	/// to [`Error::DEFAULT_RETRY_BACKOFF`].
+	pub fn retry_hint(&self) -> Option<RetryDirective> {
+		let now = OffsetDateTime::now_utc();
+		let after = |backoff: Duration, reason: String| {
+			Some(RetryDirective::new(now + backoff, backoff).with_reason(reason))
+		};
+
+		match self {
+			Self::Transient(TransientError::TokenEndpoint { retry_after: Some(delay), .. }) =>
+				after(*delay, self.to_string()),
+			Self::Transient(TransientError::RateLimited(directive)) => Some(directive.clone()),
+			Self::Transient(_) | Self::Transport(_) | Self::SingleflightTimeout { .. } =>
+				after(Self::DEFAULT_RETRY_BACKOFF, self.to_string()),
+			Self::ProviderMaintenance { ends_at, source } => {
+				let upstream = source.retry_hint().map(|hint| hint.earliest_retry_at);
+				let earliest = upstream.map_or(*ends_at, |upstream| upstream.max(*ends_at));
+				let backoff = (earliest - now).max(Duration::ZERO);
+
+				Some(RetryDirective::new(earliest, backoff).with_reason(self.to_string()))
+			},
+			_ => None,
+		}
+	}
 }
 
 /// Configuration and validation failures raised by the broker.
@@ -71,6 +152,42 @@ pub enum ConfigError {
 		#[source]
 		source: oauth2::url::ParseError,
 	},
+	/// Provider metadata document cannot be parsed.
+	#[error("Discovery document is invalid: {message}.")]
+	InvalidDiscoveryDocument {
+		/// Parser failure summary.
+		message: String,
+	},
+	/// Identifier failed validation.
+	#[error(transparent)]
+	InvalidIdentifier(#[from] crate::auth::IdentifierError),
+	/// Provider descriptor failed validation.
+	#[error(transparent)]
+	Descriptor(#[from] crate::provider::ProviderDescriptorError),
+	/// Broker configuration cannot be loaded or parsed.
+	#[error("Broker configuration is invalid: {message}.")]
+	InvalidBrokerConfig {
+		/// Loader or parser failure summary.
+		message: String,
+	},
+	/// Configuration references an environment variable that is not set.
+	#[error("Environment variable `{name}` referenced by the configuration is not set.")]
+	MissingEnvVar {
+		/// Name of the missing variable.
+		name: String,
+	},
+	/// Broker configuration declares the same provider more than once.
+	#[error("Provider `{provider}` is configured more than once.")]
+	DuplicateProvider {
+		/// Provider identifier string.
+		provider: String,
+	},
+	/// A request named a provider the broker has not registered.
+	#[error("Provider `{provider}` is not registered with this broker.")]
+	UnknownProvider {
+		/// Provider identifier string.
+		provider: String,
+	},
 	/// Redirect URI cannot be parsed.
 	#[error("Redirect URI is invalid.")]
 	InvalidRedirect {
@@ -87,6 +204,34 @@ pub enum ConfigError {
 		/// Disabled grant label.
 		grant: &'static str,
 	},
+	/// Descriptor does not declare endpoints for the requested environment.
+	#[error("Descriptor `{descriptor}` does not declare {environment} endpoints.")]
+	UnsupportedEnvironment {
+		/// Provider identifier string.
+		descriptor: String,
+		/// Environment that was requested.
+		environment: crate::provider::ProviderEnvironment,
+	},
+	/// A grant authenticates with a client secret, but the broker has none.
+	#[error(
+		"Descriptor `{descriptor}` authenticates the {grant} grant with {method}, but no client secret is configured."
+	)]
+	MissingClientSecret {
+		/// Provider identifier string.
+		descriptor: String,
+		/// Grant that needs the secret.
+		grant: &'static str,
+		/// Client authentication method label (RFC 8414 identifier).
+		method: &'static str,
+	},
+	/// A client secret is configured, but every grant authenticates as a public client.
+	#[error(
+		"Descriptor `{descriptor}` only authenticates as a public client (none), but a client secret is configured."
+	)]
+	UnusedClientSecret {
+		/// Provider identifier string.
+		descriptor: String,
+	},
 	/// Cached record is missing a refresh secret.
 	#[error("Cached token record is missing a refresh token.")]
 	MissingRefreshToken,
@@ -111,6 +256,82 @@ pub enum ConfigError {
 		/// Grant label.
 		grant: &'static str,
 	},
+	/// Descriptor has no revocation endpoint.
+	#[error("Descriptor does not define a revocation endpoint.")]
+	MissingRevocationEndpoint,
+	/// Descriptor has no backchannel authentication endpoint.
+	#[error("Descriptor does not define a backchannel authentication endpoint.")]
+	MissingBackchannelEndpoint,
+	/// Endpoint is marked experimental or was switched off at runtime.
+	#[error("The {endpoint} endpoint of descriptor `{descriptor}` is disabled.")]
+	EndpointDisabled {
+		/// Descriptor identifier.
+		descriptor: String,
+		/// Endpoint name.
+		endpoint: &'static str,
+	},
+	/// Backchannel authentication request is malformed.
+	#[error("Backchannel authentication request is invalid: {reason}.")]
+	InvalidBackchannelRequest {
+		/// Why the request was refused.
+		reason: &'static str,
+	},
+	/// JSON Web Key Set cannot be parsed.
+	#[error("JSON Web Key Set is invalid: {message}.")]
+	InvalidJwks {
+		/// Parser failure summary.
+		message: String,
+	},
+	/// Signing key material cannot be parsed.
+	#[error("Signing key is invalid: {message}.")]
+	InvalidSigningKey {
+		/// Parser failure summary.
+		message: String,
+	},
+	/// Recipient public key for sealed records cannot be used.
+	#[error("Recipient key is invalid: {message}.")]
+	InvalidRecipientKey {
+		/// Parser failure summary.
+		message: String,
+	},
+	/// Loopback redirect listener, browser launch, or callback failed.
+	#[error("Loopback redirect failed: {message}.")]
+	Loopback {
+		/// Failure detail.
+		message: String,
+	},
+	/// Token secrets could not be sealed to the recipient key.
+	#[error("Token secrets could not be sealed: {message}.")]
+	Sealing {
+		/// Encryption failure summary.
+		message: String,
+	},
+	/// JWT assertion could not be built or signed.
+	#[error("JWT assertion could not be signed: {message}.")]
+	AssertionSigning {
+		/// Signer failure summary.
+		message: String,
+	},
+	/// Token binding does not match the identity presenting the token.
+	#[error("Token binding does not match the presenting identity: {message}.")]
+	TokenBindingMismatch {
+		/// Mismatch summary.
+		message: String,
+	},
+	/// Externally issued token material cannot be imported.
+	#[error("Imported token is invalid: {message}.")]
+	InvalidImportedToken {
+		/// Validation failure summary.
+		message: String,
+	},
+	/// The provider strategy refused to send an assembled token request.
+	#[error("Token request for the {grant} grant was vetoed: {reason}.")]
+	TokenRequestVetoed {
+		/// Grant label.
+		grant: &'static str,
+		/// Strategy-provided explanation.
+		reason: String,
+	},
 }
 impl ConfigError {
 	/// Wraps a transport's builder failure inside [`ConfigError`].
@@ -147,6 +368,26 @@ pub enum TransientError {
 		/// HTTP status code, when available.
 		status: Option<u16>,
 	},
+	/// The broker's rate-limit policy deferred the call before it reached the provider.
+	#[error("Rate limit policy deferred the call until {}.", .0.earliest_retry_at)]
+	RateLimited(RetryDirective),
+	/// Too many callers were already queued behind an in-flight flow for the same token.
+	#[error(
+		"{waiting} callers are already queued behind an in-flight flow for the same token (limit {limit})."
+	)]
+	Backlogged {
+		/// Callers waiting when this one was shed.
+		waiting: usize,
+		/// Configured [`Broker::max_queued_callers`](crate::flows::Broker::max_queued_callers).
+		limit: usize,
+	},
+	/// The user has not yet approved a backchannel authentication request (CIBA
+	/// `authorization_pending` or `slow_down`).
+	#[error("The backchannel authentication request is still pending approval.")]
+	AuthorizationPending {
+		/// The provider asked the client to poll less often.
+		slow_down: bool,
+	},
 }
 /// Transport-level failures (network, IO).
 #[derive(Debug, ThisError)]
@@ -174,3 +415,29 @@ impl From<ReqwestError> for TransportError {
 		Self::network(e)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	// self
+	use super::*;
+
+	#[test]
+	fn retry_hint_prefers_upstream_retry_after_and_skips_fatal_errors() {
+		let throttled = Error::from(TransientError::TokenEndpoint {
+			message: "slow down".into(),
+			status: Some(429),
+			retry_after: Some(Duration::seconds(30)),
+		});
+		let hint = throttled.retry_hint().expect("Throttled errors should carry a retry hint.");
+
+		assert_eq!(hint.recommended_backoff, Duration::seconds(30));
+		assert!(hint.earliest_retry_at > OffsetDateTime::now_utc() + Duration::seconds(25));
+
+		let ends_at = OffsetDateTime::now_utc() + Duration::minutes(10);
+		let maintenance = Error::ProviderMaintenance { ends_at, source: Box::new(throttled) };
+
+		assert_eq!(maintenance.retry_hint().map(|hint| hint.earliest_retry_at), Some(ends_at),);
+		assert!(Error::InvalidGrant { reason: "revoked".into() }.retry_hint().is_none());
+		assert!(Error::Revoked.retry_hint().is_none());
+	}
+}
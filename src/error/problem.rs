@@ -0,0 +1,124 @@
+//! Maps broker errors onto HTTP responses for services that proxy them to their own clients.
+//!
+//! Enabled by the `problem` feature. [`Error::to_http_problem`] picks a suggested status code and
+//! builds an RFC 9457 `application/problem+json` body. Server-side failures (storage,
+//! configuration) omit the `detail` member so internal messages never leak to clients.
+
+// self
+use crate::{_prelude::*, error::Error};
+
+/// Media type for RFC 9457 problem bodies.
+pub const PROBLEM_JSON_CONTENT_TYPE: &str = "application/problem+json";
+
+/// RFC 9457 problem details object.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProblemDetails {
+	/// URI identifying the problem type.
+	#[serde(rename = "type")]
+	pub type_uri: String,
+	/// Short, human-readable summary of the problem type.
+	pub title: String,
+	/// HTTP status code generated for this occurrence.
+	pub status: u16,
+	/// Human-readable explanation specific to this occurrence.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub detail: Option<String>,
+}
+impl ProblemDetails {
+	/// Serializes the problem as a JSON body.
+	pub fn to_json(&self) -> String {
+		// A struct of strings and integers always serializes.
+		serde_json::to_string(self).unwrap_or_default()
+	}
+}
+
+/// Suggested HTTP response for a broker [`Error`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HttpProblem {
+	/// Suggested HTTP status code.
+	pub status: u16,
+	/// Value for a `Retry-After` header, in whole seconds.
+	pub retry_after_secs: Option<u64>,
+	/// Problem body to send with [`PROBLEM_JSON_CONTENT_TYPE`].
+	pub body: ProblemDetails,
+}
+
+impl Error {
+	/// Maps the error to a suggested HTTP status and RFC 9457 problem body.
+	///
+	/// `InvalidClient` and `Revoked` become 401, `InsufficientScope` and `FlowDisabled` 403,
+	/// `InvalidGrant` 400, transient, maintenance, and singleflight timeout failures 503 with a
+	/// `Retry-After` hint, transport failures 502, and everything else 500.
+	pub fn to_http_problem(&self) -> HttpProblem {
+		let (status, slug, title, public) = match self {
+			Self::InvalidClient { .. } =>
+				(401, "invalid-client", "Client authentication failed", true),
+			Self::Revoked => (401, "token-revoked", "Token has been revoked", true),
+			Self::InvalidGrant { .. } => (400, "invalid-grant", "Grant was rejected", true),
+			Self::InsufficientScope { .. } =>
+				(403, "insufficient-scope", "Token lacks the required scopes", true),
+			Self::FlowDisabled { .. } => (403, "flow-disabled", "Flow is disabled", true),
+			Self::Transient(_) =>
+				(503, "provider-unavailable", "Provider is temporarily unavailable", true),
+			Self::ProviderMaintenance { .. } =>
+				(503, "provider-maintenance", "Provider is under maintenance", true),
+			Self::SingleflightTimeout { .. } =>
+				(503, "token-busy", "Token is being renewed by another request", true),
+			Self::Transport(_) => (502, "provider-unreachable", "Provider is unreachable", true),
+			Self::Storage(_) | Self::Config(_) | Self::Cancelled { .. } =>
+				(500, "internal", "Internal broker error", false),
+		};
+		let retry_after_secs = match status {
+			503 => self.retry_hint().map(|hint| {
+				let secs = hint.recommended_backoff.whole_seconds().max(1);
+
+				u64::try_from(secs).unwrap_or(1)
+			}),
+			_ => None,
+		};
+
+		HttpProblem {
+			status,
+			retry_after_secs,
+			body: ProblemDetails {
+				type_uri: format!("urn:oauth2-broker:problem:{slug}"),
+				title: title.into(),
+				status,
+				detail: public.then(|| self.to_string()),
+			},
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	// self
+	use super::*;
+	use crate::error::{ConfigError, TransientError};
+
+	#[test]
+	fn http_problem_maps_status_retry_after_and_hides_internal_details() {
+		let denied = Error::InvalidClient { reason: "bad secret".into() }.to_http_problem();
+
+		assert_eq!(denied.status, 401);
+		assert_eq!(denied.body.type_uri, "urn:oauth2-broker:problem:invalid-client");
+		assert!(
+			denied.body.to_json().contains("\"type\":\"urn:oauth2-broker:problem:invalid-client\"")
+		);
+
+		let throttled = Error::from(TransientError::TokenEndpoint {
+			message: "slow down".into(),
+			status: Some(429),
+			retry_after: Some(Duration::seconds(12)),
+		})
+		.to_http_problem();
+
+		assert_eq!(throttled.status, 503);
+		assert_eq!(throttled.retry_after_secs, Some(12));
+
+		let internal = Error::from(ConfigError::MissingRefreshToken).to_http_problem();
+
+		assert_eq!(internal.status, 500);
+		assert!(internal.body.detail.is_none());
+	}
+}
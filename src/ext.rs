@@ -1,14 +1,50 @@
-//! Public extension contracts (request signing, token leasing, rate limiting).
+//! Public extension contracts (request signing, token leasing, rate limiting, quotas).
 //!
-//! The MVP crate intentionally exposes traits without concrete implementations so
-//! downstream services can bring their own HTTP client, token cache, and rate
-//! budgeting strategy. Future tasks will implement opinionated adapters in
-//! separate crates without expanding the surface of `oauth2-broker` itself.
+//! The crate mostly exposes traits without concrete implementations so downstream
+//! services can bring their own HTTP client, token cache, and rate budgeting
+//! strategy. [`DpopSigner`] and [`CertificateBoundSigner`] are the exceptions: sender
+//! constraints are protocol logic rather than client plumbing, so they ship here behind
+//! the [`DpopRequest`] adapter. [`QuotaPolicy`] is the other: a ready-made
+//! [`RateLimitPolicy`] that enforces fair-share provider budgets over a pluggable
+//! [`QuotaStore`].
 
+pub mod dpop;
+pub mod mtls;
+pub mod quota;
 pub mod rate_limit;
 pub mod request_signer;
 pub mod token_lease;
 
+pub use dpop::*;
+pub use mtls::*;
+pub use quota::*;
 pub use rate_limit::*;
 pub use request_signer::*;
 pub use token_lease::*;
+
+#[cfg(test)]
+mod fixtures {
+	// self
+	use crate::{
+		_prelude::*,
+		auth::{PrincipalId, ScopeSet, TenantId, TokenBinding, TokenFamily, TokenRecord},
+	};
+
+	/// Short-lived `api` record for the sender-constraint tests, bound when `binding` is set.
+	pub(super) fn bound_record(binding: Option<TokenBinding>) -> TokenRecord {
+		let family = TokenFamily::new(
+			TenantId::new("tenant").expect("Tenant fixture should be valid."),
+			PrincipalId::new("principal").expect("Principal fixture should be valid."),
+		);
+		let mut builder =
+			TokenRecord::builder(family, ScopeSet::new(["api"]).expect("Scope should be valid."))
+				.access_token("bound-token")
+				.expires_in(Duration::minutes(5));
+
+		if let Some(binding) = binding {
+			builder = builder.binding(binding);
+		}
+
+		builder.build().expect("Record fixture should build.")
+	}
+}
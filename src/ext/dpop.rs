@@ -0,0 +1,288 @@
+//! DPoP (RFC 9449) request signing for DPoP-bound tokens.
+//!
+//! [`DpopSigner`] implements [`RequestSignerExt`] for any request type that implements the small
+//! [`DpopRequest`] adapter: it checks that the [`TokenRecord`] is bound to the signer's key, then
+//! attaches `Authorization: DPoP <token>` together with a fresh per-request `DPoP` proof.
+
+// crates.io
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use rand::{Rng, distr::Alphanumeric};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+// self
+use crate::{
+	_prelude::*,
+	auth::{TokenBinding, TokenRecord},
+	error::ConfigError,
+	ext::RequestSignerExt,
+	flows::JwtSigner,
+};
+
+/// Asymmetric key that signs DPoP proofs and publishes its public half.
+pub trait DpopKey: JwtSigner {
+	/// Public JWK (RFC 7517) embedded in every proof header.
+	fn public_jwk(&self) -> Map<String, Value>;
+}
+
+/// Minimal view of an outbound request needed to build and attach a DPoP proof.
+pub trait DpopRequest: Sized {
+	/// HTTP method (`htm` claim).
+	fn method(&self) -> &str;
+
+	/// Absolute target URI; query and fragment are stripped for the `htu` claim.
+	fn target_uri(&self) -> String;
+
+	/// Returns the request with `name` set to `value`, replacing any previous value.
+	fn with_header(self, name: &'static str, value: String) -> Result<Self, ConfigError>;
+}
+impl<B> DpopRequest for oauth2::http::Request<B> {
+	fn method(&self) -> &str {
+		self.method().as_str()
+	}
+
+	fn target_uri(&self) -> String {
+		self.uri().to_string()
+	}
+
+	fn with_header(mut self, name: &'static str, value: String) -> Result<Self, ConfigError> {
+		let value = oauth2::http::HeaderValue::try_from(value)
+			.map_err(|e| ConfigError::AssertionSigning { message: e.to_string() })?;
+
+		self.headers_mut().insert(oauth2::http::HeaderName::from_static(name), value);
+
+		Ok(self)
+	}
+}
+#[cfg(feature = "reqwest")]
+impl DpopRequest for reqwest::Request {
+	fn method(&self) -> &str {
+		self.method().as_str()
+	}
+
+	fn target_uri(&self) -> String {
+		self.url().to_string()
+	}
+
+	fn with_header(mut self, name: &'static str, value: String) -> Result<Self, ConfigError> {
+		let value = reqwest::header::HeaderValue::try_from(value)
+			.map_err(|e| ConfigError::AssertionSigning { message: e.to_string() })?;
+
+		self.headers_mut().insert(reqwest::header::HeaderName::from_static(name), value);
+
+		Ok(self)
+	}
+}
+
+/// [`RequestSignerExt`] implementation that presents DPoP-bound tokens with per-request proofs.
+pub struct DpopSigner {
+	key: Arc<dyn DpopKey>,
+	jkt: String,
+	nonce: Mutex<Option<String>>,
+}
+impl DpopSigner {
+	/// Creates a signer for `key`, computing its JWK thumbprint up front.
+	pub fn new(key: Arc<dyn DpopKey>) -> Result<Self, ConfigError> {
+		let jkt = jwk_thumbprint(&key.public_jwk())?;
+
+		Ok(Self { key, jkt, nonce: Mutex::new(None) })
+	}
+
+	/// RFC 7638 thumbprint of the signer's public key, matching [`TokenBinding::Dpop::jkt`].
+	pub fn thumbprint(&self) -> &str {
+		&self.jkt
+	}
+
+	/// Records the latest server-provided `DPoP-Nonce`, included in subsequent proofs.
+	pub fn set_nonce(&self, nonce: impl Into<String>) {
+		*self.nonce.lock() = Some(nonce.into());
+	}
+
+	/// Builds a signed proof for `method` + `uri`, binding `access_token` through `ath` when set.
+	pub fn proof(
+		&self,
+		method: &str,
+		uri: &str,
+		access_token: Option<&str>,
+		now: OffsetDateTime,
+	) -> Result<String, ConfigError> {
+		let mut header = Map::new();
+
+		header.insert("typ".into(), "dpop+jwt".into());
+		header.insert("alg".into(), self.key.algorithm().into());
+		header.insert("jwk".into(), Value::Object(self.key.public_jwk()));
+
+		let mut claims = Map::new();
+		let htu = uri.split(['?', '#']).next().unwrap_or(uri);
+
+		claims.insert("jti".into(), jti().into());
+		claims.insert("htm".into(), method.to_ascii_uppercase().into());
+		claims.insert("htu".into(), htu.into());
+		claims.insert("iat".into(), now.unix_timestamp().into());
+
+		if let Some(token) = access_token {
+			claims.insert("ath".into(), URL_SAFE_NO_PAD.encode(Sha256::digest(token)).into());
+		}
+		if let Some(nonce) = self.nonce.lock().clone() {
+			claims.insert("nonce".into(), nonce.into());
+		}
+
+		let signing_input = format!("{}.{}", encode_segment(&header)?, encode_segment(&claims)?);
+		let signature = self.key.sign(signing_input.as_bytes())?;
+
+		Ok(format!("{signing_input}.{}", URL_SAFE_NO_PAD.encode(signature)))
+	}
+}
+impl<R> RequestSignerExt<R, ConfigError> for DpopSigner
+where
+	R: DpopRequest,
+{
+	fn attach_token(&self, request: R, record: &TokenRecord) -> Result<R, ConfigError> {
+		match &record.binding {
+			Some(TokenBinding::Dpop { jkt }) if jkt == &self.jkt => {},
+			Some(TokenBinding::Dpop { .. }) =>
+				return Err(ConfigError::TokenBindingMismatch {
+					message: "token is bound to a different DPoP key".into(),
+				}),
+			Some(TokenBinding::Certificate { .. }) | None =>
+				return Err(ConfigError::TokenBindingMismatch {
+					message: "token is not DPoP-bound".into(),
+				}),
+		}
+
+		let token = record.access_token.expose();
+		let proof = self.proof(
+			request.method(),
+			&request.target_uri(),
+			Some(token),
+			OffsetDateTime::now_utc(),
+		)?;
+
+		request.with_header("authorization", format!("DPoP {token}"))?.with_header("dpop", proof)
+	}
+}
+impl Debug for DpopSigner {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		f.debug_struct("DpopSigner")
+			.field("algorithm", &self.key.algorithm())
+			.field("jkt", &self.jkt)
+			.finish()
+	}
+}
+
+/// Computes the RFC 7638 JWK SHA-256 thumbprint (base64url, no padding) of a public key.
+pub fn jwk_thumbprint(jwk: &Map<String, Value>) -> Result<String, ConfigError> {
+	let invalid = |message: String| ConfigError::InvalidSigningKey { message };
+	let members: &[&str] = match jwk.get("kty").and_then(Value::as_str) {
+		Some("RSA") => &["e", "kty", "n"],
+		Some("EC") => &["crv", "kty", "x", "y"],
+		Some("OKP") => &["crv", "kty", "x"],
+		Some(kty) => return Err(invalid(format!("unsupported JWK key type `{kty}`"))),
+		None => return Err(invalid("JWK is missing `kty`".into())),
+	};
+	let mut canonical = Vec::with_capacity(members.len());
+
+	for member in members {
+		let value = jwk
+			.get(*member)
+			.and_then(Value::as_str)
+			.ok_or_else(|| invalid(format!("JWK is missing `{member}`")))?;
+		let value = serde_json::to_string(value).map_err(|e| invalid(e.to_string()))?;
+
+		canonical.push(format!("\"{member}\":{value}"));
+	}
+
+	Ok(URL_SAFE_NO_PAD.encode(Sha256::digest(format!("{{{}}}", canonical.join(",")))))
+}
+
+fn encode_segment(value: &Map<String, Value>) -> Result<String, ConfigError> {
+	let bytes = serde_json::to_vec(value)
+		.map_err(|e| ConfigError::AssertionSigning { message: e.to_string() })?;
+
+	Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+fn jti() -> String {
+	rand::rng().sample_iter(Alphanumeric).take(32).map(char::from).collect()
+}
+
+#[cfg(test)]
+mod tests {
+	// self
+	use super::*;
+	use crate::ext::fixtures::bound_record;
+
+	// RFC 7638 §3.1 example key.
+	const RFC7638_N: &str = "0vx7agoebGcQSuuPiLJXZptN9nndrQmbXEps2aiAFbWhM78LhWx4cbbfAAtVT86zwu1RK7aPFFxuhDR1L6tSoc_BJECPebWKRXjBZCiFV4n3oknjhMstn64tZ_2W-5JsGY4Hc5n9yBXArwl93lqt7_RN5w6Cf0h4QyQ5v-65YGjQR0_FDW2QvzqY368QQMicAtaSqzs8KJZgnYb9c7d0zgdAZHzu6qMQvRL5hajrn1n91CbOpbISD08qNLyrdkt-bFTWhAI4vMQFh6WeZu0fM4lFd2NcRwr3XPksINHaQ-G_xBniIqbw0Ls1jF44-csFCur-kEgU8awapJzKnqDKgw";
+
+	struct StaticKey;
+	impl JwtSigner for StaticKey {
+		fn algorithm(&self) -> &str {
+			"RS256"
+		}
+
+		fn sign(&self, _: &[u8]) -> Result<Vec<u8>, ConfigError> {
+			Ok(b"sig".to_vec())
+		}
+	}
+	impl DpopKey for StaticKey {
+		fn public_jwk(&self) -> Map<String, Value> {
+			let mut jwk = Map::new();
+
+			jwk.insert("kty".into(), "RSA".into());
+			jwk.insert("n".into(), RFC7638_N.into());
+			jwk.insert("e".into(), "AQAB".into());
+			jwk.insert("alg".into(), "RS256".into());
+
+			jwk
+		}
+	}
+
+	fn claims(proof: &str) -> Value {
+		let segment = proof.split('.').nth(1).expect("Proof should contain claims.");
+
+		serde_json::from_slice(&URL_SAFE_NO_PAD.decode(segment).expect("Claims should decode."))
+			.expect("Claims should be JSON.")
+	}
+
+	#[test]
+	fn thumbprint_matches_rfc_7638_example() {
+		let signer = DpopSigner::new(Arc::new(StaticKey)).expect("Signer should build.");
+
+		assert_eq!(signer.thumbprint(), "NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs");
+	}
+
+	#[test]
+	fn attaches_dpop_authorization_and_proof_for_bound_records() {
+		let signer = DpopSigner::new(Arc::new(StaticKey)).expect("Signer should build.");
+		let bound = bound_record(Some(TokenBinding::Dpop { jkt: signer.thumbprint().into() }));
+
+		signer.set_nonce("server-nonce");
+
+		let request = oauth2::http::Request::post("https://api.example.com/items?page=2")
+			.body(())
+			.expect("Request fixture should build.");
+		let signed = signer.attach_token(request, &bound).expect("Bound record should sign.");
+		let headers = signed.headers();
+
+		assert_eq!(headers["authorization"], "DPoP bound-token");
+
+		let proof = headers["dpop"].to_str().expect("Proof should be ASCII.");
+		let claims = claims(proof);
+
+		assert_eq!(claims["htm"], "POST");
+		assert_eq!(claims["htu"], "https://api.example.com/items");
+		assert_eq!(claims["nonce"], "server-nonce");
+		assert_eq!(claims["ath"], URL_SAFE_NO_PAD.encode(Sha256::digest("bound-token")));
+
+		for binding in [None, Some(TokenBinding::Dpop { jkt: "other".into() })] {
+			let request = oauth2::http::Request::get("https://api.example.com/items")
+				.body(())
+				.expect("Request fixture should build.");
+
+			assert!(matches!(
+				signer.attach_token(request, &bound_record(binding)),
+				Err(ConfigError::TokenBindingMismatch { .. })
+			));
+		}
+	}
+}
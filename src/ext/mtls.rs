@@ -0,0 +1,109 @@
+//! Request signing for certificate-bound access tokens (RFC 8705).
+//!
+//! [`CertificateBoundSigner`] attaches `Authorization: Bearer` only when the record's
+//! [`TokenBinding::Certificate`] thumbprint matches the client certificate the transport
+//! presents, so a bound token is never sent over a connection the provider would reject.
+
+// self
+use crate::{
+	_prelude::*,
+	auth::{TokenBinding, TokenRecord},
+	error::ConfigError,
+	ext::{DpopRequest, RequestSignerExt},
+	http::TokenHttpClient,
+};
+
+/// [`RequestSignerExt`] implementation that refuses to present tokens bound to another
+/// certificate.
+///
+/// Unbound bearer records are attached as-is; request types plug in through the
+/// [`DpopRequest`] adapter.
+#[derive(Clone, Debug)]
+pub struct CertificateBoundSigner {
+	x5t_s256: String,
+}
+impl CertificateBoundSigner {
+	/// Creates a signer for the certificate with the given `x5t#S256` thumbprint.
+	pub fn new(x5t_s256: impl Into<String>) -> Self {
+		Self { x5t_s256: x5t_s256.into() }
+	}
+
+	/// Creates a signer for the client certificate configured on `client`.
+	pub fn for_client<C>(client: &C) -> Result<Self, ConfigError>
+	where
+		C: ?Sized + TokenHttpClient,
+	{
+		client.client_certificate_thumbprint().map(Self::new).ok_or_else(|| {
+			ConfigError::TokenBindingMismatch {
+				message: "transport has no client certificate configured".into(),
+			}
+		})
+	}
+
+	/// Thumbprint of the certificate this signer presents.
+	pub fn thumbprint(&self) -> &str {
+		&self.x5t_s256
+	}
+
+	/// Fails when `record` is bound to a key or certificate other than this signer's.
+	pub fn verify(&self, record: &TokenRecord) -> Result<(), ConfigError> {
+		match &record.binding {
+			None => Ok(()),
+			Some(TokenBinding::Certificate { x5t_s256 }) if x5t_s256 == &self.x5t_s256 => Ok(()),
+			Some(TokenBinding::Certificate { .. }) => Err(ConfigError::TokenBindingMismatch {
+				message: "token is bound to a different client certificate".into(),
+			}),
+			Some(TokenBinding::Dpop { .. }) =>
+				Err(ConfigError::TokenBindingMismatch { message: "token is DPoP-bound".into() }),
+		}
+	}
+}
+impl<R> RequestSignerExt<R, ConfigError> for CertificateBoundSigner
+where
+	R: DpopRequest,
+{
+	fn attach_token(&self, request: R, record: &TokenRecord) -> Result<R, ConfigError> {
+		self.verify(record)?;
+
+		request.with_header("authorization", format!("Bearer {}", record.access_token.expose()))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	// self
+	use super::*;
+	use crate::ext::fixtures::bound_record;
+
+	#[test]
+	fn refuses_tokens_bound_to_another_identity() {
+		let thumbprint = TokenBinding::certificate_thumbprint(b"client-cert-der");
+		let signer = CertificateBoundSigner::new(thumbprint.clone());
+		let request = || {
+			oauth2::http::Request::get("https://api.example.com/items")
+				.body(())
+				.expect("Request fixture should build.")
+		};
+		let signed = signer
+			.attach_token(
+				request(),
+				&bound_record(Some(TokenBinding::Certificate { x5t_s256: thumbprint })),
+			)
+			.expect("Matching certificate binding should sign.");
+
+		assert_eq!(signed.headers()["authorization"], "Bearer bound-token");
+		assert!(signer.attach_token(request(), &bound_record(None)).is_ok());
+
+		for binding in [
+			TokenBinding::Certificate {
+				x5t_s256: TokenBinding::certificate_thumbprint(b"other-cert-der"),
+			},
+			TokenBinding::Dpop { jkt: "key".into() },
+		] {
+			assert!(matches!(
+				signer.attach_token(request(), &bound_record(Some(binding))),
+				Err(ConfigError::TokenBindingMismatch { .. })
+			));
+		}
+	}
+}
@@ -0,0 +1,360 @@
+//! Provider quota accounting for fair-share token endpoint usage.
+//!
+//! [`QuotaPolicy`] counts outbound token calls per provider and per tenant over sliding windows,
+//! keeps the call log in a [`QuotaStore`], and answers remaining-budget queries. It also
+//! implements [`RateLimitPolicy`], so installing it through
+//! [`BrokerOverrides::rate_limit`](crate::flows::BrokerOverrides::rate_limit) both records every
+//! call and defers the ones that would exceed a budget.
+
+// std
+use std::collections::VecDeque;
+// self
+use crate::{
+	_prelude::*,
+	auth::{ProviderId, TenantId},
+	ext::{RateLimitContext, RateLimitDecision, RateLimitFuture, RateLimitPolicy, RetryDirective},
+	store::{StoreError, StoreFuture},
+};
+
+/// Persistence contract for the call log behind [`QuotaPolicy`].
+///
+/// Share one store between broker replicas so their calls count against the same budgets. With
+/// the `sled` feature, [`SledStore`](crate::store::SledStore) keeps the log on disk next to the
+/// token records.
+pub trait QuotaStore
+where
+	Self: Send + Sync,
+{
+	/// Appends a call made at `at` and drops entries for `key` older than `at - retain`.
+	fn record_call<'a>(
+		&'a self,
+		key: &'a QuotaKey,
+		at: OffsetDateTime,
+		retain: Duration,
+	) -> StoreFuture<'a, ()>;
+
+	/// Returns the timestamps of calls for `key` made at or after `since`, oldest first.
+	fn calls_since<'a>(
+		&'a self,
+		key: &'a QuotaKey,
+		since: OffsetDateTime,
+	) -> StoreFuture<'a, Vec<OffsetDateTime>>;
+}
+
+/// Call log partition counted by a [`QuotaPolicy`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct QuotaKey {
+	/// Provider the calls were made to.
+	pub provider: ProviderId,
+	/// Tenant the calls were made for (`None` counts every tenant of the provider).
+	pub tenant: Option<TenantId>,
+}
+impl QuotaKey {
+	/// Key counting every call to `provider`.
+	pub fn provider(provider: ProviderId) -> Self {
+		Self { provider, tenant: None }
+	}
+
+	/// Key counting the calls `tenant` makes to `provider`.
+	pub fn tenant(provider: ProviderId, tenant: TenantId) -> Self {
+		Self { provider, tenant: Some(tenant) }
+	}
+}
+
+/// Most calls allowed within a sliding window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuotaLimit {
+	/// Calls allowed per window.
+	pub max_calls: u64,
+	/// Length of the sliding window.
+	pub window: Duration,
+}
+impl QuotaLimit {
+	/// Creates a limit of `max_calls` per `window`.
+	pub fn new(max_calls: u64, window: Duration) -> Self {
+		Self { max_calls, window }
+	}
+}
+
+/// Remaining budget reported by [`QuotaPolicy::remaining`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QuotaBudget {
+	/// Calls still allowed right now (`None` when no limit applies).
+	pub remaining: Option<u64>,
+	/// When the tightest window frees its next call, if it is currently exhausted.
+	pub resets_at: Option<OffsetDateTime>,
+}
+impl QuotaBudget {
+	/// Returns `true` when another call would exceed a limit.
+	pub fn is_exhausted(&self) -> bool {
+		self.remaining == Some(0)
+	}
+}
+
+/// Sliding-window quota accounting per provider and tenant.
+#[derive(Clone)]
+pub struct QuotaPolicy {
+	/// Call log shared by every broker using this policy.
+	pub store: Arc<dyn QuotaStore>,
+	/// Budget for all tenants of a provider combined.
+	pub provider_limits: HashMap<ProviderId, QuotaLimit>,
+	/// Fair-share budget applied to each tenant of every provider.
+	pub tenant_limit: Option<QuotaLimit>,
+	/// Per-tenant budgets replacing [`tenant_limit`](Self::tenant_limit).
+	pub tenant_overrides: HashMap<TenantId, QuotaLimit>,
+}
+impl QuotaPolicy {
+	/// Creates a policy without limits that logs calls into `store`.
+	pub fn new(store: Arc<dyn QuotaStore>) -> Self {
+		Self {
+			store,
+			provider_limits: HashMap::new(),
+			tenant_limit: None,
+			tenant_overrides: HashMap::new(),
+		}
+	}
+
+	/// Caps the calls all tenants combined make to `provider`.
+	pub fn with_provider_limit(mut self, provider: ProviderId, limit: QuotaLimit) -> Self {
+		self.provider_limits.insert(provider, limit);
+
+		self
+	}
+
+	/// Caps the calls each tenant makes to any provider.
+	pub fn with_tenant_limit(mut self, limit: QuotaLimit) -> Self {
+		self.tenant_limit = Some(limit);
+
+		self
+	}
+
+	/// Gives `tenant` its own budget instead of the shared tenant limit.
+	pub fn with_tenant_override(mut self, tenant: TenantId, limit: QuotaLimit) -> Self {
+		self.tenant_overrides.insert(tenant, limit);
+
+		self
+	}
+
+	/// Records a call to `provider` on behalf of `tenant` made at `at`.
+	pub async fn record(
+		&self,
+		provider: &ProviderId,
+		tenant: &TenantId,
+		at: OffsetDateTime,
+	) -> Result<(), StoreError> {
+		for (key, limit) in self.partitions(provider, tenant) {
+			if let Some(limit) = limit {
+				self.store.record_call(&key, at, limit.window).await?;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Returns the budget `tenant` has left with `provider` at `now`.
+	///
+	/// The tightest of the provider-wide and tenant limits wins.
+	pub async fn remaining(
+		&self,
+		provider: &ProviderId,
+		tenant: &TenantId,
+		now: OffsetDateTime,
+	) -> Result<QuotaBudget, StoreError> {
+		let mut budget = QuotaBudget { remaining: None, resets_at: None };
+
+		for (key, limit) in self.partitions(provider, tenant) {
+			let Some(limit) = limit else {
+				continue;
+			};
+			let calls = self.store.calls_since(&key, now - limit.window).await?;
+			let remaining = limit.max_calls.saturating_sub(calls.len() as u64);
+
+			if remaining == 0 {
+				// The window frees a slot once enough of the oldest calls age out.
+				let freeing = calls.len() as u64 - limit.max_calls;
+				let resets_at =
+					calls.get(freeing as usize).map_or(now, |oldest| *oldest + limit.window);
+
+				budget.resets_at = Some(budget.resets_at.map_or(resets_at, |at| at.max(resets_at)));
+			}
+
+			budget.remaining = Some(budget.remaining.map_or(remaining, |r| r.min(remaining)));
+		}
+
+		Ok(budget)
+	}
+
+	/// Yields the provider-wide and tenant partitions with the limit applied to each (`None`
+	/// partitions are neither logged nor checked).
+	fn partitions(
+		&self,
+		provider: &ProviderId,
+		tenant: &TenantId,
+	) -> [(QuotaKey, Option<QuotaLimit>); 2] {
+		let tenant_limit = self.tenant_overrides.get(tenant).copied().or(self.tenant_limit);
+
+		[
+			(QuotaKey::provider(provider.clone()), self.provider_limits.get(provider).copied()),
+			(QuotaKey::tenant(provider.clone(), tenant.clone()), tenant_limit),
+		]
+	}
+}
+impl Debug for QuotaPolicy {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		f.debug_struct("QuotaPolicy")
+			.field("provider_limits", &self.provider_limits)
+			.field("tenant_limit", &self.tenant_limit)
+			.field("tenant_overrides", &self.tenant_overrides)
+			.finish()
+	}
+}
+impl RateLimitPolicy<Error> for QuotaPolicy {
+	/// Defers the call when a budget is exhausted; otherwise records it and allows it.
+	///
+	/// Checking and recording are separate store calls, so concurrent callers can overshoot a
+	/// budget by the number of calls racing through the same instant.
+	fn evaluate(&self, context: &RateLimitContext) -> RateLimitFuture<'_, Error> {
+		let context = context.clone();
+
+		Box::pin(async move {
+			let now = context.observed_at;
+			let budget = self.remaining(&context.provider_id, &context.tenant_id, now).await?;
+
+			if budget.is_exhausted() {
+				let earliest = budget.resets_at.unwrap_or(now);
+				let directive = RetryDirective::new(earliest, (earliest - now).max(Duration::ZERO))
+					.with_reason(format!(
+						"Quota for tenant {} on provider {} is exhausted.",
+						context.tenant_id, context.provider_id
+					));
+
+				return Ok(RateLimitDecision::Delay(directive));
+			}
+
+			self.record(&context.provider_id, &context.tenant_id, now).await?;
+
+			Ok(RateLimitDecision::Allow)
+		})
+	}
+}
+
+/// In-process [`QuotaStore`] for single-replica deployments and tests.
+#[derive(Debug, Default)]
+pub struct MemoryQuotaStore {
+	calls: Mutex<HashMap<QuotaKey, VecDeque<OffsetDateTime>>>,
+}
+impl QuotaStore for MemoryQuotaStore {
+	fn record_call<'a>(
+		&'a self,
+		key: &'a QuotaKey,
+		at: OffsetDateTime,
+		retain: Duration,
+	) -> StoreFuture<'a, ()> {
+		Box::pin(async move {
+			let mut calls = self.calls.lock();
+			let log = calls.entry(key.clone()).or_default();
+			let cutoff = at - retain;
+
+			log.push_back(at);
+			log.make_contiguous().sort_unstable();
+
+			while log.front().is_some_and(|oldest| *oldest < cutoff) {
+				log.pop_front();
+			}
+
+			Ok(())
+		})
+	}
+
+	fn calls_since<'a>(
+		&'a self,
+		key: &'a QuotaKey,
+		since: OffsetDateTime,
+	) -> StoreFuture<'a, Vec<OffsetDateTime>> {
+		Box::pin(async move {
+			Ok(self
+				.calls
+				.lock()
+				.get(key)
+				.map(|log| log.iter().copied().filter(|at| *at >= since).collect())
+				.unwrap_or_default())
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	// self
+	use super::*;
+	use crate::auth::ScopeSet;
+
+	fn context(tenant: &str, at: OffsetDateTime) -> RateLimitContext {
+		RateLimitContext::new(
+			TenantId::new(tenant).expect("Tenant identifier should be valid."),
+			ProviderId::new("quota-provider").expect("Provider identifier should be valid."),
+			ScopeSet::new(["api"]).expect("Scope set should be valid."),
+			"client_credentials",
+		)
+		.with_observed_at(at)
+	}
+
+	#[tokio::test]
+	async fn quota_policy_defers_calls_beyond_each_budget() {
+		let provider =
+			ProviderId::new("quota-provider").expect("Provider identifier should be valid.");
+		let vip = TenantId::new("vip").expect("Tenant identifier should be valid.");
+		let policy = QuotaPolicy::new(Arc::new(MemoryQuotaStore::default()))
+			.with_provider_limit(provider.clone(), QuotaLimit::new(5, Duration::minutes(1)))
+			.with_tenant_limit(QuotaLimit::new(2, Duration::minutes(1)))
+			.with_tenant_override(vip.clone(), QuotaLimit::new(3, Duration::minutes(1)));
+		let start = OffsetDateTime::from_unix_timestamp(1_700_000_000)
+			.expect("Fixture timestamp should be valid.");
+
+		for offset in [0, 10] {
+			let decision = policy
+				.evaluate(&context("acme", start + Duration::seconds(offset)))
+				.await
+				.expect("Evaluation should succeed.");
+
+			assert_eq!(decision, RateLimitDecision::Allow);
+		}
+
+		let later = start + Duration::seconds(20);
+		let deferred =
+			policy.evaluate(&context("acme", later)).await.expect("Evaluation should succeed.");
+
+		assert!(matches!(
+			deferred,
+			RateLimitDecision::Delay(directive)
+				if directive.earliest_retry_at == start + Duration::minutes(1)
+					&& directive.recommended_backoff == Duration::seconds(40)
+		));
+
+		for _ in 0..3 {
+			let decision =
+				policy.evaluate(&context("vip", later)).await.expect("Evaluation should succeed.");
+
+			assert_eq!(decision, RateLimitDecision::Allow);
+		}
+
+		let budget =
+			policy.remaining(&provider, &vip, later).await.expect("Budget query should succeed.");
+
+		assert!(budget.is_exhausted());
+
+		// The provider-wide budget (5) is now spent even though `other` never called.
+		let other = TenantId::new("other").expect("Tenant identifier should be valid.");
+		let budget =
+			policy.remaining(&provider, &other, later).await.expect("Budget query should succeed.");
+
+		assert_eq!(budget.remaining, Some(0));
+		assert_eq!(
+			policy
+				.remaining(&provider, &other, start + Duration::seconds(61))
+				.await
+				.expect("Budget query should succeed.")
+				.remaining,
+			Some(1)
+		);
+	}
+}
@@ -1,21 +1,54 @@
 //! High-level flow orchestrators powered by the broker facade.
 
 pub mod auth_code_pkce;
+pub mod backchannel;
 pub mod common;
+pub mod health;
+pub mod jwt_bearer;
+pub mod policy;
 pub mod refresh;
+pub mod registry;
+pub mod step_up;
+pub mod validate;
 
 mod client_credentials;
+mod describe;
+mod import;
+#[cfg(feature = "loopback")] mod loopback;
+mod maintenance;
+mod on_behalf_of;
+mod overrides;
+mod revoke;
+#[cfg(feature = "ring")] mod sealed;
+mod warm;
 
 pub use auth_code_pkce::*;
+pub use backchannel::*;
 pub use common::*;
+pub use describe::RecordDescription;
+pub use health::*;
+pub use import::ImportedToken;
+pub use jwt_bearer::*;
+#[cfg(feature = "loopback")] pub use loopback::{LoopbackOptions, open_in_browser};
+pub use overrides::BrokerOverrides;
+pub use policy::*;
 pub use refresh::*;
+pub use registry::*;
+pub use revoke::TenantErasure;
+#[cfg(feature = "ring")] pub use sealed::SealedBroker;
+pub use step_up::*;
+pub use validate::*;
 
+// std
+use std::collections::BTreeSet;
 // self
 use crate::{
 	_prelude::*,
+	auth::{ProviderId, TokenRecord},
 	http::TokenHttpClient,
 	oauth::TransportErrorMapper,
-	provider::{ProviderDescriptor, ProviderStrategy},
+	obs::Diagnostics,
+	provider::{JwksCache, ProviderDescriptor, ProviderEnvironment, ProviderStrategy},
 	store::{BrokerStore, StoreKey},
 };
 #[cfg(feature = "reqwest")]
@@ -32,7 +65,6 @@ pub type ReqwestBroker = Broker<ReqwestHttpClient, ReqwestTransportErrorMapper>;
 /// (state + PKCE generation, code exchanges, refresh rotations, etc.). Client
 /// credentials are stored alongside the descriptor so client-auth methods can be
 /// applied consistently across token endpoints.
-#[derive(Clone)]
 pub struct Broker<C, M>
 where
 	C: ?Sized + TokenHttpClient,
@@ -52,15 +84,61 @@ where
 	pub client_id: String,
 	/// Optional client secret for confidential authentication methods.
 	pub client_secret: Option<String>,
+	/// Client identifiers whose previously minted tokens must no longer be served or refreshed.
+	pub decommissioned_clients: BTreeSet<String>,
+	/// How long revoked records are kept for audit before [`Broker::purge_revoked`] deletes
+	/// them (`None` keeps them indefinitely).
+	pub revoked_retention: Option<Duration>,
+	/// Longest a flow waits on another flow's singleflight guard for the same token (`None`
+	/// waits indefinitely).
+	pub singleflight_wait: Option<Duration>,
+	/// Maximum number of requests [`Broker::warm`] runs concurrently.
+	pub warm_concurrency: usize,
+	/// Most callers allowed to queue behind one in-flight singleflight guard before new callers
+	/// are shed with [`TransientError::Backlogged`](crate::error::TransientError::Backlogged)
+	/// (`None` queues without limit).
+	pub max_queued_callers: Option<usize>,
+	/// Optional policy that can disable flows globally or per tenant.
+	pub flow_policy: Option<Arc<dyn FlowPolicy>>,
+	/// Runtime switches for optional provider endpoints.
+	pub endpoint_toggles: Arc<EndpointToggles>,
+	/// Provider key sets used to verify signed token responses.
+	pub jwks_cache: Arc<JwksCache>,
 	/// Shared metrics recorder for refresh flow outcomes.
 	pub refresh_metrics: Arc<RefreshMetrics>,
-	flow_guards: Arc<Mutex<HashMap<StoreKey, Arc<AsyncMutex<()>>>>>,
+	/// Outcomes of token-endpoint calls, surfaced through [`Broker::health_report`].
+	pub provider_calls: Arc<ProviderCallTracker>,
+	/// Consecutive provider failures after which [`Broker::health_report`] reports the circuit
+	/// as open.
+	pub circuit_open_after: u32,
+	/// Minimum gap between two [`TokenRecord::last_served_at`] stamps (`None` disables serve
+	/// tracking).
+	pub served_tracking: Option<Duration>,
+	/// Signs authorize parameters into a request object (JAR) when set.
+	pub request_object: Option<RequestObjectConfig>,
+	/// How authorization sessions generate and validate `state`.
+	pub state_policy: Arc<StatePolicy>,
+	/// Set of exchanged session `state` values that refuses replayed callbacks.
+	pub replay_guard: Arc<dyn SessionReplayGuard>,
+	/// Per-call settings tuned through [`Broker::with_overrides`].
+	pub overrides: BrokerOverrides,
+	/// Channel for non-fatal configuration findings, surfaced through [`Broker::diagnose`].
+	pub diagnostics: Arc<Diagnostics>,
+	tasks: Arc<Mutex<Vec<TaskHeartbeat>>>,
+	providers: Arc<RwLock<HashMap<ProviderId, Arc<ProviderHandle>>>>,
+	flow_guards: Arc<FlowGuards>,
+	family_guards: Arc<FamilyGuards>,
+	rotation_journal: Arc<RotationJournal>,
+	backchannel_sessions: Arc<BackchannelSessions>,
 }
 impl<C, M> Broker<C, M>
 where
 	C: ?Sized + TokenHttpClient,
 	M: ?Sized + TransportErrorMapper<C::TransportError>,
 {
+	const DEFAULT_CIRCUIT_OPEN_AFTER: u32 = 5;
+	const DEFAULT_WARM_CONCURRENCY: usize = 4;
+
 	/// Creates a broker that reuses the caller-provided transport + mapper pair.
 	pub fn with_http_client(
 		store: Arc<dyn BrokerStore>,
@@ -78,17 +156,212 @@ where
 			strategy,
 			client_id: client_id.into(),
 			client_secret: None,
+			decommissioned_clients: BTreeSet::new(),
+			revoked_retention: None,
+			singleflight_wait: None,
+			max_queued_callers: None,
+			warm_concurrency: Self::DEFAULT_WARM_CONCURRENCY,
+			flow_policy: None,
+			endpoint_toggles: Default::default(),
+			jwks_cache: Default::default(),
 			flow_guards: Default::default(),
+			family_guards: Default::default(),
+			rotation_journal: Default::default(),
+			backchannel_sessions: Default::default(),
 			refresh_metrics: Default::default(),
+			provider_calls: Default::default(),
+			circuit_open_after: Self::DEFAULT_CIRCUIT_OPEN_AFTER,
+			served_tracking: None,
+			request_object: None,
+			state_policy: Default::default(),
+			replay_guard: Arc::new(MemoryReplayGuard::default()),
+			overrides: BrokerOverrides::default(),
+			diagnostics: Default::default(),
+			tasks: Default::default(),
+			providers: Default::default(),
 		}
 	}
 
 	/// Sets or replaces the client secret used for confidential client auth modes.
+	///
+	/// [`Broker::check_client_auth`] reports a secret that the descriptor's client authentication
+	/// would ignore.
 	pub fn with_client_secret(mut self, secret: impl Into<String>) -> Self {
 		self.client_secret = Some(secret.into());
 
 		self
 	}
+
+	/// Reports non-fatal configuration findings to `diagnostics` instead of a private channel,
+	/// so several brokers can share one set of sinks.
+	pub fn with_diagnostics(mut self, diagnostics: Arc<Diagnostics>) -> Self {
+		self.diagnostics = diagnostics;
+
+		self
+	}
+
+	/// Rejects cached tokens minted under `client_id`.
+	///
+	/// Cached-token flows re-mint instead of serving such records, and refresh flows revoke them
+	/// and fail with [`Error::InvalidGrant`].
+	pub fn with_decommissioned_client(mut self, client_id: impl Into<String>) -> Self {
+		self.decommissioned_clients.insert(client_id.into());
+
+		self
+	}
+
+	/// Keeps revoked records for `retention` before [`Broker::purge_revoked`] deletes them.
+	pub fn with_revoked_retention(mut self, retention: Duration) -> Self {
+		self.revoked_retention = Some(retention);
+
+		self
+	}
+
+	/// Bounds how long a flow waits on a peer's in-flight request for the same token.
+	///
+	/// Once `max_wait` passes, the flow fails with [`Error::SingleflightTimeout`], or returns the
+	/// still-valid cached record when the request opted in via
+	/// [`CachedTokenRequest::allow_stale_on_timeout`].
+	pub fn with_singleflight_wait(mut self, max_wait: Duration) -> Self {
+		self.singleflight_wait = Some(max_wait);
+
+		self
+	}
+
+	/// Sheds callers with [`TransientError::Backlogged`](crate::error::TransientError::Backlogged)
+	/// once `limit` others are already queued behind the same in-flight token request.
+	///
+	/// The error's [`Error::retry_hint`] tells shed callers when to come back, so a thundering
+	/// herd backs off instead of piling onto the guard.
+	pub fn with_max_queued_callers(mut self, limit: usize) -> Self {
+		self.max_queued_callers = Some(limit);
+
+		self
+	}
+
+	/// Returns how many callers are queued behind the in-flight request for `key`.
+	pub fn queued_callers(&self, key: &TokenKey) -> usize {
+		let key = key.store_key();
+
+		self.flow_guards.for_key(key).lock().get(key).map_or(0, |slot| slot.queued())
+	}
+
+	/// Returns the number of queued callers for every store key that currently has any.
+	pub fn singleflight_backlog(&self) -> HashMap<StoreKey, usize> {
+		self.flow_guards
+			.iter()
+			.flat_map(|shard| {
+				shard
+					.lock()
+					.iter()
+					.map(|(key, slot)| (key.clone(), slot.queued()))
+					.filter(|(_, queued)| *queued > 0)
+					.collect::<Vec<_>>()
+			})
+			.collect()
+	}
+
+	/// Caps how many requests [`Broker::warm`] runs at once (defaults to 4; `0` is treated as 1).
+	pub fn with_warm_concurrency(mut self, limit: usize) -> Self {
+		self.warm_concurrency = limit.max(1);
+
+		self
+	}
+
+	/// Stamps [`TokenRecord::last_served_at`] when a flow serves a cached record.
+	///
+	/// Each stamp is a store write, so a record is stamped at most once per `resolution`.
+	/// Stamping is best effort: a record that changed concurrently is served without one.
+	pub fn with_served_tracking(mut self, resolution: Duration) -> Self {
+		self.served_tracking = Some(resolution);
+
+		self
+	}
+
+	/// Points the broker at the descriptor's `environment` endpoints (e.g. provider sandboxes in
+	/// staging).
+	///
+	/// Providers added through [`Broker::register_provider`] follow the same environment when a
+	/// call is routed to them. Fails with
+	/// [`ConfigError::UnsupportedEnvironment`](crate::error::ConfigError::UnsupportedEnvironment)
+	/// when the descriptor does not declare that environment.
+	pub fn with_environment(mut self, environment: ProviderEnvironment) -> Result<Self> {
+		self.descriptor = self.descriptor.for_environment(environment)?;
+
+		Ok(self)
+	}
+
+	/// Replaces the `state` generation and validation policy for new authorization sessions.
+	pub fn with_state_policy(mut self, policy: StatePolicy) -> Self {
+		self.state_policy = Arc::new(policy);
+
+		self
+	}
+
+	/// Records exchanged session `state` values in `guard` instead of this process's memory.
+	///
+	/// Replicas sharing one guard refuse a callback replayed against any of them.
+	pub fn with_replay_guard(mut self, guard: Arc<dyn SessionReplayGuard>) -> Self {
+		self.replay_guard = guard;
+
+		self
+	}
+
+	/// Sends authorize parameters as a signed request object (JAR, RFC 9101).
+	pub fn with_request_object(mut self, config: RequestObjectConfig) -> Self {
+		self.request_object = Some(config);
+
+		self
+	}
+
+	/// Runs the dependency health checks readiness endpoints gate on.
+	///
+	/// Currently this is the store's [`BrokerStore::health_check`]; the first failure is
+	/// returned.
+	pub async fn health(&self) -> Result<()> {
+		<dyn BrokerStore>::health_check(self.store.as_ref()).await.map_err(Error::from)
+	}
+
+	/// Deletes records whose revocation is older than the configured retention window.
+	///
+	/// Returns the number of purged records, or `0` when no retention window is configured.
+	pub async fn purge_revoked(&self) -> Result<usize> {
+		let Some(retention) = self.revoked_retention else {
+			return Ok(0);
+		};
+		let cutoff = OffsetDateTime::now_utc() - retention;
+
+		<dyn BrokerStore>::purge_revoked(self.store.as_ref(), cutoff).await.map_err(Error::from)
+	}
+
+	/// Commits staged writes left behind by failed persists, returning how many were committed.
+	///
+	/// A staged record is discarded instead when the store already holds a record for the same
+	/// key issued at or after it, so reconciliation never rolls a newer token back.
+	pub async fn reconcile_pending_writes(&self) -> Result<usize> {
+		let store = self.store.as_ref();
+		let mut committed = 0;
+
+		for prepared in <dyn BrokerStore>::pending_writes(store).await? {
+			let staged = &prepared.record;
+			let current = <dyn BrokerStore>::fetch(store, &staged.family, &staged.scope).await?;
+
+			if current.is_some_and(|current| current.issued_at >= staged.issued_at) {
+				<dyn BrokerStore>::rollback(store, &prepared).await?;
+			} else {
+				<dyn BrokerStore>::commit(store, &prepared).await?;
+
+				committed += 1;
+			}
+		}
+
+		Ok(committed)
+	}
+
+	/// Returns `true` when `record` was minted under a decommissioned client.
+	pub fn is_decommissioned(&self, record: &TokenRecord) -> bool {
+		record.client_id.as_ref().is_some_and(|id| self.decommissioned_clients.contains(id))
+	}
 }
 #[cfg(feature = "reqwest")]
 impl Broker<ReqwestHttpClient, ReqwestTransportErrorMapper> {
@@ -114,6 +387,47 @@ impl Broker<ReqwestHttpClient, ReqwestTransportErrorMapper> {
 		)
 	}
 }
+// Manual impl: every field is shared or cloneable, so `C` and `M` need not be `Clone`.
+impl<C, M> Clone for Broker<C, M>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	fn clone(&self) -> Self {
+		Self {
+			http_client: self.http_client.clone(),
+			transport_mapper: self.transport_mapper.clone(),
+			store: self.store.clone(),
+			descriptor: self.descriptor.clone(),
+			strategy: self.strategy.clone(),
+			client_id: self.client_id.clone(),
+			client_secret: self.client_secret.clone(),
+			decommissioned_clients: self.decommissioned_clients.clone(),
+			revoked_retention: self.revoked_retention,
+			singleflight_wait: self.singleflight_wait,
+			max_queued_callers: self.max_queued_callers,
+			warm_concurrency: self.warm_concurrency,
+			flow_policy: self.flow_policy.clone(),
+			endpoint_toggles: self.endpoint_toggles.clone(),
+			jwks_cache: self.jwks_cache.clone(),
+			refresh_metrics: self.refresh_metrics.clone(),
+			provider_calls: self.provider_calls.clone(),
+			circuit_open_after: self.circuit_open_after,
+			served_tracking: self.served_tracking,
+			request_object: self.request_object.clone(),
+			state_policy: self.state_policy.clone(),
+			replay_guard: self.replay_guard.clone(),
+			overrides: self.overrides.clone(),
+			diagnostics: self.diagnostics.clone(),
+			tasks: self.tasks.clone(),
+			providers: self.providers.clone(),
+			flow_guards: self.flow_guards.clone(),
+			family_guards: self.family_guards.clone(),
+			rotation_journal: self.rotation_journal.clone(),
+			backchannel_sessions: self.backchannel_sessions.clone(),
+		}
+	}
+}
 impl<C, M> Debug for Broker<C, M>
 where
 	C: ?Sized + TokenHttpClient,
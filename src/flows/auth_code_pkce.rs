@@ -7,24 +7,45 @@
 //! The `AuthorizationSession` returned by [`Broker::start_authorization`] is designed to
 //! be serialized and stored by callers between the authorize redirect and the callback
 //! handler so replayed states or swapped principals can be rejected immediately.
-
+//! With a [`RequestObjectConfig`] installed, the authorize parameters travel as a signed request
+//! object (JAR, RFC 9101) instead of plain query parameters.
+//!
+//! Descriptors with
+//! [`ProviderQuirks::hybrid_id_token`](crate::provider::ProviderQuirks::hybrid_id_token)
+//! request `code id_token` responses; the ID token captured with
+//! [`AuthorizationSession::with_id_token`] must then be signed by a key from the descriptor's JWKS
+//! endpoint and carry the session's `nonce` and the code's `c_hash` before
+//! [`Broker::exchange_code`] contacts the token endpoint.
+
+mod hybrid;
+mod replay;
+mod request_object;
 mod session;
+mod state;
+#[cfg(feature = "ring")] mod vault;
 
+pub use replay::*;
+pub use request_object::*;
 pub use session::*;
+pub use state::*;
+#[cfg(feature = "ring")] pub use vault::SessionVault;
 
 // self
 use crate::{
 	_prelude::*,
 	auth::{PrincipalId, ScopeSet, TenantId, TokenFamily, TokenRecord},
 	error::ConfigError,
-	flows::Broker,
+	flows::{Broker, CancellationToken, common},
 	http::TokenHttpClient,
 	oauth::{BasicFacade, OAuth2Facade, TransportErrorMapper},
 	obs::{self, FlowKind, FlowOutcome, FlowSpan},
 	provider::GrantType,
-	store::BrokerStore,
 };
 
+/// How long exchanged `state` values are remembered, well past the lifetime providers grant
+/// authorization codes.
+const CONSUMED_STATE_RETENTION: Duration = Duration::minutes(10);
+
 impl<C, M> Broker<C, M>
 where
 	C: ?Sized + TokenHttpClient,
@@ -57,14 +78,20 @@ where
 
 		let result = (|| -> Result<AuthorizationSession> {
 			self.ensure_authorization_code_supported()?;
-			Ok(build_session(
-				&self.descriptor,
-				self.client_id.as_str(),
+			self.ensure_flow_enabled(KIND, &tenant)?;
+			build_session(
+				SessionSettings {
+					descriptor: &self.descriptor,
+					client_id: &self.client_id,
+					request_object: self.request_object.as_ref(),
+					state_policy: &self.state_policy,
+				},
 				tenant,
 				principal,
 				scope,
 				redirect_uri,
-			))
+			)
+			.map_err(Error::from)
 		})();
 
 		match &result {
@@ -83,10 +110,36 @@ where
 	/// emit a [`TokenRecord`] that has already been written to the configured
 	/// [`BrokerStore`](crate::store::BrokerStore) so subsequent fetches observe the
 	/// latest secrets.
+	///
+	/// Hybrid sessions (see [`AuthorizationSession::nonce`]) must carry the front-channel ID
+	/// token via [`AuthorizationSession::with_id_token`]; a missing token, or one whose `nonce`,
+	/// `aud`, `iss`, `exp`, or `c_hash` does not match, fails with [`Error::InvalidGrant`].
+	///
+	/// The callback's RFC 9207 `iss` parameter, captured with
+	/// [`AuthorizationSession::with_issuer`], must name the descriptor's issuer; a missing one is
+	/// rejected when
+	/// [`ProviderQuirks::require_authorization_response_iss`](crate::provider::ProviderQuirks::require_authorization_response_iss)
+	/// is set.
+	///
+	/// Each session is exchanged at most once: its state is marked consumed right before the
+	/// provider call, and replaying it (with any code) fails with [`Error::InvalidGrant`]. The
+	/// mark is kept by the broker and its clones, not across processes.
 	pub async fn exchange_code(
 		&self,
 		session: AuthorizationSession,
 		authorization_code: impl AsRef<str>,
+	) -> Result<TokenRecord> {
+		self.exchange_code_with_cancellation(session, authorization_code, &CancellationToken::new())
+			.await
+	}
+
+	/// Same as [`exchange_code`](Self::exchange_code), but stops before contacting the provider
+	/// once `cancellation` has been triggered.
+	pub async fn exchange_code_with_cancellation(
+		&self,
+		session: AuthorizationSession,
+		authorization_code: impl AsRef<str>,
+		cancellation: &CancellationToken,
 	) -> Result<TokenRecord> {
 		const KIND: FlowKind = FlowKind::AuthorizationCode;
 
@@ -97,9 +150,14 @@ where
 		let result = span
 			.instrument(async move {
 				self.ensure_authorization_code_supported()?;
+				self.ensure_flow_enabled(KIND, &session.tenant)?;
+				self.validate_hybrid_response(&session, authorization_code.as_ref()).await?;
+				session.validate_issuer(&self.descriptor)?;
+
+				let state = session.state.clone();
 				let (tenant, principal, requested_scope, redirect_uri, pkce) =
 					session.into_exchange_parts();
-				let mut family = TokenFamily::new(tenant, principal);
+				let mut family = TokenFamily::new(tenant.clone(), principal);
 
 				family.provider = Some(self.descriptor.id.clone());
 
@@ -110,21 +168,40 @@ where
 					Some(&redirect_uri),
 					self.http_client.clone(),
 					self.transport_mapper.clone(),
-				)?;
-				let record = facade
-					.exchange_authorization_code(
-						self.strategy.as_ref(),
-						family,
-						authorization_code.as_ref(),
-						&pkce.verifier,
+				)?
+				.with_jwks_cache(self.jwks_cache.clone());
+
+				cancellation.check("contacting the provider")?;
+
+				// Mark the session before the provider sees the code so a replayed callback never
+				// triggers a second exchange, even while the first is still in flight.
+				if !self
+					.replay_guard
+					.consume(&state, OffsetDateTime::now_utc(), CONSUMED_STATE_RETENTION)
+					.await?
+				{
+					return Err(Error::InvalidGrant {
+						reason: "Authorization session was already exchanged.".into(),
+					});
+				}
+
+				let record = self
+					.call_provider(
+						&tenant,
 						&requested_scope,
-						&redirect_uri,
+						"authorization_code",
+						facade.exchange_authorization_code(
+							self.strategy.as_ref(),
+							family,
+							authorization_code.as_ref(),
+							&pkce.verifier,
+							&requested_scope,
+							&redirect_uri,
+						),
 					)
 					.await?;
 
-				<dyn BrokerStore>::save(self.store.as_ref(), record.clone())
-					.await
-					.map_err(Error::from)?;
+				common::persist_record(self, &record).await?;
 
 				Ok(record)
 			})
@@ -0,0 +1,223 @@
+//! Front-channel ID token checks for OpenID Connect hybrid (`code id_token`) responses.
+//!
+//! The ID token signature is verified against the descriptor's JWKS endpoint through the
+//! broker's [`JwksCache`](crate::provider::JwksCache) before any claim is trusted; unsigned
+//! (`alg: none`) tokens and unsupported algorithms are refused. The claims then bind the token to
+//! this session (`nonce`), this client (`aud`), the provider (`iss`), and the returned code
+//! (`c_hash`) so a code injected from another authorization response is refused before it
+//! reaches the token endpoint. Signature checks need the `ring` feature.
+
+// crates.io
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use serde_json::Value;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+// self
+use crate::{
+	_prelude::*,
+	flows::{Broker, auth_code_pkce::AuthorizationSession},
+	http::TokenHttpClient,
+	oauth::TransportErrorMapper,
+	provider::{GrantType, ProviderDescriptor, jwks},
+};
+
+/// Algorithms a hybrid ID token may be signed with.
+const SIGNING_ALGORITHMS: [&str; 9] =
+	["RS256", "RS384", "RS512", "PS256", "PS384", "PS512", "ES256", "ES384", "EdDSA"];
+
+/// Inputs of [`validate_id_token`] that come from the broker rather than the callback.
+pub(super) struct HybridExpectations<'a> {
+	pub(super) nonce: &'a str,
+	pub(super) client_id: &'a str,
+	pub(super) descriptor: &'a ProviderDescriptor,
+	pub(super) now: OffsetDateTime,
+}
+
+impl<C, M> Broker<C, M>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	/// Verifies the ID token captured with a hybrid session and binds it to `code`.
+	pub(super) async fn validate_hybrid_response(
+		&self,
+		session: &AuthorizationSession,
+		code: &str,
+	) -> Result<()> {
+		let Some((nonce, id_token)) = session.hybrid_id_token()? else {
+			return Ok(());
+		};
+		let (alg, claims) = self.verify_id_token(id_token).await?;
+
+		validate_id_token(
+			&alg,
+			&claims,
+			code,
+			HybridExpectations {
+				nonce,
+				client_id: &self.client_id,
+				descriptor: &self.descriptor,
+				now: OffsetDateTime::now_utc(),
+			},
+		)
+	}
+
+	/// Checks the ID token signature with the provider's JWKS, returning its `alg` and claims.
+	async fn verify_id_token(&self, id_token: &str) -> Result<(String, Value)> {
+		let header =
+			jwks::jws_header(id_token).ok_or_else(|| refuse("ID token header is malformed."))?;
+
+		if !SIGNING_ALGORITHMS.contains(&header.alg.as_str()) {
+			return Err(refuse("ID token is unsigned or uses an unsupported algorithm."));
+		}
+
+		let jwks_uri = self
+			.descriptor
+			.endpoints
+			.jwks
+			.as_ref()
+			.ok_or_else(|| refuse("Descriptor has no JWKS endpoint to verify the ID token."))?;
+		let key = self
+			.jwks_cache
+			.key(self.http_client.as_ref(), jwks_uri, header.kid.as_deref(), &header.alg)
+			.await
+			.map_err(|e| {
+				self.transport_mapper.map_transport_error(
+					self.strategy.as_ref(),
+					GrantType::AuthorizationCode,
+					None,
+					e,
+				)
+			})?
+			.ok_or_else(|| refuse("No JWKS key matches the ID token."))?;
+		let payload = jwks::verify_jws(id_token, &header.alg, &key)
+			.map_err(|_| refuse("ID token signature is invalid."))?;
+		let claims = serde_json::from_slice(&payload)
+			.map_err(|_| refuse("ID token claims are malformed."))?;
+
+		Ok((header.alg, claims))
+	}
+}
+
+/// Checks the claims of a signature-verified ID token signed with `alg`.
+pub(super) fn validate_id_token(
+	alg: &str,
+	claims: &Value,
+	code: &str,
+	expected: HybridExpectations,
+) -> Result<()> {
+	if claims.get("nonce").and_then(Value::as_str) != Some(expected.nonce) {
+		return Err(refuse("ID token nonce does not match the authorization session."));
+	}
+
+	let audience_matches = match claims.get("aud") {
+		Some(Value::String(aud)) => aud == expected.client_id,
+		Some(Value::Array(auds)) => auds.iter().any(|aud| aud.as_str() == Some(expected.client_id)),
+		_ => false,
+	};
+
+	if !audience_matches {
+		return Err(refuse("ID token audience does not include this client."));
+	}
+
+	let descriptor = expected.descriptor;
+
+	if descriptor.issuer.is_some() || !descriptor.accepted_issuers.is_empty() {
+		let issuer = claims.get("iss").and_then(Value::as_str).unwrap_or_default();
+
+		if !descriptor.accepts_issuer(issuer) {
+			return Err(refuse("ID token issuer is not accepted."));
+		}
+	}
+
+	match claims.get("exp").and_then(Value::as_i64) {
+		Some(exp) if exp > expected.now.unix_timestamp() => {},
+		_ => return Err(refuse("ID token has expired.")),
+	}
+
+	if claims.get("c_hash").and_then(Value::as_str) != Some(code_hash(alg, code).as_str()) {
+		return Err(refuse("ID token c_hash does not match the authorization code."));
+	}
+
+	Ok(())
+}
+
+fn refuse(reason: &str) -> Error {
+	Error::InvalidGrant { reason: reason.into() }
+}
+
+/// Left half of the code digest, using the hash paired with the token's `alg` (OIDC §3.3.2.11).
+fn code_hash(alg: &str, code: &str) -> String {
+	let digest = if alg.ends_with("384") {
+		Sha384::digest(code).to_vec()
+	} else if alg.ends_with("512") || alg == "EdDSA" {
+		Sha512::digest(code).to_vec()
+	} else {
+		Sha256::digest(code).to_vec()
+	};
+
+	URL_SAFE_NO_PAD.encode(&digest[..digest.len() / 2])
+}
+
+#[cfg(test)]
+mod tests {
+	// self
+	use super::*;
+	use crate::auth::ProviderId;
+
+	#[test]
+	fn id_token_must_bind_nonce_audience_issuer_expiry_and_code() {
+		let descriptor = ProviderDescriptor::builder(
+			ProviderId::new("hybrid").expect("Provider fixture should be valid."),
+		)
+		.issuer(Url::parse("https://issuer.example.com").expect("Issuer fixture should parse."))
+		.authorization_endpoint(
+			Url::parse("https://issuer.example.com/authorize")
+				.expect("Authorization endpoint fixture should parse."),
+		)
+		.token_endpoint(
+			Url::parse("https://issuer.example.com/token")
+				.expect("Token endpoint fixture should parse."),
+		)
+		.support_grants([GrantType::AuthorizationCode])
+		.build()
+		.expect("Descriptor fixture should build.");
+		let now = OffsetDateTime::now_utc();
+		let valid = serde_json::json!({
+			"iss": "https://issuer.example.com",
+			"aud": ["web-client", "other"],
+			"nonce": "n-0S6",
+			"exp": now.unix_timestamp() + 300,
+			"c_hash": code_hash("RS256", "code-123"),
+		});
+		let check = |claims: &Value, code: &str| {
+			validate_id_token(
+				"RS256",
+				claims,
+				code,
+				HybridExpectations {
+					nonce: "n-0S6",
+					client_id: "web-client",
+					descriptor: &descriptor,
+					now,
+				},
+			)
+		};
+
+		assert!(check(&valid, "code-123").is_ok());
+		assert!(check(&valid, "injected-code").is_err());
+		assert!(check(&Value::Null, "code-123").is_err());
+
+		for (claim, value) in [
+			("nonce", Value::from("replayed")),
+			("aud", Value::from("someone-else")),
+			("iss", Value::from("https://evil.example.com")),
+			("exp", Value::from(now.unix_timestamp() - 1)),
+		] {
+			let mut claims = valid.clone();
+
+			claims[claim] = value;
+
+			assert!(check(&claims, "code-123").is_err(), "A bad {claim} should be refused.");
+		}
+	}
+}
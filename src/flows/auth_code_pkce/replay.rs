@@ -0,0 +1,121 @@
+//! Replay protection for authorization callbacks.
+//!
+//! [`Broker::exchange_code`](crate::flows::Broker::exchange_code) records each session's `state`
+//! in a [`SessionReplayGuard`] right before the provider sees the code. The default
+//! [`MemoryReplayGuard`] only covers one process; replicas behind a load balancer install a shared
+//! guard with [`Broker::with_replay_guard`](crate::flows::Broker::with_replay_guard) so a callback
+//! replayed against another replica is refused too.
+
+// std
+use std::{cmp::Reverse, collections::BinaryHeap};
+// self
+use crate::{_prelude::*, store::StoreFuture};
+
+/// Atomic set-if-absent over the `state` values of exchanged authorization sessions.
+pub trait SessionReplayGuard
+where
+	Self: Send + Sync,
+{
+	/// Records `state` as consumed at `now` unless it already is, returning `false` for a replay.
+	///
+	/// The check and the write must be atomic across every broker sharing the guard. The entry
+	/// has to be kept for at least `ttl`; guards may forget it afterwards.
+	fn consume<'a>(
+		&'a self,
+		state: &'a str,
+		now: OffsetDateTime,
+		ttl: Duration,
+	) -> StoreFuture<'a, bool>;
+}
+
+/// In-process [`SessionReplayGuard`] used by default.
+///
+/// Expired entries are popped from a deadline-ordered queue, so each call only touches the
+/// entries that actually expired instead of scanning the whole set.
+#[derive(Debug, Default)]
+pub struct MemoryReplayGuard(Mutex<ReplayEntries>);
+impl MemoryReplayGuard {
+	/// Number of `state` values currently remembered.
+	pub fn len(&self) -> usize {
+		self.0.lock().deadlines.len()
+	}
+
+	/// Returns `true` when no `state` value is remembered.
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+}
+impl SessionReplayGuard for MemoryReplayGuard {
+	fn consume<'a>(
+		&'a self,
+		state: &'a str,
+		now: OffsetDateTime,
+		ttl: Duration,
+	) -> StoreFuture<'a, bool> {
+		let mut entries = self.0.lock();
+
+		entries.prune(now);
+
+		let fresh = !entries.deadlines.contains_key(state);
+
+		if fresh {
+			let deadline = now + ttl;
+
+			entries.deadlines.insert(state.to_owned(), deadline);
+			entries.expiries.push(Reverse((deadline, state.to_owned())));
+		}
+
+		Box::pin(async move { Ok(fresh) })
+	}
+}
+
+#[derive(Debug, Default)]
+struct ReplayEntries {
+	deadlines: HashMap<String, OffsetDateTime>,
+	expiries: BinaryHeap<Reverse<(OffsetDateTime, String)>>,
+}
+impl ReplayEntries {
+	fn prune(&mut self, now: OffsetDateTime) {
+		while let Some(Reverse((deadline, _))) = self.expiries.peek()
+			&& *deadline <= now
+		{
+			if let Some(Reverse((deadline, state))) = self.expiries.pop()
+				&& self.deadlines.get(&state) == Some(&deadline)
+			{
+				self.deadlines.remove(&state);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	// crates.io
+	use tokio::runtime::Runtime;
+	// self
+	use super::*;
+
+	#[test]
+	fn memory_guard_refuses_replays_until_the_entry_expires() {
+		let runtime = Runtime::new().expect("Tokio runtime should start.");
+		let guard = MemoryReplayGuard::default();
+		let ttl = Duration::minutes(10);
+		let start = OffsetDateTime::now_utc();
+		let consume = |state: &str, now: OffsetDateTime| {
+			runtime.block_on(guard.consume(state, now, ttl)).expect("Memory guard should not fail.")
+		};
+
+		assert!(consume("state-a", start));
+		assert!(!consume("state-a", start + Duration::minutes(9)));
+		assert!(consume("state-b", start + Duration::minutes(5)));
+		assert_eq!(guard.len(), 2);
+
+		// Only `state-a` has expired; `state-b` is still refused.
+		assert!(consume("state-c", start + Duration::minutes(11)));
+		assert_eq!(guard.len(), 2);
+		assert!(!consume("state-b", start + Duration::minutes(11)));
+		assert!(consume("state-a", start + Duration::minutes(11)));
+		assert!(consume("state-d", start + Duration::minutes(30)));
+		assert_eq!(guard.len(), 1);
+	}
+}
@@ -0,0 +1,160 @@
+// crates.io
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use rand::{Rng, distr::Alphanumeric};
+use serde_json::{Map, Value};
+// self
+use crate::{_prelude::*, error::ConfigError, flows::JwtSigner, provider::ProviderDescriptor};
+
+/// Publishes request objects so the authorization server can fetch them by reference.
+pub trait RequestUriPublisher: Send + Sync {
+	/// Stores `request_object` where the authorization server can fetch it and returns its URI.
+	fn publish(&self, request_object: &str) -> Result<Url, ConfigError>;
+}
+
+/// How a signed request object reaches the authorization server.
+#[derive(Clone, Default)]
+pub enum RequestObjectDelivery {
+	/// Inline in the `request` query parameter.
+	#[default]
+	Value,
+	/// Hosted by the publisher and referenced through the `request_uri` query parameter.
+	Reference(Arc<dyn RequestUriPublisher>),
+}
+impl Debug for RequestObjectDelivery {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		match self {
+			Self::Value => f.write_str("Value"),
+			Self::Reference(_) => f.write_str("Reference"),
+		}
+	}
+}
+
+/// Signing parameters for authorization request objects (JAR, RFC 9101).
+///
+/// When installed via [`Broker::with_request_object`](crate::flows::Broker::with_request_object),
+/// [`Broker::start_authorization`](crate::flows::Broker::start_authorization) moves every
+/// authorize parameter into a JWT signed by [`signer`](Self::signer) and leaves only `client_id`
+/// plus `request` (or `request_uri`) in the authorize URL, as FAPI-profile providers require.
+#[derive(Clone)]
+pub struct RequestObjectConfig {
+	/// Signer producing the request object signature.
+	pub signer: Arc<dyn JwtSigner>,
+	/// `aud` claim; defaults to the descriptor's issuer, then its authorization endpoint.
+	pub audience: Option<String>,
+	/// Validity window between `nbf` and `exp`.
+	pub lifetime: Duration,
+	/// Whether the object travels by value or by reference.
+	pub delivery: RequestObjectDelivery,
+	/// Additional claims merged into the request object (e.g. `acr_values`, `claims`).
+	pub extra_claims: BTreeMap<String, Value>,
+}
+impl RequestObjectConfig {
+	/// Default request object lifetime; FAPI caps `exp - nbf` at one hour.
+	pub const DEFAULT_LIFETIME: Duration = Duration::minutes(5);
+	/// JWS `typ` header registered for request objects.
+	pub const MEDIA_TYPE: &str = "oauth-authz-req+jwt";
+
+	/// Creates a by-value config for the provided signer.
+	pub fn new(signer: Arc<dyn JwtSigner>) -> Self {
+		Self {
+			signer,
+			audience: None,
+			lifetime: Self::DEFAULT_LIFETIME,
+			delivery: RequestObjectDelivery::Value,
+			extra_claims: BTreeMap::new(),
+		}
+	}
+
+	/// Overrides the `aud` claim.
+	pub fn with_audience(mut self, audience: impl Into<String>) -> Self {
+		self.audience = Some(audience.into());
+
+		self
+	}
+
+	/// Overrides the request object lifetime.
+	pub fn with_lifetime(mut self, lifetime: Duration) -> Self {
+		self.lifetime = lifetime;
+
+		self
+	}
+
+	/// Sends request objects by reference through `publisher`.
+	pub fn with_request_uri(mut self, publisher: Arc<dyn RequestUriPublisher>) -> Self {
+		self.delivery = RequestObjectDelivery::Reference(publisher);
+
+		self
+	}
+
+	/// Adds a custom claim to every request object.
+	pub fn with_extra_claim(mut self, name: impl Into<String>, value: impl Into<Value>) -> Self {
+		self.extra_claims.insert(name.into(), value.into());
+
+		self
+	}
+
+	/// Builds and signs a request object carrying the authorize `parameters` issued at `now`.
+	pub fn request_object(
+		&self,
+		descriptor: &ProviderDescriptor,
+		client_id: &str,
+		parameters: &[(&str, &str)],
+		now: OffsetDateTime,
+	) -> Result<String, ConfigError> {
+		let audience = match (&self.audience, &descriptor.issuer) {
+			(Some(audience), _) => audience.clone(),
+			(None, Some(issuer)) => issuer.to_string(),
+			(None, None) => descriptor.endpoints.authorization.to_string(),
+		};
+		let issued_at = now.unix_timestamp();
+		let mut header = Map::new();
+
+		header.insert("alg".into(), self.signer.algorithm().into());
+		header.insert("typ".into(), Self::MEDIA_TYPE.into());
+
+		if let Some(kid) = self.signer.key_id() {
+			header.insert("kid".into(), kid.into());
+		}
+
+		let mut claims =
+			self.extra_claims.iter().map(|(k, v)| (k.clone(), v.clone())).collect::<Map<_, _>>();
+
+		for (name, value) in parameters {
+			claims.insert((*name).into(), (*value).into());
+		}
+
+		claims.insert("iss".into(), client_id.into());
+		claims.insert("client_id".into(), client_id.into());
+		claims.insert("aud".into(), audience.into());
+		claims.insert("iat".into(), issued_at.into());
+		claims.insert("nbf".into(), issued_at.into());
+		claims.insert("exp".into(), (issued_at + self.lifetime.whole_seconds()).into());
+		claims.insert("jti".into(), jti().into());
+
+		let signing_input = format!("{}.{}", encode_segment(&header)?, encode_segment(&claims)?);
+		let signature = self.signer.sign(signing_input.as_bytes())?;
+
+		Ok(format!("{signing_input}.{}", URL_SAFE_NO_PAD.encode(signature)))
+	}
+}
+impl Debug for RequestObjectConfig {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		f.debug_struct("RequestObjectConfig")
+			.field("algorithm", &self.signer.algorithm())
+			.field("audience", &self.audience)
+			.field("lifetime", &self.lifetime)
+			.field("delivery", &self.delivery)
+			.finish()
+	}
+}
+
+fn encode_segment(value: &Map<String, Value>) -> Result<String, ConfigError> {
+	let bytes = serde_json::to_vec(value)
+		.map_err(|e| ConfigError::AssertionSigning { message: e.to_string() })?;
+
+	Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+fn jti() -> String {
+	rand::rng().sample_iter(Alphanumeric).take(32).map(char::from).collect()
+}
@@ -6,11 +6,12 @@ use sha2::{Digest, Sha256};
 use crate::{
 	_prelude::*,
 	auth::{PrincipalId, ScopeSet, TenantId},
-	flows::common,
+	error::ConfigError,
+	flows::{ClientBinding, RequestObjectConfig, RequestObjectDelivery, StatePolicy, common},
 	provider::ProviderDescriptor,
 };
 
-const STATE_LEN: usize = 32;
+const NONCE_LEN: usize = 32;
 const PKCE_VERIFIER_LEN: usize = 64;
 
 /// Supported PKCE challenge methods surfaced via [`AuthorizationSession`].
@@ -43,7 +44,18 @@ pub struct AuthorizationSession {
 	pub redirect_uri: Url,
 	/// Fully-formed HTTPS authorize URL that callers should send end-users to.
 	pub authorize_url: Url,
+	/// Signed request object (JAR) embedded in or referenced by the authorize URL, if any.
+	pub request_object: Option<String>,
+	/// When the session was started.
+	pub issued_at: OffsetDateTime,
+	/// [`ClientBinding::fingerprint`] of the client the session is bound to, if any.
+	pub client_binding: Option<String>,
+	/// `nonce` sent with hybrid (`code id_token`) requests; `None` for plain code sessions.
+	pub nonce: Option<String>,
+	id_token: Option<String>,
+	issuer: Option<String>,
 	pkce: PkcePair,
+	policy: Arc<StatePolicy>,
 }
 impl AuthorizationSession {
 	pub(super) fn new(
@@ -55,7 +67,48 @@ impl AuthorizationSession {
 		state: String,
 		pkce: PkcePair,
 	) -> Self {
-		Self { tenant, principal, scope, state, redirect_uri, authorize_url, pkce }
+		Self {
+			tenant,
+			principal,
+			scope,
+			state,
+			redirect_uri,
+			authorize_url,
+			request_object: None,
+			issued_at: OffsetDateTime::now_utc(),
+			client_binding: None,
+			nonce: None,
+			id_token: None,
+			issuer: None,
+			pkce,
+			policy: Default::default(),
+		}
+	}
+
+	/// Binds the session to the client that started it; callbacks must then be validated with
+	/// [`AuthorizationSession::validate_state_for`] and the same client attributes.
+	pub fn with_client_binding(mut self, client: &ClientBinding) -> Self {
+		self.client_binding = Some(client.fingerprint());
+
+		self
+	}
+
+	/// Captures the ID token a hybrid authorization response returned next to the code.
+	pub fn with_id_token(mut self, id_token: impl Into<String>) -> Self {
+		self.id_token = Some(id_token.into());
+
+		self
+	}
+
+	/// Captures the `iss` parameter the authorization response returned (RFC 9207).
+	///
+	/// [`Broker::exchange_code`](crate::flows::Broker::exchange_code) checks it against the
+	/// descriptor with
+	/// [`ProviderDescriptor::validate_authorization_issuer`] before contacting the provider.
+	pub fn with_issuer(mut self, iss: impl Into<String>) -> Self {
+		self.issuer = Some(iss.into());
+
+		self
 	}
 
 	/// PKCE code challenge derived from the secret verifier.
@@ -69,11 +122,117 @@ impl AuthorizationSession {
 	}
 
 	/// Validates the returned `state` parameter after the authorization redirect.
+	///
+	/// Besides comparing `state`, this enforces the broker's [`StatePolicy`]: the session's
+	/// maximum age, its client binding (bound sessions must use
+	/// [`AuthorizationSession::validate_state_for`]), and any registered
+	/// [`StateValidator`](crate::flows::StateValidator).
 	pub fn validate_state(&self, returned_state: &str) -> Result<()> {
-		if returned_state == self.state {
-			Ok(())
-		} else {
-			Err(Error::InvalidGrant { reason: "Authorization state mismatch.".into() })
+		self.check_state(returned_state, None, OffsetDateTime::now_utc())
+	}
+
+	/// Same as [`AuthorizationSession::validate_state`], additionally requiring the callback to
+	/// come from the client the session was bound to.
+	pub fn validate_state_for(&self, returned_state: &str, client: &ClientBinding) -> Result<()> {
+		self.check_state(returned_state, Some(client), OffsetDateTime::now_utc())
+	}
+
+	fn check_state(
+		&self,
+		returned_state: &str,
+		client: Option<&ClientBinding>,
+		now: OffsetDateTime,
+	) -> Result<()> {
+		let refuse = |reason: &str| Err(Error::InvalidGrant { reason: reason.into() });
+
+		if returned_state != self.state {
+			return refuse("Authorization state mismatch.");
+		}
+		if self.policy.max_age.is_some_and(|max_age| now - self.issued_at > max_age) {
+			return refuse("Authorization session has expired.");
+		}
+
+		match (&self.client_binding, client) {
+			(Some(expected), Some(client)) if *expected != client.fingerprint() =>
+				return refuse("Authorization callback came from a different client."),
+			(Some(_), None) => return refuse("Authorization session is bound to a client."),
+			(None, _) if self.policy.require_client_binding =>
+				return refuse("Authorization session is not bound to a client."),
+			_ => {},
+		}
+
+		for validator in &self.policy.validators {
+			validator
+				.validate(self, returned_state, now)
+				.map_err(|reason| Error::InvalidGrant { reason })?;
+		}
+
+		Ok(())
+	}
+
+	/// Returns the `nonce` and ID token a hybrid session must verify, or `None` for plain code
+	/// sessions.
+	pub(super) fn hybrid_id_token(&self) -> Result<Option<(&str, &str)>> {
+		match (&self.nonce, &self.id_token) {
+			(None, None) => Ok(None),
+			(None, Some(_)) => Err(Error::InvalidGrant {
+				reason: "Authorization session did not request an ID token.".into(),
+			}),
+			(Some(_), None) => Err(Error::InvalidGrant {
+				reason: "Hybrid authorization response is missing the ID token.".into(),
+			}),
+			(Some(nonce), Some(id_token)) => Ok(Some((nonce, id_token))),
+		}
+	}
+
+	pub(super) fn validate_issuer(&self, descriptor: &ProviderDescriptor) -> Result<()> {
+		descriptor.validate_authorization_issuer(self.issuer.as_deref())
+	}
+
+	/// Captures everything needed to resume the session, PKCE verifier included.
+	#[cfg(feature = "ring")]
+	pub(super) fn persist(&self) -> PersistedSession {
+		PersistedSession {
+			tenant: self.tenant.clone(),
+			principal: self.principal.clone(),
+			scope: self.scope.clone(),
+			state: self.state.clone(),
+			redirect_uri: self.redirect_uri.clone(),
+			authorize_url: self.authorize_url.clone(),
+			request_object: self.request_object.clone(),
+			issued_at: self.issued_at,
+			client_binding: self.client_binding.clone(),
+			nonce: self.nonce.clone(),
+			id_token: self.id_token.clone(),
+			issuer: self.issuer.clone(),
+			code_verifier: self.pkce.verifier.clone(),
+		}
+	}
+
+	/// Rebuilds a persisted session under the broker's current `policy`.
+	#[cfg(feature = "ring")]
+	pub(super) fn restore(persisted: PersistedSession, policy: Arc<StatePolicy>) -> Self {
+		let challenge = compute_pkce_challenge(&persisted.code_verifier);
+
+		Self {
+			tenant: persisted.tenant,
+			principal: persisted.principal,
+			scope: persisted.scope,
+			state: persisted.state,
+			redirect_uri: persisted.redirect_uri,
+			authorize_url: persisted.authorize_url,
+			request_object: persisted.request_object,
+			issued_at: persisted.issued_at,
+			client_binding: persisted.client_binding,
+			nonce: persisted.nonce,
+			id_token: persisted.id_token,
+			issuer: persisted.issuer,
+			pkce: PkcePair {
+				verifier: persisted.code_verifier,
+				challenge,
+				method: PkceCodeChallengeMethod::S256,
+			},
+			policy,
 		}
 	}
 
@@ -92,12 +251,43 @@ impl Debug for AuthorizationSession {
 			.field("state", &self.state)
 			.field("redirect_uri", &self.redirect_uri)
 			.field("authorize_url", &self.authorize_url)
+			.field("request_object", &self.request_object.is_some())
+			.field("issued_at", &self.issued_at)
+			.field("client_binding", &self.client_binding)
+			.field("nonce", &self.nonce)
+			.field("id_token", &self.id_token.is_some())
+			.field("issuer", &self.issuer)
 			.field("code_challenge", &self.pkce.challenge)
 			.field("code_challenge_method", &self.pkce.method)
 			.finish()
 	}
 }
 
+/// Serialized form of an [`AuthorizationSession`]; holds the PKCE verifier, so only ever store
+/// it encrypted.
+#[cfg(feature = "ring")]
+#[derive(Serialize, Deserialize)]
+pub(super) struct PersistedSession {
+	tenant: TenantId,
+	principal: PrincipalId,
+	scope: ScopeSet,
+	state: String,
+	redirect_uri: Url,
+	authorize_url: Url,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	request_object: Option<String>,
+	issued_at: OffsetDateTime,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	client_binding: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	nonce: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	id_token: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	issuer: Option<String>,
+	code_verifier: String,
+}
+
 #[derive(Clone)]
 pub(super) struct PkcePair {
 	pub(super) verifier: String,
@@ -113,48 +303,89 @@ impl PkcePair {
 	}
 }
 
+/// Broker settings [`build_session`] reads.
+pub(super) struct SessionSettings<'a> {
+	pub(super) descriptor: &'a ProviderDescriptor,
+	pub(super) client_id: &'a str,
+	pub(super) request_object: Option<&'a RequestObjectConfig>,
+	pub(super) state_policy: &'a Arc<StatePolicy>,
+}
+
 pub(super) fn build_session(
-	descriptor: &ProviderDescriptor,
-	client_id: &str,
+	settings: SessionSettings,
 	tenant: TenantId,
 	principal: PrincipalId,
 	scope: ScopeSet,
 	redirect_uri: Url,
-) -> AuthorizationSession {
-	let state = random_string(STATE_LEN);
+) -> Result<AuthorizationSession, ConfigError> {
+	let SessionSettings { descriptor, client_id, request_object, state_policy } = settings;
+	let state = state_policy.generate();
 	let pkce = PkcePair::generate();
-	let authorize_url =
-		build_authorize_url(descriptor, client_id, &redirect_uri, &scope, &state, &pkce);
+	let scope_value = common::format_scope(&scope, descriptor.quirks.scope_delimiter);
+	let nonce = descriptor.quirks.hybrid_id_token.then(|| random_string(NONCE_LEN));
+	let response_type = if nonce.is_some() { "code id_token" } else { "code" };
+	let mut parameters = vec![
+		("response_type", response_type),
+		("client_id", client_id),
+		("redirect_uri", redirect_uri.as_str()),
+	];
 
-	AuthorizationSession::new(tenant, principal, scope, redirect_uri, authorize_url, state, pkce)
-}
+	if let Some(scope_value) = &scope_value {
+		parameters.push(("scope", scope_value));
+	}
+	if let Some(nonce) = &nonce {
+		parameters.push(("nonce", nonce));
+	}
 
-fn build_authorize_url(
-	descriptor: &ProviderDescriptor,
-	client_id: &str,
-	redirect_uri: &Url,
-	scope: &ScopeSet,
-	state: &str,
-	pkce: &PkcePair,
-) -> Url {
-	let mut url = descriptor.endpoints.authorization.clone();
-	let mut pairs = url.query_pairs_mut();
+	parameters.extend([
+		("state", state.as_str()),
+		("code_challenge", pkce.challenge.as_str()),
+		("code_challenge_method", pkce.method.as_str()),
+	]);
 
-	pairs.append_pair("response_type", "code");
-	pairs.append_pair("client_id", client_id);
-	pairs.append_pair("redirect_uri", redirect_uri.as_str());
+	let mut authorize_url = descriptor.endpoints.authorization.clone();
+	let signed = match request_object {
+		Some(config) => {
+			let jwt = config.request_object(
+				descriptor,
+				client_id,
+				&parameters,
+				OffsetDateTime::now_utc(),
+			)?;
+			let reference = match &config.delivery {
+				RequestObjectDelivery::Value => ("request", jwt.clone()),
+				RequestObjectDelivery::Reference(publisher) =>
+					("request_uri", publisher.publish(&jwt)?.to_string()),
+			};
 
-	if let Some(scope_value) = common::format_scope(scope, descriptor.quirks.scope_delimiter) {
-		pairs.append_pair("scope", &scope_value);
-	}
+			authorize_url
+				.query_pairs_mut()
+				.append_pair("client_id", client_id)
+				.append_pair(reference.0, &reference.1);
 
-	pairs.append_pair("state", state);
-	pairs.append_pair("code_challenge", &pkce.challenge);
-	pairs.append_pair("code_challenge_method", pkce.method.as_str());
+			Some(jwt)
+		},
+		None => {
+			authorize_url.query_pairs_mut().extend_pairs(&parameters);
 
-	drop(pairs);
+			None
+		},
+	};
+	let mut session = AuthorizationSession::new(
+		tenant,
+		principal,
+		scope,
+		redirect_uri,
+		authorize_url,
+		state,
+		pkce,
+	);
 
-	url
+	session.request_object = signed;
+	session.nonce = nonce;
+	session.policy = state_policy.clone();
+
+	Ok(session)
 }
 
 fn random_string(len: usize) -> String {
@@ -172,6 +403,7 @@ fn compute_pkce_challenge(verifier: &str) -> String {
 mod tests {
 	// self
 	use super::*;
+	use crate::flows::{StateCharset, StateValidator};
 
 	#[test]
 	fn state_validation_errors_on_mismatch() {
@@ -194,4 +426,69 @@ mod tests {
 
 		assert!(matches!(err, Error::InvalidGrant { .. }));
 	}
+
+	#[test]
+	fn state_policy_enforces_age_binding_and_validators() {
+		struct RejectAll;
+		impl StateValidator for RejectAll {
+			fn validate(
+				&self,
+				_: &AuthorizationSession,
+				_: &str,
+				_: OffsetDateTime,
+			) -> Result<(), String> {
+				Err("Rejected by policy.".into())
+			}
+		}
+
+		let policy = StatePolicy::default()
+			.with_length(4)
+			.with_charset(StateCharset::Hex)
+			.with_max_age(Duration::minutes(5))
+			.require_client_binding();
+		let state = policy.generate();
+
+		assert_eq!(state.len(), StatePolicy::MIN_LENGTH);
+		assert!(state.chars().all(|c| c.is_ascii_hexdigit()));
+
+		let mut session = AuthorizationSession::new(
+			TenantId::new("tenant").expect("Tenant fixture should be valid."),
+			PrincipalId::new("principal").expect("Principal fixture should be valid."),
+			ScopeSet::new(["openid"]).expect("Scope fixture should be valid."),
+			Url::parse("https://example.com/cb").expect("Redirect URL fixture should parse."),
+			Url::parse("https://example.com/auth")
+				.expect("Authorization URL fixture should parse."),
+			state.clone(),
+			PkcePair::generate(),
+		);
+
+		session.policy = Arc::new(policy.clone());
+
+		let client =
+			ClientBinding::new().with_ip([192, 0, 2, 1].into()).with_user_agent("Mozilla/5.0");
+		let now = session.issued_at;
+
+		assert!(session.check_state(&state, Some(&client), now).is_err());
+
+		let session = session.with_client_binding(&client);
+
+		assert!(session.check_state(&state, Some(&client), now).is_ok());
+		assert!(session.check_state(&state, None, now).is_err());
+		assert!(
+			session
+				.check_state(&state, Some(&client.clone().with_user_agent("curl/8.0")), now)
+				.is_err()
+		);
+		assert!(session.check_state(&state, Some(&client), now + Duration::minutes(6)).is_err());
+
+		let mut strict = session.clone();
+
+		strict.policy = Arc::new(policy.with_validator(Arc::new(RejectAll)));
+
+		let err = strict
+			.check_state(&state, Some(&client), now)
+			.expect_err("Registered validators should run after the built-in checks.");
+
+		assert!(matches!(err, Error::InvalidGrant { reason } if reason == "Rejected by policy."));
+	}
 }
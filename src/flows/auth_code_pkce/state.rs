@@ -0,0 +1,174 @@
+// std
+use std::net::IpAddr;
+// crates.io
+use rand::Rng;
+use sha2::{Digest, Sha256};
+// self
+use crate::{_prelude::*, flows::AuthorizationSession};
+
+/// Extra check run by [`AuthorizationSession::validate_state`] after the built-in ones.
+pub trait StateValidator: Send + Sync {
+	/// Returns the reason the callback must be refused, if any.
+	fn validate(
+		&self,
+		session: &AuthorizationSession,
+		returned_state: &str,
+		now: OffsetDateTime,
+	) -> Result<(), String>;
+}
+
+/// Alphabet random `state` values are drawn from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StateCharset {
+	/// `A-Z`, `a-z`, and `0-9` (about 5.95 bits per character).
+	#[default]
+	Alphanumeric,
+	/// RFC 3986 unreserved characters (`A-Z`, `a-z`, `0-9`, `-`, `.`, `_`, `~`).
+	Unreserved,
+	/// Lowercase hexadecimal digits (4 bits per character).
+	Hex,
+}
+impl StateCharset {
+	fn alphabet(self) -> &'static [u8] {
+		match self {
+			Self::Alphanumeric => b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+			Self::Unreserved =>
+				b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-._~",
+			Self::Hex => b"0123456789abcdef",
+		}
+	}
+}
+
+/// Client attributes a session can be bound to, so a callback arriving from another browser is
+/// refused even when it carries the right `state`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ClientBinding {
+	/// Client IP address observed by the application.
+	pub ip: Option<IpAddr>,
+	/// `User-Agent` header sent by the client.
+	pub user_agent: Option<String>,
+}
+impl ClientBinding {
+	/// Creates an empty binding.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Binds the client IP address.
+	pub fn with_ip(mut self, ip: IpAddr) -> Self {
+		self.ip = Some(ip);
+
+		self
+	}
+
+	/// Binds the client `User-Agent` header.
+	pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+		self.user_agent = Some(user_agent.into());
+
+		self
+	}
+
+	/// SHA-256 hex digest stored on the session instead of the raw attributes.
+	pub fn fingerprint(&self) -> String {
+		let mut hasher = Sha256::new();
+
+		hasher.update(self.ip.map(|ip| ip.to_string()).unwrap_or_default());
+		hasher.update([0]);
+		hasher.update(self.user_agent.as_deref().unwrap_or_default());
+
+		hasher.finalize().iter().map(|byte| format!("{byte:02x}")).collect()
+	}
+}
+
+/// How [`Broker::start_authorization`](crate::flows::Broker::start_authorization) generates
+/// `state` values and how [`AuthorizationSession::validate_state`] checks them.
+#[derive(Clone)]
+pub struct StatePolicy {
+	/// Number of random characters in each `state` value.
+	pub length: usize,
+	/// Alphabet the characters are drawn from.
+	pub charset: StateCharset,
+	/// Longest time between starting a session and validating its callback (`None` disables the
+	/// check).
+	pub max_age: Option<Duration>,
+	/// Refuses callbacks for sessions that were never bound through
+	/// [`AuthorizationSession::with_client_binding`].
+	pub require_client_binding: bool,
+	/// Deployment-specific checks run after the built-in ones.
+	pub validators: Vec<Arc<dyn StateValidator>>,
+}
+impl StatePolicy {
+	/// Default `state` length (about 190 bits of entropy with [`StateCharset::Alphanumeric`]).
+	pub const DEFAULT_LENGTH: usize = 32;
+	/// Shortest accepted `state` length; shorter requests are raised to it.
+	pub const MIN_LENGTH: usize = 16;
+
+	/// Sets the number of random characters (values below [`StatePolicy::MIN_LENGTH`] are raised
+	/// to it).
+	pub fn with_length(mut self, length: usize) -> Self {
+		self.length = length.max(Self::MIN_LENGTH);
+
+		self
+	}
+
+	/// Sets the alphabet random characters are drawn from.
+	pub fn with_charset(mut self, charset: StateCharset) -> Self {
+		self.charset = charset;
+
+		self
+	}
+
+	/// Refuses callbacks validated more than `max_age` after the session started.
+	pub fn with_max_age(mut self, max_age: Duration) -> Self {
+		self.max_age = Some(max_age);
+
+		self
+	}
+
+	/// Refuses callbacks for sessions without a [`ClientBinding`].
+	pub fn require_client_binding(mut self) -> Self {
+		self.require_client_binding = true;
+
+		self
+	}
+
+	/// Adds a deployment-specific check.
+	pub fn with_validator(mut self, validator: Arc<dyn StateValidator>) -> Self {
+		self.validators.push(validator);
+
+		self
+	}
+
+	/// Generates a random `state` value.
+	pub fn generate(&self) -> String {
+		let alphabet = self.charset.alphabet();
+		let mut rng = rand::rng();
+
+		(0..self.length.max(Self::MIN_LENGTH))
+			.map(|_| char::from(alphabet[rng.random_range(0..alphabet.len())]))
+			.collect()
+	}
+}
+impl Default for StatePolicy {
+	fn default() -> Self {
+		Self {
+			length: Self::DEFAULT_LENGTH,
+			charset: StateCharset::default(),
+			max_age: None,
+			require_client_binding: false,
+			validators: Vec::new(),
+		}
+	}
+}
+impl Debug for StatePolicy {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		f.debug_struct("StatePolicy")
+			.field("length", &self.length)
+			.field("charset", &self.charset)
+			.field("max_age", &self.max_age)
+			.field("require_client_binding", &self.require_client_binding)
+			.field("validators", &self.validators.len())
+			.finish()
+	}
+}
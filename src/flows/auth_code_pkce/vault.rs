@@ -0,0 +1,184 @@
+//! Encrypted on-disk persistence for pending authorization sessions.
+//!
+//! Native apps can be killed between opening the browser and receiving the callback. A
+//! [`SessionVault`] keeps each pending [`AuthorizationSession`] (PKCE verifier and state
+//! included) in its own AES-256-GCM sealed file, so a restarted app can still finish the login
+//! through [`Broker::exchange_persisted_code`].
+
+// std
+use std::{fs, io::ErrorKind, path::PathBuf};
+// crates.io
+use ring::aead::{AES_256_GCM, Aad, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use sha2::{Digest, Sha256};
+// self
+use crate::{
+	_prelude::*,
+	auth::TokenRecord,
+	flows::{AuthorizationSession, Broker, auth_code_pkce::session::PersistedSession},
+	http::TokenHttpClient,
+	oauth::TransportErrorMapper,
+	store::{FileStore, StoreError},
+};
+
+const SESSION_AAD: &[u8] = b"oauth2-broker/session/v1";
+const SESSION_EXTENSION: &str = "session";
+
+/// Directory of encrypted pending [`AuthorizationSession`]s, one file per `state`.
+///
+/// The key should be device-bound: generate it once and keep it in the platform keystore
+/// (Keychain, DPAPI, Secret Service, Android Keystore) so copied session files are useless on
+/// another machine. File names are SHA-256 digests of the state, so the directory does not
+/// reveal which logins are pending.
+pub struct SessionVault {
+	dir: PathBuf,
+	key: LessSafeKey,
+}
+impl SessionVault {
+	/// Opens (or creates) the vault directory, sealing sessions under `key`.
+	pub fn open(dir: impl Into<PathBuf>, key: [u8; 32]) -> Result<Self, StoreError> {
+		let dir = dir.into();
+
+		fs::create_dir_all(&dir).map_err(|e| StoreError::Backend {
+			message: format!("Failed to create session directory {}: {e}", dir.display()),
+		})?;
+
+		let key = UnboundKey::new(&AES_256_GCM, &key)
+			.map_err(|_| StoreError::Backend { message: "Invalid session key".into() })?;
+
+		Ok(Self { dir, key: LessSafeKey::new(key) })
+	}
+
+	/// Seals `session` to disk, replacing any session with the same state.
+	pub fn save(&self, session: &AuthorizationSession) -> Result<(), StoreError> {
+		let mut payload = serde_json::to_vec(&session.persist()).map_err(|e| {
+			StoreError::Serialization { message: format!("Failed to serialize session: {e}") }
+		})?;
+		let nonce = rand::random::<[u8; NONCE_LEN]>();
+
+		self.key
+			.seal_in_place_append_tag(
+				Nonce::assume_unique_for_key(nonce),
+				Aad::from(SESSION_AAD),
+				&mut payload,
+			)
+			.map_err(|_| StoreError::Backend { message: "Failed to encrypt session".into() })?;
+
+		FileStore::write_atomically(
+			&self.path_for(&session.state),
+			&[&nonce[..], &payload].concat(),
+		)
+	}
+
+	/// Returns `true` when a session for `state` is persisted.
+	pub fn contains(&self, state: &str) -> bool {
+		self.path_for(state).exists()
+	}
+
+	/// Deletes the session for `state`, returning `true` when one existed.
+	pub fn remove(&self, state: &str) -> Result<bool, StoreError> {
+		let path = self.path_for(state);
+
+		match fs::remove_file(&path) {
+			Ok(()) => Ok(true),
+			Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+			Err(e) => Err(StoreError::Backend {
+				message: format!("Failed to remove {}: {e}", path.display()),
+			}),
+		}
+	}
+
+	fn load(&self, state: &str) -> Result<Option<PersistedSession>, StoreError> {
+		let path = self.path_for(state);
+		let mut bytes = match fs::read(&path) {
+			Ok(bytes) => bytes,
+			Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+			Err(e) =>
+				return Err(StoreError::Backend {
+					message: format!("Failed to read {}: {e}", path.display()),
+				}),
+		};
+
+		if bytes.len() < NONCE_LEN {
+			return Err(StoreError::Serialization {
+				message: format!("Session file {} is truncated", path.display()),
+			});
+		}
+
+		let mut nonce = [0; NONCE_LEN];
+
+		nonce.copy_from_slice(&bytes[..NONCE_LEN]);
+
+		let plaintext = self
+			.key
+			.open_in_place(
+				Nonce::assume_unique_for_key(nonce),
+				Aad::from(SESSION_AAD),
+				&mut bytes[NONCE_LEN..],
+			)
+			.map_err(|_| StoreError::Backend {
+				message: format!("Failed to decrypt {}", path.display()),
+			})?;
+
+		serde_json::from_slice(plaintext).map(Some).map_err(|e| StoreError::Serialization {
+			message: format!("Failed to parse {}: {e}", path.display()),
+		})
+	}
+
+	fn path_for(&self, state: &str) -> PathBuf {
+		let digest = Sha256::digest(state.as_bytes());
+		let name = digest.iter().map(|byte| format!("{byte:02x}")).collect::<String>();
+
+		self.dir.join(name).with_extension(SESSION_EXTENSION)
+	}
+}
+impl Debug for SessionVault {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		f.debug_struct("SessionVault").field("dir", &self.dir).finish_non_exhaustive()
+	}
+}
+
+impl<C, M> Broker<C, M>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	/// Loads the persisted session for `state`, applying this broker's
+	/// [`StatePolicy`](crate::flows::StatePolicy).
+	pub fn resume_authorization(
+		&self,
+		vault: &SessionVault,
+		state: &str,
+	) -> Result<Option<AuthorizationSession>> {
+		Ok(vault
+			.load(state)?
+			.map(|persisted| AuthorizationSession::restore(persisted, self.state_policy.clone())))
+	}
+
+	/// Finishes a login whose session was persisted to `vault`, possibly by an earlier process.
+	///
+	/// The session is validated against `returned_state` and removed from the vault before the
+	/// provider call, so a callback can be redeemed at most once even across restarts. `iss` is
+	/// the callback's RFC 9207 issuer parameter, checked as in [`Broker::exchange_code`].
+	pub async fn exchange_persisted_code(
+		&self,
+		vault: &SessionVault,
+		returned_state: &str,
+		authorization_code: impl AsRef<str>,
+		iss: Option<&str>,
+	) -> Result<TokenRecord> {
+		let mut session = self.resume_authorization(vault, returned_state)?.ok_or_else(|| {
+			Error::InvalidGrant {
+				reason: "No persisted authorization session matches the state.".into(),
+			}
+		})?;
+
+		session.validate_state(returned_state)?;
+		vault.remove(returned_state)?;
+
+		if let Some(iss) = iss {
+			session = session.with_issuer(iss);
+		}
+
+		self.exchange_code(session, authorization_code).await
+	}
+}
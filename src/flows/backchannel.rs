@@ -0,0 +1,504 @@
+//! Client Initiated Backchannel Authentication (OpenID Connect CIBA) orchestration.
+//!
+//! [`Broker::start_backchannel_authentication`] asks the provider to authenticate a user on
+//! their own device (a call-center agent triggering a push approval, for example) and keeps the
+//! returned `auth_req_id` as a [`BackchannelSession`]. In poll mode the application calls
+//! [`Broker::poll_backchannel_authentication`] (or waits with
+//! [`Broker::wait_for_backchannel_authentication`]) until the user decides; in ping mode the
+//! provider calls the application back and [`Broker::complete_backchannel_ping`] fetches the
+//! tokens. Approved tokens are persisted like any other grant.
+
+// crates.io
+use futures_timer::Delay;
+// self
+use crate::{
+	_prelude::*,
+	auth::{PrincipalId, ScopeSet, TenantId, TokenFamily, TokenRecord},
+	error::{ConfigError, TransientError},
+	flows::{Broker, common},
+	http::TokenHttpClient,
+	oauth::{BasicFacade, OAuth2Facade, TransportErrorMapper},
+	obs::{self, FlowKind, FlowOutcome, FlowSpan},
+	provider::{GrantType, ProviderEndpoint},
+};
+
+/// How the provider tells the client that the user decided.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub enum BackchannelDelivery {
+	/// The client polls the token endpoint.
+	#[default]
+	Poll,
+	/// The provider calls the client's registered notification endpoint, authenticating with
+	/// this bearer token, and the client then fetches the tokens.
+	Ping {
+		/// `client_notification_token` the provider echoes on the callback.
+		client_notification_token: String,
+	},
+}
+impl Debug for BackchannelDelivery {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		match self {
+			Self::Poll => f.write_str("Poll"),
+			Self::Ping { .. } => f.write_str("Ping"),
+		}
+	}
+}
+
+/// Parameters of a backchannel authentication request.
+#[derive(Clone, Debug)]
+pub struct BackchannelRequest {
+	/// Tenant the resulting tokens belong to.
+	pub tenant: TenantId,
+	/// Principal the resulting tokens belong to.
+	pub principal: PrincipalId,
+	/// Requested scopes (OpenID providers require `openid`).
+	pub scope: ScopeSet,
+	/// Hint identifying the user (e-mail, phone number, ...).
+	pub login_hint: Option<String>,
+	/// Token identifying the user, issued by a party the provider trusts.
+	pub login_hint_token: Option<String>,
+	/// Previously issued ID token identifying the user.
+	pub id_token_hint: Option<String>,
+	/// Short message shown on both devices so the user can tie them together.
+	pub binding_message: Option<String>,
+	/// Secret code the user proves knowledge of, when the provider supports it.
+	pub user_code: Option<String>,
+	/// Lifetime requested for the `auth_req_id`.
+	pub requested_expiry: Option<Duration>,
+	/// Poll or ping delivery.
+	pub delivery: BackchannelDelivery,
+	/// Additional request parameters (for example `acr_values`).
+	pub extra_params: BTreeMap<String, String>,
+}
+impl BackchannelRequest {
+	/// Creates a poll-mode request; set exactly one user hint before starting it.
+	pub fn new(tenant: TenantId, principal: PrincipalId, scope: ScopeSet) -> Self {
+		Self {
+			tenant,
+			principal,
+			scope,
+			login_hint: None,
+			login_hint_token: None,
+			id_token_hint: None,
+			binding_message: None,
+			user_code: None,
+			requested_expiry: None,
+			delivery: BackchannelDelivery::Poll,
+			extra_params: BTreeMap::new(),
+		}
+	}
+
+	/// Identifies the user with a `login_hint`.
+	pub fn with_login_hint(mut self, hint: impl Into<String>) -> Self {
+		self.login_hint = Some(hint.into());
+
+		self
+	}
+
+	/// Identifies the user with a `login_hint_token`.
+	pub fn with_login_hint_token(mut self, token: impl Into<String>) -> Self {
+		self.login_hint_token = Some(token.into());
+
+		self
+	}
+
+	/// Identifies the user with a previously issued ID token.
+	pub fn with_id_token_hint(mut self, id_token: impl Into<String>) -> Self {
+		self.id_token_hint = Some(id_token.into());
+
+		self
+	}
+
+	/// Shows `message` on both the consumption and authentication devices.
+	pub fn with_binding_message(mut self, message: impl Into<String>) -> Self {
+		self.binding_message = Some(message.into());
+
+		self
+	}
+
+	/// Sends a `user_code` the user must confirm.
+	pub fn with_user_code(mut self, code: impl Into<String>) -> Self {
+		self.user_code = Some(code.into());
+
+		self
+	}
+
+	/// Requests a specific `auth_req_id` lifetime.
+	pub fn with_requested_expiry(mut self, expiry: Duration) -> Self {
+		self.requested_expiry = Some(expiry);
+
+		self
+	}
+
+	/// Switches to ping delivery authenticated with `client_notification_token`.
+	pub fn with_ping(mut self, client_notification_token: impl Into<String>) -> Self {
+		self.delivery = BackchannelDelivery::Ping {
+			client_notification_token: client_notification_token.into(),
+		};
+
+		self
+	}
+
+	/// Adds a request parameter.
+	pub fn with_extra_param(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+		self.extra_params.insert(key.into(), value.into());
+
+		self
+	}
+
+	fn into_form(self, scope_delimiter: char) -> Result<Vec<(String, String)>, ConfigError> {
+		let hints = [
+			("login_hint", self.login_hint),
+			("login_hint_token", self.login_hint_token),
+			("id_token_hint", self.id_token_hint),
+		];
+
+		if hints.iter().filter(|(_, hint)| hint.is_some()).count() != 1 {
+			return Err(ConfigError::InvalidBackchannelRequest {
+				reason: "exactly one of login_hint, login_hint_token, or id_token_hint is required",
+			});
+		}
+
+		let mut form = Vec::new();
+
+		if let Some(scope) = common::format_scope(&self.scope, scope_delimiter) {
+			form.push(("scope".to_owned(), scope));
+		}
+
+		form.extend(hints.into_iter().filter_map(|(name, hint)| Some((name.to_owned(), hint?))));
+
+		if let Some(message) = self.binding_message {
+			form.push(("binding_message".into(), message));
+		}
+		if let Some(code) = self.user_code {
+			form.push(("user_code".into(), code));
+		}
+		if let Some(expiry) = self.requested_expiry {
+			form.push(("requested_expiry".into(), expiry.whole_seconds().max(1).to_string()));
+		}
+		if let BackchannelDelivery::Ping { client_notification_token } = self.delivery {
+			form.push(("client_notification_token".into(), client_notification_token));
+		}
+
+		form.extend(self.extra_params);
+
+		Ok(form)
+	}
+}
+
+/// Pending backchannel authentication tracked by the broker.
+#[derive(Clone, Debug)]
+pub struct BackchannelSession {
+	/// Identifier the provider assigned to the request.
+	pub auth_req_id: String,
+	/// Tenant the resulting tokens belong to.
+	pub tenant: TenantId,
+	/// Principal the resulting tokens belong to.
+	pub principal: PrincipalId,
+	/// Requested scopes.
+	pub scope: ScopeSet,
+	/// Poll or ping delivery.
+	pub delivery: BackchannelDelivery,
+	/// When the provider forgets the request.
+	pub expires_at: OffsetDateTime,
+	/// Minimum gap between token endpoint polls.
+	pub interval: Duration,
+	/// Earliest instant the broker polls again.
+	pub next_poll_at: OffsetDateTime,
+}
+
+/// Outcome of [`Broker::poll_backchannel_authentication`].
+#[derive(Clone, Debug)]
+pub enum BackchannelPoll {
+	/// The user has not decided yet; poll again at `next_poll_at`.
+	Pending {
+		/// Earliest instant the next poll reaches the provider.
+		next_poll_at: OffsetDateTime,
+	},
+	/// The user approved and the tokens were stored.
+	Approved(Box<TokenRecord>),
+}
+
+/// Pending backchannel sessions keyed by `auth_req_id`, shared by every clone of a broker.
+#[derive(Debug, Default)]
+pub(crate) struct BackchannelSessions(Mutex<HashMap<String, BackchannelSession>>);
+impl BackchannelSessions {
+	fn insert(&self, session: BackchannelSession) {
+		let mut sessions = self.0.lock();
+
+		sessions.retain(|_, pending| pending.expires_at > session.next_poll_at);
+		sessions.insert(session.auth_req_id.clone(), session);
+	}
+
+	fn get(&self, auth_req_id: &str) -> Option<BackchannelSession> {
+		self.0.lock().get(auth_req_id).cloned()
+	}
+
+	fn update(&self, session: BackchannelSession) {
+		if let Some(pending) = self.0.lock().get_mut(&session.auth_req_id) {
+			*pending = session;
+		}
+	}
+
+	fn remove(&self, auth_req_id: &str) {
+		self.0.lock().remove(auth_req_id);
+	}
+}
+
+impl<C, M> Broker<C, M>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	/// Default poll interval when the provider does not send one (CIBA §7.3).
+	pub const DEFAULT_BACKCHANNEL_INTERVAL: Duration = Duration::seconds(5);
+
+	/// Starts a backchannel authentication request and tracks the returned `auth_req_id`.
+	///
+	/// Fails with [`ConfigError::InvalidBackchannelRequest`] unless exactly one user hint is set.
+	pub async fn start_backchannel_authentication(
+		&self,
+		request: BackchannelRequest,
+	) -> Result<BackchannelSession> {
+		const KIND: FlowKind = FlowKind::Backchannel;
+
+		let span = FlowSpan::new(KIND, "start_backchannel_authentication");
+
+		obs::record_flow_outcome(KIND, FlowOutcome::Attempt);
+
+		let result = span
+			.instrument(async move {
+				self.ensure_ciba_supported()?;
+				self.ensure_flow_enabled(KIND, &request.tenant)?;
+				self.ensure_endpoint_enabled(ProviderEndpoint::BackchannelAuthentication)?;
+
+				let (tenant, principal, scope, delivery) = (
+					request.tenant.clone(),
+					request.principal.clone(),
+					request.scope.clone(),
+					request.delivery.clone(),
+				);
+				let form = request.into_form(self.descriptor.quirks.scope_delimiter)?;
+				let facade = self.ciba_facade()?;
+				let response = self
+					.call_provider(
+						&tenant,
+						&scope,
+						"backchannel_authentication",
+						facade.start_backchannel_authentication(self.strategy.as_ref(), form),
+					)
+					.await?;
+				let now = OffsetDateTime::now_utc();
+				let interval = response
+					.interval
+					.and_then(|secs| i64::try_from(secs).ok())
+					.map_or(Self::DEFAULT_BACKCHANNEL_INTERVAL, Duration::seconds);
+				let session = BackchannelSession {
+					auth_req_id: response.auth_req_id,
+					tenant,
+					principal,
+					scope,
+					delivery,
+					expires_at: now
+						+ Duration::seconds(i64::try_from(response.expires_in).unwrap_or(i64::MAX)),
+					interval,
+					next_poll_at: now + interval,
+				};
+
+				self.backchannel_sessions.insert(session.clone());
+
+				Ok(session)
+			})
+			.await;
+
+		match &result {
+			Ok(_) => obs::record_flow_outcome(KIND, FlowOutcome::Success),
+			Err(_) => obs::record_flow_outcome(KIND, FlowOutcome::Failure),
+		}
+
+		result
+	}
+
+	/// Polls the token endpoint for a poll-mode session.
+	///
+	/// Calls made before the session's `next_poll_at` return [`BackchannelPoll::Pending`]
+	/// without contacting the provider. `slow_down` responses widen the interval by five
+	/// seconds; `expired_token` and `access_denied` drop the session and fail with
+	/// [`Error::InvalidGrant`].
+	pub async fn poll_backchannel_authentication(
+		&self,
+		auth_req_id: &str,
+	) -> Result<BackchannelPoll> {
+		let session = self.backchannel_session(auth_req_id)?;
+
+		if OffsetDateTime::now_utc() < session.next_poll_at {
+			return Ok(BackchannelPoll::Pending { next_poll_at: session.next_poll_at });
+		}
+
+		self.request_backchannel_tokens(session).await
+	}
+
+	/// Polls until the user decides or the request expires, honoring the provider's interval.
+	pub async fn wait_for_backchannel_authentication(
+		&self,
+		auth_req_id: &str,
+	) -> Result<TokenRecord> {
+		loop {
+			match self.poll_backchannel_authentication(auth_req_id).await? {
+				BackchannelPoll::Approved(record) => return Ok(*record),
+				BackchannelPoll::Pending { next_poll_at } => {
+					let wait = (next_poll_at - OffsetDateTime::now_utc()).max(Duration::ZERO);
+
+					Delay::new(wait.try_into().unwrap_or_default()).await;
+				},
+			}
+		}
+	}
+
+	/// Handles a ping-mode callback by fetching the tokens for `auth_req_id`.
+	///
+	/// `bearer_token` is the token from the callback's `Authorization` header; a mismatch with
+	/// the session's `client_notification_token` fails with [`Error::InvalidClient`] before the
+	/// provider is contacted.
+	pub async fn complete_backchannel_ping(
+		&self,
+		auth_req_id: &str,
+		bearer_token: &str,
+	) -> Result<TokenRecord> {
+		let session = self.backchannel_session(auth_req_id)?;
+		let BackchannelDelivery::Ping { client_notification_token } = &session.delivery else {
+			return Err(Error::InvalidClient {
+				reason: "Backchannel session was not started in ping mode.".into(),
+			});
+		};
+
+		if !constant_time_eq(client_notification_token.as_bytes(), bearer_token.as_bytes()) {
+			return Err(Error::InvalidClient {
+				reason: "Backchannel notification token does not match the session.".into(),
+			});
+		}
+
+		match self.request_backchannel_tokens(session).await? {
+			BackchannelPoll::Approved(record) => Ok(*record),
+			BackchannelPoll::Pending { .. } =>
+				Err(TransientError::AuthorizationPending { slow_down: false }.into()),
+		}
+	}
+
+	fn backchannel_session(&self, auth_req_id: &str) -> Result<BackchannelSession> {
+		let session =
+			self.backchannel_sessions.get(auth_req_id).ok_or_else(|| Error::InvalidGrant {
+				reason: "Unknown or completed backchannel authentication request.".into(),
+			})?;
+
+		if OffsetDateTime::now_utc() >= session.expires_at {
+			self.backchannel_sessions.remove(auth_req_id);
+
+			return Err(Error::InvalidGrant {
+				reason:
+					"The backchannel authentication request expired before the user approved it."
+						.into(),
+			});
+		}
+
+		Ok(session)
+	}
+
+	async fn request_backchannel_tokens(
+		&self,
+		mut session: BackchannelSession,
+	) -> Result<BackchannelPoll> {
+		const KIND: FlowKind = FlowKind::Backchannel;
+
+		let span = FlowSpan::new(KIND, "backchannel_token");
+
+		obs::record_flow_outcome(KIND, FlowOutcome::Attempt);
+
+		let result = span
+			.instrument(async {
+				let mut family =
+					TokenFamily::new(session.tenant.clone(), session.principal.clone());
+
+				family.provider = Some(self.descriptor.id.clone());
+
+				let facade = self.ciba_facade()?;
+				let outcome = self
+					.call_provider(
+						&session.tenant,
+						&session.scope,
+						"ciba",
+						facade.exchange_ciba(
+							self.strategy.as_ref(),
+							family,
+							&session.auth_req_id,
+							&session.scope,
+						),
+					)
+					.await;
+
+				match outcome {
+					Ok(record) => {
+						self.backchannel_sessions.remove(&session.auth_req_id);
+						common::persist_record(self, &record).await?;
+
+						Ok(BackchannelPoll::Approved(Box::new(record)))
+					},
+					Err(Error::Transient(TransientError::AuthorizationPending { slow_down })) => {
+						if slow_down {
+							session.interval += Self::DEFAULT_BACKCHANNEL_INTERVAL;
+						}
+
+						session.next_poll_at = OffsetDateTime::now_utc() + session.interval;
+
+						let next_poll_at = session.next_poll_at;
+
+						self.backchannel_sessions.update(session);
+
+						Ok(BackchannelPoll::Pending { next_poll_at })
+					},
+					Err(err @ Error::InvalidGrant { .. }) => {
+						self.backchannel_sessions.remove(&session.auth_req_id);
+
+						Err(err)
+					},
+					Err(err) => Err(err),
+				}
+			})
+			.await;
+
+		match &result {
+			Ok(_) => obs::record_flow_outcome(KIND, FlowOutcome::Success),
+			Err(_) => obs::record_flow_outcome(KIND, FlowOutcome::Failure),
+		}
+
+		result
+	}
+
+	fn ciba_facade(&self) -> Result<BasicFacade<C, M>> {
+		BasicFacade::from_descriptor(
+			&self.descriptor,
+			&self.client_id,
+			self.client_secret.as_deref(),
+			None,
+			self.http_client.clone(),
+			self.transport_mapper.clone(),
+		)
+		.map(|facade| facade.with_jwks_cache(self.jwks_cache.clone()))
+	}
+
+	fn ensure_ciba_supported(&self) -> Result<()> {
+		if self.descriptor.supports(GrantType::Ciba) {
+			Ok(())
+		} else {
+			Err(ConfigError::UnsupportedGrant {
+				descriptor: self.descriptor.id.to_string(),
+				grant: "ciba",
+			}
+			.into())
+		}
+	}
+}
+
+fn constant_time_eq(left: &[u8], right: &[u8]) -> bool {
+	left.len() == right.len() && left.iter().zip(right).fold(0, |acc, (l, r)| acc | (l ^ r)) == 0
+}
@@ -6,22 +6,24 @@
 //! preemptive window, and only calls the provider when the cached record is
 //! missing/expired/forced. A per-`StoreKey` singleflight guard ensures concurrent
 //! callers piggy-back on the same in-flight refresh instead of stampeding the
-//! token endpoint.
+//! token endpoint; the guard is shared with [`Broker::refresh_access_token`], and a caller that
+//! waited on either flow reuses the record it minted. Any `audience`/`resource` parameter supplied
+//! by the strategy or the request partitions the cache so tokens never leak across audiences.
 
 // self
 use crate::{
 	_prelude::*,
-	auth::{TokenFamily, TokenRecord},
+	auth::TokenRecord,
 	error::ConfigError,
 	flows::{
 		Broker,
-		common::{self, CachedTokenRequest},
+		common::{self, CachedTokenRequest, Singleflight},
 	},
 	http::TokenHttpClient,
 	oauth::{BasicFacade, OAuth2Facade, TransportErrorMapper},
-	obs::{self, FlowKind, FlowOutcome, FlowSpan},
-	provider::{GrantType, ProviderStrategy},
-	store::{BrokerStore, StoreKey},
+	obs::{self, CacheOutcome, CallRecorder, FlowKind, FlowOutcome, FlowSpan},
+	provider::GrantType,
+	store::BrokerStore,
 };
 
 impl<C, M> Broker<C, M>
@@ -33,36 +35,23 @@ where
 	pub async fn client_credentials(&self, request: CachedTokenRequest) -> Result<TokenRecord> {
 		const KIND: FlowKind = FlowKind::ClientCredentials;
 
-		let span = FlowSpan::new(KIND, "client_credentials");
+		if let Some(view) = self.route(&request)? {
+			return Box::pin(view.client_credentials(request)).await;
+		}
+
+		let span = FlowSpan::for_request(KIND, "client_credentials", &request);
+		let mut summary =
+			CallRecorder::new(KIND, "client_credentials", &self.descriptor.id, &request.tenant);
+		let recorder = &mut summary;
 
 		obs::record_flow_outcome(KIND, FlowOutcome::Attempt);
 
 		let result = span
 			.instrument(async move {
 				self.ensure_client_credentials_supported()?;
+				self.ensure_flow_enabled(KIND, &request.tenant)?;
 
-				let tenant = request.tenant.clone();
-				let principal = request.principal.clone();
-				let store_scope = request.scope.clone();
-				let requested_scope = store_scope.clone();
-				let mut family = TokenFamily::new(tenant, principal);
-
-				family.provider = Some(self.descriptor.id.clone());
-
-				let key = StoreKey::new(&family, &store_scope);
-				let guard = common::flow_guard(self, &key);
-				let _singleflight = guard.lock().await;
-				let now = OffsetDateTime::now_utc();
-
-				if let Some(current) =
-					<dyn BrokerStore>::fetch(self.store.as_ref(), &family, &store_scope)
-						.await
-						.map_err(Error::from)?
-						.filter(|record| !request.should_refresh(record, now))
-				{
-					return Ok(current);
-				}
-
+				let requested_scope = request.scope.clone();
 				let grant = GrantType::ClientCredentials;
 				let mut form = {
 					let mut map = BTreeMap::new();
@@ -72,23 +61,67 @@ where
 					map
 				};
 
+				// Strategies may inspect the scope; the reserved key is dropped before dispatch and
+				// the facade formats it from the borrowed set.
 				if let Some(scope_value) =
 					common::format_scope(&requested_scope, self.descriptor.quirks.scope_delimiter)
 				{
 					form.insert("scope".into(), scope_value);
 				}
 
-				<dyn ProviderStrategy>::augment_token_request(
-					self.strategy.as_ref(),
-					grant,
-					&mut form,
-				);
-
-				let extra_params: Vec<(String, String)> = form
-					.into_iter()
-					.filter(|(key, _)| key != "grant_type" && key != "scope")
-					.collect();
-				let scope_params = requested_scope.iter().collect::<Vec<_>>();
+				common::augment_form(self, grant, &request, &mut form);
+
+				let extra_params = common::merge_extra_params(form, &request);
+				let store_scope = requested_scope.clone();
+				let token_key = common::token_key(self, &request, &extra_params);
+				let family = token_key.family().clone();
+				let key = token_key.store_key();
+
+				request.cancellation.check("acquiring the singleflight guard")?;
+
+				let singleflight = match common::enter_singleflight(
+					self,
+					&request,
+					&family,
+					&store_scope,
+					key,
+					"client_credentials",
+				)
+				.await?
+				{
+					Singleflight::Acquired(lease) => lease,
+					Singleflight::Stale(record) => {
+						recorder.cache(CacheOutcome::Stale);
+
+						return Ok(*record);
+					},
+					Singleflight::Joined(record) => {
+						recorder.cache(CacheOutcome::Joined);
+
+						return Ok(*record);
+					},
+				};
+
+				request.cancellation.check("reading the cache")?;
+
+				let now = OffsetDateTime::now_utc();
+
+				let mut cached =
+					<dyn BrokerStore>::fetch(self.store.as_ref(), &family, &store_scope)
+						.await
+						.map_err(Error::from)?
+						.filter(|record| !self.is_decommissioned(record));
+
+				if let Some(current) =
+					cached.take_if(|record| !self.should_refresh(&request, record, now))
+				{
+					recorder.cache(CacheOutcome::Hit);
+
+					return Ok(common::mark_served(self, &family, &store_scope, current, now).await);
+				}
+
+				recorder.cache(CacheOutcome::Miss);
+
 				let facade: BasicFacade<C, M> = BasicFacade::from_descriptor(
 					&self.descriptor,
 					&self.client_id,
@@ -96,19 +129,39 @@ where
 					None,
 					self.http_client.clone(),
 					self.transport_mapper.clone(),
-				)?;
-				let record = facade
-					.exchange_client_credentials(
-						self.strategy.as_ref(),
-						family,
-						scope_params.as_slice(),
-						extra_params.as_slice(),
+				)?
+				.with_trace_context(request.trace_context.clone())
+				.with_jwks_cache(self.jwks_cache.clone());
+				request.cancellation.check("contacting the provider")?;
+
+				// Past this point the provider may mint a token, so cancellation is no longer
+				// honored and the result is always persisted.
+				let record = match self
+					.call_provider(
+						&request.tenant,
+						&requested_scope,
+						"client_credentials",
+						facade.exchange_client_credentials(
+							self.strategy.as_ref(),
+							family,
+							&requested_scope,
+							extra_params.as_slice(),
+						),
 					)
-					.await?;
-
-				<dyn BrokerStore>::save(self.store.as_ref(), record.clone())
 					.await
-					.map_err(Error::from)?;
+				{
+					Ok(record) => record,
+					Err(err) => {
+						let current = self.serve_through_maintenance(&request, cached, err)?;
+
+						recorder.cache(CacheOutcome::Stale);
+
+						return Ok(current);
+					},
+				};
+
+				common::persist_record(self, &record).await?;
+				singleflight.record_minted();
 
 				Ok(record)
 			})
@@ -119,6 +172,8 @@ where
 			Err(_) => obs::record_flow_outcome(KIND, FlowOutcome::Failure),
 		}
 
+		common::report_call(self, summary, &result);
+
 		result
 	}
 
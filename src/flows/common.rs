@@ -1,16 +1,136 @@
 //! Shared helpers for flow implementations (scope formatting, cached-request state, guards).
 
+// std
+use std::{
+	pin::pin,
+	sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+	task::{Context, Poll, Waker},
+};
+// crates.io
+use async_lock::MutexGuardArc;
+use futures_timer::Delay;
 // self
 use crate::{
 	_prelude::*,
-	auth::{PrincipalId, ScopeSet, TenantId, TokenRecord, TokenRecordBuilderError},
-	error::ConfigError,
-	flows::Broker,
+	auth::{
+		PrincipalId, PrincipalPath, ProviderId, RevocationReason, ScopeSet, TenantId, TokenFamily,
+		TokenRecord, TokenRecordBuilderError,
+	},
+	error::{ConfigError, TransientError},
+	flows::{Broker, BrokerOverrides},
 	http::TokenHttpClient,
 	oauth::TransportErrorMapper,
-	store::StoreKey,
+	obs::{self, CallRecorder, TraceContext},
+	provider::GrantType,
+	store::{BrokerStore, CompareAndSwapOutcome, Sharded, StoreError, StoreKey},
 };
 
+/// Cooperative cancellation handle shared between a caller and in-flight flows.
+///
+/// Flows observe the token only at cancellation-safe checkpoints: before waiting on the
+/// singleflight guard, while waiting on it, after acquiring it, and before contacting the
+/// provider. Once the provider has answered, the flow always finishes persisting the result,
+/// because abandoning it then would drop freshly rotated secrets.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<CancellationState>);
+impl CancellationToken {
+	/// Creates a token that is not cancelled.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Requests cancellation; every flow sharing this token stops at its next checkpoint.
+	pub fn cancel(&self) {
+		self.0.cancelled.store(true, Ordering::Release);
+
+		for (_, waker) in self.0.waiters.lock().drain() {
+			waker.wake();
+		}
+	}
+
+	/// Returns `true` once [`cancel`](Self::cancel) has been called.
+	pub fn is_cancelled(&self) -> bool {
+		self.0.cancelled.load(Ordering::Acquire)
+	}
+
+	/// Fails with [`Error::Cancelled`] when cancellation was requested.
+	pub fn check(&self, stage: &'static str) -> Result<()> {
+		if self.is_cancelled() { Err(Error::Cancelled { stage }) } else { Ok(()) }
+	}
+
+	/// Resolves once [`cancel`](Self::cancel) has been called, so waits can race against it.
+	pub(crate) fn cancelled(&self) -> Cancelled<'_> {
+		Cancelled { token: self, waiter: None }
+	}
+}
+
+/// Precomputed token family and store key for a [`CachedTokenRequest`].
+///
+/// Building a key clones the request identifiers and computes the scope fingerprint. Callers
+/// that issue the same request repeatedly can build the key once (or take it from a returned
+/// [`TokenRecord`]) and attach it with [`CachedTokenRequest::with_token_key`]; flows fall back
+/// to a fresh key when the attached one does not match the request.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TokenKey {
+	scope: ScopeSet,
+	store_key: StoreKey,
+}
+impl TokenKey {
+	/// Builds a key for `family` and `scope`, computing the scope fingerprint once.
+	///
+	/// Fails with [`StoreError::MissingProvider`] when the family names no provider.
+	pub fn new(family: TokenFamily, scope: ScopeSet) -> Result<Self, StoreError> {
+		let store_key = StoreKey::from_parts(family, scope.fingerprint())?;
+
+		Ok(Self { scope, store_key })
+	}
+
+	fn for_provider(provider: &ProviderId, family: &TokenFamily, scope: ScopeSet) -> Self {
+		let store_key = StoreKey::for_provider(provider, family, &scope);
+
+		Self { scope, store_key }
+	}
+
+	/// Token family the key resolves to.
+	pub fn family(&self) -> &TokenFamily {
+		&self.store_key.family
+	}
+
+	/// Scope set the key was built for.
+	pub fn scope(&self) -> &ScopeSet {
+		&self.scope
+	}
+
+	/// Store key (family plus scope fingerprint).
+	pub fn store_key(&self) -> &StoreKey {
+		&self.store_key
+	}
+
+	fn matches(
+		&self,
+		request: &CachedTokenRequest,
+		provider: &ProviderId,
+		audience: Option<&str>,
+		grant: Option<&str>,
+	) -> bool {
+		let family = self.family();
+
+		family.tenant == request.tenant
+			&& family.principal == request.principal
+			&& family.provider.as_ref() == Some(provider)
+			&& family.audience.as_deref() == audience
+			&& family.grant.as_deref() == grant
+			&& self.scope == request.scope
+	}
+}
+impl TryFrom<&TokenRecord> for TokenKey {
+	type Error = StoreError;
+
+	fn try_from(record: &TokenRecord) -> Result<Self, Self::Error> {
+		Self::new(record.family.clone(), record.scope.clone())
+	}
+}
+
 /// Shared request parameters for flows that evaluate cached records before
 /// contacting the provider.
 #[derive(Clone, Debug)]
@@ -21,10 +141,32 @@ pub struct CachedTokenRequest {
 	pub principal: PrincipalId,
 	/// Normalized scope set for the request.
 	pub scope: ScopeSet,
-	/// Forces cache bypass when true.
-	pub force: bool,
-	/// Jittered preemptive window used when refreshing early.
-	pub preemptive_window: Duration,
+	/// Forces cache bypass when `Some(true)` (`None` uses the broker's
+	/// [`BrokerOverrides::force_refresh`]).
+	pub force: Option<bool>,
+	/// Jittered preemptive window used when refreshing early (`None` uses the broker's
+	/// [`BrokerOverrides::preemptive_window`], then 60 seconds).
+	pub preemptive_window: Option<Duration>,
+	/// Least remaining lifetime a cached token must have to be served (`None` uses the broker's
+	/// [`BrokerOverrides::min_ttl`]).
+	pub min_ttl: Option<Duration>,
+	/// Caller-supplied token request parameters merged after strategy augmentation.
+	pub extra_params: BTreeMap<String, String>,
+	/// Cancellation handle checked at the flow's cancellation-safe checkpoints.
+	pub cancellation: CancellationToken,
+	/// Serves a still-valid cached record when the singleflight wait deadline passes (`None` uses
+	/// the broker's [`BrokerOverrides::allow_stale_on_timeout`]).
+	pub allow_stale_on_timeout: Option<bool>,
+	/// Precomputed key reused instead of rebuilding the family and scope fingerprint.
+	pub token_key: Option<TokenKey>,
+	/// Provider registered via [`Broker::register_provider`] that serves this request (`None`
+	/// uses the broker's own descriptor).
+	pub provider: Option<ProviderId>,
+	/// W3C trace context propagated as `traceparent` on every token endpoint call.
+	pub trace_context: Option<TraceContext>,
+	/// Caller span the flow span is parented to (`None` uses the current span).
+	#[cfg(feature = "tracing")]
+	pub parent_span: Option<tracing::Span>,
 }
 impl CachedTokenRequest {
 	const DEFAULT_PREEMPTIVE_WINDOW: Duration = Duration::seconds(60);
@@ -35,39 +177,179 @@ impl CachedTokenRequest {
 			tenant,
 			principal,
 			scope,
-			force: false,
-			preemptive_window: Self::DEFAULT_PREEMPTIVE_WINDOW,
+			force: None,
+			preemptive_window: None,
+			min_ttl: None,
+			extra_params: BTreeMap::new(),
+			cancellation: CancellationToken::default(),
+			allow_stale_on_timeout: None,
+			token_key: None,
+			provider: None,
+			trace_context: None,
+			#[cfg(feature = "tracing")]
+			parent_span: None,
 		}
 	}
 
 	/// Forces the broker to bypass cache checks.
 	pub fn force_refresh(mut self) -> Self {
-		self.force = true;
+		self.force = Some(true);
 
 		self
 	}
 
-	/// Overrides the force flag.
+	/// Overrides the force flag, including the broker's default.
 	pub fn with_force(mut self, force: bool) -> Self {
-		self.force = force;
+		self.force = Some(force);
 
 		self
 	}
 
 	/// Overrides the jittered preemptive window (defaults to 60 seconds).
 	pub fn with_preemptive_window(mut self, window: Duration) -> Self {
-		self.preemptive_window = if window.is_negative() { Duration::ZERO } else { window };
+		self.preemptive_window = Some(if window.is_negative() { Duration::ZERO } else { window });
+
+		self
+	}
+
+	/// Refreshes cached tokens with less than `ttl` left, regardless of the preemptive window.
+	///
+	/// Unlike the jittered window, the floor is exact, so a caller about to start a long upload
+	/// can ask for a token that stays valid for the whole transfer.
+	pub fn with_min_ttl(mut self, ttl: Duration) -> Self {
+		self.min_ttl = Some(if ttl.is_negative() { Duration::ZERO } else { ttl });
+
+		self
+	}
+
+	/// Adds a token request form parameter for this call only.
+	///
+	/// Parameters are merged after [`ProviderStrategy::augment_token_request`], so they override
+	/// strategy-provided values with the same key. Keys owned by the grant itself
+	/// (`grant_type`, `scope`, `refresh_token`, ...) are ignored.
+	///
+	/// [`ProviderStrategy::augment_token_request`]: crate::provider::ProviderStrategy::augment_token_request
+	pub fn with_extra_param(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+		self.extra_params.insert(key.into(), value.into());
+
+		self
+	}
+
+	/// Attaches a cancellation handle the caller can trigger while the flow runs.
+	pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+		self.cancellation = token;
+
+		self
+	}
+
+	/// Returns the cached record instead of [`Error::SingleflightTimeout`] when the broker's
+	/// singleflight wait deadline passes and that record has not yet expired.
+	pub fn allow_stale_on_timeout(mut self) -> Self {
+		self.allow_stale_on_timeout = Some(true);
+
+		self
+	}
+
+	/// Overrides whether stale records are served on singleflight timeouts, including the
+	/// broker's default.
+	pub fn with_allow_stale_on_timeout(mut self, allow: bool) -> Self {
+		self.allow_stale_on_timeout = Some(allow);
+
+		self
+	}
+
+	/// Routes the request to `provider`, which must be the broker's own provider or one added
+	/// through [`Broker::register_provider`].
+	pub fn for_provider(mut self, provider: ProviderId) -> Self {
+		self.provider = Some(provider);
+
+		self
+	}
+
+	/// Propagates `context` to the provider as `traceparent` (and `tracestate`) headers.
+	pub fn with_trace_context(mut self, context: TraceContext) -> Self {
+		self.trace_context = Some(context);
+
+		self
+	}
+
+	/// Propagates a raw `traceparent` header received by the application; malformed values are
+	/// ignored, as the W3C specification requires.
+	pub fn with_traceparent(mut self, traceparent: &str) -> Self {
+		self.trace_context = TraceContext::from_traceparent(traceparent);
+
+		self
+	}
+
+	/// Parents the flow span to `span` instead of the span current when the flow starts.
+	#[cfg(feature = "tracing")]
+	pub fn with_parent_span(mut self, span: tracing::Span) -> Self {
+		self.parent_span = Some(span);
+
+		self
+	}
+
+	/// Reuses `key` instead of rebuilding the token family and scope fingerprint.
+	///
+	/// When the key covers the same scopes, the request adopts the key's scope set so its cached
+	/// fingerprint is shared as well.
+	pub fn with_token_key(mut self, key: TokenKey) -> Self {
+		if key.scope == self.scope {
+			self.scope = key.scope.clone();
+		}
+
+		self.token_key = Some(key);
 
 		self
 	}
 
 	/// Determines whether the cached record should be refreshed.
 	pub fn should_refresh(&self, record: &TokenRecord, now: OffsetDateTime) -> bool {
-		if self.force || record.is_revoked() || record.is_expired_at(now) {
+		self.should_refresh_with_defaults(record, now, &BrokerOverrides::default())
+	}
+
+	/// Returns `true` when the request bypasses the cache, falling back to `defaults`.
+	pub(crate) fn forces(&self, defaults: &BrokerOverrides) -> bool {
+		self.force.unwrap_or(defaults.force_refresh)
+	}
+
+	/// Returns `true` when the request accepts stale records on singleflight timeouts, falling
+	/// back to `defaults`.
+	pub(crate) fn allows_stale_on_timeout(&self, defaults: &BrokerOverrides) -> bool {
+		self.allow_stale_on_timeout.unwrap_or(defaults.allow_stale_on_timeout)
+	}
+
+	/// Returns `true` when `record` has less than the requested minimum lifetime left.
+	pub(crate) fn below_min_ttl(
+		&self,
+		record: &TokenRecord,
+		now: OffsetDateTime,
+		defaults: &BrokerOverrides,
+	) -> bool {
+		self.min_ttl.or(defaults.min_ttl).is_some_and(|ttl| record.expires_at - now < ttl)
+	}
+
+	/// Same as [`CachedTokenRequest::should_refresh`], with the broker's `defaults` applied to
+	/// every setting the request left unset.
+	pub(crate) fn should_refresh_with_defaults(
+		&self,
+		record: &TokenRecord,
+		now: OffsetDateTime,
+		defaults: &BrokerOverrides,
+	) -> bool {
+		if self.forces(defaults)
+			|| record.is_revoked()
+			|| record.is_expired_at(now)
+			|| self.below_min_ttl(record, now, defaults)
+		{
 			return true;
 		}
 
-		let effective_window = self.effective_preemptive_window();
+		let window = self
+			.preemptive_window
+			.or(defaults.preemptive_window)
+			.unwrap_or(Self::DEFAULT_PREEMPTIVE_WINDOW);
+		let effective_window = self.effective_preemptive_window(window);
 
 		if effective_window.is_zero() {
 			return false;
@@ -78,12 +360,12 @@ impl CachedTokenRequest {
 		remaining <= effective_window
 	}
 
-	fn effective_preemptive_window(&self) -> Duration {
-		self.preemptive_window.checked_sub(self.preemptive_jitter()).unwrap_or(Duration::ZERO)
+	fn effective_preemptive_window(&self, window: Duration) -> Duration {
+		window.checked_sub(self.preemptive_jitter(window)).unwrap_or(Duration::ZERO)
 	}
 
-	fn preemptive_jitter(&self) -> Duration {
-		let window_secs = self.preemptive_window.whole_seconds();
+	fn preemptive_jitter(&self, window: Duration) -> Duration {
+		let window_secs = window.whole_seconds();
 
 		if window_secs <= 1 {
 			return Duration::ZERO;
@@ -139,15 +421,629 @@ pub(crate) fn format_scope(scope: &ScopeSet, delimiter: char) -> Option<String>
 	Some(buf)
 }
 
+/// Form keys the grant owns; neither strategies nor callers may override them.
+const RESERVED_FORM_KEYS: &[&str] = &[
+	"grant_type",
+	"scope",
+	"refresh_token",
+	"code",
+	"code_verifier",
+	"redirect_uri",
+	"client_id",
+	"client_secret",
+];
+
+/// Merges caller-supplied parameters into a strategy-augmented form and drops reserved keys.
+pub(crate) fn merge_extra_params(
+	mut form: BTreeMap<String, String>,
+	request: &CachedTokenRequest,
+) -> Vec<(String, String)> {
+	form.extend(request.extra_params.iter().map(|(key, value)| (key.clone(), value.clone())));
+
+	form.into_iter().filter(|(key, _)| !RESERVED_FORM_KEYS.contains(&key.as_str())).collect()
+}
+
+/// Derives the cache audience from the `audience`/`resource` parameters sent to the provider.
+///
+/// Tokens minted for different audiences must never be served interchangeably, so flows fold
+/// this value into [`TokenFamily::audience`](crate::auth::TokenFamily::audience) before touching
+/// the store.
+pub(crate) fn audience_of(params: &[(String, String)]) -> Option<String> {
+	let values = params
+		.iter()
+		.filter(|(key, _)| key == "audience" || key == "resource")
+		.map(|(_, value)| value.as_str())
+		.collect::<Vec<_>>();
+
+	(!values.is_empty()).then(|| values.join(" "))
+}
+
+/// Derives the cache grant variant from the `requested_token_use` parameter sent to the provider.
+///
+/// Grants that share the token endpoint and audience (JWT bearer and On-Behalf-Of) mint different
+/// tokens, so flows fold this value into [`TokenFamily::grant`](crate::auth::TokenFamily::grant).
+pub(crate) fn grant_of(params: &[(String, String)]) -> Option<String> {
+	params.iter().find(|(key, _)| key == "requested_token_use").map(|(_, value)| value.clone())
+}
+
+/// Runs the strategy's token request hook with the family the request mints for, before any
+/// `audience` the hook adds is folded into it.
+pub(crate) fn augment_form<C, M>(
+	broker: &Broker<C, M>,
+	grant: GrantType,
+	request: &CachedTokenRequest,
+	form: &mut BTreeMap<String, String>,
+) where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	let mut family = TokenFamily::new(request.tenant.clone(), request.principal.clone());
+
+	family.provider = Some(broker.descriptor.id.clone());
+
+	broker.strategy.augment_token_request_for(grant, &family, form);
+}
+
+/// Returns the request's precomputed [`TokenKey`] when it matches `extra_params`, otherwise
+/// builds a fresh one.
+pub(crate) fn token_key<C, M>(
+	broker: &Broker<C, M>,
+	request: &CachedTokenRequest,
+	extra_params: &[(String, String)],
+) -> TokenKey
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	let audience = audience_of(extra_params);
+	let grant = grant_of(extra_params);
+
+	if let Some(key) = &request.token_key
+		&& key.matches(request, &broker.descriptor.id, audience.as_deref(), grant.as_deref())
+	{
+		return key.clone();
+	}
+
+	let mut family = TokenFamily::new(request.tenant.clone(), request.principal.clone());
+
+	family.audience = audience;
+	family.grant = grant;
+
+	TokenKey::for_provider(&broker.descriptor.id, &family, request.scope.clone())
+}
+
+/// Singleflight guards keyed by store key, sharded so unrelated token families never contend.
+pub(crate) type FlowGuards = Sharded<Mutex<HashMap<StoreKey, Arc<FlowSlot>>>>;
+
 /// Returns (and creates on demand) the singleflight guard for a store key.
-pub(crate) fn flow_guard<C, M>(broker: &Broker<C, M>, key: &StoreKey) -> Arc<AsyncMutex<()>>
+fn flow_guard<C, M>(broker: &Broker<C, M>, key: &StoreKey) -> Arc<FlowSlot>
 where
 	C: ?Sized + TokenHttpClient,
 	M: ?Sized + TransportErrorMapper<C::TransportError>,
 {
-	let mut guards = broker.flow_guards.lock();
+	let mut guards = broker.flow_guards.for_key(key).lock();
 
-	guards.entry(key.clone()).or_insert_with(|| Arc::new(AsyncMutex::new(()))).clone()
+	guards.entry(key.clone()).or_default().clone()
+}
+
+/// Family-wide refresh locks, used when a provider shares one refresh token across scopes.
+pub(crate) type FamilyGuards = Sharded<Mutex<HashMap<TokenFamily, Arc<AsyncMutex<()>>>>>;
+
+/// Serializes refreshes across every scope variant of `family`.
+///
+/// Callers take this after their per-key singleflight lease, never before, so the two lock
+/// levels are always acquired in the same order.
+pub(crate) async fn lock_family<C, M>(
+	broker: &Broker<C, M>,
+	family: &TokenFamily,
+) -> MutexGuardArc<()>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	let lock =
+		broker.family_guards.for_key(family).lock().entry(family.clone()).or_default().clone();
+
+	lock.lock_arc().await
+}
+
+/// Refresh responses the store has not recorded yet, keyed by store key.
+pub(crate) type RotationJournal = Sharded<Mutex<HashMap<StoreKey, PendingRotation>>>;
+
+/// Refresh response held in the [`RotationJournal`] until the store records it.
+///
+/// The provider may already have spent [`expected_refresh`](Self::expected_refresh), so the
+/// journal lets the next refresh of the key finish a swap its dropped predecessor started
+/// instead of presenting the spent token again.
+#[derive(Clone, Debug)]
+pub(crate) struct PendingRotation {
+	/// Refresh token presented to the provider.
+	pub(crate) expected_refresh: String,
+	/// Scope the record is stored under.
+	pub(crate) scope: ScopeSet,
+	/// Record built from the provider response.
+	pub(crate) record: TokenRecord,
+	/// Whether the provider issued a new refresh token.
+	pub(crate) rotated: bool,
+}
+
+/// Journals `pending` for `key`, replacing any earlier entry.
+pub(crate) fn journal_rotation<C, M>(
+	broker: &Broker<C, M>,
+	key: &StoreKey,
+	pending: PendingRotation,
+) where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	broker.rotation_journal.for_key(key).lock().insert(key.clone(), pending);
+}
+
+/// Returns the rotation journaled for `key`, if a refresh was dropped before recording it.
+pub(crate) fn pending_rotation<C, M>(
+	broker: &Broker<C, M>,
+	key: &StoreKey,
+) -> Option<PendingRotation>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	broker.rotation_journal.for_key(key).lock().get(key).cloned()
+}
+
+/// Drops the rotation journaled for `key` once the store has recorded it.
+pub(crate) fn clear_rotation<C, M>(broker: &Broker<C, M>, key: &StoreKey)
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	broker.rotation_journal.for_key(key).lock().remove(key);
+}
+
+/// Stores `rotated` and moves every other scope record of its family that still holds
+/// `expected_refresh` onto the rotated refresh token, reporting the outcome for `rotated`.
+///
+/// Must run under [`lock_family`]. The records are replaced together through
+/// [`BrokerStore::compare_and_swap_family`]; stores without it (or a family swap that loses a
+/// race) fall back to rotating `rotated` alone and then each sibling, so the freshly rotated
+/// secret is never dropped.
+pub(crate) async fn compare_and_swap_family_refresh<C, M>(
+	broker: &Broker<C, M>,
+	rotated: &TokenRecord,
+	expected_refresh: &str,
+) -> Result<CompareAndSwapOutcome>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	let store = broker.store.as_ref();
+	let family = &rotated.family;
+	let siblings = family_siblings(broker, rotated, expected_refresh).await?;
+
+	if !siblings.is_empty() {
+		let records = std::iter::once(rotated.clone()).chain(siblings.iter().cloned()).collect();
+
+		match <dyn BrokerStore>::compare_and_swap_family(
+			store,
+			family,
+			Some(expected_refresh),
+			records,
+		)
+		.await
+		{
+			Ok(CompareAndSwapOutcome::Updated) => return Ok(CompareAndSwapOutcome::Updated),
+			Ok(_) | Err(StoreError::Unsupported { .. }) => {},
+			Err(err) => return Err(err.into()),
+		}
+	}
+
+	let outcome = <dyn BrokerStore>::compare_and_swap_refresh(
+		store,
+		family,
+		&rotated.scope,
+		Some(expected_refresh),
+		rotated.clone(),
+	)
+	.await?;
+
+	if matches!(outcome, CompareAndSwapOutcome::Updated) {
+		for sibling in siblings {
+			let scope = sibling.scope.clone();
+
+			<dyn BrokerStore>::compare_and_swap_refresh(
+				store,
+				family,
+				&scope,
+				Some(expected_refresh),
+				sibling,
+			)
+			.await?;
+		}
+	}
+
+	Ok(outcome)
+}
+
+/// Returns the other scope records of `rotated.family` that still hold `expected_refresh`,
+/// already carrying the rotated refresh token.
+///
+/// Stores that cannot enumerate records report no siblings; their next refresh then surfaces the
+/// stale secret as `invalid_grant`.
+async fn family_siblings<C, M>(
+	broker: &Broker<C, M>,
+	rotated: &TokenRecord,
+	expected_refresh: &str,
+) -> Result<Vec<TokenRecord>>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	let family = &rotated.family;
+	let Some(new_refresh) = &rotated.refresh_token else {
+		return Ok(Vec::new());
+	};
+	let Ok(prefix) = PrincipalPath::try_from(&family.principal) else {
+		return Ok(Vec::new());
+	};
+	let records = match <dyn BrokerStore>::list_principal_subtree(
+		broker.store.as_ref(),
+		&family.tenant,
+		&prefix,
+	)
+	.await
+	{
+		Ok(records) => records,
+		Err(StoreError::Unsupported { .. }) => return Ok(Vec::new()),
+		Err(err) => return Err(err.into()),
+	};
+
+	Ok(records
+		.into_iter()
+		.filter(|record| {
+			record.family == *family
+				&& record.scope != rotated.scope
+				&& !record.is_revoked()
+				&& record
+					.refresh_token
+					.as_ref()
+					.is_some_and(|secret| secret.expose() == expected_refresh)
+		})
+		.map(|mut sibling| {
+			sibling.refresh_token = Some(new_refresh.clone());
+			sibling.refresh_expires_at = rotated.refresh_expires_at;
+
+			sibling
+		})
+		.collect())
+}
+
+/// Per-`StoreKey` singleflight lock plus the flow currently holding it.
+#[derive(Debug, Default)]
+pub(crate) struct FlowSlot {
+	lock: Arc<AsyncMutex<()>>,
+	holder: Mutex<Option<(&'static str, OffsetDateTime)>>,
+	/// Bumped whenever a holder stores a freshly minted record for the key.
+	minted: AtomicU64,
+	/// Callers currently waiting for the guard.
+	queued: AtomicUsize,
+}
+impl FlowSlot {
+	/// Returns how many callers are queued behind the guard.
+	pub(crate) fn queued(&self) -> usize {
+		self.queued.load(Ordering::Acquire)
+	}
+}
+
+/// Held singleflight guard; dropping it releases the key for waiting flows.
+pub(crate) struct SingleflightLease {
+	slot: Arc<FlowSlot>,
+	/// Value of [`FlowSlot::minted`] observed before waiting for the guard.
+	minted_before: u64,
+	_guard: MutexGuardArc<()>,
+}
+impl SingleflightLease {
+	/// Tells flows waiting on this key that a fresh record has been stored for them to reuse.
+	pub(crate) fn record_minted(&self) {
+		self.slot.minted.fetch_add(1, Ordering::Release);
+	}
+
+	/// Returns `true` when another holder stored a fresh record while this lease was waiting.
+	fn joined_in_flight(&self) -> bool {
+		self.slot.minted.load(Ordering::Acquire) != self.minted_before
+	}
+}
+impl Drop for SingleflightLease {
+	fn drop(&mut self) {
+		*self.slot.holder.lock() = None;
+	}
+}
+
+/// Counts a caller in [`FlowSlot::queued`] for as long as it waits on the guard.
+struct QueuedCaller<'a>(&'a FlowSlot);
+impl<'a> QueuedCaller<'a> {
+	/// Joins the queue, or sheds the caller when `limit` callers are already waiting.
+	fn join(slot: &'a FlowSlot, limit: Option<usize>) -> Result<Self> {
+		let waiting = slot.queued.fetch_add(1, Ordering::AcqRel);
+
+		match limit {
+			Some(limit) if waiting >= limit => {
+				slot.queued.fetch_sub(1, Ordering::AcqRel);
+
+				Err(TransientError::Backlogged { waiting, limit }.into())
+			},
+			_ => Ok(Self(slot)),
+		}
+	}
+}
+impl Drop for QueuedCaller<'_> {
+	fn drop(&mut self) {
+		self.0.queued.fetch_sub(1, Ordering::AcqRel);
+	}
+}
+
+/// Outcome of waiting on a singleflight guard.
+pub(crate) enum Singleflight {
+	/// The guard is held by the current flow.
+	Acquired(SingleflightLease),
+	/// The wait deadline passed and the caller accepted this still-valid cached record.
+	Stale(Box<TokenRecord>),
+	/// Another flow minted this record for the key while the caller waited, so the caller reuses
+	/// it (even when forced) instead of issuing a second provider call.
+	Joined(Box<TokenRecord>),
+}
+
+/// Future returned by [`CancellationToken::cancelled`].
+pub(crate) struct Cancelled<'a> {
+	token: &'a CancellationToken,
+	waiter: Option<u64>,
+}
+impl Future for Cancelled<'_> {
+	type Output = ();
+
+	fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+		if self.token.is_cancelled() {
+			return Poll::Ready(());
+		}
+
+		let state = &self.token.0;
+		let waiter =
+			*self.waiter.get_or_insert_with(|| state.next_waiter.fetch_add(1, Ordering::Relaxed));
+
+		state.waiters.lock().insert(waiter, cx.waker().clone());
+
+		// `cancel` may have drained the waiters before this one was registered.
+		if self.token.is_cancelled() { Poll::Ready(()) } else { Poll::Pending }
+	}
+}
+impl Drop for Cancelled<'_> {
+	fn drop(&mut self) {
+		if let Some(waiter) = self.waiter {
+			self.token.0.waiters.lock().remove(&waiter);
+		}
+	}
+}
+
+/// Shared state behind a [`CancellationToken`].
+#[derive(Debug, Default)]
+struct CancellationState {
+	cancelled: AtomicBool,
+	/// Wakers of the [`Cancelled`] futures currently parked on the token.
+	waiters: Mutex<HashMap<u64, Waker>>,
+	next_waiter: AtomicU64,
+}
+
+/// Acquires the singleflight guard for `key` on behalf of `flow`, waiting at most
+/// [`Broker::singleflight_wait`] when configured.
+///
+/// Callers that would queue behind [`Broker::max_queued_callers`] others are shed with
+/// [`TransientError::Backlogged`] instead of waiting, and a wait ends with [`Error::Cancelled`]
+/// as soon as `cancellation` fires.
+pub(crate) async fn acquire_singleflight<C, M>(
+	broker: &Broker<C, M>,
+	key: &StoreKey,
+	flow: &'static str,
+	cancellation: &CancellationToken,
+) -> Result<SingleflightLease>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	let slot = flow_guard(broker, key);
+	let minted_before = slot.minted.load(Ordering::Acquire);
+	let guard = match slot.lock.try_lock_arc() {
+		Some(guard) => guard,
+		None => {
+			let _queued = QueuedCaller::join(&slot, broker.max_queued_callers)?;
+			let mut acquire = pin!(slot.lock.lock_arc());
+			let mut cancelled = pin!(cancellation.cancelled());
+			let mut deadline = broker
+				.singleflight_wait
+				.map(|max_wait| (Delay::new(max_wait.try_into().unwrap_or_default()), max_wait));
+
+			std::future::poll_fn(|cx| {
+				if let Poll::Ready(guard) = acquire.as_mut().poll(cx) {
+					return Poll::Ready(Ok(guard));
+				}
+				if cancelled.as_mut().poll(cx).is_ready() {
+					return Poll::Ready(Err(Error::Cancelled {
+						stage: "waiting on the singleflight guard",
+					}));
+				}
+				if let Some((delay, max_wait)) = &mut deadline
+					&& Pin::new(delay).poll(cx).is_ready()
+				{
+					let holder = *slot.holder.lock();
+
+					return Poll::Ready(Err(Error::SingleflightTimeout {
+						blocking_flow: holder.map_or("unknown", |(flow, _)| flow),
+						blocking_since: holder.map(|(_, since)| since),
+						waited: *max_wait,
+					}));
+				}
+
+				Poll::Pending
+			})
+			.await?
+		},
+	};
+
+	*slot.holder.lock() = Some((flow, OffsetDateTime::now_utc()));
+
+	Ok(SingleflightLease { slot, minted_before, _guard: guard })
+}
+
+/// Acquires the singleflight guard for a cached-token flow, falling back to the cached record
+/// when the wait times out and the request allows stale results.
+///
+/// Every cached-token flow (`client_credentials`, `refresh_access_token`, JWT bearer) shares the
+/// guard of a key, so when the holder mints a record the waiters join it whichever flow they came
+/// from.
+pub(crate) async fn enter_singleflight<C, M>(
+	broker: &Broker<C, M>,
+	request: &CachedTokenRequest,
+	family: &TokenFamily,
+	scope: &ScopeSet,
+	key: &StoreKey,
+	flow: &'static str,
+) -> Result<Singleflight>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	match acquire_singleflight(broker, key, flow, &request.cancellation).await {
+		Ok(lease) if lease.joined_in_flight() => {
+			let now = OffsetDateTime::now_utc();
+			let joined = <dyn BrokerStore>::fetch(broker.store.as_ref(), family, scope)
+				.await?
+				.filter(|record| {
+					!record.is_revoked()
+						&& !record.is_expired_at(now)
+						&& !broker.is_decommissioned(record)
+				});
+
+			Ok(match joined {
+				Some(record) => Singleflight::Joined(Box::new(record)),
+				None => Singleflight::Acquired(lease),
+			})
+		},
+		Ok(lease) => Ok(Singleflight::Acquired(lease)),
+		Err(err @ Error::SingleflightTimeout { .. })
+			if request.allows_stale_on_timeout(&broker.overrides) =>
+		{
+			let now = OffsetDateTime::now_utc();
+			let stale = <dyn BrokerStore>::fetch(broker.store.as_ref(), family, scope)
+				.await?
+				.filter(|record| {
+					!record.is_revoked()
+						&& !record.is_expired_at(now)
+						&& !broker.is_decommissioned(record)
+				});
+
+			stale.map(|record| Singleflight::Stale(Box::new(record))).ok_or(err)
+		},
+		Err(err) => Err(err),
+	}
+}
+
+/// Revokes a stored record and emits the matching audit event when a record was affected.
+pub(crate) async fn revoke_record<C, M>(
+	broker: &Broker<C, M>,
+	family: &TokenFamily,
+	scope: &ScopeSet,
+	instant: OffsetDateTime,
+	reason: RevocationReason,
+) -> Result<Option<TokenRecord>>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	let revoked =
+		<dyn BrokerStore>::revoke(broker.store.as_ref(), family, scope, instant, reason).await?;
+
+	if let Some(record) = &revoked {
+		obs::record_revocation(record, reason);
+	}
+
+	Ok(revoked)
+}
+
+/// Stamps `last_served_at` on a cached record about to be served, when serve tracking is on and
+/// the previous stamp is older than the configured resolution.
+///
+/// The stamp is swapped in against the record's refresh secret and any failure is ignored, so
+/// bookkeeping never fails or delays a cache hit beyond the write itself.
+pub(crate) async fn mark_served<C, M>(
+	broker: &Broker<C, M>,
+	family: &TokenFamily,
+	scope: &ScopeSet,
+	record: TokenRecord,
+	now: OffsetDateTime,
+) -> TokenRecord
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	let Some(resolution) = broker.served_tracking else {
+		return record;
+	};
+
+	if record.last_served_at.is_some_and(|served| now - served < resolution) {
+		return record;
+	}
+
+	let mut stamped = record.clone();
+
+	stamped.last_served_at = Some(now);
+
+	let expected = record.refresh_token.as_ref().map(|secret| secret.expose().to_owned());
+
+	match <dyn BrokerStore>::compare_and_swap_refresh(
+		broker.store.as_ref(),
+		family,
+		scope,
+		expected.as_deref(),
+		stamped.clone(),
+	)
+	.await
+	{
+		Ok(CompareAndSwapOutcome::Updated) => stamped,
+		_ => record,
+	}
+}
+
+/// Persists a freshly minted record through the store's two-phase write.
+///
+/// A failed commit is retried once; if it fails again the staged write is left in place for
+/// [`Broker::reconcile_pending_writes`] and the error surfaces to the caller.
+pub(crate) async fn persist_record<C, M>(broker: &Broker<C, M>, record: &TokenRecord) -> Result<()>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	let store = broker.store.as_ref();
+	let prepared = <dyn BrokerStore>::prepare(store, record.clone()).await?;
+
+	if <dyn BrokerStore>::commit(store, &prepared).await.is_err() {
+		<dyn BrokerStore>::commit(store, &prepared).await?;
+	}
+
+	obs::record_delegated_issuance(record);
+
+	Ok(())
+}
+
+/// Emits the call summary collected by `recorder` when the broker opted into call summaries.
+pub(crate) fn report_call<C, M, T>(
+	broker: &Broker<C, M>,
+	recorder: CallRecorder,
+	result: &Result<T>,
+) where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	if broker.overrides.call_summaries {
+		obs::record_call_summary(&recorder.finish(result));
+	}
 }
 
 /// Normalizes token builder errors into broker errors.
@@ -168,4 +1064,28 @@ mod tests {
 		assert_eq!(format_scope(&scope, ' '), Some("email profile".into()));
 		assert_eq!(format_scope(&scope, ','), Some("email,profile".into()));
 	}
+
+	#[test]
+	fn extra_params_override_strategy_values_and_skip_reserved_keys() {
+		let request = CachedTokenRequest::new(
+			TenantId::new("tenant").expect("Failed to build test tenant."),
+			PrincipalId::new("principal").expect("Failed to build test principal."),
+			ScopeSet::default(),
+		)
+		.with_extra_param("audience", "https://api.example.com")
+		.with_extra_param("grant_type", "password");
+		let form = BTreeMap::from([
+			("grant_type".to_owned(), "client_credentials".to_owned()),
+			("audience".to_owned(), "strategy".to_owned()),
+			("resource".to_owned(), "strategy".to_owned()),
+		]);
+
+		assert_eq!(
+			merge_extra_params(form, &request),
+			vec![
+				("audience".to_owned(), "https://api.example.com".to_owned()),
+				("resource".to_owned(), "strategy".to_owned()),
+			]
+		);
+	}
 }
@@ -0,0 +1,87 @@
+//! Secret-free record summaries for admin and describe endpoints.
+
+// self
+use crate::{
+	_prelude::*,
+	auth::{RevocationReason, ScopeSet, TokenFamily, TokenRecord, TokenStatus},
+	flows::Broker,
+	http::TokenHttpClient,
+	oauth::TransportErrorMapper,
+	store::BrokerStore,
+};
+
+/// Lifecycle and usage summary of a stored record, without its secrets.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordDescription {
+	/// Token family of the record.
+	pub family: TokenFamily,
+	/// Scopes granted to the record.
+	pub scope: ScopeSet,
+	/// Lifecycle status when the description was taken.
+	pub status: TokenStatus,
+	/// Issued-at instant of the current access token.
+	pub issued_at: OffsetDateTime,
+	/// Expiry instant of the current access token.
+	pub expires_at: OffsetDateTime,
+	/// Whether the record can be refreshed.
+	pub refreshable: bool,
+	/// Refresh token expiry, when known.
+	pub refresh_expires_at: Option<OffsetDateTime>,
+	/// Revocation instant, if revoked.
+	pub revoked_at: Option<OffsetDateTime>,
+	/// Revocation reason, if revoked.
+	pub revoked_reason: Option<RevocationReason>,
+	/// Last refresh instant.
+	pub last_refreshed_at: Option<OffsetDateTime>,
+	/// Successful refreshes since the original grant.
+	pub refresh_count: u32,
+	/// Last instant a flow served the record from the cache, when serve tracking is enabled.
+	pub last_served_at: Option<OffsetDateTime>,
+	/// Latest of the issue, refresh, and serve instants.
+	pub last_activity: OffsetDateTime,
+	/// Record metadata.
+	pub metadata: BTreeMap<String, String>,
+}
+impl RecordDescription {
+	/// Summarizes `record` as of `now`.
+	pub fn of(record: &TokenRecord, now: OffsetDateTime) -> Self {
+		Self {
+			family: record.family.clone(),
+			scope: record.scope.clone(),
+			status: record.status_at(now),
+			issued_at: record.issued_at,
+			expires_at: record.expires_at,
+			refreshable: record.refresh_token.is_some(),
+			refresh_expires_at: record.refresh_expires_at,
+			revoked_at: record.revoked_at,
+			revoked_reason: record.revoked_reason,
+			last_refreshed_at: record.last_refreshed_at,
+			refresh_count: record.refresh_count,
+			last_served_at: record.last_served_at,
+			last_activity: record.last_activity(),
+			metadata: record.metadata.clone(),
+		}
+	}
+
+	/// Returns `true` when the record saw no activity during the `idle` window before `now`.
+	pub fn is_idle_for(&self, idle: Duration, now: OffsetDateTime) -> bool {
+		now - self.last_activity >= idle
+	}
+}
+
+impl<C, M> Broker<C, M>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	/// Describes the stored record for `family` + `scope`, if any.
+	pub async fn describe_record(
+		&self,
+		family: &TokenFamily,
+		scope: &ScopeSet,
+	) -> Result<Option<RecordDescription>> {
+		let record = <dyn BrokerStore>::fetch(self.store.as_ref(), family, scope).await?;
+
+		Ok(record.map(|record| RecordDescription::of(&record, OffsetDateTime::now_utc())))
+	}
+}
@@ -0,0 +1,490 @@
+//! Structured readiness/liveness reporting for `/healthz`-style endpoints.
+//!
+//! [`Broker::health_report`] combines the store's [`BrokerStore::health_check`], the outcome of
+//! the most recent token-endpoint calls, and the heartbeats of background tasks registered
+//! through [`Broker::register_task`] into a serializable [`HealthReport`].
+//! [`Broker::probe_provider`] checks token endpoint reachability without submitting a grant, and
+//! [`Broker::self_check`] turns the same checks into a fail-fast startup gate.
+
+// crates.io
+use oauth2::HttpClientError;
+// self
+use crate::{
+	_prelude::*,
+	auth::ProviderId,
+	error::{TransientError, TransportError},
+	flows::{Broker, BrokerRegistry},
+	http::{self, TokenHttpClient},
+	oauth::TransportErrorMapper,
+	obs::Diagnostic,
+	store::BrokerStore,
+};
+
+/// Overall status derived from the individual components of a [`HealthReport`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+	/// Every component is healthy.
+	Healthy,
+	/// The store is reachable, but a provider circuit is open, the last provider probe failed, or
+	/// a background task is stale.
+	Degraded,
+	/// The store health check failed; the broker cannot serve or persist tokens.
+	Unhealthy,
+}
+
+/// Circuit state reported for a provider.
+///
+/// The broker does not short-circuit calls on its own; the state only reflects whether the
+/// consecutive failure streak reached [`Broker::circuit_open_after`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+	/// Recent provider calls are succeeding.
+	Closed,
+	/// The provider failed at least [`Broker::circuit_open_after`] times in a row.
+	Open,
+}
+
+/// Store section of a [`HealthReport`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoreHealth {
+	/// Whether [`BrokerStore::health_check`] succeeded.
+	pub healthy: bool,
+	/// Error message returned by the failing health check.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub error: Option<String>,
+}
+
+/// Token-endpoint call history for one provider descriptor.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProviderHealth {
+	/// Descriptor identifier.
+	pub provider: ProviderId,
+	/// When the provider last answered a token request.
+	pub last_success: Option<OffsetDateTime>,
+	/// When a token request last failed with a transient or transport error.
+	pub last_failure: Option<OffsetDateTime>,
+	/// Transient or transport failures since the last answered request.
+	pub consecutive_failures: u32,
+	/// Circuit state derived from `consecutive_failures`.
+	pub circuit: CircuitState,
+	/// Most recent [`Broker::probe_provider`] result.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub last_probe: Option<ProviderProbe>,
+}
+
+/// Result of a [`Broker::probe_provider`] reachability check.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProviderProbe {
+	/// Endpoint that was probed.
+	pub url: Url,
+	/// When the probe was issued.
+	pub checked_at: OffsetDateTime,
+	/// Time until the response (or transport failure) arrived.
+	pub latency: Duration,
+	/// HTTP status returned by the endpoint, when it answered.
+	pub status: Option<u16>,
+	/// Transport or TLS failure that prevented a response.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub error: Option<String>,
+}
+impl ProviderProbe {
+	/// Returns `true` when the endpoint answered over a valid connection, whatever the status.
+	pub fn is_reachable(&self) -> bool {
+		self.status.is_some()
+	}
+}
+
+/// Liveness of a background task registered through [`Broker::register_task`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskHealth {
+	/// Task name.
+	pub name: String,
+	/// When the task last called [`TaskHeartbeat::beat`].
+	pub last_heartbeat: Option<OffsetDateTime>,
+	/// Longest gap between heartbeats before the task counts as stalled.
+	pub stale_after: Duration,
+	/// Whether the last heartbeat is within `stale_after`.
+	pub alive: bool,
+}
+
+/// Serializable readiness/liveness snapshot of a broker (or a registry of brokers).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthReport {
+	/// Overall status.
+	pub status: HealthStatus,
+	/// When the report was assembled.
+	pub checked_at: OffsetDateTime,
+	/// Store health.
+	pub store: StoreHealth,
+	/// Provider call history, one entry per descriptor.
+	pub providers: Vec<ProviderHealth>,
+	/// Registered background tasks.
+	pub tasks: Vec<TaskHealth>,
+	/// Non-fatal configuration findings; they never change `status`.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub diagnostics: Vec<Diagnostic>,
+}
+impl HealthReport {
+	/// Returns `true` unless the report is [`HealthStatus::Unhealthy`].
+	///
+	/// Readiness endpoints typically keep serving traffic while degraded, since cached tokens
+	/// remain usable when a provider is down.
+	pub fn is_ready(&self) -> bool {
+		self.status != HealthStatus::Unhealthy
+	}
+
+	fn new(
+		checked_at: OffsetDateTime,
+		store: StoreHealth,
+		providers: Vec<ProviderHealth>,
+		tasks: Vec<TaskHealth>,
+		diagnostics: Vec<Diagnostic>,
+	) -> Self {
+		let status = if !store.healthy {
+			HealthStatus::Unhealthy
+		} else if providers.iter().any(|provider| {
+			provider.circuit == CircuitState::Open
+				|| provider.last_probe.as_ref().is_some_and(|probe| !probe.is_reachable())
+		}) || tasks.iter().any(|task| !task.alive)
+		{
+			HealthStatus::Degraded
+		} else {
+			HealthStatus::Healthy
+		};
+
+		Self { status, checked_at, store, providers, tasks, diagnostics }
+	}
+}
+
+/// Thread-safe record of token-endpoint call outcomes.
+///
+/// A call counts as answered when it succeeds or fails with anything other than a transient or
+/// transport error; an `invalid_grant` still proves the provider is reachable.
+#[derive(Debug, Default)]
+pub struct ProviderCallTracker {
+	state: Mutex<ProviderCallState>,
+}
+impl ProviderCallTracker {
+	/// Returns when the provider last answered a token request.
+	pub fn last_success(&self) -> Option<OffsetDateTime> {
+		self.state.lock().last_success
+	}
+
+	/// Returns when a token request last failed with a transient or transport error.
+	pub fn last_failure(&self) -> Option<OffsetDateTime> {
+		self.state.lock().last_failure
+	}
+
+	/// Returns the number of transient or transport failures since the last answered request.
+	pub fn consecutive_failures(&self) -> u32 {
+		self.state.lock().consecutive_failures
+	}
+
+	/// Returns the most recent reachability probe result.
+	pub fn last_probe(&self) -> Option<ProviderProbe> {
+		self.state.lock().last_probe.clone()
+	}
+
+	pub(crate) fn record_probe(&self, probe: ProviderProbe) {
+		self.state.lock().last_probe = Some(probe);
+	}
+
+	pub(crate) fn record<T>(&self, result: &Result<T>) {
+		let now = OffsetDateTime::now_utc();
+		let mut state = self.state.lock();
+
+		match result {
+			Err(Error::Transient(_) | Error::Transport(_)) => {
+				state.last_failure = Some(now);
+				state.consecutive_failures = state.consecutive_failures.saturating_add(1);
+			},
+			_ => {
+				state.last_success = Some(now);
+				state.consecutive_failures = 0;
+			},
+		}
+	}
+}
+
+#[derive(Debug, Default)]
+struct ProviderCallState {
+	last_success: Option<OffsetDateTime>,
+	last_failure: Option<OffsetDateTime>,
+	consecutive_failures: u32,
+	last_probe: Option<ProviderProbe>,
+}
+
+/// Handle a background task uses to report that it is still making progress.
+#[derive(Clone, Debug)]
+pub struct TaskHeartbeat {
+	name: String,
+	stale_after: Duration,
+	last: Arc<Mutex<Option<OffsetDateTime>>>,
+}
+impl TaskHeartbeat {
+	/// Records a heartbeat at the current time.
+	pub fn beat(&self) {
+		*self.last.lock() = Some(OffsetDateTime::now_utc());
+	}
+
+	/// Task name the heartbeat was registered under.
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	fn snapshot(&self, now: OffsetDateTime) -> TaskHealth {
+		let last_heartbeat = *self.last.lock();
+
+		TaskHealth {
+			name: self.name.clone(),
+			last_heartbeat,
+			stale_after: self.stale_after,
+			alive: last_heartbeat.is_some_and(|last| now - last <= self.stale_after),
+		}
+	}
+}
+
+impl<C, M> Broker<C, M>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	/// Reports the provider circuit as open after `failures` consecutive transient or transport
+	/// failures (defaults to 5; `0` is treated as 1).
+	pub fn with_circuit_open_after(mut self, failures: u32) -> Self {
+		self.circuit_open_after = failures.max(1);
+
+		self
+	}
+
+	/// Registers a background task whose liveness [`Broker::health_report`] should track.
+	///
+	/// The task counts as alive once it calls [`TaskHeartbeat::beat`] and for `stale_after`
+	/// after each beat. Registering an existing name replaces the previous handle.
+	pub fn register_task(&self, name: impl Into<String>, stale_after: Duration) -> TaskHeartbeat {
+		let heartbeat = TaskHeartbeat { name: name.into(), stale_after, last: Default::default() };
+		let mut tasks = self.tasks.lock();
+
+		tasks.retain(|task| task.name != heartbeat.name);
+		tasks.push(heartbeat.clone());
+
+		heartbeat
+	}
+
+	/// Token-endpoint call history for this broker's descriptor.
+	pub fn provider_health(&self) -> ProviderHealth {
+		let consecutive_failures = self.provider_calls.consecutive_failures();
+
+		ProviderHealth {
+			provider: self.descriptor.id.clone(),
+			last_success: self.provider_calls.last_success(),
+			last_failure: self.provider_calls.last_failure(),
+			consecutive_failures,
+			circuit: if consecutive_failures >= self.circuit_open_after {
+				CircuitState::Open
+			} else {
+				CircuitState::Closed
+			},
+			last_probe: self.provider_calls.last_probe(),
+		}
+	}
+
+	/// Issues a `HEAD` request to the token endpoint to confirm it is reachable over a valid TLS
+	/// connection.
+	///
+	/// No grant is ever submitted. Any HTTP status counts as reachable, since token endpoints
+	/// commonly answer `HEAD` with 405. The result, including its latency, is kept for
+	/// [`Broker::health_report`].
+	pub async fn probe_provider(&self) -> ProviderProbe {
+		self.run_probe().await.0
+	}
+
+	/// Startup self-check that fails fast when the broker cannot operate.
+	///
+	/// Runs [`Broker::check_client_auth`], the store health check, and [`Broker::probe_provider`].
+	/// An unreachable token endpoint surfaces as [`Error::Transport`] and a 5xx answer as
+	/// [`Error::Transient`].
+	pub async fn self_check(&self) -> Result<ProviderProbe> {
+		self.check_client_auth()?;
+		self.health().await?;
+
+		let (probe, outcome) = self.run_probe().await;
+
+		match outcome {
+			Err(e) => Err(TransportError::network(e).into()),
+			Ok(status) if status >= 500 => Err(TransientError::TokenEndpoint {
+				message: format!(
+					"Token endpoint {} answered the reachability probe with HTTP {status}.",
+					probe.url
+				),
+				status: Some(status),
+				retry_after: None,
+			}
+			.into()),
+			Ok(_) => Ok(probe),
+		}
+	}
+
+	/// Assembles a [`HealthReport`] covering the store, this broker's providers (including those
+	/// added via [`Broker::register_provider`]), and its registered background tasks.
+	pub async fn health_report(&self) -> HealthReport {
+		let now = OffsetDateTime::now_utc();
+		let store = check_store(self.store.as_ref()).await;
+		let tasks = self.tasks.lock().iter().map(|task| task.snapshot(now)).collect();
+		let mut providers = vec![self.provider_health()];
+
+		providers.extend(
+			self.registered_providers()
+				.iter()
+				.filter_map(|provider| self.for_provider(provider).ok())
+				.map(|view| view.provider_health()),
+		);
+
+		HealthReport::new(now, store, providers, tasks, self.diagnose())
+	}
+
+	/// Records the outcome of a token-endpoint call and applies maintenance tagging to failures.
+	pub(crate) fn observe_provider_call<T>(&self, result: Result<T>) -> Result<T> {
+		self.provider_calls.record(&result);
+
+		result.map_err(|err| self.tag_maintenance(err))
+	}
+
+	async fn run_probe(&self) -> (ProviderProbe, Result<u16, HttpClientError<C::TransportError>>) {
+		let url = self.descriptor.endpoints.token.clone();
+		let checked_at = OffsetDateTime::now_utc();
+		let outcome = http::head(self.http_client.as_ref(), &url)
+			.await
+			.map(|response| response.status().as_u16());
+		let probe = ProviderProbe {
+			url,
+			checked_at,
+			latency: OffsetDateTime::now_utc() - checked_at,
+			status: outcome.as_ref().ok().copied(),
+			error: outcome.as_ref().err().map(ToString::to_string),
+		};
+
+		self.provider_calls.record_probe(probe.clone());
+
+		(probe, outcome)
+	}
+}
+
+impl<C, M> BrokerRegistry<C, M>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	/// Assembles a [`HealthReport`] covering the shared store plus every registered broker's
+	/// provider and background tasks.
+	pub async fn health_report(&self) -> HealthReport {
+		let now = OffsetDateTime::now_utc();
+		let store = check_store(self.store().as_ref()).await;
+		let mut providers = Vec::new();
+		let mut tasks = Vec::new();
+		let mut diagnostics = Vec::new();
+
+		for provider in self.providers() {
+			let Some(broker) = self.get(provider.as_ref()) else {
+				continue;
+			};
+
+			providers.push(broker.provider_health());
+			tasks.extend(broker.tasks.lock().iter().map(|task| task.snapshot(now)));
+
+			for diagnostic in broker.diagnose() {
+				if !diagnostics.contains(&diagnostic) {
+					diagnostics.push(diagnostic);
+				}
+			}
+		}
+
+		HealthReport::new(now, store, providers, tasks, diagnostics)
+	}
+
+	/// Runs [`Broker::self_check`] for every registered broker, failing on the first problem.
+	pub async fn self_check(&self) -> Result<Vec<ProviderProbe>> {
+		let mut probes = Vec::new();
+
+		for provider in self.providers() {
+			if let Some(broker) = self.get(provider.as_ref()) {
+				probes.push(broker.self_check().await?);
+			}
+		}
+
+		Ok(probes)
+	}
+}
+
+async fn check_store(store: &dyn BrokerStore) -> StoreHealth {
+	match <dyn BrokerStore>::health_check(store).await {
+		Ok(()) => StoreHealth { healthy: true, error: None },
+		Err(err) => StoreHealth { healthy: false, error: Some(err.to_string()) },
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	// self
+	use super::*;
+
+	fn store(healthy: bool) -> StoreHealth {
+		StoreHealth { healthy, error: (!healthy).then(|| "unreachable".into()) }
+	}
+
+	fn task(alive: bool) -> TaskHealth {
+		TaskHealth {
+			name: "warm".into(),
+			last_heartbeat: None,
+			stale_after: Duration::seconds(30),
+			alive,
+		}
+	}
+
+	#[test]
+	fn status_reflects_the_worst_component() {
+		let now = OffsetDateTime::now_utc();
+
+		assert_eq!(
+			HealthReport::new(now, store(true), Vec::new(), vec![task(true)], Vec::new()).status,
+			HealthStatus::Healthy
+		);
+		assert_eq!(
+			HealthReport::new(now, store(true), Vec::new(), vec![task(false)], Vec::new()).status,
+			HealthStatus::Degraded
+		);
+
+		let report =
+			HealthReport::new(now, store(false), Vec::new(), vec![task(false)], Vec::new());
+
+		assert_eq!(report.status, HealthStatus::Unhealthy);
+		assert!(!report.is_ready());
+	}
+
+	#[test]
+	fn answered_errors_reset_the_failure_streak() {
+		let tracker = ProviderCallTracker::default();
+
+		let unavailable = || {
+			Err::<(), _>(Error::from(TransientError::TokenEndpoint {
+				message: "Service unavailable.".into(),
+				status: Some(503),
+				retry_after: None,
+			}))
+		};
+
+		tracker.record(&unavailable());
+		tracker.record(&unavailable());
+
+		assert_eq!(tracker.consecutive_failures(), 2);
+		assert!(tracker.last_success().is_none());
+
+		tracker.record::<()>(&Err(Error::Revoked));
+
+		assert_eq!(tracker.consecutive_failures(), 0);
+		assert!(tracker.last_success().is_some());
+		assert!(tracker.last_failure().is_some());
+	}
+}
@@ -0,0 +1,133 @@
+//! Adoption of tokens issued outside the broker (for example when migrating from another system).
+//!
+//! [`Broker::import_token`] accepts either a raw token endpoint response or a prebuilt
+//! [`TokenRecord`], normalizes it the way the live flows would (expiry from `expires_in`, scopes
+//! split on the provider delimiter, the family pinned to a provider), and stores it under the
+//! same key the cached-token flows use, so later refreshes are broker-managed.
+
+// self
+use crate::{
+	_prelude::*,
+	auth::{ScopeSet, TokenFamily, TokenRecord},
+	error::ConfigError,
+	flows::{Broker, CancellationToken, common},
+	http::TokenHttpClient,
+	oauth::{self, TransportErrorMapper},
+	store::StoreKey,
+};
+
+/// Token material obtained outside the broker, accepted by [`Broker::import_token`].
+#[derive(Clone, Debug)]
+pub enum ImportedToken {
+	/// Raw JSON token endpoint response body.
+	Response {
+		/// Response body exactly as the provider returned it.
+		body: String,
+		/// When the response was received; `expires_in` counts from this instant.
+		received_at: OffsetDateTime,
+	},
+	/// Record built elsewhere, such as an export from another broker.
+	Record(Box<TokenRecord>),
+}
+impl ImportedToken {
+	/// Wraps a raw response body that was received just now.
+	pub fn response(body: impl Into<String>) -> Self {
+		Self::Response { body: body.into(), received_at: OffsetDateTime::now_utc() }
+	}
+}
+impl From<TokenRecord> for ImportedToken {
+	fn from(record: TokenRecord) -> Self {
+		Self::Record(Box::new(record))
+	}
+}
+
+impl<C, M> Broker<C, M>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	/// Validates, normalizes, and stores a token obtained outside the broker.
+	///
+	/// A family without a provider is pinned to this broker's descriptor; a family naming a
+	/// provider registered through [`Broker::register_provider`] is imported with that provider's
+	/// quirks. Imported records must not be revoked, and a record whose access token already
+	/// expired must carry a refresh token. The import replaces any cached record for the same
+	/// key while holding its singleflight guard.
+	pub async fn import_token(
+		&self,
+		mut family: TokenFamily,
+		scope: ScopeSet,
+		token: impl Into<ImportedToken>,
+	) -> Result<TokenRecord> {
+		let provider = family.provider.get_or_insert_with(|| self.descriptor.id.clone()).clone();
+
+		if provider != self.descriptor.id {
+			let view = self.for_provider(&provider)?;
+
+			return Box::pin(view.import_token(family, scope, token)).await;
+		}
+
+		let record = match token.into() {
+			ImportedToken::Response { body, received_at } => oauth::map_imported_token_response(
+				family,
+				&scope,
+				&body,
+				&self.descriptor.quirks,
+				received_at,
+			)?,
+			ImportedToken::Record(record) => normalize_record(family, scope, *record)?,
+		};
+
+		if record.is_revoked() {
+			return Err(invalid("record is revoked"));
+		}
+		if record.is_expired_at(OffsetDateTime::now_utc()) && record.refresh_token.is_none() {
+			return Err(invalid("access token expired and no refresh token was supplied"));
+		}
+
+		let key = StoreKey::new(&record.family, &record.scope)?;
+		let _singleflight =
+			common::acquire_singleflight(self, &key, "import", &CancellationToken::new()).await?;
+
+		common::persist_record(self, &record).await?;
+
+		Ok(record)
+	}
+}
+
+/// Aligns a prebuilt record with the requested family and scope.
+fn normalize_record(
+	family: TokenFamily,
+	scope: ScopeSet,
+	mut record: TokenRecord,
+) -> Result<TokenRecord> {
+	if record.family.tenant != family.tenant || record.family.principal != family.principal {
+		return Err(invalid("record belongs to another tenant or principal"));
+	}
+	if record
+		.family
+		.provider
+		.as_ref()
+		.is_some_and(|provider| Some(provider) != family.provider.as_ref())
+	{
+		return Err(invalid("record belongs to another provider"));
+	}
+	if record.scope != scope {
+		return Err(invalid("record scopes differ from the requested scope set"));
+	}
+	if record.expires_at <= record.issued_at {
+		return Err(invalid("record expires before it was issued"));
+	}
+
+	if family.audience.is_some() && record.family.audience != family.audience {
+		return Err(invalid("record targets another audience"));
+	}
+
+	record.family = family;
+
+	Ok(record)
+}
+
+fn invalid(message: &str) -> Error {
+	ConfigError::InvalidImportedToken { message: message.into() }.into()
+}
@@ -0,0 +1,308 @@
+//! Loopback redirect helper for native and CLI apps (RFC 8252 §7.3).
+//!
+//! [`Broker::authorize_via_loopback`] binds a listener on `127.0.0.1`, points the redirect URI
+//! at it, opens the authorize URL in the user's browser, and exchanges the code as soon as the
+//! provider redirects back, so nobody has to copy `code` and `state` out of the address bar.
+//! Built with the `loopback` feature, which pulls in Tokio for the listener.
+//!
+//! The callback is read from the query string only, so descriptors that return the ID token in
+//! the URL fragment
+//! ([`ProviderQuirks::hybrid_id_token`](crate::provider::ProviderQuirks::hybrid_id_token)) still
+//! need a page that forwards the fragment.
+
+// std
+use std::{
+	io,
+	net::Ipv4Addr,
+	process::{Command, Stdio},
+	time::Duration as StdDuration,
+};
+// crates.io
+use tokio::{
+	io::{AsyncReadExt, AsyncWriteExt},
+	net::{TcpListener, TcpStream},
+	task::JoinSet,
+};
+// self
+use crate::{
+	_prelude::*,
+	auth::{PrincipalId, ScopeSet, TenantId, TokenRecord},
+	error::ConfigError,
+	flows::Broker,
+	http::TokenHttpClient,
+	oauth::TransportErrorMapper,
+};
+
+type Launcher = Arc<dyn Fn(&Url) -> io::Result<()> + Send + Sync>;
+
+const MAX_REQUEST_LINE: usize = 8 * 1024;
+const READ_TIMEOUT: StdDuration = StdDuration::from_secs(10);
+const ACCEPT_BACKOFF: StdDuration = StdDuration::from_millis(10);
+const MAX_ACCEPT_BACKOFF: StdDuration = StdDuration::from_secs(1);
+const CALLBACK_PAGE: &str = "<!doctype html><title>Authorization complete</title>\
+	<p>Authorization complete. You can close this window and return to the application.</p>";
+const DENIED_PAGE: &str = "<!doctype html><title>Authorization failed</title>\
+	<p>Authorization failed. Return to the application for details.</p>";
+const FOREIGN_STATE_PAGE: &str = "<!doctype html><title>Unexpected callback</title>\
+	<p>This callback does not belong to the pending authorization.</p>";
+
+/// Listener and browser settings for [`Broker::authorize_via_loopback`].
+#[derive(Clone)]
+pub struct LoopbackOptions {
+	port: u16,
+	path: String,
+	timeout: Duration,
+	launcher: Launcher,
+}
+impl LoopbackOptions {
+	/// Listens on `port` instead of an ephemeral one.
+	///
+	/// Use this for providers that match registered redirect URIs exactly, ports included.
+	pub fn with_port(mut self, port: u16) -> Self {
+		self.port = port;
+
+		self
+	}
+
+	/// Serves the callback on `path` instead of `/callback`.
+	pub fn with_path(mut self, path: impl Into<String>) -> Self {
+		let path = path.into();
+
+		self.path = if path.starts_with('/') { path } else { format!("/{path}") };
+
+		self
+	}
+
+	/// Gives up when no callback arrives within `timeout` (5 minutes by default).
+	pub fn with_timeout(mut self, timeout: Duration) -> Self {
+		self.timeout = timeout;
+
+		self
+	}
+
+	/// Hands the authorize URL to `launcher` instead of [`open_in_browser`].
+	///
+	/// Useful for headless sessions that print the URL, or for tests that drive the callback.
+	pub fn with_launcher<F>(mut self, launcher: F) -> Self
+	where
+		F: 'static + Send + Sync + Fn(&Url) -> io::Result<()>,
+	{
+		self.launcher = Arc::new(launcher);
+
+		self
+	}
+}
+impl Default for LoopbackOptions {
+	fn default() -> Self {
+		Self {
+			port: 0,
+			path: "/callback".into(),
+			timeout: Duration::minutes(5),
+			launcher: Arc::new(open_in_browser),
+		}
+	}
+}
+impl Debug for LoopbackOptions {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		f.debug_struct("LoopbackOptions")
+			.field("port", &self.port)
+			.field("path", &self.path)
+			.field("timeout", &self.timeout)
+			.finish_non_exhaustive()
+	}
+}
+
+impl<C, M> Broker<C, M>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	/// Runs an Authorization Code + PKCE login through a loopback redirect and returns the
+	/// stored record.
+	///
+	/// The redirect URI is `http://127.0.0.1:{port}{path}`; register it with the provider (most
+	/// accept any port for loopback URIs). Requests to other paths, such as `/favicon.ico`, get
+	/// a 404, and callbacks without the session's `state` get a 400; neither ends the wait. A
+	/// provider `error` callback surfaces as [`Error::InvalidGrant`]; listener, browser, and
+	/// timeout failures as [`ConfigError::Loopback`].
+	pub async fn authorize_via_loopback(
+		&self,
+		tenant: TenantId,
+		principal: PrincipalId,
+		scope: ScopeSet,
+		options: LoopbackOptions,
+	) -> Result<TokenRecord> {
+		let listener =
+			TcpListener::bind((Ipv4Addr::LOCALHOST, options.port)).await.map_err(|e| {
+				loopback_error(format!("cannot listen on 127.0.0.1:{}: {e}", options.port))
+			})?;
+		let port = listener
+			.local_addr()
+			.map_err(|e| loopback_error(format!("listener has no local address: {e}")))?
+			.port();
+		let redirect_uri = Url::parse(&format!("http://127.0.0.1:{port}{}", options.path))
+			.map_err(|source| ConfigError::InvalidRedirect { source })?;
+		let session = self.start_authorization(tenant, principal, scope, redirect_uri)?;
+
+		(options.launcher)(&session.authorize_url)
+			.map_err(|e| loopback_error(format!("cannot open the authorize URL: {e}")))?;
+
+		let timeout = options.timeout.try_into().unwrap_or_default();
+		let params = tokio::time::timeout(
+			timeout,
+			accept_callback(&listener, &options.path, &session.state),
+		)
+		.await
+		.map_err(|_| loopback_error("no callback arrived before the timeout".into()))?;
+		let param = |name: &str| params.iter().find(|(key, _)| key == name).map(|(_, value)| value);
+
+		if let Some(error) = param("error") {
+			let reason = match param("error_description") {
+				Some(description) => format!("authorization denied ({error}): {description}"),
+				None => format!("authorization denied ({error})"),
+			};
+
+			return Err(Error::InvalidGrant { reason });
+		}
+
+		let (Some(code), Some(state)) = (param("code"), param("state")) else {
+			return Err(loopback_error("callback carries no `code`".into()));
+		};
+
+		session.validate_state(state)?;
+
+		let session = match param("iss") {
+			Some(iss) => session.with_issuer(iss),
+			None => session,
+		};
+
+		self.exchange_code(session, code).await
+	}
+}
+
+/// Opens `url` in the platform's default browser without waiting for it to exit.
+pub fn open_in_browser(url: &Url) -> io::Result<()> {
+	#[cfg(target_os = "macos")]
+	let mut command = Command::new("open");
+	#[cfg(target_os = "windows")]
+	let mut command = {
+		let mut command = Command::new("rundll32");
+
+		command.arg("url.dll,FileProtocolHandler");
+
+		command
+	};
+	#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+	let mut command = Command::new("xdg-open");
+
+	command.arg(url.as_str()).stdout(Stdio::null()).stderr(Stdio::null()).spawn().map(drop)
+}
+
+/// Serves connections until a request to `path` carries `state`, returning its query
+/// parameters.
+///
+/// Each connection is read on its own task under [`READ_TIMEOUT`], so a client that connects
+/// and stalls cannot hold up the real callback.
+async fn accept_callback(listener: &TcpListener, path: &str, state: &str) -> Vec<(String, String)> {
+	let (path, state): (Arc<str>, Arc<str>) = (path.into(), state.into());
+	let mut connections = JoinSet::new();
+	let mut backoff = ACCEPT_BACKOFF;
+
+	loop {
+		tokio::select! {
+			accepted = listener.accept() => match accepted {
+				Ok((stream, _)) => {
+					backoff = ACCEPT_BACKOFF;
+
+					connections.spawn(serve_connection(stream, path.clone(), state.clone()));
+				},
+				// Accept keeps failing while the process is out of descriptors (`EMFILE`), so
+				// wait for connections to close instead of spinning.
+				Err(_) => {
+					tokio::time::sleep(backoff).await;
+
+					backoff = (backoff * 2).min(MAX_ACCEPT_BACKOFF);
+				},
+			},
+			Some(served) = connections.join_next(), if !connections.is_empty() =>
+				if let Ok(Some(params)) = served {
+					return params;
+				},
+		}
+	}
+}
+
+/// Answers one connection, returning its query parameters when it is the session's callback.
+async fn serve_connection(
+	mut stream: TcpStream,
+	path: Arc<str>,
+	state: Arc<str>,
+) -> Option<Vec<(String, String)>> {
+	// Connections that are not well-formed HTTP (port scanners, aborted prefetches) or that stall
+	// are dropped without ending the wait.
+	let target =
+		tokio::time::timeout(READ_TIMEOUT, read_request_target(&mut stream)).await.ok()??;
+	let url = Url::parse(&format!("http://127.0.0.1{target}")).ok()?;
+
+	if url.path() != &*path {
+		let _ = respond(&mut stream, "404 Not Found", "").await;
+
+		return None;
+	}
+
+	let params: Vec<(String, String)> =
+		url.query_pairs().map(|(key, value)| (key.into_owned(), value.into_owned())).collect();
+	let param = |name: &str| params.iter().find(|(key, _)| key == name).map(|(_, value)| value);
+
+	if param("state").map(String::as_str) != Some(&*state) {
+		let _ = respond(&mut stream, "400 Bad Request", FOREIGN_STATE_PAGE).await;
+
+		return None;
+	}
+
+	let page = if param("error").is_some() { DENIED_PAGE } else { CALLBACK_PAGE };
+	let _ = respond(&mut stream, "200 OK", page).await;
+
+	Some(params)
+}
+
+/// Reads the request line and returns its origin-form target.
+async fn read_request_target(stream: &mut TcpStream) -> Option<String> {
+	let mut buffer = Vec::new();
+	let mut chunk = [0; 1024];
+
+	while !buffer.windows(2).any(|window| window == b"\r\n") {
+		if buffer.len() >= MAX_REQUEST_LINE {
+			return None;
+		}
+
+		let read = stream.read(&mut chunk).await.ok()?;
+
+		if read == 0 {
+			return None;
+		}
+
+		buffer.extend_from_slice(&chunk[..read]);
+	}
+
+	let line = String::from_utf8_lossy(&buffer);
+	let mut parts = line.lines().next()?.split(' ');
+	let (method, target) = (parts.next()?, parts.next()?);
+
+	(method == "GET" && target.starts_with('/')).then(|| target.to_owned())
+}
+
+async fn respond(stream: &mut TcpStream, status: &str, body: &str) -> io::Result<()> {
+	let response = format!(
+		"HTTP/1.1 {status}\r\ncontent-type: text/html; charset=utf-8\r\ncontent-length: {}\r\n\
+		 cache-control: no-store\r\nconnection: close\r\n\r\n{body}",
+		body.len()
+	);
+
+	stream.write_all(response.as_bytes()).await?;
+	stream.shutdown().await
+}
+
+fn loopback_error(message: String) -> Error {
+	ConfigError::Loopback { message }.into()
+}
@@ -0,0 +1,82 @@
+//! Maintenance-window handling shared by the cached-token flows.
+//!
+//! Windows come from [`ProviderDescriptor::maintenance_windows`] or
+//! [`ProviderStrategy::maintenance_window`]. While one is active, unexpired cached records are
+//! served even inside the preemptive refresh window (so prewarming and early refreshes wait until
+//! the provider is back), and transient provider failures surface as
+//! [`Error::ProviderMaintenance`]. When a record below its minimum TTL cannot be renewed because of
+//! such a failure, the record is served for as long as it remains unexpired.
+//!
+//! [`ProviderDescriptor::maintenance_windows`]: crate::provider::ProviderDescriptor::maintenance_windows
+//! [`ProviderStrategy::maintenance_window`]: crate::provider::ProviderStrategy::maintenance_window
+
+// self
+use crate::{
+	_prelude::*,
+	auth::TokenRecord,
+	flows::{Broker, CachedTokenRequest},
+	http::TokenHttpClient,
+	oauth::TransportErrorMapper,
+	provider::{MaintenanceWindow, ProviderStrategy},
+};
+
+impl<C, M> Broker<C, M>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	/// Returns the provider maintenance window covering `instant`, if any.
+	pub fn maintenance_window_at(&self, instant: OffsetDateTime) -> Option<MaintenanceWindow> {
+		self.descriptor
+			.maintenance_at(instant)
+			.or_else(|| <dyn ProviderStrategy>::maintenance_window(self.strategy.as_ref(), instant))
+	}
+
+	/// Like [`CachedTokenRequest::should_refresh`], but applies the broker's request defaults and
+	/// skips preemptive refreshes during maintenance unless the request forces one.
+	pub(crate) fn should_refresh(
+		&self,
+		request: &CachedTokenRequest,
+		record: &TokenRecord,
+		now: OffsetDateTime,
+	) -> bool {
+		if !request.forces(&self.overrides) && self.maintenance_window_at(now).is_some() {
+			return record.is_revoked()
+				|| record.is_expired_at(now)
+				|| request.below_min_ttl(record, now, &self.overrides);
+		}
+
+		request.should_refresh_with_defaults(record, now, &self.overrides)
+	}
+
+	/// Serves `cached` in place of an [`Error::ProviderMaintenance`] failure while the record is
+	/// still usable, unless the request forced the provider call.
+	pub(crate) fn serve_through_maintenance(
+		&self,
+		request: &CachedTokenRequest,
+		cached: Option<TokenRecord>,
+		err: Error,
+	) -> Result<TokenRecord> {
+		let now = OffsetDateTime::now_utc();
+
+		match cached {
+			Some(record)
+				if matches!(err, Error::ProviderMaintenance { .. })
+					&& !request.forces(&self.overrides)
+					&& !record.is_revoked()
+					&& !record.is_expired_at(now) =>
+				Ok(record),
+			_ => Err(err),
+		}
+	}
+
+	/// Wraps transient and transport failures in [`Error::ProviderMaintenance`] while a window is
+	/// active.
+	pub(crate) fn tag_maintenance(&self, err: Error) -> Error {
+		match self.maintenance_window_at(OffsetDateTime::now_utc()) {
+			Some(window) if matches!(err, Error::Transient(_) | Error::Transport(_)) =>
+				Error::ProviderMaintenance { ends_at: window.ends_at, source: Box::new(err) },
+			_ => err,
+		}
+	}
+}
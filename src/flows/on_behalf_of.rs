@@ -0,0 +1,71 @@
+//! Microsoft Entra On-Behalf-Of (OBO) flow built on the JWT Bearer assertion grant.
+//!
+//! A middle-tier API trades the user token it received for a token to a downstream API by
+//! presenting it as the `assertion` of a `jwt-bearer` grant with
+//! `requested_token_use=on_behalf_of`. Results are cached per tenant/principal/downstream scope
+//! in their own [`TokenFamily::grant`](crate::auth::TokenFamily::grant) partition, apart from
+//! [`Broker::jwt_bearer`] tokens, so repeated downstream calls for the same user reuse one token
+//! until it nears expiry.
+
+// self
+use crate::{
+	_prelude::*,
+	auth::{Delegation, TokenRecord},
+	flows::{Broker, CachedTokenRequest, common},
+	http::TokenHttpClient,
+	oauth::TransportErrorMapper,
+	obs::{self, CallRecorder, FlowKind, FlowOutcome, FlowSpan},
+};
+
+impl<C, M> Broker<C, M>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	/// Exchanges `user_assertion` (the incoming user access token) for a downstream token scoped
+	/// to `request.scope`.
+	///
+	/// `request.principal` should identify the user behind `user_assertion`; the cached token is
+	/// keyed by that principal and the downstream scope, not by the assertion itself. The
+	/// descriptor must enable [`GrantType::JwtBearer`](crate::provider::GrantType::JwtBearer),
+	/// and the broker authenticates with its configured client credentials. Minted records carry
+	/// a [`Delegation`] naming the broker's client as the actor.
+	pub async fn on_behalf_of(
+		&self,
+		user_assertion: &str,
+		request: CachedTokenRequest,
+	) -> Result<TokenRecord> {
+		const KIND: FlowKind = FlowKind::JwtBearer;
+
+		if let Some(view) = self.route(&request)? {
+			return Box::pin(view.on_behalf_of(user_assertion, request)).await;
+		}
+
+		let span = FlowSpan::for_request(KIND, "on_behalf_of", &request);
+		let mut summary =
+			CallRecorder::new(KIND, "on_behalf_of", &self.descriptor.id, &request.tenant);
+
+		obs::record_flow_outcome(KIND, FlowOutcome::Attempt);
+
+		let form = BTreeMap::from([("requested_token_use".into(), "on_behalf_of".into())]);
+		let result = span
+			.instrument(self.assertion_grant(
+				request,
+				&mut summary,
+				form,
+				true,
+				Some(Delegation::new(self.client_id.clone())),
+				|_, _| Ok(user_assertion.to_owned()),
+			))
+			.await;
+
+		match &result {
+			Ok(_) => obs::record_flow_outcome(KIND, FlowOutcome::Success),
+			Err(_) => obs::record_flow_outcome(KIND, FlowOutcome::Failure),
+		}
+
+		common::report_call(self, summary, &result);
+
+		result
+	}
+}
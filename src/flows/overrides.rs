@@ -0,0 +1,129 @@
+//! Per-call settings tuned on cheap broker clones.
+//!
+//! [`Broker::with_overrides`] copies the broker's configuration and hands the copy's
+//! [`BrokerOverrides`] to a closure. Every shared handle (store, transport, singleflight guards,
+//! metrics, registered providers) stays shared, so an endpoint that needs a tighter timeout or its
+//! own rate-limit policy tunes a clone instead of standing up a second broker with separate
+//! caches and guards.
+
+// std
+use std::{io, pin::pin, task::Poll};
+// crates.io
+use futures_timer::Delay;
+// self
+use crate::{
+	_prelude::*,
+	auth::{ScopeSet, TenantId},
+	error::{TransientError, TransportError},
+	ext::{RateLimitContext, RateLimitDecision, RateLimitPolicy},
+	flows::Broker,
+	http::TokenHttpClient,
+	oauth::TransportErrorMapper,
+};
+
+/// Per-call settings that [`Broker::with_overrides`] can change without touching shared state.
+#[derive(Clone, Default)]
+pub struct BrokerOverrides {
+	/// Longest a single token endpoint call may take before failing with
+	/// [`Error::Transport`] (`None` leaves calls bounded only by the transport).
+	pub timeout: Option<Duration>,
+	/// Preemptive refresh window for requests that did not set one through
+	/// [`CachedTokenRequest::with_preemptive_window`](crate::flows::CachedTokenRequest::with_preemptive_window)
+	/// (`None` keeps the 60-second default).
+	pub preemptive_window: Option<Duration>,
+	/// Bypasses the cache for requests that did not set
+	/// [`CachedTokenRequest::with_force`](crate::flows::CachedTokenRequest::with_force).
+	pub force_refresh: bool,
+	/// Serves a still-valid cached record on singleflight timeouts for requests that did not set
+	/// [`CachedTokenRequest::with_allow_stale_on_timeout`](crate::flows::CachedTokenRequest::with_allow_stale_on_timeout).
+	pub allow_stale_on_timeout: bool,
+	/// Least remaining lifetime for requests that did not set
+	/// [`CachedTokenRequest::with_min_ttl`](crate::flows::CachedTokenRequest::with_min_ttl)
+	/// (`None` serves tokens until the preemptive window).
+	pub min_ttl: Option<Duration>,
+	/// Policy consulted before every token endpoint call; a
+	/// [`RateLimitDecision::Delay`] fails the call with [`TransientError::RateLimited`].
+	pub rate_limit: Option<Arc<dyn RateLimitPolicy<Error>>>,
+	/// Emits a redacted [`CallSummary`](crate::obs::CallSummary) event for every cached-token
+	/// call through [`record_call_summary`](crate::obs::record_call_summary).
+	pub call_summaries: bool,
+}
+impl Debug for BrokerOverrides {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		f.debug_struct("BrokerOverrides")
+			.field("timeout", &self.timeout)
+			.field("preemptive_window", &self.preemptive_window)
+			.field("force_refresh", &self.force_refresh)
+			.field("allow_stale_on_timeout", &self.allow_stale_on_timeout)
+			.field("min_ttl", &self.min_ttl)
+			.field("rate_limit_set", &self.rate_limit.is_some())
+			.field("call_summaries", &self.call_summaries)
+			.finish()
+	}
+}
+
+impl<C, M> Broker<C, M>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	/// Returns a clone whose per-call settings were adjusted by `tune`.
+	///
+	/// The clone shares the store, transport, singleflight guards, metrics, and registered
+	/// providers with `self`, so concurrent calls through either broker still coalesce.
+	pub fn with_overrides(&self, tune: impl FnOnce(&mut BrokerOverrides)) -> Self {
+		let mut broker = self.clone();
+
+		tune(&mut broker.overrides);
+
+		broker
+	}
+
+	/// Runs a token endpoint call under the rate-limit policy and timeout, then records its
+	/// outcome.
+	pub(crate) async fn call_provider<T>(
+		&self,
+		tenant: &TenantId,
+		scope: &ScopeSet,
+		operation: &'static str,
+		call: impl Future<Output = Result<T>>,
+	) -> Result<T> {
+		if let Some(policy) = &self.overrides.rate_limit {
+			let context = RateLimitContext::new(
+				tenant.clone(),
+				self.descriptor.id.clone(),
+				scope.clone(),
+				operation,
+			);
+
+			if let RateLimitDecision::Delay(directive) = policy.evaluate(&context).await? {
+				return Err(TransientError::RateLimited(directive).into());
+			}
+		}
+
+		let result = match self.overrides.timeout {
+			None => call.await,
+			Some(limit) => {
+				let mut call = pin!(call);
+				let mut deadline = Delay::new(limit.try_into().unwrap_or_default());
+
+				std::future::poll_fn(|cx| {
+					if let Poll::Ready(result) = call.as_mut().poll(cx) {
+						return Poll::Ready(result);
+					}
+
+					Pin::new(&mut deadline).poll(cx).map(|()| {
+						Err(TransportError::Io(io::Error::new(
+							io::ErrorKind::TimedOut,
+							format!("The {operation} call exceeded the {limit} timeout."),
+						))
+						.into())
+					})
+				})
+				.await
+			},
+		};
+
+		self.observe_provider_call(result)
+	}
+}
@@ -0,0 +1,147 @@
+//! Runtime flow gating for compliance-driven deployments.
+//!
+//! A [`FlowPolicy`] attached through [`Broker::with_flow_policy`] is consulted before any flow
+//! contacts the store or the provider. Disabled flows fail with [`Error::FlowDisabled`] so callers
+//! can tell policy refusals apart from provider or descriptor errors.
+//!
+//! [`EndpointToggles`] gate optional provider endpoints the same way: endpoints a descriptor marks
+//! experimental stay unused until an operator enables them, and any endpoint can be switched off
+//! again without rebuilding the broker.
+
+// std
+use std::collections::HashSet;
+// self
+use crate::{
+	_prelude::*,
+	auth::{ProviderId, TenantId},
+	error::ConfigError,
+	flows::Broker,
+	http::TokenHttpClient,
+	oauth::TransportErrorMapper,
+	obs::FlowKind,
+	provider::ProviderEndpoint,
+};
+
+/// Decides whether a flow may run for a tenant.
+pub trait FlowPolicy: Send + Sync {
+	/// Returns `true` when `flow` is allowed for `tenant`.
+	fn allows(&self, flow: FlowKind, tenant: &TenantId) -> bool;
+}
+
+/// Deny-list [`FlowPolicy`] that disables flows globally or for individual tenants.
+#[derive(Clone, Debug, Default)]
+pub struct FlowGate {
+	disabled: HashSet<FlowKind>,
+	disabled_per_tenant: HashMap<TenantId, HashSet<FlowKind>>,
+}
+impl FlowGate {
+	/// Creates a gate that allows every flow.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Disables `flow` for every tenant.
+	pub fn disable(mut self, flow: FlowKind) -> Self {
+		self.disabled.insert(flow);
+
+		self
+	}
+
+	/// Disables `flow` for `tenant` only.
+	pub fn disable_for_tenant(mut self, tenant: TenantId, flow: FlowKind) -> Self {
+		self.disabled_per_tenant.entry(tenant).or_default().insert(flow);
+
+		self
+	}
+}
+impl FlowPolicy for FlowGate {
+	fn allows(&self, flow: FlowKind, tenant: &TenantId) -> bool {
+		!self.disabled.contains(&flow)
+			&& self.disabled_per_tenant.get(tenant).is_none_or(|flows| !flows.contains(&flow))
+	}
+}
+
+/// Runtime on/off switches for optional provider endpoints, keyed by provider.
+///
+/// Endpoints without an explicit switch follow the descriptor: declared endpoints are enabled
+/// unless listed in
+/// [`ProviderDescriptor::experimental_endpoints`](crate::provider::ProviderDescriptor::experimental_endpoints).
+/// Every clone of a broker shares the same toggles, so flipping one takes effect immediately.
+#[derive(Debug, Default)]
+pub struct EndpointToggles(RwLock<HashMap<(ProviderId, ProviderEndpoint), bool>>);
+impl EndpointToggles {
+	/// Forces `endpoint` of `provider` on or off.
+	pub fn set(&self, provider: &ProviderId, endpoint: ProviderEndpoint, enabled: bool) {
+		self.0.write().insert((provider.clone(), endpoint), enabled);
+	}
+
+	/// Drops the switch so `endpoint` follows the descriptor again.
+	pub fn reset(&self, provider: &ProviderId, endpoint: ProviderEndpoint) {
+		self.0.write().remove(&(provider.clone(), endpoint));
+	}
+
+	/// Returns the explicit switch for `endpoint`, if any.
+	pub fn get(&self, provider: &ProviderId, endpoint: ProviderEndpoint) -> Option<bool> {
+		self.0.read().get(&(provider.clone(), endpoint)).copied()
+	}
+}
+
+impl<C, M> Broker<C, M>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	/// Gates every flow through `policy`.
+	pub fn with_flow_policy(mut self, policy: Arc<dyn FlowPolicy>) -> Self {
+		self.flow_policy = Some(policy);
+
+		self
+	}
+
+	/// Shares `toggles` with other brokers, for example every broker of a registry.
+	pub fn with_endpoint_toggles(mut self, toggles: Arc<EndpointToggles>) -> Self {
+		self.endpoint_toggles = toggles;
+
+		self
+	}
+
+	/// Enables `endpoint` at runtime, including endpoints the descriptor marks experimental.
+	pub fn enable_endpoint(&self, endpoint: ProviderEndpoint) {
+		self.endpoint_toggles.set(&self.descriptor.id, endpoint, true);
+	}
+
+	/// Disables `endpoint` at runtime; flows then behave as if the descriptor omitted it.
+	pub fn disable_endpoint(&self, endpoint: ProviderEndpoint) {
+		self.endpoint_toggles.set(&self.descriptor.id, endpoint, false);
+	}
+
+	/// Returns `true` when `endpoint` is declared and neither experimental nor switched off.
+	pub fn endpoint_enabled(&self, endpoint: ProviderEndpoint) -> bool {
+		self.descriptor.endpoints.get(endpoint).is_some()
+			&& self
+				.endpoint_toggles
+				.get(&self.descriptor.id, endpoint)
+				.unwrap_or_else(|| !self.descriptor.is_experimental(endpoint))
+	}
+
+	pub(crate) fn ensure_endpoint_enabled(&self, endpoint: ProviderEndpoint) -> Result<()> {
+		if self.descriptor.endpoints.get(endpoint).is_none() || self.endpoint_enabled(endpoint) {
+			// Undeclared endpoints surface their own `Missing*Endpoint` error downstream.
+			Ok(())
+		} else {
+			Err(ConfigError::EndpointDisabled {
+				descriptor: self.descriptor.id.to_string(),
+				endpoint: endpoint.as_str(),
+			}
+			.into())
+		}
+	}
+
+	pub(crate) fn ensure_flow_enabled(&self, flow: FlowKind, tenant: &TenantId) -> Result<()> {
+		match &self.flow_policy {
+			Some(policy) if !policy.allows(flow, tenant) =>
+				Err(Error::FlowDisabled { flow, tenant: tenant.clone() }),
+			_ => Ok(()),
+		}
+	}
+}
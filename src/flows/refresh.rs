@@ -6,7 +6,20 @@
 //! a jittered preemptive window, and either reuses the cached record or performs a
 //! `grant_type=refresh_token` call. Successful refreshes rotate secrets via
 //! `BrokerStore::compare_and_swap_refresh`, while invalid_grant/revoked responses
-//! revoke the cached record.
+//! revoke the cached record (and, with `ProviderQuirks::cascade_revocation`, its access token
+//! at the provider's revocation endpoint).
+//!
+//! Providers flagged with `ProviderQuirks::shared_family_refresh` hand out one refresh token for
+//! every scope variant of a family. Refreshes for such providers also take a family-wide lock,
+//! and a rotation replaces every sibling record that held the old secret in one
+//! `BrokerStore::compare_and_swap_family` call, so one scope's refresh never strands the others
+//! with a spent token.
+//!
+//! Cancellation is honored only before the provider call (see
+//! [`CancellationToken`](crate::flows::CancellationToken)); dropping the future at any await
+//! point releases the singleflight guard. The provider's response is journaled in memory before
+//! the store is touched, so a refresh dropped mid-swap leaves the stored record untouched and the
+//! next refresh of the key replays the rotation instead of presenting the spent refresh token.
 
 mod metrics;
 
@@ -15,14 +28,17 @@ pub use metrics::RefreshMetrics;
 // self
 use crate::{
 	_prelude::*,
-	auth::{TokenFamily, TokenRecord},
+	auth::{RevocationReason, TokenRecord},
 	error::ConfigError,
-	flows::{Broker, CachedTokenRequest, common},
+	flows::{
+		Broker, CachedTokenRequest,
+		common::{self, PendingRotation, Singleflight},
+	},
 	http::TokenHttpClient,
 	oauth::{BasicFacade, OAuth2Facade, TransportErrorMapper},
-	obs::{self, FlowKind, FlowOutcome, FlowSpan},
-	provider::GrantType,
-	store::{BrokerStore, CompareAndSwapOutcome, StoreKey},
+	obs::{self, CacheOutcome, CallRecorder, FlowKind, FlowOutcome, FlowSpan, RotationOutcome},
+	provider::{GrantType, ProviderEndpoint},
+	store::{self, BrokerStore, CompareAndSwapOutcome},
 };
 
 impl<C, M> Broker<C, M>
@@ -34,26 +50,84 @@ where
 	pub async fn refresh_access_token(&self, request: CachedTokenRequest) -> Result<TokenRecord> {
 		const KIND: FlowKind = FlowKind::Refresh;
 
-		let span = FlowSpan::new(KIND, "refresh_access_token");
+		if let Some(view) = self.route(&request)? {
+			return Box::pin(view.refresh_access_token(request)).await;
+		}
+
+		let span = FlowSpan::for_request(KIND, "refresh_access_token", &request);
+		let mut summary = CallRecorder::new(KIND, "refresh", &self.descriptor.id, &request.tenant);
+		let recorder = &mut summary;
 
 		obs::record_flow_outcome(KIND, FlowOutcome::Attempt);
 
 		let result = span
 			.instrument(async move {
 				self.ensure_refresh_supported()?;
+				self.ensure_flow_enabled(KIND, &request.tenant)?;
 				self.refresh_metrics.record_attempt();
 
-				let tenant = request.tenant.clone();
-				let principal = request.principal.clone();
 				let store_scope = request.scope.clone();
 				let requested_scope = store_scope.clone();
-				let mut family = TokenFamily::new(tenant, principal);
+				let mut form = BTreeMap::new();
+
+				common::augment_form(self, GrantType::RefreshToken, &request, &mut form);
+
+				let extra_params = common::merge_extra_params(form, &request);
+				let token_key = common::token_key(self, &request, &extra_params);
+				let family = token_key.family().clone();
+				let key = token_key.store_key();
+
+				request.cancellation.check("acquiring the singleflight guard").inspect_err(
+					|_| {
+						self.refresh_metrics.record_failure();
+					},
+				)?;
+
+				let singleflight = match common::enter_singleflight(
+					self,
+					&request,
+					&family,
+					&store_scope,
+					key,
+					"refresh",
+				)
+				.await
+				.inspect_err(|_| {
+					self.refresh_metrics.record_failure();
+				})? {
+					Singleflight::Acquired(lease) => lease,
+					Singleflight::Stale(record) => {
+						recorder.cache(CacheOutcome::Stale);
+						self.refresh_metrics.record_success();
+
+						return Ok(*record);
+					},
+					Singleflight::Joined(record) => {
+						recorder.cache(CacheOutcome::Joined);
+						self.refresh_metrics.record_success();
+
+						return Ok(*record);
+					},
+				};
+
+				let _family_lock = if self.descriptor.quirks.shared_family_refresh {
+					Some(common::lock_family(self, &family).await)
+				} else {
+					None
+				};
 
-				family.provider = Some(self.descriptor.id.clone());
+				request.cancellation.check("reading the cache").inspect_err(|_| {
+					self.refresh_metrics.record_failure();
+				})?;
+
+				if let Some(pending) = common::pending_rotation(self, key) {
+					self.settle_refresh(&pending).await.inspect_err(|_| {
+						self.refresh_metrics.record_failure();
+					})?;
+
+					common::clear_rotation(self, key);
+				}
 
-				let key = StoreKey::new(&family, &store_scope);
-				let guard = common::flow_guard(self, &key);
-				let _singleflight = guard.lock().await;
 				let now = OffsetDateTime::now_utc();
 				let current = <dyn BrokerStore>::fetch(self.store.as_ref(), &family, &store_scope)
 					.await
@@ -70,12 +144,31 @@ where
 						}
 					})?;
 
-				if !request.should_refresh(&current, now) {
+				if self.is_decommissioned(&current) {
+					let _ = common::revoke_record(
+						self,
+						&family,
+						&store_scope,
+						now,
+						RevocationReason::AdminAction,
+					)
+					.await;
+
+					self.refresh_metrics.record_failure();
+
+					return Err(Error::InvalidGrant {
+						reason: "Cached token was minted under a decommissioned client.".into(),
+					});
+				}
+				if !self.should_refresh(&request, &current, now) {
+					recorder.cache(CacheOutcome::Hit);
 					self.refresh_metrics.record_success();
 
-					return Ok(current);
+					return Ok(common::mark_served(self, &family, &store_scope, current, now).await);
 				}
 
+				recorder.cache(CacheOutcome::Miss);
+
 				let expected_refresh = current
 					.refresh_token
 					.as_ref()
@@ -95,33 +188,111 @@ where
 				)
 				.inspect_err(|_| {
 					self.refresh_metrics.record_failure();
+				})?
+				.with_trace_context(request.trace_context.clone())
+				.with_jwks_cache(self.jwks_cache.clone());
+				request.cancellation.check("contacting the provider").inspect_err(|_| {
+					self.refresh_metrics.record_failure();
 				})?;
-				let (facade_record, new_refresh) = match facade
-					.refresh_token(
-						self.strategy.as_ref(),
-						family.clone(),
-						&expected_refresh,
+
+				// Past this point the provider may rotate the refresh token, so cancellation is no
+				// longer honored and the CAS always runs to completion.
+				let (mut facade_record, new_refresh) = match self
+					.call_provider(
+						&request.tenant,
 						&requested_scope,
+						"refresh_token",
+						facade.refresh_token(
+							self.strategy.as_ref(),
+							family.clone(),
+							&expected_refresh,
+							&requested_scope,
+							extra_params.as_slice(),
+						),
 					)
 					.await
 				{
 					Ok(result) => result,
 					Err(err) => {
 						if matches!(err, Error::InvalidGrant { .. } | Error::Revoked) {
-							let _ = <dyn BrokerStore>::revoke(
-								self.store.as_ref(),
+							let _ = common::revoke_record(
+								self,
 								&family,
 								&store_scope,
 								now,
+								RevocationReason::ProviderInvalidGrant,
 							)
 							.await;
+
+							if self.descriptor.quirks.cascade_revocation
+								&& !self.endpoint_enabled(ProviderEndpoint::Revocation)
+							{
+								self.diagnose_revocation_endpoint();
+							} else if self.descriptor.quirks.cascade_revocation {
+								// Best effort: the local record is already revoked, so a failed
+								// provider call must not mask the original error.
+								let cascade = facade
+									.revoke_token(
+										self.strategy.as_ref(),
+										current.access_token.expose(),
+										"access_token",
+									)
+									.await;
+
+								#[cfg(feature = "tracing")]
+								if let Err(cascade_err) = &cascade {
+									tracing::warn!(
+										error = %cascade_err,
+										"Cascading access token revocation failed."
+									);
+								}
+								#[cfg(not(feature = "tracing"))]
+								let _ = cascade;
+							}
 						}
 
+						let err = match self.serve_through_maintenance(&request, Some(current), err)
+						{
+							Ok(current) => {
+								recorder.cache(CacheOutcome::Stale);
+								self.refresh_metrics.record_success();
+
+								return Ok(current);
+							},
+							Err(err) => err,
+						};
+
 						self.refresh_metrics.record_failure();
 
 						return Err(err);
 					},
 				};
+
+				// Providers such as Salesforce may omit response extras on refresh, so keep the
+				// values captured by the original grant.
+				for (key, value) in &current.extras {
+					facade_record.extras.entry(key.clone()).or_insert_with(|| value.clone());
+				}
+				// Metadata describes the family rather than one access token, so it carries over
+				// unless the strategy re-annotated the key.
+				for (key, value) in &current.metadata {
+					facade_record.metadata.entry(key.clone()).or_insert_with(|| value.clone());
+				}
+
+				// Refreshing a delegated or key-bound token keeps the original actor chain and,
+				// unless the new access token declares its own, the original binding.
+				facade_record.delegation = current.delegation.clone();
+
+				if facade_record.binding.is_none() {
+					facade_record.binding = current.binding.clone();
+				}
+
+				facade_record.last_refreshed_at = Some(facade_record.issued_at);
+				facade_record.refresh_count = current.refresh_count.saturating_add(1);
+				facade_record.last_served_at = current.last_served_at;
+				// Backends that swap without reading the stored record keep this version.
+				facade_record.version = store::next_version(&current);
+
 				let updated = if new_refresh.is_some() {
 					facade_record
 				} else {
@@ -131,63 +302,48 @@ where
 					)
 					.access_token(facade_record.access_token.expose())
 					.issued_at(facade_record.issued_at)
-					.expires_at(facade_record.expires_at);
+					.expires_at(facade_record.expires_at)
+					.extras(facade_record.extras.clone())
+					.metadata(facade_record.metadata.clone())
+					.provenance_from(&facade_record)
+					.usage_from(&facade_record);
 
 					builder = builder.refresh_token(expected_refresh.clone());
 
+					if let Some(instant) = current.refresh_expires_at {
+						builder = builder.refresh_expires_at(instant);
+					}
+
 					builder.build().map_err(|err| {
 						self.refresh_metrics.record_failure();
 
 						common::map_token_builder_error(err)
 					})?
 				};
-				let outcome = <dyn BrokerStore>::compare_and_swap_refresh(
-					self.store.as_ref(),
-					&family,
-					&store_scope,
-					Some(expected_refresh.as_str()),
-					updated.clone(),
-				)
-				.await
-				.map_err(|err| {
-					self.refresh_metrics.record_failure();
+				let pending = PendingRotation {
+					expected_refresh,
+					scope: store_scope,
+					record: updated,
+					rotated: new_refresh.is_some(),
+				};
+
+				// The provider may have spent the old refresh token already, so the response is
+				// journaled before the first store await; a flow dropped while swapping leaves
+				// it for the next refresh of the key to replay.
+				common::journal_rotation(self, key, pending.clone());
 
-					Error::from(err)
+				let (result, rotation) = self.settle_refresh(&pending).await.inspect_err(|_| {
+					self.refresh_metrics.record_failure();
 				})?;
-				let result = match outcome {
-					CompareAndSwapOutcome::Updated => updated,
-					CompareAndSwapOutcome::Missing => {
-						<dyn BrokerStore>::save(self.store.as_ref(), updated.clone())
-							.await
-							.map_err(|err| {
-								self.refresh_metrics.record_failure();
-								Error::from(err)
-							})?;
-
-						updated
-					},
-					CompareAndSwapOutcome::RefreshMismatch => {
-						match <dyn BrokerStore>::fetch(self.store.as_ref(), &family, &store_scope)
-							.await
-							.map_err(|err| {
-								self.refresh_metrics.record_failure();
-								Error::from(err)
-							})? {
-							Some(existing) => existing,
-							None => {
-								<dyn BrokerStore>::save(self.store.as_ref(), updated.clone())
-									.await
-									.map_err(|err| {
-										self.refresh_metrics.record_failure();
-										Error::from(err)
-									})?;
-
-								updated
-							},
-						}
-					},
-				};
 
+				common::clear_rotation(self, key);
+				recorder.rotation(rotation);
+
+				if rotation != RotationOutcome::Superseded {
+					obs::record_refresh_bookkeeping(&current, &result);
+				}
+
+				singleflight.record_minted();
 				self.refresh_metrics.record_success();
 				Ok(result)
 			})
@@ -198,9 +354,49 @@ where
 			Err(_) => obs::record_flow_outcome(KIND, FlowOutcome::Failure),
 		}
 
+		common::report_call(self, summary, &result);
+
 		result
 	}
 
+	/// Records a refresh response, returning the record to serve and what happened to the refresh
+	/// token.
+	///
+	/// A swap that loses to a concurrent rotation serves the winner's record, and a record that
+	/// disappeared meanwhile is written back.
+	async fn settle_refresh(
+		&self,
+		pending: &PendingRotation,
+	) -> Result<(TokenRecord, RotationOutcome)> {
+		let PendingRotation { expected_refresh, scope, record, rotated } = pending;
+		let family = &record.family;
+		let outcome = if *rotated && self.descriptor.quirks.shared_family_refresh {
+			common::compare_and_swap_family_refresh(self, record, expected_refresh).await?
+		} else {
+			<dyn BrokerStore>::compare_and_swap_refresh(
+				self.store.as_ref(),
+				family,
+				scope,
+				Some(expected_refresh),
+				record.clone(),
+			)
+			.await?
+		};
+		let rotation = if *rotated { RotationOutcome::Rotated } else { RotationOutcome::Retained };
+
+		match outcome {
+			CompareAndSwapOutcome::Updated => {},
+			CompareAndSwapOutcome::Missing => common::persist_record(self, record).await?,
+			CompareAndSwapOutcome::RefreshMismatch | CompareAndSwapOutcome::VersionMismatch =>
+				match <dyn BrokerStore>::fetch(self.store.as_ref(), family, scope).await? {
+					Some(existing) => return Ok((existing, RotationOutcome::Superseded)),
+					None => common::persist_record(self, record).await?,
+				},
+		}
+
+		Ok((record.clone(), rotation))
+	}
+
 	fn ensure_refresh_supported(&self) -> Result<()> {
 		if self.descriptor.supports(GrantType::RefreshToken) {
 			Ok(())
@@ -0,0 +1,364 @@
+//! Provider registries: a configuration-driven registry holding one broker per provider, and
+//! per-call provider routing that lets a single broker serve many descriptors, wrapped by
+//! [`MultiBroker`].
+
+// self
+use crate::{
+	_prelude::*,
+	auth::ProviderId,
+	config::{self, BrokerConfig, ObservabilityConfig, PolicyConfig},
+	error::ConfigError,
+	flows::{Broker, CachedTokenRequest, FlowGate, ProviderCallTracker},
+	http::TokenHttpClient,
+	oauth::TransportErrorMapper,
+	obs::InstrumentedStore,
+	provider::{ProviderDescriptor, ProviderRegistry, ProviderStrategy},
+	store::BrokerStore,
+};
+#[cfg(feature = "reqwest")]
+use crate::{http::ReqwestHttpClient, oauth::ReqwestTransportErrorMapper};
+
+/// Descriptor, strategy, and client credentials for a provider registered on a shared broker.
+#[derive(Clone)]
+pub struct ProviderHandle {
+	/// Provider descriptor that defines OAuth endpoints and quirks.
+	pub descriptor: ProviderDescriptor,
+	/// Strategy responsible for provider-specific token request adjustments.
+	pub strategy: Arc<dyn ProviderStrategy>,
+	/// OAuth 2.0 client identifier registered with the provider.
+	pub client_id: String,
+	/// Optional client secret for confidential authentication methods.
+	pub client_secret: Option<String>,
+	calls: Arc<ProviderCallTracker>,
+}
+impl ProviderHandle {
+	/// Creates a handle for `descriptor` authenticated as `client_id`.
+	pub fn new(
+		descriptor: ProviderDescriptor,
+		strategy: Arc<dyn ProviderStrategy>,
+		client_id: impl Into<String>,
+	) -> Self {
+		Self {
+			descriptor,
+			strategy,
+			client_id: client_id.into(),
+			client_secret: None,
+			calls: Default::default(),
+		}
+	}
+
+	/// Sets the client secret used for confidential client auth modes.
+	pub fn with_client_secret(mut self, secret: impl Into<String>) -> Self {
+		self.client_secret = Some(secret.into());
+
+		self
+	}
+}
+impl Debug for ProviderHandle {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		f.debug_struct("ProviderHandle")
+			.field("descriptor", &self.descriptor)
+			.field("client_id", &self.client_id)
+			.field("client_secret_set", &self.client_secret.is_some())
+			.finish()
+	}
+}
+
+impl<C, M> Broker<C, M>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	/// Registers another provider served through this broker's transport, store, and
+	/// singleflight guards.
+	///
+	/// Requests opt in per call via [`CachedTokenRequest::for_provider`], or callers can take a
+	/// view with [`Broker::for_provider`]. Registrations are shared by every clone of the broker.
+	pub fn register_provider(&self, handle: ProviderHandle) -> Result<()> {
+		handle.descriptor.validate().map_err(ConfigError::from)?;
+
+		let id = handle.descriptor.id.clone();
+		let mut providers = self.providers.write();
+
+		if id == self.descriptor.id || providers.contains_key(&id) {
+			return Err(ConfigError::DuplicateProvider { provider: id.to_string() }.into());
+		}
+
+		providers.insert(id, Arc::new(handle));
+
+		Ok(())
+	}
+
+	/// Identifiers of the providers added through [`Broker::register_provider`].
+	pub fn registered_providers(&self) -> Vec<ProviderId> {
+		self.providers.read().keys().cloned().collect()
+	}
+
+	/// Returns a broker view that runs flows against `provider`.
+	///
+	/// The view shares this broker's transport, store, guard map, and settings; only the
+	/// descriptor, strategy, client credentials, and provider call history differ. The view's
+	/// descriptor is pointed at the broker's
+	/// [`ProviderEnvironment`](crate::provider::ProviderEnvironment). Asking for the broker's own
+	/// provider returns a plain clone.
+	pub fn for_provider(&self, provider: &ProviderId) -> Result<Self> {
+		if *provider == self.descriptor.id {
+			return Ok(self.clone());
+		}
+
+		let handle = self
+			.providers
+			.read()
+			.get(provider)
+			.cloned()
+			.ok_or_else(|| ConfigError::UnknownProvider { provider: provider.to_string() })?;
+		let mut view = self.clone();
+
+		view.descriptor = handle.descriptor.for_environment(self.descriptor.environment)?;
+		view.strategy = handle.strategy.clone();
+		view.client_id = handle.client_id.clone();
+		view.client_secret = handle.client_secret.clone();
+		view.provider_calls = handle.calls.clone();
+
+		Ok(view)
+	}
+
+	/// Resolves the view serving `request.provider`, or `None` when this broker serves it.
+	pub(crate) fn route(&self, request: &CachedTokenRequest) -> Result<Option<Self>> {
+		match &request.provider {
+			Some(provider) if *provider != self.descriptor.id =>
+				self.for_provider(provider).map(Some),
+			_ => Ok(None),
+		}
+	}
+}
+
+/// Broker that routes flow calls to several providers by [`ProviderId`].
+///
+/// A thin wrapper over [`Broker::register_provider`] and [`Broker::for_provider`]: every provider
+/// shares the wrapped broker's HTTP client, store, and singleflight guards, so tenants can target
+/// different providers from one object. The wrapped broker's own descriptor is the default
+/// provider.
+pub struct MultiBroker<C, M>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	broker: Broker<C, M>,
+}
+impl<C, M> MultiBroker<C, M>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	/// Wraps `broker` and registers every provider in `registry` on it.
+	pub fn new(broker: Broker<C, M>, registry: ProviderRegistry) -> Result<Self> {
+		for handle in registry.into_handles() {
+			broker.register_provider(handle)?;
+		}
+
+		Ok(Self { broker })
+	}
+
+	/// Registers another provider; see [`Broker::register_provider`].
+	pub fn register(&self, handle: ProviderHandle) -> Result<()> {
+		self.broker.register_provider(handle)
+	}
+
+	/// Identifier of the provider served when a call names none.
+	pub fn default_provider(&self) -> &ProviderId {
+		&self.broker.descriptor.id
+	}
+
+	/// Identifiers of every provider served, the default first and the rest in sorted order.
+	pub fn providers(&self) -> Vec<ProviderId> {
+		let mut registered = self.broker.registered_providers();
+
+		registered.sort();
+		registered.insert(0, self.default_provider().clone());
+
+		registered
+	}
+
+	/// Returns the broker view that runs flows against `provider`; see [`Broker::for_provider`].
+	pub fn for_provider(&self, provider: &ProviderId) -> Result<Broker<C, M>> {
+		self.broker.for_provider(provider)
+	}
+
+	/// Wrapped broker; it routes requests tagged with [`CachedTokenRequest::for_provider`] itself.
+	pub fn broker(&self) -> &Broker<C, M> {
+		&self.broker
+	}
+}
+impl<C, M> Clone for MultiBroker<C, M>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	fn clone(&self) -> Self {
+		Self { broker: self.broker.clone() }
+	}
+}
+impl<C, M> Debug for MultiBroker<C, M>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		f.debug_struct("MultiBroker").field("providers", &self.providers()).finish()
+	}
+}
+
+/// Set of brokers assembled from a [`BrokerConfig`].
+///
+/// Every broker shares the same token store, HTTP client, and transport mapper, so a service
+/// talking to many providers still holds a single connection pool and a single store handle.
+pub struct BrokerRegistry<C, M>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	store: Arc<dyn BrokerStore>,
+	brokers: BTreeMap<ProviderId, Broker<C, M>>,
+}
+impl<C, M> BrokerRegistry<C, M>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	/// Assembles brokers from `config` using the caller-provided transport + mapper pair.
+	///
+	/// Descriptors are validated because deserialization bypasses
+	/// [`ProviderDescriptorBuilder`](crate::provider::ProviderDescriptorBuilder) checks, and
+	/// client secrets are resolved from their environment or file references. Every broker gets
+	/// the configured policy and observability settings.
+	pub fn from_config_with_http_client(
+		config: BrokerConfig,
+		http_client: impl Into<Arc<C>>,
+		mapper: impl Into<Arc<M>>,
+	) -> Result<Self> {
+		let mut store = config.store.build()?;
+
+		if config.observability.instrument_store {
+			store = Arc::new(InstrumentedStore::new(store));
+		}
+
+		let http_client = http_client.into();
+		let mapper = mapper.into();
+		let mut brokers = BTreeMap::new();
+
+		for provider in config.providers {
+			provider.descriptor.validate().map_err(ConfigError::from)?;
+
+			let id = provider.descriptor.id.clone();
+
+			if brokers.contains_key(&id) {
+				return Err(ConfigError::DuplicateProvider { provider: id.to_string() }.into());
+			}
+
+			let mut broker = Broker::with_http_client(
+				store.clone(),
+				provider.descriptor.for_environment(config.environment)?,
+				provider.strategy.build(),
+				provider.client_id,
+				http_client.clone(),
+				mapper.clone(),
+			);
+
+			if let Some(secret) = provider.client_secret.as_ref() {
+				broker = broker.with_client_secret(secret.resolve()?);
+			}
+
+			broker = configure(broker, &config.policy, &config.observability);
+
+			broker.check_client_auth()?;
+			broker.diagnose();
+
+			brokers.insert(id, broker);
+		}
+
+		Ok(Self { store, brokers })
+	}
+
+	/// Returns the broker registered for `provider`, if any.
+	pub fn get(&self, provider: &str) -> Option<&Broker<C, M>> {
+		self.brokers.get(provider)
+	}
+
+	/// Iterator over the registered provider identifiers.
+	pub fn providers(&self) -> impl Iterator<Item = &ProviderId> {
+		self.brokers.keys()
+	}
+
+	/// Shared token store used by every broker in the registry.
+	pub fn store(&self) -> &Arc<dyn BrokerStore> {
+		&self.store
+	}
+}
+#[cfg(feature = "reqwest")]
+impl BrokerRegistry<ReqwestHttpClient, ReqwestTransportErrorMapper> {
+	/// Assembles brokers from `config` using the crate's default reqwest transport.
+	pub fn from_config(config: BrokerConfig) -> Result<Self> {
+		Self::from_config_with_http_client(
+			config,
+			ReqwestHttpClient::default(),
+			ReqwestTransportErrorMapper,
+		)
+	}
+}
+impl<C, M> Debug for BrokerRegistry<C, M>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		f.debug_struct("BrokerRegistry").field("providers", &self.brokers.keys()).finish()
+	}
+}
+
+fn configure<C, M>(
+	mut broker: Broker<C, M>,
+	policy: &PolicyConfig,
+	observability: &ObservabilityConfig,
+) -> Broker<C, M>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	broker = broker.with_overrides(|overrides| {
+		overrides.timeout = policy.timeout_secs.map(config::seconds);
+		overrides.preemptive_window = policy.preemptive_window_secs.map(config::seconds);
+		overrides.min_ttl = policy.min_ttl_secs.map(config::seconds);
+		overrides.allow_stale_on_timeout = policy.allow_stale_on_timeout;
+		overrides.call_summaries = observability.call_summaries;
+	});
+
+	if let Some(secs) = policy.singleflight_wait_secs {
+		broker = broker.with_singleflight_wait(config::seconds(secs));
+	}
+	if let Some(limit) = policy.max_queued_callers {
+		broker = broker.with_max_queued_callers(limit);
+	}
+	if let Some(secs) = policy.revoked_retention_secs {
+		broker = broker.with_revoked_retention(config::seconds(secs));
+	}
+	if let Some(secs) = observability.served_tracking_secs {
+		broker = broker.with_served_tracking(config::seconds(secs));
+	}
+	if !policy.disabled_flows.is_empty() || !policy.disabled_tenant_flows.is_empty() {
+		let mut gate = FlowGate::new();
+
+		for flow in &policy.disabled_flows {
+			gate = gate.disable(*flow);
+		}
+		for (tenant, flows) in &policy.disabled_tenant_flows {
+			for flow in flows {
+				gate = gate.disable_for_tenant(tenant.clone(), *flow);
+			}
+		}
+
+		broker = broker.with_flow_policy(Arc::new(gate));
+	}
+
+	broker
+}
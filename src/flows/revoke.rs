@@ -0,0 +1,186 @@
+//! Local revocation helpers that tag records with a [`RevocationReason`].
+//!
+//! Revocation runs under the same per-`StoreKey` singleflight guard as the token flows, so a
+//! concurrent refresh cannot resurrect a record the caller just revoked. Every affected record
+//! emits an audit event through [`obs::record_revocation`].
+//!
+//! [`Broker::revoke_all`] and [`Broker::revoke_tenant`] revoke every cached scope combination
+//! of a family or a whole tenant without the caller knowing each
+//! [`ScopeSet`](crate::auth::ScopeSet).
+//!
+//! [`Broker::forget_tenant`] goes further for right-to-be-forgotten requests: it deletes every
+//! record of a tenant and destroys the tenant's key material in encrypting stores.
+
+// std
+use std::collections::HashSet;
+// self
+use crate::{
+	_prelude::*,
+	auth::{PrincipalPath, RevocationReason, TenantId, TokenFamily, TokenRecord},
+	flows::{Broker, CachedTokenRequest, common},
+	http::TokenHttpClient,
+	oauth::TransportErrorMapper,
+	obs,
+	store::BrokerStore,
+};
+
+/// Summary of a [`Broker::forget_tenant`] erasure.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TenantErasure {
+	/// Tenant whose data was erased.
+	pub tenant: TenantId,
+	/// Number of records revoked and deleted.
+	pub deleted: usize,
+	/// Whether the store destroyed per-tenant key material.
+	pub keys_destroyed: bool,
+}
+
+impl<C, M> Broker<C, M>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	/// Revokes the cached record matching `request`, returning it when one existed.
+	///
+	/// `audience`/`resource` extra parameters select the same cache partition the token flows
+	/// use.
+	pub async fn revoke(
+		&self,
+		request: CachedTokenRequest,
+		reason: RevocationReason,
+	) -> Result<Option<TokenRecord>> {
+		if let Some(view) = self.route(&request)? {
+			return Box::pin(view.revoke(request, reason)).await;
+		}
+
+		let extra_params = common::merge_extra_params(BTreeMap::new(), &request);
+		let token_key = common::token_key(self, &request, &extra_params);
+		let family = token_key.family();
+		let key = token_key.store_key();
+
+		request.cancellation.check("acquiring the singleflight guard")?;
+
+		let _singleflight =
+			common::acquire_singleflight(self, key, "revoke", &request.cancellation).await?;
+
+		request.cancellation.check("revoking the record")?;
+
+		common::revoke_record(self, family, &request.scope, OffsetDateTime::now_utc(), reason).await
+	}
+
+	/// Revokes every cached scope combination of `family`, returning the newly revoked records.
+	///
+	/// Use it to disable a compromised principal. Records that were already revoked keep their
+	/// original reason. Provider-side grants are not contacted.
+	pub async fn revoke_all(
+		&self,
+		family: &TokenFamily,
+		reason: RevocationReason,
+	) -> Result<Vec<TokenRecord>> {
+		let revoked = <dyn BrokerStore>::revoke_all(
+			self.store.as_ref(),
+			family,
+			OffsetDateTime::now_utc(),
+			reason,
+		)
+		.await?;
+
+		for record in &revoked {
+			obs::record_revocation(record, reason);
+		}
+
+		Ok(revoked)
+	}
+
+	/// Revokes every record of `tenant` across principals, providers, audiences, and scopes,
+	/// returning the newly revoked records.
+	///
+	/// Unlike [`forget_tenant`](Self::forget_tenant), the records are kept for audit. Requires a
+	/// store that supports [`BrokerStore::list_records`].
+	pub async fn revoke_tenant(
+		&self,
+		tenant: &TenantId,
+		reason: RevocationReason,
+	) -> Result<Vec<TokenRecord>> {
+		const PAGE: usize = 200;
+
+		let store = self.store.as_ref();
+		let mut families = HashSet::new();
+		let mut after = None;
+
+		loop {
+			let page = <dyn BrokerStore>::list_records(store, after.as_deref(), PAGE).await?;
+
+			families.extend(
+				page.records
+					.into_iter()
+					.filter(|record| record.family.tenant == *tenant && !record.is_revoked())
+					.map(|record| record.family),
+			);
+
+			match page.next {
+				Some(next) => after = Some(next),
+				None => break,
+			}
+		}
+
+		let mut revoked = Vec::new();
+
+		for family in &families {
+			revoked.extend(self.revoke_all(family, reason).await?);
+		}
+
+		Ok(revoked)
+	}
+
+	/// Revokes every record for `tenant` whose principal is `prefix` or lives below it.
+	pub async fn revoke_principal_subtree(
+		&self,
+		tenant: &TenantId,
+		prefix: &PrincipalPath,
+		reason: RevocationReason,
+	) -> Result<Vec<TokenRecord>> {
+		let revoked = <dyn BrokerStore>::revoke_principal_subtree(
+			self.store.as_ref(),
+			tenant,
+			prefix,
+			OffsetDateTime::now_utc(),
+			reason,
+		)
+		.await?;
+
+		for record in &revoked {
+			obs::record_revocation(record, reason);
+		}
+
+		Ok(revoked)
+	}
+
+	/// Erases every trace of `tenant` for right-to-be-forgotten requests.
+	///
+	/// Each record is revoked (emitting the usual revocation audit event) and deleted, staged
+	/// writes for the tenant are dropped, and stores that encrypt per tenant destroy the tenant's
+	/// key material so backups and exports of its records become unreadable. Provider-side grants
+	/// are not contacted. Stop issuing requests for the tenant first, for example through a
+	/// [`FlowPolicy`](crate::flows::FlowPolicy), so no in-flight flow writes a fresh record after
+	/// the erasure.
+	pub async fn forget_tenant(&self, tenant: &TenantId) -> Result<TenantErasure> {
+		let store = self.store.as_ref();
+		let now = OffsetDateTime::now_utc();
+		let deleted = <dyn BrokerStore>::delete_tenant(store, tenant).await?;
+
+		for mut record in deleted.iter().cloned() {
+			if !record.is_revoked() {
+				record.revoke(now, RevocationReason::AdminAction);
+
+				obs::record_revocation(&record, RevocationReason::AdminAction);
+			}
+		}
+
+		let keys_destroyed = <dyn BrokerStore>::destroy_tenant_keys(store, tenant).await?;
+
+		obs::record_tenant_erasure(tenant, deleted.len(), keys_destroyed);
+
+		Ok(TenantErasure { tenant: tenant.clone(), deleted: deleted.len(), keys_destroyed })
+	}
+}
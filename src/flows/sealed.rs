@@ -0,0 +1,73 @@
+//! Broker view that hands out sealed records instead of plaintext secrets.
+//!
+//! A broker deployed as its own service still caches, refreshes, and persists plaintext records;
+//! [`Broker::sealed_for`] only changes what leaves the process, so every consumer gets
+//! [`SealedTokenRecord`]s that only its private key can open.
+
+// self
+use crate::{
+	_prelude::*,
+	auth::{RecipientKey, SealedTokenRecord},
+	flows::{AuthorizationSession, Broker, CachedTokenRequest},
+	http::TokenHttpClient,
+	oauth::TransportErrorMapper,
+};
+
+/// [`Broker`] clone whose flows return records sealed to one [`RecipientKey`].
+pub struct SealedBroker<C, M>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	broker: Broker<C, M>,
+	recipient: RecipientKey,
+}
+impl<C, M> SealedBroker<C, M>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	/// Key every returned record is sealed to.
+	pub fn recipient(&self) -> &RecipientKey {
+		&self.recipient
+	}
+
+	/// Sealed counterpart of [`Broker::client_credentials`].
+	pub async fn client_credentials(
+		&self,
+		request: CachedTokenRequest,
+	) -> Result<SealedTokenRecord> {
+		Ok(self.broker.client_credentials(request).await?.seal(&self.recipient)?)
+	}
+
+	/// Sealed counterpart of [`Broker::refresh_access_token`].
+	pub async fn refresh_access_token(
+		&self,
+		request: CachedTokenRequest,
+	) -> Result<SealedTokenRecord> {
+		Ok(self.broker.refresh_access_token(request).await?.seal(&self.recipient)?)
+	}
+
+	/// Sealed counterpart of [`Broker::exchange_code`].
+	pub async fn exchange_code(
+		&self,
+		session: AuthorizationSession,
+		authorization_code: impl AsRef<str>,
+	) -> Result<SealedTokenRecord> {
+		Ok(self.broker.exchange_code(session, authorization_code).await?.seal(&self.recipient)?)
+	}
+}
+
+impl<C, M> Broker<C, M>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	/// Returns a view of this broker whose flows seal access and refresh tokens to `recipient`.
+	///
+	/// The view shares the store, caches, and singleflight guards with `self`; only the records
+	/// handed back to the caller are encrypted.
+	pub fn sealed_for(&self, recipient: RecipientKey) -> SealedBroker<C, M> {
+		SealedBroker { broker: self.clone(), recipient }
+	}
+}
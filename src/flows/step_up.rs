@@ -0,0 +1,221 @@
+//! Step-up authorization driven by `insufficient_scope` challenges.
+//!
+//! Resource servers signal missing scopes through an RFC 6750 `WWW-Authenticate: Bearer`
+//! challenge. [`BearerChallenge::parse`] extracts the required scopes from that header (the token
+//! endpoint mapping does the same for captured provider headers), and [`Broker::step_up`] turns
+//! the resulting [`Error::InsufficientScope`] into a follow-up [`AuthorizationSession`] that asks
+//! only for the scopes the caller does not already hold.
+
+// self
+use crate::{
+	_prelude::*,
+	auth::{PrincipalId, ScopeSet, TenantId},
+	error::ConfigError,
+	flows::{AuthorizationSession, Broker, CachedTokenRequest},
+	http::TokenHttpClient,
+	oauth::TransportErrorMapper,
+};
+
+/// Parsed RFC 6750 `Bearer` challenge from a `WWW-Authenticate` header.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BearerChallenge {
+	/// `error` parameter (e.g., `insufficient_scope`).
+	pub error: Option<String>,
+	/// `error_description` parameter.
+	pub error_description: Option<String>,
+	/// `scope` parameter listing the scopes required by the protected resource.
+	pub scope: Option<ScopeSet>,
+}
+impl BearerChallenge {
+	/// Parses the first `Bearer` challenge in `header`, returning `None` when there is none.
+	///
+	/// Malformed or invalid `scope` values are ignored rather than failing the parse.
+	pub fn parse(header: &str) -> Option<Self> {
+		let mut challenge = None;
+		let mut rest = header.trim_start();
+
+		while !rest.is_empty() {
+			let (token, after) = split_token(rest);
+
+			rest = after.trim_start();
+
+			if let Some(value) = rest.strip_prefix('=') {
+				let (value, after) = split_value(value.trim_start());
+
+				rest = after.trim_start().trim_start_matches(',').trim_start();
+
+				if let Some(challenge) = challenge.as_mut() {
+					apply_param(challenge, token, value);
+				}
+			} else if challenge.is_some() {
+				// A new auth-scheme starts; the Bearer challenge is complete.
+				break;
+			} else {
+				if token.eq_ignore_ascii_case("bearer") {
+					challenge = Some(Self::default());
+				}
+
+				rest = rest.trim_start_matches(',').trim_start();
+
+				if token.is_empty() {
+					break;
+				}
+			}
+		}
+
+		challenge
+	}
+
+	/// Converts the challenge into [`Error::InsufficientScope`] when it signals
+	/// `insufficient_scope`.
+	pub fn into_insufficient_scope(self) -> Option<Error> {
+		if self.error.as_deref() != Some("insufficient_scope") {
+			return None;
+		}
+
+		let reason = self
+			.error_description
+			.unwrap_or_else(|| "Resource server returned an insufficient_scope challenge".into());
+
+		Some(Error::InsufficientScope { reason, required: self.scope })
+	}
+}
+
+/// Tenant, principal, and granted scopes that a step-up request builds on.
+#[derive(Clone, Debug)]
+pub struct StepUpOrigin {
+	/// Tenant identifier tied to the original request.
+	pub tenant: TenantId,
+	/// Principal identifier tied to the original request.
+	pub principal: PrincipalId,
+	/// Scopes the caller already holds.
+	pub granted: ScopeSet,
+}
+impl From<&AuthorizationSession> for StepUpOrigin {
+	fn from(session: &AuthorizationSession) -> Self {
+		Self {
+			tenant: session.tenant.clone(),
+			principal: session.principal.clone(),
+			granted: session.scope.clone(),
+		}
+	}
+}
+impl From<&CachedTokenRequest> for StepUpOrigin {
+	fn from(request: &CachedTokenRequest) -> Self {
+		Self {
+			tenant: request.tenant.clone(),
+			principal: request.principal.clone(),
+			granted: request.scope.clone(),
+		}
+	}
+}
+
+impl<C, M> Broker<C, M>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	/// Starts a follow-up authorization for the scopes `error` reports as missing.
+	///
+	/// Returns `Ok(None)` when `error` is not an [`Error::InsufficientScope`] carrying the
+	/// required scopes, or when `origin` already holds all of them. Otherwise the returned
+	/// session requests only the missing scopes, as produced by
+	/// [`Broker::start_authorization`].
+	pub fn step_up(
+		&self,
+		origin: impl Into<StepUpOrigin>,
+		error: &Error,
+		redirect_uri: Url,
+	) -> Result<Option<AuthorizationSession>> {
+		let Error::InsufficientScope { required: Some(required), .. } = error else {
+			return Ok(None);
+		};
+		let origin = origin.into();
+		let missing =
+			ScopeSet::new(required.iter().filter(|scope| !origin.granted.contains(scope)))
+				.map_err(ConfigError::from)?;
+
+		if missing.is_empty() {
+			return Ok(None);
+		}
+
+		self.start_authorization(origin.tenant, origin.principal, missing, redirect_uri).map(Some)
+	}
+}
+
+fn split_token(input: &str) -> (&str, &str) {
+	let end = input
+		.find(|c: char| c.is_ascii_whitespace() || c == '=' || c == ',')
+		.unwrap_or(input.len());
+
+	input.split_at(end)
+}
+
+fn split_value(input: &str) -> (String, &str) {
+	let Some(quoted) = input.strip_prefix('"') else {
+		let (token, rest) = split_token(input);
+
+		return (token.to_owned(), rest);
+	};
+	let mut value = String::new();
+	let mut chars = quoted.char_indices();
+
+	while let Some((idx, c)) = chars.next() {
+		match c {
+			'\\' =>
+				if let Some((_, escaped)) = chars.next() {
+					value.push(escaped);
+				},
+			'"' => return (value, &quoted[idx + 1..]),
+			_ => value.push(c),
+		}
+	}
+
+	(value, "")
+}
+
+fn apply_param(challenge: &mut BearerChallenge, name: &str, value: String) {
+	if name.eq_ignore_ascii_case("error") {
+		challenge.error = Some(value);
+	} else if name.eq_ignore_ascii_case("error_description") {
+		challenge.error_description = Some(value);
+	} else if name.eq_ignore_ascii_case("scope") {
+		challenge.scope = ScopeSet::new(value.split_ascii_whitespace()).ok();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	// self
+	use super::*;
+
+	#[test]
+	fn parses_bearer_challenge_among_other_schemes() {
+		let challenge = BearerChallenge::parse(
+			"Basic realm=\"legacy\", Bearer realm=\"api\", error=\"insufficient_scope\", \
+			 error_description=\"Needs \\\"write\\\" access\", scope=\"files.write files.read\", \
+			 DPoP algs=\"ES256\"",
+		)
+		.expect("Bearer challenge should be found.");
+
+		assert_eq!(challenge.error.as_deref(), Some("insufficient_scope"));
+		assert_eq!(challenge.error_description.as_deref(), Some("Needs \"write\" access"));
+		assert_eq!(
+			challenge.scope,
+			Some(
+				ScopeSet::new(["files.read", "files.write"])
+					.expect("Scope fixture should be valid.")
+			)
+		);
+	}
+
+	#[test]
+	fn non_bearer_or_other_errors_yield_no_insufficient_scope() {
+		assert!(BearerChallenge::parse("Basic realm=\"legacy\"").is_none());
+
+		let challenge = BearerChallenge::parse("Bearer error=invalid_token")
+			.expect("Bearer challenge should be found.");
+
+		assert!(challenge.into_insufficient_scope().is_none());
+	}
+}
@@ -0,0 +1,396 @@
+//! Deploy-time dry-run validation for broker configuration.
+//!
+//! [`Broker::validate`] never mints tokens. It cross-checks the descriptor against the
+//! configured client credentials, optionally compares the descriptor's endpoints and
+//! capabilities (grants, client authentication, PKCE) with the provider's discovery document,
+//! and optionally issues a bodiless request to the token endpoint to confirm it is reachable.
+//! Every problem is collected into a [`ValidationReport`] instead of failing fast so deploy
+//! pipelines can print the full picture at once.
+
+// self
+use crate::{
+	_prelude::*,
+	error::ConfigError,
+	flows::Broker,
+	http::{self, TokenHttpClient},
+	oauth::TransportErrorMapper,
+	obs::{Diagnostic, DiagnosticKind},
+	provider::{ClientAuthMethod, DiscoveryDocument, GrantType, ProviderEndpoint},
+};
+
+/// Severity attached to a [`ValidationFinding`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidationSeverity {
+	/// The configuration works but is probably not what the operator intended.
+	Warning,
+	/// The configuration will fail at runtime.
+	Error,
+}
+
+/// Area of the configuration a [`ValidationFinding`] refers to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidationCheck {
+	/// Client authentication method versus configured credentials.
+	ClientAuthentication,
+	/// Grant flags enabled on the descriptor.
+	Grants,
+	/// Comparison against the provider's discovery document.
+	Discovery,
+	/// Grants, client authentication, and PKCE support advertised by the discovery document.
+	Capabilities,
+	/// Reachability of the token endpoint.
+	TokenEndpoint,
+}
+
+/// Options controlling which remote checks [`Broker::validate`] performs.
+#[derive(Clone, Debug, Default)]
+pub struct ValidationOptions {
+	/// Discovery document URL to compare endpoints against; skipped when `None`.
+	pub discovery_url: Option<Url>,
+	/// Issues a bodiless `GET` to the token endpoint to confirm it is reachable.
+	pub probe_token_endpoint: bool,
+}
+impl ValidationOptions {
+	/// Enables the discovery comparison using the provided metadata URL.
+	pub fn with_discovery_url(mut self, url: Url) -> Self {
+		self.discovery_url = Some(url);
+
+		self
+	}
+
+	/// Enables the token endpoint reachability probe.
+	pub fn with_token_endpoint_probe(mut self) -> Self {
+		self.probe_token_endpoint = true;
+
+		self
+	}
+}
+
+/// Single problem detected by [`Broker::validate`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationFinding {
+	/// Severity of the problem.
+	pub severity: ValidationSeverity,
+	/// Configuration area the problem belongs to.
+	pub check: ValidationCheck,
+	/// Human-readable explanation.
+	pub message: String,
+}
+
+/// Structured result returned by [`Broker::validate`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationReport {
+	/// Every finding collected during validation, in check order.
+	pub findings: Vec<ValidationFinding>,
+	/// HTTP status returned by the token endpoint probe, when it ran and got a response.
+	pub token_endpoint_status: Option<u16>,
+	/// Indicates whether the discovery document was fetched and compared.
+	pub discovery_checked: bool,
+}
+impl ValidationReport {
+	/// Returns `true` when no error-level findings were recorded.
+	pub fn is_ok(&self) -> bool {
+		self.errors().next().is_none()
+	}
+
+	/// Iterator over error-level findings.
+	pub fn errors(&self) -> impl Iterator<Item = &ValidationFinding> {
+		self.findings.iter().filter(|finding| finding.severity == ValidationSeverity::Error)
+	}
+
+	/// Iterator over warning-level findings.
+	pub fn warnings(&self) -> impl Iterator<Item = &ValidationFinding> {
+		self.findings.iter().filter(|finding| finding.severity == ValidationSeverity::Warning)
+	}
+
+	fn push(
+		&mut self,
+		severity: ValidationSeverity,
+		check: ValidationCheck,
+		message: impl Into<String>,
+	) {
+		self.findings.push(ValidationFinding { severity, check, message: message.into() });
+	}
+}
+
+impl<C, M> Broker<C, M>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	/// Performs a dry-run validation of the broker configuration.
+	///
+	/// Local checks always run. Remote checks run only when enabled through `options`, and
+	/// none of them ever submit a grant.
+	pub async fn validate(&self, options: ValidationOptions) -> ValidationReport {
+		let mut report = ValidationReport::default();
+
+		self.validate_client_authentication(&mut report);
+		self.validate_grants(&mut report);
+
+		if let Some(url) = options.discovery_url.as_ref() {
+			self.validate_discovery(url, &mut report).await;
+		}
+		if options.probe_token_endpoint {
+			self.probe_token_endpoint(&mut report).await;
+		}
+
+		report
+	}
+
+	/// Checks that the configured client secret matches the descriptor's client authentication.
+	///
+	/// Fails with [`ConfigError::MissingClientSecret`] when an enabled grant authenticates with
+	/// `client_secret_basic` or `client_secret_post` but no secret is set, and with
+	/// [`ConfigError::UnusedClientSecret`] when a secret is set although every grant uses
+	/// `none` (the secret would otherwise be silently dropped). Called when
+	/// [`BrokerRegistry`](crate::flows::BrokerRegistry) assembles brokers and by
+	/// [`Broker::self_check`]; brokers built by hand can call it right after
+	/// [`Broker::with_client_secret`].
+	pub fn check_client_auth(&self) -> Result<(), ConfigError> {
+		let descriptor = &self.descriptor;
+
+		// Assertion grants authenticate through the signed JWT, so a secret is only needed when
+		// a grant that relies on client authentication is enabled.
+		let secret_grant =
+			[GrantType::AuthorizationCode, GrantType::RefreshToken, GrantType::ClientCredentials]
+				.into_iter()
+				.filter(|grant| descriptor.supports(*grant))
+				.map(|grant| (grant, descriptor.client_auth_method(grant)))
+				.find(|(_, method)| *method != ClientAuthMethod::NoneWithPkce);
+		let always_public = descriptor.preferred_client_auth_method
+			== ClientAuthMethod::NoneWithPkce
+			&& descriptor
+				.grant_client_auth_methods
+				.values()
+				.all(|method| *method == ClientAuthMethod::NoneWithPkce);
+
+		match (secret_grant, &self.client_secret) {
+			(Some((grant, method)), None) => Err(ConfigError::MissingClientSecret {
+				descriptor: descriptor.id.to_string(),
+				grant: grant.as_str(),
+				method: method.as_str(),
+			}),
+			(_, Some(_)) if always_public =>
+				Err(ConfigError::UnusedClientSecret { descriptor: descriptor.id.to_string() }),
+			_ => Ok(()),
+		}
+	}
+
+	/// Reports non-fatal configuration findings to [`Broker::diagnostics`] and returns every
+	/// finding recorded so far, including those flows reported at runtime.
+	///
+	/// Flags a client secret that some enabled grants never send because they authenticate with
+	/// `none`, and
+	/// [`ProviderQuirks::cascade_revocation`](crate::provider::ProviderQuirks::cascade_revocation)
+	/// without a usable revocation endpoint. Mismatches that would break a flow are errors from
+	/// [`Broker::check_client_auth`] instead.
+	pub fn diagnose(&self) -> Vec<Diagnostic> {
+		let descriptor = &self.descriptor;
+		let report = |kind, message: String| {
+			self.diagnostics.report(Diagnostic::new(kind, descriptor.id.clone(), message));
+		};
+
+		if self.client_secret.is_some() && self.check_client_auth().is_ok() {
+			for grant in [
+				GrantType::AuthorizationCode,
+				GrantType::RefreshToken,
+				GrantType::ClientCredentials,
+			] {
+				if descriptor.supports(grant)
+					&& descriptor.client_auth_method(grant) == ClientAuthMethod::NoneWithPkce
+				{
+					report(
+						DiagnosticKind::IgnoredClientSecret,
+						format!(
+							"The {} grant authenticates with none, so the client secret is not sent.",
+							grant.as_str()
+						),
+					);
+				}
+			}
+		}
+		if descriptor.quirks.cascade_revocation {
+			self.diagnose_revocation_endpoint();
+		}
+
+		self.diagnostics.entries()
+	}
+
+	/// Reports cascade revocation that cannot reach the provider's revocation endpoint.
+	pub(crate) fn diagnose_revocation_endpoint(&self) {
+		if self.endpoint_enabled(ProviderEndpoint::Revocation) {
+			return;
+		}
+
+		let message = if self.descriptor.endpoints.revocation.is_none() {
+			"Cascade revocation is enabled, but the descriptor declares no revocation endpoint; access tokens are only revoked locally."
+		} else {
+			"Cascade revocation is enabled, but the revocation endpoint is switched off; access tokens are only revoked locally."
+		};
+
+		self.diagnostics.report(Diagnostic::new(
+			DiagnosticKind::MissingRevocationEndpoint,
+			self.descriptor.id.clone(),
+			message,
+		));
+	}
+
+	fn validate_client_authentication(&self, report: &mut ValidationReport) {
+		match self.check_client_auth() {
+			Err(e @ ConfigError::UnusedClientSecret { .. }) => report.push(
+				ValidationSeverity::Warning,
+				ValidationCheck::ClientAuthentication,
+				e.to_string(),
+			),
+			Err(e) => report.push(
+				ValidationSeverity::Error,
+				ValidationCheck::ClientAuthentication,
+				e.to_string(),
+			),
+			Ok(()) => {},
+		}
+
+		let descriptor = &self.descriptor;
+
+		if descriptor.client_auth_method(GrantType::ClientCredentials)
+			== ClientAuthMethod::NoneWithPkce
+			&& descriptor.supports(GrantType::ClientCredentials)
+		{
+			report.push(
+				ValidationSeverity::Error,
+				ValidationCheck::ClientAuthentication,
+				"The client_credentials grant requires a confidential client authentication method.",
+			);
+		}
+	}
+
+	fn validate_grants(&self, report: &mut ValidationReport) {
+		if self.descriptor.supports(GrantType::RefreshToken)
+			&& !self.descriptor.supports(GrantType::AuthorizationCode)
+		{
+			report.push(
+				ValidationSeverity::Warning,
+				ValidationCheck::Grants,
+				"The refresh_token grant is enabled without authorization_code, so refresh tokens must be seeded externally.",
+			);
+		}
+	}
+
+	async fn validate_discovery(&self, url: &Url, report: &mut ValidationReport) {
+		let response = match http::get(self.http_client.as_ref(), url).await {
+			Ok(response) => response,
+			Err(e) => {
+				report.push(
+					ValidationSeverity::Error,
+					ValidationCheck::Discovery,
+					format!("Failed to fetch the discovery document from {url}: {e}."),
+				);
+
+				return;
+			},
+		};
+
+		if !response.status().is_success() {
+			report.push(
+				ValidationSeverity::Error,
+				ValidationCheck::Discovery,
+				format!(
+					"Discovery document request to {url} returned HTTP {}.",
+					response.status().as_u16()
+				),
+			);
+
+			return;
+		}
+
+		let document = match DiscoveryDocument::from_json(response.body()) {
+			Ok(document) => document,
+			Err(e) => {
+				report.push(ValidationSeverity::Error, ValidationCheck::Discovery, e.to_string());
+
+				return;
+			},
+		};
+		let endpoints = &self.descriptor.endpoints;
+
+		report.discovery_checked = true;
+
+		compare_endpoint(
+			report,
+			"authorization",
+			Some(&endpoints.authorization),
+			document.authorization_endpoint.as_ref(),
+		);
+		compare_endpoint(report, "token", Some(&endpoints.token), document.token_endpoint.as_ref());
+		compare_endpoint(
+			report,
+			"revocation",
+			endpoints.revocation.as_ref(),
+			document.revocation_endpoint.as_ref(),
+		);
+		compare_endpoint(report, "jwks", endpoints.jwks.as_ref(), document.jwks_uri.as_ref());
+		compare_endpoint(
+			report,
+			"backchannel_authentication",
+			endpoints.backchannel_authentication.as_ref(),
+			document.backchannel_authentication_endpoint.as_ref(),
+		);
+		compare_endpoint(
+			report,
+			"introspection",
+			endpoints.introspection.as_ref(),
+			document.introspection_endpoint.as_ref(),
+		);
+
+		for mismatch in document.capability_mismatches(&self.descriptor) {
+			let severity = if mismatch.is_error() {
+				ValidationSeverity::Error
+			} else {
+				ValidationSeverity::Warning
+			};
+
+			report.push(severity, ValidationCheck::Capabilities, mismatch.to_string());
+		}
+	}
+
+	async fn probe_token_endpoint(&self, report: &mut ValidationReport) {
+		let url = &self.descriptor.endpoints.token;
+
+		match http::get(self.http_client.as_ref(), url).await {
+			Ok(response) => report.token_endpoint_status = Some(response.status().as_u16()),
+			Err(e) => report.push(
+				ValidationSeverity::Error,
+				ValidationCheck::TokenEndpoint,
+				format!("Token endpoint {url} is unreachable: {e}."),
+			),
+		}
+	}
+}
+
+fn compare_endpoint(
+	report: &mut ValidationReport,
+	name: &str,
+	configured: Option<&Url>,
+	advertised: Option<&Url>,
+) {
+	match (configured, advertised) {
+		(Some(configured), Some(advertised)) if configured != advertised => report.push(
+			ValidationSeverity::Error,
+			ValidationCheck::Discovery,
+			format!(
+				"The {name} endpoint {configured} does not match the discovered value {advertised}."
+			),
+		),
+		(Some(configured), None) => report.push(
+			ValidationSeverity::Warning,
+			ValidationCheck::Discovery,
+			format!(
+				"The {name} endpoint {configured} is not advertised by the discovery document."
+			),
+		),
+		_ => {},
+	}
+}
@@ -0,0 +1,86 @@
+//! Batch prefetch of cached tokens for service startup and scheduled prewarming.
+//!
+//! [`Broker::warm`] drives the same cached flows callers use at request time, so warmed records
+//! share their singleflight guards and preemptive windows. Requests run concurrently up to
+//! [`Broker::warm_concurrency`] without spawning tasks, which keeps the helper runtime-agnostic.
+
+// std
+use std::task::Poll;
+// self
+use crate::{
+	_prelude::*,
+	auth::TokenRecord,
+	error::ConfigError,
+	flows::{Broker, CachedTokenRequest},
+	http::TokenHttpClient,
+	oauth::TransportErrorMapper,
+	provider::GrantType,
+};
+
+impl<C, M> Broker<C, M>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	/// Ensures a fresh cached token for every request, returning one result per request in input
+	/// order.
+	///
+	/// Descriptors that enable `client_credentials` mint through
+	/// [`Broker::client_credentials`]; otherwise records are renewed through
+	/// [`Broker::refresh_access_token`], which requires a previously stored refresh token.
+	/// A failing request does not stop the rest of the batch.
+	pub async fn warm(
+		&self,
+		requests: impl IntoIterator<Item = CachedTokenRequest>,
+	) -> Vec<Result<TokenRecord>> {
+		let limit = self.warm_concurrency.max(1);
+		let mut queued = requests.into_iter().enumerate();
+		let mut results = Vec::new();
+		let mut in_flight = Vec::with_capacity(limit);
+
+		loop {
+			while in_flight.len() < limit {
+				let Some((index, request)) = queued.next() else {
+					break;
+				};
+
+				results.push(None);
+				in_flight.push((index, Box::pin(self.warm_one(request))));
+			}
+
+			if in_flight.is_empty() {
+				break;
+			}
+
+			let (position, result) = std::future::poll_fn(|cx| {
+				for (position, (_, flow)) in in_flight.iter_mut().enumerate() {
+					if let Poll::Ready(result) = flow.as_mut().poll(cx) {
+						return Poll::Ready((position, result));
+					}
+				}
+
+				Poll::Pending
+			})
+			.await;
+			let (index, _) = in_flight.swap_remove(position);
+
+			results[index] = Some(result);
+		}
+
+		results.into_iter().flatten().collect()
+	}
+
+	async fn warm_one(&self, request: CachedTokenRequest) -> Result<TokenRecord> {
+		if self.descriptor.supports(GrantType::ClientCredentials) {
+			self.client_credentials(request).await
+		} else if self.descriptor.supports(GrantType::RefreshToken) {
+			self.refresh_access_token(request).await
+		} else {
+			Err(ConfigError::UnsupportedGrant {
+				descriptor: self.descriptor.id.to_string(),
+				grant: "client_credentials",
+			}
+			.into())
+		}
+	}
+}
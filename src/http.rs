@@ -10,11 +10,14 @@
 // std
 use std::ops::Deref;
 // crates.io
-use oauth2::{AsyncHttpClient, HttpClientError, HttpRequest, HttpResponse};
-#[cfg(feature = "reqwest")] use reqwest::header::{HeaderMap, RETRY_AFTER};
-#[cfg(feature = "reqwest")] use time::format_description::well_known::Rfc2822;
+use oauth2::{
+	AsyncHttpClient, HttpClientError, HttpRequest, HttpResponse,
+	http::{HeaderMap, Method, Request, header::ACCEPT},
+};
+use time::format_description::well_known::Rfc2822;
 // self
 use crate::_prelude::*;
+#[cfg(feature = "reqwest")] use crate::auth::TokenBinding;
 
 /// Abstraction over HTTP transports capable of executing OAuth token exchanges while
 /// publishing response metadata to the broker's instrumentation pipeline.
@@ -60,6 +63,83 @@ where
 	/// - Never retain the slot clone beyond the lifetime of the returned handle; the handle itself
 	///   enforces borrowing rules for the transport.
 	fn with_metadata(&self, slot: ResponseMetadataSlot) -> Self::Handle;
+
+	/// RFC 8705 `x5t#S256` thumbprint of the client certificate this transport presents over
+	/// mutual TLS, or `None` when it does not use one.
+	fn client_certificate_thumbprint(&self) -> Option<String> {
+		None
+	}
+}
+
+/// Reads a backoff hint from response headers into [`ResponseMetadata::retry_after`].
+///
+/// [`ReqwestHttpClient`] consults [`RetryAfterHeaders::default`] unless another strategy is set
+/// through `ReqwestHttpClient::with_retry_after_strategy`; custom transports can call a strategy
+/// before [`ResponseMetadataSlot::store`].
+pub trait RetryAfterStrategy
+where
+	Self: 'static + Send + Sync,
+{
+	/// Returns how long to wait before retrying, or `None` when the headers carry no usable hint.
+	fn retry_after(&self, headers: &HeaderMap, now: OffsetDateTime) -> Option<Duration>;
+}
+
+/// Header a provider uses to signal backoff.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RetryAfterHeader {
+	/// Standard `Retry-After` (RFC 9110): delay seconds or an HTTP date.
+	RetryAfter,
+	/// `RateLimit-Reset` (IETF rate limit headers draft): seconds until the quota resets.
+	RateLimitReset,
+	/// `X-RateLimit-Reset`: Unix epoch seconds at which the quota resets.
+	XRateLimitReset,
+}
+impl RetryAfterHeader {
+	/// Every supported header, in the order [`RetryAfterHeaders::default`] consults them.
+	pub const ALL: [Self; 3] = [Self::RetryAfter, Self::RateLimitReset, Self::XRateLimitReset];
+
+	/// Returns the lowercase header name.
+	pub const fn as_str(self) -> &'static str {
+		match self {
+			Self::RetryAfter => "retry-after",
+			Self::RateLimitReset => "ratelimit-reset",
+			Self::XRateLimitReset => "x-ratelimit-reset",
+		}
+	}
+
+	/// Parses this header from `headers`; hints that already lapsed at `now` are ignored.
+	pub fn parse(self, headers: &HeaderMap, now: OffsetDateTime) -> Option<Duration> {
+		let raw = headers.get(self.as_str())?.to_str().ok()?.trim();
+		let delay = match self {
+			Self::RetryAfter => match raw.parse::<i64>() {
+				Ok(secs) => Duration::seconds(secs),
+				Err(_) => OffsetDateTime::parse(raw, &Rfc2822).ok()? - now,
+			},
+			Self::RateLimitReset => Duration::seconds(raw.parse::<i64>().ok()?),
+			Self::XRateLimitReset =>
+				OffsetDateTime::from_unix_timestamp(raw.parse::<i64>().ok()?).ok()? - now,
+		};
+
+		match self {
+			Self::RetryAfter | Self::RateLimitReset if !delay.is_negative() => Some(delay),
+			Self::XRateLimitReset if delay.is_positive() => Some(delay),
+			_ => None,
+		}
+	}
+}
+
+/// Consults a list of [`RetryAfterHeader`]s in order and uses the first usable hint.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetryAfterHeaders(pub Vec<RetryAfterHeader>);
+impl Default for RetryAfterHeaders {
+	fn default() -> Self {
+		Self(RetryAfterHeader::ALL.to_vec())
+	}
+}
+impl RetryAfterStrategy for RetryAfterHeaders {
+	fn retry_after(&self, headers: &HeaderMap, now: OffsetDateTime) -> Option<Duration> {
+		self.0.iter().find_map(|header| header.parse(headers, now))
+	}
 }
 
 /// Captures metadata from the most recent HTTP response for downstream error mapping.
@@ -72,8 +152,15 @@ pub struct ResponseMetadata {
 	pub status: Option<u16>,
 	/// Retry-After hint expressed as a relative duration.
 	pub retry_after: Option<Duration>,
+	/// Selected response headers (see [`CAPTURED_ERROR_HEADERS`]) keyed by lowercase name.
+	pub headers: BTreeMap<String, String>,
 }
 
+/// Response headers transports capture into [`ResponseMetadata::headers`] because providers put
+/// error details there instead of in the body.
+pub const CAPTURED_ERROR_HEADERS: &[&str] =
+	&["www-authenticate", "x-amzn-errortype", "x-ms-error-code", "x-error-code"];
+
 /// Thread-safe slot for sharing [`ResponseMetadata`] between transport and error layers.
 ///
 /// The broker creates a fresh slot for each token request and reads the captured
@@ -103,24 +190,53 @@ impl ResponseMetadataSlot {
 /// any custom [`ReqwestClient`] to disable redirect following, because the broker
 /// passes this client into the `oauth2` crate when it builds the facade layer.
 #[cfg(feature = "reqwest")]
-#[derive(Clone, Default)]
-pub struct ReqwestHttpClient(pub ReqwestClient);
+#[derive(Clone)]
+pub struct ReqwestHttpClient {
+	client: ReqwestClient,
+	certificate_thumbprint: Option<String>,
+	retry_after: Arc<dyn RetryAfterStrategy>,
+}
 #[cfg(feature = "reqwest")]
 impl ReqwestHttpClient {
 	/// Wraps an existing reqwest [`ReqwestClient`].
 	pub fn with_client(client: ReqwestClient) -> Self {
-		Self(client)
+		Self {
+			client,
+			certificate_thumbprint: None,
+			retry_after: Arc::new(RetryAfterHeaders::default()),
+		}
+	}
+
+	/// Reads backoff hints with `strategy` instead of [`RetryAfterHeaders::default`].
+	pub fn with_retry_after_strategy(mut self, strategy: impl RetryAfterStrategy) -> Self {
+		self.retry_after = Arc::new(strategy);
+
+		self
+	}
+
+	/// Declares the DER client certificate the wrapped client presents over mutual TLS, so
+	/// certificate-bound tokens can be checked against it.
+	pub fn with_client_certificate(mut self, der: &[u8]) -> Self {
+		self.certificate_thumbprint = Some(TokenBinding::certificate_thumbprint(der));
+
+		self
 	}
 
 	/// Builds an instrumented HTTP client that captures response metadata.
 	pub(crate) fn instrumented(&self, slot: ResponseMetadataSlot) -> InstrumentedHandle {
-		InstrumentedHandle::new(self.0.clone(), slot)
+		InstrumentedHandle::new(self.client.clone(), slot, self.retry_after.clone())
+	}
+}
+#[cfg(feature = "reqwest")]
+impl Default for ReqwestHttpClient {
+	fn default() -> Self {
+		Self::with_client(ReqwestClient::default())
 	}
 }
 #[cfg(feature = "reqwest")]
 impl AsRef<ReqwestClient> for ReqwestHttpClient {
 	fn as_ref(&self) -> &ReqwestClient {
-		&self.0
+		&self.client
 	}
 }
 #[cfg(feature = "reqwest")]
@@ -128,7 +244,7 @@ impl Deref for ReqwestHttpClient {
 	type Target = ReqwestClient;
 
 	fn deref(&self) -> &Self::Target {
-		&self.0
+		&self.client
 	}
 }
 
@@ -137,11 +253,16 @@ impl Deref for ReqwestHttpClient {
 pub(crate) struct InstrumentedHttpClient {
 	client: ReqwestClient,
 	slot: ResponseMetadataSlot,
+	retry_after: Arc<dyn RetryAfterStrategy>,
 }
 #[cfg(feature = "reqwest")]
 impl InstrumentedHttpClient {
-	fn new(client: ReqwestClient, slot: ResponseMetadataSlot) -> Self {
-		Self { client, slot }
+	fn new(
+		client: ReqwestClient,
+		slot: ResponseMetadataSlot,
+		retry_after: Arc<dyn RetryAfterStrategy>,
+	) -> Self {
+		Self { client, slot, retry_after }
 	}
 }
 
@@ -151,8 +272,12 @@ impl InstrumentedHttpClient {
 pub struct InstrumentedHandle(Arc<InstrumentedHttpClient>);
 #[cfg(feature = "reqwest")]
 impl InstrumentedHandle {
-	fn new(client: ReqwestClient, slot: ResponseMetadataSlot) -> Self {
-		Self(Arc::new(InstrumentedHttpClient::new(client, slot)))
+	fn new(
+		client: ReqwestClient,
+		slot: ResponseMetadataSlot,
+		retry_after: Arc<dyn RetryAfterStrategy>,
+	) -> Self {
+		Self(Arc::new(InstrumentedHttpClient::new(client, slot, retry_after)))
 	}
 }
 #[cfg(feature = "reqwest")]
@@ -174,9 +299,13 @@ impl<'c> AsyncHttpClient<'c> for InstrumentedHandle {
 				.map_err(Box::new)?;
 			let status = response.status();
 			let headers = response.headers().to_owned();
-			let retry_after = parse_retry_after(&headers);
+			let retry_after = client.retry_after.retry_after(&headers, OffsetDateTime::now_utc());
 
-			client.slot.store(ResponseMetadata { status: Some(status.as_u16()), retry_after });
+			client.slot.store(ResponseMetadata {
+				status: Some(status.as_u16()),
+				retry_after,
+				headers: capture_error_headers(&headers),
+			});
 
 			let mut response_new =
 				HttpResponse::new(response.bytes().await.map_err(Box::new)?.to_vec());
@@ -196,23 +325,125 @@ impl TokenHttpClient for ReqwestHttpClient {
 	fn with_metadata(&self, slot: ResponseMetadataSlot) -> Self::Handle {
 		self.instrumented(slot)
 	}
+
+	fn client_certificate_thumbprint(&self) -> Option<String> {
+		self.certificate_thumbprint.clone()
+	}
+}
+
+/// Issues a bodiless `GET` against `url` through the broker transport.
+///
+/// Used by metadata lookups and reachability checks that never mint tokens. The response is
+/// returned regardless of its status so callers can decide how to interpret it.
+pub(crate) async fn get<C>(
+	client: &C,
+	url: &Url,
+) -> Result<HttpResponse, HttpClientError<C::TransportError>>
+where
+	C: ?Sized + TokenHttpClient,
+{
+	send_bodiless(client, Method::GET, url).await
+}
+
+/// Issues a `HEAD` against `url` through the broker transport.
+///
+/// Used by reachability probes; like [`get`], any response status is returned as-is.
+pub(crate) async fn head<C>(
+	client: &C,
+	url: &Url,
+) -> Result<HttpResponse, HttpClientError<C::TransportError>>
+where
+	C: ?Sized + TokenHttpClient,
+{
+	send_bodiless(client, Method::HEAD, url).await
+}
+
+async fn send_bodiless<C>(
+	client: &C,
+	method: Method,
+	url: &Url,
+) -> Result<HttpResponse, HttpClientError<C::TransportError>>
+where
+	C: ?Sized + TokenHttpClient,
+{
+	let handle = client.with_metadata(ResponseMetadataSlot::default());
+	let request = Request::builder()
+		.method(method)
+		.uri(url.as_str())
+		.header(ACCEPT, "application/json")
+		.body(Vec::new())
+		.map_err(HttpClientError::Http)?;
+
+	handle.call(request).await
 }
 
 #[cfg(feature = "reqwest")]
-fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
-	let value = headers.get(RETRY_AFTER)?;
-	let raw = value.to_str().ok()?.trim();
+fn capture_error_headers(headers: &HeaderMap) -> BTreeMap<String, String> {
+	CAPTURED_ERROR_HEADERS
+		.iter()
+		.filter_map(|name| {
+			let value = headers.get(*name)?.to_str().ok()?;
 
-	if let Ok(secs) = raw.parse::<u64>() {
-		return Some(Duration::seconds(secs as i64));
-	}
-	if let Ok(moment) = OffsetDateTime::parse(raw, &Rfc2822) {
-		let delta = moment - OffsetDateTime::now_utc();
+			Some(((*name).to_owned(), value.to_owned()))
+		})
+		.collect()
+}
 
-		if delta.is_positive() {
-			return Some(delta);
-		}
+#[cfg(test)]
+mod tests {
+	// self
+	use super::*;
+
+	fn headers(pairs: &[(&'static str, String)]) -> HeaderMap {
+		pairs
+			.iter()
+			.map(|(name, value)| {
+				let value = value.parse().expect("Header fixture should be a valid header value.");
+
+				(oauth2::http::HeaderName::from_static(name), value)
+			})
+			.collect()
 	}
 
-	None
+	#[test]
+	fn retry_after_headers_fall_back_to_rate_limit_resets() {
+		let now = OffsetDateTime::from_unix_timestamp(1_700_000_000)
+			.expect("Timestamp fixture should be valid.");
+		let strategy = RetryAfterHeaders::default();
+
+		assert_eq!(
+			strategy.retry_after(
+				&headers(&[
+					("retry-after", "5".into()),
+					("x-ratelimit-reset", "1700000060".into())
+				]),
+				now
+			),
+			Some(Duration::seconds(5))
+		);
+		assert_eq!(
+			strategy.retry_after(&headers(&[("ratelimit-reset", "12".into())]), now),
+			Some(Duration::seconds(12))
+		);
+		assert_eq!(
+			strategy.retry_after(&headers(&[("x-ratelimit-reset", "1700000060".into())]), now),
+			Some(Duration::seconds(60))
+		);
+		assert_eq!(
+			strategy.retry_after(&headers(&[("x-ratelimit-reset", "1699999990".into())]), now),
+			None
+		);
+		assert_eq!(
+			strategy.retry_after(
+				&headers(&[("retry-after", "Tue, 14 Nov 2023 22:13:50 +0000".into())]),
+				now
+			),
+			Some(Duration::seconds(30))
+		);
+		assert_eq!(
+			RetryAfterHeaders(vec![RetryAfterHeader::RetryAfter])
+				.retry_after(&headers(&[("x-ratelimit-reset", "1700000060".into())]), now),
+			None
+		);
+	}
 }
@@ -4,6 +4,7 @@
 #![deny(clippy::all, missing_docs, unused_crate_dependencies)]
 
 pub mod auth;
+pub mod config;
 pub mod error;
 pub mod ext;
 pub mod flows;
@@ -11,6 +12,7 @@ pub mod http;
 pub mod oauth;
 pub mod obs;
 pub mod provider;
+#[cfg(feature = "service")] pub mod service;
 pub mod store;
 #[cfg(all(any(test, feature = "test"), feature = "reqwest"))]
 pub mod _preludet {
@@ -90,3 +92,6 @@ mod _prelude {
 #[cfg(feature = "reqwest")] pub use reqwest;
 pub use url;
 #[cfg(all(test, feature = "reqwest"))] use {color_eyre as _, httpmock as _};
+// The `cli` dependencies are only used by the `oauth2-broker` binary.
+#[cfg(feature = "cli")] use clap as _;
+#[cfg(all(feature = "cli", not(feature = "loopback")))] use tokio as _;
@@ -2,33 +2,94 @@
 
 pub use oauth2;
 
-// std
-use std::borrow::Cow;
 // crates.io
+use base64::{
+	Engine as _,
+	engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+};
 use oauth2::{
-	AuthType, AuthUrl, AuthorizationCode, ClientId, ClientSecret, EndpointNotSet, EndpointSet,
-	HttpClientError, PkceCodeVerifier, RedirectUrl, RefreshToken, RequestTokenError, Scope,
-	TokenResponse, TokenUrl,
-	basic::{BasicClient, BasicErrorResponse, BasicRequestTokenError},
+	AsyncHttpClient, AuthType, AuthUrl, Client, ClientId, ClientSecret, EndpointNotSet,
+	EndpointSet, ExtraTokenFields, HttpClientError, HttpResponse, RedirectUrl, RequestTokenError,
+	StandardRevocableToken, StandardTokenResponse, TokenResponse, TokenUrl,
+	basic::{
+		BasicErrorResponse, BasicRequestTokenError, BasicRevocationErrorResponse,
+		BasicTokenIntrospectionResponse, BasicTokenType,
+	},
+	http::{
+		HeaderValue, Method, Request, StatusCode,
+		header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE},
+	},
 };
+use url::form_urlencoded;
 // self
 #[cfg(all(test, feature = "reqwest"))] use crate::http::ReqwestHttpClient;
 use crate::{
 	_prelude::*,
-	auth::{ScopeSet, TokenFamily, TokenRecord},
+	auth::{ScopeSet, TokenBinding, TokenFamily, TokenRecord, TokenRecordBuilder},
 	error::{ConfigError, TransientError, TransportError},
+	flows::{BearerChallenge, common},
 	http::{ResponseMetadata, ResponseMetadataSlot, TokenHttpClient},
+	obs::TraceContext,
 	provider::{
-		ClientAuthMethod, GrantType, ProviderDescriptor, ProviderErrorContext, ProviderErrorKind,
-		ProviderStrategy,
+		ClientAuthMethod, GrantType, JwksCache, ProviderDescriptor, ProviderErrorContext,
+		ProviderErrorKind, ProviderQuirks, ProviderStrategy, TokenResponseFormat, jwks,
 	},
 };
 
-type ConfiguredBasicClient =
-	BasicClient<EndpointSet, EndpointNotSet, EndpointNotSet, EndpointNotSet, EndpointSet>;
-type FacadeTokenResponse = oauth2::basic::BasicTokenResponse;
+type FacadeClient<HasAuthUrl = EndpointNotSet, HasTokenUrl = EndpointNotSet> = Client<
+	BasicErrorResponse,
+	FacadeTokenResponse,
+	BasicTokenIntrospectionResponse,
+	StandardRevocableToken,
+	BasicRevocationErrorResponse,
+	HasAuthUrl,
+	EndpointNotSet,
+	EndpointNotSet,
+	EndpointNotSet,
+	HasTokenUrl,
+>;
+type ConfiguredBasicClient = FacadeClient<EndpointSet, EndpointSet>;
+type FacadeTokenResponse = StandardTokenResponse<TokenResponseExtras, BasicTokenType>;
 type FacadeFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + 'a + Send>>;
 
+/// Non-standard token response fields the broker understands.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub(crate) struct TokenResponseExtras {
+	/// Refresh token lifetime in seconds (Keycloak and other OIDC servers); `0` means the refresh
+	/// token never expires (offline tokens).
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	refresh_expires_in: Option<u64>,
+	/// API base URL for the authenticated org (Salesforce).
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	instance_url: Option<String>,
+}
+impl TokenResponseExtras {
+	fn refresh_lifetime(&self) -> Option<Duration> {
+		self.refresh_expires_in
+			.filter(|secs| *secs > 0)
+			.and_then(|secs| i64::try_from(secs).ok())
+			.map(Duration::seconds)
+	}
+
+	/// Non-secret fields copied into [`TokenRecord::extras`].
+	fn record_extras(&self) -> impl Iterator<Item = (&'static str, &str)> {
+		self.instance_url.as_deref().map(|url| ("instance_url", url)).into_iter()
+	}
+}
+impl ExtraTokenFields for TokenResponseExtras {}
+
+/// Successful backchannel authentication endpoint response (CIBA §7.3).
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct BackchannelAuthResponse {
+	/// Identifier of the pending authentication request.
+	pub(crate) auth_req_id: String,
+	/// Seconds until `auth_req_id` expires.
+	pub(crate) expires_in: u64,
+	/// Minimum seconds between polls, when the provider requires one.
+	#[serde(default)]
+	pub(crate) interval: Option<u64>,
+}
+
 /// Maps HTTP transport failures into broker [`Error`] values.
 pub trait TransportErrorMapper<E>
 where
@@ -46,29 +107,31 @@ where
 }
 
 pub(crate) trait OAuth2Facade {
-	fn exchange_client_credentials<'a, 'strategy, 'scopes, 'params>(
+	fn exchange_client_credentials<'a, 'strategy, 'scope, 'params>(
 		&'a self,
 		strategy: &'strategy dyn ProviderStrategy,
 		family: TokenFamily,
-		scopes: &'scopes [&'scopes str],
+		requested_scope: &'scope ScopeSet,
 		extra_params: &'params [(String, String)],
 	) -> FacadeFuture<'a, TokenRecord>
 	where
 		'strategy: 'a,
-		'scopes: 'a,
+		'scope: 'a,
 		'params: 'a;
 
-	fn refresh_token<'a, 'strategy, 'refresh, 'scope>(
+	fn refresh_token<'a, 'strategy, 'refresh, 'scope, 'params>(
 		&'a self,
 		strategy: &'strategy dyn ProviderStrategy,
 		family: TokenFamily,
 		refresh_token: &'refresh str,
 		requested_scope: &'scope ScopeSet,
+		extra_params: &'params [(String, String)],
 	) -> FacadeFuture<'a, (TokenRecord, Option<String>)>
 	where
 		'strategy: 'a,
 		'refresh: 'a,
-		'scope: 'a;
+		'scope: 'a,
+		'params: 'a;
 
 	fn exchange_authorization_code<'a, 'strategy, 'code, 'pkce, 'scope, 'redirect>(
 		&'a self,
@@ -85,6 +148,50 @@ pub(crate) trait OAuth2Facade {
 		'pkce: 'a,
 		'scope: 'a,
 		'redirect: 'a;
+	fn exchange_jwt_bearer<'a, 'strategy, 'assertion, 'scope, 'params>(
+		&'a self,
+		strategy: &'strategy dyn ProviderStrategy,
+		family: TokenFamily,
+		assertion: &'assertion str,
+		requested_scope: &'scope ScopeSet,
+		scope_in_form: bool,
+		extra_params: &'params [(String, String)],
+	) -> FacadeFuture<'a, TokenRecord>
+	where
+		'strategy: 'a,
+		'assertion: 'a,
+		'scope: 'a,
+		'params: 'a;
+
+	fn revoke_token<'a, 'strategy, 'token>(
+		&'a self,
+		strategy: &'strategy dyn ProviderStrategy,
+		token: &'token str,
+		token_type_hint: &'static str,
+	) -> FacadeFuture<'a, ()>
+	where
+		'strategy: 'a,
+		'token: 'a;
+
+	fn start_backchannel_authentication<'a, 'strategy>(
+		&'a self,
+		strategy: &'strategy dyn ProviderStrategy,
+		form: Vec<(String, String)>,
+	) -> FacadeFuture<'a, BackchannelAuthResponse>
+	where
+		'strategy: 'a;
+
+	fn exchange_ciba<'a, 'strategy, 'request, 'scope>(
+		&'a self,
+		strategy: &'strategy dyn ProviderStrategy,
+		family: TokenFamily,
+		auth_req_id: &'request str,
+		requested_scope: &'scope ScopeSet,
+	) -> FacadeFuture<'a, TokenRecord>
+	where
+		'strategy: 'a,
+		'request: 'a,
+		'scope: 'a;
 }
 
 #[cfg(feature = "reqwest")]
@@ -118,6 +225,43 @@ where
 	oauth_client: ConfiguredBasicClient,
 	http_client: Arc<C>,
 	error_mapper: Arc<M>,
+	quirks: ProviderQuirks,
+	form_client_auth: FormClientAuth,
+	grant_client_auth: BTreeMap<GrantType, FormClientAuth>,
+	issuer: Option<String>,
+	revocation_uri: Option<Url>,
+	backchannel_uri: Option<Url>,
+	jwks_uri: Option<Url>,
+	jwks: Arc<JwksCache>,
+	trace_context: Option<TraceContext>,
+}
+
+/// Client authentication applied to the token and revocation requests the broker assembles.
+#[derive(Default)]
+enum FormClientAuth {
+	/// No client authentication (public clients or assertion-only grants).
+	#[default]
+	None,
+	/// Pre-built `Authorization: Basic` header.
+	Basic(HeaderValue),
+	/// `client_id` + `client_secret` form parameters.
+	Post { client_id: String, client_secret: String },
+}
+impl FormClientAuth {
+	fn new(
+		method: ClientAuthMethod,
+		client_id: &str,
+		secret: &str,
+		quirks: &ProviderQuirks,
+	) -> Result<Self> {
+		Ok(match method {
+			ClientAuthMethod::ClientSecretBasic =>
+				Self::Basic(basic_authorization(client_id, secret, quirks.basic_auth_url_encode)?),
+			ClientAuthMethod::ClientSecretPost =>
+				Self::Post { client_id: client_id.to_owned(), client_secret: secret.to_owned() },
+			ClientAuthMethod::NoneWithPkce => Self::None,
+		})
+	}
 }
 impl<C, M> BasicFacade<C, M>
 where
@@ -129,7 +273,20 @@ where
 		http_client: impl Into<Arc<C>>,
 		error_mapper: impl Into<Arc<M>>,
 	) -> Self {
-		Self { oauth_client, http_client: http_client.into(), error_mapper: error_mapper.into() }
+		Self {
+			oauth_client,
+			http_client: http_client.into(),
+			error_mapper: error_mapper.into(),
+			quirks: ProviderQuirks::default(),
+			form_client_auth: FormClientAuth::default(),
+			grant_client_auth: BTreeMap::new(),
+			issuer: None,
+			revocation_uri: None,
+			backchannel_uri: None,
+			jwks_uri: None,
+			jwks: Default::default(),
+			trace_context: None,
+		}
 	}
 
 	pub(crate) fn from_descriptor(
@@ -150,7 +307,7 @@ where
 			} else {
 				client_secret.map(|value| ClientSecret::new(value.to_owned()))
 			};
-		let mut oauth_client = BasicClient::new(ClientId::new(client_id.to_owned()))
+		let mut oauth_client = <FacadeClient>::new(ClientId::new(client_id.to_owned()))
 			.set_auth_uri(auth_url)
 			.set_token_uri(token_url);
 
@@ -168,91 +325,426 @@ where
 			oauth_client = oauth_client.set_auth_type(AuthType::RequestBody);
 		}
 
-		Ok(Self::new(oauth_client, http_client, error_mapper))
+		let mut facade = Self::new(oauth_client, http_client, error_mapper);
+
+		facade.quirks = descriptor.quirks;
+		facade.issuer = descriptor.issuer.as_ref().map(Url::to_string);
+		facade.revocation_uri = descriptor.endpoints.revocation.clone();
+		facade.backchannel_uri = descriptor.endpoints.backchannel_authentication.clone();
+		facade.jwks_uri = descriptor.endpoints.jwks.clone();
+
+		if let Some(secret) = client_secret {
+			facade.form_client_auth = FormClientAuth::new(
+				descriptor.preferred_client_auth_method,
+				client_id,
+				secret,
+				&descriptor.quirks,
+			)?;
+
+			for (grant, method) in &descriptor.grant_client_auth_methods {
+				facade.grant_client_auth.insert(
+					*grant,
+					FormClientAuth::new(*method, client_id, secret, &descriptor.quirks)?,
+				);
+			}
+		}
+
+		Ok(facade)
+	}
+
+	/// Propagates `context` on every request this facade sends.
+	pub(crate) fn with_trace_context(mut self, context: Option<TraceContext>) -> Self {
+		self.trace_context = context;
+
+		self
 	}
+
+	/// Resolves signing keys for JWT token responses through the broker's shared `cache`.
+	pub(crate) fn with_jwks_cache(mut self, cache: Arc<JwksCache>) -> Self {
+		self.jwks = cache;
+
+		self
+	}
+
+	/// Stamps client and issuer provenance, any `cnf` binding declared by a JWT access token,
+	/// and the strategy's metadata annotations onto a freshly minted record.
+	fn with_provenance(
+		&self,
+		strategy: &dyn ProviderStrategy,
+		grant: GrantType,
+		mut record: TokenRecord,
+	) -> TokenRecord {
+		record.client_id = Some(self.oauth_client.client_id().to_string());
+		record.issuer = self.issuer.clone();
+		record.binding = cnf_binding(record.access_token.expose());
+
+		let mut metadata = record.metadata.clone();
+
+		strategy.annotate_record(grant, &record, &mut metadata);
+
+		record.metadata = metadata;
+
+		record
+	}
+
+	/// Joins scopes with the provider's delimiter for the `scope` form parameter.
+	fn scope_param(&self, scope: &ScopeSet) -> Option<String> {
+		common::format_scope(scope, self.quirks.scope_delimiter)
+	}
+
+	/// Completes a token request form: appends body client authentication (and the `client_id`
+	/// of public clients when `identify_public_client` is set), then hands the result to
+	/// [`ProviderStrategy::finalize_token_request`], which may rewrite or veto it.
+	fn token_form(
+		&self,
+		strategy: &dyn ProviderStrategy,
+		grant: GrantType,
+		mut form: Vec<(String, String)>,
+		identify_public_client: bool,
+	) -> Result<Vec<(String, String)>> {
+		self.append_client_auth(self.client_auth(grant), &mut form, identify_public_client);
+
+		strategy
+			.finalize_token_request(grant, &mut form)
+			.map_err(|reason| ConfigError::TokenRequestVetoed { grant: grant.as_str(), reason })?;
+
+		Ok(form)
+	}
+
+	/// Posts an assembled token request form.
+	///
+	/// Responses are handled exactly like `oauth2` handles them (only `200 OK` with a JSON body
+	/// succeeds) and parsed with the same token/error types, so error mapping is identical
+	/// across grants.
+	async fn post_token_form(
+		&self,
+		grant: GrantType,
+		meta: ResponseMetadataSlot,
+		form: Vec<(String, String)>,
+	) -> Result<FacadeTokenResponse, BasicRequestTokenError<HttpClientError<C::TransportError>>> {
+		let request = self
+			.form_request(self.client_auth(grant), self.oauth_client.token_uri().as_str(), form)
+			.map_err(|e| RequestTokenError::Request(HttpClientError::Http(e)))?;
+		let response = self
+			.http_client
+			.with_metadata(meta)
+			.call(request)
+			.await
+			.map_err(RequestTokenError::Request)?;
+		let body = response.body();
+
+		if response.status() != StatusCode::OK {
+			if body.is_empty() {
+				return Err(RequestTokenError::Other(
+					"server returned empty error response".into(),
+				));
+			}
+
+			return match serde_path_to_error::deserialize::<_, BasicErrorResponse>(
+				&mut serde_json::Deserializer::from_slice(body),
+			) {
+				Ok(error) => Err(RequestTokenError::ServerResponse(error)),
+				Err(e) => Err(RequestTokenError::Parse(e, body.clone())),
+			};
+		}
+		if self.quirks.token_response_format == TokenResponseFormat::Jwt {
+			let payload = self.verify_signed_response(&response).await?;
+
+			return serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_slice(
+				&payload,
+			))
+			.map_err(|e| RequestTokenError::Parse(e, payload.clone()));
+		}
+		if let Some(content_type) = response.headers().get(CONTENT_TYPE)
+			&& !content_type
+				.to_str()
+				.is_ok_and(|value| value.to_ascii_lowercase().starts_with("application/json"))
+		{
+			return Err(RequestTokenError::Other(format!(
+				"unexpected response Content-Type: {content_type:?}, should be `application/json`"
+			)));
+		}
+
+		serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_slice(body))
+			.map_err(|e| RequestTokenError::Parse(e, body.clone()))
+	}
+
+	/// Verifies an `application/jwt` token response against the descriptor's JWKS and returns
+	/// the JWS payload.
+	async fn verify_signed_response(
+		&self,
+		response: &HttpResponse,
+	) -> Result<Vec<u8>, BasicRequestTokenError<HttpClientError<C::TransportError>>> {
+		let refuse = |message: &str| RequestTokenError::Other(message.into());
+
+		if !response.headers().get(CONTENT_TYPE).is_some_and(|value| {
+			value
+				.to_str()
+				.is_ok_and(|value| value.to_ascii_lowercase().starts_with("application/jwt"))
+		}) {
+			return Err(refuse("unexpected response Content-Type, should be `application/jwt`"));
+		}
+
+		let jws = str::from_utf8(response.body())
+			.map_err(|_| refuse("signed token response is not UTF-8"))?
+			.trim();
+		let header = jwks::jws_header(jws)
+			.ok_or_else(|| refuse("signed token response has a malformed JWS header"))?;
+		let jwks_uri =
+			self.jwks_uri.as_ref().ok_or_else(|| refuse("descriptor has no JWKS endpoint"))?;
+		let key = self
+			.jwks
+			.key(self.http_client.as_ref(), jwks_uri, header.kid.as_deref(), &header.alg)
+			.await
+			.map_err(RequestTokenError::Request)?
+			.ok_or_else(|| refuse("no JWKS key matches the signed token response"))?;
+
+		jwks::verify_jws(jws, &header.alg, &key).map_err(RequestTokenError::Other)
+	}
+
+	/// POSTs an RFC 7009 revocation request; any 2xx response counts as success.
+	async fn post_revocation_form(
+		&self,
+		meta: ResponseMetadataSlot,
+		uri: &Url,
+		mut form: Vec<(String, String)>,
+	) -> Result<(), BasicRequestTokenError<HttpClientError<C::TransportError>>> {
+		// Public clients identify themselves in the form (RFC 7009 §2.1).
+		self.append_client_auth(&self.form_client_auth, &mut form, true);
+
+		let request = self
+			.form_request(&self.form_client_auth, uri.as_str(), form)
+			.map_err(|e| RequestTokenError::Request(HttpClientError::Http(e)))?;
+		let response = self
+			.http_client
+			.with_metadata(meta)
+			.call(request)
+			.await
+			.map_err(RequestTokenError::Request)?;
+
+		if response.status().is_success() {
+			return Ok(());
+		}
+
+		let body = response.body();
+
+		match serde_path_to_error::deserialize::<_, BasicErrorResponse>(
+			&mut serde_json::Deserializer::from_slice(body),
+		) {
+			Ok(error) => Err(RequestTokenError::ServerResponse(error)),
+			Err(_) => Err(RequestTokenError::Other(format!(
+				"revocation endpoint returned HTTP {}",
+				response.status().as_u16()
+			))),
+		}
+	}
+
+	/// POSTs a CIBA backchannel authentication request; only `200 OK` with a JSON body succeeds.
+	async fn post_backchannel_form(
+		&self,
+		meta: ResponseMetadataSlot,
+		uri: &Url,
+		form: Vec<(String, String)>,
+	) -> Result<BackchannelAuthResponse, BasicRequestTokenError<HttpClientError<C::TransportError>>>
+	{
+		let request = self
+			.form_request(self.client_auth(GrantType::Ciba), uri.as_str(), form)
+			.map_err(|e| RequestTokenError::Request(HttpClientError::Http(e)))?;
+		let response = self
+			.http_client
+			.with_metadata(meta)
+			.call(request)
+			.await
+			.map_err(RequestTokenError::Request)?;
+		let body = response.body();
+
+		if response.status() != StatusCode::OK {
+			return match serde_path_to_error::deserialize::<_, BasicErrorResponse>(
+				&mut serde_json::Deserializer::from_slice(body),
+			) {
+				Ok(error) => Err(RequestTokenError::ServerResponse(error)),
+				Err(_) => Err(RequestTokenError::Other(format!(
+					"backchannel authentication endpoint returned HTTP {}",
+					response.status().as_u16()
+				))),
+			};
+		}
+
+		serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_slice(body))
+			.map_err(|e| RequestTokenError::Parse(e, body.clone()))
+	}
+
+	/// Client authentication for token requests of `grant`.
+	fn client_auth(&self, grant: GrantType) -> &FormClientAuth {
+		self.grant_client_auth.get(&grant).unwrap_or(&self.form_client_auth)
+	}
+
+	/// Appends the form parameters of `auth`.
+	fn append_client_auth(
+		&self,
+		auth: &FormClientAuth,
+		form: &mut Vec<(String, String)>,
+		identify_public_client: bool,
+	) {
+		match auth {
+			FormClientAuth::None if identify_public_client =>
+				form.push(("client_id".into(), self.oauth_client.client_id().to_string())),
+			FormClientAuth::None | FormClientAuth::Basic(_) => {},
+			FormClientAuth::Post { client_id, client_secret } => {
+				form.push(("client_id".into(), client_id.clone()));
+				form.push(("client_secret".into(), client_secret.clone()));
+			},
+		}
+	}
+
+	/// Builds a form POST to `uri`, adding the `Authorization: Basic` header `auth` calls for.
+	fn form_request(
+		&self,
+		auth: &FormClientAuth,
+		uri: &str,
+		form: Vec<(String, String)>,
+	) -> Result<Request<Vec<u8>>, oauth2::http::Error> {
+		let mut builder = Request::builder()
+			.method(Method::POST)
+			.uri(uri)
+			.header(ACCEPT, HeaderValue::from_static("application/json"))
+			.header(CONTENT_TYPE, HeaderValue::from_static("application/x-www-form-urlencoded"));
+
+		if let FormClientAuth::Basic(header) = auth {
+			builder = builder.header(AUTHORIZATION, header.clone());
+		}
+		// Each outbound call is a child span of the caller's context.
+		if let Some(context) = &self.trace_context {
+			builder = builder.header("traceparent", context.child().traceparent());
+
+			if let Some(tracestate) = &context.tracestate {
+				builder = builder.header("tracestate", tracestate);
+			}
+		}
+
+		let body = form_urlencoded::Serializer::new(String::new()).extend_pairs(&form).finish();
+
+		builder.body(body.into_bytes())
+	}
+}
+
+/// Builds an `Authorization: Basic` header, optionally form-encoding the credentials first.
+fn basic_authorization(client_id: &str, secret: &str, url_encode: bool) -> Result<HeaderValue> {
+	let credentials = if url_encode {
+		format!(
+			"{}:{}",
+			form_urlencoded::byte_serialize(client_id.as_bytes()).collect::<String>(),
+			form_urlencoded::byte_serialize(secret.as_bytes()).collect::<String>()
+		)
+	} else {
+		format!("{client_id}:{secret}")
+	};
+
+	HeaderValue::from_str(&format!("Basic {}", STANDARD.encode(credentials)))
+		.map_err(|e| ConfigError::from(oauth2::http::Error::from(e)).into())
 }
+
 impl<C, M> OAuth2Facade for BasicFacade<C, M>
 where
 	C: ?Sized + TokenHttpClient,
 	M: ?Sized + TransportErrorMapper<C::TransportError>,
 {
-	fn exchange_client_credentials<'a, 'strategy, 'scopes, 'params>(
+	fn exchange_client_credentials<'a, 'strategy, 'scope, 'params>(
 		&'a self,
 		strategy: &'strategy dyn ProviderStrategy,
 		family: TokenFamily,
-		scopes: &'scopes [&'scopes str],
+		requested_scope: &'scope ScopeSet,
 		extra_params: &'params [(String, String)],
 	) -> FacadeFuture<'a, TokenRecord>
 	where
 		'strategy: 'a,
-		'scopes: 'a,
+		'scope: 'a,
 		'params: 'a,
 	{
 		let meta = ResponseMetadataSlot::default();
 
 		Box::pin(async move {
-			let instrumented = self.http_client.with_metadata(meta.clone());
-			let requested_scope =
-				ScopeSet::new(scopes.iter().copied()).map_err(ConfigError::from)?;
-			let mut request = self.oauth_client.exchange_client_credentials();
+			let mut form =
+				vec![("grant_type".to_owned(), GrantType::ClientCredentials.as_str().to_owned())];
 
-			for scope in scopes {
-				request = request.add_scope(Scope::new((*scope).to_owned()));
+			if let Some(scope) = self.scope_param(requested_scope) {
+				form.push(("scope".to_owned(), scope));
 			}
-			for (key, value) in extra_params {
-				request = request.add_extra_param(key, value);
-			}
-
-			let response = request.request_async(&instrumented).await.map_err(|err| {
-				map_request_error(
-					strategy,
-					GrantType::ClientCredentials,
-					meta.take(),
-					err,
-					self.error_mapper.as_ref(),
-				)
-			})?;
 
-			map_standard_token_response(family, requested_scope, response)
+			form.extend(extra_params.iter().cloned());
+
+			let form = self.token_form(strategy, GrantType::ClientCredentials, form, true)?;
+			let response = self
+				.post_token_form(GrantType::ClientCredentials, meta.clone(), form)
+				.await
+				.map_err(|err| {
+					map_request_error(
+						strategy,
+						GrantType::ClientCredentials,
+						meta.take(),
+						err,
+						self.error_mapper.as_ref(),
+					)
+				})?;
+
+			map_standard_token_response(
+				family,
+				requested_scope,
+				response,
+				&self.quirks,
+				"client_credentials",
+			)
+			.map(|record| self.with_provenance(strategy, GrantType::ClientCredentials, record))
 		})
 	}
 
-	fn refresh_token<'a, 'strategy, 'refresh, 'scope>(
+	fn refresh_token<'a, 'strategy, 'refresh, 'scope, 'params>(
 		&'a self,
 		strategy: &'strategy dyn ProviderStrategy,
 		family: TokenFamily,
 		refresh_token: &'refresh str,
 		requested_scope: &'scope ScopeSet,
+		extra_params: &'params [(String, String)],
 	) -> FacadeFuture<'a, (TokenRecord, Option<String>)>
 	where
 		'strategy: 'a,
 		'refresh: 'a,
 		'scope: 'a,
+		'params: 'a,
 	{
 		let meta = ResponseMetadataSlot::default();
 
 		Box::pin(async move {
-			let instrumented = self.http_client.with_metadata(meta.clone());
-			let refresh_secret = RefreshToken::new(refresh_token.to_owned());
-			let mut request = self.oauth_client.exchange_refresh_token(&refresh_secret);
-
-			if !requested_scope.is_empty() {
-				for scope in requested_scope.iter() {
-					request = request.add_scope(Scope::new(scope.to_owned()));
-				}
-			}
+			let mut form = vec![
+				("grant_type".to_owned(), GrantType::RefreshToken.as_str().to_owned()),
+				("refresh_token".to_owned(), refresh_token.to_owned()),
+			];
 
-			let response = request.request_async(&instrumented).await.map_err(|err| {
-				map_request_error(
-					strategy,
-					GrantType::RefreshToken,
-					meta.take(),
-					err,
-					self.error_mapper.as_ref(),
-				)
-			})?;
+			if let Some(scope) = self.scope_param(requested_scope) {
+				form.push(("scope".to_owned(), scope));
+			}
 
-			map_refresh_token_response(family, requested_scope, response)
+			form.extend(extra_params.iter().cloned());
+
+			let form = self.token_form(strategy, GrantType::RefreshToken, form, true)?;
+			let response = self
+				.post_token_form(GrantType::RefreshToken, meta.clone(), form)
+				.await
+				.map_err(|err| {
+					map_request_error(
+						strategy,
+						GrantType::RefreshToken,
+						meta.take(),
+						err,
+						self.error_mapper.as_ref(),
+					)
+				})?;
+
+			map_refresh_token_response(family, requested_scope, response, &self.quirks).map(
+				|(record, new_refresh)| {
+					(self.with_provenance(strategy, GrantType::RefreshToken, record), new_refresh)
+				},
+			)
 		})
 	}
 
@@ -275,87 +767,221 @@ where
 		let meta = ResponseMetadataSlot::default();
 
 		Box::pin(async move {
-			let instrumented = self.http_client.with_metadata(meta.clone());
-			let mut request = self
-				.oauth_client
-				.exchange_code(AuthorizationCode::new(code.to_owned()))
-				.set_pkce_verifier(PkceCodeVerifier::new(pkce_verifier.to_owned()));
-
-			if !requested_scope.is_empty() {
-				request = request.add_extra_param("scope", requested_scope.normalized());
+			let mut form = vec![
+				("grant_type".to_owned(), GrantType::AuthorizationCode.as_str().to_owned()),
+				("code".to_owned(), code.to_owned()),
+				("code_verifier".to_owned(), pkce_verifier.to_owned()),
+				("redirect_uri".to_owned(), redirect_uri.to_string()),
+			];
+
+			if let Some(scope) = self.scope_param(requested_scope) {
+				form.push(("scope".to_owned(), scope));
+			}
+
+			let form = self.token_form(strategy, GrantType::AuthorizationCode, form, true)?;
+			let response = self
+				.post_token_form(GrantType::AuthorizationCode, meta.clone(), form)
+				.await
+				.map_err(|err| {
+					map_request_error(
+						strategy,
+						GrantType::AuthorizationCode,
+						meta.take(),
+						err,
+						self.error_mapper.as_ref(),
+					)
+				})?;
+			let lifetime = token_lifetime(&response, &self.quirks)?;
+
+			ensure_scopes_unchanged(
+				&response,
+				requested_scope,
+				self.quirks.scope_delimiter,
+				"authorization_code",
+			)?;
+
+			let issued_at = OffsetDateTime::now_utc();
+			let mut builder =
+				record_builder(family, requested_scope.clone(), &response, issued_at, lifetime);
+
+			if let Some(refresh) = response.refresh_token() {
+				builder = builder.refresh_token(refresh.secret().to_owned());
+			}
+
+			builder
+				.build()
+				.map(|record| self.with_provenance(strategy, GrantType::AuthorizationCode, record))
+				.map_err(|e| ConfigError::from(e).into())
+		})
+	}
+
+	fn exchange_jwt_bearer<'a, 'strategy, 'assertion, 'scope, 'params>(
+		&'a self,
+		strategy: &'strategy dyn ProviderStrategy,
+		family: TokenFamily,
+		assertion: &'assertion str,
+		requested_scope: &'scope ScopeSet,
+		scope_in_form: bool,
+		extra_params: &'params [(String, String)],
+	) -> FacadeFuture<'a, TokenRecord>
+	where
+		'strategy: 'a,
+		'assertion: 'a,
+		'scope: 'a,
+		'params: 'a,
+	{
+		let meta = ResponseMetadataSlot::default();
+
+		Box::pin(async move {
+			let mut form = vec![
+				("grant_type".to_owned(), GrantType::JwtBearer.as_str().to_owned()),
+				("assertion".to_owned(), assertion.to_owned()),
+			];
+
+			if scope_in_form && let Some(scope) = self.scope_param(requested_scope) {
+				form.push(("scope".to_owned(), scope));
 			}
 
-			let redirect_url = RedirectUrl::new(redirect_uri.to_string())
-				.map_err(|err| ConfigError::InvalidRedirect { source: err })?;
+			form.extend(extra_params.iter().cloned());
+
+			let form = self.token_form(strategy, GrantType::JwtBearer, form, false)?;
+			let response = self
+				.post_token_form(GrantType::JwtBearer, meta.clone(), form)
+				.await
+				.map_err(|err| {
+					map_request_error(
+						strategy,
+						GrantType::JwtBearer,
+						meta.take(),
+						err,
+						self.error_mapper.as_ref(),
+					)
+				})?;
+
+			map_standard_token_response(
+				family,
+				requested_scope,
+				response,
+				&self.quirks,
+				"jwt_bearer",
+			)
+			.map(|record| self.with_provenance(strategy, GrantType::JwtBearer, record))
+		})
+	}
 
-			request = request.set_redirect_uri(Cow::Owned(redirect_url));
+	fn revoke_token<'a, 'strategy, 'token>(
+		&'a self,
+		strategy: &'strategy dyn ProviderStrategy,
+		token: &'token str,
+		token_type_hint: &'static str,
+	) -> FacadeFuture<'a, ()>
+	where
+		'strategy: 'a,
+		'token: 'a,
+	{
+		let meta = ResponseMetadataSlot::default();
+
+		Box::pin(async move {
+			let uri = self.revocation_uri.as_ref().ok_or(ConfigError::MissingRevocationEndpoint)?;
+			let form = vec![
+				("token".to_owned(), token.to_owned()),
+				("token_type_hint".to_owned(), token_type_hint.to_owned()),
+			];
 
-			let response = request.request_async(&instrumented).await.map_err(|err| {
+			self.post_revocation_form(meta.clone(), uri, form).await.map_err(|err| {
 				map_request_error(
 					strategy,
-					GrantType::AuthorizationCode,
+					GrantType::RefreshToken,
 					meta.take(),
 					err,
 					self.error_mapper.as_ref(),
 				)
-			})?;
-			let expires_in = response.expires_in().ok_or(ConfigError::MissingExpiresIn)?.as_secs();
-			let expires_in =
-				i64::try_from(expires_in).map_err(|_| ConfigError::ExpiresInOutOfRange)?;
+			})
+		})
+	}
 
-			if expires_in <= 0 {
-				return Err(ConfigError::NonPositiveExpiresIn.into());
-			}
+	fn start_backchannel_authentication<'a, 'strategy>(
+		&'a self,
+		strategy: &'strategy dyn ProviderStrategy,
+		form: Vec<(String, String)>,
+	) -> FacadeFuture<'a, BackchannelAuthResponse>
+	where
+		'strategy: 'a,
+	{
+		let meta = ResponseMetadataSlot::default();
 
-			if let Some(scopes) = response.scopes() {
-				let returned = ScopeSet::new(scopes.iter().map(|scope| scope.as_ref()))
-					.map_err(ConfigError::from)?;
-				if returned != *requested_scope {
-					return Err(ConfigError::ScopesChanged { grant: "authorization_code" }.into());
-				}
-			}
+		Box::pin(async move {
+			let uri =
+				self.backchannel_uri.as_ref().ok_or(ConfigError::MissingBackchannelEndpoint)?;
+			let form = self.token_form(strategy, GrantType::Ciba, form, true)?;
 
-			let issued_at = OffsetDateTime::now_utc();
-			let mut builder = TokenRecord::builder(family, requested_scope.clone())
-				.access_token(response.access_token().secret().to_owned())
-				.issued_at(issued_at)
-				.expires_in(Duration::seconds(expires_in));
+			self.post_backchannel_form(meta.clone(), uri, form).await.map_err(|err| {
+				map_request_error(
+					strategy,
+					GrantType::Ciba,
+					meta.take(),
+					err,
+					self.error_mapper.as_ref(),
+				)
+			})
+		})
+	}
 
-			if let Some(refresh) = response.refresh_token() {
-				builder = builder.refresh_token(refresh.secret().to_owned());
-			}
+	fn exchange_ciba<'a, 'strategy, 'request, 'scope>(
+		&'a self,
+		strategy: &'strategy dyn ProviderStrategy,
+		family: TokenFamily,
+		auth_req_id: &'request str,
+		requested_scope: &'scope ScopeSet,
+	) -> FacadeFuture<'a, TokenRecord>
+	where
+		'strategy: 'a,
+		'request: 'a,
+		'scope: 'a,
+	{
+		let meta = ResponseMetadataSlot::default();
 
-			builder.build().map_err(|e| ConfigError::from(e).into())
+		Box::pin(async move {
+			let form = vec![
+				("grant_type".to_owned(), GrantType::Ciba.as_str().to_owned()),
+				("auth_req_id".to_owned(), auth_req_id.to_owned()),
+			];
+			let form = self.token_form(strategy, GrantType::Ciba, form, true)?;
+			let response = self
+				.post_token_form(GrantType::Ciba, meta.clone(), form)
+				.await
+				.map_err(|err| match err {
+					RequestTokenError::ServerResponse(response) =>
+						map_ciba_error(strategy, response, meta.take().as_ref()),
+					err => map_request_error(
+						strategy,
+						GrantType::Ciba,
+						meta.take(),
+						err,
+						self.error_mapper.as_ref(),
+					),
+				})?;
+
+			map_standard_token_response(family, requested_scope, response, &self.quirks, "ciba")
+				.map(|record| self.with_provenance(strategy, GrantType::Ciba, record))
 		})
 	}
 }
 
 fn map_standard_token_response(
 	family: TokenFamily,
-	scope: ScopeSet,
+	scope: &ScopeSet,
 	response: FacadeTokenResponse,
+	quirks: &ProviderQuirks,
+	grant_label: &'static str,
 ) -> Result<TokenRecord> {
-	let expires_in = response.expires_in().ok_or(ConfigError::MissingExpiresIn)?.as_secs();
-	let expires_in = i64::try_from(expires_in).map_err(|_| ConfigError::ExpiresInOutOfRange)?;
-
-	if expires_in <= 0 {
-		return Err(ConfigError::NonPositiveExpiresIn.into());
-	}
+	let lifetime = token_lifetime(&response, quirks)?;
 
-	if let Some(scopes) = response.scopes() {
-		let returned =
-			ScopeSet::new(scopes.iter().map(|scope| scope.as_ref())).map_err(ConfigError::from)?;
-		if returned != scope {
-			return Err(ConfigError::ScopesChanged { grant: "client_credentials" }.into());
-		}
-	}
+	ensure_scopes_unchanged(&response, scope, quirks.scope_delimiter, grant_label)?;
 
 	let issued_at = OffsetDateTime::now_utc();
 
-	TokenRecord::builder(family, scope)
-		.access_token(response.access_token().secret().to_owned())
-		.issued_at(issued_at)
-		.expires_in(Duration::seconds(expires_in))
+	record_builder(family, scope.clone(), &response, issued_at, lifetime)
 		.build()
 		.map_err(|err| ConfigError::from(err).into())
 }
@@ -364,27 +990,15 @@ fn map_refresh_token_response(
 	family: TokenFamily,
 	requested_scope: &ScopeSet,
 	response: FacadeTokenResponse,
+	quirks: &ProviderQuirks,
 ) -> Result<(TokenRecord, Option<String>)> {
-	let expires_in = response.expires_in().ok_or(ConfigError::MissingExpiresIn)?.as_secs();
-	let expires_in = i64::try_from(expires_in).map_err(|_| ConfigError::ExpiresInOutOfRange)?;
+	let lifetime = token_lifetime(&response, quirks)?;
 
-	if expires_in <= 0 {
-		return Err(ConfigError::NonPositiveExpiresIn.into());
-	}
-
-	if let Some(scopes) = response.scopes() {
-		let returned =
-			ScopeSet::new(scopes.iter().map(|scope| scope.as_ref())).map_err(ConfigError::from)?;
-		if returned != *requested_scope {
-			return Err(ConfigError::ScopesChanged { grant: "refresh_token" }.into());
-		}
-	}
+	ensure_scopes_unchanged(&response, requested_scope, quirks.scope_delimiter, "refresh_token")?;
 
 	let issued_at = OffsetDateTime::now_utc();
-	let mut builder = TokenRecord::builder(family, requested_scope.clone())
-		.access_token(response.access_token().secret().to_owned())
-		.issued_at(issued_at)
-		.expires_in(Duration::seconds(expires_in));
+	let mut builder =
+		record_builder(family, requested_scope.clone(), &response, issued_at, lifetime);
 	let new_refresh = response.refresh_token().map(|token| token.secret().to_owned());
 
 	if let Some(secret) = &new_refresh {
@@ -396,6 +1010,101 @@ fn map_refresh_token_response(
 	Ok((record, new_refresh))
 }
 
+/// Maps a token endpoint response body obtained outside the broker into a record.
+///
+/// The body gets the same expiry and scope handling as a live grant response, with
+/// `received_at` standing in for the issue time.
+pub(crate) fn map_imported_token_response(
+	family: TokenFamily,
+	scope: &ScopeSet,
+	body: &str,
+	quirks: &ProviderQuirks,
+	received_at: OffsetDateTime,
+) -> Result<TokenRecord> {
+	let response = serde_json::from_str::<FacadeTokenResponse>(body)
+		.map_err(|e| ConfigError::InvalidImportedToken { message: e.to_string() })?;
+	let lifetime = token_lifetime(&response, quirks)?;
+
+	ensure_scopes_unchanged(&response, scope, quirks.scope_delimiter, "import").map_err(|_| {
+		ConfigError::InvalidImportedToken {
+			message: "response scopes differ from the requested scope set".into(),
+		}
+	})?;
+
+	let mut builder = record_builder(family, scope.clone(), &response, received_at, lifetime);
+
+	if let Some(refresh) = response.refresh_token() {
+		builder = builder.refresh_token(refresh.secret().to_owned());
+	}
+
+	builder.build().map_err(|err| ConfigError::from(err).into())
+}
+
+/// Seeds a record builder with the fields shared by every grant response.
+fn record_builder(
+	family: TokenFamily,
+	scope: ScopeSet,
+	response: &FacadeTokenResponse,
+	issued_at: OffsetDateTime,
+	lifetime: Duration,
+) -> TokenRecordBuilder {
+	let mut builder = TokenRecord::builder(family, scope)
+		.access_token(response.access_token().secret().to_owned())
+		.issued_at(issued_at)
+		.expires_in(lifetime);
+
+	for (key, value) in response.extra_fields().record_extras() {
+		builder = builder.extra(key, value);
+	}
+
+	match response.extra_fields().refresh_lifetime() {
+		Some(refresh_lifetime) => builder.refresh_expires_in(refresh_lifetime),
+		None => builder,
+	}
+}
+
+/// Resolves the token lifetime, falling back to the provider's assumed lifetime when the
+/// response omits `expires_in`.
+fn token_lifetime(response: &FacadeTokenResponse, quirks: &ProviderQuirks) -> Result<Duration> {
+	let expires_in = match response.expires_in() {
+		Some(expires_in) => expires_in.as_secs(),
+		None => quirks.default_expires_in_secs.ok_or(ConfigError::MissingExpiresIn)?,
+	};
+	let expires_in = i64::try_from(expires_in).map_err(|_| ConfigError::ExpiresInOutOfRange)?;
+
+	if expires_in <= 0 {
+		return Err(ConfigError::NonPositiveExpiresIn.into());
+	}
+
+	Ok(Duration::seconds(expires_in))
+}
+
+/// Rejects responses whose granted scopes differ from the requested set.
+fn ensure_scopes_unchanged(
+	response: &FacadeTokenResponse,
+	requested: &ScopeSet,
+	delimiter: char,
+	grant: &'static str,
+) -> Result<()> {
+	if let Some(scopes) = response.scopes() {
+		// Compare borrowed, sorted scope names instead of building a normalized `ScopeSet`.
+		let mut returned = scopes
+			.iter()
+			.flat_map(|scope| scope.split(delimiter))
+			.filter(|scope| !scope.is_empty())
+			.collect::<Vec<_>>();
+
+		returned.sort_unstable();
+		returned.dedup();
+
+		if !returned.iter().copied().eq(requested.iter()) {
+			return Err(ConfigError::ScopesChanged { grant }.into());
+		}
+	}
+
+	Ok(())
+}
+
 fn map_request_error<E, M>(
 	strategy: &dyn ProviderStrategy,
 	grant: GrantType,
@@ -414,6 +1123,10 @@ where
 			map_server_response_error(strategy, grant, response, meta_ref),
 		RequestTokenError::Request(error) =>
 			map_transport_error(strategy, grant, meta_ref, error, mapper),
+		RequestTokenError::Parse(_, body) if is_error_status(meta_ref) =>
+			map_unparsed_error_response(strategy, grant, &body, meta_ref),
+		RequestTokenError::Other(_) if is_error_status(meta_ref) =>
+			map_unparsed_error_response(strategy, grant, &[], meta_ref),
 		RequestTokenError::Parse(error, _body) =>
 			TransientError::TokenResponseParse { source: error, status: meta_status(meta_ref) }
 				.into(),
@@ -426,6 +1139,38 @@ where
 	}
 }
 
+/// Extracts the `cnf` confirmation claim from a JWT access token; opaque tokens yield `None`.
+fn cnf_binding(access_token: &str) -> Option<TokenBinding> {
+	let mut segments = access_token.split('.');
+	let (Some(_), Some(payload), Some(_), None) =
+		(segments.next(), segments.next(), segments.next(), segments.next())
+	else {
+		return None;
+	};
+	let claims =
+		serde_json::from_slice::<serde_json::Value>(&URL_SAFE_NO_PAD.decode(payload).ok()?).ok()?;
+
+	TokenBinding::from_cnf_claim(claims.get("cnf")?)
+}
+
+/// Maps the CIBA-specific token endpoint errors (CIBA §11) before falling back to the strategy's
+/// classification.
+fn map_ciba_error(
+	strategy: &dyn ProviderStrategy,
+	response: BasicErrorResponse,
+	meta: Option<&ResponseMetadata>,
+) -> Error {
+	match response.error().as_ref() {
+		"authorization_pending" => TransientError::AuthorizationPending { slow_down: false }.into(),
+		"slow_down" => TransientError::AuthorizationPending { slow_down: true }.into(),
+		"expired_token" => Error::InvalidGrant {
+			reason: "The backchannel authentication request expired before the user approved it."
+				.into(),
+		},
+		_ => map_server_response_error(strategy, GrantType::Ciba, response, meta),
+	}
+}
+
 fn map_server_response_error(
 	strategy: &dyn ProviderStrategy,
 	grant: GrantType,
@@ -441,18 +1186,81 @@ fn map_server_response_error(
 	if let Some(status) = meta_status(meta) {
 		ctx = ctx.with_http_status(status);
 	}
+	for (name, value) in meta.into_iter().flat_map(|value| &value.headers) {
+		ctx = ctx.with_header(name, value.clone());
+	}
 
-	let classification = strategy.classify_token_error(&ctx);
 	let message = if let Some(description) = response.error_description() {
 		format!("Token endpoint returned an OAuth error: {description}.")
 	} else {
 		format!("Token endpoint returned an OAuth error: {}.", response.error().as_ref())
 	};
 
-	match classification {
+	classify_error_response(strategy, &ctx, message, meta)
+}
+
+/// Classifies a non-2xx response whose body is empty or not an OAuth error, such as a bare
+/// `401` that only carries a `WWW-Authenticate` challenge.
+///
+/// The challenge's `error`/`error_description` stand in for the missing body fields, so the
+/// strategy sees the same context it would for a JSON error.
+fn map_unparsed_error_response(
+	strategy: &dyn ProviderStrategy,
+	grant: GrantType,
+	body: &[u8],
+	meta: Option<&ResponseMetadata>,
+) -> Error {
+	let challenge = meta
+		.and_then(|value| value.headers.get("www-authenticate"))
+		.and_then(|header| BearerChallenge::parse(header))
+		.unwrap_or_default();
+	let mut ctx = ProviderErrorContext::new(grant);
+
+	if let Some(error) = &challenge.error {
+		ctx = ctx.with_oauth_error(error.clone());
+	}
+	if let Some(description) = &challenge.error_description {
+		ctx = ctx.with_error_description(description.clone());
+	}
+	if let Some(status) = meta_status(meta) {
+		ctx = ctx.with_http_status(status);
+	}
+	for (name, value) in meta.into_iter().flat_map(|value| &value.headers) {
+		ctx = ctx.with_header(name, value.clone());
+	}
+	if !body.is_empty() {
+		ctx = ctx.with_body_preview(String::from_utf8_lossy(body));
+	}
+
+	let message = match (&challenge.error_description, &challenge.error, meta_status(meta)) {
+		(Some(description), ..) =>
+			format!("Token endpoint returned an OAuth error: {description}."),
+		(None, Some(error), _) => format!("Token endpoint returned an OAuth error: {error}."),
+		(None, None, Some(status)) =>
+			format!("Token endpoint returned HTTP {status} without an OAuth error body."),
+		(None, None, None) =>
+			"Token endpoint returned an error without an OAuth error body.".into(),
+	};
+
+	classify_error_response(strategy, &ctx, message, meta)
+}
+
+fn classify_error_response(
+	strategy: &dyn ProviderStrategy,
+	ctx: &ProviderErrorContext,
+	message: String,
+	meta: Option<&ResponseMetadata>,
+) -> Error {
+	match strategy.classify_token_error(ctx) {
 		ProviderErrorKind::InvalidGrant => Error::InvalidGrant { reason: message },
 		ProviderErrorKind::InvalidClient => Error::InvalidClient { reason: message },
-		ProviderErrorKind::InsufficientScope => Error::InsufficientScope { reason: message },
+		ProviderErrorKind::InsufficientScope => Error::InsufficientScope {
+			reason: message,
+			required: ctx
+				.header("www-authenticate")
+				.and_then(BearerChallenge::parse)
+				.and_then(|challenge| challenge.scope),
+		},
 		ProviderErrorKind::Transient => TransientError::TokenEndpoint {
 			message,
 			status: meta_status(meta),
@@ -523,6 +1331,11 @@ fn meta_status(meta: Option<&ResponseMetadata>) -> Option<u16> {
 	meta.and_then(|value| value.status)
 }
 
+/// Returns `true` when the response carried a non-2xx status.
+fn is_error_status(meta: Option<&ResponseMetadata>) -> bool {
+	meta_status(meta).is_some_and(|status| !(200..300).contains(&status))
+}
+
 fn meta_retry_after(meta: Option<&ResponseMetadata>) -> Option<Duration> {
 	meta.and_then(|value| value.retry_after)
 }
@@ -603,4 +1416,20 @@ mod tests {
 
 		assert!(result.is_ok());
 	}
+
+	#[test]
+	fn cnf_binding_reads_jwt_confirmation_claim() {
+		let jwt = |claims: &str| format!("e30.{}.sig", URL_SAFE_NO_PAD.encode(claims));
+
+		assert_eq!(
+			cnf_binding(&jwt(r#"{"sub":"svc","cnf":{"x5t#S256":"thumb"}}"#)),
+			Some(TokenBinding::Certificate { x5t_s256: "thumb".into() })
+		);
+		assert_eq!(
+			cnf_binding(&jwt(r#"{"cnf":{"jkt":"key"}}"#)),
+			Some(TokenBinding::Dpop { jkt: "key".into() })
+		);
+		assert_eq!(cnf_binding(&jwt(r#"{"sub":"svc"}"#)), None);
+		assert_eq!(cnf_binding("opaque-token"), None);
+	}
 }
@@ -6,18 +6,46 @@
 //!   and `stage` (call site) fields.
 //! - Enable `metrics` to increment the `oauth2_broker_flow_total` counter for every
 //!   attempt/success/failure, labeled by `flow` + `outcome`.
+//! - Revocations emit audit events through [`record_revocation`] (an `oauth2_broker::audit` tracing
+//!   event and the `oauth2_broker_revocation_total` counter, labeled by `reason`); delegated
+//!   records additionally emit [`record_delegated_issuance`] when persisted, and tenant erasure
+//!   emits [`record_tenant_erasure`].
+//! - [`TraceContext`] carries a caller's W3C trace context onto token endpoint calls as
+//!   `traceparent`/`tracestate` headers, independently of the `tracing` feature.
+//! - [`InstrumentedStore`] wraps any [`BrokerStore`](crate::store::BrokerStore) and reports each
+//!   call as an `oauth2_broker.store` span plus the `oauth2_broker_store_ops_total` counter and
+//!   `oauth2_broker_store_op_duration_seconds` histogram, labeled by `op` + `outcome`.
+//! - [`Diagnostics`] collects non-fatal configuration findings (ignored client secrets, missing
+//!   revocation endpoints, relaxed scope handling) as structured warnings for logs, custom
+//!   [`DiagnosticsSink`]s, and the health report.
+//! - Opting in through
+//!   [`BrokerOverrides::call_summaries`](crate::flows::BrokerOverrides::call_summaries) emits one
+//!   redacted [`CallSummary`] per cached-token call (provider, grant, cache outcome, refresh
+//!   rotation, latency, status) under the `oauth2_broker::call_summary` target, a middle ground
+//!   between audit events and bare metrics.
 
+mod audit;
+mod diagnostics;
 mod metrics;
+mod propagation;
+mod store;
+mod summary;
 mod tracing;
 
+pub use audit::*;
+pub use diagnostics::*;
 pub use metrics::*;
+pub use propagation::*;
+pub use store::*;
+pub use summary::*;
 pub use tracing::*;
 
 // self
 use crate::_prelude::*;
 
 /// OAuth flow kinds observed by the broker.
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum FlowKind {
 	/// Authorization Code + PKCE grant helpers.
 	AuthorizationCode,
@@ -25,6 +53,10 @@ pub enum FlowKind {
 	Refresh,
 	/// Client Credentials flow.
 	ClientCredentials,
+	/// JWT Bearer assertion flow.
+	JwtBearer,
+	/// Client Initiated Backchannel Authentication flow.
+	Backchannel,
 }
 impl FlowKind {
 	/// Returns a stable label suitable for span or metric fields.
@@ -33,6 +65,8 @@ impl FlowKind {
 			FlowKind::AuthorizationCode => "authorization_code",
 			FlowKind::Refresh => "refresh",
 			FlowKind::ClientCredentials => "client_credentials",
+			FlowKind::JwtBearer => "jwt_bearer",
+			FlowKind::Backchannel => "backchannel",
 		}
 	}
 }
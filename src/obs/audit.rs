@@ -0,0 +1,102 @@
+// self
+use crate::auth::{RevocationReason, TenantId, TokenRecord};
+
+/// Emits an audit event for a revoked record.
+///
+/// With `tracing` enabled the event is logged at `INFO` under the `oauth2_broker::audit` target,
+/// including the delegated actor and chain depth when present; with `metrics` enabled the
+/// `oauth2_broker_revocation_total` counter is incremented, labeled by `reason`.
+pub fn record_revocation(record: &TokenRecord, reason: RevocationReason) {
+	#[cfg(feature = "tracing")]
+	{
+		let family = &record.family;
+		let delegation = record.delegation.as_ref();
+
+		tracing::info!(
+			target: "oauth2_broker::audit",
+			tenant = %family.tenant,
+			principal = %family.principal,
+			provider = family.provider.as_ref().map(|id| id.as_ref()),
+			actor = delegation.map(|delegation| delegation.actor.as_str()),
+			delegation_depth = delegation.map(|delegation| delegation.depth),
+			reason = reason.as_str(),
+			"token record revoked"
+		);
+	}
+	#[cfg(feature = "metrics")]
+	{
+		metrics::counter!("oauth2_broker_revocation_total", "reason" => reason.as_str())
+			.increment(1);
+	}
+
+	#[cfg(not(feature = "tracing"))]
+	{
+		let _ = record;
+	}
+	#[cfg(not(any(feature = "tracing", feature = "metrics")))]
+	{
+		let _ = reason;
+	}
+}
+
+/// Emits an audit event when a delegated record is persisted; records without
+/// [`delegation`](TokenRecord::delegation) metadata are ignored.
+///
+/// With `tracing` enabled the event is logged at `INFO` under the `oauth2_broker::audit` target;
+/// with `metrics` enabled the `oauth2_broker_delegated_issuance_total` counter is incremented.
+pub fn record_delegated_issuance(record: &TokenRecord) {
+	let Some(delegation) = record.delegation.as_ref() else {
+		return;
+	};
+
+	#[cfg(feature = "tracing")]
+	{
+		let family = &record.family;
+
+		tracing::info!(
+			target: "oauth2_broker::audit",
+			tenant = %family.tenant,
+			principal = %family.principal,
+			provider = family.provider.as_ref().map(|id| id.as_ref()),
+			actor = delegation.actor.as_str(),
+			delegation_depth = delegation.depth,
+			"delegated token issued"
+		);
+	}
+	#[cfg(feature = "metrics")]
+	{
+		metrics::counter!("oauth2_broker_delegated_issuance_total").increment(1);
+	}
+
+	#[cfg(not(feature = "tracing"))]
+	{
+		let _ = delegation;
+	}
+}
+
+/// Emits an audit event once a tenant's records were erased by
+/// [`Broker::forget_tenant`](crate::flows::Broker::forget_tenant).
+///
+/// With `tracing` enabled the event is logged at `INFO` under the `oauth2_broker::audit` target;
+/// with `metrics` enabled the `oauth2_broker_tenant_erasure_total` counter is incremented.
+pub fn record_tenant_erasure(tenant: &TenantId, deleted: usize, keys_destroyed: bool) {
+	#[cfg(feature = "tracing")]
+	{
+		tracing::info!(
+			target: "oauth2_broker::audit",
+			tenant = %tenant,
+			deleted,
+			keys_destroyed,
+			"tenant erased"
+		);
+	}
+	#[cfg(feature = "metrics")]
+	{
+		metrics::counter!("oauth2_broker_tenant_erasure_total").increment(1);
+	}
+
+	#[cfg(not(feature = "tracing"))]
+	{
+		let _ = (tenant, deleted, keys_destroyed);
+	}
+}
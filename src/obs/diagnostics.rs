@@ -0,0 +1,156 @@
+// self
+use crate::{_prelude::*, auth::ProviderId};
+
+/// Receives every new [`Diagnostic`] reported to a [`Diagnostics`] channel.
+pub trait DiagnosticsSink: Send + Sync {
+	/// Handles one finding; called once per distinct diagnostic.
+	fn report(&self, diagnostic: &Diagnostic);
+}
+impl<F> DiagnosticsSink for F
+where
+	F: Send + Sync + Fn(&Diagnostic),
+{
+	fn report(&self, diagnostic: &Diagnostic) {
+		self(diagnostic)
+	}
+}
+
+/// Category of a non-fatal configuration finding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticKind {
+	/// A client secret is configured, but some grants authenticate with `none` and never send it.
+	IgnoredClientSecret,
+	/// A feature that calls the revocation endpoint is enabled, but the endpoint is not declared
+	/// or is switched off, so revocation only happens locally.
+	MissingRevocationEndpoint,
+	/// Scope handling was loosened from the broker's strict default (reported by strategies or
+	/// integrations that accept scope sets other than the requested one).
+	ScopePolicyRelaxed,
+}
+impl DiagnosticKind {
+	/// Returns a stable label suitable for log fields.
+	pub const fn as_str(self) -> &'static str {
+		match self {
+			DiagnosticKind::IgnoredClientSecret => "ignored_client_secret",
+			DiagnosticKind::MissingRevocationEndpoint => "missing_revocation_endpoint",
+			DiagnosticKind::ScopePolicyRelaxed => "scope_policy_relaxed",
+		}
+	}
+}
+impl Display for DiagnosticKind {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		f.write_str(self.as_str())
+	}
+}
+
+/// Structured warning about configuration the broker accepts but probably should not.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostic {
+	/// Category of the finding.
+	pub kind: DiagnosticKind,
+	/// Descriptor the finding belongs to.
+	pub provider: ProviderId,
+	/// Human-readable explanation.
+	pub message: String,
+}
+impl Diagnostic {
+	/// Creates a diagnostic for `provider`.
+	pub fn new(kind: DiagnosticKind, provider: ProviderId, message: impl Into<String>) -> Self {
+		Self { kind, provider, message: message.into() }
+	}
+}
+
+/// Warn-and-continue channel for non-fatal configuration findings.
+///
+/// Each distinct [`Diagnostic`] is kept for [`Diagnostics::entries`] (and therefore the health
+/// report), logged as a `WARN` event under the `oauth2_broker::diagnostics` target with the
+/// `tracing` feature, and forwarded to every attached [`DiagnosticsSink`]. Repeated reports of
+/// the same finding are dropped, so flows may report on every call.
+#[derive(Default)]
+pub struct Diagnostics {
+	entries: Mutex<Vec<Diagnostic>>,
+	sinks: Vec<Arc<dyn DiagnosticsSink>>,
+}
+impl Diagnostics {
+	/// Creates a channel without sinks.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Forwards every new finding to `sink`.
+	pub fn with_sink<S>(mut self, sink: S) -> Self
+	where
+		S: 'static + DiagnosticsSink,
+	{
+		self.sinks.push(Arc::new(sink));
+
+		self
+	}
+
+	/// Records `diagnostic`, returning `false` when the same finding was already reported.
+	pub fn report(&self, diagnostic: Diagnostic) -> bool {
+		{
+			let mut entries = self.entries.lock();
+
+			if entries.contains(&diagnostic) {
+				return false;
+			}
+
+			entries.push(diagnostic.clone());
+		}
+
+		#[cfg(feature = "tracing")]
+		tracing::warn!(
+			target: "oauth2_broker::diagnostics",
+			provider = %diagnostic.provider,
+			kind = diagnostic.kind.as_str(),
+			"{}",
+			diagnostic.message
+		);
+
+		for sink in &self.sinks {
+			sink.report(&diagnostic);
+		}
+
+		true
+	}
+
+	/// Returns every finding reported so far, in report order.
+	pub fn entries(&self) -> Vec<Diagnostic> {
+		self.entries.lock().clone()
+	}
+}
+impl Debug for Diagnostics {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		f.debug_struct("Diagnostics")
+			.field("entries", &self.entries.lock().len())
+			.field("sinks", &self.sinks.len())
+			.finish()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	// std
+	use std::sync::atomic::{AtomicUsize, Ordering};
+	// self
+	use super::*;
+
+	#[test]
+	fn repeated_findings_reach_sinks_once() {
+		let forwarded = Arc::new(AtomicUsize::new(0));
+		let counter = forwarded.clone();
+		let diagnostics = Diagnostics::new().with_sink(move |_: &Diagnostic| {
+			counter.fetch_add(1, Ordering::Relaxed);
+		});
+		let provider = ProviderId::new("diag").expect("Provider fixture should be valid.");
+		let finding =
+			Diagnostic::new(DiagnosticKind::ScopePolicyRelaxed, provider, "Scopes are relaxed.");
+
+		assert!(diagnostics.report(finding.clone()));
+		assert!(!diagnostics.report(finding.clone()));
+		assert_eq!(diagnostics.entries(), [finding]);
+		assert_eq!(forwarded.load(Ordering::Relaxed), 1);
+	}
+}
@@ -1,5 +1,10 @@
+// std
+use std::time::Duration as StdDuration;
 // self
-use crate::obs::{FlowKind, FlowOutcome};
+use crate::{
+	auth::TokenRecord,
+	obs::{FlowKind, FlowOutcome},
+};
 
 /// Records a flow outcome via the global metrics recorder (when enabled).
 pub fn record_flow_outcome(kind: FlowKind, outcome: FlowOutcome) {
@@ -19,6 +24,52 @@ pub fn record_flow_outcome(kind: FlowKind, outcome: FlowOutcome) {
 	}
 }
 
+/// Records refresh bookkeeping once `refreshed` replaced `previous` in the store.
+///
+/// With `metrics` enabled, `oauth2_broker_refresh_count` observes how many refreshes the family
+/// has seen and `oauth2_broker_refresh_idle_seconds` how long the family sat unused before this
+/// refresh; long idle times flag families that are safe to purge.
+pub fn record_refresh_bookkeeping(previous: &TokenRecord, refreshed: &TokenRecord) {
+	#[cfg(feature = "metrics")]
+	{
+		let idle = refreshed.issued_at - previous.last_activity();
+
+		metrics::histogram!("oauth2_broker_refresh_count")
+			.record(f64::from(refreshed.refresh_count));
+		metrics::histogram!("oauth2_broker_refresh_idle_seconds")
+			.record(idle.as_seconds_f64().max(0.));
+	}
+
+	#[cfg(not(feature = "metrics"))]
+	{
+		let _ = (previous, refreshed);
+	}
+}
+
+/// Records one [`InstrumentedStore`](crate::obs::InstrumentedStore) call.
+///
+/// With `metrics` enabled, `oauth2_broker_store_ops_total` counts calls and
+/// `oauth2_broker_store_op_duration_seconds` observes their latency, both labeled by `op` +
+/// `outcome`.
+pub fn record_store_operation(op: &'static str, outcome: &'static str, elapsed: StdDuration) {
+	#[cfg(feature = "metrics")]
+	{
+		metrics::counter!("oauth2_broker_store_ops_total", "op" => op, "outcome" => outcome)
+			.increment(1);
+		metrics::histogram!(
+			"oauth2_broker_store_op_duration_seconds",
+			"op" => op,
+			"outcome" => outcome
+		)
+		.record(elapsed.as_secs_f64());
+	}
+
+	#[cfg(not(feature = "metrics"))]
+	{
+		let _ = (op, outcome, elapsed);
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	// self
@@ -0,0 +1,139 @@
+// crates.io
+use rand::Rng;
+
+/// W3C Trace Context (`traceparent`/`tracestate`) propagated onto token endpoint calls.
+///
+/// Attach one to a request with
+/// [`CachedTokenRequest::with_trace_context`](crate::flows::CachedTokenRequest::with_trace_context);
+/// every token call the flow makes then carries a `traceparent` header that keeps the caller's
+/// trace id and names a fresh span id for the outbound call, so distributed traces span
+/// application, broker, and provider.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceContext {
+	/// 32 lowercase hex digits identifying the whole trace.
+	pub trace_id: String,
+	/// 16 lowercase hex digits identifying the caller's span.
+	pub parent_id: String,
+	/// Trace flags (bit 0 is `sampled`).
+	pub flags: u8,
+	/// Vendor-specific `tracestate` header forwarded verbatim.
+	pub tracestate: Option<String>,
+}
+impl TraceContext {
+	const VERSION: &str = "00";
+
+	/// Parses a `traceparent` header value, returning `None` when it is malformed.
+	///
+	/// Versions above `00` are accepted as long as their first four fields have the `00` layout,
+	/// as the specification requires.
+	pub fn from_traceparent(value: &str) -> Option<Self> {
+		let mut fields = value.trim().split('-');
+		let version = fields.next()?;
+		let trace_id = fields.next()?;
+		let parent_id = fields.next()?;
+		let flags = fields.next()?;
+		let valid = is_hex(version, 2)
+			&& version != "ff"
+			&& (version != Self::VERSION || fields.next().is_none())
+			&& is_hex(trace_id, 32)
+			&& is_hex(parent_id, 16)
+			&& is_hex(flags, 2)
+			&& trace_id.bytes().any(|b| b != b'0')
+			&& parent_id.bytes().any(|b| b != b'0');
+
+		valid.then(|| Self {
+			trace_id: trace_id.into(),
+			parent_id: parent_id.into(),
+			flags: u8::from_str_radix(flags, 16).unwrap_or_default(),
+			tracestate: None,
+		})
+	}
+
+	/// Starts a new sampled trace rooted at the broker.
+	pub fn new_root() -> Self {
+		Self {
+			trace_id: random_hex::<16>(),
+			parent_id: random_hex::<8>(),
+			flags: 1,
+			tracestate: None,
+		}
+	}
+
+	/// Forwards a `tracestate` header alongside `traceparent`.
+	pub fn with_tracestate(mut self, tracestate: impl Into<String>) -> Self {
+		self.tracestate = Some(tracestate.into());
+
+		self
+	}
+
+	/// Returns `true` when the caller sampled the trace.
+	pub fn sampled(&self) -> bool {
+		self.flags & 1 == 1
+	}
+
+	/// Context for an outbound call: same trace, a fresh span id.
+	pub fn child(&self) -> Self {
+		Self { parent_id: random_hex::<8>(), ..self.clone() }
+	}
+
+	/// Renders the `traceparent` header value.
+	pub fn traceparent(&self) -> String {
+		format!("{}-{}-{}-{:02x}", Self::VERSION, self.trace_id, self.parent_id, self.flags)
+	}
+}
+
+fn is_hex(value: &str, len: usize) -> bool {
+	value.len() == len && value.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
+
+fn random_hex<const N: usize>() -> String {
+	let mut bytes = [0_u8; N];
+
+	// All-zero ids are invalid, so redraw in the (vanishingly unlikely) all-zero case.
+	while bytes.iter().all(|b| *b == 0) {
+		rand::rng().fill(&mut bytes[..]);
+	}
+
+	bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+	// self
+	use super::*;
+
+	#[test]
+	fn traceparent_round_trips_and_rejects_malformed_values() {
+		let raw = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+		let context = TraceContext::from_traceparent(raw).expect("Valid traceparent should parse.");
+
+		assert_eq!(context.traceparent(), raw);
+		assert!(context.sampled());
+
+		let child = context.child();
+
+		assert_eq!(child.trace_id, context.trace_id);
+		assert_ne!(child.parent_id, context.parent_id);
+		assert!(TraceContext::from_traceparent(&child.traceparent()).is_some());
+		assert!(
+			TraceContext::from_traceparent(
+				"01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-future"
+			)
+			.is_some()
+		);
+
+		for invalid in [
+			"00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+			"ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+			"00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+			"00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+			"00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+			"00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+		] {
+			assert!(
+				TraceContext::from_traceparent(invalid).is_none(),
+				"{invalid} should be rejected."
+			);
+		}
+	}
+}
@@ -0,0 +1,352 @@
+// std
+use std::time::Instant;
+// self
+use crate::{
+	_prelude::*,
+	auth::{
+		PrincipalPath, ProviderId, RevocationReason, ScopeSet, TenantId, TokenFamily, TokenRecord,
+	},
+	obs::record_store_operation,
+	store::{
+		BrokerStore, CompareAndSwapOutcome, ConditionalFetch, PreparedWrite, RecordPage,
+		StoreFuture,
+	},
+};
+
+/// [`BrokerStore`] decorator that observes every call made to the wrapped store.
+///
+/// Each operation runs inside an `oauth2_broker.store` span (with `tracing`) carrying the `op`
+/// name, a `key` fingerprint for single-record calls, and the final `outcome`; with `metrics` the
+/// `oauth2_broker_store_ops_total` counter and the `oauth2_broker_store_op_duration_seconds`
+/// histogram are labeled by `op` + `outcome`. Compare-and-swap calls report `refresh_mismatch`,
+/// `version_mismatch`, and `missing` as outcomes of their own, so CAS contention is visible next to
+/// backend errors. The key fingerprint is a truncated SHA-256 of the
+/// [`StoreKey`](crate::store::StoreKey), so spans never carry tenant or principal identifiers.
+#[derive(Debug)]
+pub struct InstrumentedStore<S> {
+	inner: S,
+}
+impl<S> InstrumentedStore<S> {
+	/// Wraps `inner` so its calls are traced and measured.
+	pub fn new(inner: S) -> Self {
+		Self { inner }
+	}
+
+	/// Returns the wrapped store.
+	pub fn inner(&self) -> &S {
+		&self.inner
+	}
+
+	/// Unwraps the decorator, returning the wrapped store.
+	pub fn into_inner(self) -> S {
+		self.inner
+	}
+}
+impl<S> BrokerStore for InstrumentedStore<S>
+where
+	S: BrokerStore,
+{
+	fn save(&self, record: TokenRecord) -> StoreFuture<'_, ()> {
+		let key = StoreOp::key(&record.family, &record.scope);
+
+		StoreOp::new("save", key).observe(self.inner.save(record), ok)
+	}
+
+	fn fetch<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+	) -> StoreFuture<'a, Option<TokenRecord>> {
+		StoreOp::new("fetch", StoreOp::key(family, scope))
+			.observe(self.inner.fetch(family, scope), found)
+	}
+
+	fn compare_and_swap_refresh<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+		expected_refresh: Option<&'a str>,
+		replacement: TokenRecord,
+	) -> StoreFuture<'a, CompareAndSwapOutcome> {
+		StoreOp::new("compare_and_swap_refresh", StoreOp::key(family, scope)).observe(
+			self.inner.compare_and_swap_refresh(family, scope, expected_refresh, replacement),
+			swapped,
+		)
+	}
+
+	fn compare_and_swap_version<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+		expected_version: u64,
+		replacement: TokenRecord,
+	) -> StoreFuture<'a, CompareAndSwapOutcome> {
+		StoreOp::new("compare_and_swap_version", StoreOp::key(family, scope)).observe(
+			self.inner.compare_and_swap_version(family, scope, expected_version, replacement),
+			swapped,
+		)
+	}
+
+	fn fetch_if_changed<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+		known_fingerprint: Option<&'a str>,
+	) -> StoreFuture<'a, ConditionalFetch> {
+		StoreOp::new("fetch_if_changed", StoreOp::key(family, scope)).observe(
+			self.inner.fetch_if_changed(family, scope, known_fingerprint),
+			|fetch| match fetch {
+				ConditionalFetch::Changed { .. } => "changed",
+				ConditionalFetch::Unchanged => "unchanged",
+				ConditionalFetch::Missing => "missing",
+			},
+		)
+	}
+
+	fn revoke<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+		instant: OffsetDateTime,
+		reason: RevocationReason,
+	) -> StoreFuture<'a, Option<TokenRecord>> {
+		StoreOp::new("revoke", StoreOp::key(family, scope))
+			.observe(self.inner.revoke(family, scope, instant, reason), found)
+	}
+
+	fn prepare(&self, record: TokenRecord) -> StoreFuture<'_, PreparedWrite> {
+		let key = StoreOp::key(&record.family, &record.scope);
+
+		StoreOp::new("prepare", key).observe(self.inner.prepare(record), ok)
+	}
+
+	fn commit<'a>(&'a self, prepared: &'a PreparedWrite) -> StoreFuture<'a, ()> {
+		let key = StoreOp::key(&prepared.record.family, &prepared.record.scope);
+
+		StoreOp::new("commit", key).observe(self.inner.commit(prepared), ok)
+	}
+
+	fn rollback<'a>(&'a self, prepared: &'a PreparedWrite) -> StoreFuture<'a, ()> {
+		let key = StoreOp::key(&prepared.record.family, &prepared.record.scope);
+
+		StoreOp::new("rollback", key).observe(self.inner.rollback(prepared), ok)
+	}
+
+	fn pending_writes(&self) -> StoreFuture<'_, Vec<PreparedWrite>> {
+		StoreOp::new("pending_writes", None).observe(self.inner.pending_writes(), ok)
+	}
+
+	fn health_check(&self) -> StoreFuture<'_, ()> {
+		StoreOp::new("health_check", None).observe(self.inner.health_check(), ok)
+	}
+
+	fn purge_revoked(&self, cutoff: OffsetDateTime) -> StoreFuture<'_, usize> {
+		StoreOp::new("purge_revoked", None).observe(self.inner.purge_revoked(cutoff), ok)
+	}
+
+	fn list_principal_subtree<'a>(
+		&'a self,
+		tenant: &'a TenantId,
+		prefix: &'a PrincipalPath,
+	) -> StoreFuture<'a, Vec<TokenRecord>> {
+		StoreOp::new("list_principal_subtree", None)
+			.observe(self.inner.list_principal_subtree(tenant, prefix), ok)
+	}
+
+	fn revoke_principal_subtree<'a>(
+		&'a self,
+		tenant: &'a TenantId,
+		prefix: &'a PrincipalPath,
+		instant: OffsetDateTime,
+		reason: RevocationReason,
+	) -> StoreFuture<'a, Vec<TokenRecord>> {
+		StoreOp::new("revoke_principal_subtree", None)
+			.observe(self.inner.revoke_principal_subtree(tenant, prefix, instant, reason), ok)
+	}
+
+	fn revoke_all<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		instant: OffsetDateTime,
+		reason: RevocationReason,
+	) -> StoreFuture<'a, Vec<TokenRecord>> {
+		StoreOp::new("revoke_all", None).observe(self.inner.revoke_all(family, instant, reason), ok)
+	}
+
+	fn list_records<'a>(
+		&'a self,
+		after: Option<&'a str>,
+		limit: usize,
+	) -> StoreFuture<'a, RecordPage> {
+		StoreOp::new("list_records", None).observe(self.inner.list_records(after, limit), ok)
+	}
+
+	fn list_tenant_records<'a>(
+		&'a self,
+		tenant: &'a TenantId,
+		provider: Option<&'a ProviderId>,
+	) -> StoreFuture<'a, Vec<TokenRecord>> {
+		StoreOp::new("list_tenant_records", None)
+			.observe(self.inner.list_tenant_records(tenant, provider), ok)
+	}
+
+	fn delete_tenant<'a>(&'a self, tenant: &'a TenantId) -> StoreFuture<'a, Vec<TokenRecord>> {
+		StoreOp::new("delete_tenant", None).observe(self.inner.delete_tenant(tenant), ok)
+	}
+
+	fn destroy_tenant_keys<'a>(&'a self, tenant: &'a TenantId) -> StoreFuture<'a, bool> {
+		StoreOp::new("destroy_tenant_keys", None)
+			.observe(self.inner.destroy_tenant_keys(tenant), ok)
+	}
+
+	fn compare_and_swap_family<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		expected_refresh: Option<&'a str>,
+		records: Vec<TokenRecord>,
+	) -> StoreFuture<'a, CompareAndSwapOutcome> {
+		StoreOp::new("compare_and_swap_family", None)
+			.observe(self.inner.compare_and_swap_family(family, expected_refresh, records), swapped)
+	}
+}
+
+/// Operation name and span of one observed store call.
+struct StoreOp {
+	op: &'static str,
+	#[cfg(feature = "tracing")]
+	span: tracing::Span,
+}
+impl StoreOp {
+	fn new(op: &'static str, key: Option<String>) -> Self {
+		#[cfg(feature = "tracing")]
+		{
+			let span = tracing::debug_span!(
+				"oauth2_broker.store",
+				op,
+				key = key.as_deref(),
+				outcome = tracing::field::Empty,
+			);
+
+			Self { op, span }
+		}
+		#[cfg(not(feature = "tracing"))]
+		{
+			let _ = key;
+
+			Self { op }
+		}
+	}
+
+	/// Fingerprints the key of a single-record call; computed only when spans are emitted.
+	fn key(family: &TokenFamily, scope: &ScopeSet) -> Option<String> {
+		#[cfg(feature = "tracing")]
+		{
+			use sha2::{Digest, Sha256};
+
+			let cursor = crate::store::StoreKey::new(family, scope).ok()?.page_cursor();
+			let digest = Sha256::digest(cursor.as_bytes());
+
+			Some(digest[..8].iter().map(|byte| format!("{byte:02x}")).collect())
+		}
+		#[cfg(not(feature = "tracing"))]
+		{
+			let _ = (family, scope);
+
+			None
+		}
+	}
+
+	fn observe<'a, T>(
+		self,
+		call: StoreFuture<'a, T>,
+		outcome: fn(&T) -> &'static str,
+	) -> StoreFuture<'a, T>
+	where
+		T: 'a,
+	{
+		#[cfg(feature = "tracing")]
+		let call = {
+			use tracing::Instrument;
+
+			call.instrument(self.span.clone())
+		};
+
+		Box::pin(async move {
+			let started = Instant::now();
+			let result = call.await;
+			let label = match &result {
+				Ok(value) => outcome(value),
+				Err(_) => "error",
+			};
+
+			#[cfg(feature = "tracing")]
+			self.span.record("outcome", label);
+
+			record_store_operation(self.op, label, started.elapsed());
+
+			result
+		})
+	}
+}
+
+fn ok<T>(_: &T) -> &'static str {
+	"ok"
+}
+
+fn found(record: &Option<TokenRecord>) -> &'static str {
+	if record.is_some() { "ok" } else { "missing" }
+}
+
+fn swapped(outcome: &CompareAndSwapOutcome) -> &'static str {
+	match outcome {
+		CompareAndSwapOutcome::Updated => "updated",
+		CompareAndSwapOutcome::RefreshMismatch => "refresh_mismatch",
+		CompareAndSwapOutcome::VersionMismatch => "version_mismatch",
+		CompareAndSwapOutcome::Missing => "missing",
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	// crates.io
+	use tokio::runtime::Runtime;
+	// self
+	use super::*;
+	use crate::{auth::PrincipalId, store::MemoryStore};
+
+	#[test]
+	fn instrumented_store_passes_calls_through() {
+		let rt =
+			Runtime::new().expect("Failed to build Tokio runtime for instrumented store test.");
+		let store = InstrumentedStore::new(MemoryStore::default());
+		let family = TokenFamily::new(
+			TenantId::new("tenant-obs").expect("Tenant fixture should be valid."),
+			PrincipalId::new("principal-obs").expect("Principal fixture should be valid."),
+		)
+		.with_provider(ProviderId::new("provider-obs").expect("Provider fixture should be valid."));
+		let scope = ScopeSet::new(["read"]).expect("Scope fixture should be valid.");
+		let record = TokenRecord::builder(family.clone(), scope.clone())
+			.access_token("access")
+			.refresh_token("refresh")
+			.expires_in(Duration::hours(1))
+			.build()
+			.expect("Token record fixture should build successfully.");
+
+		rt.block_on(store.save(record.clone())).expect("Saving through the wrapper should work.");
+
+		assert!(store.inner().fetch_shared(&family, &scope).is_some());
+		assert_eq!(
+			rt.block_on(store.compare_and_swap_refresh(&family, &scope, Some("stale"), record))
+				.expect("CAS should complete."),
+			CompareAndSwapOutcome::RefreshMismatch
+		);
+		assert_eq!(found(&None), "missing");
+		assert_eq!(swapped(&CompareAndSwapOutcome::Updated), "updated");
+
+		let page = rt
+			.block_on(store.list_records(None, 10))
+			.expect("Listing through the wrapper should work.");
+
+		assert_eq!(page.records.len(), 1);
+	}
+}
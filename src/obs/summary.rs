@@ -0,0 +1,213 @@
+// std
+use std::time::{Duration as StdDuration, Instant};
+// self
+use crate::{
+	_prelude::*,
+	auth::{ProviderId, TenantId},
+	obs::FlowKind,
+};
+
+/// How a cached-token flow obtained the record it returned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CacheOutcome {
+	/// A still-valid cached record was served.
+	Hit,
+	/// The caller waited on a peer's in-flight request and reused the record it minted.
+	Joined,
+	/// The caller accepted the still-valid cached record after a singleflight timeout or a
+	/// provider maintenance failure.
+	Stale,
+	/// The provider was called for a new token.
+	Miss,
+}
+impl CacheOutcome {
+	/// Returns a stable label suitable for log fields.
+	pub const fn as_str(self) -> &'static str {
+		match self {
+			CacheOutcome::Hit => "hit",
+			CacheOutcome::Joined => "joined",
+			CacheOutcome::Stale => "stale",
+			CacheOutcome::Miss => "miss",
+		}
+	}
+}
+
+/// What a refresh did with the family's refresh token.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RotationOutcome {
+	/// The provider rotated the refresh token and the new secret was stored.
+	Rotated,
+	/// The provider kept the refresh token, so only the access token changed.
+	Retained,
+	/// A concurrent rotation won the compare-and-swap and its record was returned instead.
+	Superseded,
+}
+impl RotationOutcome {
+	/// Returns a stable label suitable for log fields.
+	pub const fn as_str(self) -> &'static str {
+		match self {
+			RotationOutcome::Rotated => "rotated",
+			RotationOutcome::Retained => "retained",
+			RotationOutcome::Superseded => "superseded",
+		}
+	}
+}
+
+/// Redacted summary of one cached-token flow call.
+///
+/// Every field is an identifier, an enum, or a duration; the status names the failing
+/// [`Error`] variant without its message, so no token, assertion, or provider-supplied text can
+/// reach the log.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CallSummary {
+	/// Flow that handled the call.
+	pub flow: FlowKind,
+	/// Grant label, distinguishing flows that share a [`FlowKind`] (e.g. `on_behalf_of`).
+	pub grant: &'static str,
+	/// Provider the call was made against.
+	pub provider: ProviderId,
+	/// Tenant the call was made for.
+	pub tenant: TenantId,
+	/// How the record was obtained (`None` when the call failed before reaching the cache).
+	pub cache: Option<CacheOutcome>,
+	/// What the refresh did with the refresh token (`None` outside refresh rotations).
+	pub rotation: Option<RotationOutcome>,
+	/// Wall-clock time spent in the flow.
+	pub latency: StdDuration,
+	/// `ok`, or a stable label naming the [`Error`] variant the call failed with.
+	pub status: &'static str,
+}
+
+/// Collects a [`CallSummary`] while a flow runs.
+#[derive(Debug)]
+pub(crate) struct CallRecorder {
+	flow: FlowKind,
+	grant: &'static str,
+	provider: ProviderId,
+	tenant: TenantId,
+	cache: Option<CacheOutcome>,
+	rotation: Option<RotationOutcome>,
+	started: Instant,
+}
+impl CallRecorder {
+	pub(crate) fn new(
+		flow: FlowKind,
+		grant: &'static str,
+		provider: &ProviderId,
+		tenant: &TenantId,
+	) -> Self {
+		Self {
+			flow,
+			grant,
+			provider: provider.clone(),
+			tenant: tenant.clone(),
+			cache: None,
+			rotation: None,
+			started: Instant::now(),
+		}
+	}
+
+	pub(crate) fn grant(&self) -> &'static str {
+		self.grant
+	}
+
+	pub(crate) fn cache(&mut self, outcome: CacheOutcome) {
+		self.cache = Some(outcome);
+	}
+
+	pub(crate) fn rotation(&mut self, outcome: RotationOutcome) {
+		self.rotation = Some(outcome);
+	}
+
+	pub(crate) fn finish<T>(self, result: &Result<T>) -> CallSummary {
+		CallSummary {
+			flow: self.flow,
+			grant: self.grant,
+			provider: self.provider,
+			tenant: self.tenant,
+			cache: self.cache,
+			rotation: self.rotation,
+			latency: self.started.elapsed(),
+			status: result.as_ref().map_or_else(status_label, |_| "ok"),
+		}
+	}
+}
+
+/// Emits a call summary event.
+///
+/// With `tracing` enabled the event is logged at `INFO` under the `oauth2_broker::call_summary`
+/// target. Flows emit one per call once
+/// [`BrokerOverrides::call_summaries`](crate::flows::BrokerOverrides::call_summaries) is set.
+pub fn record_call_summary(summary: &CallSummary) {
+	#[cfg(feature = "tracing")]
+	{
+		tracing::info!(
+			target: "oauth2_broker::call_summary",
+			flow = summary.flow.as_str(),
+			grant = summary.grant,
+			provider = %summary.provider,
+			tenant = %summary.tenant,
+			cache = summary.cache.map(CacheOutcome::as_str),
+			rotation = summary.rotation.map(RotationOutcome::as_str),
+			latency_ms = u64::try_from(summary.latency.as_millis()).unwrap_or(u64::MAX),
+			status = summary.status,
+			"token call completed"
+		);
+	}
+
+	#[cfg(not(feature = "tracing"))]
+	{
+		let _ = summary;
+	}
+}
+
+fn status_label(error: &Error) -> &'static str {
+	match error {
+		Error::Storage(_) => "storage",
+		Error::Config(_) => "config",
+		Error::Transient(_) => "transient",
+		Error::Transport(_) => "transport",
+		Error::InsufficientScope { .. } => "insufficient_scope",
+		Error::InvalidGrant { .. } => "invalid_grant",
+		Error::InvalidClient { .. } => "invalid_client",
+		Error::Revoked => "revoked",
+		Error::Cancelled { .. } => "cancelled",
+		Error::ProviderMaintenance { .. } => "provider_maintenance",
+		Error::FlowDisabled { .. } => "flow_disabled",
+		Error::SingleflightTimeout { .. } => "singleflight_timeout",
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	// self
+	use super::*;
+
+	#[test]
+	fn summaries_never_carry_error_messages() {
+		let provider = ProviderId::new("summary").expect("Provider fixture should be valid.");
+		let tenant = TenantId::new("tenant-summary").expect("Tenant fixture should be valid.");
+		let mut recorder = CallRecorder::new(FlowKind::Refresh, "refresh", &provider, &tenant);
+
+		recorder.cache(CacheOutcome::Miss);
+
+		let summary = recorder.finish::<()>(&Err(Error::InvalidGrant {
+			reason: "refresh token rt-secret-123 was revoked".into(),
+		}));
+
+		assert_eq!(summary.status, "invalid_grant");
+		assert_eq!(summary.cache, Some(CacheOutcome::Miss));
+		assert_eq!(summary.rotation, None);
+		assert!(!format!("{summary:?}").contains("rt-secret-123"));
+
+		let mut recorder = CallRecorder::new(FlowKind::Refresh, "refresh", &provider, &tenant);
+
+		recorder.cache(CacheOutcome::Miss);
+		recorder.rotation(RotationOutcome::Rotated);
+
+		let summary = recorder.finish(&Ok(()));
+
+		assert_eq!(summary.status, "ok");
+		assert_eq!(summary.rotation, Some(RotationOutcome::Rotated));
+	}
+}
@@ -1,5 +1,5 @@
 // self
-use crate::{_prelude::*, obs::FlowKind};
+use crate::{_prelude::*, flows::CachedTokenRequest, obs::FlowKind};
 
 /// Type alias that resolves to an instrumented future when tracing is enabled.
 #[cfg(feature = "tracing")]
@@ -31,6 +31,34 @@ impl FlowSpan {
 		}
 	}
 
+	/// Creates a span for `request`, parented to its caller span when one was attached and tagged
+	/// with the propagated trace id.
+	pub fn for_request(kind: FlowKind, stage: &'static str, request: &CachedTokenRequest) -> Self {
+		#[cfg(feature = "tracing")]
+		{
+			let parent = request.parent_span.clone().unwrap_or_else(tracing::Span::current);
+			let span = tracing::info_span!(
+				parent: &parent,
+				"oauth2_broker.flow",
+				flow = kind.as_str(),
+				stage,
+				trace_id = tracing::field::Empty,
+			);
+
+			if let Some(context) = &request.trace_context {
+				span.record("trace_id", &context.trace_id);
+			}
+
+			Self { span }
+		}
+		#[cfg(not(feature = "tracing"))]
+		{
+			let _ = (kind, stage, request);
+
+			Self {}
+		}
+	}
+
 	/// Enters the span for synchronous sections.
 	pub fn entered(self) -> FlowSpanGuard {
 		#[cfg(feature = "tracing")]
@@ -5,9 +5,22 @@
 //! provider quirks (PKCE requirement, redirect semantics, scope delimiter).
 //! `strategy` defines [`ProviderStrategy`], an HTTP-client-agnostic hook used by flows
 //! to augment outgoing token requests and map responses into the broker error taxonomy.
+//! `discovery` models the provider metadata document used to build and cross-check descriptors.
+//! `jwks` parses provider key sets and caches them for verifying signed token responses.
+//! `preset` ships descriptor builders and strategies for well-known providers.
+//! `registry` collects provider registrations for brokers serving several providers.
 
+#[cfg(any(test, feature = "test"))] pub mod corpus;
 pub mod descriptor;
+pub mod discovery;
+pub mod jwks;
+pub mod preset;
+pub mod registry;
 pub mod strategy;
 
 pub use descriptor::*;
+pub use discovery::*;
+pub use jwks::*;
+pub use preset::*;
+pub use registry::*;
 pub use strategy::*;
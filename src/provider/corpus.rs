@@ -0,0 +1,357 @@
+//! Canned token endpoint error payloads from real providers and a harness that checks a
+//! [`ProviderStrategy`] against them.
+//!
+//! Enabled by `cfg(test)` or the `test` crate feature. Each [`ErrorSample`] is a response body
+//! captured from Google, GitHub, Microsoft Entra ID (Azure AD), or Okta together with the
+//! [`ProviderErrorKind`] the broker should map it to. Run a custom strategy through
+//! [`assert_strategy_classifies`] to catch classification drift before it reaches production:
+//!
+//! ```
+//! use oauth2_broker::provider::{DefaultProviderStrategy, corpus};
+//!
+//! corpus::assert_strategy_classifies(&DefaultProviderStrategy, corpus::OKTA_ERRORS);
+//! ```
+
+// self
+use crate::{
+	_prelude::*,
+	provider::{GrantType, ProviderErrorContext, ProviderErrorKind, ProviderStrategy},
+};
+
+/// Google OAuth 2.0 token endpoint errors.
+pub const GOOGLE_ERRORS: &[ErrorSample] = &[
+	ErrorSample {
+		provider: "google",
+		name: "refresh token expired or revoked",
+		grant_type: GrantType::RefreshToken,
+		http_status: 400,
+		headers: &[],
+		body: r#"{"error": "invalid_grant", "error_description": "Token has been expired or revoked."}"#,
+		expected: ProviderErrorKind::InvalidGrant,
+	},
+	ErrorSample {
+		provider: "google",
+		name: "authorization code already redeemed",
+		grant_type: GrantType::AuthorizationCode,
+		http_status: 400,
+		headers: &[],
+		body: r#"{"error": "invalid_grant", "error_description": "Bad Request"}"#,
+		expected: ProviderErrorKind::InvalidGrant,
+	},
+	ErrorSample {
+		provider: "google",
+		name: "unknown OAuth client",
+		grant_type: GrantType::AuthorizationCode,
+		http_status: 401,
+		headers: &[],
+		body: r#"{"error": "invalid_client", "error_description": "The OAuth client was not found."}"#,
+		expected: ProviderErrorKind::InvalidClient,
+	},
+	ErrorSample {
+		provider: "google",
+		name: "client not allowed to use the grant",
+		grant_type: GrantType::RefreshToken,
+		http_status: 401,
+		headers: &[],
+		body: r#"{"error": "unauthorized_client", "error_description": "Unauthorized"}"#,
+		expected: ProviderErrorKind::InvalidClient,
+	},
+	ErrorSample {
+		provider: "google",
+		name: "service account asks for an unknown scope",
+		grant_type: GrantType::JwtBearer,
+		http_status: 400,
+		headers: &[],
+		body: r#"{"error": "invalid_scope", "error_description": "Invalid OAuth scope or ID token audience provided."}"#,
+		expected: ProviderErrorKind::InsufficientScope,
+	},
+	ErrorSample {
+		provider: "google",
+		name: "front end unavailable",
+		grant_type: GrantType::RefreshToken,
+		http_status: 503,
+		headers: &[("content-type", "text/html; charset=UTF-8")],
+		body: "<!DOCTYPE html><html lang=en><title>Error 503 (Service Unavailable)!!1</title>\
+			<p><b>503.</b> <ins>That’s an error.</ins><p>The service you requested is not \
+			available at this time.</html>",
+		expected: ProviderErrorKind::Transient,
+	},
+];
+/// GitHub OAuth app errors, which arrive with HTTP 200 and GitHub-specific codes.
+pub const GITHUB_ERRORS: &[ErrorSample] = &[
+	ErrorSample {
+		provider: "github",
+		name: "authorization code incorrect or expired",
+		grant_type: GrantType::AuthorizationCode,
+		http_status: 200,
+		headers: &[],
+		body: r#"{"error":"bad_verification_code","error_description":"The code passed is incorrect or expired.","error_uri":"https://docs.github.com/apps/managing-oauth-apps/troubleshooting-oauth-app-access-token-request-errors/#bad-verification-code"}"#,
+		expected: ProviderErrorKind::InvalidGrant,
+	},
+	ErrorSample {
+		provider: "github",
+		name: "refresh token incorrect or expired",
+		grant_type: GrantType::RefreshToken,
+		http_status: 200,
+		headers: &[],
+		body: r#"{"error":"bad_refresh_token","error_description":"The refresh token passed is incorrect or expired.","error_uri":"https://docs.github.com"}"#,
+		expected: ProviderErrorKind::InvalidGrant,
+	},
+	ErrorSample {
+		provider: "github",
+		name: "client credentials incorrect",
+		grant_type: GrantType::AuthorizationCode,
+		http_status: 200,
+		headers: &[],
+		body: r#"{"error":"incorrect_client_credentials","error_description":"The client_id and/or client_secret passed are incorrect.","error_uri":"https://docs.github.com/apps/managing-oauth-apps/troubleshooting-oauth-app-access-token-request-errors/#incorrect-client-credentials"}"#,
+		expected: ProviderErrorKind::InvalidClient,
+	},
+];
+/// Microsoft identity platform (Entra ID / Azure AD) errors carrying `AADSTS` codes.
+pub const AZURE_ERRORS: &[ErrorSample] = &[
+	ErrorSample {
+		provider: "azure",
+		name: "AADSTS70008 code or refresh token expired",
+		grant_type: GrantType::RefreshToken,
+		http_status: 400,
+		headers: &[],
+		body: r#"{"error":"invalid_grant","error_description":"AADSTS70008: The provided authorization code or refresh token has expired due to inactivity. Send a new interactive authorization request for this user and resource. Trace ID: 0d1c0f5e-6a42-4a0e-9a8b-3c2f4f5b2c00 Correlation ID: 4b9c7f2a-1e2d-4c3b-8a9f-5d6e7f8a9b0c Timestamp: 2025-11-10 12:00:00Z","error_codes":[70008],"timestamp":"2025-11-10 12:00:00Z","trace_id":"0d1c0f5e-6a42-4a0e-9a8b-3c2f4f5b2c00","correlation_id":"4b9c7f2a-1e2d-4c3b-8a9f-5d6e7f8a9b0c"}"#,
+		expected: ProviderErrorKind::InvalidGrant,
+	},
+	ErrorSample {
+		provider: "azure",
+		name: "AADSTS65001 consent required",
+		grant_type: GrantType::AuthorizationCode,
+		http_status: 400,
+		headers: &[],
+		body: r#"{"error":"invalid_grant","error_description":"AADSTS65001: The user or administrator has not consented to use the application with ID '00000000-0000-0000-0000-000000000000'. Send an interactive authorization request for this user and resource.","error_codes":[65001],"suberror":"consent_required"}"#,
+		expected: ProviderErrorKind::InvalidGrant,
+	},
+	ErrorSample {
+		provider: "azure",
+		name: "AADSTS7000215 invalid client secret",
+		grant_type: GrantType::ClientCredentials,
+		http_status: 401,
+		headers: &[],
+		body: r#"{"error":"invalid_client","error_description":"AADSTS7000215: Invalid client secret provided. Ensure the secret being sent in the request is the client secret value, not the client secret ID.","error_codes":[7000215]}"#,
+		expected: ProviderErrorKind::InvalidClient,
+	},
+	ErrorSample {
+		provider: "azure",
+		name: "AADSTS1002012 client credentials scope without /.default",
+		grant_type: GrantType::ClientCredentials,
+		http_status: 400,
+		headers: &[],
+		body: r#"{"error":"invalid_scope","error_description":"AADSTS1002012: The provided value for scope User.Read is not valid. Client credential flows must have a scope value with /.default suffixed to the resource identifier (application ID URI).","error_codes":[1002012]}"#,
+		expected: ProviderErrorKind::InsufficientScope,
+	},
+	ErrorSample {
+		provider: "azure",
+		name: "AADSTS90033 transient service error",
+		grant_type: GrantType::ClientCredentials,
+		http_status: 503,
+		headers: &[("retry-after", "5")],
+		body: r#"{"error":"temporarily_unavailable","error_description":"AADSTS90033: A transient error has occurred. Please try again.","error_codes":[90033]}"#,
+		expected: ProviderErrorKind::Transient,
+	},
+];
+/// Okta authorization server errors, including its non-OAuth rate-limit payload.
+pub const OKTA_ERRORS: &[ErrorSample] = &[
+	ErrorSample {
+		provider: "okta",
+		name: "authorization code invalid or expired",
+		grant_type: GrantType::AuthorizationCode,
+		http_status: 400,
+		headers: &[],
+		body: r#"{"error":"invalid_grant","error_description":"The authorization code is invalid or has expired."}"#,
+		expected: ProviderErrorKind::InvalidGrant,
+	},
+	ErrorSample {
+		provider: "okta",
+		name: "refresh token invalid or expired",
+		grant_type: GrantType::RefreshToken,
+		http_status: 400,
+		headers: &[],
+		body: r#"{"error":"invalid_grant","error_description":"The refresh token is invalid or expired."}"#,
+		expected: ProviderErrorKind::InvalidGrant,
+	},
+	ErrorSample {
+		provider: "okta",
+		name: "confidential client secret invalid",
+		grant_type: GrantType::ClientCredentials,
+		http_status: 401,
+		headers: &[("www-authenticate", r#"Basic realm="Okta""#)],
+		body: r#"{"error":"invalid_client","error_description":"The client secret supplied for a confidential client is invalid."}"#,
+		expected: ProviderErrorKind::InvalidClient,
+	},
+	ErrorSample {
+		provider: "okta",
+		name: "scope not configured on the authorization server",
+		grant_type: GrantType::ClientCredentials,
+		http_status: 400,
+		headers: &[],
+		body: r#"{"error":"invalid_scope","error_description":"One or more scopes are not configured for the authorization server resource."}"#,
+		expected: ProviderErrorKind::InsufficientScope,
+	},
+	ErrorSample {
+		provider: "okta",
+		name: "org rate limit exceeded",
+		grant_type: GrantType::ClientCredentials,
+		http_status: 429,
+		headers: &[
+			("x-rate-limit-limit", "1200"),
+			("x-rate-limit-remaining", "0"),
+			("x-rate-limit-reset", "1762776060"),
+		],
+		body: r#"{"errorCode":"E0000047","errorSummary":"API call exceeded rate limit due to too many requests.","errorLink":"E0000047","errorId":"oaeXyZ1AbCdEfGhIjKlMnOp","errorCauses":[]}"#,
+		expected: ProviderErrorKind::Transient,
+	},
+];
+
+/// One captured token endpoint error and the classification it should receive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ErrorSample {
+	/// Provider the payload was captured from.
+	pub provider: &'static str,
+	/// Short description of the failure scenario.
+	pub name: &'static str,
+	/// Grant whose token request failed.
+	pub grant_type: GrantType,
+	/// HTTP status code of the response.
+	pub http_status: u16,
+	/// Response headers relevant to classification (lowercase names).
+	pub headers: &'static [(&'static str, &'static str)],
+	/// Raw response body.
+	pub body: &'static str,
+	/// Classification the broker should produce.
+	pub expected: ProviderErrorKind,
+}
+impl ErrorSample {
+	/// Builds the [`ProviderErrorContext`] the broker hands to strategies for this response.
+	///
+	/// JSON bodies carrying an OAuth `error` member populate `oauth_error` and
+	/// `error_description`; any other body is passed as a preview.
+	pub fn context(&self) -> ProviderErrorContext {
+		let mut ctx = ProviderErrorContext::new(self.grant_type).with_http_status(self.http_status);
+
+		for (name, value) in self.headers {
+			ctx = ctx.with_header(name, *value);
+		}
+
+		let json = serde_json::from_str::<serde_json::Value>(self.body).ok();
+
+		match json.as_ref().and_then(|body| body.get("error")).and_then(|error| error.as_str()) {
+			Some(error) => {
+				ctx = ctx.with_oauth_error(error);
+
+				if let Some(description) = json
+					.as_ref()
+					.and_then(|body| body.get("error_description"))
+					.and_then(|description| description.as_str())
+				{
+					ctx = ctx.with_error_description(description);
+				}
+
+				ctx
+			},
+			None => ctx.with_body_preview(self.body),
+		}
+	}
+}
+impl Display for ErrorSample {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		write!(
+			f,
+			"{} {} ({}, HTTP {})",
+			self.provider, self.name, self.grant_type, self.http_status
+		)
+	}
+}
+
+/// Sample a strategy classified differently than expected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Misclassification {
+	/// Sample that was misclassified.
+	pub sample: &'static ErrorSample,
+	/// Classification the strategy produced.
+	pub actual: ProviderErrorKind,
+}
+impl Display for Misclassification {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		write!(f, "{}: expected {:?}, got {:?}", self.sample, self.sample.expected, self.actual)
+	}
+}
+
+/// Every shipped sample, grouped by provider.
+pub fn all_error_samples() -> impl Iterator<Item = &'static ErrorSample> {
+	[GOOGLE_ERRORS, GITHUB_ERRORS, AZURE_ERRORS, OKTA_ERRORS].into_iter().flatten()
+}
+
+/// Classifies each sample with `strategy`, returning the ones it got wrong.
+pub fn check_strategy<S>(strategy: &S, samples: &'static [ErrorSample]) -> Vec<Misclassification>
+where
+	S: ?Sized + ProviderStrategy,
+{
+	samples
+		.iter()
+		.filter_map(|sample| {
+			let actual = strategy.classify_token_error(&sample.context());
+
+			(actual != sample.expected).then_some(Misclassification { sample, actual })
+		})
+		.collect()
+}
+
+/// Panics with a report of every sample `strategy` misclassifies.
+pub fn assert_strategy_classifies<S>(strategy: &S, samples: &'static [ErrorSample])
+where
+	S: ?Sized + ProviderStrategy,
+{
+	let misses = check_strategy(strategy, samples);
+
+	if !misses.is_empty() {
+		let report = misses.iter().map(|miss| format!("  - {miss}")).collect::<Vec<_>>();
+
+		panic!(
+			"Strategy misclassified {} of {} samples:\n{}",
+			misses.len(),
+			samples.len(),
+			report.join("\n")
+		);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	// self
+	use super::*;
+	use crate::provider::{DefaultProviderStrategy, GitHubStrategy};
+
+	#[test]
+	fn shipped_strategies_classify_their_providers() {
+		assert_strategy_classifies(&DefaultProviderStrategy, GOOGLE_ERRORS);
+		assert_strategy_classifies(&DefaultProviderStrategy, AZURE_ERRORS);
+		assert_strategy_classifies(&DefaultProviderStrategy, OKTA_ERRORS);
+		assert_strategy_classifies(&GitHubStrategy, GITHUB_ERRORS);
+
+		let misses = check_strategy(&DefaultProviderStrategy, GITHUB_ERRORS);
+
+		assert_eq!(misses.len(), GITHUB_ERRORS.len(), "GitHub codes need the GitHub strategy.");
+		assert!(misses[0].to_string().contains("expected InvalidGrant, got Transient"));
+		assert_eq!(all_error_samples().count(), 19);
+	}
+
+	#[test]
+	fn contexts_mirror_broker_parsing() {
+		let ctx = OKTA_ERRORS[4].context();
+
+		assert_eq!(ctx.oauth_error, None);
+		assert!(ctx.body_preview.as_deref().is_some_and(|body| body.contains("E0000047")));
+		assert_eq!(ctx.header("X-Rate-Limit-Remaining"), Some("0"));
+
+		let ctx = AZURE_ERRORS[2].context();
+
+		assert_eq!(ctx.oauth_error.as_deref(), Some("invalid_client"));
+		assert!(ctx.error_description.is_some_and(|description| description.contains("7000215")));
+	}
+}
@@ -6,17 +6,27 @@
 
 /// Builder API for assembling provider descriptors.
 pub mod builder;
+/// Paired production and sandbox endpoint sets.
+pub mod environment;
 /// Grant helpers wired into provider descriptors.
 pub mod grant;
+/// Scheduled provider maintenance windows.
+pub mod maintenance;
 /// Provider-specific quirk toggles.
 pub mod quirks;
 
 pub use builder::*;
+pub use environment::*;
 pub use grant::*;
+pub use maintenance::*;
 pub use quirks::*;
 
+// std
+use std::collections::BTreeSet;
+// crates.io
+use serde_json::Value;
 // self
-use crate::{_prelude::*, auth::ProviderId};
+use crate::{_prelude::*, auth::ProviderId, error::ConfigError};
 
 /// Preferred client authentication modes for token endpoint calls.
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
@@ -30,6 +40,43 @@ pub enum ClientAuthMethod {
 	/// Public clients that prove possession via PKCE.
 	NoneWithPkce,
 }
+impl ClientAuthMethod {
+	/// Returns the RFC 8414 `token_endpoint_auth_methods_supported` identifier.
+	pub fn as_str(self) -> &'static str {
+		match self {
+			ClientAuthMethod::ClientSecretBasic => "client_secret_basic",
+			ClientAuthMethod::ClientSecretPost => "client_secret_post",
+			ClientAuthMethod::NoneWithPkce => "none",
+		}
+	}
+}
+
+/// Optional provider endpoint that can be rolled out behind a runtime toggle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderEndpoint {
+	/// Token revocation endpoint (RFC 7009).
+	Revocation,
+	/// Backchannel authentication endpoint (OpenID Connect CIBA).
+	BackchannelAuthentication,
+}
+impl ProviderEndpoint {
+	/// Every optional endpoint, in declaration order.
+	pub const ALL: [Self; 2] = [Self::Revocation, Self::BackchannelAuthentication];
+
+	/// Returns the endpoint name used in configs, reports, and errors.
+	pub fn as_str(self) -> &'static str {
+		match self {
+			Self::Revocation => "revocation",
+			Self::BackchannelAuthentication => "backchannel_authentication",
+		}
+	}
+}
+impl Display for ProviderEndpoint {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		f.write_str(self.as_str())
+	}
+}
 
 /// Endpoint set declared by a provider descriptor.
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -39,7 +86,34 @@ pub struct ProviderEndpoints {
 	/// Token endpoint used for exchanges and refreshes.
 	pub token: Url,
 	/// Optional revocation endpoint.
+	#[serde(default)]
 	pub revocation: Option<Url>,
+	/// Backchannel authentication endpoint used by the CIBA grant.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub backchannel_authentication: Option<Url>,
+	/// JSON Web Key Set endpoint used to verify signed token responses.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub jwks: Option<Url>,
+	/// Token introspection endpoint (RFC 7662).
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub introspection: Option<Url>,
+}
+impl ProviderEndpoints {
+	/// Returns the URL declared for an optional `endpoint`.
+	pub fn get(&self, endpoint: ProviderEndpoint) -> Option<&Url> {
+		match endpoint {
+			ProviderEndpoint::Revocation => self.revocation.as_ref(),
+			ProviderEndpoint::BackchannelAuthentication => self.backchannel_authentication.as_ref(),
+		}
+	}
+
+	/// Returns a mutable slot for an optional `endpoint`.
+	pub fn get_mut(&mut self, endpoint: ProviderEndpoint) -> &mut Option<Url> {
+		match endpoint {
+			ProviderEndpoint::Revocation => &mut self.revocation,
+			ProviderEndpoint::BackchannelAuthentication => &mut self.backchannel_authentication,
+		}
+	}
 }
 
 /// Immutable provider descriptor consumed by flows.
@@ -47,14 +121,43 @@ pub struct ProviderEndpoints {
 pub struct ProviderDescriptor {
 	/// Descriptor identifier.
 	pub id: ProviderId,
+	/// Issuer identifier of the authorization server, recorded on every minted token.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub issuer: Option<Url>,
+	/// Additional issuer values accepted on authorization responses (e.g., per-tenant issuers of
+	/// a multi-tenant Entra application).
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub accepted_issuers: Vec<Url>,
 	/// Endpoint definitions exposed by the provider.
 	pub endpoints: ProviderEndpoints,
+	/// Environment that [`issuer`](Self::issuer) and [`endpoints`](Self::endpoints) belong to.
+	#[serde(default, skip_serializing_if = "ProviderEnvironment::is_production")]
+	pub environment: ProviderEnvironment,
+	/// Production issuer and endpoints while the descriptor points at another environment.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub production: Option<EnvironmentEndpoints>,
+	/// Sandbox issuer and endpoints while the descriptor points at another environment.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub sandbox: Option<EnvironmentEndpoints>,
 	/// Supported grant flags.
 	pub supported_grants: SupportedGrants,
 	/// Preferred client authentication mechanism.
+	#[serde(default)]
 	pub preferred_client_auth_method: ClientAuthMethod,
+	/// Per-grant client authentication overrides; grants without an entry use
+	/// [`preferred_client_auth_method`](Self::preferred_client_auth_method).
+	#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+	pub grant_client_auth_methods: BTreeMap<GrantType, ClientAuthMethod>,
 	/// Provider-specific quirks.
+	#[serde(default)]
 	pub quirks: ProviderQuirks,
+	/// Scheduled maintenance windows announced by the provider.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub maintenance_windows: Vec<MaintenanceWindow>,
+	/// Optional endpoints that stay disabled until an operator enables them at runtime
+	/// (see [`Broker::enable_endpoint`](crate::flows::Broker::enable_endpoint)).
+	#[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+	pub experimental_endpoints: BTreeSet<ProviderEndpoint>,
 }
 impl ProviderDescriptor {
 	/// Creates a new builder for the provided identifier.
@@ -66,4 +169,206 @@ impl ProviderDescriptor {
 	pub fn supports(&self, grant: GrantType) -> bool {
 		self.supported_grants.supports(grant)
 	}
+
+	/// Client authentication method used for token requests of `grant`.
+	pub fn client_auth_method(&self, grant: GrantType) -> ClientAuthMethod {
+		self.grant_client_auth_methods
+			.get(&grant)
+			.copied()
+			.unwrap_or(self.preferred_client_auth_method)
+	}
+
+	/// Returns `true` when `endpoint` is declared but marked experimental.
+	pub fn is_experimental(&self, endpoint: ProviderEndpoint) -> bool {
+		self.experimental_endpoints.contains(&endpoint)
+	}
+
+	/// Returns the declared maintenance window covering `instant`, if any.
+	pub fn maintenance_at(&self, instant: OffsetDateTime) -> Option<MaintenanceWindow> {
+		self.maintenance_windows.iter().copied().find(|window| window.contains(instant))
+	}
+
+	/// Returns `true` when `iss` is [`issuer`](Self::issuer) or one of the
+	/// [`accepted_issuers`](Self::accepted_issuers).
+	///
+	/// Issuers are compared as plain strings (RFC 9207 §2.4), except that an issuer without a
+	/// path also matches without the `/` that parsing appended to it.
+	pub fn accepts_issuer(&self, iss: &str) -> bool {
+		self.issuer.iter().chain(&self.accepted_issuers).any(|issuer| {
+			issuer.as_str() == iss
+				|| (issuer.path() == "/" && issuer.as_str().strip_suffix('/') == Some(iss))
+		})
+	}
+
+	/// Validates the `iss` parameter returned on an authorization response (RFC 9207).
+	///
+	/// A returned `iss` must match one of the configured issuers; descriptors without any issuer
+	/// skip the comparison. A missing `iss` is only rejected when
+	/// [`ProviderQuirks::require_authorization_response_iss`] is enabled.
+	pub fn validate_authorization_issuer(&self, iss: Option<&str>) -> Result<()> {
+		match iss {
+			Some(_) if self.issuer.is_none() && self.accepted_issuers.is_empty() => Ok(()),
+			Some(iss) if self.accepts_issuer(iss) => Ok(()),
+			Some(iss) => Err(Error::InvalidGrant {
+				reason: format!("Authorization response issuer `{iss}` is not accepted"),
+			}),
+			None if self.quirks.require_authorization_response_iss => Err(Error::InvalidGrant {
+				reason: "Authorization response is missing the `iss` parameter".into(),
+			}),
+			None => Ok(()),
+		}
+	}
+
+	/// Parses and validates a JSON descriptor exported by [`ProviderDescriptor::to_config_string`]
+	/// or written by hand.
+	///
+	/// Omitted optional fields and quirks fall back to their defaults, and fields this crate
+	/// version does not recognize are ignored and reported through
+	/// [`LoadedDescriptor::ignored_fields`] (and a `tracing` warning when that feature is enabled),
+	/// so configs written for newer releases keep loading.
+	pub fn from_config_str(raw: &str) -> Result<LoadedDescriptor, ConfigError> {
+		let invalid = |e: serde_json::Error| ConfigError::InvalidBrokerConfig {
+			message: format!("Provider descriptor is invalid: {e}"),
+		};
+		let input = serde_json::from_str::<Value>(raw).map_err(invalid)?;
+		let descriptor = serde_json::from_value::<Self>(input.clone()).map_err(invalid)?;
+
+		descriptor.validate()?;
+
+		let known = serde_json::to_value(&descriptor).map_err(invalid)?;
+		let mut ignored_fields = Vec::new();
+
+		collect_ignored_fields(&input, &known, "", &mut ignored_fields);
+
+		#[cfg(feature = "tracing")]
+		for field in &ignored_fields {
+			tracing::warn!(provider = %descriptor.id, field, "Ignoring unknown descriptor field.");
+		}
+
+		Ok(LoadedDescriptor { descriptor, ignored_fields })
+	}
+
+	/// Serializes the descriptor as pretty-printed JSON suitable for configuration files.
+	pub fn to_config_string(&self) -> Result<String, ConfigError> {
+		serde_json::to_string_pretty(self).map_err(|e| ConfigError::InvalidBrokerConfig {
+			message: format!("Provider descriptor cannot be serialized: {e}"),
+		})
+	}
+}
+
+/// Descriptor parsed by [`ProviderDescriptor::from_config_str`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LoadedDescriptor {
+	/// Validated descriptor.
+	pub descriptor: ProviderDescriptor,
+	/// Dotted paths of input fields that were not recognized and therefore ignored.
+	pub ignored_fields: Vec<String>,
+}
+
+fn collect_ignored_fields(input: &Value, known: &Value, path: &str, out: &mut Vec<String>) {
+	let (Value::Object(input), Value::Object(known)) = (input, known) else {
+		return;
+	};
+
+	for (key, value) in input {
+		let field = if path.is_empty() { key.clone() } else { format!("{path}.{key}") };
+
+		match known.get(key) {
+			Some(known) => collect_ignored_fields(value, known, &field, out),
+			None => out.push(field),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	// self
+	use super::*;
+
+	#[test]
+	fn config_round_trip_applies_defaults_and_reports_unknown_fields() {
+		let loaded = ProviderDescriptor::from_config_str(
+			r#"{
+				"id": "future",
+				"endpoints": {
+					"authorization": "https://future.example.com/authorize",
+					"token": "https://future.example.com/token",
+					"device": "https://future.example.com/device"
+				},
+				"supported_grants": { "client_credentials": true, "device_code": true },
+				"quirks": { "pkce_required": false, "brand_new_quirk": 7 },
+				"display_name": "Future"
+			}"#,
+		)
+		.expect("Forward-compatible descriptor should load.");
+
+		assert_eq!(
+			loaded.ignored_fields,
+			[
+				"display_name",
+				"endpoints.device",
+				"quirks.brand_new_quirk",
+				"supported_grants.device_code"
+			]
+		);
+		assert_eq!(loaded.descriptor.quirks.scope_delimiter, ' ');
+		assert_eq!(
+			loaded.descriptor.preferred_client_auth_method,
+			ClientAuthMethod::ClientSecretBasic
+		);
+		assert!(loaded.descriptor.endpoints.revocation.is_none());
+
+		let exported =
+			loaded.descriptor.to_config_string().expect("Descriptor should serialize to config.");
+		let reloaded = ProviderDescriptor::from_config_str(&exported)
+			.expect("Exported descriptor should load again.");
+
+		assert_eq!(reloaded.descriptor, loaded.descriptor);
+		assert!(reloaded.ignored_fields.is_empty());
+		assert!(ProviderDescriptor::from_config_str(r#"{ "id": "broken" }"#).is_err());
+	}
+
+	#[test]
+	fn authorization_issuer_accepts_any_configured_issuer() {
+		let url = |raw: &str| Url::parse(raw).expect("Issuer fixture should parse.");
+		let builder = ProviderDescriptor::builder(
+			ProviderId::new("entra").expect("Provider identifier fixture should be valid."),
+		)
+		.issuer(url("https://login.example.com/tenant-a/v2.0"))
+		.accepted_issuer(url("https://login.example.com/tenant-b/v2.0"))
+		.authorization_endpoint(url("https://login.example.com/authorize"))
+		.token_endpoint(url("https://login.example.com/token"))
+		.support_grant(GrantType::AuthorizationCode);
+		let descriptor = builder.build().expect("Descriptor fixture should build.");
+
+		assert!(
+			descriptor
+				.validate_authorization_issuer(Some("https://login.example.com/tenant-b/v2.0"))
+				.is_ok()
+		);
+		assert!(matches!(
+			descriptor.validate_authorization_issuer(Some("https://login.example.com/evil/v2.0")),
+			Err(Error::InvalidGrant { .. })
+		));
+
+		for near_miss in
+			["https://login.example.com/tenant-b/v2.0/", "HTTPS://login.example.com/tenant-b/v2.0"]
+		{
+			assert!(
+				descriptor.validate_authorization_issuer(Some(near_miss)).is_err(),
+				"`{near_miss}` should not match without normalization."
+			);
+		}
+
+		assert!(descriptor.validate_authorization_issuer(None).is_ok());
+
+		let mut strict = descriptor;
+
+		strict.quirks.require_authorization_response_iss = true;
+
+		assert!(matches!(
+			strict.validate_authorization_issuer(None),
+			Err(Error::InvalidGrant { .. })
+		));
+	}
 }
@@ -1,12 +1,13 @@
 // std
-use std::iter::IntoIterator;
+use std::{collections::BTreeSet, iter::IntoIterator};
 // self
 use crate::{
 	_prelude::*,
 	auth::ProviderId,
 	provider::{
-		ClientAuthMethod, GrantType, ProviderDescriptor, ProviderEndpoints, ProviderQuirks,
-		SupportedGrants,
+		ClientAuthMethod, EnvironmentEndpoints, GrantType, MaintenanceWindow, ProviderDescriptor,
+		ProviderEndpoint, ProviderEndpoints, ProviderEnvironment, ProviderQuirks, SupportedGrants,
+		TokenResponseFormat,
 	},
 };
 
@@ -19,6 +20,12 @@ pub enum ProviderDescriptorError {
 	/// Token endpoint is mandatory for all flows.
 	#[error("Missing token endpoint.")]
 	MissingTokenEndpoint,
+	/// Signed token responses and hybrid ID tokens need a JWKS endpoint to verify against.
+	#[error("JWT token responses and hybrid ID tokens require a JWKS endpoint.")]
+	MissingJwksEndpoint,
+	/// The CIBA grant needs a backchannel authentication endpoint.
+	#[error("The ciba grant requires a backchannel authentication endpoint.")]
+	MissingBackchannelAuthenticationEndpoint,
 	/// At least one grant must be supported.
 	#[error("Descriptor must enable at least one grant type.")]
 	NoSupportedGrants,
@@ -33,6 +40,9 @@ pub enum ProviderDescriptorError {
 		/// Endpoint URL that failed validation.
 		url: String,
 	},
+	/// Maintenance windows must end after they start.
+	#[error("Maintenance window must end after it starts.")]
+	EmptyMaintenanceWindow,
 	/// Reject scope delimiters that are control characters.
 	#[error("Scope delimiter must be a printable character.")]
 	InvalidScopeDelimiter {
@@ -46,33 +56,74 @@ pub enum ProviderDescriptorError {
 pub struct ProviderDescriptorBuilder {
 	/// Identifier for the descriptor being constructed.
 	pub id: ProviderId,
+	/// Optional issuer identifier.
+	pub issuer: Option<Url>,
+	/// Additional issuers accepted on authorization responses.
+	pub accepted_issuers: Vec<Url>,
 	/// Optional authorization endpoint (required for Authorization Code flows).
 	pub authorization_endpoint: Option<Url>,
 	/// Token endpoint used for exchanges and refreshes.
 	pub token_endpoint: Option<Url>,
 	/// Optional revocation endpoint.
 	pub revocation_endpoint: Option<Url>,
+	/// Backchannel authentication endpoint (required for the CIBA grant).
+	pub backchannel_authentication_endpoint: Option<Url>,
+	/// JSON Web Key Set endpoint used to verify signed token responses.
+	pub jwks_endpoint: Option<Url>,
+	/// Optional token introspection endpoint.
+	pub introspection_endpoint: Option<Url>,
+	/// Sandbox issuer and endpoints paired with the production set above.
+	pub sandbox: Option<EnvironmentEndpoints>,
 	/// Grants enabled for the provider.
 	pub supported_grants: SupportedGrants,
 	/// Preferred client authentication method for the token endpoint.
 	pub preferred_client_auth_method: ClientAuthMethod,
+	/// Per-grant client authentication overrides.
+	pub grant_client_auth_methods: BTreeMap<GrantType, ClientAuthMethod>,
 	/// Provider-specific quirks.
 	pub quirks: ProviderQuirks,
+	/// Scheduled maintenance windows.
+	pub maintenance_windows: Vec<MaintenanceWindow>,
+	/// Optional endpoints disabled until toggled on at runtime.
+	pub experimental_endpoints: BTreeSet<ProviderEndpoint>,
 }
 impl ProviderDescriptorBuilder {
 	/// Creates a new builder seeded with the provided identifier.
 	pub fn new(id: ProviderId) -> Self {
 		Self {
 			id,
+			issuer: None,
+			accepted_issuers: Vec::new(),
 			authorization_endpoint: None,
 			token_endpoint: None,
 			revocation_endpoint: None,
+			backchannel_authentication_endpoint: None,
+			jwks_endpoint: None,
+			introspection_endpoint: None,
+			sandbox: None,
 			supported_grants: SupportedGrants::default(),
 			preferred_client_auth_method: ClientAuthMethod::default(),
+			grant_client_auth_methods: BTreeMap::new(),
 			quirks: ProviderQuirks::default(),
+			maintenance_windows: Vec::new(),
+			experimental_endpoints: BTreeSet::new(),
 		}
 	}
 
+	/// Sets the issuer identifier recorded on minted tokens.
+	pub fn issuer(mut self, url: Url) -> Self {
+		self.issuer = Some(url);
+
+		self
+	}
+
+	/// Accepts an additional issuer value on authorization responses.
+	pub fn accepted_issuer(mut self, url: Url) -> Self {
+		self.accepted_issuers.push(url);
+
+		self
+	}
+
 	/// Sets the authorization endpoint.
 	pub fn authorization_endpoint(mut self, url: Url) -> Self {
 		self.authorization_endpoint = Some(url);
@@ -94,6 +145,43 @@ impl ProviderDescriptorBuilder {
 		self
 	}
 
+	/// Marks an optional endpoint as experimental so brokers leave it unused until an operator
+	/// enables it with [`Broker::enable_endpoint`](crate::flows::Broker::enable_endpoint).
+	pub fn experimental_endpoint(mut self, endpoint: ProviderEndpoint) -> Self {
+		self.experimental_endpoints.insert(endpoint);
+
+		self
+	}
+
+	/// Sets the JSON Web Key Set endpoint used to verify signed token responses.
+	pub fn jwks_endpoint(mut self, url: Url) -> Self {
+		self.jwks_endpoint = Some(url);
+
+		self
+	}
+
+	/// Sets the optional token introspection endpoint (RFC 7662).
+	pub fn introspection_endpoint(mut self, url: Url) -> Self {
+		self.introspection_endpoint = Some(url);
+
+		self
+	}
+
+	/// Sets the backchannel authentication endpoint used by the CIBA grant.
+	pub fn backchannel_authentication_endpoint(mut self, url: Url) -> Self {
+		self.backchannel_authentication_endpoint = Some(url);
+
+		self
+	}
+
+	/// Declares the provider's sandbox issuer and endpoints; the issuer and endpoints set
+	/// directly on the builder are the production ones.
+	pub fn sandbox(mut self, sandbox: EnvironmentEndpoints) -> Self {
+		self.sandbox = Some(sandbox);
+
+		self
+	}
+
 	/// Marks a single grant type as supported.
 	pub fn support_grant(mut self, grant: GrantType) -> Self {
 		self.supported_grants = self.supported_grants.enable(grant);
@@ -120,6 +208,13 @@ impl ProviderDescriptorBuilder {
 		self
 	}
 
+	/// Uses `method` for token requests of `grant` instead of the preferred method.
+	pub fn client_auth_method_for(mut self, grant: GrantType, method: ClientAuthMethod) -> Self {
+		self.grant_client_auth_methods.insert(grant, method);
+
+		self
+	}
+
 	/// Overrides the provider quirks.
 	pub fn quirks(mut self, quirks: ProviderQuirks) -> Self {
 		self.quirks = quirks;
@@ -127,20 +222,41 @@ impl ProviderDescriptorBuilder {
 		self
 	}
 
+	/// Declares a scheduled maintenance window.
+	pub fn maintenance_window(mut self, window: MaintenanceWindow) -> Self {
+		self.maintenance_windows.push(window);
+
+		self
+	}
+
 	/// Consumes the builder and validates the resulting descriptor.
 	pub fn build(self) -> Result<ProviderDescriptor, ProviderDescriptorError> {
 		let authorization = self
 			.authorization_endpoint
 			.ok_or(ProviderDescriptorError::MissingAuthorizationEndpoint)?;
 		let token = self.token_endpoint.ok_or(ProviderDescriptorError::MissingTokenEndpoint)?;
-		let endpoints =
-			ProviderEndpoints { authorization, token, revocation: self.revocation_endpoint };
+		let endpoints = ProviderEndpoints {
+			authorization,
+			token,
+			revocation: self.revocation_endpoint,
+			backchannel_authentication: self.backchannel_authentication_endpoint,
+			jwks: self.jwks_endpoint,
+			introspection: self.introspection_endpoint,
+		};
 		let descriptor = ProviderDescriptor {
 			id: self.id,
+			issuer: self.issuer,
+			accepted_issuers: self.accepted_issuers,
 			endpoints,
+			environment: ProviderEnvironment::Production,
+			production: None,
+			sandbox: self.sandbox,
 			supported_grants: self.supported_grants,
 			preferred_client_auth_method: self.preferred_client_auth_method,
+			grant_client_auth_methods: self.grant_client_auth_methods,
 			quirks: self.quirks,
+			maintenance_windows: self.maintenance_windows,
+			experimental_endpoints: self.experimental_endpoints,
 		};
 
 		descriptor.validate()?;
@@ -151,23 +267,49 @@ impl ProviderDescriptorBuilder {
 
 impl ProviderDescriptor {
 	/// Validates invariants for the descriptor.
-	fn validate(&self) -> Result<(), ProviderDescriptorError> {
+	pub(crate) fn validate(&self) -> Result<(), ProviderDescriptorError> {
 		if self.supported_grants.is_empty() {
 			return Err(ProviderDescriptorError::NoSupportedGrants);
 		}
 		if self.quirks.pkce_required && !self.supports(GrantType::AuthorizationCode) {
 			return Err(ProviderDescriptorError::PkceRequiredWithoutAuthorizationCode);
 		}
+		if self.supports(GrantType::Ciba) && self.endpoints.backchannel_authentication.is_none() {
+			return Err(ProviderDescriptorError::MissingBackchannelAuthenticationEndpoint);
+		}
+		if (self.quirks.token_response_format == TokenResponseFormat::Jwt
+			|| self.quirks.hybrid_id_token)
+			&& self.endpoints.jwks.is_none()
+		{
+			return Err(ProviderDescriptorError::MissingJwksEndpoint);
+		}
 
-		validate_endpoint("authorization", &self.endpoints.authorization)?;
-		validate_endpoint("token", &self.endpoints.token)?;
+		for endpoints in std::iter::once(&self.endpoints)
+			.chain(self.inactive_environments().map(|set| &set.endpoints))
+		{
+			validate_endpoint("authorization", &endpoints.authorization)?;
+			validate_endpoint("token", &endpoints.token)?;
 
-		if let Some(revocation) = self.endpoints.revocation.as_ref() {
-			validate_endpoint("revocation", revocation)?;
+			if let Some(revocation) = endpoints.revocation.as_ref() {
+				validate_endpoint("revocation", revocation)?;
+			}
+			if let Some(backchannel) = endpoints.backchannel_authentication.as_ref() {
+				validate_endpoint("backchannel_authentication", backchannel)?;
+			}
+			if let Some(jwks) = endpoints.jwks.as_ref() {
+				validate_endpoint("jwks", jwks)?;
+			}
+			if let Some(introspection) = endpoints.introspection.as_ref() {
+				validate_endpoint("introspection", introspection)?;
+			}
 		}
 
 		validate_scope_delimiter(self.quirks.scope_delimiter)?;
 
+		if self.maintenance_windows.iter().any(|window| window.ends_at <= window.starts_at) {
+			return Err(ProviderDescriptorError::EmptyMaintenanceWindow);
+		}
+
 		Ok(())
 	}
 }
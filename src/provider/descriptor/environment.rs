@@ -0,0 +1,108 @@
+// self
+use crate::{
+	_prelude::*,
+	error::ConfigError,
+	provider::{ProviderDescriptor, ProviderEndpoints},
+};
+
+/// Provider deployment a descriptor's active endpoints point at.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderEnvironment {
+	/// Live provider endpoints.
+	#[default]
+	Production,
+	/// Provider sandbox or developer endpoints.
+	Sandbox,
+}
+impl ProviderEnvironment {
+	/// Returns the snake_case label used in configuration files.
+	pub fn as_str(self) -> &'static str {
+		match self {
+			Self::Production => "production",
+			Self::Sandbox => "sandbox",
+		}
+	}
+
+	pub(crate) fn is_production(&self) -> bool {
+		matches!(self, Self::Production)
+	}
+}
+impl Display for ProviderEnvironment {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		f.write_str(self.as_str())
+	}
+}
+
+/// Issuer and endpoint set of an environment the descriptor is not currently pointed at.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnvironmentEndpoints {
+	/// Issuer identifier of that environment's authorization server.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub issuer: Option<Url>,
+	/// Endpoint definitions of that environment.
+	pub endpoints: ProviderEndpoints,
+}
+impl EnvironmentEndpoints {
+	/// Creates an endpoint set without an issuer.
+	pub fn new(endpoints: ProviderEndpoints) -> Self {
+		Self { issuer: None, endpoints }
+	}
+
+	/// Sets the issuer identifier of the environment.
+	pub fn with_issuer(mut self, issuer: Url) -> Self {
+		self.issuer = Some(issuer);
+
+		self
+	}
+}
+
+impl ProviderDescriptor {
+	/// Returns a copy whose [`issuer`](Self::issuer) and [`endpoints`](Self::endpoints) point at
+	/// `environment`.
+	///
+	/// The set being replaced moves into [`production`](Self::production) or
+	/// [`sandbox`](Self::sandbox), so the copy can be switched back later. Fails with
+	/// [`ConfigError::UnsupportedEnvironment`] when the descriptor does not declare `environment`.
+	pub fn for_environment(&self, environment: ProviderEnvironment) -> Result<Self, ConfigError> {
+		if self.environment == environment {
+			return Ok(self.clone());
+		}
+
+		let mut descriptor = self.clone();
+		let target = match environment {
+			ProviderEnvironment::Production => descriptor.production.take(),
+			ProviderEnvironment::Sandbox => descriptor.sandbox.take(),
+		}
+		.ok_or_else(|| ConfigError::UnsupportedEnvironment {
+			descriptor: self.id.to_string(),
+			environment,
+		})?;
+		let previous = EnvironmentEndpoints {
+			issuer: std::mem::replace(&mut descriptor.issuer, target.issuer),
+			endpoints: std::mem::replace(&mut descriptor.endpoints, target.endpoints),
+		};
+
+		match self.environment {
+			ProviderEnvironment::Production => descriptor.production = Some(previous),
+			ProviderEnvironment::Sandbox => descriptor.sandbox = Some(previous),
+		}
+
+		descriptor.environment = environment;
+
+		Ok(descriptor)
+	}
+
+	/// Returns `true` when the descriptor can be pointed at `environment`.
+	pub fn declares_environment(&self, environment: ProviderEnvironment) -> bool {
+		self.environment == environment
+			|| match environment {
+				ProviderEnvironment::Production => self.production.is_some(),
+				ProviderEnvironment::Sandbox => self.sandbox.is_some(),
+			}
+	}
+
+	pub(crate) fn inactive_environments(&self) -> impl Iterator<Item = &EnvironmentEndpoints> {
+		self.production.iter().chain(&self.sandbox)
+	}
+}
@@ -2,7 +2,7 @@
 use crate::_prelude::*;
 
 /// OAuth 2.0 grant types supported by the broker.
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum GrantType {
 	/// Authorization Code grant (PKCE recommended).
@@ -11,14 +11,20 @@ pub enum GrantType {
 	RefreshToken,
 	/// Client Credentials grant for app-only tokens.
 	ClientCredentials,
+	/// JWT Bearer assertion grant (RFC 7523) for server-to-server tokens.
+	JwtBearer,
+	/// Client Initiated Backchannel Authentication grant (OpenID Connect CIBA).
+	Ciba,
 }
 impl GrantType {
-	/// Returns the RFC 6749 identifier for the grant type.
+	/// Returns the wire identifier for the grant type (RFC 6749 / RFC 7523).
 	pub fn as_str(self) -> &'static str {
 		match self {
 			GrantType::AuthorizationCode => "authorization_code",
 			GrantType::RefreshToken => "refresh_token",
 			GrantType::ClientCredentials => "client_credentials",
+			GrantType::JwtBearer => "urn:ietf:params:oauth:grant-type:jwt-bearer",
+			GrantType::Ciba => "urn:openid:params:grant-type:ciba",
 		}
 	}
 }
@@ -30,6 +36,7 @@ impl Display for GrantType {
 
 /// Collection of grant flags wired into the descriptor.
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SupportedGrants {
 	/// Indicates whether the Authorization Code grant is enabled.
 	pub authorization_code: bool,
@@ -37,6 +44,10 @@ pub struct SupportedGrants {
 	pub refresh_token: bool,
 	/// Indicates whether the Client Credentials grant is enabled.
 	pub client_credentials: bool,
+	/// Indicates whether the JWT Bearer assertion grant is enabled.
+	pub jwt_bearer: bool,
+	/// Indicates whether the CIBA grant is enabled.
+	pub ciba: bool,
 }
 impl SupportedGrants {
 	/// Returns true if the provided grant is supported.
@@ -45,6 +56,8 @@ impl SupportedGrants {
 			GrantType::AuthorizationCode => self.authorization_code,
 			GrantType::RefreshToken => self.refresh_token,
 			GrantType::ClientCredentials => self.client_credentials,
+			GrantType::JwtBearer => self.jwt_bearer,
+			GrantType::Ciba => self.ciba,
 		}
 	}
 
@@ -54,6 +67,8 @@ impl SupportedGrants {
 			GrantType::AuthorizationCode => self.authorization_code = true,
 			GrantType::RefreshToken => self.refresh_token = true,
 			GrantType::ClientCredentials => self.client_credentials = true,
+			GrantType::JwtBearer => self.jwt_bearer = true,
+			GrantType::Ciba => self.ciba = true,
 		}
 
 		self
@@ -61,6 +76,10 @@ impl SupportedGrants {
 
 	/// Returns true when no grants are enabled.
 	pub fn is_empty(self) -> bool {
-		!self.authorization_code && !self.refresh_token && !self.client_credentials
+		!self.authorization_code
+			&& !self.refresh_token
+			&& !self.client_credentials
+			&& !self.jwt_bearer
+			&& !self.ciba
 	}
 }
@@ -0,0 +1,26 @@
+// self
+use crate::_prelude::*;
+
+/// Scheduled interval `[starts_at, ends_at)` during which a provider expects degraded service.
+///
+/// While a window is active the broker keeps serving unexpired cached tokens instead of
+/// refreshing them early, tags transient provider failures with [`Error::ProviderMaintenance`],
+/// and falls back to the unexpired cached token when such a failure blocks a required renewal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceWindow {
+	/// First instant covered by the window.
+	pub starts_at: OffsetDateTime,
+	/// First instant after the window.
+	pub ends_at: OffsetDateTime,
+}
+impl MaintenanceWindow {
+	/// Creates a window covering `[starts_at, ends_at)`.
+	pub fn new(starts_at: OffsetDateTime, ends_at: OffsetDateTime) -> Self {
+		Self { starts_at, ends_at }
+	}
+
+	/// Returns `true` when `instant` falls inside the window.
+	pub fn contains(&self, instant: OffsetDateTime) -> bool {
+		self.starts_at <= instant && instant < self.ends_at
+	}
+}
@@ -1,6 +1,18 @@
 // self
 use crate::_prelude::*;
 
+/// Body format of successful token endpoint responses.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenResponseFormat {
+	/// Plain `application/json` token responses (RFC 6749 §5.1).
+	#[default]
+	Json,
+	/// `application/jwt` responses whose JWS payload carries the token response; the signature is
+	/// verified against the descriptor's JWKS endpoint before the response is used.
+	Jwt,
+}
+
 /// Provider-specific quirks that influence how flows behave.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(default)]
@@ -11,9 +23,48 @@ pub struct ProviderQuirks {
 	pub exact_redirect_match: bool,
 	/// Character used to join scopes when constructing `scope` parameters.
 	pub scope_delimiter: char,
+	/// Indicates whether `client_secret_basic` credentials are form-URL-encoded before being
+	/// base64-encoded (RFC 6749 §2.3.1); disable for providers that expect the raw values.
+	pub basic_auth_url_encode: bool,
+	/// Lifetime, in seconds, assumed when a token response omits `expires_in`.
+	///
+	/// `None` rejects such responses, which is the safe default for providers that always
+	/// advertise lifetimes.
+	pub default_expires_in_secs: Option<u64>,
+	/// When the broker revokes a record after `invalid_grant`, also revoke the paired access
+	/// token at the descriptor's revocation endpoint (RFC 7009) so provider-side sessions do
+	/// not outlive broker state.
+	pub cascade_revocation: bool,
+	/// Rejects authorization responses that omit the RFC 9207 `iss` parameter; enable for
+	/// providers that advertise `authorization_response_iss_parameter_supported`.
+	pub require_authorization_response_iss: bool,
+	/// Indicates that one refresh token is shared by every scope variant of a token family
+	/// (common with providers that ignore `scope` on refresh).
+	///
+	/// The broker then serializes refreshes per family and, when the provider rotates the
+	/// secret, moves every sibling record that held the old one onto the new one.
+	pub shared_family_refresh: bool,
+	/// Requests `response_type=code id_token` (OpenID Connect hybrid flow) with a `nonce`, so
+	/// the ID token returned next to the code is checked before the exchange; useful while
+	/// migrating implicit-flow deployments onto the code flow. Requires a JWKS endpoint.
+	pub hybrid_id_token: bool,
+	/// Format of successful token responses; [`TokenResponseFormat::Jwt`] requires a JWKS
+	/// endpoint.
+	pub token_response_format: TokenResponseFormat,
 }
 impl Default for ProviderQuirks {
 	fn default() -> Self {
-		Self { pkce_required: false, exact_redirect_match: true, scope_delimiter: ' ' }
+		Self {
+			pkce_required: false,
+			exact_redirect_match: true,
+			scope_delimiter: ' ',
+			basic_auth_url_encode: true,
+			default_expires_in_secs: None,
+			cascade_revocation: false,
+			require_authorization_response_iss: false,
+			shared_family_refresh: false,
+			hybrid_id_token: false,
+			token_response_format: TokenResponseFormat::Json,
+		}
 	}
 }
@@ -0,0 +1,532 @@
+//! Authorization server metadata (OIDC discovery / RFC 8414) consumed by the broker.
+//!
+//! [`ProviderDescriptor::discover`] fetches an issuer's `/.well-known/openid-configuration`
+//! document through the broker transport and maps it into a validated descriptor. Documents are
+//! kept in a [`DiscoveryCache`] so brokers assembled repeatedly for the same issuer do not refetch
+//! them until the cache's TTL passes.
+
+// crates.io
+use oauth2::HttpClientError;
+// self
+use crate::{
+	_prelude::*,
+	auth::ProviderId,
+	error::{ConfigError, TransportError},
+	http::{self, TokenHttpClient},
+	provider::{ClientAuthMethod, GrantType, ProviderDescriptor, ProviderEndpoint},
+};
+
+const PKCE_S256: &str = "S256";
+const WELL_KNOWN_PATH: &str = "/.well-known/openid-configuration";
+const GRANTS: [GrantType; 5] = [
+	GrantType::AuthorizationCode,
+	GrantType::RefreshToken,
+	GrantType::ClientCredentials,
+	GrantType::JwtBearer,
+	GrantType::Ciba,
+];
+
+/// Subset of the provider metadata document the broker understands.
+///
+/// Unknown fields are ignored so documents from any OIDC- or RFC 8414-compliant provider
+/// deserialize without additional configuration.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiscoveryDocument {
+	/// Issuer identifier advertised by the provider.
+	pub issuer: Option<Url>,
+	/// Authorization endpoint used by the Authorization Code flow.
+	pub authorization_endpoint: Option<Url>,
+	/// Token endpoint used for exchanges and refreshes.
+	pub token_endpoint: Option<Url>,
+	/// Revocation endpoint (RFC 7009), if advertised.
+	pub revocation_endpoint: Option<Url>,
+	/// JSON Web Key Set endpoint, if advertised.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub jwks_uri: Option<Url>,
+	/// Backchannel authentication endpoint (OpenID Connect CIBA), if advertised.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub backchannel_authentication_endpoint: Option<Url>,
+	/// Token introspection endpoint (RFC 7662), if advertised.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub introspection_endpoint: Option<Url>,
+	/// Grant types the provider accepts at the token endpoint.
+	#[serde(default)]
+	pub grant_types_supported: Vec<String>,
+	/// Client authentication methods accepted at the token endpoint.
+	#[serde(default)]
+	pub token_endpoint_auth_methods_supported: Vec<String>,
+	/// PKCE challenge methods accepted at the authorization endpoint.
+	#[serde(default)]
+	pub code_challenge_methods_supported: Vec<String>,
+}
+impl DiscoveryDocument {
+	/// Parses a metadata document from its raw JSON representation.
+	pub fn from_json(bytes: &[u8]) -> Result<Self, ConfigError> {
+		serde_json::from_slice(bytes)
+			.map_err(|e| ConfigError::InvalidDiscoveryDocument { message: e.to_string() })
+	}
+
+	/// Returns the OpenID Connect discovery URL for `issuer`.
+	pub fn url_for(issuer: &Url) -> Url {
+		let mut url = issuer.clone();
+		let path = format!("{}{WELL_KNOWN_PATH}", issuer.path().trim_end_matches('/'));
+
+		url.set_path(&path);
+		url.set_query(None);
+		url.set_fragment(None);
+
+		url
+	}
+
+	/// Maps the advertised endpoints and capabilities into a validated descriptor named `id`.
+	///
+	/// Only grants the broker implements are enabled; when the document omits
+	/// `grant_types_supported`, the RFC 8414 default (`authorization_code`) applies, and the CIBA
+	/// grant is enabled only alongside a backchannel authentication endpoint. The preferred client
+	/// authentication method is `client_secret_basic` unless the document advertises only
+	/// `client_secret_post` or `none`.
+	pub fn to_descriptor(&self, id: ProviderId) -> Result<ProviderDescriptor, ConfigError> {
+		let missing = |field: &str| ConfigError::InvalidDiscoveryDocument {
+			message: format!("the document does not advertise `{field}`"),
+		};
+		let mut builder = ProviderDescriptor::builder(id)
+			.authorization_endpoint(
+				self.authorization_endpoint
+					.clone()
+					.ok_or_else(|| missing("authorization_endpoint"))?,
+			)
+			.token_endpoint(self.token_endpoint.clone().ok_or_else(|| missing("token_endpoint"))?);
+
+		builder.issuer = self.issuer.clone();
+		builder.revocation_endpoint = self.revocation_endpoint.clone();
+		builder.backchannel_authentication_endpoint =
+			self.backchannel_authentication_endpoint.clone();
+		builder.jwks_endpoint = self.jwks_uri.clone();
+		builder.introspection_endpoint = self.introspection_endpoint.clone();
+
+		if self.grant_types_supported.is_empty() {
+			builder = builder.support_grant(GrantType::AuthorizationCode);
+		} else {
+			builder = builder.support_grants(
+				GRANTS
+					.into_iter()
+					.filter(|grant| advertises(&self.grant_types_supported, grant.as_str()))
+					.filter(|grant| {
+						*grant != GrantType::Ciba
+							|| self.backchannel_authentication_endpoint.is_some()
+					}),
+			);
+		}
+
+		let methods = &self.token_endpoint_auth_methods_supported;
+
+		if !methods.is_empty()
+			&& !advertises(methods, ClientAuthMethod::ClientSecretBasic.as_str())
+			&& let Some(method) =
+				[ClientAuthMethod::ClientSecretPost, ClientAuthMethod::NoneWithPkce]
+					.into_iter()
+					.find(|method| advertises(methods, method.as_str()))
+		{
+			builder = builder.preferred_client_auth_method(method);
+		}
+
+		Ok(builder.build()?)
+	}
+
+	/// Returns the URL advertised for an optional `endpoint`.
+	pub fn endpoint(&self, endpoint: ProviderEndpoint) -> Option<&Url> {
+		match endpoint {
+			ProviderEndpoint::Revocation => self.revocation_endpoint.as_ref(),
+			ProviderEndpoint::BackchannelAuthentication =>
+				self.backchannel_authentication_endpoint.as_ref(),
+		}
+	}
+
+	/// Copies advertised HTTPS endpoints `descriptor` does not declare yet, marking each one
+	/// experimental so it stays unused until an operator enables it.
+	///
+	/// Returns the endpoints that were staged.
+	pub fn stage_new_endpoints(
+		&self,
+		descriptor: &mut ProviderDescriptor,
+	) -> Vec<ProviderEndpoint> {
+		let mut staged = Vec::new();
+
+		for endpoint in ProviderEndpoint::ALL {
+			let slot = descriptor.endpoints.get_mut(endpoint);
+
+			if let (None, Some(url)) = (slot.as_ref(), self.endpoint(endpoint))
+				&& url.scheme() == "https"
+			{
+				*slot = Some(url.clone());
+
+				descriptor.experimental_endpoints.insert(endpoint);
+				staged.push(endpoint);
+			}
+		}
+
+		staged
+	}
+
+	/// Lists capabilities `descriptor` relies on that this document does not advertise.
+	///
+	/// Metadata lists the provider omits are treated as unknown rather than empty, so only
+	/// explicit contradictions are reported (plus a missing PKCE advertisement when the
+	/// descriptor requires PKCE).
+	pub fn capability_mismatches(
+		&self,
+		descriptor: &ProviderDescriptor,
+	) -> Vec<CapabilityMismatch> {
+		let mut mismatches = Vec::new();
+
+		if !self.grant_types_supported.is_empty() {
+			mismatches.extend(
+				GRANTS
+					.into_iter()
+					.filter(|grant| descriptor.supports(*grant))
+					.filter(|grant| !advertises(&self.grant_types_supported, grant.as_str()))
+					.map(|grant| CapabilityMismatch::Grant { grant }),
+			);
+		}
+
+		if !self.token_endpoint_auth_methods_supported.is_empty() {
+			let mut methods = vec![descriptor.preferred_client_auth_method];
+
+			for method in descriptor.grant_client_auth_methods.values() {
+				if !methods.contains(method) {
+					methods.push(*method);
+				}
+			}
+
+			mismatches.extend(
+				methods
+					.into_iter()
+					.filter(|method| {
+						!advertises(&self.token_endpoint_auth_methods_supported, method.as_str())
+					})
+					.map(|method| CapabilityMismatch::ClientAuthMethod { method }),
+			);
+		}
+		if descriptor.supports(GrantType::AuthorizationCode) {
+			let method = descriptor.client_auth_method(GrantType::AuthorizationCode);
+
+			if self.code_challenge_methods_supported.is_empty() {
+				if descriptor.quirks.pkce_required || method == ClientAuthMethod::NoneWithPkce {
+					mismatches.push(CapabilityMismatch::PkceNotAdvertised);
+				}
+			} else if !advertises(&self.code_challenge_methods_supported, PKCE_S256) {
+				mismatches.push(CapabilityMismatch::PkceS256Unsupported);
+			}
+		}
+
+		mismatches
+	}
+}
+
+/// Capability a descriptor relies on that the provider's discovery document does not advertise.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CapabilityMismatch {
+	/// Enabled grant missing from `grant_types_supported`.
+	Grant {
+		/// Grant enabled on the descriptor.
+		grant: GrantType,
+	},
+	/// Preferred (or per-grant) client authentication method missing from
+	/// `token_endpoint_auth_methods_supported`.
+	ClientAuthMethod {
+		/// Method configured on the descriptor.
+		method: ClientAuthMethod,
+	},
+	/// `code_challenge_methods_supported` does not include `S256`, the only method the broker
+	/// sends.
+	PkceS256Unsupported,
+	/// The descriptor requires PKCE but the document does not advertise any challenge method.
+	PkceNotAdvertised,
+}
+impl CapabilityMismatch {
+	/// Returns `true` when the mismatch will make requests fail, as opposed to merely being
+	/// unconfirmed by the provider.
+	pub fn is_error(self) -> bool {
+		!matches!(self, Self::PkceNotAdvertised)
+	}
+}
+impl Display for CapabilityMismatch {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		match self {
+			Self::Grant { grant } => write!(
+				f,
+				"The descriptor enables the {grant} grant but the provider does not advertise it."
+			),
+			Self::ClientAuthMethod { method } => write!(
+				f,
+				"The descriptor authenticates with {} but the provider does not advertise it.",
+				method.as_str()
+			),
+			Self::PkceS256Unsupported =>
+				f.write_str("The provider does not advertise the S256 PKCE challenge method."),
+			Self::PkceNotAdvertised => f.write_str(
+				"The descriptor requires PKCE but the provider does not advertise any challenge method.",
+			),
+		}
+	}
+}
+
+/// Discovery documents fetched by [`ProviderDescriptor::discover`], keyed by issuer.
+#[derive(Debug)]
+pub struct DiscoveryCache {
+	ttl: Duration,
+	documents: RwLock<HashMap<Url, (DiscoveryDocument, OffsetDateTime)>>,
+}
+impl DiscoveryCache {
+	/// Time a fetched document is reused before it is fetched again.
+	pub const DEFAULT_TTL: Duration = Duration::hours(1);
+
+	/// Creates an empty cache that refetches documents older than `ttl`.
+	pub fn new(ttl: Duration) -> Self {
+		Self { ttl, documents: Default::default() }
+	}
+
+	/// Returns the still-fresh document cached for `issuer`.
+	pub fn get(&self, issuer: &Url) -> Option<DiscoveryDocument> {
+		let documents = self.documents.read();
+		let (document, fetched_at) = documents.get(issuer)?;
+
+		(OffsetDateTime::now_utc() - *fetched_at < self.ttl).then(|| document.clone())
+	}
+
+	/// Forgets the document cached for `issuer`.
+	pub fn invalidate(&self, issuer: &Url) {
+		self.documents.write().remove(issuer);
+	}
+
+	fn insert(&self, issuer: Url, document: DiscoveryDocument) {
+		self.documents.write().insert(issuer, (document, OffsetDateTime::now_utc()));
+	}
+}
+impl Default for DiscoveryCache {
+	fn default() -> Self {
+		Self::new(Self::DEFAULT_TTL)
+	}
+}
+
+impl ProviderDescriptor {
+	/// Builds a descriptor named `id` from the OpenID Connect discovery document of `issuer`.
+	///
+	/// The document is served from `cache` while fresh and otherwise fetched through
+	/// `http_client`. Its `issuer` must match `issuer` (OpenID Connect Discovery §4.3); see
+	/// [`DiscoveryDocument::to_descriptor`] for how it is mapped.
+	pub async fn discover<C>(
+		id: ProviderId,
+		issuer: &Url,
+		http_client: &C,
+		cache: &DiscoveryCache,
+	) -> Result<Self>
+	where
+		C: ?Sized + TokenHttpClient,
+	{
+		if let Some(document) = cache.get(issuer) {
+			return Ok(document.to_descriptor(id)?);
+		}
+
+		let url = DiscoveryDocument::url_for(issuer);
+		let response = http::get(http_client, &url).await.map_err(map_fetch_error)?;
+
+		if !response.status().is_success() {
+			return Err(ConfigError::InvalidDiscoveryDocument {
+				message: format!("{url} returned HTTP {}", response.status().as_u16()),
+			}
+			.into());
+		}
+
+		let document = DiscoveryDocument::from_json(response.body())?;
+
+		if document.issuer.as_ref().is_none_or(|advertised| !same_issuer(advertised, issuer)) {
+			return Err(ConfigError::InvalidDiscoveryDocument {
+				message: format!("the advertised issuer does not match {issuer}"),
+			}
+			.into());
+		}
+
+		let descriptor = document.to_descriptor(id)?;
+
+		cache.insert(issuer.clone(), document);
+
+		Ok(descriptor)
+	}
+}
+
+fn map_fetch_error<E>(error: HttpClientError<E>) -> Error
+where
+	E: 'static + Send + Sync + StdError,
+{
+	match error {
+		HttpClientError::Io(inner) => TransportError::Io(inner).into(),
+		other => TransportError::network(other).into(),
+	}
+}
+
+fn same_issuer(advertised: &Url, expected: &Url) -> bool {
+	advertised.as_str().trim_end_matches('/') == expected.as_str().trim_end_matches('/')
+}
+
+fn advertises(values: &[String], expected: &str) -> bool {
+	values.iter().any(|value| value == expected)
+}
+
+#[cfg(test)]
+mod tests {
+	// self
+	use super::*;
+
+	#[test]
+	fn parses_minimal_document_and_ignores_unknown_fields() {
+		let document = DiscoveryDocument::from_json(
+			br#"{
+				"issuer": "https://issuer.example.com",
+				"token_endpoint": "https://issuer.example.com/token",
+				"userinfo_endpoint": "https://issuer.example.com/userinfo"
+			}"#,
+		)
+		.expect("Minimal discovery document should parse.");
+
+		assert_eq!(
+			document.token_endpoint.as_ref().map(Url::as_str),
+			Some("https://issuer.example.com/token")
+		);
+		assert!(document.authorization_endpoint.is_none());
+		assert!(document.grant_types_supported.is_empty());
+		assert!(DiscoveryDocument::from_json(b"not json").is_err());
+	}
+
+	#[test]
+	fn documents_map_into_validated_descriptors() {
+		let issuer =
+			Url::parse("https://issuer.example.com/realms/acme/").expect("Issuer should parse.");
+
+		assert_eq!(
+			DiscoveryDocument::url_for(&issuer).as_str(),
+			"https://issuer.example.com/realms/acme/.well-known/openid-configuration"
+		);
+
+		let document = DiscoveryDocument::from_json(
+			br#"{
+				"issuer": "https://issuer.example.com/realms/acme",
+				"authorization_endpoint": "https://issuer.example.com/authorize",
+				"token_endpoint": "https://issuer.example.com/token",
+				"introspection_endpoint": "https://issuer.example.com/introspect",
+				"grant_types_supported": [
+					"authorization_code",
+					"client_credentials",
+					"urn:openid:params:grant-type:ciba",
+					"implicit"
+				],
+				"token_endpoint_auth_methods_supported": ["private_key_jwt", "client_secret_post"]
+			}"#,
+		)
+		.expect("Discovery fixture should parse.");
+		let descriptor = document
+			.to_descriptor(
+				crate::auth::ProviderId::new("acme").expect("Provider fixture should be valid."),
+			)
+			.expect("Discovered descriptor should validate.");
+
+		assert!(descriptor.supports(GrantType::AuthorizationCode));
+		assert!(descriptor.supports(GrantType::ClientCredentials));
+		assert!(!descriptor.supports(GrantType::Ciba));
+		assert!(!descriptor.supports(GrantType::RefreshToken));
+		assert_eq!(descriptor.preferred_client_auth_method, ClientAuthMethod::ClientSecretPost);
+		assert_eq!(
+			descriptor.endpoints.introspection.as_ref().map(Url::as_str),
+			Some("https://issuer.example.com/introspect")
+		);
+
+		let insecure = DiscoveryDocument::from_json(
+			br#"{
+				"authorization_endpoint": "https://issuer.example.com/authorize",
+				"token_endpoint": "http://issuer.example.com/token"
+			}"#,
+		)
+		.expect("Discovery fixture should parse.");
+
+		assert!(
+			insecure
+				.to_descriptor(
+					crate::auth::ProviderId::new("insecure")
+						.expect("Provider fixture should be valid."),
+				)
+				.is_err()
+		);
+	}
+
+	#[test]
+	fn capability_mismatches_flag_grants_auth_methods_and_pkce() {
+		let descriptor = ProviderDescriptor::builder(
+			crate::auth::ProviderId::new("capabilities")
+				.expect("Provider fixture should be valid."),
+		)
+		.authorization_endpoint(
+			Url::parse("https://issuer.example.com/authorize")
+				.expect("Authorization endpoint fixture should parse."),
+		)
+		.token_endpoint(
+			Url::parse("https://issuer.example.com/token")
+				.expect("Token endpoint fixture should parse."),
+		)
+		.support_grants([GrantType::AuthorizationCode, GrantType::ClientCredentials])
+		.preferred_client_auth_method(ClientAuthMethod::ClientSecretPost)
+		.build()
+		.expect("Descriptor fixture should build.");
+		let document = DiscoveryDocument::from_json(
+			br#"{
+				"grant_types_supported": ["authorization_code", "refresh_token"],
+				"token_endpoint_auth_methods_supported": ["client_secret_basic"],
+				"code_challenge_methods_supported": ["plain"]
+			}"#,
+		)
+		.expect("Discovery fixture should parse.");
+
+		assert_eq!(
+			document.capability_mismatches(&descriptor),
+			[
+				CapabilityMismatch::Grant { grant: GrantType::ClientCredentials },
+				CapabilityMismatch::ClientAuthMethod { method: ClientAuthMethod::ClientSecretPost },
+				CapabilityMismatch::PkceS256Unsupported,
+			]
+		);
+
+		let silent = DiscoveryDocument::from_json(b"{}").expect("Empty document should parse.");
+
+		assert!(silent.capability_mismatches(&descriptor).is_empty());
+	}
+
+	#[test]
+	fn new_endpoints_are_staged_as_experimental() {
+		let mut descriptor = ProviderDescriptor::builder(
+			crate::auth::ProviderId::new("staging").expect("Provider fixture should be valid."),
+		)
+		.authorization_endpoint(
+			Url::parse("https://issuer.example.com/authorize")
+				.expect("Authorization endpoint fixture should parse."),
+		)
+		.token_endpoint(
+			Url::parse("https://issuer.example.com/token")
+				.expect("Token endpoint fixture should parse."),
+		)
+		.support_grants([GrantType::ClientCredentials])
+		.build()
+		.expect("Descriptor fixture should build.");
+		let document = DiscoveryDocument::from_json(
+			br#"{
+				"revocation_endpoint": "https://issuer.example.com/revoke",
+				"backchannel_authentication_endpoint": "http://issuer.example.com/bc"
+			}"#,
+		)
+		.expect("Discovery fixture should parse.");
+
+		assert_eq!(document.stage_new_endpoints(&mut descriptor), [ProviderEndpoint::Revocation]);
+		assert!(descriptor.is_experimental(ProviderEndpoint::Revocation));
+		assert!(descriptor.endpoints.backchannel_authentication.is_none());
+		assert!(document.stage_new_endpoints(&mut descriptor).is_empty());
+	}
+}
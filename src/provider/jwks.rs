@@ -0,0 +1,306 @@
+//! JSON Web Key Sets (RFC 7517) and the cache used to verify signed provider responses.
+//!
+//! Providers whose descriptor sets
+//! [`ProviderQuirks::token_response_format`](crate::provider::ProviderQuirks::token_response_format)
+//! to [`TokenResponseFormat::Jwt`](crate::provider::TokenResponseFormat::Jwt) return token
+//! responses as compact JWS objects. The broker resolves the signing key from the descriptor's
+//! JWKS endpoint through a [`JwksCache`], refetching once when a `kid` is unknown so provider key
+//! rotations are picked up without a restart. Signature checks need the `ring` feature.
+
+// crates.io
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use oauth2::HttpClientError;
+// self
+use crate::{
+	_prelude::*,
+	error::ConfigError,
+	http::{self, TokenHttpClient},
+};
+
+/// Public key from a JSON Web Key Set; only the members the broker verifies with are kept.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Jwk {
+	/// Key type (`RSA`, `EC`, or `OKP`).
+	pub kty: String,
+	/// Key identifier matched against the JWS `kid` header.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub kid: Option<String>,
+	/// Algorithm the key is restricted to, if any.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub alg: Option<String>,
+	/// Intended use (`sig` or `enc`).
+	#[serde(default, rename = "use", skip_serializing_if = "Option::is_none")]
+	pub key_use: Option<String>,
+	/// RSA modulus (base64url).
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub n: Option<String>,
+	/// RSA public exponent (base64url).
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub e: Option<String>,
+	/// Curve name of `EC` and `OKP` keys.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub crv: Option<String>,
+	/// X coordinate (`EC`) or public key (`OKP`), base64url.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub x: Option<String>,
+	/// Y coordinate of `EC` keys (base64url).
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub y: Option<String>,
+}
+impl Jwk {
+	fn verifies(&self, kid: Option<&str>, alg: &str) -> bool {
+		let kty = match alg {
+			"RS256" | "RS384" | "RS512" | "PS256" | "PS384" | "PS512" => "RSA",
+			"ES256" | "ES384" => "EC",
+			"EdDSA" => "OKP",
+			_ => return false,
+		};
+
+		self.kty == kty
+			&& self.key_use.as_deref().is_none_or(|key_use| key_use == "sig")
+			&& self.alg.as_deref().is_none_or(|key_alg| key_alg == alg)
+			&& kid.is_none_or(|kid| self.kid.as_deref() == Some(kid))
+	}
+}
+
+/// JSON Web Key Set document served at a provider's `jwks_uri`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JwkSet {
+	/// Published keys; entries the broker cannot parse are dropped.
+	#[serde(deserialize_with = "deserialize_keys")]
+	pub keys: Vec<Jwk>,
+}
+impl JwkSet {
+	/// Parses a key set from its raw JSON representation.
+	pub fn from_json(bytes: &[u8]) -> Result<Self, ConfigError> {
+		serde_json::from_slice(bytes)
+			.map_err(|e| ConfigError::InvalidJwks { message: e.to_string() })
+	}
+
+	/// Returns the first key usable for `alg` (and `kid`, when the JWS names one).
+	pub fn find(&self, kid: Option<&str>, alg: &str) -> Option<&Jwk> {
+		self.keys.iter().find(|key| key.verifies(kid, alg))
+	}
+}
+
+/// Key sets fetched from provider JWKS endpoints, shared by every clone of a broker.
+#[derive(Debug)]
+pub struct JwksCache {
+	ttl: Duration,
+	sets: RwLock<HashMap<Url, (JwkSet, OffsetDateTime)>>,
+}
+impl JwksCache {
+	/// Time a fetched key set is reused before it is fetched again.
+	pub const DEFAULT_TTL: Duration = Duration::hours(1);
+
+	/// Creates an empty cache that refetches key sets older than `ttl`.
+	pub fn new(ttl: Duration) -> Self {
+		Self { ttl, sets: Default::default() }
+	}
+
+	/// Seeds (or replaces) the key set for `url`, e.g. for providers that publish keys out of
+	/// band.
+	pub fn insert(&self, url: Url, set: JwkSet) {
+		self.sets.write().insert(url, (set, OffsetDateTime::now_utc()));
+	}
+
+	/// Forgets the key set cached for `url`.
+	pub fn invalidate(&self, url: &Url) {
+		self.sets.write().remove(url);
+	}
+
+	/// Resolves the key for `kid`/`alg`, fetching the set when it is missing, stale, or lacks
+	/// the key.
+	pub(crate) async fn key<C>(
+		&self,
+		client: &C,
+		url: &Url,
+		kid: Option<&str>,
+		alg: &str,
+	) -> Result<Option<Jwk>, HttpClientError<C::TransportError>>
+	where
+		C: ?Sized + TokenHttpClient,
+	{
+		let now = OffsetDateTime::now_utc();
+
+		if let Some((set, fetched_at)) = self.sets.read().get(url)
+			&& now - *fetched_at < self.ttl
+			&& let Some(key) = set.find(kid, alg)
+		{
+			return Ok(Some(key.clone()));
+		}
+
+		let response = http::get(client, url).await?;
+
+		if !response.status().is_success() {
+			return Err(HttpClientError::Other(format!(
+				"JWKS endpoint returned HTTP {}",
+				response.status().as_u16()
+			)));
+		}
+
+		let set = JwkSet::from_json(response.body())
+			.map_err(|e| HttpClientError::Other(e.to_string()))?;
+		let key = set.find(kid, alg).cloned();
+
+		self.sets.write().insert(url.clone(), (set, now));
+
+		Ok(key)
+	}
+}
+impl Default for JwksCache {
+	fn default() -> Self {
+		Self::new(Self::DEFAULT_TTL)
+	}
+}
+
+/// Protected header members of a compact JWS the broker inspects.
+#[derive(Debug, Deserialize)]
+pub(crate) struct JwsHeader {
+	pub(crate) alg: String,
+	#[serde(default)]
+	pub(crate) kid: Option<String>,
+}
+
+/// Decodes the protected header of a compact JWS.
+pub(crate) fn jws_header(jws: &str) -> Option<JwsHeader> {
+	let header = jws.split('.').next()?;
+
+	serde_json::from_slice(&URL_SAFE_NO_PAD.decode(header).ok()?).ok()
+}
+
+/// Verifies a compact JWS with `key`, returning the decoded payload.
+pub(crate) fn verify_jws(jws: &str, alg: &str, key: &Jwk) -> Result<Vec<u8>, String> {
+	let mut segments = jws.split('.');
+	let (Some(header), Some(payload), Some(signature), None) =
+		(segments.next(), segments.next(), segments.next(), segments.next())
+	else {
+		return Err("response is not a compact JWS".into());
+	};
+	let signature =
+		URL_SAFE_NO_PAD.decode(signature).map_err(|_| "JWS signature is not base64url")?;
+	let signing_input = &jws[..header.len() + 1 + payload.len()];
+
+	verify_signature(alg, key, signing_input.as_bytes(), &signature)?;
+
+	URL_SAFE_NO_PAD.decode(payload).map_err(|_| "JWS payload is not base64url".into())
+}
+
+#[cfg(feature = "ring")]
+fn verify_signature(alg: &str, key: &Jwk, message: &[u8], signature: &[u8]) -> Result<(), String> {
+	use ring::signature::{self, RsaPublicKeyComponents, UnparsedPublicKey};
+
+	let member = |value: &Option<String>, name: &str| {
+		value
+			.as_deref()
+			.and_then(|value| URL_SAFE_NO_PAD.decode(value).ok())
+			.ok_or_else(|| format!("JWK is missing a valid `{name}` member"))
+	};
+	let rsa = |params: &'static signature::RsaParameters| -> Result<(), String> {
+		RsaPublicKeyComponents { n: member(&key.n, "n")?, e: member(&key.e, "e")? }
+			.verify(params, message, signature)
+			.map_err(|_| "JWS signature is invalid".into())
+	};
+	let ec = |params: &'static signature::EcdsaVerificationAlgorithm| -> Result<(), String> {
+		let point = [vec![0x04], member(&key.x, "x")?, member(&key.y, "y")?].concat();
+
+		UnparsedPublicKey::new(params, point)
+			.verify(message, signature)
+			.map_err(|_| "JWS signature is invalid".into())
+	};
+
+	match alg {
+		"RS256" => rsa(&signature::RSA_PKCS1_2048_8192_SHA256),
+		"RS384" => rsa(&signature::RSA_PKCS1_2048_8192_SHA384),
+		"RS512" => rsa(&signature::RSA_PKCS1_2048_8192_SHA512),
+		"PS256" => rsa(&signature::RSA_PSS_2048_8192_SHA256),
+		"PS384" => rsa(&signature::RSA_PSS_2048_8192_SHA384),
+		"PS512" => rsa(&signature::RSA_PSS_2048_8192_SHA512),
+		"ES256" => ec(&signature::ECDSA_P256_SHA256_FIXED),
+		"ES384" => ec(&signature::ECDSA_P384_SHA384_FIXED),
+		"EdDSA" => UnparsedPublicKey::new(&signature::ED25519, member(&key.x, "x")?)
+			.verify(message, signature)
+			.map_err(|_| "JWS signature is invalid".into()),
+		other => Err(format!("JWS algorithm `{other}` is not supported")),
+	}
+}
+
+#[cfg(not(feature = "ring"))]
+fn verify_signature(_: &str, _: &Jwk, _: &[u8], _: &[u8]) -> Result<(), String> {
+	Err("verifying signed token responses requires the `ring` feature".into())
+}
+
+fn deserialize_keys<'de, D>(deserializer: D) -> Result<Vec<Jwk>, D::Error>
+where
+	D: serde::Deserializer<'de>,
+{
+	let raw = Vec::<serde_json::Value>::deserialize(deserializer)?;
+
+	Ok(raw.into_iter().filter_map(|key| serde_json::from_value(key).ok()).collect())
+}
+
+#[cfg(all(test, feature = "ring"))]
+mod tests {
+	// crates.io
+	use ring::{
+		rand::SystemRandom,
+		signature::{ECDSA_P256_SHA256_FIXED_SIGNING, EcdsaKeyPair, KeyPair},
+	};
+	// self
+	use super::*;
+
+	#[test]
+	fn es256_responses_verify_against_the_matching_key_only() {
+		let rng = SystemRandom::new();
+		let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng)
+			.expect("P-256 key generation should succeed.");
+		let key_pair =
+			EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng)
+				.expect("Generated key should load.");
+		let point = key_pair.public_key().as_ref();
+		let set = JwkSet::from_json(
+			serde_json::json!({
+				"keys": [
+					{ "kty": "oct", "k": "c2VjcmV0" },
+					{
+						"kty": "EC",
+						"kid": "k1",
+						"use": "sig",
+						"crv": "P-256",
+						"x": URL_SAFE_NO_PAD.encode(&point[1..33]),
+						"y": URL_SAFE_NO_PAD.encode(&point[33..]),
+					},
+				]
+			})
+			.to_string()
+			.as_bytes(),
+		)
+		.expect("Key set fixture should parse.");
+		let signing_input = format!(
+			"{}.{}",
+			URL_SAFE_NO_PAD.encode(r#"{"alg":"ES256","kid":"k1"}"#),
+			URL_SAFE_NO_PAD.encode(r#"{"access_token":"signed"}"#)
+		);
+		let signature =
+			key_pair.sign(&rng, signing_input.as_bytes()).expect("Signing should succeed.");
+		let jws = format!("{signing_input}.{}", URL_SAFE_NO_PAD.encode(signature.as_ref()));
+		let header = jws_header(&jws).expect("Header should decode.");
+		let key = set.find(header.kid.as_deref(), &header.alg).expect("Key k1 should match.");
+
+		assert_eq!(set.keys.len(), 2);
+		assert!(set.find(Some("k2"), "ES256").is_none());
+		assert!(set.find(Some("k1"), "RS256").is_none());
+		assert_eq!(
+			verify_jws(&jws, &header.alg, key).expect("Signature should verify."),
+			br#"{"access_token":"signed"}"#
+		);
+
+		let tampered = jws.replacen(
+			&URL_SAFE_NO_PAD.encode(r#"{"access_token":"signed"}"#),
+			&URL_SAFE_NO_PAD.encode(r#"{"access_token":"forged"}"#),
+			1,
+		);
+
+		assert!(verify_jws(&tampered, &header.alg, key).is_err());
+	}
+}
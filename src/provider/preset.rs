@@ -0,0 +1,17 @@
+//! Ready-made descriptor builders and strategies for well-known providers.
+//!
+//! Each preset returns a [`ProviderDescriptorBuilder`](crate::provider::ProviderDescriptorBuilder)
+//! with endpoints, grants, client authentication, and quirks already filled in, so callers can
+//! still adjust grants or endpoints before calling `build()`.
+
+pub mod github;
+pub mod google;
+pub mod keycloak;
+pub mod salesforce;
+pub mod x;
+
+pub use github::*;
+pub use google::*;
+pub use keycloak::*;
+pub use salesforce::*;
+pub use x::*;
@@ -0,0 +1,103 @@
+//! GitHub OAuth App / GitHub App user-to-server preset.
+//!
+//! GitHub's token endpoint answers with a form-encoded body unless the request carries
+//! `Accept: application/json`; the broker's facade always sends that header, so responses are
+//! parsed as JSON. GitHub also joins scopes with commas and omits `expires_in` for classic
+//! OAuth App tokens, which never expire; the preset quirks cover both.
+
+// self
+use crate::{
+	_prelude::*,
+	auth::ProviderId,
+	error::ConfigError,
+	provider::{
+		ClientAuthMethod, DefaultProviderStrategy, GrantType, ProviderDescriptor,
+		ProviderDescriptorBuilder, ProviderErrorContext, ProviderErrorKind, ProviderQuirks,
+		ProviderStrategy,
+	},
+};
+
+/// Provider identifier used by [`github_descriptor_builder`].
+pub const GITHUB_PROVIDER_ID: &str = "github";
+/// Lifetime assumed for classic GitHub tokens that omit `expires_in` (365 days).
+pub const GITHUB_CLASSIC_TOKEN_LIFETIME_SECS: u64 = 60 * 60 * 24 * 365;
+
+const AUTHORIZATION_ENDPOINT: &str = "https://github.com/login/oauth/authorize";
+const TOKEN_ENDPOINT: &str = "https://github.com/login/oauth/access_token";
+
+/// Quirks matching GitHub's token endpoint behavior.
+pub fn github_quirks() -> ProviderQuirks {
+	ProviderQuirks {
+		scope_delimiter: ',',
+		default_expires_in_secs: Some(GITHUB_CLASSIC_TOKEN_LIFETIME_SECS),
+		..ProviderQuirks::default()
+	}
+}
+
+/// Descriptor builder preconfigured for GitHub's Authorization Code flow.
+///
+/// Refresh tokens are enabled for GitHub Apps with expiring user tokens; classic OAuth Apps
+/// simply never return one.
+pub fn github_descriptor_builder() -> Result<ProviderDescriptorBuilder, ConfigError> {
+	let id = ProviderId::new(GITHUB_PROVIDER_ID)?;
+	let authorization = Url::parse(AUTHORIZATION_ENDPOINT)
+		.map_err(|source| ConfigError::InvalidDescriptor { source })?;
+	let token =
+		Url::parse(TOKEN_ENDPOINT).map_err(|source| ConfigError::InvalidDescriptor { source })?;
+
+	Ok(ProviderDescriptor::builder(id)
+		.authorization_endpoint(authorization)
+		.token_endpoint(token)
+		.support_grants([GrantType::AuthorizationCode, GrantType::RefreshToken])
+		.preferred_client_auth_method(ClientAuthMethod::ClientSecretPost)
+		.quirks(github_quirks()))
+}
+
+/// Strategy that understands GitHub's non-standard OAuth error codes.
+///
+/// Unknown codes fall back to [`DefaultProviderStrategy`].
+#[derive(Debug, Default)]
+pub struct GitHubStrategy;
+impl Display for GitHubStrategy {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		f.write_str("github-provider-strategy")
+	}
+}
+impl ProviderStrategy for GitHubStrategy {
+	fn classify_token_error(&self, ctx: &ProviderErrorContext) -> ProviderErrorKind {
+		match ctx.oauth_error.as_deref() {
+			Some("bad_verification_code" | "bad_refresh_token") => ProviderErrorKind::InvalidGrant,
+			Some("incorrect_client_credentials") => ProviderErrorKind::InvalidClient,
+			_ => DefaultProviderStrategy.classify_token_error(ctx),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	// self
+	use super::*;
+
+	#[test]
+	fn preset_builds_with_github_quirks() {
+		let descriptor = github_descriptor_builder()
+			.expect("GitHub preset should be constructible.")
+			.build()
+			.expect("GitHub preset should pass descriptor validation.");
+
+		assert_eq!(descriptor.id.as_ref(), GITHUB_PROVIDER_ID);
+		assert_eq!(descriptor.quirks.scope_delimiter, ',');
+		assert_eq!(
+			descriptor.quirks.default_expires_in_secs,
+			Some(GITHUB_CLASSIC_TOKEN_LIFETIME_SECS)
+		);
+		assert!(descriptor.supports(GrantType::AuthorizationCode));
+		assert_eq!(
+			GitHubStrategy.classify_token_error(
+				&ProviderErrorContext::new(GrantType::AuthorizationCode)
+					.with_oauth_error("bad_verification_code")
+			),
+			ProviderErrorKind::InvalidGrant
+		);
+	}
+}
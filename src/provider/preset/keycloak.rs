@@ -0,0 +1,77 @@
+//! Keycloak realm preset with offline token support.
+//!
+//! Keycloak issues offline tokens when the `offline_access` scope is requested. Their refresh
+//! tokens survive SSO session expiry and are reported with `refresh_expires_in: 0`, which the
+//! broker records as a non-expiring refresh token.
+
+// self
+use crate::{
+	_prelude::*,
+	auth::{ProviderId, ScopeSet, ScopeValidationError},
+	error::ConfigError,
+	provider::{GrantType, ProviderDescriptor, ProviderDescriptorBuilder},
+};
+
+/// Provider identifier used by [`keycloak_descriptor_builder`].
+pub const KEYCLOAK_PROVIDER_ID: &str = "keycloak";
+/// Scope that asks Keycloak for an offline token.
+pub const KEYCLOAK_OFFLINE_SCOPE: &str = "offline_access";
+
+/// Descriptor builder for a Keycloak realm, e.g. `https://sso.example.com/realms/acme`.
+///
+/// Endpoints follow Keycloak's `protocol/openid-connect` layout under the realm URL.
+pub fn keycloak_descriptor_builder(realm: &Url) -> Result<ProviderDescriptorBuilder, ConfigError> {
+	let id = ProviderId::new(KEYCLOAK_PROVIDER_ID)?;
+	let base = realm.as_str().trim_end_matches('/');
+	let endpoint = |path: &str| {
+		Url::parse(&format!("{base}/protocol/openid-connect/{path}"))
+			.map_err(|source| ConfigError::InvalidDescriptor { source })
+	};
+
+	Ok(ProviderDescriptor::builder(id)
+		.authorization_endpoint(endpoint("auth")?)
+		.token_endpoint(endpoint("token")?)
+		.revocation_endpoint(endpoint("revoke")?)
+		.support_grants([
+			GrantType::AuthorizationCode,
+			GrantType::RefreshToken,
+			GrantType::ClientCredentials,
+		]))
+}
+
+/// Returns `scope` with [`KEYCLOAK_OFFLINE_SCOPE`] added so Keycloak issues an offline token.
+pub fn keycloak_offline_scope(scope: &ScopeSet) -> Result<ScopeSet, ScopeValidationError> {
+	ScopeSet::new(scope.iter().chain([KEYCLOAK_OFFLINE_SCOPE]))
+}
+
+#[cfg(test)]
+mod tests {
+	// self
+	use super::*;
+
+	#[test]
+	fn preset_derives_endpoints_from_realm_url() {
+		let realm = Url::parse("https://sso.example.com/realms/acme/")
+			.expect("Realm URL fixture should parse.");
+		let descriptor = keycloak_descriptor_builder(&realm)
+			.expect("Keycloak preset should be constructible.")
+			.build()
+			.expect("Keycloak preset should pass descriptor validation.");
+
+		assert_eq!(
+			descriptor.endpoints.token.as_str(),
+			"https://sso.example.com/realms/acme/protocol/openid-connect/token"
+		);
+		assert_eq!(
+			descriptor.endpoints.revocation.as_ref().map(Url::as_str),
+			Some("https://sso.example.com/realms/acme/protocol/openid-connect/revoke")
+		);
+
+		let scope = keycloak_offline_scope(
+			&ScopeSet::new(["openid"]).expect("Scope fixture should be valid."),
+		)
+		.expect("Offline scope should be valid.");
+
+		assert!(scope.contains(KEYCLOAK_OFFLINE_SCOPE));
+	}
+}
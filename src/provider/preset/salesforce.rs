@@ -0,0 +1,128 @@
+//! Salesforce preset for the JWT Bearer and Authorization Code flows.
+//!
+//! Salesforce answers every grant with an `instance_url` pointing at the org's API host; the
+//! broker captures it into [`TokenRecord::extras`] so callers can build REST URLs without a second
+//! lookup. Token responses omit `expires_in` because session lifetime is an org setting, so the
+//! preset assumes Salesforce's default two-hour session timeout.
+
+// self
+use crate::{
+	_prelude::*,
+	auth::{ProviderId, TokenRecord},
+	error::ConfigError,
+	flows::{JwtBearerConfig, JwtSigner},
+	provider::{
+		ClientAuthMethod, GrantType, ProviderDescriptor, ProviderDescriptorBuilder, ProviderQuirks,
+	},
+};
+
+/// Provider identifier used by [`salesforce_descriptor_builder`].
+pub const SALESFORCE_PROVIDER_ID: &str = "salesforce";
+/// Login host for production and developer orgs.
+pub const SALESFORCE_LOGIN_URL: &str = "https://login.salesforce.com";
+/// Login host for sandbox orgs.
+pub const SALESFORCE_SANDBOX_LOGIN_URL: &str = "https://test.salesforce.com";
+/// [`TokenRecord::extras`] key holding the org's API base URL.
+pub const SALESFORCE_INSTANCE_URL_EXTRA: &str = "instance_url";
+/// Lifetime assumed for Salesforce sessions, which omit `expires_in` (two hours).
+pub const SALESFORCE_DEFAULT_SESSION_SECS: u64 = 2 * 60 * 60;
+/// Longest assertion lifetime Salesforce accepts (three minutes).
+pub const SALESFORCE_ASSERTION_LIFETIME_SECS: i64 = 3 * 60;
+
+/// Quirks matching Salesforce's token endpoint behavior.
+pub fn salesforce_quirks() -> ProviderQuirks {
+	ProviderQuirks {
+		default_expires_in_secs: Some(SALESFORCE_DEFAULT_SESSION_SECS),
+		..ProviderQuirks::default()
+	}
+}
+
+/// Descriptor builder for a Salesforce login host or My Domain URL, e.g.
+/// [`SALESFORCE_LOGIN_URL`] or `https://acme.my.salesforce.com`.
+pub fn salesforce_descriptor_builder(
+	login: &Url,
+) -> Result<ProviderDescriptorBuilder, ConfigError> {
+	let id = ProviderId::new(SALESFORCE_PROVIDER_ID)?;
+	let base = login.as_str().trim_end_matches('/');
+	let endpoint = |path: &str| {
+		Url::parse(&format!("{base}/services/oauth2/{path}"))
+			.map_err(|source| ConfigError::InvalidDescriptor { source })
+	};
+
+	Ok(ProviderDescriptor::builder(id)
+		.authorization_endpoint(endpoint("authorize")?)
+		.token_endpoint(endpoint("token")?)
+		.revocation_endpoint(endpoint("revoke")?)
+		.support_grants([
+			GrantType::AuthorizationCode,
+			GrantType::RefreshToken,
+			GrantType::JwtBearer,
+		])
+		.preferred_client_auth_method(ClientAuthMethod::ClientSecretPost)
+		.quirks(salesforce_quirks()))
+}
+
+/// Builds the assertion config Salesforce expects for the JWT Bearer flow.
+///
+/// `consumer_key` is the connected app's client identifier, `username` the pre-authorized user
+/// the token is minted for, and `login` the login host used as the assertion audience (not the
+/// token endpoint).
+pub fn salesforce_jwt_bearer_config(
+	signer: Arc<dyn JwtSigner>,
+	consumer_key: impl Into<String>,
+	username: impl Into<String>,
+	login: &Url,
+) -> JwtBearerConfig {
+	JwtBearerConfig::new(signer, consumer_key)
+		.with_subject(username)
+		.with_audience(login.as_str().trim_end_matches('/'))
+		.with_lifetime(Duration::seconds(SALESFORCE_ASSERTION_LIFETIME_SECS))
+}
+
+/// Returns the org's API base URL captured from the token response.
+pub fn salesforce_instance_url(record: &TokenRecord) -> Option<Url> {
+	record.extra(SALESFORCE_INSTANCE_URL_EXTRA).and_then(|raw| Url::parse(raw).ok())
+}
+
+#[cfg(test)]
+mod tests {
+	// self
+	use super::*;
+
+	struct NoopSigner;
+	impl JwtSigner for NoopSigner {
+		fn algorithm(&self) -> &str {
+			"RS256"
+		}
+
+		fn sign(&self, _: &[u8]) -> Result<Vec<u8>, ConfigError> {
+			Ok(Vec::new())
+		}
+	}
+
+	#[test]
+	fn preset_targets_login_host_for_endpoints_and_audience() {
+		let login = Url::parse(SALESFORCE_SANDBOX_LOGIN_URL).expect("Login URL should parse.");
+		let descriptor = salesforce_descriptor_builder(&login)
+			.expect("Salesforce preset should be constructible.")
+			.build()
+			.expect("Salesforce preset should pass descriptor validation.");
+
+		assert_eq!(
+			descriptor.endpoints.token.as_str(),
+			"https://test.salesforce.com/services/oauth2/token"
+		);
+		assert!(descriptor.supports(GrantType::JwtBearer));
+
+		let config = salesforce_jwt_bearer_config(
+			Arc::new(NoopSigner),
+			"consumer-key",
+			"integration@acme.com",
+			&login,
+		);
+
+		assert_eq!(config.audience.as_deref(), Some(SALESFORCE_SANDBOX_LOGIN_URL));
+		assert_eq!(config.subject.as_deref(), Some("integration@acme.com"));
+		assert_eq!(config.lifetime, Duration::minutes(3));
+	}
+}
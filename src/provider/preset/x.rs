@@ -0,0 +1,146 @@
+//! X (formerly Twitter) OAuth 2.0 preset.
+//!
+//! X requires PKCE on every authorization request, including confidential clients, which
+//! authenticate at the token endpoint with HTTP Basic. Refresh tokens are only issued when the
+//! `offline.access` scope is requested.
+
+// self
+use crate::{
+	_prelude::*,
+	auth::{ProviderId, ScopeSet, ScopeValidationError},
+	error::ConfigError,
+	provider::{
+		ClientAuthMethod, GrantType, ProviderDescriptor, ProviderDescriptorBuilder, ProviderQuirks,
+	},
+};
+
+/// Provider identifier used by [`x_com`].
+pub const X_PROVIDER_ID: &str = "x-com";
+/// Scope that asks X to issue a refresh token.
+pub const X_OFFLINE_SCOPE: &str = "offline.access";
+/// Read posts, including those from protected accounts the user follows.
+pub const X_SCOPE_TWEET_READ: &str = "tweet.read";
+/// Publish and delete posts on the user's behalf.
+pub const X_SCOPE_TWEET_WRITE: &str = "tweet.write";
+/// Hide and unhide replies to the user's posts.
+pub const X_SCOPE_TWEET_MODERATE_WRITE: &str = "tweet.moderate.write";
+/// Read profile information for any account the user can see.
+pub const X_SCOPE_USERS_READ: &str = "users.read";
+/// Read the user's email address.
+pub const X_SCOPE_USERS_EMAIL: &str = "users.email";
+/// Read the accounts the user follows and is followed by.
+pub const X_SCOPE_FOLLOWS_READ: &str = "follows.read";
+/// Follow and unfollow accounts.
+pub const X_SCOPE_FOLLOWS_WRITE: &str = "follows.write";
+/// Read Spaces.
+pub const X_SCOPE_SPACE_READ: &str = "space.read";
+/// Read the accounts the user has muted.
+pub const X_SCOPE_MUTE_READ: &str = "mute.read";
+/// Mute and unmute accounts.
+pub const X_SCOPE_MUTE_WRITE: &str = "mute.write";
+/// Read posts the user has liked.
+pub const X_SCOPE_LIKE_READ: &str = "like.read";
+/// Like and unlike posts.
+pub const X_SCOPE_LIKE_WRITE: &str = "like.write";
+/// Read Lists the user owns, follows, or belongs to.
+pub const X_SCOPE_LIST_READ: &str = "list.read";
+/// Create and manage Lists.
+pub const X_SCOPE_LIST_WRITE: &str = "list.write";
+/// Read the accounts the user has blocked.
+pub const X_SCOPE_BLOCK_READ: &str = "block.read";
+/// Block and unblock accounts.
+pub const X_SCOPE_BLOCK_WRITE: &str = "block.write";
+/// Read the user's bookmarks.
+pub const X_SCOPE_BOOKMARK_READ: &str = "bookmark.read";
+/// Add and remove bookmarks.
+pub const X_SCOPE_BOOKMARK_WRITE: &str = "bookmark.write";
+/// Upload media.
+pub const X_SCOPE_MEDIA_WRITE: &str = "media.write";
+/// Read direct messages.
+pub const X_SCOPE_DM_READ: &str = "dm.read";
+/// Send direct messages.
+pub const X_SCOPE_DM_WRITE: &str = "dm.write";
+/// Every scope X accepts for OAuth 2.0 user-context tokens.
+pub const X_SCOPES: &[&str] = &[
+	X_SCOPE_TWEET_READ,
+	X_SCOPE_TWEET_WRITE,
+	X_SCOPE_TWEET_MODERATE_WRITE,
+	X_SCOPE_USERS_READ,
+	X_SCOPE_USERS_EMAIL,
+	X_SCOPE_FOLLOWS_READ,
+	X_SCOPE_FOLLOWS_WRITE,
+	X_OFFLINE_SCOPE,
+	X_SCOPE_SPACE_READ,
+	X_SCOPE_MUTE_READ,
+	X_SCOPE_MUTE_WRITE,
+	X_SCOPE_LIKE_READ,
+	X_SCOPE_LIKE_WRITE,
+	X_SCOPE_LIST_READ,
+	X_SCOPE_LIST_WRITE,
+	X_SCOPE_BLOCK_READ,
+	X_SCOPE_BLOCK_WRITE,
+	X_SCOPE_BOOKMARK_READ,
+	X_SCOPE_BOOKMARK_WRITE,
+	X_SCOPE_MEDIA_WRITE,
+	X_SCOPE_DM_READ,
+	X_SCOPE_DM_WRITE,
+];
+
+const AUTHORIZATION_ENDPOINT: &str = "https://x.com/i/oauth2/authorize";
+const TOKEN_ENDPOINT: &str = "https://api.x.com/2/oauth2/token";
+const REVOCATION_ENDPOINT: &str = "https://api.x.com/2/oauth2/revoke";
+
+/// Quirks matching X's authorization server behavior.
+pub fn x_com_quirks() -> ProviderQuirks {
+	ProviderQuirks { pkce_required: true, ..ProviderQuirks::default() }
+}
+
+/// Descriptor builder preconfigured for X confidential clients.
+///
+/// Public (PKCE-only) clients should switch the builder to
+/// [`ClientAuthMethod::NoneWithPkce`] before calling `build()`.
+pub fn x_com() -> Result<ProviderDescriptorBuilder, ConfigError> {
+	let id = ProviderId::new(X_PROVIDER_ID)?;
+	let endpoint =
+		|raw: &str| Url::parse(raw).map_err(|source| ConfigError::InvalidDescriptor { source });
+
+	Ok(ProviderDescriptor::builder(id)
+		.authorization_endpoint(endpoint(AUTHORIZATION_ENDPOINT)?)
+		.token_endpoint(endpoint(TOKEN_ENDPOINT)?)
+		.revocation_endpoint(endpoint(REVOCATION_ENDPOINT)?)
+		.support_grants([GrantType::AuthorizationCode, GrantType::RefreshToken])
+		.preferred_client_auth_method(ClientAuthMethod::ClientSecretBasic)
+		.quirks(x_com_quirks()))
+}
+
+/// Returns `scope` with [`X_OFFLINE_SCOPE`] added so X issues a refresh token.
+pub fn x_com_offline_scope(scope: &ScopeSet) -> Result<ScopeSet, ScopeValidationError> {
+	ScopeSet::new(scope.iter().chain([X_OFFLINE_SCOPE]))
+}
+
+#[cfg(test)]
+mod tests {
+	// self
+	use super::*;
+
+	#[test]
+	fn preset_requires_pkce_and_basic_auth() {
+		let descriptor = x_com()
+			.expect("X preset should be constructible.")
+			.build()
+			.expect("X preset should pass descriptor validation.");
+
+		assert!(descriptor.quirks.pkce_required);
+		assert_eq!(descriptor.preferred_client_auth_method, ClientAuthMethod::ClientSecretBasic);
+		assert!(X_SCOPES.contains(&X_OFFLINE_SCOPE));
+
+		let scope = x_com_offline_scope(
+			&ScopeSet::new([X_SCOPE_TWEET_READ, X_SCOPE_USERS_READ])
+				.expect("Scope fixture should be valid."),
+		)
+		.expect("Offline scope should be valid.");
+
+		assert!(scope.contains(X_OFFLINE_SCOPE));
+		assert!(ScopeSet::new(X_SCOPES.iter().copied()).is_ok());
+	}
+}
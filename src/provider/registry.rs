@@ -0,0 +1,106 @@
+//! Provider registrations handed to a [`MultiBroker`](crate::flows::MultiBroker).
+//!
+//! A [`ProviderRegistry`] collects the descriptor, strategy, and client credentials of each
+//! provider keyed by [`ProviderId`]. Registrations are validated up front, so a registry that
+//! built successfully registers on a broker without descriptor errors.
+
+// self
+use crate::{_prelude::*, auth::ProviderId, error::ConfigError, flows::ProviderHandle};
+
+/// Providers keyed by identifier, registered together on a broker.
+#[derive(Clone, Debug, Default)]
+pub struct ProviderRegistry {
+	handles: BTreeMap<ProviderId, ProviderHandle>,
+}
+impl ProviderRegistry {
+	/// Creates an empty registry.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Adds `handle`, chaining like a builder.
+	pub fn with_provider(mut self, handle: ProviderHandle) -> Result<Self, ConfigError> {
+		self.register(handle)?;
+
+		Ok(self)
+	}
+
+	/// Adds `handle` after validating its descriptor; each provider may be registered once.
+	pub fn register(&mut self, handle: ProviderHandle) -> Result<(), ConfigError> {
+		handle.descriptor.validate()?;
+
+		let id = handle.descriptor.id.clone();
+
+		if self.handles.contains_key(&id) {
+			return Err(ConfigError::DuplicateProvider { provider: id.to_string() });
+		}
+
+		self.handles.insert(id, handle);
+
+		Ok(())
+	}
+
+	/// Returns the registration for `provider`, if any.
+	pub fn get(&self, provider: &ProviderId) -> Option<&ProviderHandle> {
+		self.handles.get(provider)
+	}
+
+	/// Iterator over the registered provider identifiers, in sorted order.
+	pub fn providers(&self) -> impl Iterator<Item = &ProviderId> {
+		self.handles.keys()
+	}
+
+	pub(crate) fn into_handles(self) -> impl Iterator<Item = ProviderHandle> {
+		self.handles.into_values()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	// self
+	use super::*;
+	use crate::provider::{DefaultProviderStrategy, GrantType, ProviderDescriptor};
+
+	fn handle(id: &str) -> ProviderHandle {
+		let descriptor = ProviderDescriptor::builder(
+			ProviderId::new(id).expect("Provider fixture should be valid."),
+		)
+		.authorization_endpoint(
+			Url::parse(&format!("https://{id}.example.com/authorize"))
+				.expect("Authorization endpoint fixture should parse."),
+		)
+		.token_endpoint(
+			Url::parse(&format!("https://{id}.example.com/token"))
+				.expect("Token endpoint fixture should parse."),
+		)
+		.support_grants([GrantType::ClientCredentials])
+		.build()
+		.expect("Descriptor fixture should build.");
+
+		ProviderHandle::new(descriptor, Arc::new(DefaultProviderStrategy), format!("{id}-client"))
+	}
+
+	#[test]
+	fn registry_keeps_one_handle_per_provider() {
+		let mut registry = ProviderRegistry::new()
+			.with_provider(handle("beta"))
+			.and_then(|registry| registry.with_provider(handle("alpha")))
+			.expect("Distinct providers should register.");
+
+		assert_eq!(
+			registry.providers().map(|id| id.to_string()).collect::<Vec<_>>(),
+			["alpha", "beta"]
+		);
+		assert!(matches!(
+			registry.register(handle("alpha")),
+			Err(ConfigError::DuplicateProvider { .. })
+		));
+
+		let alpha = ProviderId::new("alpha").expect("Provider fixture should be valid.");
+
+		assert_eq!(
+			registry.get(&alpha).map(|handle| handle.client_id.as_str()),
+			Some("alpha-client")
+		);
+	}
+}
@@ -6,14 +6,18 @@
 // std
 use std::collections::BTreeMap;
 // self
-use crate::{_prelude::*, provider::descriptor::GrantType};
+use crate::{
+	_prelude::*,
+	auth::{TokenFamily, TokenRecord},
+	provider::descriptor::{GrantType, MaintenanceWindow},
+};
 
 /// Strategy hook that allows providers to decorate requests and classify errors.
 ///
 /// Implementors are required to be `Send + Sync`, and the hooks intentionally use
 /// crate-owned data types so downstream crates never depend on reqwest-specific
-/// structures.  Override only what you need—`augment_token_request` has a default
-/// no-op implementation.
+/// structures.  Override only what you need—`augment_token_request` and
+/// `augment_token_request_for` have default no-op implementations.
 pub trait ProviderStrategy: Send + Sync {
 	/// Maps low-level HTTP/JSON errors into the broker taxonomy for a token request.
 	fn classify_token_error(&self, ctx: &ProviderErrorContext) -> ProviderErrorKind;
@@ -25,6 +29,59 @@ pub trait ProviderStrategy: Send + Sync {
 	/// etc.).  The method works on a plain `BTreeMap` so implementations remain HTTP
 	/// client agnostic.
 	fn augment_token_request(&self, _grant: GrantType, _form: &mut BTreeMap<String, String>) {}
+
+	/// Identity-aware variant of [`augment_token_request`](Self::augment_token_request).
+	///
+	/// Receives the tenant, principal, and provider the token is minted for, so strategies can
+	/// inject tenant-specific `audience`/`resource` values. An `audience` added here also
+	/// partitions the cache, since it becomes part of the record's [`TokenFamily`]. The default
+	/// delegates to `augment_token_request`; flows only call this variant.
+	fn augment_token_request_for(
+		&self,
+		grant: GrantType,
+		_family: &TokenFamily,
+		form: &mut BTreeMap<String, String>,
+	) {
+		self.augment_token_request(grant, form);
+	}
+
+	/// Inspects the fully assembled token request form right before it is sent.
+	///
+	/// Runs after [`augment_token_request_for`](Self::augment_token_request_for), caller extras,
+	/// and body client authentication (`client_id`/`client_secret` for `client_secret_post`), so
+	/// it sees every parameter in wire order and can rewrite, reorder, or duplicate them for
+	/// providers with form quirks. Returning an error vetoes the request, which fails with
+	/// [`ConfigError::TokenRequestVetoed`](crate::error::ConfigError::TokenRequestVetoed). The
+	/// form holds client secrets, so never log it verbatim. The default sends it unchanged.
+	fn finalize_token_request(
+		&self,
+		_grant: GrantType,
+		_form: &mut Vec<(String, String)>,
+	) -> Result<(), String> {
+		Ok(())
+	}
+
+	/// Annotates a freshly minted record through its [`TokenRecord::metadata`] map.
+	///
+	/// Runs after the broker has parsed the token response, so `record` already carries the
+	/// captured response extras. Use it to index records by provider-side identifiers (account,
+	/// installation, workspace) without extending the record schema. The default adds nothing.
+	fn annotate_record(
+		&self,
+		_grant: GrantType,
+		_record: &TokenRecord,
+		_metadata: &mut BTreeMap<String, String>,
+	) {
+	}
+
+	/// Reports a maintenance window active at `now` that the descriptor does not declare.
+	///
+	/// Override when the provider publishes its schedule dynamically (status page, discovery
+	/// metadata). The default defers to
+	/// [`ProviderDescriptor::maintenance_windows`](crate::provider::ProviderDescriptor::maintenance_windows).
+	fn maintenance_window(&self, _now: OffsetDateTime) -> Option<MaintenanceWindow> {
+		None
+	}
 }
 
 /// Canonical provider error categories used by strategies.
@@ -60,6 +117,8 @@ pub struct ProviderErrorContext {
 	pub body_preview: Option<String>,
 	/// Indicates whether the failure originated from the network/transport layer.
 	pub network_error: bool,
+	/// Response headers captured by the transport, keyed by lowercase name.
+	pub headers: BTreeMap<String, String>,
 }
 impl ProviderErrorContext {
 	const BODY_PREVIEW_LIMIT: usize = 256;
@@ -73,6 +132,7 @@ impl ProviderErrorContext {
 			error_description: None,
 			body_preview: None,
 			network_error: false,
+			headers: BTreeMap::new(),
 		}
 	}
 
@@ -113,6 +173,18 @@ impl ProviderErrorContext {
 		self
 	}
 
+	/// Adds a response header; names are matched case-insensitively.
+	pub fn with_header(mut self, name: impl AsRef<str>, value: impl Into<String>) -> Self {
+		self.headers.insert(name.as_ref().to_ascii_lowercase(), value.into());
+
+		self
+	}
+
+	/// Returns the captured value of the `name` response header.
+	pub fn header(&self, name: &str) -> Option<&str> {
+		self.headers.get(&name.to_ascii_lowercase()).map(String::as_str)
+	}
+
 	/// Adds a body preview for providers that return non-JSON payloads.
 	pub fn with_body_preview(mut self, body: impl Into<String>) -> Self {
 		self.body_preview = Some(truncate_preview(body.into()));
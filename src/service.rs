@@ -0,0 +1,251 @@
+//! Embeddable HTTP façade that exposes broker flows to non-Rust services.
+//!
+//! Enabled by the `service` feature. [`BrokerService::handle`] maps one `http::Request` onto a
+//! broker flow and returns the `http::Response` to send, so it mounts into any server (hyper,
+//! axum, a serverless handler) without the crate choosing one. Callers authenticate with a bearer
+//! API key that [`ServiceAuth`] maps to exactly one tenant, so a service can never read or revoke
+//! another tenant's tokens. Refresh tokens never leave the broker.
+//!
+//! | Route               | Flow                                                    |
+//! | ------------------- | ------------------------------------------------------- |
+//! | `POST /v1/token`    | [`Broker::client_credentials`]                          |
+//! | `POST /v1/refresh`  | [`Broker::refresh_access_token`]                        |
+//! | `POST /v1/revoke`   | [`Broker::revoke`]                                      |
+//!
+//! Every route takes a JSON body `{"principal": "...", "scope": ["..."]}` with optional
+//! `audience` and `force` members (`reason` for revocation). Failures are RFC 9457 problem
+//! bodies built by [`Error::to_http_problem`].
+
+// crates.io
+use oauth2::http::{
+	HeaderValue, Method, Request, Response, StatusCode,
+	header::{ALLOW, AUTHORIZATION, CONTENT_TYPE, RETRY_AFTER, WWW_AUTHENTICATE},
+};
+use sha2::{Digest, Sha256};
+// self
+use crate::{
+	_prelude::*,
+	auth::{PrincipalId, RevocationReason, ScopeSet, TenantId, TokenRecord},
+	error::{HttpProblem, PROBLEM_JSON_CONTENT_TYPE, ProblemDetails},
+	flows::{Broker, CachedTokenRequest},
+	http::TokenHttpClient,
+	oauth::TransportErrorMapper,
+};
+
+/// Bearer API keys accepted by a [`BrokerService`], each bound to one tenant.
+///
+/// Only SHA-256 digests of the keys are kept in memory.
+#[derive(Clone, Debug, Default)]
+pub struct ServiceAuth {
+	keys: HashMap<[u8; 32], TenantId>,
+}
+impl ServiceAuth {
+	/// Creates an empty key set that rejects every caller.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Accepts `api_key` as a bearer credential for `tenant`.
+	pub fn with_api_key(mut self, api_key: impl AsRef<[u8]>, tenant: TenantId) -> Self {
+		self.keys.insert(Sha256::digest(api_key).into(), tenant);
+
+		self
+	}
+
+	/// Resolves the tenant for an `Authorization` header value.
+	pub fn tenant_for(&self, authorization: &str) -> Option<&TenantId> {
+		let (scheme, key) = authorization.split_once(' ')?;
+
+		if !scheme.eq_ignore_ascii_case("bearer") {
+			return None;
+		}
+
+		let digest: [u8; 32] = Sha256::digest(key.trim()).into();
+
+		self.keys.get(&digest)
+	}
+}
+
+/// HTTP façade over a [`Broker`]; see the [module docs](self) for routes.
+pub struct BrokerService<C, M>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	broker: Broker<C, M>,
+	auth: ServiceAuth,
+}
+impl<C, M> BrokerService<C, M>
+where
+	C: ?Sized + TokenHttpClient,
+	M: ?Sized + TransportErrorMapper<C::TransportError>,
+{
+	/// Serves `broker` to callers holding a key from `auth`.
+	pub fn new(broker: Broker<C, M>, auth: ServiceAuth) -> Self {
+		Self { broker, auth }
+	}
+
+	/// Broker the service delegates to.
+	pub fn broker(&self) -> &Broker<C, M> {
+		&self.broker
+	}
+
+	/// Handles one request and returns the response to send.
+	pub async fn handle(&self, request: Request<Vec<u8>>) -> Response<Vec<u8>> {
+		let route = match request.uri().path() {
+			"/v1/token" => Route::Token,
+			"/v1/refresh" => Route::Refresh,
+			"/v1/revoke" => Route::Revoke,
+			_ => return local_problem(StatusCode::NOT_FOUND, "not-found", "Route not found", None),
+		};
+
+		if request.method() != Method::POST {
+			let mut response = local_problem(
+				StatusCode::METHOD_NOT_ALLOWED,
+				"method-not-allowed",
+				"Only POST is supported",
+				None,
+			);
+
+			response.headers_mut().insert(ALLOW, HeaderValue::from_static("POST"));
+
+			return response;
+		}
+
+		let Some(tenant) = request
+			.headers()
+			.get(AUTHORIZATION)
+			.and_then(|value| value.to_str().ok())
+			.and_then(|value| self.auth.tenant_for(value))
+			.cloned()
+		else {
+			let mut response = local_problem(
+				StatusCode::UNAUTHORIZED,
+				"unauthorized",
+				"A valid bearer API key is required",
+				None,
+			);
+
+			response.headers_mut().insert(WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+
+			return response;
+		};
+		let body = match serde_json::from_slice::<ServiceRequest>(request.body()) {
+			Ok(body) => body,
+			Err(e) => return bad_request(e.to_string()),
+		};
+		let reason = body.reason.unwrap_or(RevocationReason::UserRequested);
+		let request = match body.into_request(tenant) {
+			Ok(request) => request,
+			Err(detail) => return bad_request(detail),
+		};
+		let result = match route {
+			Route::Token => self.broker.client_credentials(request).await.map(token_body),
+			Route::Refresh => self.broker.refresh_access_token(request).await.map(token_body),
+			Route::Revoke => self
+				.broker
+				.revoke(request, reason)
+				.await
+				.map(|revoked| serde_json::json!({ "revoked": revoked.is_some() })),
+		};
+
+		match result {
+			Ok(body) => json_response(StatusCode::OK, "application/json", body.to_string()),
+			Err(e) => problem_response(e.to_http_problem()),
+		}
+	}
+}
+
+enum Route {
+	Token,
+	Refresh,
+	Revoke,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ServiceRequest {
+	principal: String,
+	scope: Vec<String>,
+	#[serde(default)]
+	audience: Option<String>,
+	#[serde(default)]
+	force: Option<bool>,
+	#[serde(default)]
+	reason: Option<RevocationReason>,
+}
+impl ServiceRequest {
+	fn into_request(self, tenant: TenantId) -> Result<CachedTokenRequest, String> {
+		let principal = PrincipalId::new(&self.principal).map_err(|e| e.to_string())?;
+		let scope = ScopeSet::new(self.scope).map_err(|e| e.to_string())?;
+		let mut request = CachedTokenRequest::new(tenant, principal, scope);
+
+		if let Some(force) = self.force {
+			request = request.with_force(force);
+		}
+
+		if let Some(audience) = self.audience {
+			request = request.with_extra_param("audience", audience);
+		}
+
+		Ok(request)
+	}
+}
+
+fn token_body(record: TokenRecord) -> serde_json::Value {
+	let expires_in = (record.expires_at - OffsetDateTime::now_utc()).whole_seconds().max(0);
+
+	serde_json::json!({
+		"access_token": record.access_token.expose(),
+		"token_type": "Bearer",
+		"expires_in": expires_in,
+		"expires_at": record.expires_at.unix_timestamp(),
+		"scope": record.scope.normalized(),
+	})
+}
+
+fn bad_request(detail: String) -> Response<Vec<u8>> {
+	local_problem(StatusCode::BAD_REQUEST, "bad-request", "Request body is invalid", Some(detail))
+}
+
+fn local_problem(
+	status: StatusCode,
+	slug: &str,
+	title: &str,
+	detail: Option<String>,
+) -> Response<Vec<u8>> {
+	problem_response(HttpProblem {
+		status: status.as_u16(),
+		retry_after_secs: None,
+		body: ProblemDetails {
+			type_uri: format!("urn:oauth2-broker:problem:{slug}"),
+			title: title.into(),
+			status: status.as_u16(),
+			detail,
+		},
+	})
+}
+
+fn problem_response(problem: HttpProblem) -> Response<Vec<u8>> {
+	let status = StatusCode::from_u16(problem.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+	let mut response = json_response(status, PROBLEM_JSON_CONTENT_TYPE, problem.body.to_json());
+
+	if let Some(secs) = problem.retry_after_secs {
+		response.headers_mut().insert(RETRY_AFTER, secs.into());
+	}
+
+	response
+}
+
+fn json_response(
+	status: StatusCode,
+	content_type: &'static str,
+	body: String,
+) -> Response<Vec<u8>> {
+	let mut response = Response::new(body.into_bytes());
+
+	*response.status_mut() = status;
+	response.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+
+	response
+}
@@ -1,15 +1,40 @@
 //! Storage contracts and built-in store implementations for broker token records.
 
+pub mod cached;
+#[cfg(feature = "ring")] pub mod encrypted;
+#[cfg(feature = "etcd")] pub mod etcd;
 pub mod file;
 pub mod memory;
+pub mod migration;
+#[cfg(feature = "redis")] pub mod redis;
+pub mod shard;
+#[cfg(feature = "sled")] pub mod sled;
+#[cfg(feature = "sqlx")] pub mod sql;
+pub mod transaction;
 
+#[cfg(feature = "redis")] pub use self::redis::RedisStore;
+#[cfg(feature = "sled")] pub use self::sled::SledStore;
+pub use cached::CachedStore;
+#[cfg(feature = "ring")]
+pub use encrypted::{Cipher, DataKey, EncryptedStore, KeyProvider, MasterKey, TenantKeys};
+#[cfg(feature = "etcd")] pub use etcd::EtcdStore;
 pub use file::FileStore;
+#[cfg(feature = "ring")] pub use file::SnapshotKey;
 pub use memory::MemoryStore;
+pub use migration::{ConflictPolicy, MigrateOptions, MigrationConflict, MigrationReport, migrate};
+pub use shard::Sharded;
+#[cfg(feature = "sqlx")] pub use sql::{SqlDialect, SqlExecutor, SqlRow, SqlStore, SqlValue};
+pub use transaction::Transaction;
 
+// crates.io
+use sha2::{Digest, Sha256};
 // self
 use crate::{
 	_prelude::*,
-	auth::{ScopeSet, TokenFamily, TokenRecord},
+	auth::{
+		FingerprintScheme, PrincipalPath, ProviderId, RevocationReason, ScopeSet, TenantId,
+		TokenFamily, TokenRecord,
+	},
 };
 
 /// Persistence contract for broker-issued tokens.
@@ -31,6 +56,9 @@ where
 	) -> StoreFuture<'a, Option<TokenRecord>>;
 
 	/// Atomically rotates a refresh token if the expected secret matches.
+	///
+	/// The replacement is stored with the stored record's [`version`](TokenRecord::version) plus
+	/// one.
 	fn compare_and_swap_refresh<'a>(
 		&'a self,
 		family: &'a TokenFamily,
@@ -39,13 +67,413 @@ where
 		replacement: TokenRecord,
 	) -> StoreFuture<'a, CompareAndSwapOutcome>;
 
-	/// Marks a record as revoked at the provided instant.
+	/// Atomically replaces a record if its [`version`](TokenRecord::version) still equals
+	/// `expected_version`.
+	///
+	/// The replacement is stored with version `expected_version + 1`, whatever it carried. Unlike
+	/// [`compare_and_swap_refresh`](Self::compare_and_swap_refresh) this also guards records
+	/// without a refresh token (client credentials), whose access token is their only secret.
+	/// Backends that cannot compare and write atomically keep the default, which reports
+	/// [`StoreError::Unsupported`].
+	fn compare_and_swap_version<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+		expected_version: u64,
+		replacement: TokenRecord,
+	) -> StoreFuture<'a, CompareAndSwapOutcome> {
+		let _ = (family, scope, expected_version, replacement);
+
+		Box::pin(async {
+			Err(StoreError::Unsupported { operation: "compare_and_swap_version".into() })
+		})
+	}
+
+	/// Fetches the record only when its fingerprint differs from `known_fingerprint`.
+	///
+	/// Fingerprints are the [`record_fingerprint`] of the stored record, so they work as etags
+	/// across processes. They track content, unlike the [`TokenRecord::version`] counter that
+	/// [`compare_and_swap_version`](Self::compare_and_swap_version) checks. The default fetches
+	/// and fingerprints the record; backends that can compare fingerprints without transferring or
+	/// decoding the record override it.
+	fn fetch_if_changed<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+		known_fingerprint: Option<&'a str>,
+	) -> StoreFuture<'a, ConditionalFetch> {
+		Box::pin(async move {
+			match self.fetch(family, scope).await? {
+				Some(record) => ConditionalFetch::compare(record, known_fingerprint),
+				None => Ok(ConditionalFetch::Missing),
+			}
+		})
+	}
+
+	/// Marks a record as revoked at the provided instant for `reason`.
 	fn revoke<'a>(
 		&'a self,
 		family: &'a TokenFamily,
 		scope: &'a ScopeSet,
 		instant: OffsetDateTime,
+		reason: RevocationReason,
 	) -> StoreFuture<'a, Option<TokenRecord>>;
+
+	/// Stages a record without making it visible to [`fetch`](Self::fetch) (phase one of a
+	/// two-phase write).
+	///
+	/// Durable backends persist the staged record so a failure between provider success and
+	/// [`commit`](Self::commit) leaves it recoverable through
+	/// [`pending_writes`](Self::pending_writes). The default stages nothing and leaves all work to
+	/// `commit`.
+	fn prepare(&self, record: TokenRecord) -> StoreFuture<'_, PreparedWrite> {
+		Box::pin(async move { Ok(PreparedWrite::new(record)) })
+	}
+
+	/// Publishes a staged record (phase two). The default delegates to [`save`](Self::save).
+	///
+	/// Committing the same write twice must be harmless so callers can retry.
+	fn commit<'a>(&'a self, prepared: &'a PreparedWrite) -> StoreFuture<'a, ()> {
+		self.save(prepared.record.clone())
+	}
+
+	/// Discards a staged record without publishing it.
+	fn rollback<'a>(&'a self, prepared: &'a PreparedWrite) -> StoreFuture<'a, ()> {
+		let _ = prepared;
+
+		Box::pin(async { Ok(()) })
+	}
+
+	/// Lists staged writes that were neither committed nor rolled back.
+	fn pending_writes(&self) -> StoreFuture<'_, Vec<PreparedWrite>> {
+		Box::pin(async { Ok(Vec::new()) })
+	}
+
+	/// Reports whether the backend can currently serve reads and writes.
+	///
+	/// Backends override this with a cheap liveness probe (a ping, a write probe); the default
+	/// reports healthy.
+	fn health_check(&self) -> StoreFuture<'_, ()> {
+		Box::pin(async { Ok(()) })
+	}
+
+	/// Deletes records revoked before `cutoff`, returning how many were purged.
+	///
+	/// Revoked records are otherwise retained for audit; see
+	/// [`Broker::with_revoked_retention`](crate::flows::Broker::with_revoked_retention).
+	fn purge_revoked(&self, cutoff: OffsetDateTime) -> StoreFuture<'_, usize> {
+		let _ = cutoff;
+
+		Box::pin(async { Err(StoreError::Unsupported { operation: "purge_revoked".into() }) })
+	}
+
+	/// Lists every record for `tenant` whose principal is `prefix` or lives below it.
+	///
+	/// Backends that cannot enumerate keys keep the default, which reports
+	/// [`StoreError::Unsupported`].
+	fn list_principal_subtree<'a>(
+		&'a self,
+		tenant: &'a TenantId,
+		prefix: &'a PrincipalPath,
+	) -> StoreFuture<'a, Vec<TokenRecord>> {
+		let _ = (tenant, prefix);
+
+		Box::pin(async {
+			Err(StoreError::Unsupported { operation: "list_principal_subtree".into() })
+		})
+	}
+
+	/// Revokes every record for `tenant` whose principal is `prefix` or lives below it, returning
+	/// the revoked records.
+	fn revoke_principal_subtree<'a>(
+		&'a self,
+		tenant: &'a TenantId,
+		prefix: &'a PrincipalPath,
+		instant: OffsetDateTime,
+		reason: RevocationReason,
+	) -> StoreFuture<'a, Vec<TokenRecord>> {
+		let _ = (tenant, prefix, instant, reason);
+
+		Box::pin(async {
+			Err(StoreError::Unsupported { operation: "revoke_principal_subtree".into() })
+		})
+	}
+
+	/// Revokes every record of `family`, whatever its scope, returning the newly revoked records.
+	///
+	/// Records that are already revoked keep their original instant and reason and are not
+	/// returned. The default pages through [`list_records`](Self::list_records) and revokes each
+	/// match, so backends that cannot enumerate keys report [`StoreError::Unsupported`]; backends
+	/// that can revoke a family in one pass override it.
+	fn revoke_all<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		instant: OffsetDateTime,
+		reason: RevocationReason,
+	) -> StoreFuture<'a, Vec<TokenRecord>> {
+		Box::pin(async move {
+			const PAGE: usize = 200;
+
+			let mut scopes = Vec::new();
+			let mut after = None;
+
+			loop {
+				let page = self.list_records(after.as_deref(), PAGE).await?;
+
+				scopes.extend(
+					page.records
+						.into_iter()
+						.filter(|record| record.family == *family && !record.is_revoked())
+						.map(|record| record.scope),
+				);
+
+				match page.next {
+					Some(next) => after = Some(next),
+					None => break,
+				}
+			}
+
+			let mut revoked = Vec::new();
+
+			for scope in &scopes {
+				revoked.extend(self.revoke(family, scope, instant, reason).await?);
+			}
+
+			Ok(revoked)
+		})
+	}
+
+	/// Lists up to `limit` records ordered by [`StoreKey::page_cursor`], starting after `after`.
+	///
+	/// Pass the previous page's [`RecordPage::next`] to continue; `None` there means the listing
+	/// is complete. Backends that cannot enumerate keys keep the default, which reports
+	/// [`StoreError::Unsupported`].
+	fn list_records<'a>(
+		&'a self,
+		after: Option<&'a str>,
+		limit: usize,
+	) -> StoreFuture<'a, RecordPage> {
+		let _ = (after, limit);
+
+		Box::pin(async { Err(StoreError::Unsupported { operation: "list_records".into() }) })
+	}
+
+	/// Lists every record of `tenant` across all providers, or only those of `provider`, ordered by
+	/// [`StoreKey::page_cursor`].
+	///
+	/// The default pages through [`list_records`](Self::list_records), so backends that cannot
+	/// enumerate keys report [`StoreError::Unsupported`]; backends that can filter by tenant in
+	/// one pass override it.
+	fn list_tenant_records<'a>(
+		&'a self,
+		tenant: &'a TenantId,
+		provider: Option<&'a ProviderId>,
+	) -> StoreFuture<'a, Vec<TokenRecord>> {
+		Box::pin(async move {
+			const PAGE: usize = 200;
+
+			let mut records = Vec::new();
+			let mut after = None;
+
+			loop {
+				let page = self.list_records(after.as_deref(), PAGE).await?;
+
+				records.extend(page.records.into_iter().filter(|record| {
+					StoreKey::new(&record.family, &record.scope)
+						.is_ok_and(|key| key.in_namespace(tenant, provider))
+				}));
+
+				match page.next {
+					Some(next) => after = Some(next),
+					None => break,
+				}
+			}
+
+			Ok(records)
+		})
+	}
+
+	/// Deletes every record and staged write for `tenant`, returning the deleted records.
+	///
+	/// Backends that cannot enumerate keys keep the default, which reports
+	/// [`StoreError::Unsupported`].
+	fn delete_tenant<'a>(&'a self, tenant: &'a TenantId) -> StoreFuture<'a, Vec<TokenRecord>> {
+		let _ = tenant;
+
+		Box::pin(async { Err(StoreError::Unsupported { operation: "delete_tenant".into() }) })
+	}
+
+	/// Destroys any key material held for `tenant`, returning `true` when some existed.
+	///
+	/// Only encrypting backends hold per-tenant keys; the default reports `false`.
+	fn destroy_tenant_keys<'a>(&'a self, tenant: &'a TenantId) -> StoreFuture<'a, bool> {
+		let _ = tenant;
+
+		Box::pin(async { Ok(false) })
+	}
+
+	/// Atomically replaces several scope records of `family` that share one refresh token.
+	///
+	/// Every record in `records` must belong to `family`, and the stored record for each scope
+	/// must exist and hold `expected_refresh`; otherwise nothing is written and the first
+	/// failing check is reported. Providers that reuse one refresh token across scopes rely on
+	/// this so a rotation never leaves some scope records holding the spent secret.
+	///
+	/// Backends that cannot update several keys at once keep the default, which reports
+	/// [`StoreError::Unsupported`].
+	fn compare_and_swap_family<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		expected_refresh: Option<&'a str>,
+		records: Vec<TokenRecord>,
+	) -> StoreFuture<'a, CompareAndSwapOutcome> {
+		let _ = (family, expected_refresh, records);
+
+		Box::pin(async {
+			Err(StoreError::Unsupported { operation: "compare_and_swap_family".into() })
+		})
+	}
+}
+/// Shared handles forward to the store they point at, so wrappers such as
+/// [`CachedStore`] can sit on top of an `Arc<dyn BrokerStore>`.
+impl<S> BrokerStore for Arc<S>
+where
+	S: ?Sized + BrokerStore,
+{
+	fn save(&self, record: TokenRecord) -> StoreFuture<'_, ()> {
+		(**self).save(record)
+	}
+
+	fn fetch<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+	) -> StoreFuture<'a, Option<TokenRecord>> {
+		(**self).fetch(family, scope)
+	}
+
+	fn compare_and_swap_refresh<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+		expected_refresh: Option<&'a str>,
+		replacement: TokenRecord,
+	) -> StoreFuture<'a, CompareAndSwapOutcome> {
+		(**self).compare_and_swap_refresh(family, scope, expected_refresh, replacement)
+	}
+
+	fn compare_and_swap_version<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+		expected_version: u64,
+		replacement: TokenRecord,
+	) -> StoreFuture<'a, CompareAndSwapOutcome> {
+		(**self).compare_and_swap_version(family, scope, expected_version, replacement)
+	}
+
+	fn fetch_if_changed<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+		known_fingerprint: Option<&'a str>,
+	) -> StoreFuture<'a, ConditionalFetch> {
+		(**self).fetch_if_changed(family, scope, known_fingerprint)
+	}
+
+	fn revoke<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+		instant: OffsetDateTime,
+		reason: RevocationReason,
+	) -> StoreFuture<'a, Option<TokenRecord>> {
+		(**self).revoke(family, scope, instant, reason)
+	}
+
+	fn prepare(&self, record: TokenRecord) -> StoreFuture<'_, PreparedWrite> {
+		(**self).prepare(record)
+	}
+
+	fn commit<'a>(&'a self, prepared: &'a PreparedWrite) -> StoreFuture<'a, ()> {
+		(**self).commit(prepared)
+	}
+
+	fn rollback<'a>(&'a self, prepared: &'a PreparedWrite) -> StoreFuture<'a, ()> {
+		(**self).rollback(prepared)
+	}
+
+	fn pending_writes(&self) -> StoreFuture<'_, Vec<PreparedWrite>> {
+		(**self).pending_writes()
+	}
+
+	fn health_check(&self) -> StoreFuture<'_, ()> {
+		(**self).health_check()
+	}
+
+	fn purge_revoked(&self, cutoff: OffsetDateTime) -> StoreFuture<'_, usize> {
+		(**self).purge_revoked(cutoff)
+	}
+
+	fn list_principal_subtree<'a>(
+		&'a self,
+		tenant: &'a TenantId,
+		prefix: &'a PrincipalPath,
+	) -> StoreFuture<'a, Vec<TokenRecord>> {
+		(**self).list_principal_subtree(tenant, prefix)
+	}
+
+	fn revoke_principal_subtree<'a>(
+		&'a self,
+		tenant: &'a TenantId,
+		prefix: &'a PrincipalPath,
+		instant: OffsetDateTime,
+		reason: RevocationReason,
+	) -> StoreFuture<'a, Vec<TokenRecord>> {
+		(**self).revoke_principal_subtree(tenant, prefix, instant, reason)
+	}
+
+	fn revoke_all<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		instant: OffsetDateTime,
+		reason: RevocationReason,
+	) -> StoreFuture<'a, Vec<TokenRecord>> {
+		(**self).revoke_all(family, instant, reason)
+	}
+
+	fn list_records<'a>(
+		&'a self,
+		after: Option<&'a str>,
+		limit: usize,
+	) -> StoreFuture<'a, RecordPage> {
+		(**self).list_records(after, limit)
+	}
+
+	fn list_tenant_records<'a>(
+		&'a self,
+		tenant: &'a TenantId,
+		provider: Option<&'a ProviderId>,
+	) -> StoreFuture<'a, Vec<TokenRecord>> {
+		(**self).list_tenant_records(tenant, provider)
+	}
+
+	fn delete_tenant<'a>(&'a self, tenant: &'a TenantId) -> StoreFuture<'a, Vec<TokenRecord>> {
+		(**self).delete_tenant(tenant)
+	}
+
+	fn destroy_tenant_keys<'a>(&'a self, tenant: &'a TenantId) -> StoreFuture<'a, bool> {
+		(**self).destroy_tenant_keys(tenant)
+	}
+
+	fn compare_and_swap_family<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		expected_refresh: Option<&'a str>,
+		records: Vec<TokenRecord>,
+	) -> StoreFuture<'a, CompareAndSwapOutcome> {
+		(**self).compare_and_swap_family(family, expected_refresh, records)
+	}
 }
 
 /// Result of a refresh-token compare-and-swap attempt.
@@ -55,10 +483,107 @@ pub enum CompareAndSwapOutcome {
 	Updated,
 	/// The record exists but the expected refresh secret did not match.
 	RefreshMismatch,
+	/// The record exists but its version differs from the expected one.
+	VersionMismatch,
 	/// No record matched the provided family + scope.
 	Missing,
 }
 
+/// Version a conditional write stores over `current`.
+pub(crate) fn next_version(current: &TokenRecord) -> u64 {
+	current.version.saturating_add(1)
+}
+
+/// Revokes a stored record in place, advancing its version like any other conditional write.
+pub(crate) fn revoke_stored(
+	record: &mut TokenRecord,
+	instant: OffsetDateTime,
+	reason: RevocationReason,
+) {
+	record.revoke(instant, reason);
+	record.version = next_version(record);
+}
+
+/// Joins the key components into the cursor ordering [`BrokerStore::list_records`].
+pub(crate) fn page_cursor(family: &TokenFamily, scope_fingerprint: &str) -> String {
+	[
+		family.tenant.as_ref(),
+		family.principal.as_ref(),
+		family.provider.as_ref().map_or("", |provider| provider.as_ref()),
+		family.audience.as_deref().unwrap_or_default(),
+		family.grant.as_deref().unwrap_or_default(),
+		scope_fingerprint,
+	]
+	.join("\u{1f}")
+}
+
+/// Keys the replacements passed to [`BrokerStore::compare_and_swap_family`], rejecting records
+/// from another family.
+pub(crate) fn family_replacements(
+	family: &TokenFamily,
+	records: Vec<TokenRecord>,
+) -> Result<Vec<(StoreKey, TokenRecord)>, StoreError> {
+	records
+		.into_iter()
+		.map(|record| {
+			if record.family != *family {
+				return Err(StoreError::Backend {
+					message: "Family swap received a record from another token family".into(),
+				});
+			}
+
+			Ok((StoreKey::new(&record.family, &record.scope)?, record))
+		})
+		.collect()
+}
+
+/// Result of [`BrokerStore::fetch_if_changed`].
+#[derive(Clone, Debug)]
+pub enum ConditionalFetch {
+	/// The stored record still matches the caller's fingerprint.
+	Unchanged,
+	/// The record differs from the caller's fingerprint (or the caller had none).
+	Changed {
+		/// Current record.
+		record: Box<TokenRecord>,
+		/// Fingerprint to pass on the next conditional fetch.
+		fingerprint: String,
+	},
+	/// No record exists for the key.
+	Missing,
+}
+impl ConditionalFetch {
+	/// Classifies `record` against `known_fingerprint`.
+	pub fn compare(
+		record: TokenRecord,
+		known_fingerprint: Option<&str>,
+	) -> Result<Self, StoreError> {
+		let fingerprint = record_fingerprint(&record)?;
+
+		if known_fingerprint == Some(fingerprint.as_str()) {
+			Ok(Self::Unchanged)
+		} else {
+			Ok(Self::Changed { record: Box::new(record), fingerprint })
+		}
+	}
+}
+
+/// SHA-256 over the serialized record, hex encoded; equal records yield equal fingerprints.
+///
+/// [`TokenRecord::metadata`] is left out, so annotating a record does not make it differ.
+pub fn record_fingerprint(record: &TokenRecord) -> Result<String, StoreError> {
+	let serialization = |e: serde_json::Error| StoreError::Serialization { message: e.to_string() };
+	let mut value = serde_json::to_value(record).map_err(serialization)?;
+
+	if let Some(fields) = value.as_object_mut() {
+		fields.remove("metadata");
+	}
+
+	let bytes = serde_json::to_vec(&value).map_err(serialization)?;
+
+	Ok(Sha256::digest(bytes).iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
 /// Error type produced by [`BrokerStore`] implementations.
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ThisError)]
 pub enum StoreError {
@@ -74,32 +599,175 @@ pub enum StoreError {
 		/// Human-readable error payload.
 		message: String,
 	},
+	/// A store key was requested for a token family without a provider.
+	#[error("Token family has no provider to namespace its store key.")]
+	MissingProvider,
+	/// The backend does not implement an optional operation.
+	#[error("Store does not support {operation}.")]
+	Unsupported {
+		/// Name of the unsupported [`BrokerStore`] method.
+		operation: String,
+	},
+}
+
+/// Record staged by [`BrokerStore::prepare`] and awaiting [`BrokerStore::commit`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PreparedWrite {
+	/// Identifier that is unique among the backend's staged writes.
+	pub id: String,
+	/// Record that becomes visible once committed.
+	pub record: TokenRecord,
+}
+impl PreparedWrite {
+	/// Stages `record` under a fresh random identifier.
+	pub fn new(record: TokenRecord) -> Self {
+		Self { id: format!("{:032x}", rand::random::<u128>()), record }
+	}
+}
+
+/// One page of records returned by [`BrokerStore::list_records`].
+#[derive(Clone, Debug, Default)]
+pub struct RecordPage {
+	/// Records on this page, ordered by key cursor.
+	pub records: Vec<TokenRecord>,
+	/// Cursor to pass as `after` for the next page, or `None` on the last page.
+	pub next: Option<String>,
+}
+impl RecordPage {
+	/// Builds a page from `(cursor, record)` entries that lie after the requested cursor.
+	pub(crate) fn collect(
+		entries: impl IntoIterator<Item = (String, TokenRecord)>,
+		limit: usize,
+	) -> Self {
+		let mut entries = entries.into_iter().collect::<Vec<_>>();
+
+		entries.sort_unstable_by(|(left, _), (right, _)| left.cmp(right));
+
+		let limit = limit.max(1);
+		let next = (entries.len() > limit).then(|| entries[limit - 1].0.clone());
+
+		entries.truncate(limit);
+
+		Self { records: entries.into_iter().map(|(_, record)| record).collect(), next }
+	}
 }
 
 /// Unique key identifying a stored token record.
+///
+/// Every key lives in a provider namespace, so families that differ only by provider never
+/// share a key. Keys cannot be built for families without a provider; the provider always
+/// mirrors `family.provider`.
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "StoreKeyRepr")]
 pub struct StoreKey {
+	/// Provider namespace component.
+	pub provider: ProviderId,
 	/// Token family component.
 	pub family: TokenFamily,
 	/// Scope fingerprint used for partitioning.
 	pub scope_fingerprint: String,
 }
 impl StoreKey {
-	/// Builds a key using the provided family and scope fingerprint.
-	pub fn new(family: &TokenFamily, scope: &ScopeSet) -> Self {
-		Self { family: family.clone(), scope_fingerprint: scope.fingerprint() }
+	/// Builds a key using the provided family and scope fingerprint, namespaced by the family's
+	/// provider.
+	///
+	/// Fails with [`StoreError::MissingProvider`] when the family names no provider.
+	pub fn new(family: &TokenFamily, scope: &ScopeSet) -> Result<Self, StoreError> {
+		Self::from_parts(family.clone(), scope.fingerprint())
+	}
+
+	/// Builds a key under `provider`, which replaces the family's own provider.
+	pub fn for_provider(provider: &ProviderId, family: &TokenFamily, scope: &ScopeSet) -> Self {
+		let mut family = family.clone();
+
+		family.provider = Some(provider.clone());
+
+		Self { provider: provider.clone(), family, scope_fingerprint: scope.fingerprint() }
+	}
+
+	/// Builds a key under an explicit [`FingerprintScheme`] instead of the crate-wide one.
+	///
+	/// Fails with [`StoreError::MissingProvider`] when the family names no provider.
+	pub fn with_scheme(
+		family: &TokenFamily,
+		scope: &ScopeSet,
+		scheme: &dyn FingerprintScheme,
+	) -> Result<Self, StoreError> {
+		Self::from_parts(family.clone(), scope.fingerprint_with(scheme))
+	}
+
+	pub(crate) fn from_parts(
+		family: TokenFamily,
+		scope_fingerprint: String,
+	) -> Result<Self, StoreError> {
+		let provider = family.provider.clone().ok_or(StoreError::MissingProvider)?;
+
+		Ok(Self { provider, family, scope_fingerprint })
+	}
+
+	/// Stable, totally ordered string form used to paginate [`BrokerStore::list_records`].
+	pub fn page_cursor(&self) -> String {
+		page_cursor(&self.family, &self.scope_fingerprint)
+	}
+
+	/// Returns `true` when the key belongs to `tenant` and a principal under `prefix`.
+	pub fn in_principal_subtree(&self, tenant: &TenantId, prefix: &PrincipalPath) -> bool {
+		self.family.tenant == *tenant && prefix.contains(&self.family.principal)
+	}
+
+	/// Returns `true` when the key belongs to `tenant` and, if given, to `provider`.
+	pub fn in_namespace(&self, tenant: &TenantId, provider: Option<&ProviderId>) -> bool {
+		self.family.tenant == *tenant && provider.is_none_or(|provider| self.provider == *provider)
+	}
+}
+impl TryFrom<StoreKeyRepr> for StoreKey {
+	type Error = StoreError;
+
+	fn try_from(repr: StoreKeyRepr) -> Result<Self, Self::Error> {
+		let key = Self::from_parts(repr.family, repr.scope_fingerprint)?;
+
+		if let Some(provider) = repr.provider
+			&& provider != key.provider
+		{
+			return Err(StoreError::Serialization {
+				message: format!(
+					"Store key provider {provider} does not match its family's provider {}",
+					key.provider
+				),
+			});
+		}
+
+		Ok(key)
+	}
+}
+
+/// Serialized form of a [`StoreKey`], checked against the family on load.
+///
+/// Keys written before the provider was serialized omit it and fall back to
+/// `family.provider`; families without one cannot become a [`StoreKey`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct StoreKeyRepr {
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub(crate) provider: Option<ProviderId>,
+	pub(crate) family: TokenFamily,
+	pub(crate) scope_fingerprint: String,
+}
+impl StoreKeyRepr {
+	pub(crate) fn page_cursor(&self) -> String {
+		page_cursor(&self.family, &self.scope_fingerprint)
 	}
 }
 
 #[cfg(test)]
 mod tests {
+	// std
+	use std::error::Error as StdError;
 	// self
 	use super::*;
 	use crate::{
 		auth::{PrincipalId, ScopeSet, TenantId},
 		error::Error,
 	};
-	use std::error::Error as StdError;
 
 	#[test]
 	fn store_error_converts_into_broker_error_with_source() {
@@ -120,13 +788,15 @@ mod tests {
 		let tenant = TenantId::new("tenant-1").expect("Tenant fixture should be valid.");
 		let principal =
 			PrincipalId::new("principal-1").expect("Principal fixture should be valid.");
-		let family = TokenFamily::new(tenant, principal);
+		let family = TokenFamily::new(tenant, principal).with_provider(
+			ProviderId::new("provider-1").expect("Provider fixture should be valid."),
+		);
 		let scope_a =
 			ScopeSet::new(["profile", "email"]).expect("First scope fixture should be valid.");
 		let scope_b =
 			ScopeSet::new(["email", "profile"]).expect("Second scope fixture should be valid.");
-		let key_a = StoreKey::new(&family, &scope_a);
-		let key_b = StoreKey::new(&family, &scope_b);
+		let key_a = StoreKey::new(&family, &scope_a).expect("First store key should build.");
+		let key_b = StoreKey::new(&family, &scope_b).expect("Second store key should build.");
 
 		assert_eq!(key_a.scope_fingerprint, key_b.scope_fingerprint);
 		assert_eq!(key_a.family, key_b.family);
@@ -145,4 +815,81 @@ mod tests {
 
 		assert_eq!(round_trip, CompareAndSwapOutcome::Updated);
 	}
+
+	#[test]
+	fn store_keys_are_namespaced_by_provider() {
+		let tenant = TenantId::new("tenant-ns").expect("Tenant fixture should be valid.");
+		let family = TokenFamily::new(
+			tenant.clone(),
+			PrincipalId::new("principal-ns").expect("Principal fixture should be valid."),
+		);
+		let scope = ScopeSet::new(["read"]).expect("Scope fixture should be valid.");
+		let github = ProviderId::new("github").expect("Provider fixture should be valid.");
+		let gitlab = ProviderId::new("gitlab").expect("Provider fixture should be valid.");
+		let keyed = StoreKey::for_provider(&github, &family, &scope);
+
+		assert!(matches!(StoreKey::new(&family, &scope), Err(StoreError::MissingProvider)));
+		assert_eq!(
+			StoreKey::new(&family.clone().with_provider(github.clone()), &scope)
+				.expect("Store key should build."),
+			keyed
+		);
+		assert_ne!(keyed, StoreKey::for_provider(&gitlab, &family, &scope));
+
+		let payload = serde_json::to_value(&keyed).expect("StoreKey should serialize to JSON.");
+
+		assert_eq!(payload["provider"], "github");
+
+		let round_trip: StoreKey = serde_json::from_value(payload.clone())
+			.expect("Serialized key should deserialize from JSON.");
+
+		assert_eq!(round_trip, keyed);
+
+		let mut baseline = payload.clone();
+
+		if let Some(fields) = baseline.as_object_mut() {
+			fields.remove("provider");
+		}
+
+		assert_eq!(
+			serde_json::from_value::<StoreKey>(baseline)
+				.expect("Keys without a provider field should fall back to the family's."),
+			keyed
+		);
+
+		let mut mismatched = payload;
+
+		mismatched["provider"] = "gitlab".into();
+
+		assert!(serde_json::from_value::<StoreKey>(mismatched).is_err());
+		assert!(keyed.in_namespace(&tenant, Some(&github)));
+		assert!(keyed.in_namespace(&tenant, None));
+		assert!(!keyed.in_namespace(&tenant, Some(&gitlab)));
+
+		let store = MemoryStore::default();
+		let rt = tokio::runtime::Runtime::new()
+			.expect("Failed to build Tokio runtime for namespaced key test.");
+
+		for provider in [&github, &gitlab] {
+			let record =
+				TokenRecord::builder(family.clone().with_provider(provider.clone()), scope.clone())
+					.access_token(format!("access-{provider}"))
+					.expires_in(Duration::hours(1))
+					.build()
+					.expect("Token record fixture should build successfully.");
+
+			rt.block_on(store.save(record)).expect("Failed to save fixture record.");
+		}
+
+		let across = rt
+			.block_on(store.list_tenant_records(&tenant, None))
+			.expect("Failed to list tenant records.");
+		let scoped = rt
+			.block_on(store.list_tenant_records(&tenant, Some(&gitlab)))
+			.expect("Failed to list tenant records.");
+
+		assert_eq!(across.len(), 2);
+		assert_eq!(scoped.len(), 1);
+		assert_eq!(scoped[0].access_token.expose(), "access-gitlab");
+	}
 }
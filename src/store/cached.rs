@@ -0,0 +1,574 @@
+//! Write-through, in-process LRU cache layered over a remote [`BrokerStore`].
+//!
+//! Network-backed stores (Redis, SQL, etcd) pay a round trip on every `fetch`, even for token
+//! families served hundreds of times per second. [`CachedStore`] keeps recently fetched records in
+//! memory, bounded by entry count and TTL, and invalidates them whenever a write goes through it.
+
+// std
+use std::{
+	collections::BTreeMap,
+	time::{Duration as StdDuration, Instant},
+};
+// self
+use crate::{
+	_prelude::*,
+	auth::{PrincipalPath, RevocationReason, ScopeSet, TenantId, TokenFamily, TokenRecord},
+	store::{
+		BrokerStore, CompareAndSwapOutcome, ConditionalFetch, PreparedWrite, RecordPage,
+		StoreFuture, StoreKey,
+	},
+};
+
+/// [`BrokerStore`] decorator that serves hot records from an in-process LRU.
+///
+/// Only successful fetches of existing records are cached. Every write (save, commit, CAS,
+/// revocation, purge, tenant erasure) invalidates the affected keys before and after it reaches
+/// the wrapped store, and a fetch that raced with an invalidation does not repopulate the cache,
+/// so this process never reads its own stale writes. Writes made by other processes become
+/// visible once the TTL expires; keep it shorter than the refresh skew so a peer's rotation is
+/// picked up before the old refresh token is used.
+#[derive(Debug)]
+pub struct CachedStore<S> {
+	inner: S,
+	capacity: usize,
+	ttl: StdDuration,
+	cache: Mutex<Lru>,
+}
+impl<S> CachedStore<S> {
+	/// Default number of cached records.
+	pub const DEFAULT_CAPACITY: usize = 1_024;
+	/// Default time a cached record is served without consulting the wrapped store.
+	pub const DEFAULT_TTL: Duration = Duration::seconds(5);
+
+	/// Wraps `inner` with the default capacity and TTL.
+	pub fn new(inner: S) -> Self {
+		Self {
+			inner,
+			capacity: Self::DEFAULT_CAPACITY,
+			ttl: Self::DEFAULT_TTL.try_into().unwrap_or_default(),
+			cache: Default::default(),
+		}
+	}
+
+	/// Caches at most `entries` records, evicting the least recently used (`0` disables caching).
+	pub fn with_capacity(mut self, entries: usize) -> Self {
+		self.capacity = entries;
+
+		self
+	}
+
+	/// Serves a cached record for at most `ttl` (non-positive values disable caching).
+	pub fn with_ttl(mut self, ttl: Duration) -> Self {
+		self.ttl = ttl.try_into().unwrap_or_default();
+
+		self
+	}
+
+	/// Returns the wrapped store.
+	///
+	/// Writes made directly through it bypass invalidation; call [`CachedStore::clear`] afterwards.
+	pub fn inner(&self) -> &S {
+		&self.inner
+	}
+
+	/// Unwraps the decorator, returning the wrapped store.
+	pub fn into_inner(self) -> S {
+		self.inner
+	}
+
+	/// Number of records currently cached, expired ones included.
+	pub fn len(&self) -> usize {
+		self.cache.lock().entries.len()
+	}
+
+	/// Returns `true` when nothing is cached.
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Drops the cached record for `family` + `scope`, if any.
+	pub fn invalidate(&self, family: &TokenFamily, scope: &ScopeSet) {
+		if let Ok(key) = StoreKey::new(family, scope) {
+			self.cache.lock().remove(&key);
+		}
+	}
+
+	/// Drops every cached record.
+	pub fn clear(&self) {
+		self.cache.lock().retain(|_| false);
+	}
+
+	fn invalidate_where(&self, stale: impl Fn(&StoreKey) -> bool) {
+		self.cache.lock().retain(|key| !stale(key));
+	}
+}
+impl<S> BrokerStore for CachedStore<S>
+where
+	S: BrokerStore,
+{
+	fn save(&self, record: TokenRecord) -> StoreFuture<'_, ()> {
+		Box::pin(async move {
+			let key = StoreKey::new(&record.family, &record.scope)?;
+
+			self.cache.lock().remove(&key);
+
+			let result = self.inner.save(record).await;
+
+			self.cache.lock().remove(&key);
+
+			result
+		})
+	}
+
+	fn fetch<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+	) -> StoreFuture<'a, Option<TokenRecord>> {
+		Box::pin(async move {
+			let key = StoreKey::new(family, scope)?;
+			let epoch = {
+				let mut cache = self.cache.lock();
+
+				if let Some(record) = cache.get(&key, self.ttl) {
+					return Ok(Some(record));
+				}
+
+				cache.epoch
+			};
+			let record = self.inner.fetch(family, scope).await?;
+
+			if let Some(record) = &record
+				&& self.capacity > 0
+				&& !self.ttl.is_zero()
+			{
+				self.cache.lock().insert(key, record.clone(), epoch, self.capacity);
+			}
+
+			Ok(record)
+		})
+	}
+
+	fn compare_and_swap_refresh<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+		expected_refresh: Option<&'a str>,
+		replacement: TokenRecord,
+	) -> StoreFuture<'a, CompareAndSwapOutcome> {
+		Box::pin(async move {
+			self.invalidate(family, scope);
+
+			let result = self
+				.inner
+				.compare_and_swap_refresh(family, scope, expected_refresh, replacement)
+				.await;
+
+			self.invalidate(family, scope);
+
+			result
+		})
+	}
+
+	fn compare_and_swap_version<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+		expected_version: u64,
+		replacement: TokenRecord,
+	) -> StoreFuture<'a, CompareAndSwapOutcome> {
+		Box::pin(async move {
+			self.invalidate(family, scope);
+
+			let result = self
+				.inner
+				.compare_and_swap_version(family, scope, expected_version, replacement)
+				.await;
+
+			self.invalidate(family, scope);
+
+			result
+		})
+	}
+
+	fn fetch_if_changed<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+		known_fingerprint: Option<&'a str>,
+	) -> StoreFuture<'a, ConditionalFetch> {
+		let cached =
+			StoreKey::new(family, scope).ok().and_then(|key| self.cache.lock().get(&key, self.ttl));
+
+		match cached {
+			Some(record) =>
+				Box::pin(async move { ConditionalFetch::compare(record, known_fingerprint) }),
+			None => self.inner.fetch_if_changed(family, scope, known_fingerprint),
+		}
+	}
+
+	fn revoke<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+		instant: OffsetDateTime,
+		reason: RevocationReason,
+	) -> StoreFuture<'a, Option<TokenRecord>> {
+		Box::pin(async move {
+			self.invalidate(family, scope);
+
+			let result = self.inner.revoke(family, scope, instant, reason).await;
+
+			self.invalidate(family, scope);
+
+			result
+		})
+	}
+
+	fn prepare(&self, record: TokenRecord) -> StoreFuture<'_, PreparedWrite> {
+		self.inner.prepare(record)
+	}
+
+	fn commit<'a>(&'a self, prepared: &'a PreparedWrite) -> StoreFuture<'a, ()> {
+		Box::pin(async move {
+			let record = &prepared.record;
+
+			self.invalidate(&record.family, &record.scope);
+
+			let result = self.inner.commit(prepared).await;
+
+			self.invalidate(&record.family, &record.scope);
+
+			result
+		})
+	}
+
+	fn rollback<'a>(&'a self, prepared: &'a PreparedWrite) -> StoreFuture<'a, ()> {
+		self.inner.rollback(prepared)
+	}
+
+	fn pending_writes(&self) -> StoreFuture<'_, Vec<PreparedWrite>> {
+		self.inner.pending_writes()
+	}
+
+	fn health_check(&self) -> StoreFuture<'_, ()> {
+		self.inner.health_check()
+	}
+
+	fn purge_revoked(&self, cutoff: OffsetDateTime) -> StoreFuture<'_, usize> {
+		Box::pin(async move {
+			let result = self.inner.purge_revoked(cutoff).await;
+
+			self.cache.lock().retain_records(|record| !record.is_revoked());
+
+			result
+		})
+	}
+
+	fn list_principal_subtree<'a>(
+		&'a self,
+		tenant: &'a TenantId,
+		prefix: &'a PrincipalPath,
+	) -> StoreFuture<'a, Vec<TokenRecord>> {
+		self.inner.list_principal_subtree(tenant, prefix)
+	}
+
+	fn revoke_principal_subtree<'a>(
+		&'a self,
+		tenant: &'a TenantId,
+		prefix: &'a PrincipalPath,
+		instant: OffsetDateTime,
+		reason: RevocationReason,
+	) -> StoreFuture<'a, Vec<TokenRecord>> {
+		Box::pin(async move {
+			let stale = |key: &StoreKey| key.in_principal_subtree(tenant, prefix);
+
+			self.invalidate_where(stale);
+
+			let result = self.inner.revoke_principal_subtree(tenant, prefix, instant, reason).await;
+
+			self.invalidate_where(stale);
+
+			result
+		})
+	}
+
+	fn revoke_all<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		instant: OffsetDateTime,
+		reason: RevocationReason,
+	) -> StoreFuture<'a, Vec<TokenRecord>> {
+		Box::pin(async move {
+			let stale = |key: &StoreKey| key.family == *family;
+
+			self.invalidate_where(stale);
+
+			let result = self.inner.revoke_all(family, instant, reason).await;
+
+			self.invalidate_where(stale);
+
+			result
+		})
+	}
+
+	fn list_records<'a>(
+		&'a self,
+		after: Option<&'a str>,
+		limit: usize,
+	) -> StoreFuture<'a, RecordPage> {
+		self.inner.list_records(after, limit)
+	}
+
+	fn delete_tenant<'a>(&'a self, tenant: &'a TenantId) -> StoreFuture<'a, Vec<TokenRecord>> {
+		Box::pin(async move {
+			let stale = |key: &StoreKey| key.family.tenant == *tenant;
+
+			self.invalidate_where(stale);
+
+			let result = self.inner.delete_tenant(tenant).await;
+
+			self.invalidate_where(stale);
+
+			result
+		})
+	}
+
+	fn destroy_tenant_keys<'a>(&'a self, tenant: &'a TenantId) -> StoreFuture<'a, bool> {
+		Box::pin(async move {
+			let result = self.inner.destroy_tenant_keys(tenant).await;
+
+			// Records sealed under the destroyed keys must not outlive them in memory.
+			self.invalidate_where(|key| key.family.tenant == *tenant);
+
+			result
+		})
+	}
+
+	fn compare_and_swap_family<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		expected_refresh: Option<&'a str>,
+		records: Vec<TokenRecord>,
+	) -> StoreFuture<'a, CompareAndSwapOutcome> {
+		Box::pin(async move {
+			let stale = |key: &StoreKey| key.family == *family;
+
+			self.invalidate_where(stale);
+
+			let result =
+				self.inner.compare_and_swap_family(family, expected_refresh, records).await;
+
+			self.invalidate_where(stale);
+
+			result
+		})
+	}
+}
+
+/// Cached record plus its position in the recency order.
+#[derive(Debug)]
+struct Slot {
+	record: TokenRecord,
+	cached_at: Instant,
+	tick: u64,
+}
+
+/// Recency-ordered record cache.
+///
+/// `epoch` advances on every invalidation, so a fetch that started before one cannot insert the
+/// record it read.
+#[derive(Debug, Default)]
+struct Lru {
+	entries: HashMap<StoreKey, Slot>,
+	recency: BTreeMap<u64, StoreKey>,
+	tick: u64,
+	epoch: u64,
+}
+impl Lru {
+	fn get(&mut self, key: &StoreKey, ttl: StdDuration) -> Option<TokenRecord> {
+		let tick = self.next_tick();
+		let slot = self.entries.get_mut(key)?;
+
+		if slot.cached_at.elapsed() >= ttl {
+			self.remove(key);
+
+			return None;
+		}
+
+		self.recency.remove(&slot.tick);
+		self.recency.insert(tick, key.clone());
+		slot.tick = tick;
+
+		Some(slot.record.clone())
+	}
+
+	fn insert(&mut self, key: StoreKey, record: TokenRecord, epoch: u64, capacity: usize) {
+		if epoch != self.epoch {
+			return;
+		}
+
+		let tick = self.next_tick();
+
+		if let Some(previous) =
+			self.entries.insert(key.clone(), Slot { record, cached_at: Instant::now(), tick })
+		{
+			self.recency.remove(&previous.tick);
+		}
+
+		self.recency.insert(tick, key);
+
+		while self.entries.len() > capacity {
+			let Some((_, oldest)) = self.recency.pop_first() else {
+				break;
+			};
+
+			self.entries.remove(&oldest);
+		}
+	}
+
+	fn remove(&mut self, key: &StoreKey) {
+		self.epoch += 1;
+
+		if let Some(slot) = self.entries.remove(key) {
+			self.recency.remove(&slot.tick);
+		}
+	}
+
+	fn retain(&mut self, keep: impl Fn(&StoreKey) -> bool) {
+		self.epoch += 1;
+		self.entries.retain(|key, _| keep(key));
+		self.recency.retain(|_, key| keep(key));
+	}
+
+	fn retain_records(&mut self, keep: impl Fn(&TokenRecord) -> bool) {
+		self.epoch += 1;
+		self.entries.retain(|_, slot| keep(&slot.record));
+
+		let entries = &self.entries;
+
+		self.recency.retain(|_, key| entries.contains_key(key));
+	}
+
+	fn next_tick(&mut self) -> u64 {
+		self.tick += 1;
+
+		self.tick
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	// crates.io
+	use tokio::runtime::Runtime;
+	// self
+	use super::*;
+	use crate::{
+		auth::{PrincipalId, ProviderId},
+		store::MemoryStore,
+	};
+
+	fn record(principal: &str, access: &str) -> TokenRecord {
+		let family = TokenFamily::new(
+			TenantId::new("tenant-cache").expect("Tenant fixture should be valid."),
+			PrincipalId::new(principal).expect("Principal fixture should be valid."),
+		)
+		.with_provider(
+			ProviderId::new("provider-cache").expect("Provider fixture should be valid."),
+		);
+		let scope = ScopeSet::new(["read"]).expect("Scope fixture should be valid.");
+
+		TokenRecord::builder(family, scope)
+			.access_token(access)
+			.refresh_token("refresh")
+			.expires_in(Duration::hours(1))
+			.build()
+			.expect("Token record fixture should build successfully.")
+	}
+
+	#[test]
+	fn fetches_are_served_from_cache_until_a_write_invalidates_them() {
+		let rt = Runtime::new().expect("Failed to build Tokio runtime for cached store test.");
+		let store = CachedStore::new(MemoryStore::default());
+		let first = record("alice", "first");
+		let (family, scope) = (first.family.clone(), first.scope.clone());
+
+		rt.block_on(store.save(first)).expect("Saving through the cache should work.");
+
+		let fetched = rt.block_on(store.fetch(&family, &scope)).expect("Fetch should work.");
+
+		assert_eq!(
+			fetched.map(|record| record.access_token.expose().to_owned()).as_deref(),
+			Some("first")
+		);
+		assert_eq!(store.len(), 1);
+
+		// A write that bypasses the decorator stays invisible until the entry is invalidated.
+		rt.block_on(store.inner().save(record("alice", "bypass")))
+			.expect("Saving to the inner store should work.");
+
+		let cached = rt.block_on(store.fetch(&family, &scope)).expect("Fetch should work.");
+
+		assert_eq!(
+			cached.map(|record| record.access_token.expose().to_owned()).as_deref(),
+			Some("first")
+		);
+
+		rt.block_on(store.save(record("alice", "second"))).expect("Saving should work.");
+
+		let fresh = rt.block_on(store.fetch(&family, &scope)).expect("Fetch should work.");
+
+		assert_eq!(
+			fresh.map(|record| record.access_token.expose().to_owned()).as_deref(),
+			Some("second")
+		);
+
+		rt.block_on(store.revoke(
+			&family,
+			&scope,
+			OffsetDateTime::now_utc(),
+			RevocationReason::AdminAction,
+		))
+		.expect("Revoking should work.");
+
+		let revoked = rt.block_on(store.fetch(&family, &scope)).expect("Fetch should work.");
+
+		assert!(revoked.is_some_and(|record| record.is_revoked()));
+	}
+
+	#[test]
+	fn least_recently_used_and_expired_entries_are_dropped() {
+		let rt = Runtime::new().expect("Failed to build Tokio runtime for cached store test.");
+		let store = CachedStore::new(MemoryStore::default()).with_capacity(2);
+		let records = ["alice", "bob", "carol"].map(|principal| record(principal, principal));
+
+		for record in &records {
+			rt.block_on(store.save(record.clone())).expect("Saving should work.");
+		}
+		for record in [&records[0], &records[1], &records[0], &records[2]] {
+			rt.block_on(store.fetch(&record.family, &record.scope)).expect("Fetch should work.");
+		}
+
+		let cache = store.cache.lock();
+		let cached = |record: &TokenRecord| {
+			cache.entries.contains_key(
+				&StoreKey::new(&record.family, &record.scope).expect("Store key should build."),
+			)
+		};
+
+		assert!(cached(&records[0]));
+		assert!(!cached(&records[1]));
+		assert!(cached(&records[2]));
+
+		drop(cache);
+
+		let store = store.with_ttl(Duration::ZERO);
+
+		rt.block_on(store.fetch(&records[0].family, &records[0].scope))
+			.expect("Fetch should work.");
+
+		assert!(!store.cache.lock().entries.contains_key(
+			&StoreKey::new(&records[0].family, &records[0].scope).expect("Store key should build.")
+		));
+	}
+}
@@ -0,0 +1,1104 @@
+//! Encrypt-at-rest [`BrokerStore`] decorator that seals access and refresh secrets with
+//! AES-256-GCM (or ChaCha20-Poly1305, see [`Cipher`]) before they reach the inner backend.
+//!
+//! Keys are derived from a [`MasterKey`] with HKDF-SHA256. By default every record shares one
+//! store key; [`EncryptedStore::with_per_tenant_keys`] switches new writes to per-tenant keys
+//! derived from the master key, the tenant identifier, and a random per-tenant salt kept in
+//! [`TenantKeys`]. An export of one tenant's records together with its derived key therefore
+//! reveals nothing about other tenants, and destroying a tenant's salt
+//! ([`EncryptedStore::destroy_tenant_key`]) crypto-shreds every record sealed under it.
+//!
+//! [`EncryptedStore::with_key_provider`] uses envelope encryption instead: every write asks a
+//! [`KeyProvider`] (AWS KMS, GCP KMS, Vault transit, ...) for a fresh data key, seals the secrets
+//! locally, and stores the KMS-wrapped data key alongside the ciphertext. Reads send the wrapped
+//! key back to the provider for unwrapping, so the master key never enters process memory.
+//!
+//! Sealed secrets carry a prefix naming the cipher and key they were sealed under, so records
+//! written before per-tenant keys were enabled (or before the wrapper was introduced at all) stay
+//! readable. Master keys rotate by registering the old key with
+//! [`EncryptedStore::with_retired_key`], which keeps existing records readable, and then
+//! re-sealing them under the new key with [`EncryptedStore::rotate_keys`].
+
+// crates.io
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use ring::{
+	aead::{
+		AES_256_GCM, Aad, Algorithm, CHACHA20_POLY1305, LessSafeKey, NONCE_LEN, Nonce, UnboundKey,
+	},
+	hkdf::{HKDF_SHA256, Salt},
+};
+// self
+use crate::{
+	_prelude::*,
+	auth::{
+		PrincipalPath, RevocationReason, ScopeSet, TenantId, TokenFamily, TokenRecord, TokenSecret,
+	},
+	store::{
+		BrokerStore, CompareAndSwapOutcome, PreparedWrite, RecordPage, StoreError, StoreFuture,
+	},
+};
+
+const SEALED_PREFIX: &str = "enc:v1:";
+const CHACHA_SEALED_PREFIX: &str = "enc:v1c:";
+const SHARED_KEY_TAG: &str = "s:";
+const TENANT_KEY_TAG: &str = "t:";
+const ENVELOPE_KEY_TAG: &str = "k:";
+const SHARED_KEY_INFO: &[u8] = b"oauth2-broker/store-key/v1";
+const TENANT_KEY_INFO: &[u8] = b"oauth2-broker/tenant-key/v1";
+const TENANT_SALT_LEN: usize = 32;
+
+/// 256-bit root secret every store key is derived from.
+#[derive(Clone)]
+pub struct MasterKey([u8; 32]);
+impl MasterKey {
+	/// Wraps existing key material (e.g., loaded from a secret manager).
+	pub fn new(bytes: [u8; 32]) -> Self {
+		Self(bytes)
+	}
+
+	/// Generates a random master key.
+	pub fn generate() -> Self {
+		Self(rand::random())
+	}
+}
+impl Debug for MasterKey {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		f.write_str("MasterKey(**redacted**)")
+	}
+}
+
+/// Data key minted by a [`KeyProvider`] for one envelope-encrypted record.
+pub struct DataKey {
+	plaintext: [u8; 32],
+	wrapped: Vec<u8>,
+}
+impl DataKey {
+	/// Pairs the plaintext key with the copy wrapped by the provider's master key.
+	pub fn new(plaintext: [u8; 32], wrapped: Vec<u8>) -> Self {
+		Self { plaintext, wrapped }
+	}
+
+	/// Wrapped key as stored next to the ciphertext.
+	pub fn wrapped(&self) -> &[u8] {
+		&self.wrapped
+	}
+}
+impl Debug for DataKey {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		f.debug_struct("DataKey")
+			.field("plaintext", &"**redacted**")
+			.field("wrapped_len", &self.wrapped.len())
+			.finish()
+	}
+}
+
+/// Envelope-encryption key source backed by an external KMS.
+///
+/// Implementations map onto the provider's data key API: AWS KMS `GenerateDataKey` (256-bit
+/// AES key) and `Decrypt`, or a locally generated key wrapped through GCP KMS `Encrypt` and
+/// unwrapped through `Decrypt`. `tenant` is passed as the encryption context (or additional
+/// authenticated data) so a wrapped key only unwraps for the tenant it was minted for, and KMS
+/// policies can scope access per tenant.
+pub trait KeyProvider
+where
+	Self: Send + Sync,
+{
+	/// Mints a fresh 256-bit data key for `tenant`, returning it in plaintext and wrapped form.
+	fn generate_data_key<'a>(&'a self, tenant: &'a TenantId) -> StoreFuture<'a, DataKey>;
+
+	/// Recovers the plaintext of a data key produced by
+	/// [`generate_data_key`](Self::generate_data_key) for `tenant`.
+	fn unwrap_data_key<'a>(
+		&'a self,
+		tenant: &'a TenantId,
+		wrapped: &'a [u8],
+	) -> StoreFuture<'a, [u8; 32]>;
+}
+impl<P> KeyProvider for Arc<P>
+where
+	P: ?Sized + KeyProvider,
+{
+	fn generate_data_key<'a>(&'a self, tenant: &'a TenantId) -> StoreFuture<'a, DataKey> {
+		(**self).generate_data_key(tenant)
+	}
+
+	fn unwrap_data_key<'a>(
+		&'a self,
+		tenant: &'a TenantId,
+		wrapped: &'a [u8],
+	) -> StoreFuture<'a, [u8; 32]> {
+		(**self).unwrap_data_key(tenant, wrapped)
+	}
+}
+
+/// AEAD cipher used to seal new writes.
+///
+/// Reads accept both ciphers regardless of this setting, so switching ciphers only needs a
+/// [`EncryptedStore::rotate_keys`] pass to convert existing records.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Cipher {
+	/// AES-256-GCM, hardware-accelerated on most server CPUs.
+	#[default]
+	Aes256Gcm,
+	/// ChaCha20-Poly1305, faster on CPUs without AES instructions.
+	ChaCha20Poly1305,
+}
+impl Cipher {
+	const ALL: [Self; 2] = [Self::Aes256Gcm, Self::ChaCha20Poly1305];
+
+	fn algorithm(self) -> &'static Algorithm {
+		match self {
+			Self::Aes256Gcm => &AES_256_GCM,
+			Self::ChaCha20Poly1305 => &CHACHA20_POLY1305,
+		}
+	}
+
+	fn prefix(self) -> &'static str {
+		match self {
+			Self::Aes256Gcm => SEALED_PREFIX,
+			Self::ChaCha20Poly1305 => CHACHA_SEALED_PREFIX,
+		}
+	}
+}
+
+/// Per-tenant salts that, together with the [`MasterKey`], determine each tenant's key.
+///
+/// Salts are not secret on their own, but losing one makes the tenant's records unreadable.
+/// Persist the value returned by [`EncryptedStore::tenant_keys`] whenever a new tenant is first
+/// written and restore it with [`EncryptedStore::with_tenant_keys`] on startup.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TenantKeys(BTreeMap<TenantId, String>);
+impl TenantKeys {
+	/// Returns `true` when key material exists for `tenant`.
+	pub fn contains(&self, tenant: &TenantId) -> bool {
+		self.0.contains_key(tenant)
+	}
+
+	/// Tenants that currently have key material.
+	pub fn tenants(&self) -> impl Iterator<Item = &TenantId> {
+		self.0.keys()
+	}
+
+	fn salt_for(&self, tenant: &TenantId) -> Result<Vec<u8>, StoreError> {
+		let encoded = self.0.get(tenant).ok_or_else(|| StoreError::Backend {
+			message: format!("Key material for tenant {tenant} is missing or was destroyed"),
+		})?;
+
+		URL_SAFE_NO_PAD.decode(encoded).map_err(|e| StoreError::Serialization {
+			message: format!("Invalid key salt for tenant {tenant}: {e}"),
+		})
+	}
+}
+impl Debug for TenantKeys {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		f.debug_set().entries(self.0.keys()).finish()
+	}
+}
+
+/// [`BrokerStore`] decorator that encrypts token secrets before delegating to `S`.
+///
+/// Only the access and refresh secrets are sealed; identifiers, scopes, and timestamps stay in
+/// the clear so the inner backend can still key, list, and purge records. Refresh CAS decrypts
+/// the current record and then swaps against its exact ciphertext, so the inner backend's
+/// atomicity guarantees carry over unchanged.
+pub struct EncryptedStore<S> {
+	inner: S,
+	keys: KeySource,
+	retired: Vec<MasterKey>,
+	cipher: Cipher,
+	per_tenant: bool,
+	tenant_keys: RwLock<TenantKeys>,
+}
+impl<S> EncryptedStore<S>
+where
+	S: BrokerStore,
+{
+	/// Wraps `inner`, sealing every record under one key derived from `master`.
+	pub fn new(inner: S, master: MasterKey) -> Self {
+		Self {
+			inner,
+			keys: KeySource::Master(master),
+			retired: Vec::new(),
+			cipher: Cipher::default(),
+			per_tenant: false,
+			tenant_keys: Default::default(),
+		}
+	}
+
+	/// Wraps `inner`, sealing every record under a fresh data key from `provider`.
+	///
+	/// Each write costs one provider call and each read one unwrap per record. Records sealed
+	/// under a [`MasterKey`] cannot be opened by this store; copy them over with
+	/// [`migrate`](crate::store::migrate) from a master-key wrapper instead.
+	pub fn with_key_provider(inner: S, provider: impl 'static + KeyProvider) -> Self {
+		Self {
+			inner,
+			keys: KeySource::Envelope(Arc::new(provider)),
+			retired: Vec::new(),
+			cipher: Cipher::default(),
+			per_tenant: false,
+			tenant_keys: Default::default(),
+		}
+	}
+
+	/// Seals new writes under per-tenant keys derived from the master key and tenant id.
+	///
+	/// Envelope stores already mint a data key per record and ignore this setting.
+	pub fn with_per_tenant_keys(mut self) -> Self {
+		self.per_tenant = true;
+
+		self
+	}
+
+	/// Seals new writes with `cipher` instead of AES-256-GCM.
+	pub fn with_cipher(mut self, cipher: Cipher) -> Self {
+		self.cipher = cipher;
+
+		self
+	}
+
+	/// Keeps records sealed under a previous master key readable.
+	///
+	/// Retired keys only decrypt; new writes always use the current key. Tenant salts are shared
+	/// across master keys, so per-tenant records rotate without re-exporting [`TenantKeys`].
+	/// Envelope stores ignore retired keys because the [`KeyProvider`] handles KMS key versions.
+	pub fn with_retired_key(mut self, master: MasterKey) -> Self {
+		self.retired.push(master);
+
+		self
+	}
+
+	/// Restores previously exported tenant salts.
+	pub fn with_tenant_keys(mut self, keys: TenantKeys) -> Self {
+		self.tenant_keys = RwLock::new(keys);
+
+		self
+	}
+
+	/// Snapshot of the tenant salts to persist alongside the master key.
+	pub fn tenant_keys(&self) -> TenantKeys {
+		self.tenant_keys.read().clone()
+	}
+
+	/// Destroys the key material for `tenant`, returning `true` when any existed.
+	///
+	/// Records sealed under the tenant's key can no longer be decrypted; reads for them fail
+	/// with [`StoreError::Backend`]. Persist [`EncryptedStore::tenant_keys`] afterwards so the
+	/// salt does not come back from an older snapshot.
+	pub fn destroy_tenant_key(&self, tenant: &TenantId) -> bool {
+		self.tenant_keys.write().0.remove(tenant).is_some()
+	}
+
+	/// Returns the wrapped backend.
+	pub fn inner(&self) -> &S {
+		&self.inner
+	}
+
+	/// Re-seals every record that was not written under the current key, cipher, and key mode,
+	/// returning how many records were rewritten.
+	///
+	/// Covers records sealed under a retired key or the other [`Cipher`], records written before
+	/// per-tenant keys were enabled, and plaintext records written before the wrapper was
+	/// introduced. Each record is swapped against its exact stored refresh ciphertext, so a
+	/// concurrent refresh (which already writes under the current key) wins untouched. Once
+	/// this returns, retired keys can be dropped. Records whose tenant key was destroyed cannot be
+	/// opened and fail the pass; delete them with [`BrokerStore::delete_tenant`] first.
+	pub async fn rotate_keys(&self) -> Result<usize, StoreError> {
+		const PAGE: usize = 200;
+
+		let mut after = None;
+		let mut rotated = 0;
+
+		loop {
+			let page = self.inner.list_records(after.as_deref(), PAGE).await?;
+
+			for record in page.records {
+				let (opened, stale) = self.open_record_tracked(record.clone()).await?;
+
+				if !stale {
+					continue;
+				}
+
+				let outcome = self
+					.inner
+					.compare_and_swap_refresh(
+						&record.family,
+						&record.scope,
+						record.refresh_token.as_ref().map(TokenSecret::expose),
+						self.seal_record(opened).await?,
+					)
+					.await?;
+
+				if outcome == CompareAndSwapOutcome::Updated {
+					rotated += 1;
+				}
+			}
+
+			match page.next {
+				Some(next) => after = Some(next),
+				None => return Ok(rotated),
+			}
+		}
+	}
+
+	fn master(&self) -> Result<&MasterKey, StoreError> {
+		match &self.keys {
+			KeySource::Master(master) => Ok(master),
+			KeySource::Envelope(_) => Err(StoreError::Backend {
+				message: "Record was sealed under a master key, but this store uses a key provider"
+					.into(),
+			}),
+		}
+	}
+
+	/// Current master key followed by retired ones, in the order reads try them.
+	fn masters(&self) -> Result<impl Iterator<Item = &MasterKey>, StoreError> {
+		Ok(std::iter::once(self.master()?).chain(&self.retired))
+	}
+
+	/// Key tag new writes are sealed under.
+	fn write_tag(&self) -> &'static str {
+		match &self.keys {
+			KeySource::Envelope(_) => ENVELOPE_KEY_TAG,
+			KeySource::Master(_) if self.per_tenant => TENANT_KEY_TAG,
+			KeySource::Master(_) => SHARED_KEY_TAG,
+		}
+	}
+
+	fn shared_key(&self) -> Result<LessSafeKey, StoreError> {
+		derive_key(self.master()?, &[], &[SHARED_KEY_INFO], self.cipher)
+	}
+
+	fn tenant_key(&self, tenant: &TenantId, create: bool) -> Result<LessSafeKey, StoreError> {
+		let salt = self.tenant_salt(tenant, create)?;
+
+		derive_key(self.master()?, &salt, &[TENANT_KEY_INFO, tenant.as_bytes()], self.cipher)
+	}
+
+	fn tenant_salt(&self, tenant: &TenantId, create: bool) -> Result<Vec<u8>, StoreError> {
+		let existing = self.tenant_keys.read().salt_for(tenant);
+
+		match existing {
+			Ok(salt) => Ok(salt),
+			Err(_) if create => {
+				let mut keys = self.tenant_keys.write();
+				let encoded = keys.0.entry(tenant.clone()).or_insert_with(|| {
+					URL_SAFE_NO_PAD.encode(rand::random::<[u8; TENANT_SALT_LEN]>())
+				});
+
+				URL_SAFE_NO_PAD.decode(encoded.as_str()).map_err(|e| StoreError::Serialization {
+					message: format!("Invalid key salt for tenant {tenant}: {e}"),
+				})
+			},
+			Err(err) => Err(err),
+		}
+	}
+
+	async fn seal_record(&self, mut record: TokenRecord) -> Result<TokenRecord, StoreError> {
+		let (key, header) = match &self.keys {
+			KeySource::Envelope(provider) => {
+				let data_key = provider.generate_data_key(&record.family.tenant).await?;
+
+				(aead_key(&data_key.plaintext, self.cipher)?, envelope_header(&data_key)?)
+			},
+			KeySource::Master(_) if self.per_tenant =>
+				(self.tenant_key(&record.family.tenant, true)?, Vec::new()),
+			KeySource::Master(_) => (self.shared_key()?, Vec::new()),
+		};
+		let prefix = format!("{}{}", self.cipher.prefix(), self.write_tag());
+
+		record.access_token =
+			seal(&key, &prefix, &header, &record.family, "access_token", &record.access_token)?;
+
+		if let Some(refresh) = &record.refresh_token {
+			record.refresh_token =
+				Some(seal(&key, &prefix, &header, &record.family, "refresh_token", refresh)?);
+		}
+
+		Ok(record)
+	}
+
+	async fn open_record(&self, record: TokenRecord) -> Result<TokenRecord, StoreError> {
+		Ok(self.open_record_tracked(record).await?.0)
+	}
+
+	/// Opens both secrets, also reporting whether either needs re-sealing under the current key.
+	async fn open_record_tracked(
+		&self,
+		mut record: TokenRecord,
+	) -> Result<(TokenRecord, bool), StoreError> {
+		// Both secrets of an envelope record share one data key; unwrap it once.
+		let mut unwrapped = None;
+		let (access, mut stale) =
+			self.open(&record.family, "access_token", &record.access_token, &mut unwrapped).await?;
+
+		record.access_token = access;
+
+		if let Some(refresh) = &record.refresh_token {
+			let (refresh, refresh_stale) =
+				self.open(&record.family, "refresh_token", refresh, &mut unwrapped).await?;
+
+			record.refresh_token = Some(refresh);
+			stale |= refresh_stale;
+		}
+
+		Ok((record, stale))
+	}
+
+	async fn open(
+		&self,
+		family: &TokenFamily,
+		field: &str,
+		secret: &TokenSecret,
+		unwrapped: &mut Option<(Vec<u8>, [u8; 32])>,
+	) -> Result<(TokenSecret, bool), StoreError> {
+		let Some((cipher, sealed)) = Cipher::ALL.into_iter().find_map(|cipher| {
+			secret.expose().strip_prefix(cipher.prefix()).map(|sealed| (cipher, sealed))
+		}) else {
+			// Written before encryption was enabled.
+			return Ok((secret.clone(), true));
+		};
+		let decode = |payload: &str| {
+			URL_SAFE_NO_PAD.decode(payload).map_err(|e| StoreError::Serialization {
+				message: format!("Sealed {field} is not valid base64: {e}"),
+			})
+		};
+		let (keys, bytes, tag) = if let Some(payload) = sealed.strip_prefix(TENANT_KEY_TAG) {
+			let salt = self.tenant_salt(&family.tenant, false)?;
+			let keys = self
+				.masters()?
+				.map(|master| {
+					derive_key(master, &salt, &[TENANT_KEY_INFO, family.tenant.as_bytes()], cipher)
+				})
+				.collect::<Result<Vec<_>, _>>()?;
+
+			(keys, decode(payload)?, TENANT_KEY_TAG)
+		} else if let Some(payload) = sealed.strip_prefix(SHARED_KEY_TAG) {
+			let keys = self
+				.masters()?
+				.map(|master| derive_key(master, &[], &[SHARED_KEY_INFO], cipher))
+				.collect::<Result<Vec<_>, _>>()?;
+
+			(keys, decode(payload)?, SHARED_KEY_TAG)
+		} else if let Some(payload) = sealed.strip_prefix(ENVELOPE_KEY_TAG) {
+			let (wrapped, bytes) = split_envelope(decode(payload)?, field)?;
+			let plaintext = match unwrapped {
+				Some((cached, plaintext)) if *cached == wrapped => *plaintext,
+				_ => {
+					let KeySource::Envelope(provider) = &self.keys else {
+						return Err(StoreError::Backend {
+							message: format!(
+								"Sealed {field} needs a key provider, but this store uses a master key"
+							),
+						});
+					};
+					let plaintext = provider.unwrap_data_key(&family.tenant, &wrapped).await?;
+
+					*unwrapped = Some((wrapped, plaintext));
+
+					plaintext
+				},
+			};
+
+			(vec![aead_key(&plaintext, cipher)?], bytes, ENVELOPE_KEY_TAG)
+		} else {
+			return Err(StoreError::Serialization {
+				message: format!("Unknown key tag on sealed {field}"),
+			});
+		};
+
+		if bytes.len() < NONCE_LEN {
+			return Err(StoreError::Serialization {
+				message: format!("Sealed {field} is truncated"),
+			});
+		}
+
+		let mut nonce = [0; NONCE_LEN];
+
+		nonce.copy_from_slice(&bytes[..NONCE_LEN]);
+
+		let aad = associated_data(family, field);
+
+		// The current key comes first; later candidates are retired master keys.
+		for (idx, key) in keys.iter().enumerate() {
+			let mut attempt = bytes.clone();
+			let Ok(plaintext) = key.open_in_place(
+				Nonce::assume_unique_for_key(nonce),
+				Aad::from(&aad),
+				&mut attempt[NONCE_LEN..],
+			) else {
+				continue;
+			};
+			let plaintext =
+				String::from_utf8(plaintext.to_vec()).map_err(|e| StoreError::Serialization {
+					message: format!("Decrypted {field} is not UTF-8: {e}"),
+				})?;
+			let stale = idx > 0 || cipher != self.cipher || tag != self.write_tag();
+
+			return Ok((TokenSecret::new(plaintext), stale));
+		}
+
+		Err(StoreError::Backend {
+			message: format!("Failed to decrypt {field} for tenant {}", family.tenant),
+		})
+	}
+
+	async fn open_all(&self, records: Vec<TokenRecord>) -> Result<Vec<TokenRecord>, StoreError> {
+		let mut opened = Vec::with_capacity(records.len());
+
+		for record in records {
+			opened.push(self.open_record(record).await?);
+		}
+
+		Ok(opened)
+	}
+}
+impl<S> BrokerStore for EncryptedStore<S>
+where
+	S: BrokerStore,
+{
+	fn save(&self, record: TokenRecord) -> StoreFuture<'_, ()> {
+		Box::pin(async move { self.inner.save(self.seal_record(record).await?).await })
+	}
+
+	fn fetch<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+	) -> StoreFuture<'a, Option<TokenRecord>> {
+		Box::pin(async move {
+			match self.inner.fetch(family, scope).await? {
+				Some(record) => Ok(Some(self.open_record(record).await?)),
+				None => Ok(None),
+			}
+		})
+	}
+
+	fn compare_and_swap_refresh<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+		expected_refresh: Option<&'a str>,
+		replacement: TokenRecord,
+	) -> StoreFuture<'a, CompareAndSwapOutcome> {
+		Box::pin(async move {
+			let Some(current) = self.inner.fetch(family, scope).await? else {
+				return Ok(CompareAndSwapOutcome::Missing);
+			};
+			let opened = match &current.refresh_token {
+				Some(secret) =>
+					Some(self.open(family, "refresh_token", secret, &mut None).await?.0),
+				None => None,
+			};
+
+			if opened.as_ref().map(TokenSecret::expose) != expected_refresh {
+				return Ok(CompareAndSwapOutcome::RefreshMismatch);
+			}
+
+			// Swap against the exact stored ciphertext so a concurrent writer still loses.
+			let sealed_expected = current.refresh_token.as_ref().map(TokenSecret::expose);
+
+			self.inner
+				.compare_and_swap_refresh(
+					family,
+					scope,
+					sealed_expected,
+					self.seal_record(replacement).await?,
+				)
+				.await
+		})
+	}
+
+	fn compare_and_swap_version<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+		expected_version: u64,
+		replacement: TokenRecord,
+	) -> StoreFuture<'a, CompareAndSwapOutcome> {
+		// Versions are stored in the clear, so the inner store can compare them directly.
+		Box::pin(async move {
+			self.inner
+				.compare_and_swap_version(
+					family,
+					scope,
+					expected_version,
+					self.seal_record(replacement).await?,
+				)
+				.await
+		})
+	}
+
+	fn revoke<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+		instant: OffsetDateTime,
+		reason: RevocationReason,
+	) -> StoreFuture<'a, Option<TokenRecord>> {
+		Box::pin(async move {
+			match self.inner.revoke(family, scope, instant, reason).await? {
+				Some(record) => Ok(Some(self.open_record(record).await?)),
+				None => Ok(None),
+			}
+		})
+	}
+
+	fn prepare(&self, record: TokenRecord) -> StoreFuture<'_, PreparedWrite> {
+		Box::pin(async move {
+			let mut prepared = self.inner.prepare(self.seal_record(record).await?).await?;
+
+			prepared.record = self.open_record(prepared.record).await?;
+
+			Ok(prepared)
+		})
+	}
+
+	fn commit<'a>(&'a self, prepared: &'a PreparedWrite) -> StoreFuture<'a, ()> {
+		Box::pin(async move {
+			let sealed = PreparedWrite {
+				id: prepared.id.clone(),
+				record: self.seal_record(prepared.record.clone()).await?,
+			};
+
+			self.inner.commit(&sealed).await
+		})
+	}
+
+	fn rollback<'a>(&'a self, prepared: &'a PreparedWrite) -> StoreFuture<'a, ()> {
+		self.inner.rollback(prepared)
+	}
+
+	fn pending_writes(&self) -> StoreFuture<'_, Vec<PreparedWrite>> {
+		Box::pin(async move {
+			let mut opened = Vec::new();
+
+			for prepared in self.inner.pending_writes().await? {
+				opened.push(PreparedWrite {
+					id: prepared.id,
+					record: self.open_record(prepared.record).await?,
+				});
+			}
+
+			Ok(opened)
+		})
+	}
+
+	fn health_check(&self) -> StoreFuture<'_, ()> {
+		self.inner.health_check()
+	}
+
+	fn purge_revoked(&self, cutoff: OffsetDateTime) -> StoreFuture<'_, usize> {
+		self.inner.purge_revoked(cutoff)
+	}
+
+	fn list_principal_subtree<'a>(
+		&'a self,
+		tenant: &'a TenantId,
+		prefix: &'a PrincipalPath,
+	) -> StoreFuture<'a, Vec<TokenRecord>> {
+		Box::pin(async move {
+			self.open_all(self.inner.list_principal_subtree(tenant, prefix).await?).await
+		})
+	}
+
+	fn revoke_principal_subtree<'a>(
+		&'a self,
+		tenant: &'a TenantId,
+		prefix: &'a PrincipalPath,
+		instant: OffsetDateTime,
+		reason: RevocationReason,
+	) -> StoreFuture<'a, Vec<TokenRecord>> {
+		Box::pin(async move {
+			self.open_all(
+				self.inner.revoke_principal_subtree(tenant, prefix, instant, reason).await?,
+			)
+			.await
+		})
+	}
+
+	fn revoke_all<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		instant: OffsetDateTime,
+		reason: RevocationReason,
+	) -> StoreFuture<'a, Vec<TokenRecord>> {
+		Box::pin(async move {
+			self.open_all(self.inner.revoke_all(family, instant, reason).await?).await
+		})
+	}
+
+	fn list_records<'a>(
+		&'a self,
+		after: Option<&'a str>,
+		limit: usize,
+	) -> StoreFuture<'a, RecordPage> {
+		Box::pin(async move {
+			let page = self.inner.list_records(after, limit).await?;
+
+			Ok(RecordPage { records: self.open_all(page.records).await?, next: page.next })
+		})
+	}
+
+	fn delete_tenant<'a>(&'a self, tenant: &'a TenantId) -> StoreFuture<'a, Vec<TokenRecord>> {
+		Box::pin(async move {
+			let mut deleted = Vec::new();
+
+			for record in self.inner.delete_tenant(tenant).await? {
+				// Records whose key was already destroyed are returned sealed.
+				deleted.push(self.open_record(record.clone()).await.unwrap_or(record));
+			}
+
+			Ok(deleted)
+		})
+	}
+
+	fn destroy_tenant_keys<'a>(&'a self, tenant: &'a TenantId) -> StoreFuture<'a, bool> {
+		Box::pin(async move { Ok(self.destroy_tenant_key(tenant)) })
+	}
+}
+impl<S> Debug for EncryptedStore<S>
+where
+	S: Debug,
+{
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		f.debug_struct("EncryptedStore")
+			.field("inner", &self.inner)
+			.field("envelope", &matches!(self.keys, KeySource::Envelope(_)))
+			.field("retired_keys", &self.retired.len())
+			.field("cipher", &self.cipher)
+			.field("per_tenant", &self.per_tenant)
+			.field("tenant_keys", &*self.tenant_keys.read())
+			.finish()
+	}
+}
+
+enum KeySource {
+	Master(MasterKey),
+	Envelope(Arc<dyn KeyProvider>),
+}
+
+fn derive_key(
+	master: &MasterKey,
+	salt: &[u8],
+	info: &[&[u8]],
+	cipher: Cipher,
+) -> Result<LessSafeKey, StoreError> {
+	let prk = Salt::new(HKDF_SHA256, salt).extract(&master.0);
+	let okm = prk
+		.expand(info, cipher.algorithm())
+		.map_err(|_| StoreError::Backend { message: "Failed to derive a store key".into() })?;
+
+	Ok(LessSafeKey::new(UnboundKey::from(okm)))
+}
+
+fn aead_key(bytes: &[u8; 32], cipher: Cipher) -> Result<LessSafeKey, StoreError> {
+	let key = UnboundKey::new(cipher.algorithm(), bytes)
+		.map_err(|_| StoreError::Backend { message: "Invalid data key".into() })?;
+
+	Ok(LessSafeKey::new(key))
+}
+
+/// Length-prefixed wrapped data key that precedes the nonce in envelope payloads.
+fn envelope_header(data_key: &DataKey) -> Result<Vec<u8>, StoreError> {
+	let len = u16::try_from(data_key.wrapped.len()).map_err(|_| StoreError::Backend {
+		message: "Wrapped data key exceeds 65535 bytes".into(),
+	})?;
+	let mut header = len.to_be_bytes().to_vec();
+
+	header.extend_from_slice(&data_key.wrapped);
+
+	Ok(header)
+}
+
+fn split_envelope(mut bytes: Vec<u8>, field: &str) -> Result<(Vec<u8>, Vec<u8>), StoreError> {
+	let truncated =
+		|| StoreError::Serialization { message: format!("Sealed {field} is truncated") };
+	let len = bytes.get(..2).ok_or_else(truncated)?;
+	let end = 2 + usize::from(u16::from_be_bytes([len[0], len[1]]));
+
+	if bytes.len() < end {
+		return Err(truncated());
+	}
+
+	let rest = bytes.split_off(end);
+
+	bytes.drain(..2);
+
+	Ok((bytes, rest))
+}
+
+fn seal(
+	key: &LessSafeKey,
+	prefix: &str,
+	header: &[u8],
+	family: &TokenFamily,
+	field: &str,
+	secret: &TokenSecret,
+) -> Result<TokenSecret, StoreError> {
+	let nonce = rand::random::<[u8; NONCE_LEN]>();
+	let mut payload = secret.expose().as_bytes().to_vec();
+
+	key.seal_in_place_append_tag(
+		Nonce::assume_unique_for_key(nonce),
+		Aad::from(associated_data(family, field)),
+		&mut payload,
+	)
+	.map_err(|_| StoreError::Backend { message: format!("Failed to encrypt {field}") })?;
+
+	let mut sealed = header.to_vec();
+
+	sealed.extend_from_slice(&nonce);
+	sealed.extend_from_slice(&payload);
+
+	Ok(TokenSecret::new(format!("{prefix}{}", URL_SAFE_NO_PAD.encode(sealed))))
+}
+
+/// Binds ciphertext to its owner and field so sealed values cannot be swapped between records.
+fn associated_data(family: &TokenFamily, field: &str) -> Vec<u8> {
+	format!("{}\n{}\n{field}", family.tenant, family.principal).into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+	// crates.io
+	use tokio::runtime::Runtime;
+	// self
+	use super::*;
+	use crate::{
+		auth::{PrincipalId, ProviderId},
+		store::MemoryStore,
+	};
+
+	/// Stand-in KMS that wraps data keys by XOR with a key it never reveals.
+	#[derive(Default)]
+	struct FakeKms {
+		unwraps: Mutex<usize>,
+	}
+	impl FakeKms {
+		const WRAPPING_KEY: [u8; 32] = [0x5a; 32];
+
+		fn wrap(tenant: &TenantId, key: &[u8; 32]) -> Vec<u8> {
+			let mut wrapped = tenant.as_bytes().to_vec();
+
+			wrapped.extend(key.iter().zip(Self::WRAPPING_KEY).map(|(byte, mask)| byte ^ mask));
+
+			wrapped
+		}
+	}
+	impl KeyProvider for FakeKms {
+		fn generate_data_key<'a>(&'a self, tenant: &'a TenantId) -> StoreFuture<'a, DataKey> {
+			let plaintext = rand::random::<[u8; 32]>();
+
+			Box::pin(async move { Ok(DataKey::new(plaintext, Self::wrap(tenant, &plaintext))) })
+		}
+
+		fn unwrap_data_key<'a>(
+			&'a self,
+			tenant: &'a TenantId,
+			wrapped: &'a [u8],
+		) -> StoreFuture<'a, [u8; 32]> {
+			Box::pin(async move {
+				*self.unwraps.lock() += 1;
+
+				let masked = wrapped.strip_prefix(tenant.as_bytes()).ok_or_else(|| {
+					StoreError::Backend { message: "Encryption context mismatch".into() }
+				})?;
+				let mut key = [0; 32];
+
+				for (idx, (byte, mask)) in masked.iter().zip(Self::WRAPPING_KEY).enumerate() {
+					key[idx] = byte ^ mask;
+				}
+
+				Ok(key)
+			})
+		}
+	}
+
+	fn build_record(tenant: &str) -> TokenRecord {
+		let family = TokenFamily::new(
+			TenantId::new(tenant).expect("Tenant fixture should be valid."),
+			PrincipalId::new("principal-enc").expect("Principal fixture should be valid."),
+		)
+		.with_provider(ProviderId::new("provider-enc").expect("Provider fixture should be valid."));
+
+		TokenRecord::builder(
+			family,
+			ScopeSet::new(["read"]).expect("Scope fixture should be valid."),
+		)
+		.access_token("access-plain")
+		.refresh_token("refresh-plain")
+		.expires_in(Duration::hours(1))
+		.build()
+		.expect("Token record fixture should build successfully.")
+	}
+
+	#[test]
+	fn secrets_are_sealed_at_rest_and_cas_matches_plaintext() {
+		let rt = Runtime::new().expect("Failed to build Tokio runtime for encrypted store test.");
+		let store = EncryptedStore::new(MemoryStore::default(), MasterKey::generate());
+		let record = build_record("tenant-a");
+
+		rt.block_on(store.save(record.clone())).expect("Saving through the wrapper should work.");
+
+		let raw = store
+			.inner()
+			.fetch_shared(&record.family, &record.scope)
+			.expect("Inner store should hold the sealed record.");
+
+		assert!(raw.access_token.expose().starts_with(SEALED_PREFIX));
+		assert!(!raw.access_token.expose().contains("access-plain"));
+
+		let mut rotated = record.clone();
+
+		rotated.refresh_token = Some(TokenSecret::new("refresh-rotated"));
+
+		let outcome = rt
+			.block_on(store.compare_and_swap_refresh(
+				&record.family,
+				&record.scope,
+				Some("refresh-plain"),
+				rotated,
+			))
+			.expect("CAS through the wrapper should work.");
+
+		assert_eq!(outcome, CompareAndSwapOutcome::Updated);
+
+		let fetched = rt
+			.block_on(store.fetch(&record.family, &record.scope))
+			.expect("Fetching through the wrapper should work.")
+			.expect("Record should be present.");
+
+		assert_eq!(fetched.access_token.expose(), "access-plain");
+		assert_eq!(
+			fetched.refresh_token.as_ref().map(TokenSecret::expose),
+			Some("refresh-rotated")
+		);
+	}
+
+	#[test]
+	fn tenant_keys_isolate_tenants_and_support_shredding() {
+		let rt = Runtime::new().expect("Failed to build Tokio runtime for encrypted store test.");
+		let store = EncryptedStore::new(MemoryStore::default(), MasterKey::generate())
+			.with_per_tenant_keys();
+		let a = build_record("tenant-a");
+		let b = build_record("tenant-b");
+
+		rt.block_on(store.save(a.clone())).expect("Saving tenant A should work.");
+		rt.block_on(store.save(b.clone())).expect("Saving tenant B should work.");
+
+		let key_a = store.tenant_key(&a.family.tenant, false).expect("Tenant A key should exist.");
+		let sealed_b = store
+			.inner()
+			.fetch_shared(&b.family, &b.scope)
+			.expect("Inner store should hold tenant B's record.");
+		let payload = sealed_b
+			.access_token
+			.expose()
+			.strip_prefix(SEALED_PREFIX)
+			.and_then(|sealed| sealed.strip_prefix(TENANT_KEY_TAG))
+			.expect("Tenant B's secret should be sealed under a tenant key.");
+		let mut bytes = URL_SAFE_NO_PAD.decode(payload).expect("Sealed payload should decode.");
+		let mut nonce = [0; NONCE_LEN];
+
+		nonce.copy_from_slice(&bytes[..NONCE_LEN]);
+
+		assert!(
+			key_a
+				.open_in_place(
+					Nonce::assume_unique_for_key(nonce),
+					Aad::from(associated_data(&b.family, "access_token")),
+					&mut bytes[NONCE_LEN..],
+				)
+				.is_err(),
+			"Tenant A's key must not open tenant B's records."
+		);
+		assert!(store.destroy_tenant_key(&a.family.tenant));
+		assert!(rt.block_on(store.fetch(&a.family, &a.scope)).is_err());
+		assert!(
+			rt.block_on(store.fetch(&b.family, &b.scope))
+				.expect("Tenant B should stay readable.")
+				.is_some()
+		);
+		assert!(!store.tenant_keys().contains(&a.family.tenant));
+	}
+
+	#[test]
+	fn retired_keys_stay_readable_until_rotated() {
+		let rt = Runtime::new().expect("Failed to build Tokio runtime for encrypted store test.");
+		let old = MasterKey::generate();
+		let new = MasterKey::generate();
+		let legacy = EncryptedStore::new(MemoryStore::default(), old.clone());
+		let record = build_record("tenant-rotate");
+
+		rt.block_on(legacy.save(record.clone())).expect("Saving under the old key should work.");
+
+		let store = EncryptedStore::new(legacy.inner().clone(), new.clone())
+			.with_retired_key(old)
+			.with_cipher(Cipher::ChaCha20Poly1305);
+		let fetched = rt
+			.block_on(store.fetch(&record.family, &record.scope))
+			.expect("Retired keys should still decrypt.")
+			.expect("Record should be present.");
+
+		assert_eq!(fetched.access_token.expose(), "access-plain");
+		assert_eq!(rt.block_on(store.rotate_keys()).expect("Rotation should succeed."), 1);
+		assert_eq!(rt.block_on(store.rotate_keys()).expect("Rotation should succeed."), 0);
+
+		let raw = store
+			.inner()
+			.fetch_shared(&record.family, &record.scope)
+			.expect("Inner store should hold the rotated record.");
+
+		assert!(raw.access_token.expose().starts_with(CHACHA_SEALED_PREFIX));
+
+		let rotated = EncryptedStore::new(store.inner().clone(), new);
+		let fetched = rt
+			.block_on(rotated.fetch(&record.family, &record.scope))
+			.expect("Rotated records should open without the retired key.")
+			.expect("Record should be present.");
+
+		assert_eq!(fetched.refresh_token.as_ref().map(TokenSecret::expose), Some("refresh-plain"));
+	}
+
+	#[test]
+	fn envelope_records_store_wrapped_data_keys() {
+		let rt = Runtime::new().expect("Failed to build Tokio runtime for encrypted store test.");
+		let kms = Arc::new(FakeKms::default());
+		let store = EncryptedStore::with_key_provider(MemoryStore::default(), Arc::clone(&kms));
+		let record = build_record("tenant-kms");
+
+		rt.block_on(store.save(record.clone())).expect("Saving through the wrapper should work.");
+
+		let raw = store
+			.inner()
+			.fetch_shared(&record.family, &record.scope)
+			.expect("Inner store should hold the sealed record.");
+		let payload = raw
+			.access_token
+			.expose()
+			.strip_prefix(SEALED_PREFIX)
+			.and_then(|sealed| sealed.strip_prefix(ENVELOPE_KEY_TAG))
+			.expect("Secrets should be sealed under an envelope data key.");
+		let (wrapped, _) = split_envelope(
+			URL_SAFE_NO_PAD.decode(payload).expect("Sealed payload should decode."),
+			"access_token",
+		)
+		.expect("Envelope payload should carry the wrapped data key.");
+
+		assert!(wrapped.starts_with(b"tenant-kms"));
+
+		let fetched = rt
+			.block_on(store.fetch(&record.family, &record.scope))
+			.expect("Fetching through the wrapper should work.")
+			.expect("Record should be present.");
+
+		assert_eq!(fetched.access_token.expose(), "access-plain");
+		assert_eq!(fetched.refresh_token.as_ref().map(TokenSecret::expose), Some("refresh-plain"));
+		assert_eq!(*kms.unwraps.lock(), 1, "Both secrets should share one unwrap.");
+
+		let master_store = EncryptedStore::new(store.inner().clone(), MasterKey::generate());
+
+		assert!(rt.block_on(master_store.fetch(&record.family, &record.scope)).is_err());
+	}
+}
@@ -0,0 +1,1039 @@
+//! etcd-backed [`BrokerStore`] for control-plane deployments such as Kubernetes operators.
+//!
+//! The store talks to etcd's v3 JSON gateway (`/v3/kv/*`, `/v3/lease/*`) over `reqwest`, so it
+//! needs no gRPC stack. Each record is stored as JSON under `<prefix><StoreKey::page_cursor>`.
+//! Every mutation of an existing record is a transaction that compares both the key's
+//! `mod_revision` and its current value, so brokers sharing a cluster never overwrite each
+//! other's rotations. Records without a refresh token are attached to a lease that ends shortly
+//! after the access token expires, letting etcd drop dead records on its own. Clusters with auth
+//! enabled are reached through [`EtcdStore::with_credentials`]; the store authenticates on first
+//! use and again whenever the gateway rejects its token.
+
+// crates.io
+use base64::{Engine, engine::general_purpose::STANDARD};
+use reqwest::{
+	Client, StatusCode,
+	header::{AUTHORIZATION, CONTENT_TYPE},
+};
+use serde::de::{DeserializeOwned, IgnoredAny};
+// self
+use crate::{
+	_prelude::*,
+	auth::{
+		PrincipalPath, RevocationReason, ScopeSet, TenantId, TokenFamily, TokenRecord, TokenSecret,
+	},
+	store::{
+		self, BrokerStore, CompareAndSwapOutcome, RecordPage, StoreError, StoreFuture, StoreKey,
+	},
+};
+
+/// Persists broker records in etcd through its v3 JSON gateway.
+///
+/// Endpoints that carry a path (a proxy mount) must end with `/` so the gateway routes resolve
+/// below it.
+#[derive(Clone, Debug)]
+pub struct EtcdStore {
+	client: Client,
+	endpoint: Url,
+	prefix: String,
+	lease_grace: Duration,
+	auth: Option<Arc<EtcdAuth>>,
+}
+impl EtcdStore {
+	const DEFAULT_LEASE_GRACE: Duration = Duration::minutes(5);
+	/// Key prefix used unless [`EtcdStore::with_prefix`] overrides it.
+	pub const DEFAULT_PREFIX: &str = "/oauth2-broker/tokens/";
+	const MAX_TXN_ATTEMPTS: usize = 8;
+	const SCAN_PAGE: usize = 500;
+
+	/// Creates a store for the etcd gateway at `endpoint` (e.g. `http://127.0.0.1:2379`).
+	pub fn new(endpoint: Url) -> Self {
+		Self {
+			client: Client::new(),
+			endpoint,
+			prefix: Self::DEFAULT_PREFIX.into(),
+			lease_grace: Self::DEFAULT_LEASE_GRACE,
+			auth: None,
+		}
+	}
+
+	/// Uses a preconfigured HTTP client (TLS roots, client certificates, timeouts).
+	pub fn with_client(mut self, client: Client) -> Self {
+		self.client = client;
+
+		self
+	}
+
+	/// Stores every key under `prefix` so several brokers can share one cluster.
+	pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+		self.prefix = prefix.into();
+
+		self
+	}
+
+	/// Keeps records without a refresh token for `grace` past their expiry before their lease
+	/// ends.
+	pub fn with_lease_grace(mut self, grace: Duration) -> Self {
+		self.lease_grace = grace;
+
+		self
+	}
+
+	/// Authenticates as the etcd user `name` before every call.
+	///
+	/// The auth token is shared by every clone of the store and renewed when the gateway answers
+	/// `401 Unauthorized`.
+	pub fn with_credentials(
+		mut self,
+		name: impl Into<String>,
+		password: impl Into<String>,
+	) -> Self {
+		self.auth = Some(Arc::new(EtcdAuth {
+			name: name.into(),
+			password: password.into(),
+			token: Default::default(),
+		}));
+
+		self
+	}
+
+	fn key_for(&self, key: &StoreKey) -> String {
+		format!("{}{}", self.prefix, key.page_cursor())
+	}
+
+	fn record_key(&self, record: &TokenRecord) -> Result<String, StoreError> {
+		Ok(self.key_for(&StoreKey::new(&record.family, &record.scope)?))
+	}
+
+	async fn call<Req, Resp>(&self, path: &str, body: &Req) -> Result<Resp, StoreError>
+	where
+		Req: Serialize,
+		Resp: DeserializeOwned,
+	{
+		let body = serde_json::to_vec(body)
+			.map_err(|e| StoreError::Serialization { message: e.to_string() })?;
+		let token = self.auth_token(false).await?;
+		let (mut status, mut bytes) = self.send(path, &body, token.as_deref()).await?;
+
+		if status == StatusCode::UNAUTHORIZED && self.auth.is_some() {
+			let token = self.auth_token(true).await?;
+
+			(status, bytes) = self.send(path, &body, token.as_deref()).await?;
+		}
+
+		parse_response(path, status, &bytes)
+	}
+
+	async fn send(
+		&self,
+		path: &str,
+		body: &[u8],
+		token: Option<&str>,
+	) -> Result<(StatusCode, Vec<u8>), StoreError> {
+		let url = self.endpoint.join(path).map_err(|e| StoreError::Backend {
+			message: format!("Invalid etcd endpoint {}: {e}", self.endpoint),
+		})?;
+		let mut request =
+			self.client.post(url).header(CONTENT_TYPE, "application/json").body(body.to_vec());
+
+		if let Some(token) = token {
+			request = request.header(AUTHORIZATION, token);
+		}
+
+		let response = request
+			.send()
+			.await
+			.map_err(|e| StoreError::Backend { message: format!("etcd {path} failed: {e}") })?;
+		let status = response.status();
+		let bytes = response.bytes().await.map_err(|e| StoreError::Backend {
+			message: format!("etcd {path} response could not be read: {e}"),
+		})?;
+
+		Ok((status, bytes.to_vec()))
+	}
+
+	/// Returns the cached auth token, authenticating first when there is none or `renew` is set.
+	async fn auth_token(&self, renew: bool) -> Result<Option<String>, StoreError> {
+		const PATH: &str = "v3/auth/authenticate";
+
+		let Some(auth) = &self.auth else {
+			return Ok(None);
+		};
+
+		if !renew && let Some(token) = auth.token.read().clone() {
+			return Ok(Some(token));
+		}
+
+		let body =
+			serde_json::to_vec(&AuthenticateRequest { name: &auth.name, password: &auth.password })
+				.map_err(|e| StoreError::Serialization { message: e.to_string() })?;
+		let (status, bytes) = self.send(PATH, &body, None).await?;
+		let AuthenticateResponse { token } = parse_response(PATH, status, &bytes)?;
+
+		*auth.token.write() = Some(token.clone());
+
+		Ok(Some(token))
+	}
+
+	async fn range(
+		&self,
+		key: &[u8],
+		range_end: Option<&[u8]>,
+		limit: usize,
+	) -> Result<RangeResponse, StoreError> {
+		let request = RangeRequest {
+			key: STANDARD.encode(key),
+			range_end: range_end.map(|end| STANDARD.encode(end)),
+			limit: i64::try_from(limit).unwrap_or(i64::MAX),
+		};
+
+		self.call("v3/kv/range", &request).await
+	}
+
+	async fn get(&self, key: &str) -> Result<Option<KeyValue>, StoreError> {
+		Ok(self.range(key.as_bytes(), None, 1).await?.kvs.into_iter().next())
+	}
+
+	/// Reads every key that starts with `prefix`, one page at a time.
+	async fn scan(&self, prefix: &str) -> Result<Vec<KeyValue>, StoreError> {
+		let end = prefix_end(prefix.as_bytes());
+		let mut start = prefix.as_bytes().to_vec();
+		let mut kvs = Vec::new();
+
+		loop {
+			let page = self.range(&start, Some(&end), Self::SCAN_PAGE).await?;
+
+			if let Some(last) = page.kvs.last() {
+				start = last.key_bytes()?;
+				start.push(0);
+			}
+
+			kvs.extend(page.kvs);
+
+			if !page.more {
+				return Ok(kvs);
+			}
+		}
+	}
+
+	async fn txn(
+		&self,
+		compare: Vec<Compare>,
+		success: Vec<RequestOp>,
+	) -> Result<bool, StoreError> {
+		let response: TxnResponse =
+			self.call("v3/kv/txn", &TxnRequest { compare, success }).await?;
+
+		Ok(response.succeeded)
+	}
+
+	/// Lease for `record`: only records that cannot be refreshed expire with their access token.
+	async fn lease_for(&self, record: &TokenRecord) -> Result<i64, StoreError> {
+		if record.refresh_token.is_some() {
+			return Ok(0);
+		}
+
+		let ttl = (record.expires_at + self.lease_grace - OffsetDateTime::now_utc())
+			.whole_seconds()
+			.max(1);
+		let response: LeaseGrantResponse =
+			self.call("v3/lease/grant", &LeaseGrantRequest { ttl }).await?;
+
+		Ok(response.id)
+	}
+
+	/// Revokes leases granted for a write that did not land, so failed transactions leave no
+	/// orphaned leases behind.
+	async fn release_leases(&self, leases: &[i64]) {
+		for &id in leases.iter().filter(|id| **id != 0) {
+			// A lease that cannot be revoked still ends with its TTL.
+			let _: Result<IgnoredAny, _> =
+				self.call("v3/lease/revoke", &LeaseRevokeRequest { id }).await;
+		}
+	}
+
+	fn put_op(&self, record: &TokenRecord, lease: i64) -> Result<PutRequest, StoreError> {
+		let value = serde_json::to_vec(record)
+			.map_err(|e| StoreError::Serialization { message: e.to_string() })?;
+
+		Ok(PutRequest {
+			key: STANDARD.encode(self.record_key(record)?),
+			value: STANDARD.encode(value),
+			lease,
+		})
+	}
+
+	fn contention(key: &str) -> StoreError {
+		StoreError::Backend {
+			message: format!("etcd transaction on {key} kept losing to concurrent writers"),
+		}
+	}
+
+	/// Replaces the record at `key` when `matches` accepts it, reporting `mismatch` otherwise.
+	///
+	/// The lease is granted once for every attempt and revoked unless the write lands.
+	async fn cas_now<F>(
+		&self,
+		key: String,
+		matches: F,
+		mismatch: CompareAndSwapOutcome,
+		mut replacement: TokenRecord,
+	) -> Result<CompareAndSwapOutcome, StoreError>
+	where
+		F: Fn(&TokenRecord) -> bool,
+	{
+		let lease = self.lease_for(&replacement).await?;
+		let outcome = async {
+			for _ in 0..Self::MAX_TXN_ATTEMPTS {
+				let Some(current) = self.get(&key).await? else {
+					return Ok(CompareAndSwapOutcome::Missing);
+				};
+				let stored = current.record()?;
+
+				if !matches(&stored) {
+					return Ok(mismatch);
+				}
+
+				replacement.version = store::next_version(&stored);
+
+				let put = self.put_op(&replacement, lease)?;
+
+				if self.txn(current.guard(), vec![RequestOp::RequestPut(put)]).await? {
+					return Ok(CompareAndSwapOutcome::Updated);
+				}
+			}
+
+			Err(Self::contention(&key))
+		}
+		.await;
+
+		if !matches!(outcome, Ok(CompareAndSwapOutcome::Updated)) {
+			self.release_leases(&[lease]).await;
+		}
+
+		outcome
+	}
+
+	async fn cas_family_now(
+		&self,
+		family: &TokenFamily,
+		expected_refresh: Option<&str>,
+		records: Vec<TokenRecord>,
+	) -> Result<CompareAndSwapOutcome, StoreError> {
+		let replacements = store::family_replacements(family, records)?;
+
+		if replacements.is_empty() {
+			return Ok(CompareAndSwapOutcome::Missing);
+		}
+
+		let mut leases = Vec::with_capacity(replacements.len());
+
+		for (_, record) in &replacements {
+			match self.lease_for(record).await {
+				Ok(lease) => leases.push(lease),
+				Err(e) => {
+					self.release_leases(&leases).await;
+
+					return Err(e);
+				},
+			}
+		}
+
+		let outcome = async {
+			for _ in 0..Self::MAX_TXN_ATTEMPTS {
+				let mut compare = Vec::new();
+				let mut success = Vec::new();
+
+				for ((key, record), lease) in replacements.iter().zip(&leases) {
+					let Some(current) = self.get(&self.key_for(key)).await? else {
+						return Ok(CompareAndSwapOutcome::Missing);
+					};
+					let stored = current.record()?;
+
+					if stored.refresh_token.as_ref().map(TokenSecret::expose) != expected_refresh {
+						return Ok(CompareAndSwapOutcome::RefreshMismatch);
+					}
+
+					let mut record = record.clone();
+
+					record.version = store::next_version(&stored);
+
+					compare.extend(current.guard());
+					success.push(RequestOp::RequestPut(self.put_op(&record, *lease)?));
+				}
+
+				if self.txn(compare, success).await? {
+					return Ok(CompareAndSwapOutcome::Updated);
+				}
+			}
+
+			Err(Self::contention(&self.key_for(&replacements[0].0)))
+		}
+		.await;
+
+		if !matches!(outcome, Ok(CompareAndSwapOutcome::Updated)) {
+			self.release_leases(&leases).await;
+		}
+
+		outcome
+	}
+
+	async fn revoke_now(
+		&self,
+		key: &str,
+		instant: OffsetDateTime,
+		reason: RevocationReason,
+	) -> Result<Option<TokenRecord>, StoreError> {
+		for _ in 0..Self::MAX_TXN_ATTEMPTS {
+			let Some(current) = self.get(key).await? else {
+				return Ok(None);
+			};
+			let mut record = current.record()?;
+
+			store::revoke_stored(&mut record, instant, reason);
+
+			// The lease follows the record read in this attempt, so a lost race releases it.
+			let lease = self.lease_for(&record).await?;
+			let written = match self.put_op(&record, lease) {
+				Ok(put) => self.txn(current.guard(), vec![RequestOp::RequestPut(put)]).await,
+				Err(e) => Err(e),
+			};
+
+			if matches!(written, Ok(true)) {
+				return Ok(Some(record));
+			}
+
+			self.release_leases(&[lease]).await;
+			written?;
+		}
+
+		Err(Self::contention(key))
+	}
+
+	async fn subtree_now(
+		&self,
+		tenant: &TenantId,
+		prefix: &PrincipalPath,
+		revoke_at: Option<(OffsetDateTime, RevocationReason)>,
+	) -> Result<Vec<TokenRecord>, StoreError> {
+		let mut records = Vec::new();
+
+		for kv in self.scan(&self.tenant_prefix(tenant)).await? {
+			let record = kv.record()?;
+
+			if !StoreKey::new(&record.family, &record.scope)
+				.is_ok_and(|key| key.in_principal_subtree(tenant, prefix))
+			{
+				continue;
+			}
+
+			match revoke_at {
+				Some((instant, reason)) => records.extend(
+					self.revoke_now(&String::from_utf8_lossy(&kv.key_bytes()?), instant, reason)
+						.await?,
+				),
+				None => records.push(record),
+			}
+		}
+
+		Ok(records)
+	}
+
+	fn tenant_prefix(&self, tenant: &TenantId) -> String {
+		format!("{}{}\u{1f}", self.prefix, tenant.as_ref())
+	}
+}
+impl BrokerStore for EtcdStore {
+	fn save(&self, record: TokenRecord) -> StoreFuture<'_, ()> {
+		Box::pin(async move {
+			let lease = self.lease_for(&record).await?;
+			let written = match self.put_op(&record, lease) {
+				Ok(put) => self.call::<_, IgnoredAny>("v3/kv/put", &put).await.map(drop),
+				Err(e) => Err(e),
+			};
+
+			if written.is_err() {
+				self.release_leases(&[lease]).await;
+			}
+
+			written
+		})
+	}
+
+	fn fetch<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+	) -> StoreFuture<'a, Option<TokenRecord>> {
+		Box::pin(async move {
+			let key = self.key_for(&StoreKey::new(family, scope)?);
+
+			self.get(&key).await?.map(|kv| kv.record()).transpose()
+		})
+	}
+
+	fn compare_and_swap_refresh<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+		expected_refresh: Option<&'a str>,
+		replacement: TokenRecord,
+	) -> StoreFuture<'a, CompareAndSwapOutcome> {
+		Box::pin(async move {
+			self.cas_now(
+				self.key_for(&StoreKey::new(family, scope)?),
+				move |stored| {
+					stored.refresh_token.as_ref().map(TokenSecret::expose) == expected_refresh
+				},
+				CompareAndSwapOutcome::RefreshMismatch,
+				replacement,
+			)
+			.await
+		})
+	}
+
+	fn compare_and_swap_version<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+		expected_version: u64,
+		replacement: TokenRecord,
+	) -> StoreFuture<'a, CompareAndSwapOutcome> {
+		Box::pin(async move {
+			self.cas_now(
+				self.key_for(&StoreKey::new(family, scope)?),
+				move |stored| stored.version == expected_version,
+				CompareAndSwapOutcome::VersionMismatch,
+				replacement,
+			)
+			.await
+		})
+	}
+
+	fn revoke<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+		instant: OffsetDateTime,
+		reason: RevocationReason,
+	) -> StoreFuture<'a, Option<TokenRecord>> {
+		Box::pin(async move {
+			self.revoke_now(&self.key_for(&StoreKey::new(family, scope)?), instant, reason).await
+		})
+	}
+
+	fn health_check(&self) -> StoreFuture<'_, ()> {
+		Box::pin(async move {
+			let _: IgnoredAny = self.call("v3/maintenance/status", &serde_json::json!({})).await?;
+
+			Ok(())
+		})
+	}
+
+	fn purge_revoked(&self, cutoff: OffsetDateTime) -> StoreFuture<'_, usize> {
+		Box::pin(async move {
+			let mut purged = 0;
+
+			for kv in self.scan(&self.prefix).await? {
+				if kv.record()?.revoked_at.is_none_or(|revoked| revoked >= cutoff) {
+					continue;
+				}
+
+				let delete =
+					DeleteRangeRequest { key: kv.key.clone(), range_end: None, prev_kv: false };
+
+				// A record rewritten since the scan is left for the next purge.
+				if self.txn(kv.guard(), vec![RequestOp::RequestDeleteRange(delete)]).await? {
+					purged += 1;
+				}
+			}
+
+			Ok(purged)
+		})
+	}
+
+	fn list_principal_subtree<'a>(
+		&'a self,
+		tenant: &'a TenantId,
+		prefix: &'a PrincipalPath,
+	) -> StoreFuture<'a, Vec<TokenRecord>> {
+		Box::pin(self.subtree_now(tenant, prefix, None))
+	}
+
+	fn revoke_principal_subtree<'a>(
+		&'a self,
+		tenant: &'a TenantId,
+		prefix: &'a PrincipalPath,
+		instant: OffsetDateTime,
+		reason: RevocationReason,
+	) -> StoreFuture<'a, Vec<TokenRecord>> {
+		Box::pin(self.subtree_now(tenant, prefix, Some((instant, reason))))
+	}
+
+	fn list_records<'a>(
+		&'a self,
+		after: Option<&'a str>,
+		limit: usize,
+	) -> StoreFuture<'a, RecordPage> {
+		Box::pin(async move {
+			let mut start = self.prefix.clone().into_bytes();
+
+			if let Some(after) = after {
+				start.extend(after.as_bytes());
+				start.push(0);
+			}
+
+			// One extra record tells the page whether another one follows.
+			let page = self
+				.range(&start, Some(&prefix_end(self.prefix.as_bytes())), limit.max(1) + 1)
+				.await?;
+			let entries = page
+				.kvs
+				.iter()
+				.map(|kv| {
+					let key = kv.key_bytes()?;
+					let cursor = String::from_utf8_lossy(&key[self.prefix.len()..]).into_owned();
+
+					Ok((cursor, kv.record()?))
+				})
+				.collect::<Result<Vec<_>, StoreError>>()?;
+
+			Ok(RecordPage::collect(entries, limit))
+		})
+	}
+
+	fn delete_tenant<'a>(&'a self, tenant: &'a TenantId) -> StoreFuture<'a, Vec<TokenRecord>> {
+		Box::pin(async move {
+			let prefix = self.tenant_prefix(tenant);
+			let request = DeleteRangeRequest {
+				key: STANDARD.encode(&prefix),
+				range_end: Some(STANDARD.encode(prefix_end(prefix.as_bytes()))),
+				prev_kv: true,
+			};
+			let response: DeleteRangeResponse = self.call("v3/kv/deleterange", &request).await?;
+
+			response.prev_kvs.iter().map(KeyValue::record).collect()
+		})
+	}
+
+	fn compare_and_swap_family<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		expected_refresh: Option<&'a str>,
+		records: Vec<TokenRecord>,
+	) -> StoreFuture<'a, CompareAndSwapOutcome> {
+		Box::pin(self.cas_family_now(family, expected_refresh, records))
+	}
+}
+
+/// etcd user the store authenticates as, with its current auth token.
+struct EtcdAuth {
+	name: String,
+	password: String,
+	token: RwLock<Option<String>>,
+}
+impl Debug for EtcdAuth {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		f.debug_struct("EtcdAuth")
+			.field("name", &self.name)
+			.field("password", &"<redacted>")
+			.field("token_set", &self.token.read().is_some())
+			.finish()
+	}
+}
+
+#[derive(Serialize)]
+struct AuthenticateRequest<'a> {
+	name: &'a str,
+	password: &'a str,
+}
+
+#[derive(Deserialize)]
+struct AuthenticateResponse {
+	token: String,
+}
+
+#[derive(Serialize)]
+struct RangeRequest {
+	key: String,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	range_end: Option<String>,
+	limit: i64,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct RangeResponse {
+	kvs: Vec<KeyValue>,
+	more: bool,
+}
+
+#[derive(Deserialize)]
+struct KeyValue {
+	key: String,
+	#[serde(default)]
+	value: String,
+	#[serde(default, deserialize_with = "int64")]
+	mod_revision: i64,
+}
+impl KeyValue {
+	fn key_bytes(&self) -> Result<Vec<u8>, StoreError> {
+		STANDARD.decode(&self.key).map_err(|e| StoreError::Serialization {
+			message: format!("etcd returned a malformed key: {e}"),
+		})
+	}
+
+	fn record(&self) -> Result<TokenRecord, StoreError> {
+		let value = STANDARD.decode(&self.value).map_err(|e| StoreError::Serialization {
+			message: format!("etcd returned a malformed value: {e}"),
+		})?;
+
+		serde_json::from_slice(&value).map_err(|e| StoreError::Serialization {
+			message: format!("Failed to parse etcd record: {e}"),
+		})
+	}
+
+	/// Transaction guard that holds only while the key is untouched since this read.
+	fn guard(&self) -> Vec<Compare> {
+		vec![
+			Compare {
+				key: self.key.clone(),
+				target: "MOD",
+				result: "EQUAL",
+				mod_revision: Some(self.mod_revision),
+				value: None,
+			},
+			Compare {
+				key: self.key.clone(),
+				target: "VALUE",
+				result: "EQUAL",
+				mod_revision: None,
+				value: Some(self.value.clone()),
+			},
+		]
+	}
+}
+
+#[derive(Serialize)]
+struct Compare {
+	key: String,
+	target: &'static str,
+	result: &'static str,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	mod_revision: Option<i64>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	value: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum RequestOp {
+	RequestPut(PutRequest),
+	RequestDeleteRange(DeleteRangeRequest),
+}
+
+#[derive(Serialize)]
+struct PutRequest {
+	key: String,
+	value: String,
+	#[serde(skip_serializing_if = "is_zero")]
+	lease: i64,
+}
+
+#[derive(Serialize)]
+struct DeleteRangeRequest {
+	key: String,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	range_end: Option<String>,
+	prev_kv: bool,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct DeleteRangeResponse {
+	prev_kvs: Vec<KeyValue>,
+}
+
+#[derive(Serialize)]
+struct TxnRequest {
+	compare: Vec<Compare>,
+	success: Vec<RequestOp>,
+}
+
+// The gateway omits `succeeded` when the comparison fails.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct TxnResponse {
+	succeeded: bool,
+}
+
+#[derive(Serialize)]
+struct LeaseGrantRequest {
+	#[serde(rename = "TTL")]
+	ttl: i64,
+}
+
+#[derive(Serialize)]
+struct LeaseRevokeRequest {
+	#[serde(rename = "ID")]
+	id: i64,
+}
+
+#[derive(Deserialize)]
+struct LeaseGrantResponse {
+	#[serde(rename = "ID", deserialize_with = "int64")]
+	id: i64,
+}
+
+fn parse_response<Resp>(path: &str, status: StatusCode, bytes: &[u8]) -> Result<Resp, StoreError>
+where
+	Resp: DeserializeOwned,
+{
+	if !status.is_success() {
+		return Err(StoreError::Backend {
+			message: format!("etcd {path} returned {status}: {}", String::from_utf8_lossy(bytes)),
+		});
+	}
+
+	serde_json::from_slice(bytes).map_err(|e| StoreError::Serialization {
+		message: format!("Failed to parse etcd {path} response: {e}"),
+	})
+}
+
+fn is_zero(value: &i64) -> bool {
+	*value == 0
+}
+
+/// The gateway encodes 64-bit integers as JSON strings.
+fn int64<'de, D>(deserializer: D) -> Result<i64, D::Error>
+where
+	D: serde::Deserializer<'de>,
+{
+	#[derive(Deserialize)]
+	#[serde(untagged)]
+	enum Int64 {
+		Text(String),
+		Number(i64),
+	}
+
+	match Int64::deserialize(deserializer)? {
+		Int64::Text(text) => text.parse().map_err(serde::de::Error::custom),
+		Int64::Number(number) => Ok(number),
+	}
+}
+
+/// Smallest key greater than every key that starts with `prefix` (etcd's `range_end`).
+fn prefix_end(prefix: &[u8]) -> Vec<u8> {
+	let mut end = prefix.to_vec();
+
+	while let Some(last) = end.pop() {
+		if last < u8::MAX {
+			end.push(last + 1);
+
+			return end;
+		}
+	}
+
+	// `\0` ranges to the end of the keyspace.
+	vec![0]
+}
+
+#[cfg(test)]
+mod tests {
+	// crates.io
+	use httpmock::prelude::*;
+	use tokio::runtime::Runtime;
+	// self
+	use super::*;
+	use crate::auth::{PrincipalId, ProviderId};
+
+	fn build_record(access: &str, refresh: &str) -> TokenRecord {
+		let family = TokenFamily::new(
+			TenantId::new("tenant-etcd").expect("Tenant fixture should be valid."),
+			PrincipalId::new("principal-etcd").expect("Principal fixture should be valid."),
+		)
+		.with_provider(
+			ProviderId::new("provider-etcd").expect("Provider fixture should be valid."),
+		);
+		let now = OffsetDateTime::now_utc();
+
+		TokenRecord::builder(
+			family,
+			ScopeSet::new(["read"]).expect("Scope fixture should be valid."),
+		)
+		.access_token(access)
+		.refresh_token(refresh)
+		.issued_at(now)
+		.expires_at(now + Duration::hours(1))
+		.build()
+		.expect("Token record fixture should build successfully.")
+	}
+
+	#[test]
+	fn prefix_end_increments_the_last_byte() {
+		assert_eq!(prefix_end(b"/tokens/"), b"/tokens0");
+		assert_eq!(prefix_end(b"a\xff"), b"b");
+		assert_eq!(prefix_end(b"\xff"), vec![0]);
+	}
+
+	#[test]
+	fn refresh_cas_guards_on_revision_and_value() {
+		let server = MockServer::start();
+		let store =
+			EtcdStore::new(Url::parse(&server.base_url()).expect("Mock server URL should parse."));
+		let current = build_record("access-old", "refresh-old");
+		let key = store.record_key(&current).expect("Record key should build.");
+		let value = STANDARD
+			.encode(serde_json::to_vec(&current).expect("Record fixture should serialize."));
+		let range = server.mock(|when, then| {
+			when.method(POST).path("/v3/kv/range");
+			then.status(200).json_body(serde_json::json!({
+				"kvs": [{ "key": STANDARD.encode(&key), "value": value, "mod_revision": "7" }],
+			}));
+		});
+		let txn = server.mock(|when, then| {
+			when.method(POST)
+				.path("/v3/kv/txn")
+				.body_includes("\"target\":\"MOD\",\"result\":\"EQUAL\",\"mod_revision\":7")
+				.body_includes(format!(
+					"\"target\":\"VALUE\",\"result\":\"EQUAL\",\"value\":\"{value}\""
+				));
+			then.status(200).json_body(serde_json::json!({ "succeeded": true }));
+		});
+		let rt = Runtime::new().expect("Failed to build Tokio runtime for etcd store test.");
+		let replacement = build_record("access-new", "refresh-new");
+		let outcome = rt.block_on(store.compare_and_swap_refresh(
+			&current.family,
+			&current.scope,
+			Some("refresh-old"),
+			replacement.clone(),
+		));
+
+		assert_eq!(outcome.expect("CAS should succeed."), CompareAndSwapOutcome::Updated);
+
+		let mismatch = rt.block_on(store.compare_and_swap_refresh(
+			&current.family,
+			&current.scope,
+			Some("refresh-stale"),
+			replacement,
+		));
+
+		assert_eq!(
+			mismatch.expect("Stale CAS should still succeed."),
+			CompareAndSwapOutcome::RefreshMismatch
+		);
+
+		range.assert_calls(2);
+		txn.assert_calls(1);
+	}
+
+	#[test]
+	fn contended_writes_grant_one_lease_and_revoke_it() {
+		let server = MockServer::start();
+		let store =
+			EtcdStore::new(Url::parse(&server.base_url()).expect("Mock server URL should parse."));
+		let now = OffsetDateTime::now_utc();
+		let current = TokenRecord::builder(
+			build_record("access", "refresh").family,
+			ScopeSet::new(["read"]).expect("Scope fixture should be valid."),
+		)
+		.access_token("access-old")
+		.issued_at(now)
+		.expires_at(now + Duration::hours(1))
+		.build()
+		.expect("Client credentials record fixture should build.");
+		let key = store.record_key(&current).expect("Record key should build.");
+		let value = STANDARD
+			.encode(serde_json::to_vec(&current).expect("Record fixture should serialize."));
+		let range = server.mock(|when, then| {
+			when.method(POST).path("/v3/kv/range");
+			then.status(200).json_body(serde_json::json!({
+				"kvs": [{ "key": STANDARD.encode(&key), "value": value, "mod_revision": "3" }],
+			}));
+		});
+		let grant = server.mock(|when, then| {
+			when.method(POST).path("/v3/lease/grant");
+			then.status(200).json_body(serde_json::json!({ "ID": "42", "TTL": "3900" }));
+		});
+		let txn = server.mock(|when, then| {
+			when.method(POST).path("/v3/kv/txn").body_includes("\"lease\":42");
+			then.status(200).json_body(serde_json::json!({}));
+		});
+		let revoke = server.mock(|when, then| {
+			when.method(POST).path("/v3/lease/revoke").json_body(serde_json::json!({ "ID": 42 }));
+			then.status(200).json_body(serde_json::json!({}));
+		});
+		let rt = Runtime::new().expect("Failed to build Tokio runtime for etcd store test.");
+		let mut replacement = current.clone();
+
+		replacement.access_token = TokenSecret::new("access-new");
+
+		let err = rt
+			.block_on(store.compare_and_swap_version(
+				&current.family,
+				&current.scope,
+				current.version,
+				replacement,
+			))
+			.expect_err("A write that keeps losing should fail.");
+
+		assert!(matches!(err, StoreError::Backend { .. }));
+
+		range.assert_calls(EtcdStore::MAX_TXN_ATTEMPTS);
+		txn.assert_calls(EtcdStore::MAX_TXN_ATTEMPTS);
+		grant.assert_calls(1);
+		revoke.assert_calls(1);
+	}
+
+	#[test]
+	fn credentials_authenticate_once_and_renew_rejected_tokens() {
+		let server = MockServer::start();
+		let store =
+			EtcdStore::new(Url::parse(&server.base_url()).expect("Mock server URL should parse."))
+				.with_credentials("broker", "s3cret");
+		let record = build_record("access", "refresh");
+		let mut authenticate = server.mock(|when, then| {
+			when.method(POST)
+				.path("/v3/auth/authenticate")
+				.json_body(serde_json::json!({ "name": "broker", "password": "s3cret" }));
+			then.status(200).json_body(serde_json::json!({ "token": "token-1" }));
+		});
+		let mut first = server.mock(|when, then| {
+			when.method(POST).path("/v3/kv/range").header("authorization", "token-1");
+			then.status(200).json_body(serde_json::json!({}));
+		});
+		let rt = Runtime::new().expect("Failed to build Tokio runtime for etcd store test.");
+
+		for _ in 0..2 {
+			rt.block_on(store.fetch(&record.family, &record.scope))
+				.expect("Authenticated fetch should succeed.");
+		}
+
+		authenticate.assert_calls(1);
+		first.assert_calls(2);
+		assert!(!format!("{store:?}").contains("s3cret"));
+
+		first.delete();
+		authenticate.delete();
+		first = server.mock(|when, then| {
+			when.method(POST).path("/v3/kv/range").header("authorization", "token-1");
+			then.status(401).body("etcdserver: invalid auth token");
+		});
+		authenticate = server.mock(|when, then| {
+			when.method(POST).path("/v3/auth/authenticate");
+			then.status(200).json_body(serde_json::json!({ "token": "token-2" }));
+		});
+
+		let renewed = server.mock(|when, then| {
+			when.method(POST).path("/v3/kv/range").header("authorization", "token-2");
+			then.status(200).json_body(serde_json::json!({}));
+		});
+
+		rt.block_on(store.fetch(&record.family, &record.scope))
+			.expect("Fetch should succeed after renewing the token.");
+
+		first.assert_calls(1);
+		authenticate.assert_calls(1);
+		renewed.assert_calls(1);
+	}
+}
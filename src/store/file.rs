@@ -1,59 +1,379 @@
 //! Simple file-backed [`BrokerStore`] for lightweight deployments and bots.
 
+#[cfg(feature = "ring")] mod crypto;
+
+#[cfg(feature = "ring")] pub use crypto::SnapshotKey;
+
 // std
 use std::{
-	fs::{self, File},
-	io::Write,
+	fs::{self, File, OpenOptions},
+	io::{ErrorKind, Write},
 	path::{Path, PathBuf},
 };
+// crates.io
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
 // self
 use crate::{
 	_prelude::*,
-	auth::{ScopeSet, TokenFamily, TokenRecord, TokenSecret},
-	store::{BrokerStore, CompareAndSwapOutcome, StoreError, StoreFuture, StoreKey},
+	auth::{
+		PrincipalPath, ProviderId, RevocationReason, ScopeSet, TenantId, TokenFamily, TokenRecord,
+		TokenSecret,
+	},
+	store::{
+		self, BrokerStore, CompareAndSwapOutcome, PreparedWrite, RecordPage, StoreError,
+		StoreFuture, StoreKey, StoreKeyRepr, Transaction,
+	},
 };
+#[cfg(feature = "ring")] use crypto::SnapshotCipher;
+
+/// Records loaded from a snapshot written before keys required a provider, keyed by page cursor.
+type LegacyRecords = BTreeMap<String, (StoreKeyRepr, TokenRecord)>;
+
+/// Leading bytes of a snapshot or staged-write file sealed under a [`SnapshotKey`].
+const SEALED_MAGIC: &[u8] = b"O2BSNAP1";
 
-/// Persists broker records to a JSON file after each mutation.
+/// Persists broker records to a JSON snapshot plus an append-only journal.
+///
+/// Each mutation appends one line to a journal next to the snapshot (`<path>` with a `journal`
+/// extension) instead of rewriting the snapshot, so saves cost O(1). Once the journal holds
+/// [`FileStore::DEFAULT_COMPACTION_THRESHOLD`] entries (see
+/// [`FileStore::with_compaction_threshold`]), and whenever a store with a non-empty journal is
+/// opened, the records are folded into a fresh snapshot through an atomic rename and the journal
+/// is truncated. A journal line torn by a crash is dropped on replay.
+///
+/// Two-phase writes are staged in a sidecar file next to the snapshot (`<path>` with a
+/// `pending` extension) so records minted right before a crash survive until reconciled.
+/// With the `ring` feature, [`FileStore::open_encrypted`] seals every file with AES-256-GCM.
+///
+/// Snapshots written before store keys carried a provider still open. Records whose family
+/// names no provider cannot be fetched or written; they stay on disk and are returned by
+/// [`BrokerStore::list_records`] so [`store::migrate`] can move them into a provider namespace
+/// with [`MigrateOptions::with_default_provider`](store::MigrateOptions::with_default_provider).
 #[derive(Clone, Debug)]
 pub struct FileStore {
 	path: PathBuf,
 	inner: Arc<RwLock<HashMap<StoreKey, TokenRecord>>>,
+	legacy: Arc<RwLock<LegacyRecords>>,
+	pending: Arc<RwLock<BTreeMap<String, TokenRecord>>>,
+	journal: Arc<Mutex<Journal>>,
+	compaction_threshold: usize,
+	#[cfg(feature = "ring")]
+	cipher: Option<Arc<SnapshotCipher>>,
 }
 impl FileStore {
+	/// Number of journal entries after which the next mutation compacts the store.
+	pub const DEFAULT_COMPACTION_THRESHOLD: usize = 1_024;
+
 	/// Opens (or creates) a store at the provided path, eagerly loading existing data.
+	///
+	/// Snapshots sealed by [`FileStore::open_encrypted`] are rejected rather than misread.
 	pub fn open(path: impl Into<PathBuf>) -> Result<Self, StoreError> {
 		let path = path.into();
 
 		Self::ensure_parent_exists(&path)?;
 
-		let snapshot = if path.exists() { Self::load_snapshot(&path)? } else { HashMap::new() };
+		let (mut snapshot, mut legacy) = match Self::read_file(&path)? {
+			Some(bytes) => Self::parse_snapshot(&path, &Self::ensure_plaintext(&path, bytes)?)?,
+			None => Default::default(),
+		};
+		let journal_path = Self::journal_path_for(&path);
+		let journal = Self::read_file(&journal_path)?;
+
+		if let Some(bytes) = &journal {
+			Self::replay_journal(&journal_path, bytes, &mut snapshot, &mut legacy, |line| {
+				Self::ensure_plaintext(&journal_path, line)
+			})?;
+		}
+
+		let pending_path = Self::pending_path_for(&path);
+		let pending = match Self::read_file(&pending_path)? {
+			Some(bytes) =>
+				Self::parse_pending(&pending_path, &Self::ensure_plaintext(&pending_path, bytes)?)?,
+			None => BTreeMap::new(),
+		};
+		let store = Self {
+			path,
+			inner: Arc::new(RwLock::new(snapshot)),
+			legacy: Arc::new(RwLock::new(legacy)),
+			pending: Arc::new(RwLock::new(pending)),
+			journal: Default::default(),
+			compaction_threshold: Self::DEFAULT_COMPACTION_THRESHOLD,
+			#[cfg(feature = "ring")]
+			cipher: None,
+		};
+
+		if journal.is_some() {
+			store.compact()?;
+		}
 
-		Ok(Self { path, inner: Arc::new(RwLock::new(snapshot)) })
+		Ok(store)
 	}
 
-	fn load_snapshot(path: &Path) -> Result<HashMap<StoreKey, TokenRecord>, StoreError> {
-		if !path.exists() {
-			return Ok(HashMap::new());
+	/// Opens (or creates) a store whose snapshot and staged writes are encrypted at rest.
+	///
+	/// The AES-256-GCM key is derived from `key` (a password or key file) under a per-store salt
+	/// kept in the file header. Existing plaintext files are sealed right away, so switching a
+	/// store to encryption needs no separate migration; a wrong key fails with
+	/// [`StoreError::Backend`] instead of loading an empty store.
+	#[cfg(feature = "ring")]
+	pub fn open_encrypted(path: impl Into<PathBuf>, key: &SnapshotKey) -> Result<Self, StoreError> {
+		let path = path.into();
+
+		Self::ensure_parent_exists(&path)?;
+
+		let snapshot_bytes = Self::read_file(&path)?;
+		let pending_path = Self::pending_path_for(&path);
+		let pending_bytes = Self::read_file(&pending_path)?;
+		let fresh = snapshot_bytes.is_none();
+		let cipher = match &snapshot_bytes {
+			Some(bytes) if bytes.starts_with(SEALED_MAGIC) =>
+				SnapshotCipher::for_sealed(key, bytes)?,
+			_ => SnapshotCipher::generate(key)?,
+		};
+		let mut reseal = false;
+		let mut open = |bytes: Vec<u8>| -> Result<Vec<u8>, StoreError> {
+			if cipher.matches(&bytes) {
+				return cipher.open(&bytes);
+			}
+
+			reseal = true;
+
+			if bytes.starts_with(SEALED_MAGIC) {
+				SnapshotCipher::for_sealed(key, &bytes)?.open(&bytes)
+			} else {
+				Ok(bytes)
+			}
+		};
+		let (mut snapshot, mut legacy) = match snapshot_bytes {
+			Some(bytes) => Self::parse_snapshot(&path, &open(bytes)?)?,
+			None => Default::default(),
+		};
+		let journal_path = Self::journal_path_for(&path);
+		let journal = Self::read_file(&journal_path)?;
+
+		if let Some(bytes) = &journal {
+			Self::replay_journal(&journal_path, bytes, &mut snapshot, &mut legacy, &mut open)?;
 		}
 
-		let metadata = path.metadata().map_err(|e| StoreError::Backend {
-			message: format!("Failed to inspect {}: {e}", path.display()),
-		})?;
+		let pending = match pending_bytes {
+			Some(bytes) => Self::parse_pending(&pending_path, &open(bytes)?)?,
+			None => BTreeMap::new(),
+		};
+		let store = Self {
+			path,
+			inner: Arc::new(RwLock::new(snapshot)),
+			legacy: Arc::new(RwLock::new(legacy)),
+			pending: Arc::new(RwLock::new(pending)),
+			journal: Default::default(),
+			compaction_threshold: Self::DEFAULT_COMPACTION_THRESHOLD,
+			cipher: Some(Arc::new(cipher)),
+		};
+
+		// Fold the journal, seal plaintext left by an unencrypted store (or a file under another
+		// salt), and pin the salt of a new store on disk right away.
+		if reseal || fresh || journal.is_some() {
+			store.compact()?;
+		}
+		if reseal {
+			let pending = store.pending.read();
+
+			if !pending.is_empty() {
+				store.persist_pending_locked(&pending)?;
+			}
+		}
+
+		Ok(store)
+	}
+
+	/// Runs `f` against every record under the store lock, applying its staged writes atomically
+	/// when it returns `Ok`.
+	///
+	/// All writes of a transaction land in one journal entry, so a crash leaves either none or all
+	/// of them on disk.
+	pub fn transaction<T, F>(&self, f: F) -> Result<T, StoreError>
+	where
+		F: FnOnce(&mut Transaction<'_>) -> Result<T, StoreError>,
+	{
+		let mut guard = self.inner.write();
+		let (value, writes) = {
+			let mut txn = Transaction::new(&*guard);
+			let value = f(&mut txn)?;
+
+			(value, txn.into_writes())
+		};
+
+		if writes.is_empty() {
+			return Ok(value);
+		}
+
+		let keys = writes.iter().map(|(key, _)| key.clone()).collect::<Vec<_>>();
+
+		for (key, staged) in writes {
+			match staged {
+				Some(record) => guard.insert(key, record),
+				None => guard.remove(&key),
+			};
+		}
+
+		self.append_locked(&guard, &keys)?;
+
+		Ok(value)
+	}
+
+	/// Compacts once the journal holds `batches` entries instead of
+	/// [`FileStore::DEFAULT_COMPACTION_THRESHOLD`] (`0` is treated as 1).
+	pub fn with_compaction_threshold(mut self, batches: usize) -> Self {
+		self.compaction_threshold = batches.max(1);
+
+		self
+	}
+
+	/// Folds the journal into a fresh snapshot and truncates it.
+	///
+	/// Runs automatically once the journal reaches the compaction threshold and on open; call it
+	/// directly before copying the snapshot elsewhere (e.g. for backups).
+	pub fn compact(&self) -> Result<(), StoreError> {
+		let guard = self.inner.read();
+
+		self.compact_locked(&guard, &mut self.journal.lock())
+	}
+
+	fn pending_path_for(path: &Path) -> PathBuf {
+		path.with_extension("pending")
+	}
+
+	fn journal_path_for(path: &Path) -> PathBuf {
+		path.with_extension("journal")
+	}
+
+	fn tmp_path_for(path: &Path) -> PathBuf {
+		let mut name = path.file_name().unwrap_or_default().to_os_string();
+
+		name.push(".tmp");
+
+		path.with_file_name(name)
+	}
 
-		if metadata.len() == 0 {
-			return Ok(HashMap::new());
+	/// Reads `path`, treating a missing or empty file as no data.
+	fn read_file(path: &Path) -> Result<Option<Vec<u8>>, StoreError> {
+		if !path.exists() {
+			return Ok(None);
 		}
 
 		let bytes = fs::read(path).map_err(|e| StoreError::Backend {
 			message: format!("Failed to read {}: {e}", path.display()),
 		})?;
 
-		let entries: Vec<(StoreKey, TokenRecord)> =
-			serde_json::from_slice(&bytes).map_err(|e| StoreError::Serialization {
+		Ok((!bytes.is_empty()).then_some(bytes))
+	}
+
+	fn ensure_plaintext(path: &Path, bytes: Vec<u8>) -> Result<Vec<u8>, StoreError> {
+		if bytes.starts_with(SEALED_MAGIC) {
+			return Err(StoreError::Backend {
+				message: format!(
+					"{} is encrypted; open it with FileStore::open_encrypted",
+					path.display()
+				),
+			});
+		}
+
+		Ok(bytes)
+	}
+
+	fn parse_pending(
+		path: &Path,
+		bytes: &[u8],
+	) -> Result<BTreeMap<String, TokenRecord>, StoreError> {
+		serde_json::from_slice(bytes).map_err(|e| StoreError::Serialization {
+			message: format!("Failed to parse {}: {e}", path.display()),
+		})
+	}
+
+	fn parse_snapshot(
+		path: &Path,
+		bytes: &[u8],
+	) -> Result<(HashMap<StoreKey, TokenRecord>, LegacyRecords), StoreError> {
+		let entries: Vec<(StoreKeyRepr, TokenRecord)> =
+			serde_json::from_slice(bytes).map_err(|e| StoreError::Serialization {
 				message: format!("Failed to parse {}: {e}", path.display()),
 			})?;
+		let mut snapshot = HashMap::new();
+		let mut legacy = BTreeMap::new();
 
-		Ok(entries.into_iter().collect())
+		for (key, record) in entries {
+			Self::apply_entry(&mut snapshot, &mut legacy, key, Some(record))?;
+		}
+
+		Ok((snapshot, legacy))
+	}
+
+	/// Stores or removes one snapshot or journal entry, setting aside keys without a provider.
+	fn apply_entry(
+		snapshot: &mut HashMap<StoreKey, TokenRecord>,
+		legacy: &mut LegacyRecords,
+		key: StoreKeyRepr,
+		record: Option<TokenRecord>,
+	) -> Result<(), StoreError> {
+		if key.provider.is_none() && key.family.provider.is_none() {
+			let cursor = key.page_cursor();
+
+			match record {
+				Some(record) => legacy.insert(cursor, (key, record)),
+				None => legacy.remove(&cursor),
+			};
+
+			return Ok(());
+		}
+
+		let key = StoreKey::try_from(key)?;
+
+		match record {
+			Some(record) => snapshot.insert(key, record),
+			None => snapshot.remove(&key),
+		};
+
+		Ok(())
+	}
+
+	/// Applies every complete journal line to `snapshot`; a trailing line without a newline was
+	/// torn by a crash and is dropped.
+	fn replay_journal<F>(
+		path: &Path,
+		bytes: &[u8],
+		snapshot: &mut HashMap<StoreKey, TokenRecord>,
+		legacy: &mut LegacyRecords,
+		mut open: F,
+	) -> Result<(), StoreError>
+	where
+		F: FnMut(Vec<u8>) -> Result<Vec<u8>, StoreError>,
+	{
+		let invalid = |e: &dyn Display| StoreError::Serialization {
+			message: format!("Failed to parse {}: {e}", path.display()),
+		};
+
+		for line in bytes.split_inclusive(|byte| *byte == b'\n') {
+			let Some(line) = line.strip_suffix(b"\n") else { break };
+
+			if line.is_empty() {
+				continue;
+			}
+
+			// Plaintext entries are JSON arrays; sealed entries are base64 and never start with
+			// `[`.
+			let line = if line.starts_with(b"[") {
+				line.to_vec()
+			} else {
+				URL_SAFE_NO_PAD.decode(line).map_err(|e| invalid(&e))?
+			};
+			let batch: Vec<(StoreKeyRepr, Option<TokenRecord>)> =
+				serde_json::from_slice(&open(line)?).map_err(|e| invalid(&e))?;
+
+			for (key, record) in batch {
+				Self::apply_entry(snapshot, legacy, key, record)?;
+			}
+		}
+
+		Ok(())
 	}
 
 	fn ensure_parent_exists(path: &Path) -> Result<(), StoreError> {
@@ -66,23 +386,113 @@ impl FileStore {
 	}
 
 	fn persist_locked(&self, contents: &HashMap<StoreKey, TokenRecord>) -> Result<(), StoreError> {
-		Self::ensure_parent_exists(&self.path)?;
-
-		let snapshot: Vec<_> = contents.iter().collect();
+		let legacy = self.legacy.read();
+		let snapshot = contents
+			.iter()
+			.map(|(key, record)| (SnapshotEntryKey::Keyed(key), record))
+			.chain(legacy.values().map(|(key, record)| (SnapshotEntryKey::Legacy(key), record)))
+			.collect::<Vec<_>>();
 		let serialized =
 			serde_json::to_vec_pretty(&snapshot).map_err(|e| StoreError::Serialization {
 				message: format!("Failed to serialize store snapshot: {e}"),
 			})?;
-		let mut tmp_path = self.path.clone();
 
-		tmp_path.set_extension("tmp");
+		Self::write_atomically(&self.path, &self.seal(serialized)?)
+	}
+
+	/// Journals the current value of every key in `keys` (`null` for removed records), compacting
+	/// instead once the journal is due.
+	///
+	/// Callers apply the mutation to `contents` first, so a compaction already includes it.
+	fn append_locked<'k, I>(
+		&self,
+		contents: &HashMap<StoreKey, TokenRecord>,
+		keys: I,
+	) -> Result<(), StoreError>
+	where
+		I: IntoIterator<Item = &'k StoreKey>,
+	{
+		let mut journal = self.journal.lock();
+
+		if journal.batches >= self.compaction_threshold {
+			return self.compact_locked(contents, &mut journal);
+		}
+
+		let batch = keys.into_iter().map(|key| (key, contents.get(key))).collect::<Vec<_>>();
+		let serialized = serde_json::to_vec(&batch).map_err(|e| StoreError::Serialization {
+			message: format!("Failed to serialize journal entry: {e}"),
+		})?;
+		let mut line = self.seal_line(serialized)?;
+
+		line.push(b'\n');
+
+		journal.append(&Self::journal_path_for(&self.path), &line)
+	}
+
+	fn compact_locked(
+		&self,
+		contents: &HashMap<StoreKey, TokenRecord>,
+		journal: &mut Journal,
+	) -> Result<(), StoreError> {
+		self.persist_locked(contents)?;
+
+		// A crash before the journal is removed only replays records the snapshot already holds.
+		journal.file = None;
+		journal.batches = 0;
+
+		let path = Self::journal_path_for(&self.path);
+
+		match fs::remove_file(&path) {
+			Err(e) if e.kind() != ErrorKind::NotFound => Err(StoreError::Backend {
+				message: format!("Failed to truncate {}: {e}", path.display()),
+			}),
+			_ => Ok(()),
+		}
+	}
+
+	fn persist_pending_locked(
+		&self,
+		pending: &BTreeMap<String, TokenRecord>,
+	) -> Result<(), StoreError> {
+		let serialized = serde_json::to_vec_pretty(pending).map_err(|e| {
+			StoreError::Serialization { message: format!("Failed to serialize staged writes: {e}") }
+		})?;
+
+		Self::write_atomically(&Self::pending_path_for(&self.path), &self.seal(serialized)?)
+	}
+
+	fn seal(&self, plaintext: Vec<u8>) -> Result<Vec<u8>, StoreError> {
+		#[cfg(feature = "ring")]
+		if let Some(cipher) = &self.cipher {
+			return cipher.seal(&plaintext);
+		}
+
+		Ok(plaintext)
+	}
+
+	/// Seals a journal line under the snapshot key; sealed lines are base64 so they stay
+	/// newline-free.
+	fn seal_line(&self, plaintext: Vec<u8>) -> Result<Vec<u8>, StoreError> {
+		#[cfg(feature = "ring")]
+		if let Some(cipher) = &self.cipher {
+			return Ok(URL_SAFE_NO_PAD.encode(cipher.seal(&plaintext)?).into_bytes());
+		}
+
+		Ok(plaintext)
+	}
+
+	pub(crate) fn write_atomically(path: &Path, bytes: &[u8]) -> Result<(), StoreError> {
+		Self::ensure_parent_exists(path)?;
+
+		// Keep the full file name so the snapshot and its sidecars never share a temp file.
+		let tmp_path = Self::tmp_path_for(path);
 
 		{
 			let mut file = File::create(&tmp_path).map_err(|e| StoreError::Backend {
 				message: format!("Failed to create {}: {e}", tmp_path.display()),
 			})?;
 
-			file.write_all(&serialized).map_err(|e| StoreError::Backend {
+			file.write_all(bytes).map_err(|e| StoreError::Backend {
 				message: format!("Failed to write {}: {e}", tmp_path.display()),
 			})?;
 			file.sync_all().map_err(|e| StoreError::Backend {
@@ -90,13 +500,64 @@ impl FileStore {
 			})?;
 		}
 
-		fs::rename(&tmp_path, &self.path).map_err(|e| StoreError::Backend {
-			message: format!("Failed to replace {}: {e}", self.path.display()),
+		fs::rename(&tmp_path, path).map_err(|e| StoreError::Backend {
+			message: format!("Failed to replace {}: {e}", path.display()),
 		})
 	}
 
-	fn make_key(family: &TokenFamily, scope: &ScopeSet) -> StoreKey {
-		StoreKey::new(family, scope)
+	fn health_path_for(path: &Path) -> PathBuf {
+		path.with_extension("health")
+	}
+
+	/// Writes, syncs, and removes a probe file next to the snapshot, surfacing permission, mount,
+	/// and out-of-space failures before a real persist hits them.
+	fn write_probe(&self) -> Result<(), StoreError> {
+		let probe = Self::health_path_for(&self.path);
+		let backend = |action: &str, e: std::io::Error| StoreError::Backend {
+			message: format!("Failed to {action} health probe {}: {e}", probe.display()),
+		};
+
+		Self::ensure_parent_exists(&probe)?;
+
+		{
+			let mut file = File::create(&probe).map_err(|e| backend("create", e))?;
+
+			file.write_all(OffsetDateTime::now_utc().unix_timestamp().to_string().as_bytes())
+				.map_err(|e| backend("write", e))?;
+			file.sync_all().map_err(|e| backend("sync", e))?;
+		}
+
+		fs::remove_file(&probe).map_err(|e| backend("remove", e))
+	}
+
+	/// Replaces the record at `key` when `matches` accepts it, reporting `mismatch` otherwise.
+	fn cas_now<F>(
+		&self,
+		key: StoreKey,
+		matches: F,
+		mismatch: CompareAndSwapOutcome,
+		mut replacement: TokenRecord,
+	) -> Result<CompareAndSwapOutcome, StoreError>
+	where
+		F: FnOnce(&TokenRecord) -> bool,
+	{
+		let mut guard = self.inner.write();
+		let outcome = match guard.get(&key) {
+			Some(existing) if matches(existing) => {
+				replacement.version = store::next_version(existing);
+
+				CompareAndSwapOutcome::Updated
+			},
+			Some(_) => mismatch,
+			None => CompareAndSwapOutcome::Missing,
+		};
+
+		if matches!(outcome, CompareAndSwapOutcome::Updated) {
+			guard.insert(key.clone(), replacement);
+			self.append_locked(&guard, [&key])?;
+		}
+
+		Ok(outcome)
 	}
 
 	fn refresh_matches(current: Option<&TokenSecret>, expected: Option<&str>) -> bool {
@@ -110,11 +571,11 @@ impl FileStore {
 impl BrokerStore for FileStore {
 	fn save(&self, record: TokenRecord) -> StoreFuture<'_, ()> {
 		Box::pin(async move {
-			let key = Self::make_key(&record.family, &record.scope);
+			let key = StoreKey::new(&record.family, &record.scope)?;
 			let mut guard = self.inner.write();
 
-			guard.insert(key, record);
-			self.persist_locked(&guard)?;
+			guard.insert(key.clone(), record);
+			self.append_locked(&guard, [&key])?;
 
 			Ok(())
 		})
@@ -126,7 +587,7 @@ impl BrokerStore for FileStore {
 		scope: &'a ScopeSet,
 	) -> StoreFuture<'a, Option<TokenRecord>> {
 		Box::pin(async move {
-			let key = Self::make_key(family, scope);
+			let key = StoreKey::new(family, scope)?;
 
 			Ok(self.inner.read().get(&key).cloned())
 		})
@@ -140,22 +601,29 @@ impl BrokerStore for FileStore {
 		replacement: TokenRecord,
 	) -> StoreFuture<'a, CompareAndSwapOutcome> {
 		Box::pin(async move {
-			let key = Self::make_key(family, scope);
-			let mut guard = self.inner.write();
-			let outcome = match guard.get(&key) {
-				Some(existing)
-					if Self::refresh_matches(existing.refresh_token.as_ref(), expected_refresh) =>
-					CompareAndSwapOutcome::Updated,
-				Some(_) => CompareAndSwapOutcome::RefreshMismatch,
-				None => CompareAndSwapOutcome::Missing,
-			};
-
-			if matches!(outcome, CompareAndSwapOutcome::Updated) {
-				guard.insert(key, replacement);
-				self.persist_locked(&guard)?;
-			}
+			self.cas_now(
+				StoreKey::new(family, scope)?,
+				|existing| Self::refresh_matches(existing.refresh_token.as_ref(), expected_refresh),
+				CompareAndSwapOutcome::RefreshMismatch,
+				replacement,
+			)
+		})
+	}
 
-			Ok(outcome)
+	fn compare_and_swap_version<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+		expected_version: u64,
+		replacement: TokenRecord,
+	) -> StoreFuture<'a, CompareAndSwapOutcome> {
+		Box::pin(async move {
+			self.cas_now(
+				StoreKey::new(family, scope)?,
+				|existing| existing.version == expected_version,
+				CompareAndSwapOutcome::VersionMismatch,
+				replacement,
+			)
 		})
 	}
 
@@ -164,32 +632,301 @@ impl BrokerStore for FileStore {
 		family: &'a TokenFamily,
 		scope: &'a ScopeSet,
 		instant: OffsetDateTime,
+		reason: RevocationReason,
 	) -> StoreFuture<'a, Option<TokenRecord>> {
 		Box::pin(async move {
-			let key = Self::make_key(family, scope);
+			let key = StoreKey::new(family, scope)?;
 			let mut guard = self.inner.write();
-			let result = match guard.get_mut(&key) {
-				Some(record) => {
-					record.revoke(instant);
+			let Some(record) = guard.get_mut(&key) else { return Ok(None) };
 
-					let cloned = record.clone();
+			store::revoke_stored(record, instant, reason);
 
-					self.persist_locked(&guard)?;
+			let revoked = record.clone();
 
-					Some(cloned)
-				},
-				None => None,
-			};
+			self.append_locked(&guard, [&key])?;
+
+			Ok(Some(revoked))
+		})
+	}
+
+	fn prepare(&self, record: TokenRecord) -> StoreFuture<'_, PreparedWrite> {
+		Box::pin(async move {
+			let prepared = PreparedWrite::new(record);
+			let mut pending = self.pending.write();
 
-			Ok(result)
+			pending.insert(prepared.id.clone(), prepared.record.clone());
+			self.persist_pending_locked(&pending)?;
+
+			Ok(prepared)
+		})
+	}
+
+	fn commit<'a>(&'a self, prepared: &'a PreparedWrite) -> StoreFuture<'a, ()> {
+		Box::pin(async move {
+			let mut guard = self.inner.write();
+			let mut pending = self.pending.write();
+			let record = prepared.record.clone();
+			let key = StoreKey::new(&record.family, &record.scope)?;
+
+			guard.insert(key.clone(), record);
+			self.append_locked(&guard, [&key])?;
+
+			if pending.remove(&prepared.id).is_some() {
+				self.persist_pending_locked(&pending)?;
+			}
+
+			Ok(())
+		})
+	}
+
+	fn rollback<'a>(&'a self, prepared: &'a PreparedWrite) -> StoreFuture<'a, ()> {
+		Box::pin(async move {
+			let mut pending = self.pending.write();
+
+			if pending.remove(&prepared.id).is_some() {
+				self.persist_pending_locked(&pending)?;
+			}
+
+			Ok(())
+		})
+	}
+
+	fn pending_writes(&self) -> StoreFuture<'_, Vec<PreparedWrite>> {
+		Box::pin(async move {
+			Ok(self
+				.pending
+				.read()
+				.iter()
+				.map(|(id, record)| PreparedWrite { id: id.clone(), record: record.clone() })
+				.collect())
+		})
+	}
+
+	fn health_check(&self) -> StoreFuture<'_, ()> {
+		Box::pin(async move { self.write_probe() })
+	}
+
+	fn purge_revoked(&self, cutoff: OffsetDateTime) -> StoreFuture<'_, usize> {
+		Box::pin(async move {
+			let mut guard = self.inner.write();
+			let purged = guard
+				.iter()
+				.filter(|(_, record)| record.revoked_at.is_some_and(|revoked| revoked < cutoff))
+				.map(|(key, _)| key.clone())
+				.collect::<Vec<_>>();
+
+			for key in &purged {
+				guard.remove(key);
+			}
+
+			if !purged.is_empty() {
+				self.append_locked(&guard, &purged)?;
+			}
+
+			Ok(purged.len())
+		})
+	}
+
+	fn list_principal_subtree<'a>(
+		&'a self,
+		tenant: &'a TenantId,
+		prefix: &'a PrincipalPath,
+	) -> StoreFuture<'a, Vec<TokenRecord>> {
+		Box::pin(async move {
+			Ok(self
+				.inner
+				.read()
+				.iter()
+				.filter(|(key, _)| key.in_principal_subtree(tenant, prefix))
+				.map(|(_, record)| record.clone())
+				.collect())
+		})
+	}
+
+	fn revoke_principal_subtree<'a>(
+		&'a self,
+		tenant: &'a TenantId,
+		prefix: &'a PrincipalPath,
+		instant: OffsetDateTime,
+		reason: RevocationReason,
+	) -> StoreFuture<'a, Vec<TokenRecord>> {
+		Box::pin(async move {
+			let mut guard = self.inner.write();
+			let (keys, revoked) = guard
+				.iter_mut()
+				.filter(|(key, _)| key.in_principal_subtree(tenant, prefix))
+				.map(|(key, record)| {
+					store::revoke_stored(record, instant, reason);
+
+					(key.clone(), record.clone())
+				})
+				.unzip::<_, _, Vec<_>, Vec<_>>();
+
+			if !revoked.is_empty() {
+				self.append_locked(&guard, &keys)?;
+			}
+
+			Ok(revoked)
+		})
+	}
+
+	fn list_records<'a>(
+		&'a self,
+		after: Option<&'a str>,
+		limit: usize,
+	) -> StoreFuture<'a, RecordPage> {
+		Box::pin(async move {
+			let guard = self.inner.read();
+			let legacy = self.legacy.read();
+			let entries = guard
+				.iter()
+				.map(|(key, record)| (key.page_cursor(), record))
+				.chain(legacy.iter().map(|(cursor, (_, record))| (cursor.clone(), record)))
+				.filter(|(cursor, _)| after.is_none_or(|after| cursor.as_str() > after))
+				.map(|(cursor, record)| (cursor, record.clone()));
+
+			Ok(RecordPage::collect(entries, limit))
+		})
+	}
+
+	fn list_tenant_records<'a>(
+		&'a self,
+		tenant: &'a TenantId,
+		provider: Option<&'a ProviderId>,
+	) -> StoreFuture<'a, Vec<TokenRecord>> {
+		Box::pin(async move {
+			let mut entries = self
+				.inner
+				.read()
+				.iter()
+				.filter(|(key, _)| key.in_namespace(tenant, provider))
+				.map(|(key, record)| (key.page_cursor(), record.clone()))
+				.collect::<Vec<_>>();
+
+			entries.sort_unstable_by(|(left, _), (right, _)| left.cmp(right));
+
+			Ok(entries.into_iter().map(|(_, record)| record).collect())
+		})
+	}
+
+	fn delete_tenant<'a>(&'a self, tenant: &'a TenantId) -> StoreFuture<'a, Vec<TokenRecord>> {
+		Box::pin(async move {
+			let mut guard = self.inner.write();
+			let mut pending = self.pending.write();
+			let keys = guard
+				.keys()
+				.filter(|key| key.family.tenant == *tenant)
+				.cloned()
+				.collect::<Vec<_>>();
+			let mut deleted = keys.iter().filter_map(|key| guard.remove(key)).collect::<Vec<_>>();
+			let keyed = deleted.len();
+			let staged = pending.len();
+
+			pending.retain(|_, record| record.family.tenant != *tenant);
+			self.legacy.write().retain(|_, (key, record)| {
+				if key.family.tenant != *tenant {
+					return true;
+				}
+
+				deleted.push(record.clone());
+
+				false
+			});
+
+			// The journal only addresses provider keys, so dropping legacy records rewrites the
+			// snapshot.
+			if deleted.len() != keyed {
+				self.compact_locked(&guard, &mut self.journal.lock())?;
+			} else if !deleted.is_empty() {
+				self.append_locked(&guard, &keys)?;
+			}
+			if pending.len() != staged {
+				self.persist_pending_locked(&pending)?;
+			}
+
+			Ok(deleted)
+		})
+	}
+
+	fn compare_and_swap_family<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		expected_refresh: Option<&'a str>,
+		records: Vec<TokenRecord>,
+	) -> StoreFuture<'a, CompareAndSwapOutcome> {
+		Box::pin(async move {
+			let replacements = store::family_replacements(family, records)?;
+
+			if replacements.is_empty() {
+				return Ok(CompareAndSwapOutcome::Missing);
+			}
+
+			self.transaction(|txn| {
+				for (key, _) in &replacements {
+					match txn.get_key(key) {
+						Some(existing)
+							if Self::refresh_matches(
+								existing.refresh_token.as_ref(),
+								expected_refresh,
+							) => {},
+						Some(_) => return Ok(CompareAndSwapOutcome::RefreshMismatch),
+						None => return Ok(CompareAndSwapOutcome::Missing),
+					}
+				}
+				for (_, record) in replacements {
+					txn.put(record)?;
+				}
+
+				Ok(CompareAndSwapOutcome::Updated)
+			})
 		})
 	}
 }
 
+/// Key of a persisted snapshot entry.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum SnapshotEntryKey<'a> {
+	Keyed(&'a StoreKey),
+	Legacy(&'a StoreKeyRepr),
+}
+
+/// Append handle of the mutation journal and the number of entries it holds.
+#[derive(Debug, Default)]
+struct Journal {
+	file: Option<File>,
+	batches: usize,
+}
+impl Journal {
+	fn append(&mut self, path: &Path, line: &[u8]) -> Result<(), StoreError> {
+		let mut file = match self.file.take() {
+			Some(file) => file,
+			None => OpenOptions::new().create(true).append(true).open(path).map_err(|e| {
+				StoreError::Backend { message: format!("Failed to open {}: {e}", path.display()) }
+			})?,
+		};
+
+		if let Err(e) = file.write_all(line).and_then(|()| file.sync_data()) {
+			// A partially written line would swallow the next append, so compact on the next
+			// mutation instead.
+			self.batches = usize::MAX;
+
+			return Err(StoreError::Backend {
+				message: format!("Failed to append to {}: {e}", path.display()),
+			});
+		}
+
+		self.file = Some(file);
+		self.batches += 1;
+
+		Ok(())
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	// std
-	use std::{env, process};
+	use std::{env, process, thread};
 	// crates.io
 	use tokio::runtime::Runtime;
 	// self
@@ -211,7 +948,9 @@ mod tests {
 		let principal =
 			PrincipalId::new("principal-demo").expect("Failed to build principal fixture.");
 		let scope = ScopeSet::new(["tweet.read"]).expect("Failed to build scope fixture.");
-		let family = TokenFamily::new(tenant, principal);
+		let family = TokenFamily::new(tenant, principal).with_provider(
+			ProviderId::new("provider-demo").expect("Provider fixture should be valid."),
+		);
 		let record = TokenRecord::builder(family.clone(), scope.clone())
 			.access_token("access-token")
 			.expires_in(Duration::hours(1))
@@ -244,4 +983,427 @@ mod tests {
 			panic!("Failed to remove temporary file store snapshot {}: {e}", path.display())
 		});
 	}
+
+	#[test]
+	fn health_check_probes_the_snapshot_directory() {
+		let dir = env::temp_dir().join(format!(
+			"oauth2_broker_file_store_health_{}_{}",
+			process::id(),
+			OffsetDateTime::now_utc().unix_timestamp_nanos(),
+		));
+		let path = dir.join("store.json");
+		let store = FileStore::open(&path).expect("Failed to open file store snapshot.");
+		let rt = Runtime::new().expect("Failed to build Tokio runtime for file store test.");
+
+		rt.block_on(store.health_check()).expect("Writable directory should be healthy.");
+
+		assert!(!FileStore::health_path_for(&path).exists(), "Health probe must clean up.");
+
+		fs::remove_dir_all(&dir).unwrap_or_else(|e| {
+			panic!("Failed to remove temporary directory {}: {e}", dir.display())
+		});
+		// Replace the directory with a plain file so the probe cannot be created.
+		fs::write(&dir, b"").expect("Failed to create blocking file.");
+
+		assert!(matches!(rt.block_on(store.health_check()), Err(StoreError::Backend { .. })));
+
+		fs::remove_file(&dir)
+			.unwrap_or_else(|e| panic!("Failed to remove blocking file {}: {e}", dir.display()));
+	}
+
+	#[test]
+	fn staged_writes_survive_reopen_until_committed() {
+		let path = temp_path();
+		let store = FileStore::open(&path).expect("Failed to open file store snapshot.");
+		let (family, scope, record) = build_record();
+		let rt = Runtime::new().expect("Failed to build Tokio runtime for file store test.");
+		let prepared = rt.block_on(store.prepare(record)).expect("Failed to stage fixture record.");
+
+		assert!(
+			rt.block_on(store.fetch(&family, &scope))
+				.expect("Failed to fetch from file store.")
+				.is_none(),
+			"Staged records must stay invisible until committed."
+		);
+
+		drop(store);
+
+		let reopened = FileStore::open(&path).expect("Failed to reopen file store snapshot.");
+		let pending =
+			rt.block_on(reopened.pending_writes()).expect("Failed to list staged writes.");
+
+		assert_eq!(pending.len(), 1);
+		assert_eq!(pending[0].id, prepared.id);
+
+		rt.block_on(reopened.commit(&pending[0])).expect("Failed to commit staged write.");
+
+		assert!(
+			rt.block_on(reopened.fetch(&family, &scope))
+				.expect("Failed to fetch from file store.")
+				.is_some()
+		);
+		assert!(
+			rt.block_on(reopened.pending_writes())
+				.expect("Failed to list staged writes.")
+				.is_empty()
+		);
+
+		for leftover in [FileStore::pending_path_for(&path), FileStore::journal_path_for(&path)] {
+			fs::remove_file(&leftover).unwrap_or_else(|e| {
+				panic!("Failed to remove temporary file {}: {e}", leftover.display())
+			});
+		}
+	}
+
+	#[test]
+	fn concurrent_prepare_and_save_stage_into_separate_temp_files() {
+		let path = temp_path();
+		let store = FileStore::open(&path)
+			.expect("Failed to open file store snapshot.")
+			.with_compaction_threshold(1);
+		let (family, scope, record) = build_record();
+
+		assert_ne!(
+			FileStore::tmp_path_for(&path),
+			FileStore::tmp_path_for(&FileStore::pending_path_for(&path))
+		);
+
+		thread::scope(|threads| {
+			let saver = store.clone();
+			let saved = record.clone();
+
+			threads.spawn(move || {
+				let rt =
+					Runtime::new().expect("Failed to build Tokio runtime for file store test.");
+
+				for _ in 0..50 {
+					rt.block_on(saver.save(saved.clone())).expect("Failed to save fixture record.");
+				}
+			});
+
+			let preparer = store.clone();
+			let staged = record.clone();
+
+			threads.spawn(move || {
+				let rt =
+					Runtime::new().expect("Failed to build Tokio runtime for file store test.");
+
+				for _ in 0..50 {
+					rt.block_on(preparer.prepare(staged.clone()))
+						.expect("Failed to stage fixture record.");
+				}
+			});
+		});
+		drop(store);
+
+		let rt = Runtime::new().expect("Failed to build Tokio runtime for file store test.");
+		let reopened = FileStore::open(&path).expect("Concurrent writes must leave valid files.");
+
+		assert!(
+			rt.block_on(reopened.fetch(&family, &scope))
+				.expect("Failed to fetch from file store.")
+				.is_some()
+		);
+		assert_eq!(
+			rt.block_on(reopened.pending_writes()).expect("Failed to list staged writes.").len(),
+			50
+		);
+
+		for leftover in [path.clone(), FileStore::pending_path_for(&path)] {
+			fs::remove_file(&leftover).unwrap_or_else(|e| {
+				panic!("Failed to remove temporary file {}: {e}", leftover.display())
+			});
+		}
+	}
+
+	#[cfg(feature = "ring")]
+	#[test]
+	fn encrypted_snapshots_round_trip_and_reject_other_keys() {
+		let path = temp_path();
+		let (family, scope, record) = build_record();
+		let rt = Runtime::new().expect("Failed to build Tokio runtime for file store test.");
+		let plain = FileStore::open(&path).expect("Failed to open file store snapshot.");
+
+		rt.block_on(plain.save(record.clone())).expect("Failed to save fixture record.");
+		drop(plain);
+
+		let key = SnapshotKey::from_bytes([7; 32]).expect("Key fixture should be long enough.");
+		let sealed = FileStore::open_encrypted(&path, &key)
+			.expect("Plaintext snapshots should be sealed on open.");
+		let bytes = fs::read(&path).expect("Failed to read sealed snapshot.");
+
+		assert!(bytes.starts_with(SEALED_MAGIC));
+		assert!(!bytes.windows(b"access-token".len()).any(|window| window == b"access-token"));
+
+		rt.block_on(sealed.prepare(record.clone())).expect("Failed to stage fixture record.");
+		drop(sealed);
+
+		let reopened =
+			FileStore::open_encrypted(&path, &key).expect("Failed to reopen sealed snapshot.");
+
+		assert_eq!(
+			rt.block_on(reopened.fetch(&family, &scope))
+				.expect("Failed to fetch from file store.")
+				.expect("Sealed record should survive reopen.")
+				.access_token
+				.expose(),
+			"access-token"
+		);
+		assert_eq!(
+			rt.block_on(reopened.pending_writes()).expect("Failed to list staged writes.").len(),
+			1
+		);
+		assert!(matches!(FileStore::open(&path), Err(StoreError::Backend { .. })));
+
+		let wrong = SnapshotKey::from_bytes([8; 32]).expect("Key fixture should be long enough.");
+
+		assert!(matches!(
+			FileStore::open_encrypted(&path, &wrong),
+			Err(StoreError::Backend { .. })
+		));
+
+		let password = SnapshotKey::from_password("hunter2").with_pbkdf2_iterations(1_000);
+
+		assert!(
+			FileStore::open_encrypted(&path, &password).is_err(),
+			"Key-file snapshots must not open with a password."
+		);
+
+		for leftover in [path.clone(), FileStore::pending_path_for(&path)] {
+			fs::remove_file(&leftover).unwrap_or_else(|e| {
+				panic!("Failed to remove temporary file {}: {e}", leftover.display())
+			});
+		}
+	}
+
+	#[cfg(feature = "ring")]
+	#[test]
+	fn password_sealed_snapshots_reopen_with_the_recorded_iterations() {
+		let path = temp_path();
+		let (family, scope, record) = build_record();
+		let rt = Runtime::new().expect("Failed to build Tokio runtime for file store test.");
+		let key = SnapshotKey::from_password("correct horse").with_pbkdf2_iterations(1_000);
+		let store = FileStore::open_encrypted(&path, &key).expect("Failed to open sealed store.");
+
+		rt.block_on(store.save(record)).expect("Failed to save fixture record.");
+		drop(store);
+
+		// The header records 1,000 iterations, so the default count does not matter on reopen.
+		let reopened =
+			FileStore::open_encrypted(&path, &SnapshotKey::from_password("correct horse"))
+				.expect("Failed to reopen password-sealed store.");
+
+		assert!(
+			rt.block_on(reopened.fetch(&family, &scope))
+				.expect("Failed to fetch from file store.")
+				.is_some()
+		);
+		assert!(
+			FileStore::open_encrypted(
+				&path,
+				&SnapshotKey::from_password("wrong").with_pbkdf2_iterations(1_000)
+			)
+			.is_err()
+		);
+
+		fs::remove_file(&path).unwrap_or_else(|e| {
+			panic!("Failed to remove temporary file store snapshot {}: {e}", path.display())
+		});
+	}
+
+	#[test]
+	fn family_swap_persists_every_scope_in_one_snapshot() {
+		let path = temp_path();
+		let store = FileStore::open(&path).expect("Failed to open file store snapshot.");
+		let (family, scope, record) = build_record();
+		let write_scope = ScopeSet::new(["tweet.write"]).expect("Failed to build scope fixture.");
+		let mut sibling = record.clone();
+
+		sibling.scope = write_scope.clone();
+
+		let rt = Runtime::new().expect("Failed to build Tokio runtime for file store test.");
+
+		rt.block_on(store.save(record.clone())).expect("Failed to save fixture record.");
+		rt.block_on(store.save(sibling.clone())).expect("Failed to save sibling record.");
+
+		let mut foreign = record.clone();
+
+		foreign.family.tenant =
+			TenantId::new("tenant-other").expect("Failed to build tenant fixture.");
+
+		assert!(
+			rt.block_on(store.compare_and_swap_family(&family, None, vec![foreign])).is_err(),
+			"Records from another family must be rejected."
+		);
+
+		let replacements = [record, sibling]
+			.into_iter()
+			.map(|mut replacement| {
+				replacement.access_token = TokenSecret::new("access-rotated");
+
+				replacement
+			})
+			.collect();
+		let outcome = rt
+			.block_on(store.compare_and_swap_family(&family, None, replacements))
+			.expect("Failed to swap the token family.");
+
+		assert_eq!(outcome, CompareAndSwapOutcome::Updated);
+
+		drop(store);
+
+		let reopened = FileStore::open(&path).expect("Failed to reopen file store snapshot.");
+
+		for scope in [&scope, &write_scope] {
+			let fetched = rt
+				.block_on(reopened.fetch(&family, scope))
+				.expect("Failed to fetch from file store.")
+				.expect("Swapped record should survive reopen.");
+
+			assert_eq!(fetched.access_token.expose(), "access-rotated");
+		}
+
+		fs::remove_file(&path).unwrap_or_else(|e| {
+			panic!("Failed to remove temporary file store snapshot {}: {e}", path.display())
+		});
+	}
+
+	#[test]
+	fn baseline_snapshots_open_and_migrate_provider_less_families() {
+		let path = temp_path();
+		let (family, scope, record) = build_record();
+		let mut orphan = record.clone();
+
+		orphan.family.provider = None;
+		orphan.access_token = TokenSecret::new("access-orphan");
+
+		// Snapshots written before keys carried a provider hold `{family, scope_fingerprint}`.
+		let baseline = serde_json::json!([
+			[{ "family": family, "scope_fingerprint": scope.fingerprint() }, record],
+			[{ "family": orphan.family, "scope_fingerprint": scope.fingerprint() }, orphan],
+		]);
+
+		fs::write(
+			&path,
+			serde_json::to_vec(&baseline).expect("Baseline snapshot should serialize."),
+		)
+		.expect("Failed to write baseline snapshot.");
+
+		let store = FileStore::open(&path).expect("Baseline snapshots should open.");
+		let rt = Runtime::new().expect("Failed to build Tokio runtime for file store test.");
+
+		assert_eq!(
+			rt.block_on(store.fetch(&family, &scope))
+				.expect("Failed to fetch from file store.")
+				.expect("Keyed baseline record should load.")
+				.access_token
+				.expose(),
+			"access-token"
+		);
+		assert!(matches!(
+			rt.block_on(store.fetch(&orphan.family, &scope)),
+			Err(StoreError::MissingProvider)
+		));
+
+		// Compaction keeps the provider-less record on disk until it is migrated.
+		store.compact().expect("Failed to compact baseline snapshot.");
+		drop(store);
+
+		let store = FileStore::open(&path).expect("Compacted snapshot should reopen.");
+		let destination = store::MemoryStore::default();
+		let provider =
+			ProviderId::new("provider-legacy").expect("Provider fixture should be valid.");
+		let report = rt
+			.block_on(store::migrate(&store, &destination, store::MigrateOptions::default()))
+			.expect("Migration without a default provider should succeed.");
+
+		assert_eq!((report.copied, report.unassigned), (1, 1));
+		assert!(!report.is_complete());
+
+		let report = rt
+			.block_on(store::migrate(
+				&store,
+				&destination,
+				store::MigrateOptions::default().with_default_provider(provider.clone()),
+			))
+			.expect("Migration with a default provider should succeed.");
+
+		assert_eq!((report.copied, report.unchanged, report.unassigned), (1, 1, 0));
+		assert!(report.is_complete());
+
+		let migrated = destination
+			.fetch_shared(&orphan.family.clone().with_provider(provider), &scope)
+			.expect("Provider-less record should be migrated under the default provider.");
+
+		assert_eq!(migrated.access_token.expose(), "access-orphan");
+
+		fs::remove_file(&path).unwrap_or_else(|e| {
+			panic!("Failed to remove temporary file store snapshot {}: {e}", path.display())
+		});
+	}
+
+	#[test]
+	fn mutations_append_to_the_journal_until_compaction() {
+		let path = temp_path();
+		let journal_path = FileStore::journal_path_for(&path);
+		let store = FileStore::open(&path)
+			.expect("Failed to open file store snapshot.")
+			.with_compaction_threshold(2);
+		let (family, scope, record) = build_record();
+		let rt = Runtime::new().expect("Failed to build Tokio runtime for file store test.");
+
+		rt.block_on(store.save(record.clone())).expect("Failed to save fixture record.");
+		rt.block_on(store.revoke(
+			&family,
+			&scope,
+			OffsetDateTime::now_utc(),
+			RevocationReason::UserRequested,
+		))
+		.expect("Failed to revoke fixture record.");
+
+		assert!(!path.exists(), "Saves must not rewrite the snapshot.");
+		assert_eq!(
+			fs::read(&journal_path)
+				.expect("Failed to read journal.")
+				.split(|b| *b == b'\n')
+				.count(),
+			3
+		);
+
+		rt.block_on(store.save(record.clone())).expect("Failed to save fixture record.");
+
+		assert!(path.exists(), "The third mutation must compact.");
+		assert!(!journal_path.exists());
+
+		let mut sibling = record;
+
+		sibling.scope = ScopeSet::new(["tweet.write"]).expect("Failed to build scope fixture.");
+
+		rt.block_on(store.save(sibling.clone())).expect("Failed to save sibling record.");
+		drop(store);
+
+		// Simulate a crash in the middle of an append.
+		let mut journal =
+			OpenOptions::new().append(true).open(&journal_path).expect("Failed to open journal.");
+
+		journal.write_all(b"[[{\"family\"").expect("Failed to tear the journal.");
+		drop(journal);
+
+		let reopened = FileStore::open(&path).expect("A torn journal line must not fail the open.");
+
+		for scope in [&scope, &sibling.scope] {
+			let fetched = rt
+				.block_on(reopened.fetch(&family, scope))
+				.expect("Failed to fetch from file store.")
+				.expect("Journaled records should survive reopen.");
+
+			assert!(fetched.revoked_at.is_none());
+		}
+
+		assert!(!journal_path.exists(), "Opening must compact the journal.");
+
+		fs::remove_file(&path).unwrap_or_else(|e| {
+			panic!("Failed to remove temporary file store snapshot {}: {e}", path.display())
+		});
+	}
 }
@@ -0,0 +1,242 @@
+//! At-rest encryption for [`FileStore`](super::FileStore) snapshots.
+//!
+//! Sealed files start with a fixed header (magic, KDF, PBKDF2 iterations, salt, nonce) followed by
+//! the AES-256-GCM ciphertext of the plaintext JSON. The header is authenticated as associated
+//! data, so tampering with the KDF parameters fails decryption like any other modification.
+
+// std
+use std::{fs, num::NonZeroU32, path::Path};
+// crates.io
+use ring::{
+	aead::{AES_256_GCM, Aad, LessSafeKey, NONCE_LEN, Nonce, UnboundKey},
+	hkdf, pbkdf2,
+};
+// self
+use crate::{_prelude::*, store::StoreError};
+
+const SALT_LEN: usize = 16;
+const HEADER_LEN: usize = super::SEALED_MAGIC.len() + 1 + 4 + SALT_LEN;
+const KDF_HKDF: u8 = 0;
+const KDF_PBKDF2: u8 = 1;
+const HKDF_INFO: &[u8] = b"oauth2-broker/file-store/v1";
+const MIN_SECRET_LEN: usize = 32;
+
+/// Secret a [`FileStore`](super::FileStore) derives its snapshot encryption key from.
+///
+/// Passwords go through PBKDF2-HMAC-SHA256 and key files (or raw key bytes) through
+/// HKDF-SHA256, each with a random per-store salt kept in the file header. Keep key files outside
+/// the snapshot directory with owner-only permissions.
+#[derive(Clone)]
+pub struct SnapshotKey {
+	material: KeyMaterial,
+}
+impl SnapshotKey {
+	/// PBKDF2 iteration count used for password-derived keys.
+	pub const DEFAULT_PBKDF2_ITERATIONS: u32 = 600_000;
+
+	/// Derives the key from `password`.
+	pub fn from_password(password: impl Into<String>) -> Self {
+		Self {
+			material: KeyMaterial::Password {
+				password: password.into(),
+				iterations: Self::DEFAULT_PBKDF2_ITERATIONS,
+			},
+		}
+	}
+
+	/// Derives the key from at least 32 bytes of random secret material.
+	pub fn from_bytes(secret: impl Into<Vec<u8>>) -> Result<Self, StoreError> {
+		let secret = secret.into();
+
+		if secret.len() < MIN_SECRET_LEN {
+			return Err(StoreError::Backend {
+				message: format!("Snapshot key material must be at least {MIN_SECRET_LEN} bytes"),
+			});
+		}
+
+		Ok(Self { material: KeyMaterial::Secret(secret) })
+	}
+
+	/// Derives the key from the contents of a key file (at least 32 bytes, e.g. from
+	/// `head -c 32 /dev/urandom`).
+	pub fn from_key_file(path: impl AsRef<Path>) -> Result<Self, StoreError> {
+		let path = path.as_ref();
+		let secret = fs::read(path).map_err(|e| StoreError::Backend {
+			message: format!("Failed to read key file {}: {e}", path.display()),
+		})?;
+
+		Self::from_bytes(secret)
+	}
+
+	/// Uses `iterations` PBKDF2 rounds for new snapshots (`0` is treated as 1); ignored for key
+	/// files.
+	///
+	/// Existing snapshots keep the count recorded in their header.
+	pub fn with_pbkdf2_iterations(mut self, iterations: u32) -> Self {
+		if let KeyMaterial::Password { iterations: current, .. } = &mut self.material {
+			*current = iterations.max(1);
+		}
+
+		self
+	}
+}
+impl Debug for SnapshotKey {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		let kind = match self.material {
+			KeyMaterial::Password { .. } => "password",
+			KeyMaterial::Secret(_) => "key",
+		};
+
+		f.debug_struct("SnapshotKey").field("kind", &kind).finish_non_exhaustive()
+	}
+}
+
+#[derive(Clone)]
+enum KeyMaterial {
+	Password { password: String, iterations: u32 },
+	Secret(Vec<u8>),
+}
+
+/// Derived key plus the header parameters it was derived under.
+pub(super) struct SnapshotCipher {
+	header: [u8; HEADER_LEN],
+	key: LessSafeKey,
+}
+impl SnapshotCipher {
+	/// Derives a cipher for a new snapshot under a fresh random salt.
+	pub(super) fn generate(key: &SnapshotKey) -> Result<Self, StoreError> {
+		let (kdf, iterations) = match &key.material {
+			KeyMaterial::Password { iterations, .. } => (KDF_PBKDF2, *iterations),
+			KeyMaterial::Secret(_) => (KDF_HKDF, 0),
+		};
+
+		Self::derive(key, kdf, iterations, rand::random())
+	}
+
+	/// Derives the cipher recorded in the header of `sealed` bytes.
+	pub(super) fn for_sealed(key: &SnapshotKey, sealed: &[u8]) -> Result<Self, StoreError> {
+		let header = sealed.get(..HEADER_LEN).ok_or_else(invalid_header)?;
+		let mut offset = super::SEALED_MAGIC.len();
+		let kdf = header[offset];
+
+		offset += 1;
+
+		let mut iterations = [0; 4];
+
+		iterations.copy_from_slice(&header[offset..offset + 4]);
+		offset += 4;
+
+		let mut salt = [0; SALT_LEN];
+
+		salt.copy_from_slice(&header[offset..]);
+
+		Self::derive(key, kdf, u32::from_be_bytes(iterations), salt)
+	}
+
+	/// Returns `true` when `sealed` bytes were written under this cipher's salt and parameters.
+	pub(super) fn matches(&self, sealed: &[u8]) -> bool {
+		sealed.get(..HEADER_LEN) == Some(&self.header[..])
+	}
+
+	pub(super) fn seal(&self, plaintext: &[u8]) -> Result<Vec<u8>, StoreError> {
+		let nonce = rand::random::<[u8; NONCE_LEN]>();
+		let mut payload = plaintext.to_vec();
+
+		self.key
+			.seal_in_place_append_tag(
+				Nonce::assume_unique_for_key(nonce),
+				Aad::from(&self.header[..]),
+				&mut payload,
+			)
+			.map_err(|_| StoreError::Backend { message: "Failed to encrypt snapshot".into() })?;
+
+		Ok([&self.header[..], &nonce[..], &payload].concat())
+	}
+
+	pub(super) fn open(&self, sealed: &[u8]) -> Result<Vec<u8>, StoreError> {
+		if sealed.len() < HEADER_LEN + NONCE_LEN {
+			return Err(invalid_header());
+		}
+
+		let mut nonce = [0; NONCE_LEN];
+
+		nonce.copy_from_slice(&sealed[HEADER_LEN..HEADER_LEN + NONCE_LEN]);
+
+		let mut payload = sealed[HEADER_LEN + NONCE_LEN..].to_vec();
+		let plaintext = self
+			.key
+			.open_in_place(
+				Nonce::assume_unique_for_key(nonce),
+				Aad::from(&sealed[..HEADER_LEN]),
+				&mut payload,
+			)
+			.map_err(|_| StoreError::Backend {
+				message: "Failed to decrypt snapshot; the key does not match".into(),
+			})?;
+
+		Ok(plaintext.to_vec())
+	}
+
+	fn derive(
+		key: &SnapshotKey,
+		kdf: u8,
+		iterations: u32,
+		salt: [u8; SALT_LEN],
+	) -> Result<Self, StoreError> {
+		let mut derived = [0; 32];
+
+		match (&key.material, kdf) {
+			(KeyMaterial::Password { password, .. }, KDF_PBKDF2) => {
+				let iterations = NonZeroU32::new(iterations).ok_or_else(invalid_header)?;
+
+				pbkdf2::derive(
+					pbkdf2::PBKDF2_HMAC_SHA256,
+					iterations,
+					&salt,
+					password.as_bytes(),
+					&mut derived,
+				);
+			},
+			(KeyMaterial::Secret(secret), KDF_HKDF) => {
+				hkdf::Salt::new(hkdf::HKDF_SHA256, &salt)
+					.extract(secret)
+					.expand(&[HKDF_INFO], &AES_256_GCM)
+					.and_then(|okm| okm.fill(&mut derived))
+					.map_err(|_| StoreError::Backend {
+						message: "Failed to derive snapshot key".into(),
+					})?;
+			},
+			(_, KDF_HKDF | KDF_PBKDF2) =>
+				return Err(StoreError::Backend {
+					message: "Snapshot was sealed with a different kind of key (password or key \
+					          file)"
+						.into(),
+				}),
+			(_, other) =>
+				return Err(StoreError::Serialization {
+					message: format!("Snapshot uses unknown key derivation {other}"),
+				}),
+		}
+
+		let unbound = UnboundKey::new(&AES_256_GCM, &derived)
+			.map_err(|_| StoreError::Backend { message: "Invalid snapshot key".into() })?;
+		let mut header = [0; HEADER_LEN];
+		let magic_len = super::SEALED_MAGIC.len();
+
+		header[..magic_len].copy_from_slice(super::SEALED_MAGIC);
+		header[magic_len] = kdf;
+		header[magic_len + 1..magic_len + 5].copy_from_slice(&iterations.to_be_bytes());
+		header[magic_len + 5..].copy_from_slice(&salt);
+
+		Ok(Self { header, key: LessSafeKey::new(unbound) })
+	}
+}
+impl Debug for SnapshotCipher {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		f.debug_struct("SnapshotCipher").finish_non_exhaustive()
+	}
+}
+
+fn invalid_header() -> StoreError {
+	StoreError::Serialization { message: "Sealed snapshot header is truncated or invalid".into() }
+}
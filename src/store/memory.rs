@@ -1,56 +1,162 @@
 //! Thread-safe in-memory [`BrokerStore`] implementation for local development and tests.
+//!
+//! Records are kept behind `Arc` so lookups only copy the record outside the map lock, and
+//! [`MemoryStore::fetch_shared`] skips the copy entirely. Refresh CAS checks run under an
+//! upgradable read lock, so a mismatch never blocks concurrent readers, and the map is split into
+//! hash shards so unrelated token families never share a lock. Family swaps write-lock every
+//! shard they touch in ascending order.
 
+// std
+use std::collections::BTreeSet;
+// crates.io
+use parking_lot::{RwLockUpgradableReadGuard, RwLockWriteGuard};
 // self
 use crate::{
 	_prelude::*,
-	auth::{ScopeSet, TokenFamily, TokenRecord, token::secret::TokenSecret},
-	store::{BrokerStore, CompareAndSwapOutcome, StoreError, StoreFuture, StoreKey},
+	auth::{
+		PrincipalPath, ProviderId, RevocationReason, ScopeSet, TenantId, TokenFamily, TokenRecord,
+		token::secret::TokenSecret,
+	},
+	store::{
+		self, BrokerStore, CompareAndSwapOutcome, ConditionalFetch, RecordPage, Sharded,
+		StoreError, StoreFuture, StoreKey, Transaction, transaction::TransactionBase,
+	},
 };
 
-type StoreMap = Arc<RwLock<HashMap<StoreKey, TokenRecord>>>;
+type StoreMap = RwLock<HashMap<StoreKey, Arc<TokenRecord>>>;
 
 /// Thread-safe storage backend that keeps records in-process for tests and demos.
 #[derive(Clone, Debug, Default)]
-pub struct MemoryStore(StoreMap);
+pub struct MemoryStore(Arc<Sharded<StoreMap>>);
 impl MemoryStore {
-	fn save_now(map: StoreMap, record: TokenRecord) -> Result<(), StoreError> {
-		let key = StoreKey::new(&record.family, &record.scope);
+	/// Creates a store split into `shards` independently locked maps (rounded up to a power of
+	/// two); [`MemoryStore::default`] uses [`Sharded::DEFAULT_SHARDS`].
+	pub fn with_shards(shards: usize) -> Self {
+		Self(Arc::new(Sharded::new(shards, Default::default)))
+	}
+
+	/// Returns the stored record without copying it.
+	///
+	/// The returned handle is a snapshot; later writes replace the map entry instead of mutating
+	/// records that callers still hold. Families without a provider have no key and therefore no
+	/// record.
+	pub fn fetch_shared(&self, family: &TokenFamily, scope: &ScopeSet) -> Option<Arc<TokenRecord>> {
+		self.get(&StoreKey::new(family, scope).ok()?)
+	}
 
-		map.write().insert(key, record);
+	/// Runs `f` against every record under all shard locks, applying its staged writes atomically
+	/// when it returns `Ok`.
+	///
+	/// Writers on every shard wait for the transaction, so keep the closure short. Swapping a
+	/// single family is cheaper through [`BrokerStore::compare_and_swap_family`], which only locks
+	/// the shards it touches.
+	pub fn transaction<T, F>(&self, f: F) -> Result<T, StoreError>
+	where
+		F: FnOnce(&mut Transaction<'_>) -> Result<T, StoreError>,
+	{
+		let mut locked =
+			LockedShards { store: &self.0, guards: self.0.iter().map(RwLock::write).collect() };
+		let (value, writes) = {
+			let mut txn = Transaction::new(&locked);
+			let value = f(&mut txn)?;
 
-		Ok(())
+			(value, txn.into_writes())
+		};
+
+		for (key, staged) in writes {
+			let Some(guard) = locked.guards.get_mut(self.0.index_for(&key)) else { continue };
+
+			match staged {
+				Some(record) => guard.insert(key, Arc::new(record)),
+				None => guard.remove(&key),
+			};
+		}
+
+		Ok(value)
+	}
+
+	fn get(&self, key: &StoreKey) -> Option<Arc<TokenRecord>> {
+		self.0.for_key(key).read().get(key).cloned()
 	}
 
-	fn fetch_now(map: StoreMap, family: TokenFamily, scope: ScopeSet) -> Option<TokenRecord> {
-		let key = StoreKey::new(&family, &scope);
+	fn save_now(&self, record: TokenRecord) -> Result<(), StoreError> {
+		let key = StoreKey::new(&record.family, &record.scope)?;
+
+		self.0.for_key(&key).write().insert(key, Arc::new(record));
 
-		map.read().get(&key).cloned()
+		Ok(())
 	}
 
-	fn cas_now(
-		map: StoreMap,
-		family: TokenFamily,
-		scope: ScopeSet,
-		expected_refresh: Option<&str>,
-		replacement: TokenRecord,
-	) -> CompareAndSwapOutcome {
-		let key = StoreKey::new(&family, &scope);
-		let mut guard = map.write();
+	/// Replaces the record at `key` when `matches` accepts it, reporting `mismatch` otherwise.
+	fn cas_now<F>(
+		&self,
+		key: StoreKey,
+		matches: F,
+		mismatch: CompareAndSwapOutcome,
+		mut replacement: TokenRecord,
+	) -> CompareAndSwapOutcome
+	where
+		F: FnOnce(&TokenRecord) -> bool,
+	{
+		let guard = self.0.for_key(&key).upgradable_read();
 		let outcome = match guard.get(&key) {
-			Some(existing)
-				if Self::refresh_matches(existing.refresh_token.as_ref(), expected_refresh) =>
-				CompareAndSwapOutcome::Updated,
-			Some(_) => CompareAndSwapOutcome::RefreshMismatch,
+			Some(existing) if matches(existing) => {
+				replacement.version = store::next_version(existing);
+
+				CompareAndSwapOutcome::Updated
+			},
+			Some(_) => mismatch,
 			None => CompareAndSwapOutcome::Missing,
 		};
 
 		if matches!(outcome, CompareAndSwapOutcome::Updated) {
-			guard.insert(key, replacement);
+			RwLockUpgradableReadGuard::upgrade(guard).insert(key, Arc::new(replacement));
 		}
 
 		outcome
 	}
 
+	fn cas_family_now(
+		&self,
+		family: &TokenFamily,
+		expected_refresh: Option<&str>,
+		records: Vec<TokenRecord>,
+	) -> Result<CompareAndSwapOutcome, StoreError> {
+		let replacements = store::family_replacements(family, records)?;
+
+		if replacements.is_empty() {
+			return Ok(CompareAndSwapOutcome::Missing);
+		}
+
+		let indices =
+			replacements.iter().map(|(key, _)| self.0.index_for(key)).collect::<BTreeSet<_>>();
+		let mut guards = self
+			.0
+			.iter()
+			.enumerate()
+			.filter(|(idx, _)| indices.contains(idx))
+			.map(|(idx, shard)| (idx, shard.write()))
+			.collect::<BTreeMap<_, _>>();
+
+		for (key, _) in &replacements {
+			match guards.get(&self.0.index_for(key)).and_then(|guard| guard.get(key)) {
+				Some(existing)
+					if Self::refresh_matches(existing.refresh_token.as_ref(), expected_refresh) => {},
+				Some(_) => return Ok(CompareAndSwapOutcome::RefreshMismatch),
+				None => return Ok(CompareAndSwapOutcome::Missing),
+			}
+		}
+		for (key, mut record) in replacements {
+			if let Some(guard) = guards.get_mut(&self.0.index_for(&key)) {
+				record.version =
+					guard.get(&key).map_or(0, |existing| store::next_version(existing));
+				guard.insert(key, Arc::new(record));
+			}
+		}
+
+		Ok(CompareAndSwapOutcome::Updated)
+	}
+
 	fn refresh_matches(current: Option<&TokenSecret>, expected: Option<&str>) -> bool {
 		match (current.map(TokenSecret::expose), expected) {
 			(None, None) => true,
@@ -60,29 +166,61 @@ impl MemoryStore {
 	}
 
 	fn revoke_now(
-		map: StoreMap,
-		family: TokenFamily,
-		scope: ScopeSet,
+		&self,
+		key: &StoreKey,
 		instant: OffsetDateTime,
+		reason: RevocationReason,
 	) -> Option<TokenRecord> {
-		let key = StoreKey::new(&family, &scope);
-		let mut guard = map.write();
+		let mut guard = self.0.for_key(key).write();
+		let record = Arc::make_mut(guard.get_mut(key)?);
 
-		match guard.get_mut(&key) {
-			Some(record) => {
-				record.revoke(instant);
+		store::revoke_stored(record, instant, reason);
 
-				Some(record.clone())
-			},
-			None => None,
+		Some(record.clone())
+	}
+
+	fn subtree_now(
+		&self,
+		tenant: &TenantId,
+		prefix: &PrincipalPath,
+		revoke_at: Option<(OffsetDateTime, RevocationReason)>,
+	) -> Vec<TokenRecord> {
+		let mut records = Vec::new();
+
+		for shard in self.0.iter() {
+			if let Some((instant, reason)) = revoke_at {
+				let mut guard = shard.write();
+				let revoked = guard
+					.iter_mut()
+					.filter(|(key, _)| key.in_principal_subtree(tenant, prefix))
+					.map(|(_, record)| {
+						let record = Arc::make_mut(record);
+
+						store::revoke_stored(record, instant, reason);
+
+						record.clone()
+					});
+
+				records.extend(revoked);
+			} else {
+				let matches = shard
+					.read()
+					.iter()
+					.filter(|(key, _)| key.in_principal_subtree(tenant, prefix))
+					.map(|(_, record)| Arc::clone(record))
+					.collect::<Vec<_>>();
+
+				// Copy the records after the read lock is released.
+				records.extend(matches.into_iter().map(Arc::unwrap_or_clone));
+			}
 		}
+
+		records
 	}
 }
 impl BrokerStore for MemoryStore {
 	fn save(&self, record: TokenRecord) -> StoreFuture<'_, ()> {
-		let map = self.0.clone();
-
-		Box::pin(async move { Self::save_now(map, record) })
+		Box::pin(async move { self.save_now(record) })
 	}
 
 	fn fetch<'a>(
@@ -90,11 +228,34 @@ impl BrokerStore for MemoryStore {
 		family: &'a TokenFamily,
 		scope: &'a ScopeSet,
 	) -> StoreFuture<'a, Option<TokenRecord>> {
-		let map = self.0.clone();
-		let family = family.to_owned();
-		let scope = scope.to_owned();
+		// Copy the record after the read lock is released.
+		Box::pin(
+			async move { Ok(self.get(&StoreKey::new(family, scope)?).map(Arc::unwrap_or_clone)) },
+		)
+	}
 
-		Box::pin(async move { Ok(Self::fetch_now(map, family, scope)) })
+	fn fetch_if_changed<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+		known_fingerprint: Option<&'a str>,
+	) -> StoreFuture<'a, ConditionalFetch> {
+		Box::pin(async move {
+			let Some(record) = self.get(&StoreKey::new(family, scope)?) else {
+				return Ok(ConditionalFetch::Missing);
+			};
+			let fingerprint = store::record_fingerprint(&record)?;
+
+			// Only copy the record when the caller's fingerprint is stale.
+			if known_fingerprint == Some(fingerprint.as_str()) {
+				Ok(ConditionalFetch::Unchanged)
+			} else {
+				Ok(ConditionalFetch::Changed {
+					record: Box::new(Arc::unwrap_or_clone(record)),
+					fingerprint,
+				})
+			}
+		})
 	}
 
 	fn compare_and_swap_refresh<'a>(
@@ -104,13 +265,31 @@ impl BrokerStore for MemoryStore {
 		expected_refresh: Option<&'a str>,
 		replacement: TokenRecord,
 	) -> StoreFuture<'a, CompareAndSwapOutcome> {
-		let map = self.0.clone();
-		let family = family.to_owned();
-		let scope = scope.to_owned();
+		Box::pin(async move {
+			Ok(self.cas_now(
+				StoreKey::new(family, scope)?,
+				|existing| Self::refresh_matches(existing.refresh_token.as_ref(), expected_refresh),
+				CompareAndSwapOutcome::RefreshMismatch,
+				replacement,
+			))
+		})
+	}
 
-		Box::pin(
-			async move { Ok(Self::cas_now(map, family, scope, expected_refresh, replacement)) },
-		)
+	fn compare_and_swap_version<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+		expected_version: u64,
+		replacement: TokenRecord,
+	) -> StoreFuture<'a, CompareAndSwapOutcome> {
+		Box::pin(async move {
+			Ok(self.cas_now(
+				StoreKey::new(family, scope)?,
+				|existing| existing.version == expected_version,
+				CompareAndSwapOutcome::VersionMismatch,
+				replacement,
+			))
+		})
 	}
 
 	fn revoke<'a>(
@@ -118,11 +297,168 @@ impl BrokerStore for MemoryStore {
 		family: &'a TokenFamily,
 		scope: &'a ScopeSet,
 		instant: OffsetDateTime,
+		reason: RevocationReason,
 	) -> StoreFuture<'a, Option<TokenRecord>> {
-		let map = self.0.clone();
-		let family = family.to_owned();
-		let scope = scope.to_owned();
+		Box::pin(
+			async move { Ok(self.revoke_now(&StoreKey::new(family, scope)?, instant, reason)) },
+		)
+	}
+
+	fn purge_revoked(&self, cutoff: OffsetDateTime) -> StoreFuture<'_, usize> {
+		Box::pin(async move {
+			let mut purged = 0;
+
+			for shard in self.0.iter() {
+				let mut guard = shard.write();
+				let before = guard.len();
+
+				guard.retain(|_, record| record.revoked_at.is_none_or(|revoked| revoked >= cutoff));
+
+				purged += before - guard.len();
+			}
+
+			Ok(purged)
+		})
+	}
+
+	fn list_principal_subtree<'a>(
+		&'a self,
+		tenant: &'a TenantId,
+		prefix: &'a PrincipalPath,
+	) -> StoreFuture<'a, Vec<TokenRecord>> {
+		Box::pin(async move { Ok(self.subtree_now(tenant, prefix, None)) })
+	}
+
+	fn revoke_principal_subtree<'a>(
+		&'a self,
+		tenant: &'a TenantId,
+		prefix: &'a PrincipalPath,
+		instant: OffsetDateTime,
+		reason: RevocationReason,
+	) -> StoreFuture<'a, Vec<TokenRecord>> {
+		Box::pin(async move { Ok(self.subtree_now(tenant, prefix, Some((instant, reason)))) })
+	}
+
+	fn revoke_all<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		instant: OffsetDateTime,
+		reason: RevocationReason,
+	) -> StoreFuture<'a, Vec<TokenRecord>> {
+		Box::pin(async move {
+			let mut revoked = Vec::new();
+
+			for shard in self.0.iter() {
+				revoked.extend(
+					shard
+						.write()
+						.iter_mut()
+						.filter(|(key, record)| key.family == *family && !record.is_revoked())
+						.map(|(_, record)| {
+							let record = Arc::make_mut(record);
+
+							store::revoke_stored(record, instant, reason);
+
+							record.clone()
+						}),
+				);
+			}
+
+			Ok(revoked)
+		})
+	}
+
+	fn list_records<'a>(
+		&'a self,
+		after: Option<&'a str>,
+		limit: usize,
+	) -> StoreFuture<'a, RecordPage> {
+		Box::pin(async move {
+			let mut entries = Vec::new();
+
+			for shard in self.0.iter() {
+				entries.extend(
+					shard
+						.read()
+						.iter()
+						.map(|(key, record)| (key.page_cursor(), Arc::clone(record)))
+						.filter(|(cursor, _)| after.is_none_or(|after| cursor.as_str() > after)),
+				);
+			}
+
+			Ok(RecordPage::collect(
+				entries.into_iter().map(|(cursor, record)| (cursor, Arc::unwrap_or_clone(record))),
+				limit,
+			))
+		})
+	}
+
+	fn list_tenant_records<'a>(
+		&'a self,
+		tenant: &'a TenantId,
+		provider: Option<&'a ProviderId>,
+	) -> StoreFuture<'a, Vec<TokenRecord>> {
+		Box::pin(async move {
+			let mut entries = Vec::new();
+
+			for shard in self.0.iter() {
+				entries.extend(
+					shard
+						.read()
+						.iter()
+						.filter(|(key, _)| key.in_namespace(tenant, provider))
+						.map(|(key, record)| (key.page_cursor(), Arc::clone(record))),
+				);
+			}
+
+			entries.sort_unstable_by(|(left, _), (right, _)| left.cmp(right));
+
+			Ok(entries.into_iter().map(|(_, record)| Arc::unwrap_or_clone(record)).collect())
+		})
+	}
+
+	fn delete_tenant<'a>(&'a self, tenant: &'a TenantId) -> StoreFuture<'a, Vec<TokenRecord>> {
+		Box::pin(async move {
+			let mut deleted = Vec::new();
+
+			for shard in self.0.iter() {
+				let mut guard = shard.write();
+				let keys = guard
+					.keys()
+					.filter(|key| key.family.tenant == *tenant)
+					.cloned()
+					.collect::<Vec<_>>();
+
+				deleted.extend(keys.iter().filter_map(|key| guard.remove(key)));
+			}
 
-		Box::pin(async move { Ok(Self::revoke_now(map, family, scope, instant)) })
+			Ok(deleted.into_iter().map(Arc::unwrap_or_clone).collect())
+		})
+	}
+
+	fn compare_and_swap_family<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		expected_refresh: Option<&'a str>,
+		records: Vec<TokenRecord>,
+	) -> StoreFuture<'a, CompareAndSwapOutcome> {
+		Box::pin(async move { self.cas_family_now(family, expected_refresh, records) })
+	}
+}
+
+/// Write guards over every shard, held for the duration of a [`MemoryStore::transaction`].
+struct LockedShards<'a> {
+	store: &'a Sharded<StoreMap>,
+	guards: Vec<RwLockWriteGuard<'a, HashMap<StoreKey, Arc<TokenRecord>>>>,
+}
+impl TransactionBase for LockedShards<'_> {
+	fn get(&self, key: &StoreKey) -> Option<&TokenRecord> {
+		self.guards.get(self.store.index_for(key))?.get(key).map(Arc::as_ref)
+	}
+
+	fn for_each(&self, visit: &mut dyn FnMut(&StoreKey, &TokenRecord)) {
+		for (key, record) in self.guards.iter().flat_map(|guard| guard.iter()) {
+			visit(key, record);
+		}
 	}
 }
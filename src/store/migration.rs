@@ -0,0 +1,298 @@
+//! Bulk copy of token records between [`BrokerStore`] backends.
+//!
+//! [`migrate`] pages through the source with [`BrokerStore::list_records`], writes every record
+//! to the destination, and reads each write back to compare fingerprints. Records already present
+//! in the destination are resolved through a [`ConflictPolicy`], so the copy can run while both
+//! backends serve traffic and be repeated to catch up on writes that landed mid-run before the
+//! switch-over. Wrapping either side in an encrypting store re-encrypts records in flight, since
+//! the copy only ever sees plaintext records.
+//!
+//! Records whose family names no provider (left by stores written before keys required one) are
+//! moved into the namespace set by [`MigrateOptions::with_default_provider`]; without it they are
+//! counted in [`MigrationReport::unassigned`] and left behind.
+
+// self
+use crate::{
+	_prelude::*,
+	auth::ProviderId,
+	store::{BrokerStore, StoreError, StoreKey, record_fingerprint},
+};
+
+/// How [`migrate`] resolves a destination record that differs from the source.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConflictPolicy {
+	/// Keep the destination record and report the conflict.
+	#[default]
+	Skip,
+	/// Replace the destination record with the source record.
+	Overwrite,
+	/// Keep whichever record was issued last.
+	KeepNewer,
+}
+
+/// Tuning knobs for [`migrate`].
+#[derive(Clone, Debug)]
+pub struct MigrateOptions {
+	/// Records requested per [`BrokerStore::list_records`] call.
+	pub page_size: usize,
+	/// Resolution for records that already exist in the destination with different contents.
+	pub conflicts: ConflictPolicy,
+	/// Reads every write back from the destination and compares fingerprints.
+	pub verify: bool,
+	/// Provider assigned to source records whose family names none.
+	pub default_provider: Option<ProviderId>,
+}
+impl MigrateOptions {
+	const DEFAULT_PAGE_SIZE: usize = 500;
+
+	/// Sets how many records are listed per page (`0` is treated as 1).
+	pub fn with_page_size(mut self, page_size: usize) -> Self {
+		self.page_size = page_size.max(1);
+
+		self
+	}
+
+	/// Sets the conflict resolution policy.
+	pub fn with_conflict_policy(mut self, policy: ConflictPolicy) -> Self {
+		self.conflicts = policy;
+
+		self
+	}
+
+	/// Skips the read-back verification of every write.
+	pub fn without_verification(mut self) -> Self {
+		self.verify = false;
+
+		self
+	}
+
+	/// Copies records whose family names no provider into `provider`'s namespace.
+	pub fn with_default_provider(mut self, provider: ProviderId) -> Self {
+		self.default_provider = Some(provider);
+
+		self
+	}
+}
+impl Default for MigrateOptions {
+	fn default() -> Self {
+		Self {
+			page_size: Self::DEFAULT_PAGE_SIZE,
+			conflicts: ConflictPolicy::Skip,
+			verify: true,
+			default_provider: None,
+		}
+	}
+}
+
+/// Destination record that differed from the source record with the same key.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MigrationConflict {
+	/// Key shared by both records.
+	pub key: StoreKey,
+	/// Issue time of the source record.
+	pub source_issued_at: OffsetDateTime,
+	/// Issue time of the destination record.
+	pub destination_issued_at: OffsetDateTime,
+	/// Whether the source record replaced the destination record.
+	pub overwritten: bool,
+}
+
+/// Outcome of a [`migrate`] run.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct MigrationReport {
+	/// Records read from the source.
+	pub scanned: usize,
+	/// Records written to the destination.
+	pub copied: usize,
+	/// Records the destination already held with identical contents.
+	pub unchanged: usize,
+	/// Records that differed in the destination, with their resolution.
+	pub conflicts: Vec<MigrationConflict>,
+	/// Writes whose read-back fingerprint matched the source.
+	pub verified: usize,
+	/// Keys whose read-back fingerprint did not match (or that vanished after the write).
+	pub mismatches: Vec<StoreKey>,
+	/// Source records left behind because their family names no provider and no default
+	/// provider was set.
+	#[serde(default)]
+	pub unassigned: usize,
+}
+impl MigrationReport {
+	/// Returns `true` when every scanned record is accounted for, no write failed verification,
+	/// and no conflict was left unresolved.
+	pub fn is_complete(&self) -> bool {
+		let skipped = self.conflicts.iter().filter(|conflict| !conflict.overwritten).count();
+
+		self.mismatches.is_empty()
+			&& skipped == 0
+			&& self.unassigned == 0
+			&& self.scanned == self.copied + self.unchanged + skipped
+	}
+}
+
+/// Copies every record from `source` into `destination`.
+///
+/// Fails with [`StoreError::Unsupported`] when the source cannot enumerate records. Store errors
+/// abort the run; records copied before the failure stay in the destination, and a rerun
+/// reports them as unchanged.
+pub async fn migrate(
+	source: &dyn BrokerStore,
+	destination: &dyn BrokerStore,
+	options: MigrateOptions,
+) -> Result<MigrationReport, StoreError> {
+	let mut report = MigrationReport::default();
+	let mut cursor = None;
+
+	loop {
+		let page = source.list_records(cursor.as_deref(), options.page_size).await?;
+
+		for mut record in page.records {
+			report.scanned += 1;
+
+			if record.family.provider.is_none() {
+				let Some(provider) = &options.default_provider else {
+					report.unassigned += 1;
+
+					continue;
+				};
+
+				record.family.provider = Some(provider.clone());
+			}
+
+			let fingerprint = record_fingerprint(&record)?;
+
+			if let Some(existing) = destination.fetch(&record.family, &record.scope).await? {
+				if record_fingerprint(&existing)? == fingerprint {
+					report.unchanged += 1;
+
+					continue;
+				}
+
+				let overwritten = match options.conflicts {
+					ConflictPolicy::Skip => false,
+					ConflictPolicy::Overwrite => true,
+					ConflictPolicy::KeepNewer => record.issued_at > existing.issued_at,
+				};
+
+				report.conflicts.push(MigrationConflict {
+					key: StoreKey::new(&record.family, &record.scope)?,
+					source_issued_at: record.issued_at,
+					destination_issued_at: existing.issued_at,
+					overwritten,
+				});
+
+				if !overwritten {
+					continue;
+				}
+			}
+
+			destination.save(record.clone()).await?;
+
+			report.copied += 1;
+
+			if !options.verify {
+				continue;
+			}
+
+			let stored = destination.fetch(&record.family, &record.scope).await?;
+
+			if stored.map(|stored| record_fingerprint(&stored)).transpose()? == Some(fingerprint) {
+				report.verified += 1;
+			} else {
+				report.mismatches.push(StoreKey::new(&record.family, &record.scope)?);
+			}
+		}
+
+		match page.next {
+			Some(next) => cursor = Some(next),
+			None => break,
+		}
+	}
+
+	Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+	// crates.io
+	use tokio::runtime::Runtime;
+	// self
+	use super::*;
+	use crate::{
+		auth::{PrincipalId, ProviderId, ScopeSet, TenantId, TokenFamily, TokenRecord},
+		store::MemoryStore,
+	};
+
+	fn build_record(principal: &str, access: &str, issued_at: OffsetDateTime) -> TokenRecord {
+		let family = TokenFamily::new(
+			TenantId::new("tenant-migrate").expect("Tenant fixture should be valid."),
+			PrincipalId::new(principal).expect("Principal fixture should be valid."),
+		)
+		.with_provider(
+			ProviderId::new("provider-migrate").expect("Provider fixture should be valid."),
+		);
+
+		TokenRecord::builder(
+			family,
+			ScopeSet::new(["read"]).expect("Scope fixture should be valid."),
+		)
+		.access_token(access)
+		.issued_at(issued_at)
+		.expires_in(Duration::hours(1))
+		.build()
+		.expect("Token record fixture should build successfully.")
+	}
+
+	#[test]
+	fn migrate_pages_copies_and_resolves_conflicts() {
+		let rt = Runtime::new().expect("Failed to build Tokio runtime for migration test.");
+		let source = MemoryStore::default();
+		let destination = MemoryStore::default();
+		let now = OffsetDateTime::now_utc();
+
+		rt.block_on(async {
+			for idx in 0..5 {
+				source
+					.save(build_record(&format!("principal-{idx}"), "access-source", now))
+					.await
+					.expect("Seeding the source should succeed.");
+			}
+
+			destination
+				.save(build_record("principal-0", "access-source", now))
+				.await
+				.expect("Seeding the destination should succeed.");
+			destination
+				.save(build_record("principal-1", "access-newer", now + Duration::minutes(1)))
+				.await
+				.expect("Seeding the destination should succeed.");
+		});
+
+		let options = MigrateOptions::default()
+			.with_page_size(2)
+			.with_conflict_policy(ConflictPolicy::KeepNewer);
+		let report = rt
+			.block_on(migrate(&source, &destination, options.clone()))
+			.expect("Migration should succeed.");
+
+		assert_eq!(report.scanned, 5);
+		assert_eq!(report.unchanged, 1);
+		assert_eq!(report.copied, 3);
+		assert_eq!(report.verified, 3);
+		assert_eq!(report.conflicts.len(), 1);
+		assert!(!report.conflicts[0].overwritten);
+		assert!(!report.is_complete(), "A skipped conflict leaves the migration incomplete.");
+
+		let rerun = rt
+			.block_on(migrate(
+				&source,
+				&destination,
+				options.with_conflict_policy(ConflictPolicy::Overwrite),
+			))
+			.expect("Migration rerun should succeed.");
+
+		assert_eq!(rerun.unchanged, 4);
+		assert_eq!(rerun.copied, 1);
+		assert!(rerun.is_complete());
+	}
+}
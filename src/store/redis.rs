@@ -0,0 +1,668 @@
+//! [Redis](https://redis.io) [`BrokerStore`] shared by every broker replica.
+//!
+//! Records live in one hash keyed by [`StoreKey::page_cursor`], next to a sorted set holding the
+//! same cursors (all scored `0`) that `ZRANGEBYLEX` walks in cursor order for pagination and
+//! tenant scans. Every write is a Lua script, so Redis applies it atomically to both keys:
+//! conditional writes replace a record only while it still holds the JSON they read (retrying
+//! when another writer got there first), and family swaps check every sibling before writing any.
+
+// crates.io
+use redis::{
+	Client, Cmd, FromRedisValue, RedisError, Script,
+	aio::{ConnectionLike, ConnectionManager, ConnectionManagerConfig},
+};
+// self
+use crate::{
+	_prelude::*,
+	auth::{
+		PrincipalPath, RevocationReason, ScopeSet, TenantId, TokenFamily, TokenRecord, TokenSecret,
+	},
+	store::{
+		self, BrokerStore, CompareAndSwapOutcome, RecordPage, StoreError, StoreFuture, StoreKey,
+	},
+};
+
+/// Returns `[cursor, record, ...]` for the cursors between `ARGV[1]` and `ARGV[2]`, at most
+/// `ARGV[3]` of them (`-1` for all).
+const LIST_SCRIPT: &str = r"
+local entries = {}
+for _, field in ipairs(redis.call('ZRANGEBYLEX', KEYS[2], ARGV[1], ARGV[2], 'LIMIT', 0, ARGV[3])) do
+	table.insert(entries, field)
+	table.insert(entries, redis.call('HGET', KEYS[1], field))
+end
+return entries
+";
+/// Writes `ARGV` as `cursor, record` pairs; an empty record deletes the cursor.
+const PUT_SCRIPT: &str = r"
+for i = 1, #ARGV, 2 do
+	if ARGV[i + 1] == '' then
+		redis.call('HDEL', KEYS[1], ARGV[i])
+		redis.call('ZREM', KEYS[2], ARGV[i])
+	else
+		redis.call('HSET', KEYS[1], ARGV[i], ARGV[i + 1])
+		redis.call('ZADD', KEYS[2], 0, ARGV[i])
+	end
+end
+return 1
+";
+/// Writes `ARGV` as `cursor, expected, replacement` triples only while every cursor still holds
+/// its expected record; an empty replacement deletes the cursor.
+const SWAP_SCRIPT: &str = r"
+for i = 1, #ARGV, 3 do
+	if redis.call('HGET', KEYS[1], ARGV[i]) ~= ARGV[i + 1] then
+		return 0
+	end
+end
+for i = 1, #ARGV, 3 do
+	if ARGV[i + 2] == '' then
+		redis.call('HDEL', KEYS[1], ARGV[i])
+		redis.call('ZREM', KEYS[2], ARGV[i])
+	else
+		redis.call('HSET', KEYS[1], ARGV[i], ARGV[i + 2])
+		redis.call('ZADD', KEYS[2], 0, ARGV[i])
+	end
+end
+return 1
+";
+
+/// Persists broker records in Redis over any async connection.
+///
+/// [`RedisStore::connect_lazy`] opens a reconnecting [`ConnectionManager`]; callers that already
+/// hold a connection of their own (a cluster client, a pooled connection) pass it to
+/// [`RedisStore::new`]. Clones share the connection.
+#[derive(Clone)]
+pub struct RedisStore<C = ConnectionManager>
+where
+	C: ConnectionLike + Clone + Send + Sync,
+{
+	connection: C,
+	prefix: String,
+}
+impl<C> RedisStore<C>
+where
+	C: ConnectionLike + Clone + Send + Sync,
+{
+	/// Key prefix used unless [`RedisStore::with_prefix`] overrides it.
+	///
+	/// The braces are a Redis Cluster hash tag, so both keys of the store share one slot.
+	pub const DEFAULT_PREFIX: &str = "{oauth2-broker}:";
+	const PURGE_PAGE: usize = 200;
+
+	/// Creates a store that issues its commands on `connection`.
+	pub fn new(connection: C) -> Self {
+		Self { connection, prefix: Self::DEFAULT_PREFIX.into() }
+	}
+
+	/// Namespaces the store's keys under `prefix`; on Redis Cluster it must contain a hash tag.
+	pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+		self.prefix = prefix.into();
+
+		self
+	}
+
+	fn records_key(&self) -> String {
+		format!("{}records", self.prefix)
+	}
+
+	fn index_key(&self) -> String {
+		format!("{}index", self.prefix)
+	}
+
+	async fn query<T>(&self, cmd: &Cmd) -> Result<T, StoreError>
+	where
+		T: FromRedisValue,
+	{
+		cmd.query_async(&mut self.connection.clone()).await.map_err(backend)
+	}
+
+	async fn invoke<T>(&self, source: &str, args: &[&str]) -> Result<T, StoreError>
+	where
+		T: FromRedisValue,
+	{
+		let script = Script::new(source);
+		let mut invocation = script.key(self.records_key());
+
+		invocation.key(self.index_key()).arg(args);
+		invocation.invoke_async(&mut self.connection.clone()).await.map_err(backend)
+	}
+
+	async fn get(&self, key: &str) -> Result<Option<(String, TokenRecord)>, StoreError> {
+		let json: Option<String> =
+			self.query(redis::cmd("HGET").arg(self.records_key()).arg(key)).await?;
+
+		json.map(|json| decode(&json).map(|record| (json, record))).transpose()
+	}
+
+	/// Records whose cursor lies between `min` and `max` (in `ZRANGEBYLEX` syntax), as
+	/// `(key, json, record)` triples.
+	async fn range(
+		&self,
+		min: &str,
+		max: &str,
+		limit: Option<usize>,
+	) -> Result<Vec<(String, String, TokenRecord)>, StoreError> {
+		let limit = limit.map_or_else(|| "-1".into(), |limit| limit.to_string());
+		let flat: Vec<Option<String>> = self.invoke(LIST_SCRIPT, &[min, max, &limit]).await?;
+
+		flat.chunks(2)
+			.filter_map(|entry| match entry {
+				[Some(key), Some(json)] => Some((key, json)),
+				_ => None,
+			})
+			.map(|(key, json)| Ok((key.clone(), json.clone(), decode(json)?)))
+			.collect()
+	}
+
+	async fn put(&self, writes: &[(&str, &str)]) -> Result<(), StoreError> {
+		let args = writes.iter().flat_map(|(key, json)| [*key, *json]).collect::<Vec<_>>();
+
+		self.invoke::<i64>(PUT_SCRIPT, &args).await.map(drop)
+	}
+
+	/// Applies `(key, expected, replacement)` writes only while every key still holds its
+	/// expected JSON; an empty replacement deletes the key.
+	async fn swap(&self, writes: &[(&str, &str, &str)]) -> Result<bool, StoreError> {
+		let args = writes
+			.iter()
+			.flat_map(|(key, expected, replacement)| [*key, *expected, *replacement])
+			.collect::<Vec<_>>();
+
+		Ok(self.invoke::<i64>(SWAP_SCRIPT, &args).await? == 1)
+	}
+
+	/// Replaces the record at `key` when `matches` accepts it, reporting `mismatch` otherwise.
+	async fn cas_now<F>(
+		&self,
+		key: &str,
+		matches: F,
+		mismatch: CompareAndSwapOutcome,
+		mut replacement: TokenRecord,
+	) -> Result<CompareAndSwapOutcome, StoreError>
+	where
+		F: Fn(&TokenRecord) -> bool,
+	{
+		loop {
+			let Some((json, stored)) = self.get(key).await? else {
+				return Ok(CompareAndSwapOutcome::Missing);
+			};
+
+			if !matches(&stored) {
+				return Ok(mismatch);
+			}
+
+			replacement.version = store::next_version(&stored);
+
+			if self.swap(&[(key, &json, &encode(&replacement)?)]).await? {
+				return Ok(CompareAndSwapOutcome::Updated);
+			}
+		}
+	}
+
+	async fn revoke_now(
+		&self,
+		key: &str,
+		instant: OffsetDateTime,
+		reason: RevocationReason,
+	) -> Result<Option<TokenRecord>, StoreError> {
+		loop {
+			let Some((json, mut record)) = self.get(key).await? else {
+				return Ok(None);
+			};
+
+			store::revoke_stored(&mut record, instant, reason);
+
+			if self.swap(&[(key, &json, &encode(&record)?)]).await? {
+				return Ok(Some(record));
+			}
+		}
+	}
+
+	async fn tenant_records(
+		&self,
+		tenant: &TenantId,
+	) -> Result<Vec<(String, String, TokenRecord)>, StoreError> {
+		// Cursors join their parts with `\u{1f}`, so `\u{20}` is the first byte past the tenant.
+		let mut entries =
+			self.range(&format!("[{tenant}\u{1f}"), &format!("({tenant}\u{20}"), None).await?;
+
+		entries.retain(|(_, _, record)| record.family.tenant == *tenant);
+
+		Ok(entries)
+	}
+
+	async fn subtree_now(
+		&self,
+		tenant: &TenantId,
+		prefix: &PrincipalPath,
+		revoke: Option<(OffsetDateTime, RevocationReason)>,
+	) -> Result<Vec<TokenRecord>, StoreError> {
+		let mut records = Vec::new();
+
+		for (key, _, record) in self.tenant_records(tenant).await? {
+			if !StoreKey::new(&record.family, &record.scope)
+				.is_ok_and(|key| key.in_principal_subtree(tenant, prefix))
+			{
+				continue;
+			}
+
+			match revoke {
+				Some((instant, reason)) =>
+					records.extend(self.revoke_now(&key, instant, reason).await?),
+				None => records.push(record),
+			}
+		}
+
+		Ok(records)
+	}
+
+	async fn cas_family_now(
+		&self,
+		family: &TokenFamily,
+		expected_refresh: Option<&str>,
+		records: Vec<TokenRecord>,
+	) -> Result<CompareAndSwapOutcome, StoreError> {
+		let replacements = store::family_replacements(family, records)?
+			.into_iter()
+			.map(|(key, record)| (key.page_cursor(), record))
+			.collect::<Vec<_>>();
+
+		if replacements.is_empty() {
+			return Ok(CompareAndSwapOutcome::Missing);
+		}
+
+		loop {
+			let mut writes = Vec::with_capacity(replacements.len());
+
+			for (key, record) in &replacements {
+				let Some((json, stored)) = self.get(key).await? else {
+					return Ok(CompareAndSwapOutcome::Missing);
+				};
+
+				if stored.refresh_token.as_ref().map(TokenSecret::expose) != expected_refresh {
+					return Ok(CompareAndSwapOutcome::RefreshMismatch);
+				}
+
+				let mut record = record.clone();
+
+				record.version = store::next_version(&stored);
+				writes.push((key.as_str(), json, encode(&record)?));
+			}
+
+			let writes = writes
+				.iter()
+				.map(|(key, expected, replacement)| (*key, expected.as_str(), replacement.as_str()))
+				.collect::<Vec<_>>();
+
+			if self.swap(&writes).await? {
+				return Ok(CompareAndSwapOutcome::Updated);
+			}
+		}
+	}
+}
+impl RedisStore {
+	/// Creates a reconnecting [`ConnectionManager`] for a `redis://` URL that connects on first
+	/// use.
+	///
+	/// Must be called inside a Tokio runtime, which drives the connection.
+	pub fn connect_lazy(url: &str) -> Result<Self, StoreError> {
+		tokio::runtime::Handle::try_current().map_err(|e| StoreError::Backend {
+			message: format!("Redis connections need a Tokio runtime: {e}"),
+		})?;
+
+		let client = Client::open(url).map_err(backend)?;
+		let connection =
+			ConnectionManager::new_lazy_with_config(client, ConnectionManagerConfig::new())
+				.map_err(backend)?;
+
+		Ok(Self::new(connection))
+	}
+}
+impl<C> Debug for RedisStore<C>
+where
+	C: ConnectionLike + Clone + Send + Sync,
+{
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		f.debug_struct("RedisStore").field("prefix", &self.prefix).finish_non_exhaustive()
+	}
+}
+impl<C> BrokerStore for RedisStore<C>
+where
+	C: ConnectionLike + Clone + Send + Sync,
+{
+	fn save(&self, record: TokenRecord) -> StoreFuture<'_, ()> {
+		Box::pin(async move {
+			let key = StoreKey::new(&record.family, &record.scope)?.page_cursor();
+
+			self.put(&[(&key, &encode(&record)?)]).await
+		})
+	}
+
+	fn fetch<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+	) -> StoreFuture<'a, Option<TokenRecord>> {
+		Box::pin(async move {
+			let key = StoreKey::new(family, scope)?.page_cursor();
+
+			Ok(self.get(&key).await?.map(|(_, record)| record))
+		})
+	}
+
+	fn compare_and_swap_refresh<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+		expected_refresh: Option<&'a str>,
+		replacement: TokenRecord,
+	) -> StoreFuture<'a, CompareAndSwapOutcome> {
+		Box::pin(async move {
+			self.cas_now(
+				&StoreKey::new(family, scope)?.page_cursor(),
+				|existing| {
+					existing.refresh_token.as_ref().map(TokenSecret::expose) == expected_refresh
+				},
+				CompareAndSwapOutcome::RefreshMismatch,
+				replacement,
+			)
+			.await
+		})
+	}
+
+	fn compare_and_swap_version<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+		expected_version: u64,
+		replacement: TokenRecord,
+	) -> StoreFuture<'a, CompareAndSwapOutcome> {
+		Box::pin(async move {
+			self.cas_now(
+				&StoreKey::new(family, scope)?.page_cursor(),
+				|existing| existing.version == expected_version,
+				CompareAndSwapOutcome::VersionMismatch,
+				replacement,
+			)
+			.await
+		})
+	}
+
+	fn revoke<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+		instant: OffsetDateTime,
+		reason: RevocationReason,
+	) -> StoreFuture<'a, Option<TokenRecord>> {
+		Box::pin(async move {
+			self.revoke_now(&StoreKey::new(family, scope)?.page_cursor(), instant, reason).await
+		})
+	}
+
+	fn health_check(&self) -> StoreFuture<'_, ()> {
+		Box::pin(async move { self.query::<String>(&redis::cmd("PING")).await.map(drop) })
+	}
+
+	fn purge_revoked(&self, cutoff: OffsetDateTime) -> StoreFuture<'_, usize> {
+		Box::pin(async move {
+			let mut purged = 0;
+			let mut after = None;
+
+			loop {
+				let min = after.as_ref().map_or_else(|| "-".into(), |key| format!("({key}"));
+				let entries = self.range(&min, "+", Some(Self::PURGE_PAGE)).await?;
+
+				after = entries.last().map(|(key, _, _)| key.clone());
+
+				for (key, json, record) in &entries {
+					if record.revoked_at.is_some_and(|revoked| revoked < cutoff)
+						&& self.swap(&[(key, json, "")]).await?
+					{
+						purged += 1;
+					}
+				}
+
+				if entries.len() < Self::PURGE_PAGE {
+					return Ok(purged);
+				}
+			}
+		})
+	}
+
+	fn list_principal_subtree<'a>(
+		&'a self,
+		tenant: &'a TenantId,
+		prefix: &'a PrincipalPath,
+	) -> StoreFuture<'a, Vec<TokenRecord>> {
+		Box::pin(self.subtree_now(tenant, prefix, None))
+	}
+
+	fn revoke_principal_subtree<'a>(
+		&'a self,
+		tenant: &'a TenantId,
+		prefix: &'a PrincipalPath,
+		instant: OffsetDateTime,
+		reason: RevocationReason,
+	) -> StoreFuture<'a, Vec<TokenRecord>> {
+		Box::pin(self.subtree_now(tenant, prefix, Some((instant, reason))))
+	}
+
+	fn list_records<'a>(
+		&'a self,
+		after: Option<&'a str>,
+		limit: usize,
+	) -> StoreFuture<'a, RecordPage> {
+		Box::pin(async move {
+			let min = after.map_or_else(|| "-".into(), |cursor| format!("({cursor}"));
+			// One entry past the limit tells `RecordPage::collect` whether another page exists.
+			let entries = self.range(&min, "+", Some(limit.max(1) + 1)).await?;
+
+			Ok(RecordPage::collect(
+				entries.into_iter().map(|(key, _, record)| (key, record)),
+				limit,
+			))
+		})
+	}
+
+	fn delete_tenant<'a>(&'a self, tenant: &'a TenantId) -> StoreFuture<'a, Vec<TokenRecord>> {
+		Box::pin(async move {
+			let entries = self.tenant_records(tenant).await?;
+
+			if !entries.is_empty() {
+				self.put(&entries.iter().map(|(key, _, _)| (key.as_str(), "")).collect::<Vec<_>>())
+					.await?;
+			}
+
+			Ok(entries.into_iter().map(|(_, _, record)| record).collect())
+		})
+	}
+
+	fn compare_and_swap_family<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		expected_refresh: Option<&'a str>,
+		records: Vec<TokenRecord>,
+	) -> StoreFuture<'a, CompareAndSwapOutcome> {
+		Box::pin(self.cas_family_now(family, expected_refresh, records))
+	}
+}
+
+fn encode(record: &TokenRecord) -> Result<String, StoreError> {
+	serde_json::to_string(record).map_err(|e| StoreError::Serialization {
+		message: format!("Failed to serialize token record: {e}"),
+	})
+}
+
+fn decode(json: &str) -> Result<TokenRecord, StoreError> {
+	serde_json::from_str(json).map_err(|e| StoreError::Serialization {
+		message: format!("Failed to parse stored token record: {e}"),
+	})
+}
+
+fn backend(e: RedisError) -> StoreError {
+	StoreError::Backend { message: format!("redis: {e}") }
+}
+
+#[cfg(test)]
+mod tests {
+	// std
+	use std::collections::VecDeque;
+	// crates.io
+	use redis::{Arg, Pipeline, RedisFuture, Value};
+	use tokio::runtime::Runtime;
+	// self
+	use super::*;
+	use crate::auth::{PrincipalId, ProviderId};
+
+	/// Connection that records each command and answers with queued replies.
+	#[derive(Clone, Default)]
+	struct ScriptedConnection {
+		commands: Arc<Mutex<Vec<Vec<String>>>>,
+		replies: Arc<Mutex<VecDeque<Value>>>,
+	}
+	impl ScriptedConnection {
+		fn reply(&self, value: Value) {
+			self.replies.lock().push_back(value);
+		}
+	}
+	impl ConnectionLike for ScriptedConnection {
+		fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+			self.commands.lock().push(
+				cmd.args_iter()
+					.map(|arg| match arg {
+						Arg::Simple(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+						_ => String::new(),
+					})
+					.collect(),
+			);
+
+			let reply = self.replies.lock().pop_front().unwrap_or(Value::Nil);
+
+			Box::pin(async move { Ok(reply) })
+		}
+
+		fn req_packed_commands<'a>(
+			&'a mut self,
+			_: &'a Pipeline,
+			_: usize,
+			_: usize,
+		) -> RedisFuture<'a, Vec<Value>> {
+			Box::pin(async { Ok(Vec::new()) })
+		}
+
+		fn get_db(&self) -> i64 {
+			0
+		}
+	}
+
+	fn build_record(refresh: &str) -> TokenRecord {
+		TokenRecord::builder(
+			TokenFamily::new(
+				TenantId::new("tenant-redis").expect("Tenant fixture should be valid."),
+				PrincipalId::new("principal-redis").expect("Principal fixture should be valid."),
+			)
+			.with_provider(
+				ProviderId::new("provider-redis").expect("Provider fixture should be valid."),
+			),
+			ScopeSet::new(["read"]).expect("Scope fixture should be valid."),
+		)
+		.access_token("access")
+		.refresh_token(refresh)
+		.expires_in(Duration::hours(1))
+		.build()
+		.expect("Token record fixture should build successfully.")
+	}
+
+	fn bulk(value: &str) -> Value {
+		Value::BulkString(value.as_bytes().to_vec())
+	}
+
+	#[test]
+	fn refresh_cas_retries_when_another_writer_swapped_first() {
+		let connection = ScriptedConnection::default();
+		let store = RedisStore::new(connection.clone()).with_prefix("{tokens}:");
+		let current = build_record("refresh-old");
+		let json = serde_json::to_string(&current).expect("Record fixture should serialize.");
+		let rt = Runtime::new().expect("Failed to build Tokio runtime for Redis store test.");
+
+		// The first swap loses a race, the second lands on the re-read record.
+		for swapped in [0, 1] {
+			connection.reply(bulk(&json));
+			connection.reply(Value::Int(swapped));
+		}
+
+		let outcome = rt.block_on(store.compare_and_swap_refresh(
+			&current.family,
+			&current.scope,
+			Some("refresh-old"),
+			build_record("refresh-new"),
+		));
+
+		assert_eq!(outcome.expect("CAS should succeed."), CompareAndSwapOutcome::Updated);
+
+		let commands = connection.commands.lock().clone();
+		let key = StoreKey::new(&current.family, &current.scope)
+			.expect("Store key fixture should build.")
+			.page_cursor();
+
+		assert_eq!(commands.len(), 4);
+		assert_eq!(commands[0], ["HGET", "{tokens}:records", key.as_str()]);
+		assert_eq!(commands[3][0], "EVALSHA");
+		assert_eq!(commands[3][2..5], ["2", "{tokens}:records", "{tokens}:index"]);
+		assert_eq!(commands[3][5], key);
+		assert_eq!(commands[3][6], json);
+		assert!(commands[3][7].contains("\"version\":1"));
+
+		connection.reply(bulk(&json));
+
+		let outcome = rt.block_on(store.compare_and_swap_refresh(
+			&current.family,
+			&current.scope,
+			Some("refresh-stale"),
+			build_record("refresh-new"),
+		));
+
+		assert_eq!(outcome.expect("CAS should complete."), CompareAndSwapOutcome::RefreshMismatch);
+		assert_eq!(connection.commands.lock().len(), 5, "A mismatch must not write.");
+	}
+
+	#[test]
+	fn list_records_reads_one_entry_past_the_cursor_and_limit() {
+		let connection = ScriptedConnection::default();
+		let store = RedisStore::new(connection.clone());
+		let record = build_record("refresh");
+		let json = serde_json::to_string(&record).expect("Record fixture should serialize.");
+		let rt = Runtime::new().expect("Failed to build Tokio runtime for Redis store test.");
+
+		connection.reply(Value::Array(vec![
+			bulk("b"),
+			bulk(&json),
+			bulk("c"),
+			bulk(&json),
+			bulk("d"),
+			bulk(&json),
+		]));
+
+		let page = rt.block_on(store.list_records(Some("a"), 2)).expect("Listing should succeed.");
+
+		assert_eq!(page.records.len(), 2);
+		assert_eq!(page.next.as_deref(), Some("c"));
+		assert_eq!(connection.commands.lock()[0][5..], ["(a", "+", "3"]);
+	}
+
+	#[test]
+	fn connect_lazy_needs_a_runtime_and_a_redis_url() {
+		let rt = Runtime::new().expect("Failed to build Tokio runtime for Redis store test.");
+
+		assert!(RedisStore::connect_lazy("redis://127.0.0.1").is_err(), "No runtime is entered.");
+
+		rt.block_on(async {
+			assert!(RedisStore::connect_lazy("postgres://localhost").is_err());
+
+			let store = RedisStore::connect_lazy("redis://127.0.0.1:6379/0")
+				.expect("A Redis URL should create a lazy connection.");
+
+			assert_eq!(format!("{store:?}"), "RedisStore { prefix: \"{oauth2-broker}:\", .. }");
+		});
+	}
+}
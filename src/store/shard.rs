@@ -0,0 +1,86 @@
+//! Hash-sharded containers that spread lock contention across independent slots.
+
+// self
+use crate::_prelude::*;
+
+/// Fixed set of `T` slots selected by key hash.
+///
+/// Wrapping a lock-protected map in `Sharded` lets unrelated keys proceed in parallel instead of
+/// serializing on one lock. The shard count is rounded up to a power of two.
+#[derive(Debug)]
+pub struct Sharded<T> {
+	shards: Box<[T]>,
+}
+impl<T> Sharded<T> {
+	/// Default number of shards used by [`Sharded::default`].
+	pub const DEFAULT_SHARDS: usize = 32;
+
+	/// Builds `count` shards (rounded up to a power of two, at least 1) using `init`.
+	pub fn new(count: usize, init: impl FnMut() -> T) -> Self {
+		let count = count.max(1).next_power_of_two();
+
+		Self { shards: std::iter::repeat_with(init).take(count).collect() }
+	}
+
+	/// Returns the shard responsible for `key`.
+	pub fn for_key<K>(&self, key: &K) -> &T
+	where
+		K: ?Sized + Hash,
+	{
+		&self.shards[self.index_for(key)]
+	}
+
+	/// Returns the position of the shard responsible for `key` within [`Sharded::iter`].
+	///
+	/// Operations that lock several shards at once take them in ascending index order so they
+	/// cannot deadlock with each other.
+	pub fn index_for<K>(&self, key: &K) -> usize
+	where
+		K: ?Sized + Hash,
+	{
+		let mut hasher = DefaultHasher::new();
+
+		key.hash(&mut hasher);
+
+		// The shard count is a power of two, so masking keeps the index in bounds.
+		hasher.finish() as usize & (self.shards.len() - 1)
+	}
+
+	/// Iterator over every shard, for operations that span all keys.
+	pub fn iter(&self) -> impl Iterator<Item = &T> {
+		self.shards.iter()
+	}
+
+	/// Number of shards.
+	pub fn len(&self) -> usize {
+		self.shards.len()
+	}
+
+	/// Always `false`; a sharded container holds at least one shard.
+	pub fn is_empty(&self) -> bool {
+		self.shards.is_empty()
+	}
+}
+impl<T> Default for Sharded<T>
+where
+	T: Default,
+{
+	fn default() -> Self {
+		Self::new(Self::DEFAULT_SHARDS, T::default)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	// self
+	use super::*;
+
+	#[test]
+	fn shard_count_rounds_up_and_keys_map_stably() {
+		let sharded = Sharded::new(5, || Mutex::new(0_u32));
+
+		assert_eq!(sharded.len(), 8);
+		assert!(std::ptr::eq(sharded.for_key("family-a"), sharded.for_key("family-a")));
+		assert_eq!(Sharded::new(0, || ()).len(), 1);
+	}
+}
@@ -0,0 +1,736 @@
+//! Embedded [sled](https://docs.rs/sled) [`BrokerStore`] for single-binary bots.
+//!
+//! Records live in a `records` tree keyed by [`StoreKey::page_cursor`], two-phase writes in a
+//! `pending` tree keyed by their [`PreparedWrite::id`], and
+//! [`QuotaPolicy`](crate::ext::QuotaPolicy) call logs in a `quota` tree keyed by their
+//! [`QuotaKey`], so quota budgets survive restarts. Unlike [`FileStore`](super::FileStore),
+//! records are indexed on disk instead of loaded into memory and compacted into snapshots, and
+//! every conditional write is a sled `compare_and_swap` against the exact bytes that were read, so
+//! concurrent writers never lose an update.
+
+// std
+use std::{ops::Bound, path::Path};
+// crates.io
+use sled::{
+	Db, IVec, Tree,
+	transaction::{ConflictableTransactionError, TransactionError, Transactional},
+};
+// self
+use crate::{
+	_prelude::*,
+	auth::{
+		PrincipalPath, RevocationReason, ScopeSet, TenantId, TokenFamily, TokenRecord, TokenSecret,
+	},
+	ext::{QuotaKey, QuotaStore},
+	store::{
+		self, BrokerStore, CompareAndSwapOutcome, PreparedWrite, RecordPage, StoreError,
+		StoreFuture, StoreKey,
+	},
+};
+
+/// Persists broker records in an embedded sled database.
+///
+/// Every mutation is flushed before it returns, so a record acknowledged to the broker survives
+/// a crash. Clones share the same database handle.
+#[derive(Clone, Debug)]
+pub struct SledStore {
+	db: Db,
+	records: Tree,
+	pending: Tree,
+	quota: Tree,
+}
+impl SledStore {
+	/// Opens (or creates) a database in the directory at `path`.
+	pub fn open(path: impl AsRef<Path>) -> Result<Self, StoreError> {
+		let path = path.as_ref();
+		let db = sled::open(path).map_err(|e| StoreError::Backend {
+			message: format!("Failed to open sled database {}: {e}", path.display()),
+		})?;
+
+		Self::from_db(db)
+	}
+
+	/// Uses an already opened database, e.g. one configured through `sled::Config` or shared with
+	/// other application data.
+	pub fn from_db(db: Db) -> Result<Self, StoreError> {
+		let records = db.open_tree("records").map_err(backend)?;
+		let pending = db.open_tree("pending").map_err(backend)?;
+		let quota = db.open_tree("quota").map_err(backend)?;
+
+		Ok(Self { db, records, pending, quota })
+	}
+
+	fn key_for(family: &TokenFamily, scope: &ScopeSet) -> Result<String, StoreError> {
+		Ok(StoreKey::new(family, scope)?.page_cursor())
+	}
+
+	async fn flush(&self) -> Result<(), StoreError> {
+		self.db.flush_async().await.map(drop).map_err(backend)
+	}
+
+	/// Replaces the record at `key` when `matches` accepts it, reporting `mismatch` otherwise.
+	///
+	/// The write is a `compare_and_swap` against the bytes that were checked, retried when another
+	/// writer changed the record in between.
+	fn cas_now<F>(
+		&self,
+		key: &str,
+		matches: F,
+		mismatch: CompareAndSwapOutcome,
+		mut replacement: TokenRecord,
+	) -> Result<CompareAndSwapOutcome, StoreError>
+	where
+		F: Fn(&TokenRecord) -> bool,
+	{
+		loop {
+			let Some(current) = self.records.get(key).map_err(backend)? else {
+				return Ok(CompareAndSwapOutcome::Missing);
+			};
+			let stored = decode(&current)?;
+
+			if !matches(&stored) {
+				return Ok(mismatch);
+			}
+
+			replacement.version = store::next_version(&stored);
+
+			if self.swap(key, &current, Some(encode(&replacement)?))? {
+				return Ok(CompareAndSwapOutcome::Updated);
+			}
+		}
+	}
+
+	fn revoke_now(
+		&self,
+		key: &str,
+		instant: OffsetDateTime,
+		reason: RevocationReason,
+	) -> Result<Option<TokenRecord>, StoreError> {
+		loop {
+			let Some(current) = self.records.get(key).map_err(backend)? else {
+				return Ok(None);
+			};
+			let mut record = decode(&current)?;
+
+			store::revoke_stored(&mut record, instant, reason);
+
+			if self.swap(key, &current, Some(encode(&record)?))? {
+				return Ok(Some(record));
+			}
+		}
+	}
+
+	/// Writes `new` (or removes the key) only while it still holds `current`.
+	fn swap(&self, key: &str, current: &IVec, new: Option<Vec<u8>>) -> Result<bool, StoreError> {
+		Ok(self.records.compare_and_swap(key, Some(current), new).map_err(backend)?.is_ok())
+	}
+
+	/// Records whose key starts with `tenant`, as `(key, bytes, record)` triples.
+	fn tenant_records(
+		&self,
+		tenant: &TenantId,
+	) -> Result<Vec<(IVec, IVec, TokenRecord)>, StoreError> {
+		let prefix = format!("{tenant}\u{1f}");
+		let mut entries = Vec::new();
+
+		for entry in self.records.scan_prefix(prefix.as_bytes()) {
+			let (key, bytes) = entry.map_err(backend)?;
+			let record = decode(&bytes)?;
+
+			if record.family.tenant == *tenant {
+				entries.push((key, bytes, record));
+			}
+		}
+
+		Ok(entries)
+	}
+
+	fn subtree_now(
+		&self,
+		tenant: &TenantId,
+		prefix: &PrincipalPath,
+	) -> Result<Vec<(IVec, IVec, TokenRecord)>, StoreError> {
+		Ok(self
+			.tenant_records(tenant)?
+			.into_iter()
+			.filter(|(_, _, record)| {
+				StoreKey::new(&record.family, &record.scope)
+					.is_ok_and(|key| key.in_principal_subtree(tenant, prefix))
+			})
+			.collect())
+	}
+
+	fn cas_family_now(
+		&self,
+		family: &TokenFamily,
+		expected_refresh: Option<&str>,
+		records: Vec<TokenRecord>,
+	) -> Result<CompareAndSwapOutcome, StoreError> {
+		let replacements = store::family_replacements(family, records)?
+			.into_iter()
+			.map(|(key, record)| (key.page_cursor(), record))
+			.collect::<Vec<_>>();
+
+		if replacements.is_empty() {
+			return Ok(CompareAndSwapOutcome::Missing);
+		}
+
+		let result = self.records.transaction(|tree| {
+			let mut writes = Vec::with_capacity(replacements.len());
+
+			for (key, record) in &replacements {
+				let Some(current) = tree.get(key)? else {
+					return Err(ConflictableTransactionError::Abort(Ok(
+						CompareAndSwapOutcome::Missing,
+					)));
+				};
+				let stored =
+					decode(&current).map_err(|e| ConflictableTransactionError::Abort(Err(e)))?;
+
+				if stored.refresh_token.as_ref().map(TokenSecret::expose) != expected_refresh {
+					return Err(ConflictableTransactionError::Abort(Ok(
+						CompareAndSwapOutcome::RefreshMismatch,
+					)));
+				}
+
+				let mut record = record.clone();
+
+				record.version = store::next_version(&stored);
+				writes.push((
+					key,
+					encode(&record).map_err(|e| ConflictableTransactionError::Abort(Err(e)))?,
+				));
+			}
+			for (key, bytes) in writes {
+				tree.insert(key.as_bytes(), bytes)?;
+			}
+
+			Ok(CompareAndSwapOutcome::Updated)
+		});
+
+		match result {
+			Ok(outcome) => Ok(outcome),
+			Err(TransactionError::Abort(aborted)) => aborted,
+			Err(TransactionError::Storage(e)) => Err(backend(e)),
+		}
+	}
+}
+impl BrokerStore for SledStore {
+	fn save(&self, record: TokenRecord) -> StoreFuture<'_, ()> {
+		Box::pin(async move {
+			let key = Self::key_for(&record.family, &record.scope)?;
+
+			self.records.insert(key, encode(&record)?).map_err(backend)?;
+			self.flush().await
+		})
+	}
+
+	fn fetch<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+	) -> StoreFuture<'a, Option<TokenRecord>> {
+		Box::pin(async move {
+			self.records
+				.get(Self::key_for(family, scope)?)
+				.map_err(backend)?
+				.map(|bytes| decode(&bytes))
+				.transpose()
+		})
+	}
+
+	fn compare_and_swap_refresh<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+		expected_refresh: Option<&'a str>,
+		replacement: TokenRecord,
+	) -> StoreFuture<'a, CompareAndSwapOutcome> {
+		Box::pin(async move {
+			let outcome = self.cas_now(
+				&Self::key_for(family, scope)?,
+				|existing| {
+					existing.refresh_token.as_ref().map(TokenSecret::expose) == expected_refresh
+				},
+				CompareAndSwapOutcome::RefreshMismatch,
+				replacement,
+			)?;
+
+			self.flush().await?;
+
+			Ok(outcome)
+		})
+	}
+
+	fn compare_and_swap_version<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+		expected_version: u64,
+		replacement: TokenRecord,
+	) -> StoreFuture<'a, CompareAndSwapOutcome> {
+		Box::pin(async move {
+			let outcome = self.cas_now(
+				&Self::key_for(family, scope)?,
+				|existing| existing.version == expected_version,
+				CompareAndSwapOutcome::VersionMismatch,
+				replacement,
+			)?;
+
+			self.flush().await?;
+
+			Ok(outcome)
+		})
+	}
+
+	fn revoke<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+		instant: OffsetDateTime,
+		reason: RevocationReason,
+	) -> StoreFuture<'a, Option<TokenRecord>> {
+		Box::pin(async move {
+			let revoked = self.revoke_now(&Self::key_for(family, scope)?, instant, reason)?;
+
+			self.flush().await?;
+
+			Ok(revoked)
+		})
+	}
+
+	fn prepare(&self, record: TokenRecord) -> StoreFuture<'_, PreparedWrite> {
+		Box::pin(async move {
+			let prepared = PreparedWrite::new(record);
+
+			self.pending.insert(&prepared.id, encode(&prepared.record)?).map_err(backend)?;
+			self.flush().await?;
+
+			Ok(prepared)
+		})
+	}
+
+	fn commit<'a>(&'a self, prepared: &'a PreparedWrite) -> StoreFuture<'a, ()> {
+		Box::pin(async move {
+			let record = &prepared.record;
+			let key = Self::key_for(&record.family, &record.scope)?;
+			let bytes = encode(record)?;
+
+			// Publishing the record and dropping the staged copy happen in one transaction, so a
+			// crash never leaves both or neither.
+			(&self.records, &self.pending)
+				.transaction(|(records, pending)| {
+					records.insert(key.as_bytes(), bytes.as_slice())?;
+					pending.remove(prepared.id.as_bytes())?;
+
+					Ok::<_, ConflictableTransactionError>(())
+				})
+				.map_err(|e| backend(e.to_string()))?;
+
+			self.flush().await
+		})
+	}
+
+	fn rollback<'a>(&'a self, prepared: &'a PreparedWrite) -> StoreFuture<'a, ()> {
+		Box::pin(async move {
+			self.pending.remove(&prepared.id).map_err(backend)?;
+			self.flush().await
+		})
+	}
+
+	fn pending_writes(&self) -> StoreFuture<'_, Vec<PreparedWrite>> {
+		Box::pin(async move {
+			self.pending
+				.iter()
+				.map(|entry| {
+					let (id, bytes) = entry.map_err(backend)?;
+
+					Ok(PreparedWrite {
+						id: String::from_utf8_lossy(&id).into_owned(),
+						record: decode(&bytes)?,
+					})
+				})
+				.collect()
+		})
+	}
+
+	fn health_check(&self) -> StoreFuture<'_, ()> {
+		Box::pin(async move {
+			// A write plus flush surfaces a full disk or a read-only mount before a real write.
+			self.db
+				.insert("health", OffsetDateTime::now_utc().unix_timestamp().to_be_bytes().to_vec())
+				.map_err(backend)?;
+			self.flush().await
+		})
+	}
+
+	fn purge_revoked(&self, cutoff: OffsetDateTime) -> StoreFuture<'_, usize> {
+		Box::pin(async move {
+			let mut purged = 0;
+
+			for entry in self.records.iter() {
+				let (key, bytes) = entry.map_err(backend)?;
+
+				if decode(&bytes)?.revoked_at.is_some_and(|revoked| revoked < cutoff)
+					&& self
+						.records
+						.compare_and_swap(&key, Some(&bytes), None::<IVec>)
+						.map_err(backend)?
+						.is_ok()
+				{
+					purged += 1;
+				}
+			}
+
+			self.flush().await?;
+
+			Ok(purged)
+		})
+	}
+
+	fn list_principal_subtree<'a>(
+		&'a self,
+		tenant: &'a TenantId,
+		prefix: &'a PrincipalPath,
+	) -> StoreFuture<'a, Vec<TokenRecord>> {
+		Box::pin(async move {
+			Ok(self.subtree_now(tenant, prefix)?.into_iter().map(|(_, _, record)| record).collect())
+		})
+	}
+
+	fn revoke_principal_subtree<'a>(
+		&'a self,
+		tenant: &'a TenantId,
+		prefix: &'a PrincipalPath,
+		instant: OffsetDateTime,
+		reason: RevocationReason,
+	) -> StoreFuture<'a, Vec<TokenRecord>> {
+		Box::pin(async move {
+			let mut revoked = Vec::new();
+
+			for (key, _, _) in self.subtree_now(tenant, prefix)? {
+				revoked.extend(self.revoke_now(&String::from_utf8_lossy(&key), instant, reason)?);
+			}
+
+			self.flush().await?;
+
+			Ok(revoked)
+		})
+	}
+
+	fn list_records<'a>(
+		&'a self,
+		after: Option<&'a str>,
+		limit: usize,
+	) -> StoreFuture<'a, RecordPage> {
+		Box::pin(async move {
+			let start = after.map_or(Bound::Unbounded, |cursor| Bound::Excluded(cursor.as_bytes()));
+			let mut entries = Vec::new();
+
+			// One entry past the limit tells `RecordPage::collect` whether another page exists.
+			for entry in
+				self.records.range::<&[u8], _>((start, Bound::Unbounded)).take(limit.max(1) + 1)
+			{
+				let (key, bytes) = entry.map_err(backend)?;
+
+				entries.push((String::from_utf8_lossy(&key).into_owned(), decode(&bytes)?));
+			}
+
+			Ok(RecordPage::collect(entries, limit))
+		})
+	}
+
+	fn delete_tenant<'a>(&'a self, tenant: &'a TenantId) -> StoreFuture<'a, Vec<TokenRecord>> {
+		Box::pin(async move {
+			let mut deleted = Vec::new();
+
+			for (key, _, record) in self.tenant_records(tenant)? {
+				if self.records.remove(&key).map_err(backend)?.is_some() {
+					deleted.push(record);
+				}
+			}
+			for entry in self.pending.iter() {
+				let (id, bytes) = entry.map_err(backend)?;
+
+				if decode(&bytes)?.family.tenant == *tenant {
+					self.pending.remove(&id).map_err(backend)?;
+				}
+			}
+
+			self.flush().await?;
+
+			Ok(deleted)
+		})
+	}
+
+	fn compare_and_swap_family<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		expected_refresh: Option<&'a str>,
+		records: Vec<TokenRecord>,
+	) -> StoreFuture<'a, CompareAndSwapOutcome> {
+		Box::pin(async move {
+			let outcome = self.cas_family_now(family, expected_refresh, records)?;
+
+			self.flush().await?;
+
+			Ok(outcome)
+		})
+	}
+}
+
+impl QuotaStore for SledStore {
+	fn record_call<'a>(
+		&'a self,
+		key: &'a QuotaKey,
+		at: OffsetDateTime,
+		retain: Duration,
+	) -> StoreFuture<'a, ()> {
+		Box::pin(async move {
+			let slot = quota_slot(key)?;
+			let cutoff = at - retain;
+
+			// Brokers sharing the store may log calls concurrently; retry until the swap lands on
+			// the log that was read.
+			loop {
+				let current = self.quota.get(&slot).map_err(backend)?;
+				let mut calls = decode_calls(current.as_deref())?;
+
+				calls.push(at);
+				calls.sort_unstable();
+				calls.retain(|call| *call >= cutoff);
+
+				let updated = serde_json::to_vec(&calls)
+					.map_err(|e| StoreError::Serialization { message: e.to_string() })?;
+
+				if self
+					.quota
+					.compare_and_swap(&slot, current, Some(updated))
+					.map_err(backend)?
+					.is_ok()
+				{
+					break;
+				}
+			}
+
+			self.flush().await
+		})
+	}
+
+	fn calls_since<'a>(
+		&'a self,
+		key: &'a QuotaKey,
+		since: OffsetDateTime,
+	) -> StoreFuture<'a, Vec<OffsetDateTime>> {
+		Box::pin(async move {
+			let current = self.quota.get(quota_slot(key)?).map_err(backend)?;
+			let mut calls = decode_calls(current.as_deref())?;
+
+			calls.retain(|call| *call >= since);
+
+			Ok(calls)
+		})
+	}
+}
+
+fn quota_slot(key: &QuotaKey) -> Result<Vec<u8>, StoreError> {
+	serde_json::to_vec(key).map_err(|e| StoreError::Serialization { message: e.to_string() })
+}
+
+fn decode_calls(bytes: Option<&[u8]>) -> Result<Vec<OffsetDateTime>, StoreError> {
+	bytes.map_or(Ok(Vec::new()), |bytes| {
+		serde_json::from_slice(bytes).map_err(|e| StoreError::Serialization {
+			message: format!("Failed to parse sled quota log: {e}"),
+		})
+	})
+}
+
+fn encode(record: &TokenRecord) -> Result<Vec<u8>, StoreError> {
+	serde_json::to_vec(record).map_err(|e| StoreError::Serialization {
+		message: format!("Failed to serialize token record: {e}"),
+	})
+}
+
+fn decode(bytes: &[u8]) -> Result<TokenRecord, StoreError> {
+	serde_json::from_slice(bytes).map_err(|e| StoreError::Serialization {
+		message: format!("Failed to parse stored token record: {e}"),
+	})
+}
+
+fn backend(e: impl Display) -> StoreError {
+	StoreError::Backend { message: format!("sled: {e}") }
+}
+
+#[cfg(test)]
+mod tests {
+	// std
+	use std::{env, fs, process, thread};
+	// crates.io
+	use tokio::runtime::Runtime;
+	// self
+	use super::*;
+	use crate::{
+		auth::{PrincipalId, ProviderId},
+		ext::{QuotaLimit, QuotaPolicy},
+	};
+
+	fn temp_dir() -> std::path::PathBuf {
+		env::temp_dir().join(format!(
+			"oauth2_broker_sled_store_{}_{}",
+			process::id(),
+			OffsetDateTime::now_utc().unix_timestamp_nanos(),
+		))
+	}
+
+	// sled's background threads may hold the previous handle (and its file lock) briefly after the
+	// store is dropped.
+	fn reopen(dir: &Path) -> SledStore {
+		for _ in 0..50 {
+			if let Ok(store) = SledStore::open(dir) {
+				return store;
+			}
+
+			thread::sleep(std::time::Duration::from_millis(20));
+		}
+
+		SledStore::open(dir).expect("Failed to reopen sled store.")
+	}
+
+	fn build_record(scope: &str) -> TokenRecord {
+		let family = TokenFamily::new(
+			TenantId::new("tenant-sled").expect("Tenant fixture should be valid."),
+			PrincipalId::new("principal-sled").expect("Principal fixture should be valid."),
+		)
+		.with_provider(
+			ProviderId::new("provider-sled").expect("Provider fixture should be valid."),
+		);
+
+		TokenRecord::builder(
+			family,
+			ScopeSet::new([scope]).expect("Scope fixture should be valid."),
+		)
+		.access_token("access")
+		.refresh_token("refresh")
+		.expires_in(Duration::hours(1))
+		.build()
+		.expect("Token record fixture should build successfully.")
+	}
+
+	#[test]
+	fn records_and_staged_writes_survive_reopen() {
+		let dir = temp_dir();
+		let rt = Runtime::new().expect("Failed to build Tokio runtime for sled store test.");
+		let record = build_record("read");
+		let (family, scope) = (record.family.clone(), record.scope.clone());
+
+		{
+			let store = SledStore::open(&dir).expect("Failed to open sled store.");
+			let mut rotated = record.clone();
+
+			rotated.refresh_token = Some(TokenSecret::new("refresh-2"));
+
+			rt.block_on(store.save(record.clone())).expect("Failed to save fixture record.");
+			assert_eq!(
+				rt.block_on(store.compare_and_swap_refresh(
+					&family,
+					&scope,
+					Some("stale"),
+					rotated.clone()
+				))
+				.expect("CAS should complete."),
+				CompareAndSwapOutcome::RefreshMismatch
+			);
+			assert_eq!(
+				rt.block_on(store.compare_and_swap_refresh(
+					&family,
+					&scope,
+					Some("refresh"),
+					rotated
+				))
+				.expect("CAS should complete."),
+				CompareAndSwapOutcome::Updated
+			);
+			rt.block_on(store.prepare(build_record("write"))).expect("Failed to stage record.");
+		}
+
+		let store = reopen(&dir);
+		let fetched = rt
+			.block_on(store.fetch(&family, &scope))
+			.expect("Failed to fetch from sled store.")
+			.expect("Record should survive reopen.");
+
+		assert_eq!(fetched.refresh_token.as_ref().map(TokenSecret::expose), Some("refresh-2"));
+		assert_eq!(fetched.version, 1);
+
+		let pending = rt.block_on(store.pending_writes()).expect("Failed to list staged writes.");
+
+		assert_eq!(pending.len(), 1);
+
+		rt.block_on(store.commit(&pending[0])).expect("Failed to commit staged write.");
+
+		assert!(
+			rt.block_on(store.pending_writes()).expect("Failed to list staged writes.").is_empty()
+		);
+
+		let page = rt.block_on(store.list_records(None, 1)).expect("Failed to list records.");
+
+		assert_eq!(page.records.len(), 1);
+
+		let rest = rt
+			.block_on(store.list_records(page.next.as_deref(), 10))
+			.expect("Failed to list records.");
+
+		assert_eq!(rest.records.len(), 1);
+		assert!(rest.next.is_none());
+
+		let tenant = family.tenant.clone();
+
+		assert_eq!(
+			rt.block_on(store.delete_tenant(&tenant)).expect("Failed to erase tenant.").len(),
+			2
+		);
+
+		drop(store);
+		fs::remove_dir_all(&dir).unwrap_or_else(|e| {
+			panic!("Failed to remove temporary sled directory {}: {e}", dir.display())
+		});
+	}
+
+	#[test]
+	fn quota_logs_survive_reopen() {
+		let dir = temp_dir();
+		let rt = Runtime::new().expect("Failed to build Tokio runtime for sled store test.");
+		let provider = ProviderId::new("provider-sled").expect("Provider fixture should be valid.");
+		let tenant = TenantId::new("tenant-sled").expect("Tenant fixture should be valid.");
+		let limit = QuotaLimit::new(2, Duration::minutes(1));
+		let start = OffsetDateTime::now_utc();
+
+		{
+			let store = SledStore::open(&dir).expect("Failed to open sled store.");
+			let policy = QuotaPolicy::new(Arc::new(store)).with_tenant_limit(limit);
+
+			for offset in [0, 10] {
+				rt.block_on(policy.record(&provider, &tenant, start + Duration::seconds(offset)))
+					.expect("Failed to record a quota call.");
+			}
+		}
+
+		let store = reopen(&dir);
+		let key = QuotaKey::tenant(provider.clone(), tenant.clone());
+		let calls = rt.block_on(store.calls_since(&key, start)).expect("Failed to read quota log.");
+
+		assert_eq!(calls, [start, start + Duration::seconds(10)]);
+
+		let policy = QuotaPolicy::new(Arc::new(store)).with_tenant_limit(limit);
+		let budget = rt
+			.block_on(policy.remaining(&provider, &tenant, start + Duration::seconds(20)))
+			.expect("Failed to compute the remaining budget.");
+
+		assert!(budget.is_exhausted());
+		assert_eq!(budget.resets_at, Some(start + Duration::minutes(1)));
+
+		fs::remove_dir_all(&dir).unwrap_or_else(|e| {
+			panic!("Failed to remove temporary sled directory {}: {e}", dir.display())
+		});
+	}
+}
@@ -0,0 +1,829 @@
+//! SQL-backed [`BrokerStore`] for Postgres, MySQL, and SQLite.
+//!
+//! The store owns the schema and every statement; the connection is a [`SqlExecutor`].
+//! [`SqlStore::connect_lazy`] opens a `sqlx` [`AnyPool`] from a DSN, and callers that already hold
+//! a pool or driver of their own implement the trait over it (binding each [`SqlValue`] in order
+//! and reading result columns as optional strings).
+//! Each record is one row keyed by [`StoreKey::page_cursor`] and holds the record as JSON next to
+//! the columns the store filters on. Refresh CAS is a single
+//! `UPDATE ... WHERE store_key = ? AND refresh_token = ?`; revocation is a guarded update that
+//! only applies while the row still holds the JSON it was read with.
+
+// crates.io
+use sqlx::{AnyPool, Row, any::AnyArguments, query::Query};
+// self
+use crate::{
+	_prelude::*,
+	auth::{PrincipalPath, RevocationReason, ScopeSet, TenantId, TokenFamily, TokenRecord},
+	store::{
+		self, BrokerStore, CompareAndSwapOutcome, RecordPage, StoreError, StoreFuture, StoreKey,
+	},
+};
+
+/// Row returned by [`SqlExecutor::query`], one optional text value per selected column.
+pub type SqlRow = Vec<Option<String>>;
+
+/// Connection the [`SqlStore`] runs its statements on.
+///
+/// Statements use the placeholder syntax of the store's [`SqlDialect`] and bind `params` in
+/// order. Text columns are the only ones the store selects.
+pub trait SqlExecutor
+where
+	Self: Send + Sync,
+{
+	/// Runs a statement and returns the number of affected rows.
+	fn execute<'a>(&'a self, sql: &'a str, params: &'a [SqlValue]) -> StoreFuture<'a, u64>;
+
+	/// Runs a query and returns its rows.
+	fn query<'a>(&'a self, sql: &'a str, params: &'a [SqlValue]) -> StoreFuture<'a, Vec<SqlRow>>;
+}
+
+/// SQL flavor the store generates statements for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SqlDialect {
+	/// PostgreSQL (`$1` placeholders, `ON CONFLICT` upserts).
+	Postgres,
+	/// MySQL and MariaDB (`?` placeholders, `ON DUPLICATE KEY` upserts).
+	MySql,
+	/// SQLite 3.24+ (`?` placeholders, `ON CONFLICT` upserts).
+	Sqlite,
+}
+impl SqlDialect {
+	/// Picks the dialect from the scheme of a `sqlx` connection string.
+	fn for_dsn(dsn: &str) -> Result<Self, StoreError> {
+		match dsn.split_once(':').map(|(scheme, _)| scheme) {
+			Some("postgres" | "postgresql") => Ok(Self::Postgres),
+			Some("mysql" | "mariadb") => Ok(Self::MySql),
+			Some("sqlite") => Ok(Self::Sqlite),
+			_ => Err(StoreError::Backend {
+				message: "SQL connection string must use a postgres, mysql, or sqlite scheme"
+					.into(),
+			}),
+		}
+	}
+
+	fn quote(self, identifier: &str) -> String {
+		match self {
+			Self::MySql => format!("`{}`", identifier.replace('`', "``")),
+			Self::Postgres | Self::Sqlite => format!("\"{}\"", identifier.replace('"', "\"\"")),
+		}
+	}
+
+	/// Rewrites the `?` placeholders of `sql` into the dialect's syntax.
+	fn bind(self, sql: &str) -> String {
+		if self != Self::Postgres {
+			return sql.into();
+		}
+
+		let mut position = 0;
+
+		sql.split('?')
+			.enumerate()
+			.map(|(index, part)| {
+				if index == 0 {
+					return part.to_owned();
+				}
+
+				position += 1;
+
+				format!("${position}{part}")
+			})
+			.collect()
+	}
+}
+
+/// Value bound to a statement parameter.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SqlValue {
+	/// Text parameter.
+	Text(String),
+	/// 64-bit integer parameter.
+	Int(i64),
+	/// SQL `NULL`.
+	Null,
+}
+impl From<Option<&str>> for SqlValue {
+	fn from(value: Option<&str>) -> Self {
+		value.map_or(Self::Null, |value| Self::Text(value.into()))
+	}
+}
+
+impl SqlExecutor for AnyPool {
+	fn execute<'a>(&'a self, sql: &'a str, params: &'a [SqlValue]) -> StoreFuture<'a, u64> {
+		Box::pin(async move {
+			let result = bind_any(sqlx::query(sql), params).execute(self).await.map_err(backend)?;
+
+			Ok(result.rows_affected())
+		})
+	}
+
+	fn query<'a>(&'a self, sql: &'a str, params: &'a [SqlValue]) -> StoreFuture<'a, Vec<SqlRow>> {
+		Box::pin(async move {
+			let rows = bind_any(sqlx::query(sql), params).fetch_all(self).await.map_err(backend)?;
+
+			rows.iter()
+				.map(|row| {
+					(0..row.len())
+						.map(|column| row.try_get::<Option<String>, _>(column))
+						.collect::<Result<_, _>>()
+						.map_err(backend)
+				})
+				.collect()
+		})
+	}
+}
+
+/// Persists broker records in a SQL table through a caller-provided [`SqlExecutor`].
+///
+/// Refresh swaps are a single `UPDATE` guarded by the `refresh_token` column, so they store the
+/// replacement's [`version`](TokenRecord::version) as given; version swaps and revocations
+/// rewrite the row only while it still holds the JSON they read.
+pub struct SqlStore<E>
+where
+	E: SqlExecutor,
+{
+	executor: Arc<E>,
+	dialect: SqlDialect,
+	table: String,
+}
+impl<E> SqlStore<E>
+where
+	E: SqlExecutor,
+{
+	/// Table used unless [`SqlStore::with_table`] overrides it.
+	pub const DEFAULT_TABLE: &str = "oauth2_broker_tokens";
+	const MAX_GUARDED_ATTEMPTS: usize = 8;
+
+	/// Creates a store that issues `dialect` statements on `executor`.
+	pub fn new(executor: Arc<E>, dialect: SqlDialect) -> Self {
+		Self { executor, dialect, table: Self::DEFAULT_TABLE.into() }
+	}
+
+	/// Stores records in `table` (quoted, so any name is accepted).
+	pub fn with_table(mut self, table: impl Into<String>) -> Self {
+		self.table = table.into();
+
+		self
+	}
+
+	/// Statements that create the token table and its tenant index when missing.
+	///
+	/// Deployments that manage schema with their own migration tool can copy these; others call
+	/// [`migrate`](Self::migrate).
+	pub fn schema(&self) -> Vec<String> {
+		let table = self.dialect.quote(&self.table);
+		let index = self.dialect.quote(&format!("{}_tenant_idx", self.table));
+		// MySQL caps indexed key length and has no `CREATE INDEX IF NOT EXISTS`.
+		let (key_type, text_type) = match self.dialect {
+			SqlDialect::MySql => ("VARCHAR(768)", "LONGTEXT"),
+			SqlDialect::Postgres | SqlDialect::Sqlite => ("TEXT", "TEXT"),
+		};
+		let columns = format!(
+			"store_key {key_type} NOT NULL PRIMARY KEY, tenant {key_type} NOT NULL, \
+			 refresh_token {text_type}, revoked_at BIGINT, record {text_type} NOT NULL"
+		);
+
+		match self.dialect {
+			SqlDialect::MySql => vec![format!(
+				"CREATE TABLE IF NOT EXISTS {table} ({columns}, INDEX {index} (tenant))"
+			)],
+			SqlDialect::Postgres | SqlDialect::Sqlite => vec![
+				format!("CREATE TABLE IF NOT EXISTS {table} ({columns})"),
+				format!("CREATE INDEX IF NOT EXISTS {index} ON {table} (tenant)"),
+			],
+		}
+	}
+
+	/// Applies [`schema`](Self::schema); safe to run on every start.
+	pub async fn migrate(&self) -> Result<(), StoreError> {
+		for statement in self.schema() {
+			self.executor.execute(&statement, &[]).await?;
+		}
+
+		Ok(())
+	}
+
+	fn table(&self) -> String {
+		self.dialect.quote(&self.table)
+	}
+
+	async fn execute(&self, sql: &str, params: &[SqlValue]) -> Result<u64, StoreError> {
+		self.executor.execute(&self.dialect.bind(sql), params).await
+	}
+
+	async fn query_records(
+		&self,
+		sql: &str,
+		params: &[SqlValue],
+	) -> Result<Vec<(String, TokenRecord)>, StoreError> {
+		self.executor
+			.query(&self.dialect.bind(sql), params)
+			.await?
+			.into_iter()
+			.map(|row| {
+				let mut columns = row.into_iter();
+				let (Some(Some(key)), Some(Some(json))) = (columns.next(), columns.next()) else {
+					return Err(StoreError::Serialization {
+						message: "SQL row is missing its key or record column".into(),
+					});
+				};
+				let record =
+					serde_json::from_str(&json).map_err(|e| StoreError::Serialization {
+						message: format!("Failed to parse SQL record {key}: {e}"),
+					})?;
+
+				Ok((key, record))
+			})
+			.collect()
+	}
+
+	async fn get(&self, key: &str) -> Result<Option<(String, TokenRecord)>, StoreError> {
+		let sql = format!("SELECT record FROM {} WHERE store_key = ?", self.table());
+		let rows =
+			self.executor.query(&self.dialect.bind(&sql), &[SqlValue::Text(key.into())]).await?;
+		let Some(json) = rows.into_iter().next().and_then(|row| row.into_iter().next().flatten())
+		else {
+			return Ok(None);
+		};
+		let record = serde_json::from_str(&json).map_err(|e| StoreError::Serialization {
+			message: format!("Failed to parse SQL record {key}: {e}"),
+		})?;
+
+		Ok(Some((json, record)))
+	}
+
+	/// Values of the `refresh_token`, `revoked_at`, and `record` columns for `record`.
+	fn row_values(record: &TokenRecord) -> Result<[SqlValue; 3], StoreError> {
+		let json = serde_json::to_string(record)
+			.map_err(|e| StoreError::Serialization { message: e.to_string() })?;
+		let revoked_at = record.revoked_at.map_or(SqlValue::Null, |instant| {
+			SqlValue::Int(i64::try_from(instant.unix_timestamp_nanos()).unwrap_or(i64::MAX))
+		});
+
+		Ok([
+			record.refresh_token.as_ref().map(|secret| secret.expose()).into(),
+			revoked_at,
+			SqlValue::Text(json),
+		])
+	}
+
+	async fn save_now(&self, record: TokenRecord) -> Result<(), StoreError> {
+		let key = StoreKey::new(&record.family, &record.scope)?;
+		let [refresh, revoked, json] = Self::row_values(&record)?;
+		let table = self.table();
+		let upsert = match self.dialect {
+			SqlDialect::MySql =>
+				"ON DUPLICATE KEY UPDATE refresh_token = VALUES(refresh_token), \
+			                      revoked_at = VALUES(revoked_at), record = VALUES(record)",
+			SqlDialect::Postgres | SqlDialect::Sqlite =>
+				"ON CONFLICT (store_key) DO UPDATE SET refresh_token = excluded.refresh_token, \
+				 revoked_at = excluded.revoked_at, record = excluded.record",
+		};
+		let sql = format!(
+			"INSERT INTO {table} (store_key, tenant, refresh_token, revoked_at, record) \
+			 VALUES (?, ?, ?, ?, ?) {upsert}"
+		);
+
+		self.execute(
+			&sql,
+			&[
+				SqlValue::Text(key.page_cursor()),
+				SqlValue::Text(record.family.tenant.as_ref().into()),
+				refresh,
+				revoked,
+				json,
+			],
+		)
+		.await?;
+
+		Ok(())
+	}
+
+	async fn cas_now(
+		&self,
+		key: String,
+		expected_refresh: Option<&str>,
+		replacement: TokenRecord,
+	) -> Result<CompareAndSwapOutcome, StoreError> {
+		let [refresh, revoked, json] = Self::row_values(&replacement)?;
+		let mut params = vec![refresh, revoked, json, SqlValue::Text(key.clone())];
+		let guard = match expected_refresh {
+			Some(expected) => {
+				params.push(SqlValue::Text(expected.into()));
+
+				"refresh_token = ?"
+			},
+			None => "refresh_token IS NULL",
+		};
+		let sql = format!(
+			"UPDATE {} SET refresh_token = ?, revoked_at = ?, record = ? \
+			 WHERE store_key = ? AND {guard}",
+			self.table()
+		);
+
+		if self.execute(&sql, &params).await? > 0 {
+			return Ok(CompareAndSwapOutcome::Updated);
+		}
+
+		match self.get(&key).await? {
+			Some(_) => Ok(CompareAndSwapOutcome::RefreshMismatch),
+			None => Ok(CompareAndSwapOutcome::Missing),
+		}
+	}
+
+	async fn cas_version_now(
+		&self,
+		key: String,
+		expected_version: u64,
+		mut replacement: TokenRecord,
+	) -> Result<CompareAndSwapOutcome, StoreError> {
+		for _ in 0..Self::MAX_GUARDED_ATTEMPTS {
+			let Some((current, record)) = self.get(&key).await? else {
+				return Ok(CompareAndSwapOutcome::Missing);
+			};
+
+			if record.version != expected_version {
+				return Ok(CompareAndSwapOutcome::VersionMismatch);
+			}
+
+			replacement.version = store::next_version(&record);
+
+			if self.update_guarded(&key, current, &replacement).await? {
+				return Ok(CompareAndSwapOutcome::Updated);
+			}
+		}
+
+		Err(StoreError::Backend {
+			message: format!("SQL version swap of {key} kept losing to concurrent writers"),
+		})
+	}
+
+	/// Rewrites the row at `key` with `record` only while it still holds the `current` JSON.
+	async fn update_guarded(
+		&self,
+		key: &str,
+		current: String,
+		record: &TokenRecord,
+	) -> Result<bool, StoreError> {
+		let sql = format!(
+			"UPDATE {} SET refresh_token = ?, revoked_at = ?, record = ? \
+			 WHERE store_key = ? AND record = ?",
+			self.table()
+		);
+		let [refresh, revoked, json] = Self::row_values(record)?;
+		let params = [refresh, revoked, json, SqlValue::Text(key.into()), SqlValue::Text(current)];
+
+		Ok(self.execute(&sql, &params).await? > 0)
+	}
+
+	/// Rewrites the row at `key` only while it still holds the JSON it was read with.
+	async fn revoke_now(
+		&self,
+		key: &str,
+		instant: OffsetDateTime,
+		reason: RevocationReason,
+	) -> Result<Option<TokenRecord>, StoreError> {
+		for _ in 0..Self::MAX_GUARDED_ATTEMPTS {
+			let Some((current, mut record)) = self.get(key).await? else {
+				return Ok(None);
+			};
+
+			store::revoke_stored(&mut record, instant, reason);
+
+			if self.update_guarded(key, current, &record).await? {
+				return Ok(Some(record));
+			}
+		}
+
+		Err(StoreError::Backend {
+			message: format!("SQL revocation of {key} kept losing to concurrent writers"),
+		})
+	}
+
+	async fn tenant_records(
+		&self,
+		tenant: &TenantId,
+	) -> Result<Vec<(String, TokenRecord)>, StoreError> {
+		let sql = format!(
+			"SELECT store_key, record FROM {} WHERE tenant = ? ORDER BY store_key",
+			self.table()
+		);
+
+		self.query_records(&sql, &[SqlValue::Text(tenant.as_ref().into())]).await
+	}
+
+	async fn subtree_now(
+		&self,
+		tenant: &TenantId,
+		prefix: &PrincipalPath,
+		revoke_at: Option<(OffsetDateTime, RevocationReason)>,
+	) -> Result<Vec<TokenRecord>, StoreError> {
+		let mut records = Vec::new();
+
+		for (key, record) in self.tenant_records(tenant).await? {
+			if !StoreKey::new(&record.family, &record.scope)
+				.is_ok_and(|key| key.in_principal_subtree(tenant, prefix))
+			{
+				continue;
+			}
+
+			match revoke_at {
+				Some((instant, reason)) =>
+					records.extend(self.revoke_now(&key, instant, reason).await?),
+				None => records.push(record),
+			}
+		}
+
+		Ok(records)
+	}
+}
+impl SqlStore<AnyPool> {
+	/// Opens a lazily connecting `sqlx` pool for `dsn` (`postgres://`, `mysql://`, or `sqlite:`)
+	/// and picks the dialect from its scheme.
+	///
+	/// Must be called inside a Tokio runtime, which the pool uses to recycle connections.
+	pub fn connect_lazy(dsn: &str) -> Result<Self, StoreError> {
+		let dialect = SqlDialect::for_dsn(dsn)?;
+
+		tokio::runtime::Handle::try_current().map_err(|e| StoreError::Backend {
+			message: format!("SQL pools need a Tokio runtime: {e}"),
+		})?;
+		sqlx::any::install_default_drivers();
+
+		let pool = AnyPool::connect_lazy(dsn).map_err(backend)?;
+
+		Ok(Self::new(Arc::new(pool), dialect))
+	}
+}
+impl<E> Clone for SqlStore<E>
+where
+	E: SqlExecutor,
+{
+	fn clone(&self) -> Self {
+		Self { executor: self.executor.clone(), dialect: self.dialect, table: self.table.clone() }
+	}
+}
+impl<E> Debug for SqlStore<E>
+where
+	E: SqlExecutor,
+{
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		f.debug_struct("SqlStore")
+			.field("dialect", &self.dialect)
+			.field("table", &self.table)
+			.finish_non_exhaustive()
+	}
+}
+impl<E> BrokerStore for SqlStore<E>
+where
+	E: SqlExecutor,
+{
+	fn save(&self, record: TokenRecord) -> StoreFuture<'_, ()> {
+		Box::pin(self.save_now(record))
+	}
+
+	fn fetch<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+	) -> StoreFuture<'a, Option<TokenRecord>> {
+		Box::pin(async move {
+			let key = StoreKey::new(family, scope)?.page_cursor();
+
+			Ok(self.get(&key).await?.map(|(_, record)| record))
+		})
+	}
+
+	fn compare_and_swap_refresh<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+		expected_refresh: Option<&'a str>,
+		replacement: TokenRecord,
+	) -> StoreFuture<'a, CompareAndSwapOutcome> {
+		Box::pin(async move {
+			self.cas_now(StoreKey::new(family, scope)?.page_cursor(), expected_refresh, replacement)
+				.await
+		})
+	}
+
+	fn compare_and_swap_version<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+		expected_version: u64,
+		replacement: TokenRecord,
+	) -> StoreFuture<'a, CompareAndSwapOutcome> {
+		Box::pin(async move {
+			self.cas_version_now(
+				StoreKey::new(family, scope)?.page_cursor(),
+				expected_version,
+				replacement,
+			)
+			.await
+		})
+	}
+
+	fn revoke<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+		instant: OffsetDateTime,
+		reason: RevocationReason,
+	) -> StoreFuture<'a, Option<TokenRecord>> {
+		Box::pin(async move {
+			self.revoke_now(&StoreKey::new(family, scope)?.page_cursor(), instant, reason).await
+		})
+	}
+
+	fn health_check(&self) -> StoreFuture<'_, ()> {
+		Box::pin(async move {
+			self.executor.query("SELECT 1", &[]).await?;
+
+			Ok(())
+		})
+	}
+
+	fn purge_revoked(&self, cutoff: OffsetDateTime) -> StoreFuture<'_, usize> {
+		Box::pin(async move {
+			let sql = format!(
+				"DELETE FROM {} WHERE revoked_at IS NOT NULL AND revoked_at < ?",
+				self.table()
+			);
+			let cutoff = i64::try_from(cutoff.unix_timestamp_nanos()).unwrap_or(i64::MAX);
+			let purged = self.execute(&sql, &[SqlValue::Int(cutoff)]).await?;
+
+			Ok(usize::try_from(purged).unwrap_or(usize::MAX))
+		})
+	}
+
+	fn list_principal_subtree<'a>(
+		&'a self,
+		tenant: &'a TenantId,
+		prefix: &'a PrincipalPath,
+	) -> StoreFuture<'a, Vec<TokenRecord>> {
+		Box::pin(self.subtree_now(tenant, prefix, None))
+	}
+
+	fn revoke_principal_subtree<'a>(
+		&'a self,
+		tenant: &'a TenantId,
+		prefix: &'a PrincipalPath,
+		instant: OffsetDateTime,
+		reason: RevocationReason,
+	) -> StoreFuture<'a, Vec<TokenRecord>> {
+		Box::pin(self.subtree_now(tenant, prefix, Some((instant, reason))))
+	}
+
+	fn list_records<'a>(
+		&'a self,
+		after: Option<&'a str>,
+		limit: usize,
+	) -> StoreFuture<'a, RecordPage> {
+		Box::pin(async move {
+			// One extra row tells the page whether another one follows.
+			let sql = format!(
+				"SELECT store_key, record FROM {} WHERE store_key > ? ORDER BY store_key LIMIT ?",
+				self.table()
+			);
+			let fetch = i64::try_from(limit.max(1) + 1).unwrap_or(i64::MAX);
+			let entries = self
+				.query_records(
+					&sql,
+					&[SqlValue::Text(after.unwrap_or_default().into()), SqlValue::Int(fetch)],
+				)
+				.await?;
+
+			Ok(RecordPage::collect(entries, limit))
+		})
+	}
+
+	fn delete_tenant<'a>(&'a self, tenant: &'a TenantId) -> StoreFuture<'a, Vec<TokenRecord>> {
+		Box::pin(async move {
+			let records = self.tenant_records(tenant).await?;
+			let sql = format!("DELETE FROM {} WHERE tenant = ?", self.table());
+
+			self.execute(&sql, &[SqlValue::Text(tenant.as_ref().into())]).await?;
+
+			Ok(records.into_iter().map(|(_, record)| record).collect())
+		})
+	}
+}
+
+fn bind_any<'q>(
+	mut query: Query<'q, sqlx::Any, AnyArguments<'q>>,
+	params: &'q [SqlValue],
+) -> Query<'q, sqlx::Any, AnyArguments<'q>> {
+	for param in params {
+		query = match param {
+			SqlValue::Text(value) => query.bind(value),
+			SqlValue::Int(value) => query.bind(*value),
+			SqlValue::Null => query.bind(None::<String>),
+		};
+	}
+
+	query
+}
+
+fn backend(e: sqlx::Error) -> StoreError {
+	StoreError::Backend { message: format!("sql: {e}") }
+}
+
+#[cfg(test)]
+mod tests {
+	// std
+	use std::{env, fs, process};
+	// crates.io
+	use tokio::runtime::Runtime;
+	// self
+	use super::*;
+	use crate::auth::{PrincipalId, ProviderId};
+
+	#[derive(Default)]
+	struct ScriptedExecutor {
+		statements: Mutex<Vec<(String, Vec<SqlValue>)>>,
+		affected: Mutex<Vec<u64>>,
+		rows: Mutex<Vec<Vec<SqlRow>>>,
+	}
+	impl SqlExecutor for ScriptedExecutor {
+		fn execute<'a>(&'a self, sql: &'a str, params: &'a [SqlValue]) -> StoreFuture<'a, u64> {
+			self.statements.lock().push((sql.into(), params.to_vec()));
+
+			let affected = self.affected.lock().pop().unwrap_or_default();
+
+			Box::pin(async move { Ok(affected) })
+		}
+
+		fn query<'a>(
+			&'a self,
+			sql: &'a str,
+			params: &'a [SqlValue],
+		) -> StoreFuture<'a, Vec<SqlRow>> {
+			self.statements.lock().push((sql.into(), params.to_vec()));
+
+			let rows = self.rows.lock().pop().unwrap_or_default();
+
+			Box::pin(async move { Ok(rows) })
+		}
+	}
+
+	fn build_record(refresh: &str) -> TokenRecord {
+		TokenRecord::builder(
+			TokenFamily::new(
+				TenantId::new("tenant-sql").expect("Tenant fixture should be valid."),
+				PrincipalId::new("principal-sql").expect("Principal fixture should be valid."),
+			)
+			.with_provider(
+				ProviderId::new("provider-sql").expect("Provider fixture should be valid."),
+			),
+			ScopeSet::new(["read"]).expect("Scope fixture should be valid."),
+		)
+		.access_token("access")
+		.refresh_token(refresh)
+		.expires_in(Duration::hours(1))
+		.build()
+		.expect("Token record fixture should build successfully.")
+	}
+
+	#[test]
+	fn postgres_placeholders_are_numbered_and_mysql_keeps_question_marks() {
+		let sql = "UPDATE t SET a = ? WHERE b = ? AND c = ?";
+
+		assert_eq!(SqlDialect::Postgres.bind(sql), "UPDATE t SET a = $1 WHERE b = $2 AND c = $3");
+		assert_eq!(SqlDialect::MySql.bind(sql), sql);
+		assert_eq!(SqlDialect::Postgres.quote("to\"kens"), "\"to\"\"kens\"");
+		assert_eq!(SqlDialect::MySql.quote("tokens"), "`tokens`");
+	}
+
+	#[test]
+	fn refresh_cas_is_one_guarded_update() {
+		let executor = Arc::new(ScriptedExecutor::default());
+		let store = SqlStore::new(executor.clone(), SqlDialect::Postgres);
+		let current = build_record("refresh-old");
+		let rt = Runtime::new().expect("Failed to build Tokio runtime for SQL store test.");
+
+		executor.affected.lock().push(1);
+
+		let outcome = rt.block_on(store.compare_and_swap_refresh(
+			&current.family,
+			&current.scope,
+			Some("refresh-old"),
+			build_record("refresh-new"),
+		));
+
+		assert_eq!(outcome.expect("CAS should succeed."), CompareAndSwapOutcome::Updated);
+
+		let statements = executor.statements.lock().clone();
+		let (sql, params) = &statements[0];
+
+		assert!(sql.ends_with("WHERE store_key = $4 AND refresh_token = $5"));
+		assert_eq!(params[0], SqlValue::Text("refresh-new".into()));
+		assert_eq!(params[4], SqlValue::Text("refresh-old".into()));
+
+		// Nothing updated and no row found: the key is missing, not mismatched.
+		let outcome = rt.block_on(store.compare_and_swap_refresh(
+			&current.family,
+			&current.scope,
+			None,
+			build_record("refresh-new"),
+		));
+
+		assert_eq!(outcome.expect("CAS should succeed."), CompareAndSwapOutcome::Missing);
+		assert!(executor.statements.lock()[1].0.ends_with("refresh_token IS NULL"));
+	}
+
+	#[test]
+	fn version_cas_rewrites_only_the_row_it_read() {
+		let executor = Arc::new(ScriptedExecutor::default());
+		let store = SqlStore::new(executor.clone(), SqlDialect::Sqlite);
+		let current = build_record("refresh");
+		let json = serde_json::to_string(&current).expect("Record fixture should serialize.");
+		let rt = Runtime::new().expect("Failed to build Tokio runtime for SQL store test.");
+
+		executor.rows.lock().push(vec![vec![Some(json.clone())]]);
+		executor.affected.lock().push(1);
+
+		let outcome = rt.block_on(store.compare_and_swap_version(
+			&current.family,
+			&current.scope,
+			0,
+			build_record("refresh"),
+		));
+
+		assert_eq!(outcome.expect("CAS should succeed."), CompareAndSwapOutcome::Updated);
+
+		let statements = executor.statements.lock().clone();
+		let (sql, params) = &statements[1];
+
+		assert!(sql.ends_with("WHERE store_key = ? AND record = ?"));
+		assert!(matches!(&params[2], SqlValue::Text(record) if record.contains("\"version\":1")));
+		assert_eq!(params[4], SqlValue::Text(json.clone()));
+
+		executor.rows.lock().push(vec![vec![Some(json)]]);
+
+		let outcome = rt.block_on(store.compare_and_swap_version(
+			&current.family,
+			&current.scope,
+			3,
+			build_record("refresh"),
+		));
+
+		assert_eq!(outcome.expect("CAS should complete."), CompareAndSwapOutcome::VersionMismatch);
+	}
+
+	#[test]
+	fn schema_fits_each_dialect() {
+		let executor = Arc::new(ScriptedExecutor::default());
+		let mysql = SqlStore::new(executor.clone(), SqlDialect::MySql).with_table("tokens");
+		let sqlite = SqlStore::new(executor, SqlDialect::Sqlite);
+
+		assert_eq!(mysql.schema().len(), 1);
+		assert!(mysql.schema()[0].contains("VARCHAR(768)"));
+		assert!(mysql.schema()[0].starts_with("CREATE TABLE IF NOT EXISTS `tokens`"));
+		assert_eq!(sqlite.schema().len(), 2);
+		assert!(sqlite.schema()[1].starts_with("CREATE INDEX IF NOT EXISTS"));
+	}
+
+	#[test]
+	fn any_pool_round_trips_records_on_sqlite() {
+		let path = env::temp_dir().join(format!("oauth2-broker-sql-{}.db", process::id()));
+		let rt = Runtime::new().expect("Failed to build Tokio runtime for SQL store test.");
+		let current = build_record("refresh-old");
+
+		assert!(SqlStore::connect_lazy("redis://localhost").is_err());
+		assert!(SqlStore::connect_lazy("sqlite::memory:").is_err(), "No runtime is entered here.");
+
+		rt.block_on(async {
+			let store = SqlStore::connect_lazy(&format!("sqlite://{}?mode=rwc", path.display()))
+				.expect("SQLite DSN should open a pool.");
+
+			store.migrate().await.expect("Migration should succeed.");
+			store.save(current.clone()).await.expect("Save should succeed.");
+
+			let outcome = store
+				.compare_and_swap_refresh(
+					&current.family,
+					&current.scope,
+					Some("refresh-old"),
+					build_record("refresh-new"),
+				)
+				.await
+				.expect("CAS should succeed.");
+
+			assert_eq!(outcome, CompareAndSwapOutcome::Updated);
+
+			let fetched = store
+				.fetch(&current.family, &current.scope)
+				.await
+				.expect("Fetch should succeed.")
+				.expect("Record should be stored.");
+
+			assert_eq!(
+				fetched.refresh_token.as_ref().map(|secret| secret.expose()),
+				Some("refresh-new")
+			);
+		});
+
+		let _ = fs::remove_file(&path);
+	}
+}
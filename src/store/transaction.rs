@@ -0,0 +1,210 @@
+//! Atomic multi-record read-modify-write for the built-in stores.
+//!
+//! [`MemoryStore::transaction`](super::MemoryStore::transaction) and
+//! [`FileStore::transaction`](super::FileStore::transaction) lock the whole store, hand the
+//! closure a [`Transaction`] over the current records, and apply its staged writes only when the
+//! closure returns `Ok`. Other callers never observe a partially applied transaction.
+
+// self
+use crate::{
+	_prelude::*,
+	auth::{ScopeSet, TokenFamily, TokenRecord},
+	store::{self, StoreError, StoreKey},
+};
+
+/// Read access to the locked records a [`Transaction`] runs against.
+pub(crate) trait TransactionBase {
+	/// Returns the committed record stored under `key`.
+	fn get(&self, key: &StoreKey) -> Option<&TokenRecord>;
+
+	/// Visits every committed record.
+	fn for_each(&self, visit: &mut dyn FnMut(&StoreKey, &TokenRecord));
+}
+impl TransactionBase for HashMap<StoreKey, TokenRecord> {
+	fn get(&self, key: &StoreKey) -> Option<&TokenRecord> {
+		HashMap::get(self, key)
+	}
+
+	fn for_each(&self, visit: &mut dyn FnMut(&StoreKey, &TokenRecord)) {
+		for (key, record) in self {
+			visit(key, record);
+		}
+	}
+}
+
+/// Writes staged against a locked store; reads see the transaction's own writes first.
+///
+/// Records passed to [`Transaction::put`] are stored with the replaced record's
+/// [`version`](TokenRecord::version) plus one, like the compare-and-swap operations. New records
+/// keep their version.
+pub struct Transaction<'a> {
+	base: &'a dyn TransactionBase,
+	writes: HashMap<StoreKey, Option<TokenRecord>>,
+}
+impl<'a> Transaction<'a> {
+	pub(crate) fn new(base: &'a dyn TransactionBase) -> Self {
+		Self { base, writes: HashMap::new() }
+	}
+
+	/// Returns the record for the family + scope as the transaction currently sees it.
+	///
+	/// Families without a provider have no key and therefore no record.
+	pub fn get(&self, family: &TokenFamily, scope: &ScopeSet) -> Option<&TokenRecord> {
+		self.get_key(&StoreKey::new(family, scope).ok()?)
+	}
+
+	/// Returns the record stored under `key` as the transaction currently sees it.
+	pub fn get_key(&self, key: &StoreKey) -> Option<&TokenRecord> {
+		match self.writes.get(key) {
+			Some(staged) => staged.as_ref(),
+			None => self.base.get(key),
+		}
+	}
+
+	/// Returns every record of `family`, across all scope combinations.
+	pub fn family(&self, family: &TokenFamily) -> Vec<TokenRecord> {
+		let mut records = Vec::new();
+
+		self.base.for_each(&mut |key, record| {
+			if key.family == *family && !self.writes.contains_key(key) {
+				records.push(record.clone());
+			}
+		});
+		records.extend(
+			self.writes
+				.iter()
+				.filter(|(key, _)| key.family == *family)
+				.filter_map(|(_, staged)| staged.clone()),
+		);
+
+		records
+	}
+
+	/// Stages `record` as the new value for its family + scope.
+	pub fn put(&mut self, record: TokenRecord) -> Result<(), StoreError> {
+		self.writes.insert(StoreKey::new(&record.family, &record.scope)?, Some(record));
+
+		Ok(())
+	}
+
+	/// Stages the removal of the family + scope, returning the record it currently holds.
+	pub fn remove(
+		&mut self,
+		family: &TokenFamily,
+		scope: &ScopeSet,
+	) -> Result<Option<TokenRecord>, StoreError> {
+		let key = StoreKey::new(family, scope)?;
+		let current = self.get_key(&key).cloned();
+
+		self.writes.insert(key, None);
+
+		Ok(current)
+	}
+
+	/// Returns `true` when nothing has been staged.
+	pub fn is_empty(&self) -> bool {
+		self.writes.is_empty()
+	}
+
+	/// Resolves the staged writes, stamping versions against the committed records.
+	pub(crate) fn into_writes(self) -> Vec<(StoreKey, Option<TokenRecord>)> {
+		let base = self.base;
+
+		self.writes
+			.into_iter()
+			.map(|(key, staged)| {
+				let staged = staged.map(|mut record| {
+					if let Some(existing) = base.get(&key) {
+						record.version = store::next_version(existing);
+					}
+
+					record
+				});
+
+				(key, staged)
+			})
+			.collect()
+	}
+}
+impl Debug for Transaction<'_> {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		f.debug_struct("Transaction").field("writes", &self.writes.len()).finish_non_exhaustive()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	// self
+	use super::*;
+	use crate::{
+		auth::{PrincipalId, ProviderId, TenantId, TokenSecret},
+		store::MemoryStore,
+	};
+
+	fn build_record(scope: &str) -> TokenRecord {
+		let family = TokenFamily::new(
+			TenantId::new("tenant-txn").expect("Tenant fixture should be valid."),
+			PrincipalId::new("principal-txn").expect("Principal fixture should be valid."),
+		)
+		.with_provider(ProviderId::new("provider-txn").expect("Provider fixture should be valid."));
+
+		TokenRecord::builder(
+			family,
+			ScopeSet::new([scope]).expect("Scope fixture should be valid."),
+		)
+		.access_token("access")
+		.refresh_token("refresh")
+		.expires_in(Duration::hours(1))
+		.build()
+		.expect("Token record fixture should build successfully.")
+	}
+
+	#[test]
+	fn writes_apply_together_or_not_at_all() {
+		let store = MemoryStore::default();
+		let (read, write) = (build_record("read"), build_record("write"));
+		let family = read.family.clone();
+
+		store
+			.transaction(|txn| {
+				txn.put(read.clone())?;
+				txn.put(write.clone())?;
+
+				assert_eq!(txn.family(&family).len(), 2);
+
+				Ok(())
+			})
+			.expect("Transaction should commit.");
+
+		let result = store.transaction(|txn| {
+			txn.remove(&read.family, &read.scope)?;
+
+			Err::<(), _>(StoreError::Backend { message: "abort".into() })
+		});
+
+		assert!(result.is_err());
+		assert!(store.fetch_shared(&read.family, &read.scope).is_some());
+
+		store
+			.transaction(|txn| {
+				let mut rotated = txn
+					.get(&write.family, &write.scope)
+					.cloned()
+					.expect("Committed record should be visible.");
+
+				rotated.refresh_token = Some(TokenSecret::new("refresh-2"));
+				txn.put(rotated)?;
+				txn.remove(&read.family, &read.scope)?;
+
+				Ok(())
+			})
+			.expect("Transaction should commit.");
+
+		let rotated = store
+			.fetch_shared(&write.family, &write.scope)
+			.expect("Rotated record should be stored.");
+
+		assert_eq!(rotated.version, 1);
+		assert!(store.fetch_shared(&read.family, &read.scope).is_none());
+	}
+}
@@ -0,0 +1,399 @@
+#![cfg(feature = "reqwest")]
+
+// std
+use std::{env, fs, process};
+// crates.io
+use httpmock::prelude::*;
+// self
+use oauth2_broker::{
+	_preludet::*,
+	auth::{PrincipalId, ProviderId, ScopeSet, TenantId},
+	config::{BrokerConfig, ProviderConfig, SecretSource, StoreConfig, StrategyConfig},
+	error::ConfigError,
+	flows::{BrokerRegistry, CachedTokenRequest, MultiBroker, ProviderHandle},
+	obs::FlowKind,
+	provider::{
+		ClientAuthMethod, DefaultProviderStrategy, EnvironmentEndpoints, GrantType,
+		ProviderDescriptor, ProviderEndpoints, ProviderEnvironment, ProviderErrorContext,
+		ProviderErrorKind, ProviderRegistry,
+	},
+	store::BrokerStore,
+};
+
+fn descriptor_json(id: &str, token_endpoint: &str) -> String {
+	format!(
+		r#"{{
+			"id": "{id}",
+			"endpoints": {{
+				"authorization": "https://{id}.example.com/authorize",
+				"token": "{token_endpoint}",
+				"revocation": null
+			}},
+			"supported_grants": {{
+				"authorization_code": true,
+				"refresh_token": true,
+				"client_credentials": false
+			}},
+			"preferred_client_auth_method": "client_secret_basic",
+			"quirks": {{}}
+		}}"#
+	)
+}
+
+fn provider(id: &str, token_endpoint: &str) -> ProviderConfig {
+	let descriptor: ProviderDescriptor = serde_json::from_str(&descriptor_json(id, token_endpoint))
+		.expect("Descriptor fixture should deserialize.");
+
+	ProviderConfig {
+		descriptor,
+		client_id: format!("{id}-client"),
+		client_secret: Some(format!("{id}-secret").into()),
+		strategy: StrategyConfig::Default,
+	}
+}
+
+#[test]
+fn registry_assembles_brokers_sharing_one_store() {
+	let config = BrokerConfig {
+		store: StoreConfig::Memory,
+		providers: vec![
+			provider("alpha", "https://alpha.example.com/token"),
+			provider("beta", "https://beta.example.com/token"),
+		],
+		..Default::default()
+	};
+	let registry =
+		BrokerRegistry::from_config(config).expect("Registry should assemble from config.");
+
+	assert_eq!(
+		registry.providers().map(|id| id.to_string()).collect::<Vec<_>>(),
+		["alpha", "beta"]
+	);
+
+	let alpha = registry.get("alpha").expect("Alpha broker should be registered.");
+	let beta = registry.get("beta").expect("Beta broker should be registered.");
+
+	assert_eq!(alpha.client_id, "alpha-client");
+	assert_eq!(beta.client_secret.as_deref(), Some("beta-secret"));
+	assert!(Arc::ptr_eq(&alpha.store, &beta.store));
+	assert!(Arc::ptr_eq(&alpha.store, registry.store()));
+	assert!(registry.get("gamma").is_none());
+}
+
+#[test]
+fn registry_applies_policy_observability_and_strategy() {
+	let config = BrokerConfig::from_json_str(&format!(
+		r#"{{
+			"providers": [
+				{{
+					"descriptor": {},
+					"client_id": "alpha-client",
+					"client_secret": "alpha-secret",
+					"strategy": "github"
+				}},
+				{{
+					"descriptor": {},
+					"client_id": "beta-client",
+					"client_secret": "beta-secret"
+				}}
+			],
+			"policy": {{
+				"timeout_secs": 5,
+				"preemptive_window_secs": 120,
+				"allow_stale_on_timeout": true,
+				"singleflight_wait_secs": 2,
+				"max_queued_callers": 16,
+				"disabled_flows": ["client_credentials"],
+				"disabled_tenant_flows": {{ "tenant-frozen": ["refresh"] }}
+			}},
+			"observability": {{ "call_summaries": true, "served_tracking_secs": 30 }}
+		}}"#,
+		descriptor_json("alpha", "https://alpha.example.com/token"),
+		descriptor_json("beta", "https://beta.example.com/token"),
+	))
+	.expect("Configuration fixture should parse.");
+	let registry =
+		BrokerRegistry::from_config(config).expect("Registry should assemble from config.");
+	let alpha = registry.get("alpha").expect("Alpha broker should be registered.");
+	let beta = registry.get("beta").expect("Beta broker should be registered.");
+	let github_error = ProviderErrorContext::new(GrantType::AuthorizationCode)
+		.with_oauth_error("bad_verification_code");
+
+	assert_eq!(alpha.strategy.classify_token_error(&github_error), ProviderErrorKind::InvalidGrant);
+	assert_ne!(beta.strategy.classify_token_error(&github_error), ProviderErrorKind::InvalidGrant);
+	assert_eq!(beta.overrides.timeout, Some(Duration::seconds(5)));
+	assert_eq!(beta.overrides.preemptive_window, Some(Duration::minutes(2)));
+	assert!(beta.overrides.allow_stale_on_timeout);
+	assert!(beta.overrides.call_summaries);
+	assert_eq!(beta.singleflight_wait, Some(Duration::seconds(2)));
+	assert_eq!(beta.max_queued_callers, Some(16));
+	assert_eq!(beta.served_tracking, Some(Duration::seconds(30)));
+
+	let policy = beta.flow_policy.as_ref().expect("Disabled flows should install a flow gate.");
+	let tenant = TenantId::new("tenant-live").expect("Tenant fixture should be valid.");
+	let frozen = TenantId::new("tenant-frozen").expect("Tenant fixture should be valid.");
+
+	assert!(!policy.allows(FlowKind::ClientCredentials, &tenant));
+	assert!(policy.allows(FlowKind::Refresh, &tenant));
+	assert!(!policy.allows(FlowKind::Refresh, &frozen));
+}
+
+#[test]
+fn registry_resolves_secret_file_references() {
+	let path = env::temp_dir().join(format!(
+		"oauth2_broker_registry_secret_{}_{}",
+		process::id(),
+		OffsetDateTime::now_utc().unix_timestamp_nanos(),
+	));
+
+	fs::write(&path, "file-secret\n").expect("Secret fixture should be written.");
+
+	let mut alpha = provider("alpha", "https://alpha.example.com/token");
+
+	alpha.client_secret =
+		Some(SecretSource::File { file: path.to_str().expect("Temp path is UTF-8.").into() });
+
+	let registry = BrokerRegistry::from_config(BrokerConfig {
+		store: StoreConfig::Memory,
+		providers: vec![alpha],
+		..Default::default()
+	})
+	.expect("Registry should resolve file-backed secrets.");
+	let broker = registry.get("alpha").expect("Alpha broker should be registered.");
+
+	assert_eq!(broker.client_secret.as_deref(), Some("file-secret"));
+
+	fs::remove_file(&path).expect("Secret fixture should be removed.");
+}
+
+#[test]
+fn registry_rejects_duplicate_and_invalid_descriptors() {
+	let duplicate = BrokerConfig {
+		store: StoreConfig::Memory,
+		providers: vec![
+			provider("alpha", "https://alpha.example.com/token"),
+			provider("alpha", "https://alpha.example.com/token"),
+		],
+		..Default::default()
+	};
+	let err = BrokerRegistry::from_config(duplicate)
+		.expect_err("Duplicate providers should be rejected.");
+
+	assert!(matches!(err, Error::Config(ConfigError::DuplicateProvider { .. })));
+
+	let insecure = BrokerConfig {
+		store: StoreConfig::Memory,
+		providers: vec![provider("alpha", "http://alpha.example.com/token")],
+		..Default::default()
+	};
+	let err = BrokerRegistry::from_config(insecure)
+		.expect_err("Descriptors deserialized from config should still be validated.");
+
+	assert!(matches!(err, Error::Config(ConfigError::Descriptor(_))));
+}
+
+#[test]
+fn registry_rejects_client_secret_and_auth_method_mismatches() {
+	let mut confidential = provider("alpha", "https://alpha.example.com/token");
+
+	confidential.client_secret = None;
+
+	let err = BrokerRegistry::from_config(BrokerConfig {
+		store: StoreConfig::Memory,
+		providers: vec![confidential],
+		..Default::default()
+	})
+	.expect_err("Secret-based client authentication without a secret should be rejected.");
+
+	assert!(matches!(
+		err,
+		Error::Config(ConfigError::MissingClientSecret {
+			ref descriptor,
+			grant: "authorization_code",
+			method: "client_secret_basic",
+		}) if descriptor == "alpha"
+	));
+
+	let mut public = provider("beta", "https://beta.example.com/token");
+
+	public.descriptor.preferred_client_auth_method = ClientAuthMethod::NoneWithPkce;
+
+	let err = BrokerRegistry::from_config(BrokerConfig {
+		store: StoreConfig::Memory,
+		providers: vec![public.clone()],
+		..Default::default()
+	})
+	.expect_err("A secret that public client authentication would drop should be rejected.");
+
+	assert!(matches!(
+		err,
+		Error::Config(ConfigError::UnusedClientSecret { ref descriptor }) if descriptor == "beta"
+	));
+
+	public.client_secret = None;
+
+	BrokerRegistry::from_config(BrokerConfig {
+		store: StoreConfig::Memory,
+		providers: vec![public],
+		..Default::default()
+	})
+	.expect("Public clients without a secret should assemble.");
+}
+
+#[test]
+fn registry_points_every_provider_at_the_configured_environment() {
+	let mut alpha = provider("alpha", "https://alpha.example.com/token");
+	let sandbox_token = Url::parse("https://sandbox.alpha.example.com/token")
+		.expect("Sandbox token endpoint should parse.");
+
+	alpha.descriptor.sandbox = Some(
+		EnvironmentEndpoints::new(ProviderEndpoints {
+			authorization: Url::parse("https://sandbox.alpha.example.com/authorize")
+				.expect("Sandbox authorization endpoint should parse."),
+			token: sandbox_token.clone(),
+			revocation: None,
+			backchannel_authentication: None,
+			jwks: None,
+			introspection: None,
+		})
+		.with_issuer(
+			Url::parse("https://sandbox.alpha.example.com").expect("Sandbox issuer should parse."),
+		),
+	);
+
+	let registry = BrokerRegistry::from_config(BrokerConfig {
+		store: StoreConfig::Memory,
+		providers: vec![alpha.clone()],
+		environment: ProviderEnvironment::Sandbox,
+		..Default::default()
+	})
+	.expect("Registry should assemble sandbox brokers.");
+	let broker = registry.get("alpha").expect("Alpha broker should be registered.");
+
+	assert_eq!(broker.descriptor.environment, ProviderEnvironment::Sandbox);
+	assert_eq!(broker.descriptor.endpoints.token, sandbox_token);
+
+	let production = broker
+		.descriptor
+		.for_environment(ProviderEnvironment::Production)
+		.expect("The production endpoints should be kept for switching back.");
+
+	assert_eq!(production, alpha.descriptor);
+
+	let err = BrokerRegistry::from_config(BrokerConfig {
+		store: StoreConfig::Memory,
+		providers: vec![provider("beta", "https://beta.example.com/token")],
+		environment: ProviderEnvironment::Sandbox,
+		..Default::default()
+	})
+	.expect_err("Providers without sandbox endpoints should not fall back to production.");
+
+	assert!(matches!(
+		err,
+		Error::Config(ConfigError::UnsupportedEnvironment {
+			environment: ProviderEnvironment::Sandbox,
+			..
+		})
+	));
+}
+
+#[tokio::test]
+async fn multi_broker_routes_flows_by_provider_over_one_store() {
+	let server = MockServer::start_async().await;
+	let descriptor = |id: &str| {
+		ProviderDescriptor::builder(
+			ProviderId::new(id).expect("Provider identifier fixture should be valid."),
+		)
+		.authorization_endpoint(
+			Url::parse(&server.url(format!("/{id}/authorize")))
+				.expect("Authorization endpoint fixture should parse."),
+		)
+		.token_endpoint(
+			Url::parse(&server.url(format!("/{id}/token")))
+				.expect("Token endpoint fixture should parse."),
+		)
+		.support_grants([GrantType::ClientCredentials])
+		.preferred_client_auth_method(ClientAuthMethod::ClientSecretPost)
+		.build()
+		.expect("Descriptor fixture should build.")
+	};
+	let (broker, store) = build_reqwest_test_broker(descriptor("alpha"), "alpha-client", "secret");
+	let registry = ProviderRegistry::new()
+		.with_provider(
+			ProviderHandle::new(
+				descriptor("beta"),
+				Arc::new(DefaultProviderStrategy),
+				"beta-client",
+			)
+			.with_client_secret("beta-secret"),
+		)
+		.expect("Beta should register.");
+	let multi = MultiBroker::new(broker, registry).expect("Registered providers should attach.");
+	let mint = |id: &'static str| {
+		server.mock(|when, then| {
+			when.method(POST)
+				.path(format!("/{id}/token"))
+				.form_urlencoded_tuple("client_id", format!("{id}-client"));
+			then.status(200).header("content-type", "application/json").body(format!(
+				"{{\"access_token\":\"{id}-token\",\"token_type\":\"bearer\",\"expires_in\":600}}"
+			));
+		})
+	};
+	let (alpha_mint, beta_mint) = (mint("alpha"), mint("beta"));
+	let alpha = ProviderId::new("alpha").expect("Provider identifier fixture should be valid.");
+	let beta = ProviderId::new("beta").expect("Provider identifier fixture should be valid.");
+
+	assert_eq!(multi.default_provider(), &alpha);
+	assert_eq!(multi.providers(), [alpha.clone(), beta.clone()]);
+
+	let request = CachedTokenRequest::new(
+		TenantId::new("tenant-multi").expect("Tenant identifier fixture should be valid."),
+		PrincipalId::new("principal-multi").expect("Principal identifier fixture should be valid."),
+		ScopeSet::new(["api.read"]).expect("Scope fixture should be valid."),
+	);
+	let alpha_record = multi
+		.for_provider(&alpha)
+		.expect("The default provider should resolve.")
+		.client_credentials(request.clone())
+		.await
+		.expect("Alpha should mint a token.");
+	let beta_record = multi
+		.for_provider(&beta)
+		.expect("Beta should resolve.")
+		.client_credentials(request.clone())
+		.await
+		.expect("Beta should mint a token.");
+	let routed = multi
+		.broker()
+		.client_credentials(request.clone().for_provider(beta.clone()))
+		.await
+		.expect("Requests naming beta should be served from its cache.");
+
+	assert_eq!(alpha_record.access_token.expose(), "alpha-token");
+	assert_eq!(beta_record.access_token.expose(), "beta-token");
+	assert_eq!(routed.access_token.expose(), "beta-token");
+	assert!(
+		store
+			.fetch(&beta_record.family, &beta_record.scope)
+			.await
+			.is_ok_and(|stored| stored.is_some())
+	);
+
+	alpha_mint.assert_calls(1);
+	beta_mint.assert_calls(1);
+
+	assert!(matches!(
+		multi.register(ProviderHandle::new(
+			descriptor("beta"),
+			Arc::new(DefaultProviderStrategy),
+			"beta-client",
+		)),
+		Err(Error::Config(ConfigError::DuplicateProvider { .. }))
+	));
+	assert!(matches!(
+		multi.for_provider(
+			&ProviderId::new("gamma").expect("Provider identifier fixture should be valid.")
+		),
+		Err(Error::Config(ConfigError::UnknownProvider { .. }))
+	));
+}
@@ -0,0 +1,219 @@
+#![cfg(feature = "reqwest")]
+
+// crates.io
+use httpmock::prelude::*;
+// self
+use oauth2_broker::{
+	_preludet::*,
+	auth::ProviderId,
+	flows::{Broker, ValidationCheck, ValidationOptions, ValidationSeverity},
+	oauth::ReqwestTransportErrorMapper,
+	obs::{Diagnostic, DiagnosticKind, Diagnostics},
+	provider::{
+		ClientAuthMethod, DefaultProviderStrategy, DiscoveryCache, GrantType, ProviderDescriptor,
+		ProviderQuirks,
+	},
+	store::MemoryStore,
+};
+
+fn build_descriptor(server: &MockServer, method: ClientAuthMethod) -> ProviderDescriptor {
+	let provider_id = ProviderId::new("mock-validate")
+		.expect("Provider identifier should be valid for validation tests.");
+
+	ProviderDescriptor::builder(provider_id)
+		.authorization_endpoint(
+			Url::parse(&server.url("/authorize"))
+				.expect("Mock authorization endpoint should parse successfully."),
+		)
+		.token_endpoint(
+			Url::parse(&server.url("/token"))
+				.expect("Mock token endpoint should parse successfully."),
+		)
+		.support_grants([GrantType::AuthorizationCode, GrantType::ClientCredentials])
+		.preferred_client_auth_method(method)
+		.build()
+		.expect("Provider descriptor should build successfully.")
+}
+
+#[tokio::test]
+async fn validate_reports_missing_secret_without_remote_checks() {
+	let server = MockServer::start_async().await;
+	let descriptor = build_descriptor(&server, ClientAuthMethod::ClientSecretBasic);
+	let broker: ReqwestTestBroker = Broker::with_http_client(
+		Arc::new(MemoryStore::default()),
+		descriptor,
+		Arc::new(DefaultProviderStrategy),
+		"client-validate",
+		test_reqwest_http_client(),
+		Arc::new(ReqwestTransportErrorMapper),
+	);
+	let report = broker.validate(ValidationOptions::default()).await;
+
+	assert!(!report.is_ok());
+	assert!(!report.discovery_checked);
+	assert_eq!(report.token_endpoint_status, None);
+
+	let error = report.errors().next().expect("Missing secret should be reported as an error.");
+
+	assert_eq!(error.check, ValidationCheck::ClientAuthentication);
+}
+
+#[tokio::test]
+async fn diagnose_reports_ignored_secret_and_missing_revocation_endpoint() {
+	let server = MockServer::start_async().await;
+	let descriptor = ProviderDescriptor::builder(
+		ProviderId::new("mock-diagnose").expect("Provider identifier should be valid."),
+	)
+	.authorization_endpoint(
+		Url::parse(&server.url("/authorize")).expect("Mock authorization endpoint should parse."),
+	)
+	.token_endpoint(Url::parse(&server.url("/token")).expect("Mock token endpoint should parse."))
+	.support_grants([GrantType::AuthorizationCode, GrantType::ClientCredentials])
+	.client_auth_method_for(GrantType::AuthorizationCode, ClientAuthMethod::NoneWithPkce)
+	.quirks(ProviderQuirks { cascade_revocation: true, ..Default::default() })
+	.build()
+	.expect("Provider descriptor should build successfully.");
+	let forwarded = Arc::new(Mutex::new(Vec::new()));
+	let sink = forwarded.clone();
+	let diagnostics = Diagnostics::new()
+		.with_sink(move |diagnostic: &Diagnostic| sink.lock().push(diagnostic.kind));
+	let (broker, _store) = build_reqwest_test_broker(descriptor, "client-diagnose", "secret");
+	let broker = broker.with_diagnostics(Arc::new(diagnostics));
+
+	broker.check_client_auth().expect("Client credentials still needs the secret.");
+
+	let kinds = broker.diagnose().into_iter().map(|diagnostic| diagnostic.kind).collect::<Vec<_>>();
+
+	assert_eq!(
+		kinds,
+		[DiagnosticKind::IgnoredClientSecret, DiagnosticKind::MissingRevocationEndpoint]
+	);
+
+	let report = broker.health_report().await;
+
+	assert_eq!(report.diagnostics.len(), 2);
+	assert_eq!(*forwarded.lock(), kinds);
+}
+
+#[tokio::test]
+async fn validate_compares_discovery_and_probes_token_endpoint() {
+	let server = MockServer::start_async().await;
+	let descriptor = build_descriptor(&server, ClientAuthMethod::ClientSecretPost);
+	let (broker, _store) = build_reqwest_test_broker(descriptor, "client-validate", "secret");
+	let discovery = server
+		.mock_async(|when, then| {
+			when.method(GET).path("/.well-known/openid-configuration");
+			then.status(200).header("content-type", "application/json").body(format!(
+				"{{\"authorization_endpoint\":\"{}\",\"token_endpoint\":\"{}\"}}",
+				server.url("/authorize"),
+				server.url("/other-token"),
+			));
+		})
+		.await;
+	let probe = server
+		.mock_async(|when, then| {
+			when.method(GET).path("/token");
+			then.status(405);
+		})
+		.await;
+	let options = ValidationOptions::default()
+		.with_discovery_url(
+			Url::parse(&server.url("/.well-known/openid-configuration"))
+				.expect("Discovery URL should parse successfully."),
+		)
+		.with_token_endpoint_probe();
+	let report = broker.validate(options).await;
+
+	discovery.assert_async().await;
+	probe.assert_async().await;
+
+	assert!(report.discovery_checked);
+	assert_eq!(report.token_endpoint_status, Some(405));
+
+	let errors = report.errors().collect::<Vec<_>>();
+
+	assert_eq!(errors.len(), 1, "Only the token endpoint mismatch should be an error.");
+	assert_eq!(errors[0].check, ValidationCheck::Discovery);
+	assert_eq!(errors[0].severity, ValidationSeverity::Error);
+	assert!(errors[0].message.contains("token"));
+}
+
+#[tokio::test]
+async fn validate_reports_capability_mismatches_from_discovery() {
+	let server = MockServer::start_async().await;
+	let descriptor = build_descriptor(&server, ClientAuthMethod::ClientSecretPost);
+	let (broker, _store) = build_reqwest_test_broker(descriptor, "client-validate", "secret");
+	let discovery = server
+		.mock_async(|when, then| {
+			when.method(GET).path("/.well-known/oauth-authorization-server");
+			then.status(200).header("content-type", "application/json").body(format!(
+				"{{\"authorization_endpoint\":\"{}\",\"token_endpoint\":\"{}\",\"grant_types_supported\":[\"authorization_code\"],\"token_endpoint_auth_methods_supported\":[\"client_secret_post\"],\"code_challenge_methods_supported\":[\"S256\"]}}",
+				server.url("/authorize"),
+				server.url("/token"),
+			));
+		})
+		.await;
+	let report = broker
+		.validate(
+			ValidationOptions::default().with_discovery_url(
+				Url::parse(&server.url("/.well-known/oauth-authorization-server"))
+					.expect("Discovery URL should parse successfully."),
+			),
+		)
+		.await;
+
+	discovery.assert_async().await;
+
+	let errors = report.errors().collect::<Vec<_>>();
+
+	assert_eq!(errors.len(), 1, "Only the undeclared client_credentials grant should be an error.");
+	assert_eq!(errors[0].check, ValidationCheck::Capabilities);
+	assert!(errors[0].message.contains("client_credentials"));
+}
+
+#[tokio::test]
+async fn discover_builds_descriptors_and_reuses_cached_documents() {
+	let server = MockServer::start_async().await;
+	let issuer = Url::parse(&server.url("/tenant")).expect("Issuer should parse successfully.");
+	let discovery = server
+		.mock_async(|when, then| {
+			when.method(GET).path("/tenant/.well-known/openid-configuration");
+			then.status(200).header("content-type", "application/json").body(format!(
+				"{{\"issuer\":\"{}\",\"authorization_endpoint\":\"{}\",\"token_endpoint\":\"{}\",\
+				 \"revocation_endpoint\":\"{}\",\"grant_types_supported\":[\"client_credentials\",\
+				 \"refresh_token\"]}}",
+				server.url("/tenant"),
+				server.url("/tenant/authorize"),
+				server.url("/tenant/token"),
+				server.url("/tenant/revoke"),
+			));
+		})
+		.await;
+	let client = test_reqwest_http_client();
+	let cache = DiscoveryCache::default();
+	let id = ProviderId::new("mock-discover").expect("Provider identifier should be valid.");
+	let descriptor = ProviderDescriptor::discover(id.clone(), &issuer, &client, &cache)
+		.await
+		.expect("Discovery should build a descriptor.");
+
+	assert_eq!(descriptor.id, id);
+	assert_eq!(descriptor.issuer.as_ref(), Some(&issuer));
+	assert!(descriptor.supports(GrantType::ClientCredentials));
+	assert!(descriptor.supports(GrantType::RefreshToken));
+	assert!(!descriptor.supports(GrantType::AuthorizationCode));
+	assert_eq!(
+		descriptor.endpoints.revocation.as_ref().map(Url::as_str),
+		Some(server.url("/tenant/revoke").as_str())
+	);
+
+	let cached = ProviderDescriptor::discover(id.clone(), &issuer, &client, &cache)
+		.await
+		.expect("Cached discovery should build a descriptor.");
+
+	assert_eq!(cached, descriptor);
+	discovery.assert_calls_async(1).await;
+
+	let other = Url::parse(&server.url("/other")).expect("Issuer should parse successfully.");
+
+	assert!(ProviderDescriptor::discover(id, &other, &client, &cache).await.is_err());
+}
@@ -1,19 +1,49 @@
 #![cfg(feature = "reqwest")]
 
 // crates.io
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
 use httpmock::prelude::*;
+use sha2::{Digest, Sha256};
 // self
 use oauth2_broker::{
 	_preludet::*,
 	auth::{PrincipalId, ProviderId, ScopeSet, TenantId, TokenFamily},
-	flows::PkceCodeChallengeMethod,
-	provider::{ClientAuthMethod, GrantType, ProviderDescriptor},
+	error::ConfigError,
+	flows::{
+		BearerChallenge, CachedTokenRequest, JwtSigner, MemoryReplayGuard, PkceCodeChallengeMethod,
+		RequestObjectConfig, RequestUriPublisher,
+	},
+	provider::{ClientAuthMethod, GrantType, ProviderDescriptor, ProviderQuirks},
 	store::BrokerStore,
 };
 
 const CLIENT_ID: &str = "client-it";
 const CLIENT_SECRET: &str = "secret-it";
 
+struct FixedSigner;
+impl JwtSigner for FixedSigner {
+	fn algorithm(&self) -> &str {
+		"PS256"
+	}
+
+	fn sign(&self, _: &[u8]) -> Result<Vec<u8>, ConfigError> {
+		Ok(b"signature".to_vec())
+	}
+}
+
+#[derive(Default)]
+struct RecordingPublisher(Mutex<Vec<String>>);
+impl RequestUriPublisher for RecordingPublisher {
+	fn publish(&self, request_object: &str) -> Result<Url, ConfigError> {
+		let mut published = self.0.lock();
+
+		published.push(request_object.into());
+
+		Ok(Url::parse(&format!("https://app.example.com/jar/{}", published.len()))
+			.expect("Request URI fixture should parse successfully."))
+	}
+}
+
 fn build_descriptor(server: &MockServer) -> ProviderDescriptor {
 	let provider_id = ProviderId::new("mock-http")
 		.expect("Provider identifier should be valid for auth code test.");
@@ -111,7 +141,7 @@ async fn start_authorization_and_exchange_successfully_save_tokens() {
 async fn exchange_code_classifies_invalid_grant_errors() {
 	let server = MockServer::start_async().await;
 	let descriptor = build_descriptor(&server);
-	let (broker, store) = build_reqwest_test_broker(descriptor, CLIENT_ID, CLIENT_SECRET);
+	let (broker, store) = build_reqwest_test_broker(descriptor.clone(), CLIENT_ID, CLIENT_SECRET);
 	let tenant =
 		TenantId::new("tenant-err").expect("Tenant identifier should be valid for error test.");
 	let principal = PrincipalId::new("principal-err")
@@ -141,7 +171,7 @@ async fn exchange_code_classifies_invalid_grant_errors() {
 	mock.assert_async().await;
 
 	let maybe_record = store
-		.fetch(&TokenFamily::new(tenant, principal), &scope)
+		.fetch(&TokenFamily::new(tenant, principal).with_provider(descriptor.id), &scope)
 		.await
 		.expect("Token store fetch should succeed.");
 
@@ -150,3 +180,660 @@ async fn exchange_code_classifies_invalid_grant_errors() {
 		"Store must not retain records when the authorization code exchange fails."
 	);
 }
+
+#[tokio::test]
+async fn replayed_sessions_never_reach_the_provider_twice() {
+	let server = MockServer::start_async().await;
+	let guard = Arc::new(MemoryReplayGuard::default());
+	let replica = |guard: Arc<MemoryReplayGuard>| {
+		let (broker, _store) =
+			build_reqwest_test_broker(build_descriptor(&server), CLIENT_ID, CLIENT_SECRET);
+
+		broker.with_replay_guard(guard)
+	};
+	let (broker, other_replica) = (replica(guard.clone()), replica(guard.clone()));
+	let session = broker
+		.start_authorization(
+			TenantId::new("tenant-replay").expect("Tenant identifier should be valid."),
+			PrincipalId::new("principal-replay").expect("Principal identifier should be valid."),
+			ScopeSet::new(["email"]).expect("Scope set should be valid."),
+			Url::parse("https://app.example.com/callback")
+				.expect("Redirect URI should parse successfully."),
+		)
+		.expect("Authorization session should start successfully.");
+	let mock = server
+		.mock_async(|when, then| {
+			when.method(POST).path("/token");
+			then.status(200).header("content-type", "application/json").body(
+				"{\"access_token\":\"access-once\",\"token_type\":\"bearer\",\"expires_in\":3600}",
+			);
+		})
+		.await;
+
+	broker
+		.exchange_code(session.clone(), "code-once")
+		.await
+		.expect("The first exchange should succeed.");
+
+	for replayed_on in [broker.clone(), other_replica] {
+		let err = replayed_on
+			.exchange_code(session.clone(), "code-once")
+			.await
+			.expect_err("A replayed callback should be refused by every replica.");
+
+		assert!(matches!(err, Error::InvalidGrant { .. }));
+	}
+
+	assert_eq!(guard.len(), 1);
+
+	mock.assert_calls_async(1).await;
+}
+
+#[cfg(feature = "ring")]
+#[tokio::test]
+async fn hybrid_sessions_require_a_signed_id_token_bound_to_the_nonce_and_code() {
+	// crates.io
+	use ring::{
+		rand::SystemRandom,
+		signature::{ECDSA_P256_SHA256_FIXED_SIGNING, EcdsaKeyPair, KeyPair},
+	};
+
+	let server = MockServer::start_async().await;
+	let mut descriptor = build_descriptor(&server);
+
+	descriptor.quirks = ProviderQuirks { hybrid_id_token: true, ..descriptor.quirks };
+	descriptor.endpoints.jwks =
+		Some(Url::parse(&server.url("/jwks")).expect("Mock JWKS endpoint should parse."));
+
+	let (broker, _store) = build_reqwest_test_broker(descriptor, CLIENT_ID, CLIENT_SECRET);
+	let session = broker
+		.start_authorization(
+			TenantId::new("tenant-hybrid").expect("Tenant identifier should be valid."),
+			PrincipalId::new("principal-hybrid").expect("Principal identifier should be valid."),
+			ScopeSet::new(["openid"]).expect("Scope set should be valid."),
+			Url::parse("https://app.example.com/callback")
+				.expect("Redirect URI should parse successfully."),
+		)
+		.expect("Hybrid authorization session should start successfully.");
+	let nonce = session.nonce.clone().expect("Hybrid sessions should carry a nonce.");
+	let authorize_pairs: HashMap<_, _> = session.authorize_url.query_pairs().into_owned().collect();
+
+	assert_eq!(authorize_pairs.get("response_type"), Some(&"code id_token".into()));
+	assert_eq!(authorize_pairs.get("nonce"), Some(&nonce));
+
+	let rng = SystemRandom::new();
+	let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng)
+		.expect("P-256 key generation should succeed.");
+	let key_pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng)
+		.expect("Generated key should load.");
+	let point = key_pair.public_key().as_ref();
+	let jwks = server
+		.mock_async(|when, then| {
+			when.method(GET).path("/jwks");
+			then.status(200).header("content-type", "application/json").body(
+				serde_json::json!({
+					"keys": [{
+						"kty": "EC",
+						"kid": "id-1",
+						"crv": "P-256",
+						"x": URL_SAFE_NO_PAD.encode(&point[1..33]),
+						"y": URL_SAFE_NO_PAD.encode(&point[33..]),
+					}]
+				})
+				.to_string(),
+			);
+		})
+		.await;
+	let mock = server
+		.mock_async(|when, then| {
+			when.method(POST).path("/token").form_urlencoded_tuple("code", "code-hybrid");
+			then.status(200).header("content-type", "application/json").body(
+				"{\"access_token\":\"access-hybrid\",\"token_type\":\"bearer\",\"expires_in\":3600}",
+			);
+		})
+		.await;
+	let claims = |nonce: &str, code: &str| {
+		let digest = Sha256::digest(code);
+
+		URL_SAFE_NO_PAD.encode(
+			serde_json::json!({
+				"aud": CLIENT_ID,
+				"nonce": nonce,
+				"exp": OffsetDateTime::now_utc().unix_timestamp() + 300,
+				"c_hash": URL_SAFE_NO_PAD.encode(&digest[..16]),
+			})
+			.to_string(),
+		)
+	};
+	let id_token = |nonce: &str, code: &str| {
+		let signing_input = format!(
+			"{}.{}",
+			URL_SAFE_NO_PAD.encode(r#"{"alg":"ES256","kid":"id-1"}"#),
+			claims(nonce, code)
+		);
+		let signature =
+			key_pair.sign(&rng, signing_input.as_bytes()).expect("Signing should succeed.");
+
+		format!("{signing_input}.{}", URL_SAFE_NO_PAD.encode(signature.as_ref()))
+	};
+	let signed = id_token(&nonce, "code-hybrid");
+	let (signing_input, _) =
+		signed.rsplit_once('.').expect("Signed ID tokens should have three segments.");
+	let unsigned = format!(
+		"{}.{}.",
+		URL_SAFE_NO_PAD.encode(r#"{"alg":"none"}"#),
+		claims(&nonce, "code-hybrid")
+	);
+	let forged = format!("{signing_input}.{}", URL_SAFE_NO_PAD.encode([0_u8; 64]));
+
+	for rejected in [
+		session.clone(),
+		session.clone().with_id_token(id_token("other-nonce", "code-hybrid")),
+		session.clone().with_id_token(id_token(&nonce, "injected-code")),
+		session.clone().with_id_token(unsigned),
+		session.clone().with_id_token(forged),
+	] {
+		let err = broker
+			.exchange_code(rejected, "code-hybrid")
+			.await
+			.expect_err("Unbound or unverified hybrid responses should be refused.");
+
+		assert!(matches!(err, Error::InvalidGrant { .. }));
+	}
+
+	let record = broker
+		.exchange_code(session.with_id_token(signed), "code-hybrid")
+		.await
+		.expect("A signed and bound hybrid response should be exchanged.");
+
+	assert_eq!(record.access_token.expose(), "access-hybrid");
+
+	jwks.assert_calls_async(1).await;
+	mock.assert_calls_async(1).await;
+}
+
+#[tokio::test]
+async fn exchange_rejects_a_mismatched_or_missing_callback_issuer() {
+	let server = MockServer::start_async().await;
+	let mut descriptor = build_descriptor(&server);
+
+	descriptor.issuer =
+		Some(Url::parse("https://login.example.com").expect("Issuer fixture should parse."));
+	descriptor.quirks =
+		ProviderQuirks { require_authorization_response_iss: true, ..descriptor.quirks };
+
+	let (broker, _store) = build_reqwest_test_broker(descriptor, CLIENT_ID, CLIENT_SECRET);
+	let start = || {
+		broker
+			.start_authorization(
+				TenantId::new("tenant-iss").expect("Tenant identifier should be valid."),
+				PrincipalId::new("principal-iss").expect("Principal identifier should be valid."),
+				ScopeSet::new(["openid"]).expect("Scope set should be valid."),
+				Url::parse("https://app.example.com/callback")
+					.expect("Redirect URI should parse successfully."),
+			)
+			.expect("Authorization session should start successfully.")
+	};
+	let mock = server
+		.mock_async(|when, then| {
+			when.method(POST).path("/token");
+			then.status(200).header("content-type", "application/json").body(
+				"{\"access_token\":\"access-iss\",\"token_type\":\"bearer\",\"expires_in\":3600}",
+			);
+		})
+		.await;
+
+	for rejected in [
+		start(),
+		start().with_issuer("https://attacker.example.com"),
+		start().with_issuer("https://LOGIN.example.com"),
+	] {
+		let err = broker
+			.exchange_code(rejected, "code-iss")
+			.await
+			.expect_err("Callbacks without the expected issuer should be refused.");
+
+		assert!(matches!(err, Error::InvalidGrant { .. }));
+	}
+
+	mock.assert_calls_async(0).await;
+
+	let record = broker
+		.exchange_code(start().with_issuer("https://login.example.com"), "code-iss")
+		.await
+		.expect("A callback naming the configured issuer should be exchanged.");
+
+	assert_eq!(record.access_token.expose(), "access-iss");
+
+	mock.assert_calls_async(1).await;
+}
+
+#[tokio::test]
+async fn step_up_requests_only_missing_scopes() {
+	let server = MockServer::start_async().await;
+	let descriptor = build_descriptor(&server);
+	let (broker, _store) = build_reqwest_test_broker(descriptor, CLIENT_ID, CLIENT_SECRET);
+	let tenant = TenantId::new("tenant-step-up")
+		.expect("Tenant identifier should be valid for step-up test.");
+	let principal = PrincipalId::new("principal-step-up")
+		.expect("Principal identifier should be valid for step-up test.");
+	let redirect_uri = Url::parse("https://app.example.com/callback")
+		.expect("Redirect URI should parse successfully.");
+	let session = broker
+		.start_authorization(
+			tenant.clone(),
+			principal.clone(),
+			ScopeSet::new(["openid", "files.read"]).expect("Scope set should be valid."),
+			redirect_uri.clone(),
+		)
+		.expect("Authorization session should start successfully.");
+	let err = BearerChallenge::parse(
+		"Bearer error=\"insufficient_scope\", scope=\"files.read files.write\"",
+	)
+	.and_then(BearerChallenge::into_insufficient_scope)
+	.expect("Challenge should map to an insufficient scope error.");
+	let step_up = broker
+		.step_up(&session, &err, redirect_uri.clone())
+		.expect("Step-up should start successfully.")
+		.expect("Missing scopes should produce a follow-up session.");
+
+	assert_eq!(step_up.tenant, tenant);
+	assert_eq!(step_up.principal, principal);
+	assert_eq!(
+		step_up.scope,
+		ScopeSet::new(["files.write"]).expect("Expected scope set should be valid.")
+	);
+
+	let authorize_pairs: HashMap<_, _> = step_up.authorize_url.query_pairs().into_owned().collect();
+
+	assert_eq!(authorize_pairs.get("scope"), Some(&"files.write".into()));
+
+	let holder = CachedTokenRequest::new(
+		tenant,
+		principal,
+		ScopeSet::new(["files.read", "files.write"]).expect("Scope set should be valid."),
+	);
+	let satisfied =
+		broker.step_up(&holder, &err, redirect_uri).expect("Step-up should evaluate successfully.");
+
+	assert!(satisfied.is_none());
+}
+
+#[tokio::test]
+async fn request_objects_carry_the_authorize_parameters() {
+	let server = MockServer::start_async().await;
+	let descriptor = build_descriptor(&server);
+	let (broker, _store) = build_reqwest_test_broker(descriptor, CLIENT_ID, CLIENT_SECRET);
+	let broker = broker.with_request_object(
+		RequestObjectConfig::new(Arc::new(FixedSigner))
+			.with_audience("https://issuer.example.com")
+			.with_extra_claim("acr_values", "urn:mace:incommon:iap:silver"),
+	);
+	let tenant = TenantId::new("tenant-jar").expect("Tenant identifier should be valid.");
+	let principal =
+		PrincipalId::new("principal-jar").expect("Principal identifier should be valid.");
+	let scope = ScopeSet::new(["openid", "accounts"]).expect("Scope set should be valid.");
+	let redirect_uri = Url::parse("https://app.example.com/callback")
+		.expect("Redirect URI should parse successfully.");
+	let session = broker
+		.start_authorization(tenant.clone(), principal.clone(), scope.clone(), redirect_uri.clone())
+		.expect("Authorization session should start successfully.");
+	let request_object =
+		session.request_object.clone().expect("Session should expose the request object.");
+	let authorize_pairs: HashMap<_, _> = session.authorize_url.query_pairs().into_owned().collect();
+
+	assert_eq!(authorize_pairs.len(), 2, "Only client_id and request may stay in the URL.");
+	assert_eq!(authorize_pairs.get("client_id"), Some(&CLIENT_ID.into()));
+	assert_eq!(authorize_pairs.get("request"), Some(&request_object));
+
+	let segments = request_object.split('.').collect::<Vec<_>>();
+	let decode = |segment: &str| -> serde_json::Value {
+		serde_json::from_slice(
+			&URL_SAFE_NO_PAD.decode(segment).expect("Request object segment should be base64url."),
+		)
+		.expect("Request object segment should be JSON.")
+	};
+	let header = decode(segments[0]);
+	let claims = decode(segments[1]);
+
+	assert_eq!(header["alg"], "PS256");
+	assert_eq!(header["typ"], RequestObjectConfig::MEDIA_TYPE);
+	assert_eq!(claims["iss"], CLIENT_ID);
+	assert_eq!(claims["aud"], "https://issuer.example.com");
+	assert_eq!(claims["response_type"], "code");
+	assert_eq!(claims["redirect_uri"], redirect_uri.as_str());
+	assert_eq!(claims["scope"], "accounts openid");
+	assert_eq!(claims["state"], session.state.as_str());
+	assert_eq!(claims["code_challenge"], session.code_challenge());
+	assert_eq!(claims["acr_values"], "urn:mace:incommon:iap:silver");
+	assert_eq!(
+		claims["exp"].as_i64().zip(claims["nbf"].as_i64()).map(|(exp, nbf)| exp - nbf),
+		Some(RequestObjectConfig::DEFAULT_LIFETIME.whole_seconds())
+	);
+
+	let publisher = Arc::new(RecordingPublisher::default());
+	let by_reference = broker
+		.with_request_object(
+			RequestObjectConfig::new(Arc::new(FixedSigner)).with_request_uri(publisher.clone()),
+		)
+		.start_authorization(tenant, principal, scope, redirect_uri)
+		.expect("Authorization session should start successfully.");
+	let authorize_pairs: HashMap<_, _> =
+		by_reference.authorize_url.query_pairs().into_owned().collect();
+
+	assert_eq!(authorize_pairs.get("request_uri"), Some(&"https://app.example.com/jar/1".into()));
+	assert!(!authorize_pairs.contains_key("request"));
+	assert_eq!(publisher.0.lock().as_slice(), by_reference.request_object.as_slice());
+}
+
+#[tokio::test]
+async fn client_auth_methods_apply_per_grant() {
+	let server = MockServer::start_async().await;
+	let provider_id = ProviderId::new("mock-per-grant-auth").expect("Provider id should be valid.");
+	let descriptor = ProviderDescriptor::builder(provider_id)
+		.authorization_endpoint(
+			Url::parse(&server.url("/authorize")).expect("Authorization endpoint should parse."),
+		)
+		.token_endpoint(Url::parse(&server.url("/token")).expect("Token endpoint should parse."))
+		.support_grants([GrantType::AuthorizationCode, GrantType::RefreshToken])
+		.preferred_client_auth_method(ClientAuthMethod::ClientSecretBasic)
+		.client_auth_method_for(GrantType::AuthorizationCode, ClientAuthMethod::NoneWithPkce)
+		.build()
+		.expect("Provider descriptor should build successfully.");
+
+	assert_eq!(
+		descriptor.client_auth_method(GrantType::AuthorizationCode),
+		ClientAuthMethod::NoneWithPkce
+	);
+	assert_eq!(
+		descriptor.client_auth_method(GrantType::RefreshToken),
+		ClientAuthMethod::ClientSecretBasic
+	);
+
+	let (broker, _store) = build_reqwest_test_broker(descriptor, CLIENT_ID, CLIENT_SECRET);
+	let tenant = TenantId::new("tenant-per-grant").expect("Tenant identifier should be valid.");
+	let principal =
+		PrincipalId::new("principal-per-grant").expect("Principal identifier should be valid.");
+	let scope = ScopeSet::new(["openid"]).expect("Scope set should be valid.");
+	let session = broker
+		.start_authorization(
+			tenant.clone(),
+			principal.clone(),
+			scope.clone(),
+			Url::parse("https://app.example.com/callback")
+				.expect("Redirect URI should parse successfully."),
+		)
+		.expect("Authorization session should start successfully.");
+	let exchange = server
+		.mock_async(|when, then| {
+			when.method(POST)
+				.path("/token")
+				.form_urlencoded_tuple("grant_type", "authorization_code")
+				.form_urlencoded_tuple("client_id", CLIENT_ID)
+				.form_urlencoded_tuple_missing("client_secret")
+				.header_missing("authorization");
+			then.status(200).header("content-type", "application/json").body(
+				"{\"access_token\":\"access-public\",\"refresh_token\":\"refresh-public\",\"token_type\":\"bearer\",\"expires_in\":3600}",
+			);
+		})
+		.await;
+
+	broker
+		.exchange_code(session, "code-public")
+		.await
+		.expect("The public code exchange should succeed.");
+
+	let refresh = server
+		.mock_async(|when, then| {
+			when.method(POST)
+				.path("/token")
+				.form_urlencoded_tuple("grant_type", "refresh_token")
+				.form_urlencoded_tuple_missing("client_id")
+				.header_exists("authorization");
+			then.status(200).header("content-type", "application/json").body(
+				"{\"access_token\":\"access-confidential\",\"token_type\":\"bearer\",\"expires_in\":3600}",
+			);
+		})
+		.await;
+	let refreshed = broker
+		.refresh_access_token(CachedTokenRequest::new(tenant, principal, scope).with_force(true))
+		.await
+		.expect("The Basic-authenticated refresh should succeed.");
+
+	assert_eq!(refreshed.access_token.expose(), "access-confidential");
+
+	exchange.assert_async().await;
+	refresh.assert_async().await;
+}
+
+#[cfg(feature = "loopback")]
+fn loopback_get(redirect_uri: &Url, target: &str) -> String {
+	// std
+	use std::{
+		io::{Read, Write},
+		net::TcpStream,
+	};
+
+	let address = format!(
+		"{}:{}",
+		redirect_uri.host_str().unwrap_or_default(),
+		redirect_uri.port().unwrap_or_default()
+	);
+	let mut stream = TcpStream::connect(address).expect("Loopback listener should accept.");
+	let mut response = String::new();
+
+	write!(stream, "GET {target} HTTP/1.1\r\nhost: 127.0.0.1\r\n\r\n")
+		.expect("Callback request should be written.");
+	stream.read_to_string(&mut response).expect("Callback response should be readable.");
+
+	response
+}
+
+#[cfg(feature = "loopback")]
+#[tokio::test]
+async fn authorize_via_loopback_exchanges_the_redirected_code() {
+	// std
+	use std::{net::TcpStream, thread};
+	// self
+	use oauth2_broker::flows::LoopbackOptions;
+
+	let server = MockServer::start_async().await;
+	let mut descriptor = build_descriptor(&server);
+
+	// The exchange only succeeds when the loopback forwards the callback's `iss`.
+	descriptor.issuer =
+		Some(Url::parse("https://login.example.com").expect("Issuer fixture should parse."));
+	descriptor.quirks =
+		ProviderQuirks { require_authorization_response_iss: true, ..descriptor.quirks };
+
+	let (broker, _) = build_reqwest_test_broker(descriptor, CLIENT_ID, CLIENT_SECRET);
+	let mock = server
+		.mock_async(|when, then| {
+			when.method(POST).path("/token").body_includes("code=loopback-code");
+			then.status(200).header("content-type", "application/json").body(
+				"{\"access_token\":\"access-loopback\",\"token_type\":\"bearer\",\"expires_in\":3600}",
+			);
+		})
+		.await;
+	let responses = Arc::new(Mutex::new(Vec::new()));
+	let captured = Arc::clone(&responses);
+	let options = LoopbackOptions::default().with_path("cb").with_launcher(move |authorize_url| {
+		let pairs: HashMap<_, _> = authorize_url.query_pairs().into_owned().collect();
+		let redirect_uri = Url::parse(&pairs["redirect_uri"]).expect("Redirect URI should parse.");
+		let state = pairs["state"].clone();
+		let captured = Arc::clone(&captured);
+
+		thread::spawn(move || {
+			// A client that connects and never sends a request must not block the callback.
+			let _stalled = TcpStream::connect(format!(
+				"127.0.0.1:{}",
+				redirect_uri.port().unwrap_or_default()
+			))
+			.expect("Loopback listener should accept.");
+			let favicon = loopback_get(&redirect_uri, "/favicon.ico");
+			let forged = loopback_get(&redirect_uri, "/cb?code=forged-code&state=forged-state");
+			let callback = loopback_get(
+				&redirect_uri,
+				&format!(
+					"/cb?code=loopback-code&state={state}&iss=https%3A%2F%2Flogin.example.com"
+				),
+			);
+
+			captured.lock().extend([favicon, forged, callback]);
+		});
+
+		Ok(())
+	});
+	let record = broker
+		.authorize_via_loopback(
+			TenantId::new("tenant-loopback").expect("Tenant identifier should be valid."),
+			PrincipalId::new("principal-loopback").expect("Principal identifier should be valid."),
+			ScopeSet::new(["profile"]).expect("Scope set should be valid."),
+			options,
+		)
+		.await
+		.expect("Loopback login should exchange the code.");
+
+	mock.assert_async().await;
+
+	assert_eq!(record.access_token.expose(), "access-loopback");
+
+	// The callback thread records both responses right after the exchange unblocks it.
+	for _ in 0..50 {
+		if responses.lock().len() == 3 {
+			break;
+		}
+
+		tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+	}
+
+	let responses = responses.lock();
+
+	assert!(responses[0].starts_with("HTTP/1.1 404"));
+	assert!(responses[1].starts_with("HTTP/1.1 400"));
+	assert!(responses[2].starts_with("HTTP/1.1 200"));
+	assert!(responses[2].contains("Authorization complete"));
+}
+
+#[cfg(feature = "loopback")]
+#[tokio::test]
+async fn authorize_via_loopback_reports_denied_authorizations() {
+	// std
+	use std::sync::mpsc;
+	// self
+	use oauth2_broker::flows::LoopbackOptions;
+
+	let server = MockServer::start_async().await;
+	let (broker, _) =
+		build_reqwest_test_broker(build_descriptor(&server), CLIENT_ID, CLIENT_SECRET);
+	let (sender, responses) = mpsc::channel();
+	let options = LoopbackOptions::default().with_launcher(move |authorize_url| {
+		let pairs: HashMap<_, _> = authorize_url.query_pairs().into_owned().collect();
+		let redirect_uri = Url::parse(&pairs["redirect_uri"]).expect("Redirect URI should parse.");
+		let state = pairs["state"].clone();
+		let sender = sender.clone();
+
+		std::thread::spawn(move || {
+			let forged = loopback_get(&redirect_uri, "/callback?error=access_denied&state=forged");
+			let denied = loopback_get(
+				&redirect_uri,
+				&format!("/callback?error=access_denied&error_description=nope&state={state}"),
+			);
+			let _ = sender.send((forged, denied));
+		});
+
+		Ok(())
+	});
+	let err = broker
+		.authorize_via_loopback(
+			TenantId::new("tenant-loopback").expect("Tenant identifier should be valid."),
+			PrincipalId::new("principal-loopback").expect("Principal identifier should be valid."),
+			ScopeSet::new(["profile"]).expect("Scope set should be valid."),
+			options,
+		)
+		.await
+		.expect_err("A denied authorization should fail the login.");
+
+	assert!(matches!(&err, Error::InvalidGrant { reason } if reason.contains("nope")));
+
+	let (forged, denied) = responses
+		.recv_timeout(std::time::Duration::from_secs(5))
+		.expect("The callback thread should report both responses.");
+
+	assert!(forged.starts_with("HTTP/1.1 400"));
+	assert!(denied.starts_with("HTTP/1.1 200"));
+	assert!(denied.contains("Authorization failed"));
+	assert!(!denied.contains("Authorization complete"));
+}
+
+#[cfg(feature = "ring")]
+#[tokio::test]
+async fn persisted_sessions_survive_a_restart_and_redeem_once() {
+	// std
+	use std::{env, fs};
+	// self
+	use oauth2_broker::flows::SessionVault;
+
+	let server = MockServer::start_async().await;
+	let descriptor = build_descriptor(&server);
+	let dir = env::temp_dir().join(format!(
+		"oauth2-broker-sessions-{}",
+		OffsetDateTime::now_utc().unix_timestamp_nanos()
+	));
+	let key = rand::random::<[u8; 32]>();
+	let state = {
+		// The first "process" starts the login, persists it, and exits.
+		let (broker, _) = build_reqwest_test_broker(descriptor.clone(), CLIENT_ID, CLIENT_SECRET);
+		let vault = SessionVault::open(&dir, key).expect("Session vault should open.");
+		let session = broker
+			.start_authorization(
+				TenantId::new("tenant-native").expect("Tenant identifier should be valid."),
+				PrincipalId::new("principal-native")
+					.expect("Principal identifier should be valid."),
+				ScopeSet::new(["profile"]).expect("Scope set should be valid."),
+				Url::parse("http://127.0.0.1:8765/callback").expect("Redirect URI should parse."),
+			)
+			.expect("Authorization session should start.");
+
+		vault.save(&session).expect("Session should persist.");
+
+		session.state
+	};
+	let mock = server
+		.mock_async(|when, then| {
+			when.method(POST).path("/token").body_includes("code_verifier=");
+			then.status(200).header("content-type", "application/json").body(
+				"{\"access_token\":\"access-resumed\",\"token_type\":\"bearer\",\"expires_in\":3600}",
+			);
+		})
+		.await;
+	let (broker, _) = build_reqwest_test_broker(descriptor, CLIENT_ID, CLIENT_SECRET);
+	let vault = SessionVault::open(&dir, key).expect("Session vault should reopen.");
+
+	assert!(vault.contains(&state));
+	assert!(
+		broker
+			.resume_authorization(
+				&SessionVault::open(&dir, [0; 32]).expect("Session vault should open."),
+				&state,
+			)
+			.is_err(),
+		"A different key must not decrypt the session."
+	);
+
+	let record = broker
+		.exchange_persisted_code(&vault, &state, "resumed-code", None)
+		.await
+		.expect("Persisted session should be redeemable after a restart.");
+
+	mock.assert_async().await;
+
+	assert_eq!(record.access_token.expose(), "access-resumed");
+	assert!(!vault.contains(&state));
+	assert!(matches!(
+		broker.exchange_persisted_code(&vault, &state, "resumed-code", None).await,
+		Err(Error::InvalidGrant { .. })
+	));
+
+	let _ = fs::remove_dir_all(&dir);
+}
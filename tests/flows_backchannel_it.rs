@@ -0,0 +1,268 @@
+#![cfg(feature = "reqwest")]
+
+// crates.io
+use httpmock::prelude::*;
+// self
+use oauth2_broker::{
+	_preludet::*,
+	auth::{PrincipalId, ProviderId, ScopeSet, TenantId, TokenFamily},
+	error::ConfigError,
+	flows::{BackchannelPoll, BackchannelRequest},
+	provider::{ClientAuthMethod, GrantType, ProviderDescriptor, ProviderEndpoint},
+	store::BrokerStore,
+};
+
+const CLIENT_ID: &str = "client-ciba";
+const CLIENT_SECRET: &str = "secret-ciba";
+const CIBA_GRANT: &str = "urn:openid:params:grant-type:ciba";
+
+fn build_descriptor(server: &MockServer) -> ProviderDescriptor {
+	let provider_id = ProviderId::new("mock-ciba")
+		.expect("Provider identifier should be valid for backchannel tests.");
+
+	ProviderDescriptor::builder(provider_id)
+		.authorization_endpoint(
+			Url::parse(&server.url("/authorize"))
+				.expect("Mock authorization endpoint should parse successfully."),
+		)
+		.token_endpoint(
+			Url::parse(&server.url("/token"))
+				.expect("Mock token endpoint should parse successfully."),
+		)
+		.backchannel_authentication_endpoint(
+			Url::parse(&server.url("/bc-authorize"))
+				.expect("Mock backchannel endpoint should parse successfully."),
+		)
+		.support_grants([GrantType::Ciba])
+		.preferred_client_auth_method(ClientAuthMethod::ClientSecretPost)
+		.build()
+		.expect("Provider descriptor should build successfully.")
+}
+
+fn build_request(tenant: &str, principal: &str) -> BackchannelRequest {
+	BackchannelRequest::new(
+		TenantId::new(tenant).expect("Tenant identifier should be valid for backchannel tests."),
+		PrincipalId::new(principal)
+			.expect("Principal identifier should be valid for backchannel tests."),
+		ScopeSet::new(["openid", "profile"])
+			.expect("Scope set should be valid for backchannel tests."),
+	)
+}
+
+#[tokio::test]
+async fn backchannel_poll_mode_waits_for_approval_and_stores_tokens() {
+	let server = MockServer::start_async().await;
+	let (broker, store) =
+		build_reqwest_test_broker(build_descriptor(&server), CLIENT_ID, CLIENT_SECRET);
+	let authorize = server
+		.mock_async(|when, then| {
+			when.method(POST)
+				.path("/bc-authorize")
+				.form_urlencoded_tuple("client_id", CLIENT_ID)
+				.form_urlencoded_tuple("login_hint", "agent@example.com")
+				.form_urlencoded_tuple("binding_message", "W4SCT");
+			then.status(200)
+				.header("content-type", "application/json")
+				.body("{\"auth_req_id\":\"req-1\",\"expires_in\":120,\"interval\":0}");
+		})
+		.await;
+	let pending = server
+		.mock_async(|when, then| {
+			when.method(POST)
+				.path("/token")
+				.form_urlencoded_tuple("grant_type", CIBA_GRANT)
+				.form_urlencoded_tuple("auth_req_id", "req-1");
+			then.status(400)
+				.header("content-type", "application/json")
+				.body("{\"error\":\"authorization_pending\"}");
+		})
+		.await;
+	let session = broker
+		.start_backchannel_authentication(
+			build_request("tenant-ciba", "principal-ciba")
+				.with_login_hint("agent@example.com")
+				.with_binding_message("W4SCT"),
+		)
+		.await
+		.expect("Backchannel authentication should start.");
+
+	assert_eq!(session.auth_req_id, "req-1");
+	assert!(matches!(
+		broker
+			.poll_backchannel_authentication("req-1")
+			.await
+			.expect("Pending poll should succeed."),
+		BackchannelPoll::Pending { .. }
+	));
+
+	authorize.assert_calls_async(1).await;
+	pending.assert_calls_async(1).await;
+	pending.delete_async().await;
+
+	let approved = server
+		.mock_async(|when, then| {
+			when.method(POST).path("/token").form_urlencoded_tuple("grant_type", CIBA_GRANT);
+			then.status(200).header("content-type", "application/json").body(
+				"{\"access_token\":\"ciba-token\",\"token_type\":\"bearer\",\"expires_in\":600}",
+			);
+		})
+		.await;
+	let record = broker
+		.wait_for_backchannel_authentication("req-1")
+		.await
+		.expect("Approved backchannel request should yield tokens.");
+
+	assert_eq!(record.access_token.expose(), "ciba-token");
+
+	let mut family = TokenFamily::new(session.tenant.clone(), session.principal.clone());
+
+	family.provider = Some(
+		ProviderId::new("mock-ciba")
+			.expect("Provider identifier should be valid for backchannel tests."),
+	);
+	let stored = <dyn BrokerStore>::fetch(store.as_ref(), &family, &session.scope)
+		.await
+		.expect("Store fetch should succeed.")
+		.expect("Approved tokens should be persisted.");
+
+	assert_eq!(stored.access_token.expose(), "ciba-token");
+	assert!(broker.poll_backchannel_authentication("req-1").await.is_err());
+
+	approved.assert_calls_async(1).await;
+}
+
+#[tokio::test]
+async fn backchannel_slow_down_defers_polls_and_expired_token_drops_the_session() {
+	let server = MockServer::start_async().await;
+	let (broker, _) =
+		build_reqwest_test_broker(build_descriptor(&server), CLIENT_ID, CLIENT_SECRET);
+
+	server
+		.mock_async(|when, then| {
+			when.method(POST).path("/bc-authorize");
+			then.status(200)
+				.header("content-type", "application/json")
+				.body("{\"auth_req_id\":\"req-2\",\"expires_in\":120,\"interval\":0}");
+		})
+		.await;
+
+	let slow_down = server
+		.mock_async(|when, then| {
+			when.method(POST).path("/token");
+			then.status(400)
+				.header("content-type", "application/json")
+				.body("{\"error\":\"slow_down\"}");
+		})
+		.await;
+
+	broker
+		.start_backchannel_authentication(
+			build_request("tenant-ciba-slow", "principal-ciba-slow").with_login_hint("user"),
+		)
+		.await
+		.expect("Backchannel authentication should start.");
+
+	let BackchannelPoll::Pending { next_poll_at } = broker
+		.poll_backchannel_authentication("req-2")
+		.await
+		.expect("Slow-down poll should succeed.")
+	else {
+		panic!("Slow-down poll should stay pending.");
+	};
+
+	assert!(next_poll_at >= OffsetDateTime::now_utc() + Duration::seconds(4));
+	assert!(matches!(
+		broker.poll_backchannel_authentication("req-2").await.expect("Early poll should succeed."),
+		BackchannelPoll::Pending { .. }
+	));
+
+	slow_down.assert_calls_async(1).await;
+	slow_down.delete_async().await;
+
+	let expired = server
+		.mock_async(|when, then| {
+			when.method(POST).path("/token");
+			then.status(400)
+				.header("content-type", "application/json")
+				.body("{\"error\":\"expired_token\"}");
+		})
+		.await;
+	let ping = broker
+		.start_backchannel_authentication(
+			build_request("tenant-ciba-slow", "principal-ciba-slow")
+				.with_login_hint("user")
+				.with_ping("notify-secret"),
+		)
+		.await
+		.expect("Ping-mode backchannel authentication should start.");
+
+	assert_eq!(ping.auth_req_id, "req-2");
+	assert!(matches!(
+		broker.complete_backchannel_ping("req-2", "wrong-secret").await,
+		Err(Error::InvalidClient { .. })
+	));
+	assert!(matches!(
+		broker.complete_backchannel_ping("req-2", "notify-secret").await,
+		Err(Error::InvalidGrant { .. })
+	));
+	assert!(matches!(
+		broker.complete_backchannel_ping("req-2", "notify-secret").await,
+		Err(Error::InvalidGrant { .. })
+	));
+
+	expired.assert_calls_async(1).await;
+}
+
+#[tokio::test]
+async fn backchannel_request_requires_exactly_one_user_hint() {
+	let server = MockServer::start_async().await;
+	let (broker, _) =
+		build_reqwest_test_broker(build_descriptor(&server), CLIENT_ID, CLIENT_SECRET);
+	let result = broker
+		.start_backchannel_authentication(
+			build_request("tenant-ciba-hint", "principal-ciba-hint")
+				.with_login_hint("user")
+				.with_id_token_hint("id-token"),
+		)
+		.await;
+
+	assert!(matches!(result, Err(Error::Config(ConfigError::InvalidBackchannelRequest { .. }))));
+}
+
+#[tokio::test]
+async fn experimental_backchannel_endpoint_stays_off_until_enabled() {
+	let server = MockServer::start_async().await;
+	let mut descriptor = build_descriptor(&server);
+
+	descriptor.experimental_endpoints.insert(ProviderEndpoint::BackchannelAuthentication);
+
+	let (broker, _) = build_reqwest_test_broker(descriptor, CLIENT_ID, CLIENT_SECRET);
+	let authorize = server
+		.mock_async(|when, then| {
+			when.method(POST).path("/bc-authorize");
+			then.status(200)
+				.header("content-type", "application/json")
+				.body("{\"auth_req_id\":\"req-3\",\"expires_in\":120}");
+		})
+		.await;
+	let start = || {
+		broker.start_backchannel_authentication(
+			build_request("tenant-ciba-toggle", "principal-ciba-toggle").with_login_hint("user"),
+		)
+	};
+
+	assert!(!broker.endpoint_enabled(ProviderEndpoint::BackchannelAuthentication));
+	assert!(matches!(start().await, Err(Error::Config(ConfigError::EndpointDisabled { .. }))));
+
+	broker.enable_endpoint(ProviderEndpoint::BackchannelAuthentication);
+
+	let session = start().await.expect("Enabled backchannel endpoint should be used.");
+
+	assert_eq!(session.auth_req_id, "req-3");
+
+	broker.disable_endpoint(ProviderEndpoint::BackchannelAuthentication);
+
+	assert!(matches!(start().await, Err(Error::Config(ConfigError::EndpointDisabled { .. }))));
+
+	authorize.assert_calls_async(1).await;
+}
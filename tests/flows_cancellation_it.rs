@@ -0,0 +1,373 @@
+#![cfg(feature = "reqwest")]
+
+// crates.io
+use httpmock::prelude::*;
+// self
+use oauth2_broker::{
+	_preludet::*,
+	auth::{
+		PrincipalId, ProviderId, RevocationReason, ScopeSet, TenantId, TokenFamily, TokenRecord,
+	},
+	flows::{Broker, CachedTokenRequest, CancellationToken},
+	oauth::ReqwestTransportErrorMapper,
+	provider::{
+		ClientAuthMethod, DefaultProviderStrategy, GrantType, ProviderDescriptor, ProviderStrategy,
+	},
+	store::{BrokerStore, CompareAndSwapOutcome, MemoryStore, PreparedWrite, StoreFuture},
+};
+
+const CLIENT_ID: &str = "client-cancel";
+const CLIENT_SECRET: &str = "secret-cancel";
+const DROP_AFTER: std::time::Duration = std::time::Duration::from_millis(200);
+
+/// Store wrapper that never resolves the operation named in `stall`, simulating a caller that
+/// drops the flow future while it is parked on that await point.
+#[derive(Default)]
+struct StallingStore {
+	inner: MemoryStore,
+	stall: Mutex<Option<&'static str>>,
+}
+impl StallingStore {
+	fn stall_at(&self, operation: &'static str) {
+		*self.stall.lock() = Some(operation);
+	}
+
+	fn release(&self) {
+		*self.stall.lock() = None;
+	}
+
+	fn gate<'a, T>(&self, operation: &'static str, future: StoreFuture<'a, T>) -> StoreFuture<'a, T>
+	where
+		T: 'a,
+	{
+		let stalled = *self.stall.lock() == Some(operation);
+
+		Box::pin(async move {
+			if stalled {
+				std::future::pending::<()>().await;
+			}
+
+			future.await
+		})
+	}
+}
+impl BrokerStore for StallingStore {
+	fn save(&self, record: TokenRecord) -> StoreFuture<'_, ()> {
+		self.gate("save", self.inner.save(record))
+	}
+
+	fn fetch<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+	) -> StoreFuture<'a, Option<TokenRecord>> {
+		self.gate("fetch", self.inner.fetch(family, scope))
+	}
+
+	fn compare_and_swap_refresh<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+		expected_refresh: Option<&'a str>,
+		replacement: TokenRecord,
+	) -> StoreFuture<'a, CompareAndSwapOutcome> {
+		self.gate(
+			"compare_and_swap_refresh",
+			self.inner.compare_and_swap_refresh(family, scope, expected_refresh, replacement),
+		)
+	}
+
+	fn revoke<'a>(
+		&'a self,
+		family: &'a TokenFamily,
+		scope: &'a ScopeSet,
+		instant: OffsetDateTime,
+		reason: RevocationReason,
+	) -> StoreFuture<'a, Option<TokenRecord>> {
+		self.gate("revoke", self.inner.revoke(family, scope, instant, reason))
+	}
+
+	fn prepare(&self, record: TokenRecord) -> StoreFuture<'_, PreparedWrite> {
+		self.gate("prepare", self.inner.prepare(record))
+	}
+
+	fn commit<'a>(&'a self, prepared: &'a PreparedWrite) -> StoreFuture<'a, ()> {
+		self.gate("commit", self.inner.commit(prepared))
+	}
+}
+
+fn build_descriptor(server: &MockServer) -> ProviderDescriptor {
+	let provider_id = ProviderId::new("mock-cancellation")
+		.expect("Provider identifier should be valid for cancellation tests.");
+
+	ProviderDescriptor::builder(provider_id)
+		.authorization_endpoint(
+			Url::parse(&server.url("/authorize"))
+				.expect("Mock authorization endpoint should parse successfully."),
+		)
+		.token_endpoint(
+			Url::parse(&server.url("/token"))
+				.expect("Mock token endpoint should parse successfully."),
+		)
+		.support_grants([GrantType::ClientCredentials, GrantType::RefreshToken])
+		.preferred_client_auth_method(ClientAuthMethod::ClientSecretPost)
+		.build()
+		.expect("Provider descriptor should build successfully.")
+}
+
+fn build_broker(descriptor: ProviderDescriptor) -> (ReqwestTestBroker, Arc<StallingStore>) {
+	let store_backend = Arc::new(StallingStore::default());
+	let store: Arc<dyn BrokerStore> = store_backend.clone();
+	let strategy: Arc<dyn ProviderStrategy> = Arc::new(DefaultProviderStrategy);
+	let broker = Broker::with_http_client(
+		store,
+		descriptor,
+		strategy,
+		CLIENT_ID,
+		test_reqwest_http_client(),
+		Arc::new(ReqwestTransportErrorMapper),
+	)
+	.with_client_secret(CLIENT_SECRET);
+
+	(broker, store_backend)
+}
+
+fn request(label: &str) -> CachedTokenRequest {
+	let tenant = TenantId::new(format!("tenant-{label}"))
+		.expect("Tenant identifier should be valid for cancellation tests.");
+	let principal = PrincipalId::new(format!("principal-{label}"))
+		.expect("Principal identifier should be valid for cancellation tests.");
+	let scope =
+		ScopeSet::new(["api.read"]).expect("Scope set should be valid for cancellation tests.");
+
+	CachedTokenRequest::new(tenant, principal, scope)
+}
+
+fn family_of(request: &CachedTokenRequest, descriptor: &ProviderDescriptor) -> TokenFamily {
+	let mut family = TokenFamily::new(request.tenant.clone(), request.principal.clone());
+
+	family.provider = Some(descriptor.id.clone());
+
+	family
+}
+
+#[tokio::test]
+async fn cancelled_token_stops_before_contacting_provider() {
+	let server = MockServer::start_async().await;
+	let descriptor = build_descriptor(&server);
+	let (broker, store) = build_broker(descriptor.clone());
+	let mock = server
+		.mock_async(|when, then| {
+			when.method(POST).path("/token");
+			then.status(200).header("content-type", "application/json").body(
+				"{\"access_token\":\"never-issued\",\"token_type\":\"bearer\",\"expires_in\":1800}",
+			);
+		})
+		.await;
+	let cancellation = CancellationToken::new();
+
+	cancellation.cancel();
+
+	let request = request("pre-cancelled").with_cancellation(cancellation);
+	let err = broker
+		.client_credentials(request.clone())
+		.await
+		.expect_err("Cancelled requests should not complete.");
+
+	assert!(matches!(err, Error::Cancelled { .. }));
+
+	mock.assert_calls_async(0).await;
+
+	let stored = store
+		.fetch(&family_of(&request, &descriptor), &request.scope)
+		.await
+		.expect("Store fetch should succeed after cancellation.");
+
+	assert!(stored.is_none());
+}
+
+#[tokio::test]
+async fn client_credentials_survives_drop_at_each_store_await() {
+	for operation in ["fetch", "prepare", "commit"] {
+		let server = MockServer::start_async().await;
+		let descriptor = build_descriptor(&server);
+		let (broker, store) = build_broker(descriptor.clone());
+		let mock = server
+			.mock_async(|when, then| {
+				when.method(POST).path("/token");
+				then.status(200).header("content-type", "application/json").body(
+					"{\"access_token\":\"issued-token\",\"token_type\":\"bearer\",\"expires_in\":1800}",
+				);
+			})
+			.await;
+		let request = request(operation);
+
+		store.stall_at(operation);
+
+		let dropped =
+			tokio::time::timeout(DROP_AFTER, broker.client_credentials(request.clone())).await;
+
+		assert!(dropped.is_err(), "Flow should still be parked on {operation} when dropped.");
+
+		store.release();
+
+		let stored = store
+			.fetch(&family_of(&request, &descriptor), &request.scope)
+			.await
+			.expect("Store fetch should succeed after the dropped flow.");
+
+		assert!(stored.is_none(), "Dropping at {operation} should not publish a record.");
+
+		let record = tokio::time::timeout(DROP_AFTER * 10, broker.client_credentials(request))
+			.await
+			.expect("Singleflight guard should be released once the flow is dropped.")
+			.expect("Follow-up client_credentials request should succeed.");
+
+		assert_eq!(record.access_token.expose(), "issued-token");
+
+		mock.assert_calls_async(if operation == "fetch" { 1 } else { 2 }).await;
+	}
+}
+
+#[tokio::test]
+async fn refresh_survives_drop_at_each_store_await() {
+	for operation in ["fetch", "compare_and_swap_refresh"] {
+		let server = MockServer::start_async().await;
+		let descriptor = build_descriptor(&server);
+		let (broker, store) = build_broker(descriptor.clone());
+		let request = request(operation).force_refresh();
+		let family = family_of(&request, &descriptor);
+		let seeded = TokenRecord::builder(family.clone(), request.scope.clone())
+			.access_token("access-old")
+			.refresh_token("refresh-old")
+			.issued_at(OffsetDateTime::now_utc())
+			.expires_at(OffsetDateTime::now_utc() + Duration::minutes(30))
+			.build()
+			.expect("Seed record should build successfully.");
+
+		store.save(seeded).await.expect("Seeding the store should succeed.");
+
+		let rotate_old = server
+			.mock_async(|when, then| {
+				when.method(POST).path("/token").form_urlencoded_tuple("refresh_token", "refresh-old");
+				then.status(200)
+					.header("content-type", "application/json")
+					.body(
+						"{\"access_token\":\"access-new\",\"refresh_token\":\"refresh-new\",\"token_type\":\"bearer\",\"expires_in\":1800}",
+					);
+			})
+			.await;
+		let _rotate_new = server
+			.mock_async(|when, then| {
+				when.method(POST).path("/token").form_urlencoded_tuple("refresh_token", "refresh-new");
+				then.status(200)
+					.header("content-type", "application/json")
+					.body(
+						"{\"access_token\":\"access-newer\",\"refresh_token\":\"refresh-newer\",\"token_type\":\"bearer\",\"expires_in\":1800}",
+					);
+			})
+			.await;
+
+		store.stall_at(operation);
+
+		let dropped =
+			tokio::time::timeout(DROP_AFTER, broker.refresh_access_token(request.clone())).await;
+
+		assert!(dropped.is_err(), "Refresh should still be parked on {operation} when dropped.");
+
+		store.release();
+
+		let stored = store
+			.fetch(&family, &request.scope)
+			.await
+			.expect("Store fetch should succeed after the dropped refresh.")
+			.expect("Seeded record should remain present.");
+
+		assert_eq!(
+			stored.refresh_token.as_ref().map(|secret| secret.expose()),
+			Some("refresh-old")
+		);
+
+		// Once the provider has rotated, it rejects the spent refresh token.
+		let spent = rotate_old.calls_async().await == 1;
+		let reuse = if spent {
+			rotate_old.delete_async().await;
+
+			Some(
+				server
+					.mock_async(|when, then| {
+						when.method(POST)
+							.path("/token")
+							.form_urlencoded_tuple("refresh_token", "refresh-old");
+						then.status(400).header("content-type", "application/json").body(
+							"{\"error\":\"invalid_grant\",\"error_description\":\"refresh token reused\"}",
+						);
+					})
+					.await,
+			)
+		} else {
+			None
+		};
+		let record = tokio::time::timeout(DROP_AFTER * 10, broker.refresh_access_token(request))
+			.await
+			.expect("Singleflight guard should be released once the refresh is dropped.")
+			.expect("Follow-up refresh should not present a spent refresh token.");
+
+		if let Some(reuse) = reuse {
+			reuse.assert_calls_async(0).await;
+
+			assert_eq!(record.access_token.expose(), "access-newer");
+			assert_eq!(
+				record.refresh_token.as_ref().map(|secret| secret.expose()),
+				Some("refresh-newer")
+			);
+		} else {
+			assert_eq!(record.access_token.expose(), "access-new");
+		}
+	}
+}
+
+#[tokio::test]
+async fn cancellation_interrupts_a_singleflight_wait() {
+	let server = MockServer::start_async().await;
+	let descriptor = build_descriptor(&server);
+	let (broker, store) = build_broker(descriptor);
+	let _mock = server
+		.mock_async(|when, then| {
+			when.method(POST).path("/token");
+			then.status(200).header("content-type", "application/json").body(
+				"{\"access_token\":\"issued-token\",\"token_type\":\"bearer\",\"expires_in\":1800}",
+			);
+		})
+		.await;
+	let request = request("waiting");
+
+	store.stall_at("prepare");
+
+	let holder = broker.client_credentials(request.clone());
+	let cancellation = CancellationToken::new();
+	let waiter = broker.client_credentials(request.with_cancellation(cancellation.clone()));
+	let cancel = async {
+		while broker.singleflight_backlog().is_empty() {
+			tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+		}
+
+		cancellation.cancel();
+	};
+	let outcome = tokio::time::timeout(DROP_AFTER * 10, async {
+		// Poll the holder first so it takes the guard before the waiter queues on it.
+		tokio::select! {
+			biased;
+
+			_ = holder => panic!("The holder should stay parked on the stalled prepare."),
+			(result, ()) = async { tokio::join!(waiter, cancel) } => result,
+		}
+	})
+	.await
+	.expect("Cancelling should end the wait on the holder's guard.");
+
+	assert!(matches!(
+		outcome,
+		Err(Error::Cancelled { stage: "waiting on the singleflight guard" })
+	));
+}
@@ -5,9 +5,16 @@ use httpmock::prelude::*;
 // self
 use oauth2_broker::{
 	_preludet::*,
-	auth::{PrincipalId, ProviderId, ScopeSet, TenantId, TokenFamily, TokenRecord},
-	flows::CachedTokenRequest,
-	provider::{ClientAuthMethod, GrantType, ProviderDescriptor},
+	auth::{
+		PrincipalId, ProviderId, RevocationReason, ScopeSet, TenantId, TokenFamily, TokenRecord,
+	},
+	error::ConfigError,
+	flows::{Broker, CachedTokenRequest, ImportedToken},
+	oauth::ReqwestTransportErrorMapper,
+	provider::{
+		ClientAuthMethod, DefaultProviderStrategy, GrantType, MaintenanceWindow,
+		ProviderDescriptor, ProviderErrorContext, ProviderErrorKind, ProviderStrategy,
+	},
 	store::{BrokerStore, MemoryStore},
 };
 
@@ -118,6 +125,135 @@ async fn refresh_rotates_tokens_and_updates_store() {
 	assert_eq!(stored.refresh_token.as_ref().map(|secret| secret.expose()), Some("refresh-new"));
 }
 
+#[tokio::test]
+async fn refresh_sends_strategy_audience_and_uses_its_partition() {
+	/// Targets every token request at one API.
+	struct AudienceStrategy;
+	impl ProviderStrategy for AudienceStrategy {
+		fn classify_token_error(&self, ctx: &ProviderErrorContext) -> ProviderErrorKind {
+			DefaultProviderStrategy.classify_token_error(ctx)
+		}
+
+		fn augment_token_request(&self, _grant: GrantType, form: &mut BTreeMap<String, String>) {
+			form.insert("audience".into(), "https://api.example.com".into());
+		}
+	}
+
+	let server = MockServer::start_async().await;
+	let descriptor = build_descriptor(&server);
+	let store = Arc::new(MemoryStore::default());
+	let broker: ReqwestTestBroker = Broker::with_http_client(
+		store.clone(),
+		descriptor.clone(),
+		Arc::new(AudienceStrategy),
+		CLIENT_ID,
+		test_reqwest_http_client(),
+		Arc::new(ReqwestTransportErrorMapper),
+	)
+	.with_client_secret(CLIENT_SECRET);
+	let tenant = TenantId::new("tenant-audience").expect("Tenant identifier should be valid.");
+	let principal =
+		PrincipalId::new("principal-audience").expect("Principal identifier should be valid.");
+	let scope = ScopeSet::new(["api.read"]).expect("Scope set should be valid.");
+	let family = TokenFamily::new(tenant.clone(), principal.clone())
+		.with_audience("https://api.example.com");
+	let seeded = TokenRecord::builder(
+		TokenFamily { provider: Some(descriptor.id.clone()), ..family },
+		scope.clone(),
+	)
+	.access_token("access-audience")
+	.refresh_token("refresh-audience")
+	.issued_at(OffsetDateTime::now_utc() - Duration::minutes(5))
+	.expires_at(OffsetDateTime::now_utc() + Duration::minutes(30))
+	.build()
+	.expect("Token record fixture should build successfully.");
+
+	store.save(seeded).await.expect("Seeding the store should succeed.");
+
+	let mock = server
+		.mock_async(|when, then| {
+			when.method(POST)
+				.path("/token")
+				.form_urlencoded_tuple("grant_type", "refresh_token")
+				.form_urlencoded_tuple("refresh_token", "refresh-audience")
+				.form_urlencoded_tuple("audience", "https://api.example.com");
+			then.status(200).header("content-type", "application/json").body(
+				"{\"access_token\":\"access-audience-new\",\"token_type\":\"bearer\",\"expires_in\":1800}",
+			);
+		})
+		.await;
+	let record = broker
+		.refresh_access_token(CachedTokenRequest::new(tenant, principal, scope).force_refresh())
+		.await
+		.expect("Refresh should find the audience-partitioned record.");
+
+	mock.assert_async().await;
+
+	assert_eq!(record.access_token.expose(), "access-audience-new");
+	assert_eq!(record.family.audience.as_deref(), Some("https://api.example.com"));
+}
+
+#[tokio::test]
+async fn refresh_and_serve_bookkeeping_is_described() {
+	let server = MockServer::start_async().await;
+	let descriptor = build_descriptor(&server);
+	let (broker, store) = build_reqwest_test_broker(descriptor.clone(), CLIENT_ID, CLIENT_SECRET);
+	let broker = broker.with_served_tracking(Duration::minutes(1));
+	let tenant = TenantId::new("tenant-bookkeeping").expect("Tenant identifier should be valid.");
+	let principal =
+		PrincipalId::new("principal-bookkeeping").expect("Principal identifier should be valid.");
+	let scope = ScopeSet::new(["email"]).expect("Scope set should be valid.");
+
+	seed_record(
+		&store,
+		&descriptor,
+		tenant.clone(),
+		principal.clone(),
+		scope.clone(),
+		"access-old",
+		"refresh-old",
+		Duration::seconds(30),
+	)
+	.await;
+
+	let _mock = server
+		.mock_async(|when, then| {
+			when.method(POST).path("/token");
+			then.status(200)
+				.header("content-type", "application/json")
+				.body(
+					"{\"access_token\":\"access-new\",\"refresh_token\":\"refresh-new\",\"token_type\":\"bearer\",\"expires_in\":1800}",
+				);
+		})
+		.await;
+	let request = CachedTokenRequest::new(tenant, principal, scope);
+	let refreshed = broker
+		.refresh_access_token(request.clone())
+		.await
+		.expect("Refresh should rotate the due record.");
+
+	assert_eq!(refreshed.refresh_count, 1);
+	assert!(refreshed.last_refreshed_at.is_some());
+	assert!(refreshed.last_served_at.is_none());
+
+	let served =
+		broker.refresh_access_token(request).await.expect("Fresh record should be served.");
+
+	assert_eq!(served.access_token.expose(), "access-new");
+	assert!(served.last_served_at.is_some());
+
+	let description = broker
+		.describe_record(&refreshed.family, &refreshed.scope)
+		.await
+		.expect("Describing the record should succeed.")
+		.expect("Record should be present.");
+
+	assert_eq!(description.refresh_count, 1);
+	assert_eq!(description.last_served_at, served.last_served_at);
+	assert_eq!(description.last_activity, served.last_activity());
+	assert!(!description.is_idle_for(Duration::minutes(5), OffsetDateTime::now_utc()));
+}
+
 #[tokio::test]
 async fn refresh_singleflight_hits_provider_once() {
 	let server = MockServer::start_async().await;
@@ -221,4 +357,265 @@ async fn refresh_invalid_grant_revokes_record() {
 		.expect("Revoked record should remain present for inspection.");
 
 	assert!(revoked.revoked_at.is_some());
+	assert_eq!(revoked.revoked_reason, Some(RevocationReason::ProviderInvalidGrant));
+}
+
+#[tokio::test]
+async fn refresh_invalid_grant_cascades_access_token_revocation() {
+	let server = MockServer::start_async().await;
+	let mut descriptor = build_descriptor(&server);
+
+	descriptor.endpoints.revocation = Some(
+		Url::parse(&server.url("/revoke"))
+			.expect("Mock revocation endpoint should parse successfully."),
+	);
+	descriptor.quirks.cascade_revocation = true;
+
+	let (broker, store) = build_reqwest_test_broker(descriptor.clone(), CLIENT_ID, CLIENT_SECRET);
+	let tenant = TenantId::new("tenant-cascade").expect("Tenant identifier should be valid.");
+	let principal =
+		PrincipalId::new("principal-cascade").expect("Principal identifier should be valid.");
+	let scope = ScopeSet::new(["repo"]).expect("Scope set should be valid.");
+
+	seed_record(
+		&store,
+		&descriptor,
+		tenant.clone(),
+		principal.clone(),
+		scope.clone(),
+		"access-cascade",
+		"refresh-cascade",
+		Duration::minutes(10),
+	)
+	.await;
+
+	let _token = server
+		.mock_async(|when, then| {
+			when.method(POST).path("/token");
+			then.status(400)
+				.header("content-type", "application/json")
+				.body("{\"error\":\"invalid_grant\"}");
+		})
+		.await;
+	let revoke = server
+		.mock_async(|when, then| {
+			when.method(POST)
+				.path("/revoke")
+				.form_urlencoded_tuple("token", "access-cascade")
+				.form_urlencoded_tuple("token_type_hint", "access_token")
+				.form_urlencoded_tuple("client_id", CLIENT_ID)
+				.form_urlencoded_tuple("client_secret", CLIENT_SECRET);
+			then.status(200);
+		})
+		.await;
+	let err = broker
+		.refresh_access_token(CachedTokenRequest::new(tenant, principal, scope).force_refresh())
+		.await
+		.expect_err("Invalid grant errors should surface to the caller.");
+
+	assert!(matches!(err, Error::InvalidGrant { .. }));
+
+	revoke.assert_async().await;
+}
+
+#[tokio::test]
+async fn refresh_prefers_cached_tokens_during_maintenance() {
+	let server = MockServer::start_async().await;
+	let now = OffsetDateTime::now_utc();
+	let mut descriptor = build_descriptor(&server);
+
+	descriptor
+		.maintenance_windows
+		.push(MaintenanceWindow::new(now - Duration::minutes(5), now + Duration::hours(1)));
+
+	let (broker, store) = build_reqwest_test_broker(descriptor.clone(), CLIENT_ID, CLIENT_SECRET);
+	let tenant = TenantId::new("tenant-maintenance").expect("Tenant identifier should be valid.");
+	let principal =
+		PrincipalId::new("principal-maintenance").expect("Principal identifier should be valid.");
+	let scope = ScopeSet::new(["repo"]).expect("Scope set should be valid.");
+
+	seed_record(
+		&store,
+		&descriptor,
+		tenant.clone(),
+		principal.clone(),
+		scope.clone(),
+		"access-maintenance",
+		"refresh-maintenance",
+		Duration::minutes(6),
+	)
+	.await;
+
+	let mock = server
+		.mock_async(|when, then| {
+			when.method(POST).path("/token");
+			then.status(503).body("down for maintenance");
+		})
+		.await;
+	let request = CachedTokenRequest::new(tenant, principal, scope)
+		.with_preemptive_window(Duration::minutes(5));
+	let cached = broker
+		.refresh_access_token(request.clone())
+		.await
+		.expect("Unexpired tokens should be served during maintenance.");
+
+	assert_eq!(cached.access_token.expose(), "access-maintenance");
+
+	mock.assert_calls_async(0).await;
+
+	let cached = broker
+		.refresh_access_token(request.clone().with_min_ttl(Duration::minutes(10)))
+		.await
+		.expect("Unexpired tokens below the minimum TTL should outlast a maintenance failure.");
+
+	assert_eq!(cached.access_token.expose(), "access-maintenance");
+
+	mock.assert_calls_async(1).await;
+
+	let err = broker
+		.refresh_access_token(request.force_refresh())
+		.await
+		.expect_err("Forced refreshes should surface the provider outage.");
+
+	assert!(matches!(err, Error::ProviderMaintenance { .. }));
+
+	mock.assert_calls_async(2).await;
+}
+
+#[tokio::test]
+async fn shared_family_refresh_rotates_sibling_scope_records() {
+	let server = MockServer::start_async().await;
+	let mut descriptor = build_descriptor(&server);
+
+	descriptor.quirks.shared_family_refresh = true;
+
+	let (broker, store) = build_reqwest_test_broker(descriptor.clone(), CLIENT_ID, CLIENT_SECRET);
+	let tenant = TenantId::new("tenant-shared").expect("Tenant identifier should be valid.");
+	let principal =
+		PrincipalId::new("principal-shared").expect("Principal identifier should be valid.");
+	let read = ScopeSet::new(["repo.read"]).expect("Scope set should be valid.");
+	let write = ScopeSet::new(["repo.write"]).expect("Scope set should be valid.");
+
+	for (scope, access) in [(&read, "access-read"), (&write, "access-write")] {
+		seed_record(
+			&store,
+			&descriptor,
+			tenant.clone(),
+			principal.clone(),
+			scope.clone(),
+			access,
+			"refresh-shared",
+			Duration::minutes(10),
+		)
+		.await;
+	}
+
+	let first = server
+		.mock_async(|when, then| {
+			when.method(POST).path("/token").form_urlencoded_tuple("refresh_token", "refresh-shared");
+			then.status(200)
+				.header("content-type", "application/json")
+				.body(
+					"{\"access_token\":\"access-read-new\",\"refresh_token\":\"refresh-rotated\",\"token_type\":\"bearer\",\"expires_in\":1800}",
+				);
+		})
+		.await;
+	let record = broker
+		.refresh_access_token(
+			CachedTokenRequest::new(tenant.clone(), principal.clone(), read).force_refresh(),
+		)
+		.await
+		.expect("Refreshing the read scope should succeed.");
+
+	first.assert_async().await;
+
+	let sibling = store
+		.fetch(&record.family, &write)
+		.await
+		.expect("Token store fetch should succeed.")
+		.expect("Sibling record should remain present.");
+
+	assert_eq!(sibling.access_token.expose(), "access-write");
+	assert_eq!(
+		sibling.refresh_token.as_ref().map(|secret| secret.expose()),
+		Some("refresh-rotated")
+	);
+
+	let second = server
+		.mock_async(|when, then| {
+			when.method(POST).path("/token").form_urlencoded_tuple("refresh_token", "refresh-rotated");
+			then.status(200)
+				.header("content-type", "application/json")
+				.body(
+					"{\"access_token\":\"access-write-new\",\"refresh_token\":\"refresh-rotated-again\",\"token_type\":\"bearer\",\"expires_in\":1800}",
+				);
+		})
+		.await;
+	let record = broker
+		.refresh_access_token(CachedTokenRequest::new(tenant, principal, write).force_refresh())
+		.await
+		.expect("Refreshing the sibling scope should reuse the rotated secret.");
+
+	second.assert_async().await;
+
+	assert_eq!(record.access_token.expose(), "access-write-new");
+}
+
+#[tokio::test]
+async fn imported_tokens_are_refreshed_by_the_broker() {
+	let server = MockServer::start_async().await;
+	let descriptor = build_descriptor(&server);
+	let (broker, _store) = build_reqwest_test_broker(descriptor.clone(), CLIENT_ID, CLIENT_SECRET);
+	let family = TokenFamily::new(
+		TenantId::new("tenant-import").expect("Tenant identifier should be valid."),
+		PrincipalId::new("principal-import").expect("Principal identifier should be valid."),
+	);
+	let scope = ScopeSet::new(["repo", "user"]).expect("Scope set should be valid.");
+	let imported = broker
+		.import_token(
+			family.clone(),
+			scope.clone(),
+			ImportedToken::response(
+				"{\"access_token\":\"access-imported\",\"refresh_token\":\"refresh-imported\",\"token_type\":\"bearer\",\"expires_in\":600,\"scope\":\"user repo\"}",
+			),
+		)
+		.await
+		.expect("Importing a raw token response should succeed.");
+
+	assert_eq!(imported.family.provider.as_ref(), Some(&descriptor.id));
+	assert_eq!(imported.expires_at - imported.issued_at, Duration::minutes(10));
+
+	let expired = TokenRecord::builder(family.clone(), scope.clone())
+		.access_token("access-stale")
+		.issued_at(OffsetDateTime::now_utc() - Duration::hours(2))
+		.expires_in(Duration::hours(1))
+		.build()
+		.expect("Token record fixture should build successfully.");
+	let err = broker
+		.import_token(family.clone(), scope.clone(), expired)
+		.await
+		.expect_err("Expired records without a refresh token should be rejected.");
+
+	assert!(matches!(err, Error::Config(ConfigError::InvalidImportedToken { .. })));
+
+	let mock = server
+		.mock_async(|when, then| {
+			when.method(POST)
+				.path("/token")
+				.form_urlencoded_tuple("refresh_token", "refresh-imported");
+			then.status(200).header("content-type", "application/json").body(
+				"{\"access_token\":\"access-managed\",\"token_type\":\"bearer\",\"expires_in\":1800}",
+			);
+		})
+		.await;
+	let record = broker
+		.refresh_access_token(
+			CachedTokenRequest::new(family.tenant, family.principal, scope).force_refresh(),
+		)
+		.await
+		.expect("Refreshing an imported token should succeed.");
+
+	mock.assert_async().await;
+
+	assert_eq!(record.access_token.expose(), "access-managed");
 }
@@ -0,0 +1,117 @@
+#![cfg(all(feature = "reqwest", feature = "service"))]
+
+// crates.io
+use httpmock::prelude::*;
+// self
+use oauth2_broker::{
+	_preludet::*,
+	auth::{ProviderId, TenantId},
+	oauth::oauth2::http::{Request, Response, StatusCode},
+	provider::{ClientAuthMethod, GrantType, ProviderDescriptor},
+	service::{BrokerService, ServiceAuth},
+};
+
+const CLIENT_ID: &str = "service-client";
+const CLIENT_SECRET: &str = "service-secret";
+
+fn build_descriptor(server: &MockServer) -> ProviderDescriptor {
+	ProviderDescriptor::builder(
+		ProviderId::new("mock-service").expect("Provider identifier should be valid."),
+	)
+	.authorization_endpoint(
+		Url::parse(&server.url("/authorize")).expect("Mock authorization endpoint should parse."),
+	)
+	.token_endpoint(Url::parse(&server.url("/token")).expect("Mock token endpoint should parse."))
+	.support_grants([GrantType::ClientCredentials])
+	.preferred_client_auth_method(ClientAuthMethod::ClientSecretPost)
+	.build()
+	.expect("Provider descriptor should build successfully.")
+}
+
+fn post(path: &str, api_key: Option<&str>, body: serde_json::Value) -> Request<Vec<u8>> {
+	let mut builder = Request::post(path).header("content-type", "application/json");
+
+	if let Some(api_key) = api_key {
+		builder = builder.header("authorization", format!("Bearer {api_key}"));
+	}
+
+	builder.body(body.to_string().into_bytes()).expect("Service request should build.")
+}
+
+fn json(response: &Response<Vec<u8>>) -> serde_json::Value {
+	serde_json::from_slice(response.body()).expect("Service responses should be JSON.")
+}
+
+#[tokio::test]
+async fn service_issues_and_revokes_tokens_per_authenticated_tenant() {
+	let server = MockServer::start_async().await;
+	let (broker, _store) =
+		build_reqwest_test_broker(build_descriptor(&server), CLIENT_ID, CLIENT_SECRET);
+	let service = BrokerService::new(
+		broker,
+		ServiceAuth::new()
+			.with_api_key("key-a", TenantId::new("tenant-a").expect("Tenant should be valid."))
+			.with_api_key("key-b", TenantId::new("tenant-b").expect("Tenant should be valid.")),
+	);
+	let token = server
+		.mock_async(|when, then| {
+			when.method(POST).path("/token");
+			then.status(200).header("content-type", "application/json").body(
+				"{\"access_token\":\"fleet-token\",\"refresh_token\":\"kept-inside\",\"token_type\":\"bearer\",\"expires_in\":600}",
+			);
+		})
+		.await;
+	let body = serde_json::json!({ "principal": "worker", "scope": ["api.read"] });
+	let issued = service.handle(post("/v1/token", Some("key-a"), body.clone())).await;
+
+	assert_eq!(issued.status(), StatusCode::OK);
+
+	let issued = json(&issued);
+
+	assert_eq!(issued["access_token"], "fleet-token");
+	assert_eq!(issued["scope"], "api.read");
+	assert!(issued.get("refresh_token").is_none());
+
+	// The cache is shared, so a second call for the same tenant never reaches the provider.
+	let cached = service.handle(post("/v1/token", Some("key-a"), body.clone())).await;
+
+	assert_eq!(json(&cached)["access_token"], "fleet-token");
+
+	token.assert_calls_async(1).await;
+
+	// Another tenant's key cannot see or revoke tenant-a's record.
+	let foreign = service.handle(post("/v1/revoke", Some("key-b"), body.clone())).await;
+
+	assert_eq!(json(&foreign)["revoked"], false);
+
+	let revoked = service.handle(post("/v1/revoke", Some("key-a"), body.clone())).await;
+
+	assert_eq!(json(&revoked)["revoked"], true);
+
+	let anonymous = service.handle(post("/v1/token", None, body.clone())).await;
+
+	assert_eq!(anonymous.status(), StatusCode::UNAUTHORIZED);
+	assert_eq!(
+		anonymous.headers().get("content-type").and_then(|value| value.to_str().ok()),
+		Some("application/problem+json")
+	);
+
+	let wrong_key = service.handle(post("/v1/token", Some("key-c"), body.clone())).await;
+
+	assert_eq!(wrong_key.status(), StatusCode::UNAUTHORIZED);
+
+	let malformed =
+		service.handle(post("/v1/token", Some("key-a"), serde_json::json!({ "scope": [] }))).await;
+
+	assert_eq!(malformed.status(), StatusCode::BAD_REQUEST);
+
+	let missing = service.handle(post("/v1/unknown", Some("key-a"), body)).await;
+
+	assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+
+	let get = service
+		.handle(Request::get("/v1/token").body(Vec::new()).expect("GET request should build."))
+		.await;
+
+	assert_eq!(get.status(), StatusCode::METHOD_NOT_ALLOWED);
+}
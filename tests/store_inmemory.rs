@@ -3,8 +3,11 @@ use time::macros;
 // self
 use oauth2_broker::{
 	_preludet::*,
-	auth::{PrincipalId, ScopeSet, TenantId, TokenFamily, TokenRecord, TokenStatus},
-	store::{BrokerStore, CompareAndSwapOutcome, MemoryStore},
+	auth::{
+		PrincipalId, PrincipalPath, ProviderId, RevocationReason, ScopeSet, TenantId, TokenFamily,
+		TokenRecord, TokenStatus,
+	},
+	store::{BrokerStore, CompareAndSwapOutcome, ConditionalFetch, MemoryStore},
 };
 
 fn make_family() -> TokenFamily {
@@ -14,6 +17,7 @@ fn make_family() -> TokenFamily {
 		.expect("Failed to build principal identifier for memory store tests.");
 
 	TokenFamily::new(tenant, principal)
+		.with_provider(ProviderId::new("provider-789").expect("Provider fixture should be valid."))
 }
 
 fn make_scope() -> ScopeSet {
@@ -115,6 +119,83 @@ async fn cas_success_and_mismatch() {
 	assert_eq!(missing, CompareAndSwapOutcome::Missing);
 }
 
+#[tokio::test]
+async fn family_cas_replaces_every_scope_or_none() {
+	// Plenty of shards so the scopes most likely land under different locks.
+	let store = MemoryStore::with_shards(64);
+	let family = make_family();
+	let read = ScopeSet::new(["files.read"]).expect("Failed to build read scope for family CAS.");
+	let write =
+		ScopeSet::new(["files.write"]).expect("Failed to build write scope for family CAS.");
+
+	for (scope, access) in [(&read, "access-read"), (&write, "access-write")] {
+		store
+			.save(build_record(&family, scope, access, Some("shared-refresh")))
+			.await
+			.expect("Saving family CAS fixture should succeed.");
+	}
+
+	let stale = store
+		.compare_and_swap_family(
+			&family,
+			Some("other-refresh"),
+			vec![
+				build_record(&family, &read, "access-read-2", Some("rotated")),
+				build_record(&family, &write, "access-write", Some("rotated")),
+			],
+		)
+		.await
+		.expect("Family CAS with a stale secret should not error.");
+
+	assert_eq!(stale, CompareAndSwapOutcome::RefreshMismatch);
+
+	let missing = store
+		.compare_and_swap_family(
+			&family,
+			Some("shared-refresh"),
+			vec![
+				build_record(&family, &read, "access-read-2", Some("rotated")),
+				build_record(&family, &make_scope(), "access-new", Some("rotated")),
+			],
+		)
+		.await
+		.expect("Family CAS with an unknown scope should not error.");
+
+	assert_eq!(missing, CompareAndSwapOutcome::Missing);
+
+	let untouched = store
+		.fetch(&family, &read)
+		.await
+		.expect("Fetching after rejected family CAS should succeed.")
+		.expect("Read scope record should remain present.");
+
+	assert_eq!(untouched.access_token.expose(), "access-read");
+
+	let updated = store
+		.compare_and_swap_family(
+			&family,
+			Some("shared-refresh"),
+			vec![
+				build_record(&family, &read, "access-read-2", Some("rotated")),
+				build_record(&family, &write, "access-write", Some("rotated")),
+			],
+		)
+		.await
+		.expect("Family CAS with the shared secret should succeed.");
+
+	assert_eq!(updated, CompareAndSwapOutcome::Updated);
+
+	for scope in [&read, &write] {
+		let record = store
+			.fetch(&family, scope)
+			.await
+			.expect("Fetching after family CAS should succeed.")
+			.expect("Family record should remain present.");
+
+		assert_eq!(record.refresh_token.as_ref().map(|secret| secret.expose()), Some("rotated"));
+	}
+}
+
 #[tokio::test]
 async fn concurrent_cas_allows_single_winner() {
 	let store = MemoryStore::default();
@@ -197,6 +278,62 @@ async fn cas_supports_records_without_refresh_tokens() {
 	assert_eq!(outcome, CompareAndSwapOutcome::Updated);
 }
 
+#[tokio::test]
+async fn version_cas_rotates_access_only_records_once() {
+	let store = MemoryStore::default();
+	let family = make_family();
+	let scope = make_scope();
+
+	store
+		.save(build_record(&family, &scope, "access-1", None))
+		.await
+		.expect("Saving client credentials record should succeed.");
+
+	let first = build_record(&family, &scope, "access-2", None);
+	let second = build_record(&family, &scope, "access-3", None);
+	let (first, second) = tokio::join!(
+		store.compare_and_swap_version(&family, &scope, 0, first),
+		store.compare_and_swap_version(&family, &scope, 0, second),
+	);
+	let outcomes = [
+		first.expect("First version CAS should not error."),
+		second.expect("Second version CAS should not error."),
+	];
+
+	assert!(outcomes.contains(&CompareAndSwapOutcome::Updated));
+	assert!(outcomes.contains(&CompareAndSwapOutcome::VersionMismatch));
+
+	let stored = store
+		.fetch(&family, &scope)
+		.await
+		.expect("Fetching after version CAS should succeed.")
+		.expect("Rotated record should be present.");
+
+	assert_eq!(stored.version, 1);
+
+	let revoked = store
+		.revoke(&family, &scope, stored.issued_at, RevocationReason::AdminAction)
+		.await
+		.expect("Revoking should succeed.")
+		.expect("Record should be revoked.");
+
+	assert_eq!(revoked.version, 2);
+	assert_eq!(
+		store
+			.compare_and_swap_version(&family, &scope, 1, build_record(&family, &scope, "a", None))
+			.await
+			.expect("Stale version CAS should not error."),
+		CompareAndSwapOutcome::VersionMismatch
+	);
+	assert_eq!(
+		store
+			.compare_and_swap_version(&make_family(), &ScopeSet::default(), 0, stored)
+			.await
+			.expect("Version CAS on a missing key should not error."),
+		CompareAndSwapOutcome::Missing
+	);
+}
+
 #[tokio::test]
 async fn revoke_marks_records() {
 	let store = MemoryStore::default();
@@ -208,12 +345,13 @@ async fn revoke_marks_records() {
 
 	let instant = OffsetDateTime::now_utc();
 	let revoked = store
-		.revoke(&family, &scope, instant)
+		.revoke(&family, &scope, instant, RevocationReason::ProviderInvalidGrant)
 		.await
 		.expect("Revocation operation should succeed.")
 		.expect("Revocation should return the affected record.");
 
 	assert_eq!(revoked.revoked_at, Some(instant));
+	assert_eq!(revoked.revoked_reason, Some(RevocationReason::ProviderInvalidGrant));
 	assert_eq!(revoked.status_at(instant), TokenStatus::Revoked);
 
 	let fetched = store
@@ -225,6 +363,82 @@ async fn revoke_marks_records() {
 	assert_eq!(fetched.revoked_at, Some(instant));
 }
 
+#[tokio::test]
+async fn shared_fetch_snapshots_survive_later_writes() {
+	let store = MemoryStore::default();
+	let family = make_family();
+	let scope = make_scope();
+
+	store
+		.save(build_record(&family, &scope, "access", Some("refresh")))
+		.await
+		.expect("Saving shared record should succeed.");
+
+	let shared =
+		store.fetch_shared(&family, &scope).expect("Shared fetch should return the record.");
+
+	store
+		.revoke(&family, &scope, OffsetDateTime::now_utc(), RevocationReason::AdminAction)
+		.await
+		.expect("Revocation operation should succeed.");
+
+	assert!(shared.revoked_at.is_none());
+	assert!(
+		store
+			.fetch_shared(&family, &scope)
+			.expect("Revoked record should remain present.")
+			.revoked_at
+			.is_some()
+	);
+}
+
+#[tokio::test]
+async fn conditional_fetch_skips_unchanged_records() {
+	let store = MemoryStore::default();
+	let family = make_family();
+	let scope = make_scope();
+
+	assert!(matches!(
+		store.fetch_if_changed(&family, &scope, None).await,
+		Ok(ConditionalFetch::Missing)
+	));
+
+	store
+		.save(build_record(&family, &scope, "access", Some("refresh")))
+		.await
+		.expect("Saving conditional record should succeed.");
+
+	let ConditionalFetch::Changed { record, fingerprint } = store
+		.fetch_if_changed(&family, &scope, None)
+		.await
+		.expect("Conditional fetch without a fingerprint should succeed.")
+	else {
+		panic!("Conditional fetch without a fingerprint should return the record.");
+	};
+
+	assert_eq!(record.access_token.expose(), "access");
+	assert!(matches!(
+		store.fetch_if_changed(&family, &scope, Some(&fingerprint)).await,
+		Ok(ConditionalFetch::Unchanged)
+	));
+
+	store
+		.save(build_record(&family, &scope, "access-2", Some("refresh-2")))
+		.await
+		.expect("Overwriting conditional record should succeed.");
+
+	let ConditionalFetch::Changed { record, fingerprint: next } = store
+		.fetch_if_changed(&family, &scope, Some(&fingerprint))
+		.await
+		.expect("Conditional fetch with a stale fingerprint should succeed.")
+	else {
+		panic!("Conditional fetch with a stale fingerprint should return the new record.");
+	};
+
+	assert_eq!(record.access_token.expose(), "access-2");
+	assert_ne!(next, fingerprint);
+}
+
 #[tokio::test]
 async fn revoke_returns_none_for_missing_record() {
 	let store = MemoryStore::default();
@@ -232,9 +446,145 @@ async fn revoke_returns_none_for_missing_record() {
 	let scope = make_scope();
 	let instant = OffsetDateTime::now_utc();
 	let outcome = store
-		.revoke(&family, &scope, instant)
+		.revoke(&family, &scope, instant, RevocationReason::ProviderInvalidGrant)
 		.await
 		.expect("Revocation should not error when the record is missing.");
 
 	assert!(outcome.is_none());
 }
+
+#[tokio::test]
+async fn principal_subtree_lists_and_revokes_descendants_only() {
+	let store = MemoryStore::default();
+	let tenant = TenantId::new("tenant-123").expect("Tenant identifier fixture should be valid.");
+	let scope = make_scope();
+	let family_for = |principal: &str| {
+		TokenFamily::new(
+			tenant.clone(),
+			PrincipalId::new(principal).expect("Principal fixture should be valid."),
+		)
+		.with_provider(ProviderId::new("provider-789").expect("Provider fixture should be valid."))
+	};
+
+	for principal in ["acme/ops", "acme/ops/bob", "acme/ops-legacy/eve", "acme/sales/ann"] {
+		store
+			.save(build_record(&family_for(principal), &scope, principal, None))
+			.await
+			.expect("Saving subtree fixture should succeed.");
+	}
+
+	let prefix = PrincipalPath::new("acme/ops").expect("Prefix fixture should be valid.");
+	let mut listed = store
+		.list_principal_subtree(&tenant, &prefix)
+		.await
+		.expect("Listing principal subtree should succeed.")
+		.into_iter()
+		.map(|record| record.family.principal.to_string())
+		.collect::<Vec<_>>();
+
+	listed.sort();
+
+	assert_eq!(listed, ["acme/ops", "acme/ops/bob"]);
+
+	let instant = macros::datetime!(2025-11-10 12:30 UTC);
+	let revoked = store
+		.revoke_principal_subtree(&tenant, &prefix, instant, RevocationReason::AdminAction)
+		.await
+		.expect("Revoking principal subtree should succeed.");
+
+	assert_eq!(revoked.len(), 2);
+
+	let sibling = store
+		.fetch(&family_for("acme/ops-legacy/eve"), &scope)
+		.await
+		.expect("Fetching sibling record should succeed.")
+		.expect("Sibling record should remain present.");
+
+	assert_eq!(sibling.revoked_at, None);
+}
+
+#[tokio::test]
+async fn revoke_all_covers_every_scope_of_one_family() {
+	let store = MemoryStore::default();
+	let family = make_family();
+	let other = TokenFamily::new(
+		family.tenant.clone(),
+		PrincipalId::new("principal-other").expect("Principal fixture should be valid."),
+	)
+	.with_provider(ProviderId::new("provider-789").expect("Provider fixture should be valid."));
+	let scopes = [make_scope(), ScopeSet::new(["admin"]).expect("Scope fixture should be valid.")];
+
+	for scope in &scopes {
+		store
+			.save(build_record(&family, scope, "access", None))
+			.await
+			.expect("Saving family fixture should succeed.");
+	}
+
+	store
+		.save(build_record(&other, &scopes[0], "access-other", None))
+		.await
+		.expect("Saving sibling fixture should succeed.");
+
+	let first = macros::datetime!(2025-11-10 12:30 UTC);
+	let revoked = store
+		.revoke_all(&family, first, RevocationReason::ReuseDetected)
+		.await
+		.expect("Revoking the family should succeed.");
+
+	assert_eq!(revoked.len(), 2);
+	assert!(revoked.iter().all(|record| record.revoked_at == Some(first)));
+
+	let again = store
+		.revoke_all(&family, first + Duration::minutes(5), RevocationReason::AdminAction)
+		.await
+		.expect("Revoking the family again should succeed.");
+
+	assert!(again.is_empty(), "Already revoked records keep their original revocation.");
+
+	let sibling = store
+		.fetch(&other, &scopes[0])
+		.await
+		.expect("Fetching sibling record should succeed.")
+		.expect("Sibling record should remain present.");
+
+	assert_eq!(sibling.revoked_at, None);
+}
+
+#[tokio::test]
+async fn purge_revoked_drops_only_records_revoked_before_cutoff() {
+	let store = MemoryStore::default();
+	let family = make_family();
+	let old_scope = make_scope();
+	let new_scope = ScopeSet::new(["email"]).expect("Second scope fixture should be valid.");
+	let live_scope = ScopeSet::new(["profile"]).expect("Third scope fixture should be valid.");
+
+	for scope in [&old_scope, &new_scope, &live_scope] {
+		store
+			.save(build_record(&family, scope, "access", None))
+			.await
+			.expect("Saving purge fixture should succeed.");
+	}
+
+	let cutoff = macros::datetime!(2025-11-10 12:30 UTC);
+
+	store
+		.revoke(&family, &old_scope, cutoff - Duration::minutes(1), RevocationReason::UserRequested)
+		.await
+		.expect("Revoking old record should succeed.");
+	store
+		.revoke(&family, &new_scope, cutoff, RevocationReason::ReuseDetected)
+		.await
+		.expect("Revoking recent record should succeed.");
+
+	let purged =
+		store.purge_revoked(cutoff).await.expect("Purging revoked records should succeed.");
+
+	assert_eq!(purged, 1);
+	assert!(
+		store.fetch(&family, &old_scope).await.expect("Fetch should succeed.").is_none(),
+		"Records revoked before the cutoff should be purged."
+	);
+	assert!(store.fetch(&family, &new_scope).await.expect("Fetch should succeed.").is_some());
+	assert!(store.fetch(&family, &live_scope).await.expect("Fetch should succeed.").is_some());
+}
@@ -64,7 +64,11 @@ impl<'a> AsyncHttpClient<'a> for FakeHttpHandle {
 				slot.take().is_none(),
 				"ResponseMetadataSlot must be clear before dispatching a request."
 			);
-			slot.store(ResponseMetadata { status: Some(429), retry_after: Some(retry_after) });
+			slot.store(ResponseMetadata {
+				status: Some(429),
+				retry_after: Some(retry_after),
+				headers: BTreeMap::new(),
+			});
 
 			Err(HttpClientError::Reqwest(Box::new(FakeTransportError::Throttled)))
 		})